@@ -0,0 +1,21 @@
+-- Edit authorship audit for collaboration sessions
+-- Run with: sqlx migrate run
+
+-- One row per applied edit; participant_id is the connection id, resolved to user/agent when known
+CREATE TABLE IF NOT EXISTS collaboration_edit_audit (
+    id UUID PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES collaboration_sessions(id) ON DELETE CASCADE,
+    participant_id UUID NOT NULL,
+    user_id UUID,
+    agent_id UUID,
+    file_path VARCHAR(500) NOT NULL,
+    position INTEGER NOT NULL,
+    length INTEGER NOT NULL DEFAULT 0,
+    content TEXT NOT NULL DEFAULT '',
+    version INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_collaboration_edit_audit_file ON collaboration_edit_audit(session_id, file_path, created_at);
+CREATE INDEX IF NOT EXISTS idx_collaboration_edit_audit_user ON collaboration_edit_audit(user_id);
+CREATE INDEX IF NOT EXISTS idx_collaboration_edit_audit_agent ON collaboration_edit_audit(agent_id);
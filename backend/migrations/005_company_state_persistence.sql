@@ -0,0 +1,30 @@
+-- Company state persistence: current metrics and role constraint fix
+-- Run with: sqlx migrate run
+
+-- Roles are stored with serde's snake_case names (DevOpsEngineer -> dev_ops_engineer)
+ALTER TABLE company_members DROP CONSTRAINT IF EXISTS valid_role;
+ALTER TABLE company_members ADD CONSTRAINT valid_role CHECK (role IN ('ceo', 'cto', 'product_manager', 'backend_engineer', 'frontend_engineer', 'dev_ops_engineer', 'qa_engineer', 'ui_designer', 'ux_designer', 'visual_designer', 'content_creator', 'documentation_specialist', 'customer_support'));
+
+-- Latest cumulative company metrics (single row), restored on startup.
+-- company_metrics_snapshots keeps the history.
+CREATE TABLE IF NOT EXISTS company_metrics (
+    id SMALLINT PRIMARY KEY DEFAULT 1,
+    total_agents INTEGER NOT NULL DEFAULT 0,
+    active_agents INTEGER NOT NULL DEFAULT 0,
+    total_tasks_completed BIGINT NOT NULL DEFAULT 0,
+    total_tasks_failed BIGINT NOT NULL DEFAULT 0,
+    success_rate DOUBLE PRECISION NOT NULL DEFAULT 0,
+    average_task_time_ms BIGINT NOT NULL DEFAULT 0,
+    total_tokens_used BIGINT NOT NULL DEFAULT 0,
+    uptime_seconds BIGINT NOT NULL DEFAULT 0,
+    visual_creatives_completed BIGINT NOT NULL DEFAULT 0,
+    collaborations_count BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT single_row CHECK (id = 1)
+);
+
+CREATE INDEX IF NOT EXISTS idx_company_members_last_active ON company_members(last_active DESC);
+
+CREATE TRIGGER update_company_metrics_updated_at BEFORE UPDATE ON company_metrics
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,16 @@
+-- Periodic full-content snapshots of edited files
+-- Run with: sqlx migrate run
+
+-- Reconstruction starts from the newest snapshot at or before the wanted version
+-- and replays the collaboration_edit_audit rows after it
+CREATE TABLE IF NOT EXISTS collaboration_file_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES collaboration_sessions(id) ON DELETE CASCADE,
+    file_path VARCHAR(500) NOT NULL,
+    version INTEGER NOT NULL,
+    op_count INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_collaboration_file_snapshots_file ON collaboration_file_snapshots(session_id, file_path, version);
@@ -0,0 +1,18 @@
+-- Periodic provider health summaries
+-- Run with: sqlx migrate run
+
+-- The newest row per provider seeds the router's health tracker at startup;
+-- older rows back GET /api/v1/models/health
+CREATE TABLE IF NOT EXISTS provider_health_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    provider VARCHAR(50) NOT NULL,
+    calls DOUBLE PRECISION NOT NULL,
+    success_rate DOUBLE PRECISION NOT NULL,
+    avg_latency_ms DOUBLE PRECISION,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    available BOOLEAN NOT NULL DEFAULT true,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_provider_health_snapshots_provider ON provider_health_snapshots(provider, recorded_at DESC);
+CREATE INDEX IF NOT EXISTS idx_provider_health_snapshots_recorded_at ON provider_health_snapshots(recorded_at);
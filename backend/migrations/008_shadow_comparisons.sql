@@ -0,0 +1,26 @@
+-- Shadow routing comparisons
+-- Run with: sqlx migrate run
+
+-- One row per sampled chat request replayed against an alternate provider;
+-- backs GET /api/v1/models/shadow-report. Shadow columns are NULL when the
+-- shadow call failed.
+CREATE TABLE IF NOT EXISTS shadow_comparisons (
+    id BIGSERIAL PRIMARY KEY,
+    primary_provider VARCHAR(50) NOT NULL,
+    primary_model VARCHAR(255) NOT NULL,
+    primary_latency_ms BIGINT NOT NULL,
+    primary_prompt_tokens INTEGER NOT NULL,
+    primary_completion_tokens INTEGER NOT NULL,
+    primary_cost_usd DOUBLE PRECISION NOT NULL,
+    shadow_provider VARCHAR(50) NOT NULL,
+    shadow_model VARCHAR(255),
+    shadow_latency_ms BIGINT,
+    shadow_prompt_tokens INTEGER,
+    shadow_completion_tokens INTEGER,
+    shadow_cost_usd DOUBLE PRECISION,
+    error TEXT,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_shadow_comparisons_recorded_at ON shadow_comparisons(recorded_at);
+CREATE INDEX IF NOT EXISTS idx_shadow_comparisons_pair ON shadow_comparisons(primary_provider, shadow_provider);
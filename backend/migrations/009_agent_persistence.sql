@@ -0,0 +1,33 @@
+-- Agent manager state persistence
+-- Run with: sqlx migrate run
+
+-- Agents as last known; the full struct is kept in agent_data
+CREATE TABLE IF NOT EXISTS agents (
+    id VARCHAR(255) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    agent_type VARCHAR(50) NOT NULL,
+    status VARCHAR(50) NOT NULL,
+    agent_data JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Tasks and subtasks. `queued` marks tasks that were handed to the execution queue
+-- (subtasks); unfinished queued tasks are re-queued at startup.
+CREATE TABLE IF NOT EXISTS agent_tasks (
+    id VARCHAR(255) PRIMARY KEY,
+    task_type VARCHAR(50) NOT NULL,
+    description TEXT NOT NULL,
+    priority VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    context JSONB NOT NULL DEFAULT '{}',
+    result TEXT,
+    error TEXT,
+    queued BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_tasks_status ON agent_tasks(status);
+CREATE INDEX IF NOT EXISTS idx_agent_tasks_completed_at ON agent_tasks(completed_at DESC);
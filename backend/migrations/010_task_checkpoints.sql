@@ -0,0 +1,10 @@
+-- Execution checkpoints for agent tasks
+-- Run with: sqlx migrate run
+
+-- Latest progress of a task that is running or was interrupted; removed once it completes
+CREATE TABLE IF NOT EXISTS task_checkpoints (
+    task_id VARCHAR(255) PRIMARY KEY,
+    agent_id VARCHAR(255) NOT NULL,
+    checkpoint_data JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
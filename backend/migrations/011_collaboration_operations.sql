@@ -0,0 +1,12 @@
+-- Versioned edit operations replayed to reconnecting collaborators
+-- Run with: sqlx migrate run
+
+-- Versions are assigned per session by the server, one per edit, starting at 1
+CREATE TABLE IF NOT EXISTS collaboration_operations (
+    id UUID PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES collaboration_sessions(id) ON DELETE CASCADE,
+    version BIGINT NOT NULL,
+    operation JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (session_id, version)
+);
@@ -0,0 +1,22 @@
+-- Company metrics sampled every minute, for trend charts
+-- Run with: sqlx migrate run
+
+-- One row per metrics_update_loop pass; rows older than
+-- COMPANY_METRICS_RETENTION_DAYS are pruned as new ones are written
+CREATE TABLE IF NOT EXISTS company_metrics_history (
+    id BIGSERIAL PRIMARY KEY,
+    state VARCHAR(20) NOT NULL,
+    total_agents INTEGER NOT NULL,
+    active_agents INTEGER NOT NULL,
+    total_tasks_completed BIGINT NOT NULL,
+    total_tasks_failed BIGINT NOT NULL,
+    success_rate DOUBLE PRECISION NOT NULL,
+    average_task_time_ms BIGINT NOT NULL,
+    total_tokens_used BIGINT NOT NULL,
+    uptime_seconds BIGINT NOT NULL,
+    visual_creatives_completed BIGINT NOT NULL,
+    collaborations_count BIGINT NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_company_metrics_history_recorded_at ON company_metrics_history(recorded_at);
@@ -0,0 +1,21 @@
+-- Security audit log
+-- Run with: sqlx migrate run
+
+-- One row per AuditLogger event; the in-memory ring only keeps the most
+-- recent ones. Backs GET /api/v1/security/audit. Enum columns hold the
+-- Rust variant names (e.g. 'SecurityViolation', 'High').
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id VARCHAR(36) PRIMARY KEY,
+    timestamp TIMESTAMPTZ NOT NULL,
+    event_type VARCHAR(50) NOT NULL,
+    user_id VARCHAR(255),
+    ip_address VARCHAR(64),
+    resource VARCHAR(255) NOT NULL,
+    action VARCHAR(255) NOT NULL,
+    result VARCHAR(20) NOT NULL,
+    details JSONB,
+    threat_level VARCHAR(20) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_timestamp ON audit_logs(timestamp);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_event_type ON audit_logs(event_type, timestamp);
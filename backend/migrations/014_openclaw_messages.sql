@@ -0,0 +1,16 @@
+-- OpenClaw message history
+-- Run with: sqlx migrate run
+
+-- Both sides of each exchange sent through POST /api/v1/openclaw/message,
+-- returned by GET /api/v1/openclaw/sessions/:id/history.
+CREATE TABLE IF NOT EXISTS openclaw_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id VARCHAR(255) NOT NULL REFERENCES openclaw_sessions(session_id) ON DELETE CASCADE,
+    role VARCHAR(20) NOT NULL,
+    content TEXT NOT NULL,
+    model VARCHAR(100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT valid_role CHECK (role IN ('user', 'assistant'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_openclaw_messages_session ON openclaw_messages(session_id, created_at);
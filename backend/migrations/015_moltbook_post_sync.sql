@@ -0,0 +1,18 @@
+-- Moltbook post sync state
+-- Run with: sqlx migrate run
+
+-- Where a shared post was published on Moltbook. 'local' posts were saved
+-- while Moltbook was disabled; 'pending_sync' ones failed to publish and are
+-- retried in the background.
+ALTER TABLE moltbook_posts ADD COLUMN IF NOT EXISTS remote_id VARCHAR(255);
+ALTER TABLE moltbook_posts ADD COLUMN IF NOT EXISTS remote_url TEXT;
+ALTER TABLE moltbook_posts ADD COLUMN IF NOT EXISTS sync_status VARCHAR(20) NOT NULL DEFAULT 'local';
+ALTER TABLE moltbook_posts ADD COLUMN IF NOT EXISTS sync_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE moltbook_posts ADD COLUMN IF NOT EXISTS last_sync_error TEXT;
+
+ALTER TABLE moltbook_posts DROP CONSTRAINT IF EXISTS valid_sync_status;
+ALTER TABLE moltbook_posts ADD CONSTRAINT valid_sync_status
+    CHECK (sync_status IN ('local', 'pending_sync', 'synced'));
+
+CREATE INDEX IF NOT EXISTS idx_moltbook_posts_pending ON moltbook_posts(created_at)
+    WHERE sync_status = 'pending_sync';
@@ -0,0 +1,14 @@
+-- Dead-lettered agent tasks
+-- Run with: sqlx migrate run
+
+-- Tasks that failed after every retry, kept for inspection and manual requeue; the
+-- task is stored as it was when it failed
+CREATE TABLE IF NOT EXISTS agent_dead_letters (
+    task_id VARCHAR(255) PRIMARY KEY,
+    task_data JSONB NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_dead_letters_failed_at ON agent_dead_letters(failed_at DESC);
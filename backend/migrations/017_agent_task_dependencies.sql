@@ -0,0 +1,10 @@
+-- Ordering between queued agent subtasks
+-- Run with: sqlx migrate run
+
+-- A queued task stays in the queue until every task it depends on has completed;
+-- rows are removed once the task leaves the queue
+CREATE TABLE IF NOT EXISTS agent_task_dependencies (
+    task_id VARCHAR(255) NOT NULL,
+    depends_on VARCHAR(255) NOT NULL,
+    PRIMARY KEY (task_id, depends_on)
+);
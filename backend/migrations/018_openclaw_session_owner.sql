@@ -0,0 +1,8 @@
+-- OpenClaw session owners
+-- Run with: sqlx migrate run
+
+-- The user whose messages started the session; NULL for sessions created
+-- without authentication. History and appends are limited to the owner.
+ALTER TABLE openclaw_sessions ADD COLUMN IF NOT EXISTS user_id UUID;
+
+CREATE INDEX IF NOT EXISTS idx_openclaw_sessions_user ON openclaw_sessions(user_id, created_at DESC);
@@ -0,0 +1,162 @@
+/**
+ * Admin API route handlers
+ * Runtime operations that don't need a restart; every one requires the admin key
+ */
+use axum::{
+    extract::Extension,
+    http::HeaderMap,
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use crate::config::Config;
+use crate::config_reload::{ConfigReloader, HOT_RELOADABLE_SETTINGS};
+use crate::middleware::auth::is_admin;
+use crate::services::agent::AgentManager;
+use crate::services::ai::ResponseCache;
+use crate::types::errors::{ApiError, ApiResult};
+
+#[derive(Debug, Serialize)]
+pub struct ConfigReloadResponse {
+    pub changed: Vec<String>,
+    pub hot_reloadable: Vec<String>,
+}
+
+fn require_admin(headers: &HeaderMap, config: &Config) -> ApiResult<()> {
+    if !is_admin(headers, &config.admin_api_key) {
+        return Err(ApiError::forbidden());
+    }
+    Ok(())
+}
+
+/// Re-read the environment and apply hot-reloadable settings
+pub async fn reload_config(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(reloader): Extension<Arc<ConfigReloader>>,
+) -> ApiResult<Json<ConfigReloadResponse>> {
+    require_admin(&headers, &config)?;
+    let changed = reloader.reload_from_env()
+        .await
+        .map_err(|e| ApiError::validation_error(format!("Config reload rejected: {}", e)))?;
+
+    Ok(Json(ConfigReloadResponse {
+        changed: changed.iter().map(|s| s.to_string()).collect(),
+        hot_reloadable: HOT_RELOADABLE_SETTINGS.iter().map(|s| s.to_string()).collect(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricsResetResponse {
+    pub reset_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Zero the agent metrics totals and recent-window samples
+pub async fn reset_agent_metrics(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+) -> ApiResult<Json<MetricsResetResponse>> {
+    require_admin(&headers, &config)?;
+    manager.metrics().reset().await;
+    tracing::info!("Agent metrics reset");
+    Ok(Json(MetricsResetResponse { reset_at: chrono::Utc::now() }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheClearResponse {
+    pub cleared: usize,
+}
+
+/// Drop every cached chat response
+pub async fn clear_response_cache(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(cache): Extension<Arc<ResponseCache>>,
+) -> ApiResult<Json<CacheClearResponse>> {
+    require_admin(&headers, &config)?;
+    let cleared = cache.clear();
+    tracing::info!("Cleared {} cached chat responses", cleared);
+    Ok(Json(CacheClearResponse { cleared }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_reload::LiveConfig;
+    use crate::security::AdaptiveRateLimiter;
+    use crate::services::ai::router::ModelRouter;
+    use crate::services::company::CompanyOrchestrator;
+    use crate::types::errors::error_codes;
+
+    fn config() -> Config {
+        let mut config = Config::from_env().unwrap();
+        config.admin_api_key = "admin-key".to_string();
+        config
+    }
+
+    fn headers(api_key: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = api_key {
+            headers.insert("X-API-Key", key.parse().unwrap());
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_config_reload_requires_admin() {
+        let config = config();
+        let shared = Arc::new(config.clone());
+        let router = Arc::new(ModelRouter::new(&shared));
+        let manager = AgentManager::new(Arc::clone(&router), Arc::clone(&shared), None);
+        let reloader = Arc::new(ConfigReloader::new(
+            Arc::new(LiveConfig::new(config.clone())),
+            Arc::clone(&manager),
+            CompanyOrchestrator::new(manager, router, shared, None),
+            Arc::new(AdaptiveRateLimiter::default()),
+            std::collections::HashSet::new(),
+        ));
+
+        for key in [None, Some("wrong")] {
+            let err = reload_config(headers(key), Extension(config.clone()), Extension(Arc::clone(&reloader)))
+                .await
+                .unwrap_err();
+            assert_eq!(err.error.code, error_codes::FORBIDDEN);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_reset_requires_admin() {
+        let config = config();
+        let shared = Arc::new(config.clone());
+        let manager = AgentManager::new(Arc::new(ModelRouter::new(&shared)), shared, None);
+        manager.metrics().record_task_started("task").await;
+
+        let err = reset_agent_metrics(headers(None), Extension(config.clone()), Extension(Arc::clone(&manager)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.error.code, error_codes::FORBIDDEN);
+        assert_eq!(manager.metrics().get_metrics().await.total_tasks_executed, 1);
+
+        reset_agent_metrics(headers(Some("admin-key")), Extension(config), Extension(Arc::clone(&manager)))
+            .await
+            .unwrap();
+        assert_eq!(manager.metrics().get_metrics().await.total_tasks_executed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cache_clear_requires_admin() {
+        let config = config();
+        let cache = Arc::new(ResponseCache::new(std::time::Duration::from_secs(60), 10, false));
+
+        let err = clear_response_cache(headers(Some("wrong")), Extension(config.clone()), Extension(Arc::clone(&cache)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.error.code, error_codes::FORBIDDEN);
+
+        let Json(cleared) = clear_response_cache(headers(Some("admin-key")), Extension(config), Extension(cache))
+            .await
+            .unwrap();
+        assert_eq!(cleared.cleared, 0);
+    }
+}
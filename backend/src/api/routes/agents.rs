@@ -6,11 +6,13 @@ use axum::{
     http::StatusCode,
     response::Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use crate::types::{AgentTask, TaskType, Priority};
 use crate::config::Config;
-use crate::services::agent::AgentManager;
-use crate::services::agent::types::AgentType;
+use crate::services::agent::{AgentManager, DeadLetter, TaskUsage, WebhookDelivery};
+use crate::security::AdvancedValidator;
+use crate::services::agent::types::{Agent, AgentType, BulkAgentSpec, DecompositionMode};
+use crate::types::errors::{ApiError, ApiResult};
 use std::sync::Arc;
 
 #[derive(Deserialize)]
@@ -25,6 +27,11 @@ pub struct CreateTaskRequest {
     pub description: String,
     pub priority: Option<Priority>,
     pub context: Option<crate::types::CodebaseContext>,
+    /// "ai" lets a model plan the subtasks; defaults to the static templates
+    #[serde(default)]
+    pub decomposition: DecompositionMode,
+    /// Receives the final task JSON once the task completes, fails or is cancelled
+    pub callback_url: Option<String>,
 }
 
 /// Create a new agent
@@ -62,15 +69,52 @@ pub async fn create_agent(
     }
 }
 
+#[derive(Deserialize)]
+pub struct BulkCreateAgentsRequest {
+    pub agents: Vec<BulkAgentSpec>,
+}
+
+/// Create several agents in one request (all-or-nothing against the agent limit)
+pub async fn create_agents_bulk(
+    Extension(_config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+    Json(request): Json<BulkCreateAgentsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if request.agents.is_empty() || request.agents.iter().any(|spec| spec.count == 0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match manager.create_agents_bulk(&request.agents).await {
+        Ok(agents) => Ok(Json(serde_json::json!({
+            "agents": agents,
+            "total": agents.len(),
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to create agents in bulk: {}", e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
 /// Create a new task (will be decomposed and assigned to agents)
 pub async fn create_task(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
     Extension(manager): Extension<Arc<AgentManager>>,
+    Extension(validator): Extension<Arc<AdvancedValidator>>,
     Json(request): Json<CreateTaskRequest>,
-) -> Result<Json<AgentTask>, StatusCode> {
+) -> ApiResult<Json<AgentTask>> {
     use uuid::Uuid;
     use chrono::Utc;
 
+    // Checked before anything is queued so a bad URL doesn't leave a task behind
+    let callback_url = match request.callback_url.as_deref() {
+        Some(url) => Some(
+            validator.validate_callback_url(url, config.webhook_allow_private_urls)
+                .map_err(|e| ApiError::validation_error(e).with_field("callback_url".to_string()))?,
+        ),
+        None => None,
+    };
+
     let task = AgentTask {
         id: Uuid::new_v4().to_string(),
         r#type: request.task_type,
@@ -84,13 +128,14 @@ pub async fn create_task(
         completed_at: None,
     };
 
-    match manager.create_task(task).await {
-        Ok(task) => Ok(Json(task)),
-        Err(e) => {
-            tracing::error!("Failed to create task: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    let task = manager.create_task_with_mode(task, request.decomposition).await.map_err(|e| {
+        tracing::error!("Failed to create task: {}", e);
+        ApiError::internal_error(e)
+    })?;
+    if let Some(url) = callback_url {
+        manager.register_webhook(&task.id, url.to_string()).await;
     }
+    Ok(Json(task))
 }
 
 /// Get agent status by ID
@@ -98,7 +143,7 @@ pub async fn get_agent_status(
     Extension(_config): Extension<Config>,
     Extension(manager): Extension<Arc<AgentManager>>,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> ApiResult<Json<serde_json::Value>> {
     match manager.get_agent(&id).await {
         Some(agent) => Ok(Json(serde_json::json!({
             "id": agent.id,
@@ -108,19 +153,89 @@ pub async fn get_agent_status(
             "current_task": agent.current_task,
             "capabilities": agent.capabilities,
         }))),
-        None => Err(StatusCode::NOT_FOUND),
+        None => Err(ApiError::not_found("Agent").with_details(format!("No agent with id {}", id))),
+    }
+}
+
+/// Delete an idle agent
+pub async fn delete_agent(
+    Extension(_config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Agent>> {
+    match manager.delete_agent(&id).await {
+        Ok(Some(agent)) => Ok(Json(agent)),
+        Ok(None) => Err(ApiError::not_found("Agent").with_details(format!("No agent with id {}", id))),
+        Err(e) => Err(ApiError::conflict(e)),
     }
 }
 
+#[derive(Serialize)]
+pub struct TaskStatusResponse {
+    #[serde(flatten)]
+    pub task: AgentTask,
+    /// Tokens and estimated cost across the task's subtasks and retries
+    pub usage: TaskUsage,
+}
+
 /// Get task status by ID
 pub async fn get_task_status(
     Extension(_config): Extension<Config>,
     Extension(manager): Extension<Arc<AgentManager>>,
     Path(id): Path<String>,
-) -> Result<Json<AgentTask>, StatusCode> {
+) -> ApiResult<Json<TaskStatusResponse>> {
     match manager.get_task_status(&id).await {
-        Some(task) => Ok(Json(task)),
-        None => Err(StatusCode::NOT_FOUND),
+        Some(task) => Ok(Json(TaskStatusResponse {
+            usage: manager.task_usage(&id).unwrap_or_default(),
+            task,
+        })),
+        None => Err(ApiError::not_found("Task").with_details(format!("No task with id {}", id))),
+    }
+}
+
+/// Cancel a task; queued work is dropped and running work is told to stop
+pub async fn cancel_task(
+    Extension(_config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<AgentTask>> {
+    match manager.cancel_task(&id).await {
+        Ok(Some(task)) => Ok(Json(task)),
+        Ok(None) => Err(ApiError::not_found("Task").with_details(format!("No task with id {}", id))),
+        Err(e) => Err(ApiError::conflict(e)),
+    }
+}
+
+/// Delivery status of a task's completion webhook
+pub async fn get_task_webhook(
+    Extension(_config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WebhookDelivery>> {
+    match manager.webhook_delivery(&id) {
+        Some(delivery) => Ok(Json(delivery)),
+        None => Err(ApiError::not_found("Webhook").with_details(format!("No callback registered for task {}", id))),
+    }
+}
+
+/// Tasks that failed after exhausting their retries
+pub async fn list_dead_letters(
+    Extension(_config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+) -> ApiResult<Json<Vec<DeadLetter>>> {
+    Ok(Json(manager.dead_letters().await))
+}
+
+/// Put a dead-lettered task back on the queue
+pub async fn requeue_dead_letter(
+    Extension(_config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<AgentTask>> {
+    match manager.requeue_dead_letter(&id).await {
+        Ok(Some(task)) => Ok(Json(task)),
+        Ok(None) => Err(ApiError::not_found("Dead letter").with_details(format!("No dead-lettered task with id {}", id))),
+        Err(e) => Err(ApiError::internal_error(e)),
     }
 }
 
@@ -156,6 +271,7 @@ pub async fn get_metrics(
     let metrics = manager.metrics().get_metrics().await;
     let avg_execution_time = manager.metrics().get_average_execution_time().await;
     let success_rate = manager.metrics().get_success_rate().await;
+    let recent = manager.metrics().recent_stats().await;
     let queue_status = manager.get_queue_status().await;
     let health_status = manager.get_health_status().await;
     
@@ -170,6 +286,7 @@ pub async fn get_metrics(
         "total_tokens_used": metrics.total_tokens_used,
         "active_agents": metrics.active_agents,
         "active_tasks": metrics.active_tasks,
+        "recent": recent,
         "queue_status": queue_status,
         "health_status": health_status,
     })))
@@ -190,3 +307,32 @@ pub async fn get_health_status(
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     Ok(Json(manager.get_health_status().await))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ai::router::ModelRouter;
+
+    #[tokio::test]
+    async fn test_unknown_agent_and_task_are_404() {
+        let config = Config::from_env().unwrap();
+        let router = Arc::new(ModelRouter::new(&config));
+        let manager = AgentManager::new(router, Arc::new(config.clone()), None);
+        let id = uuid::Uuid::new_v4().to_string();
+
+        let err = get_agent_status(Extension(config.clone()), Extension(Arc::clone(&manager)), Path(id.clone()))
+            .await
+            .unwrap_err();
+        let (status, body) = err.into_test_response().await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+        assert_eq!(body["error"]["message"], "Agent not found");
+
+        let err = get_task_status(Extension(config), Extension(manager), Path(id))
+            .await
+            .unwrap_err();
+        let (status, body) = err.into_test_response().await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["message"], "Task not found");
+    }
+}
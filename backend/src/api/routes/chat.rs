@@ -6,74 +6,135 @@ use axum::{
     http::StatusCode,
     response::Json,
 };
-use crate::types::AIRequest;
+use crate::types::{AIRequest, AIResponse};
+use crate::security::AdvancedValidator;
 use crate::services::ai::router::ModelRouter;
+use crate::services::ai::localization::{localize_request, resolve_response_language};
+use crate::services::ai::history::{summarize_with_router, HistoryCompactor};
+use crate::services::ai::{ResponseCache, ShadowEvaluator};
+use crate::services::codebase::{CodebaseIndexer, ContextPruner};
+use crate::types::MessageRole;
 use crate::config::Config;
 use std::sync::Arc;
 
 pub async fn handle_chat(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
     Extension(router): Extension<Arc<ModelRouter>>,
-    Json(request): Json<AIRequest>,
-) -> Result<Json<crate::types::AIResponse>, StatusCode> {
-    // Select best model
-    let model_info = router.select_best_model(&request)
+    Extension(validator): Extension<Arc<AdvancedValidator>>,
+    Extension(indexer): Extension<Arc<CodebaseIndexer>>,
+    Extension(history_compactor): Extension<Arc<HistoryCompactor>>,
+    Extension(shadow_evaluator): Extension<Arc<ShadowEvaluator>>,
+    Extension(response_cache): Extension<Arc<ResponseCache>>,
+    Json(mut request): Json<AIRequest>,
+) -> Result<Json<AIResponse>, StatusCode> {
+    // Respond in the requested (or default) language
+    let response_language = resolve_response_language(request.response_language.as_deref(), &config.default_response_language)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    request.response_language = Some(response_language.to_string());
+    localize_request(&mut request);
+    
+    // Identical requests answered recently skip routing altogether
+    if let Some(response) = response_cache.get(&request) {
+        tracing::debug!("Serving chat response from cache");
+        return Ok(Json(response));
+    }
+    let cache_request = response_cache.is_cacheable(&request).then(|| request.clone());
+    
+    // Keep only the attached context the question is about
+    let mut pruning_report = None;
+    if config.context_pruning_enabled {
+        let query = request.messages.iter()
+            .rev()
+            .find(|m| matches!(m.role, MessageRole::User))
+            .map(|m| m.content.clone());
+        if let (Some(query), Some(context)) = (query, request.context.as_mut()) {
+            let pruner = ContextPruner::new(indexer.reference_tracker(), config.context_pruning_extra_files);
+            pruning_report = pruner.prune(&query, context).await;
+        }
+    }
+    
+    // Fold long history into a summary (or drop it) before it competes for the context window
+    let compaction_report = history_compactor
+        .compact(&mut request.messages, |prompt| summarize_with_router(&router, prompt))
+        .await;
+    
+    // Select best model, escalating or truncating if the context doesn't fit
+    let routing = router.select_with_context_policy(&request)
         .map_err(|e| {
             tracing::error!("Model selection error: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+    let model_info = routing.model;
+    let mut routing_metadata = routing.metadata;
+    if let Some(report) = pruning_report {
+        tracing::debug!("Context pruning dropped {} files", report.pruned_files.len());
+        routing_metadata.insert("context_pruning".to_string(), serde_json::json!(report));
+    }
+    if let Some(report) = compaction_report {
+        routing_metadata.insert("history_compaction".to_string(), serde_json::json!(report));
+    }
+    let request = routing.request;
     
-    // Try primary model first, with fallback to alternatives
-    let mut tried_providers = Vec::new();
-    
-    // Try primary provider
-    if let Some(service) = router.get_service(model_info.provider.clone()) {
-        tried_providers.push(model_info.provider.clone());
-        match service.generate(request.clone_for_fallback()).await {
-            Ok(response) => {
+    // Try the selected model first, then the next best providers if it errors
+    let started = std::time::Instant::now();
+    match router.generate_with_fallback(request.clone()).await {
+        Ok(mut response) => {
+            let fell_back = response.metadata.as_ref().is_some_and(|m| m.contains_key("fallback_from"));
+            if !fell_back {
                 tracing::info!("Successfully used provider: {:?}", model_info.provider);
-                return Ok(Json(response));
+                if let Some(service) = router.get_service(model_info.provider.clone()) {
+                    shadow_evaluator.observe(&router, &service, &request, &response, started.elapsed());
+                }
+            }
+            if !routing_metadata.is_empty() {
+                response.metadata.get_or_insert_with(Default::default).extend(routing_metadata);
             }
-            Err(e) => {
-                tracing::warn!("Primary provider {:?} failed: {}", model_info.provider, e);
+            let response = apply_output_policy(&validator, request.sanitize_output, response);
+            if let Some(cache_request) = cache_request {
+                response_cache.put(&cache_request, &response);
             }
+            Ok(Json(response))
         }
-    }
-    
-    // Fallback: Try other available providers
-    let fallback_providers = vec![
-        crate::types::ModelProvider::OpenAI,
-        crate::types::ModelProvider::Anthropic,
-        crate::types::ModelProvider::Google,
-        crate::types::ModelProvider::Moonshot,
-        crate::types::ModelProvider::DeepSeek,
-        crate::types::ModelProvider::Mistral,
-        crate::types::ModelProvider::XAI,
-        crate::types::ModelProvider::Together,
-        crate::types::ModelProvider::Anyscale,
-    ];
-    
-    for provider in fallback_providers {
-        if tried_providers.contains(&provider) {
-            continue;
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
         }
-        
-        if let Some(service) = router.get_service(provider.clone()) {
-            tried_providers.push(provider.clone());
-            tracing::info!("Trying fallback provider: {:?}", provider);
-            match service.generate(request.clone_for_fallback()).await {
-                Ok(response) => {
-                    tracing::info!("Fallback provider {:?} succeeded", provider);
-                    return Ok(Json(response));
-                }
-                Err(e) => {
-                    tracing::warn!("Fallback provider {:?} failed: {}", provider, e);
-                }
-            }
+    }
+}
+
+/// Escape injected markup when the client asked for HTML-safe output
+fn apply_output_policy(validator: &AdvancedValidator, sanitize: bool, mut response: AIResponse) -> AIResponse {
+    if sanitize {
+        response.content = validator.sanitize_output(&response.content);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(content: &str) -> AIResponse {
+        AIResponse {
+            content: content.to_string(),
+            model: "gpt-4o".to_string(),
+            usage: None,
+            finish_reason: None,
+            metadata: None,
         }
     }
-    
-    // All providers failed
-    tracing::error!("All providers failed. Tried: {:?}", tried_providers);
-    Err(StatusCode::SERVICE_UNAVAILABLE)
+
+    #[test]
+    fn test_script_is_neutralized_only_when_requested() {
+        let validator = AdvancedValidator::new();
+        let content = "Done <script>alert(1)</script>\n```html\n<script src=\"app.js\"></script>\n```";
+
+        let raw = apply_output_policy(&validator, false, response(content));
+        assert_eq!(raw.content, content);
+
+        let sanitized = apply_output_policy(&validator, true, response(content));
+        assert!(sanitized.content.starts_with("Done &lt;script&gt;alert(1)"));
+        // Code blocks are escaped too; they render as the same text
+        assert!(sanitized.content.ends_with("```html\n&lt;script src=&quot;app.js&quot;&gt;&lt;/script&gt;\n```"));
+    }
 }
@@ -4,15 +4,20 @@
  * Endpoints for codebase analysis, search, review, etc.
  */
 use axum::{
+    body::Body,
     extract::{Extension, Path, Query},
-    http::StatusCode,
-    response::Json,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use crate::services::codebase::*;
 use crate::services::ai::router::ModelRouter;
 use crate::config::Config;
+use crate::config_reload::LiveConfig;
+use crate::services::ai::localization::resolve_response_language;
+use crate::security::{Vulnerability, VulnerabilityScanner};
+use crate::security::codebase_scan::{scan_codebase, scope_targets, ScanTarget};
 
 #[derive(Deserialize)]
 pub struct SearchRequest {
@@ -36,18 +41,78 @@ pub async fn search_codebase(
     Ok(Json(SearchResponse { results }))
 }
 
+#[derive(Deserialize)]
+pub struct UnifiedSearchRequest {
+    pub query: String,
+    /// Sources to search; symbols, semantic and references when omitted
+    pub kinds: Option<Vec<SearchKind>>,
+    /// Most hits taken from each source before merging
+    #[serde(default)]
+    pub limits: std::collections::HashMap<SearchKind, usize>,
+}
+
+#[derive(Serialize)]
+pub struct UnifiedSearchResponse {
+    pub query: String,
+    pub kinds: Vec<SearchKind>,
+    pub results: Vec<SearchHit>,
+}
+
+/// Symbols, semantic matches, references and patterns for one query, merged and ranked
+pub async fn unified_search(
+    Extension(live_config): Extension<Arc<LiveConfig>>,
+    Extension(indexer): Extension<Arc<CodebaseIndexer>>,
+    Json(payload): Json<UnifiedSearchRequest>,
+) -> Result<Json<UnifiedSearchResponse>, StatusCode> {
+    let query = payload.query.trim();
+    if query.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let kinds = payload.kinds.filter(|k| !k.is_empty()).unwrap_or_else(|| SearchKind::DEFAULT.to_vec());
+
+    let config = live_config.load();
+    let search = UnifiedSearch::new(indexer, config.pattern_min_confidence, config.analyzer_profiles.clone());
+    let results = search.search(query, &kinds, &payload.limits).await;
+
+    Ok(Json(UnifiedSearchResponse { query: query.to_string(), kinds, results }))
+}
+
 /// Review code
 pub async fn review_code(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
+    Extension(live_config): Extension<Arc<LiveConfig>>,
     Extension(router): Extension<Arc<ModelRouter>>,
+    Extension(indexer): Extension<Arc<CodebaseIndexer>>,
+    Extension(scanner): Extension<Arc<VulnerabilityScanner>>,
     Json(payload): Json<ReviewCodeRequest>,
-) -> Result<Json<super::codebase::code_reviewer::CodeReviewResult>, StatusCode> {
-    let reviewer = CodeReviewer::new(Arc::clone(&router));
-    let result = reviewer.review_code(&payload.file_path, &payload.code, &payload.language)
+) -> Result<Json<ReviewCodeResponse>, StatusCode> {
+    let response_language = resolve_response_language(payload.response_language.as_deref(), &config.default_response_language)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    
+    // Give the reviewer the definitions of types and functions used from other files
+    let enricher = ContextEnricher::new(indexer.reference_tracker(), config.context_enrichment_token_budget);
+    let (definitions, enrichment) = enricher.enrich(&payload.file_path, &payload.code).await;
+    
+    let reviewer = CodeReviewer::new(Arc::clone(&router))
+        .with_model(Some(config.review_model.clone()))
+        .with_definitions(context_enricher::render_definitions(&definitions))
+        .with_response_language(Some(response_language.to_string()));
+    let review = reviewer.review_code(&payload.file_path, &payload.code, &payload.language)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    Ok(Json(result))
+    let min_confidence = payload.min_confidence.unwrap_or(live_config.load().pattern_min_confidence);
+    let profile = live_config.load().analyzer_profiles.for_language(&payload.language);
+    let patterns = detect_code_patterns(&payload.code, &payload.language, min_confidence, profile);
+
+    // Static findings (hardcoded secrets, injection, weak crypto) alongside the model's review
+    let vulnerabilities = if payload.code.trim().is_empty() {
+        Vec::new()
+    } else {
+        scanner.scan_file(&payload.file_path, &payload.code, &payload.language)
+    };
+    
+    Ok(Json(ReviewCodeResponse { review, patterns, context_enrichment: enrichment, vulnerabilities }))
 }
 
 #[derive(Deserialize)]
@@ -55,6 +120,109 @@ pub struct ReviewCodeRequest {
     pub file_path: String,
     pub code: String,
     pub language: String,
+    pub min_confidence: Option<f64>,
+    pub response_language: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReviewCodeResponse {
+    #[serde(flatten)]
+    pub review: code_reviewer::CodeReviewResult,
+    pub patterns: Vec<DetectedPattern>,
+    /// Definitions from other files that were shown to the reviewer
+    pub context_enrichment: EnrichmentReport,
+    /// Vulnerability scanner findings for the submitted code
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+/// Review only the lines a change touched
+pub async fn review_diff(
+    Extension(config): Extension<Config>,
+    Extension(router): Extension<Arc<ModelRouter>>,
+    Json(payload): Json<ReviewDiffRequest>,
+) -> Result<Json<ReviewDiffResponse>, StatusCode> {
+    let response_language = resolve_response_language(payload.response_language.as_deref(), &config.default_response_language)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let reviewer = CodeReviewer::new(Arc::clone(&router))
+        .with_model(Some(config.review_model.clone()))
+        .with_response_language(Some(response_language.to_string()));
+
+    let (review, hunks) = match (&payload.diff, &payload.old_code, &payload.new_code) {
+        (Some(unified), None, None) => {
+            let hunks = diff::parse_unified_diff(unified, &payload.file_path).map_err(|e| {
+                tracing::warn!("Rejected diff review: {}", e);
+                StatusCode::BAD_REQUEST
+            })?;
+            (reviewer.review_hunks(&payload.file_path, &hunks, &payload.language).await, hunks)
+        }
+        (None, Some(old_code), Some(new_code)) => {
+            let hunks = diff::diff_hunks(old_code, new_code, diff::DEFAULT_CONTEXT_LINES);
+            (reviewer.review_diff(&payload.file_path, old_code, new_code, &payload.language).await, hunks)
+        }
+        // Exactly one of a unified diff or a before/after pair
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let review = review.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ReviewDiffResponse { review, changed_lines: diff::changed_ranges(&hunks) }))
+}
+
+#[derive(Deserialize)]
+pub struct ReviewDiffRequest {
+    pub file_path: String,
+    pub language: String,
+    /// Unified diff of the file
+    pub diff: Option<String>,
+    /// Before/after pair, as an alternative to `diff`
+    pub old_code: Option<String>,
+    pub new_code: Option<String>,
+    pub response_language: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReviewDiffResponse {
+    #[serde(flatten)]
+    pub review: code_reviewer::CodeReviewResult,
+    /// Line ranges of the new file that were reviewed
+    pub changed_lines: Vec<diff::LineRange>,
+}
+
+/// Detect design patterns, anti-patterns and code smells
+pub async fn detect_patterns(
+    Extension(live_config): Extension<Arc<LiveConfig>>,
+    Json(payload): Json<DetectPatternsRequest>,
+) -> Result<Json<DetectPatternsResponse>, StatusCode> {
+    let min_confidence = payload.min_confidence.unwrap_or(live_config.load().pattern_min_confidence);
+    let profile = live_config.load().analyzer_profiles.for_language(&payload.language);
+    let patterns = detect_code_patterns(&payload.code, &payload.language, min_confidence, profile);
+    
+    Ok(Json(DetectPatternsResponse { min_confidence, patterns }))
+}
+
+#[derive(Deserialize)]
+pub struct DetectPatternsRequest {
+    pub code: String,
+    pub language: String,
+    pub min_confidence: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct DetectPatternsResponse {
+    pub min_confidence: f64,
+    pub patterns: Vec<DetectedPattern>,
+}
+
+fn detect_code_patterns(code: &str, language: &str, min_confidence: f64, profile: AnalyzerProfile) -> Vec<DetectedPattern> {
+    let mut parser = ast_parser::ASTParser::new();
+    match parser.parse(code, language) {
+        Ok(ast) => PatternDetector::with_min_confidence(min_confidence)
+            .with_profile(profile)
+            .detect_patterns(&ast, code),
+        Err(e) => {
+            tracing::warn!("Pattern detection skipped, parse failed: {}", e);
+            Vec::new()
+        }
+    }
 }
 
 /// Generate tests
@@ -84,11 +252,14 @@ pub struct GenerateTestsRequest {
 
 /// Generate documentation
 pub async fn generate_docs(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
     Extension(router): Extension<Arc<ModelRouter>>,
     Json(payload): Json<GenerateDocsRequest>,
 ) -> Result<Json<doc_generator::Documentation>, StatusCode> {
-    let generator = DocGenerator::new(Arc::clone(&router));
+    let response_language = resolve_response_language(payload.response_language.as_deref(), &config.default_response_language)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let generator = DocGenerator::new(Arc::clone(&router))
+        .with_response_language(Some(response_language.to_string()));
     let result = generator.generate_docs(&payload.code, &payload.language, &payload.file_path)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -101,6 +272,48 @@ pub struct GenerateDocsRequest {
     pub code: String,
     pub language: String,
     pub file_path: String,
+    pub response_language: Option<String>,
+}
+
+/// Explain a stack trace: failing source locations plus root cause and fix
+pub async fn explain_error(
+    Extension(config): Extension<Config>,
+    Extension(router): Extension<Arc<ModelRouter>>,
+    Extension(indexer): Extension<Arc<CodebaseIndexer>>,
+    Json(payload): Json<ExplainErrorRequest>,
+) -> Result<Json<ErrorExplanation>, StatusCode> {
+    if payload.stack_trace.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let response_language = resolve_response_language(payload.response_language.as_deref(), &config.default_response_language)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    
+    let explainer = ErrorExplainer::new(
+        Arc::clone(&router),
+        Arc::clone(&indexer),
+        config.explain_error_model.clone(),
+        config.explain_error_max_frames,
+    ).with_response_language(Some(response_language.to_string()));
+    let explanation = explainer.explain(
+        &payload.stack_trace,
+        &payload.language,
+        payload.context_files.as_deref().unwrap_or_default(),
+    )
+        .await
+        .map_err(|e| {
+            tracing::error!("{}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    
+    Ok(Json(explanation))
+}
+
+#[derive(Deserialize)]
+pub struct ExplainErrorRequest {
+    pub stack_trace: String,
+    pub language: String,
+    pub context_files: Option<Vec<ContextFile>>,
+    pub response_language: Option<String>,
 }
 
 /// Get dependencies
@@ -117,3 +330,114 @@ pub async fn get_dependencies(
         "dependents": dependents,
     })))
 }
+
+#[derive(Deserialize)]
+pub struct ImpactRequest {
+    pub file_path: String,
+    pub content: String,
+    pub language: Option<String>,
+}
+
+/// Analyze the impact of a proposed file change
+pub async fn analyze_impact(
+    Extension(indexer): Extension<Arc<CodebaseIndexer>>,
+    Json(payload): Json<ImpactRequest>,
+) -> Result<Json<ChangeImpactReport>, StatusCode> {
+    if payload.file_path.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let analyzer = ImpactAnalyzer::new(Arc::clone(&indexer));
+    let report = analyzer.analyze(&payload.file_path, &payload.content, payload.language.as_deref()).await;
+    
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+pub struct DebtQuery {
+    /// Collaboration session used to attribute markers that have no inline author
+    pub session_id: Option<uuid::Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct DebtResponse {
+    pub total: usize,
+    pub by_marker: std::collections::BTreeMap<String, Vec<DebtMarker>>,
+}
+
+/// Technical-debt inventory (TODO/FIXME/HACK/XXX) across indexed files
+pub async fn get_debt(
+    Extension(_config): Extension<Config>,
+    Extension(indexer): Extension<Arc<CodebaseIndexer>>,
+    Extension(edit_audit): Extension<Arc<crate::services::collaboration::EditAuditLog>>,
+    Query(query): Query<DebtQuery>,
+) -> Result<Json<DebtResponse>, StatusCode> {
+    let mut markers = indexer.debt_markers().await;
+
+    if let Some(session_id) = query.session_id {
+        let mut blame_cache: std::collections::HashMap<String, Vec<crate::services::collaboration::edit_audit::BlameLine>> =
+            std::collections::HashMap::new();
+        for marker in markers.iter_mut().filter(|m| m.author.is_none()) {
+            if !blame_cache.contains_key(&marker.file_path) {
+                let blame = edit_audit.blame(session_id, &marker.file_path).await;
+                blame_cache.insert(marker.file_path.clone(), blame);
+            }
+            marker.author = blame_cache[&marker.file_path]
+                .get(marker.line.saturating_sub(1) as usize)
+                .and_then(|b| {
+                    b.user_id.map(|id| format!("user:{}", id))
+                        .or_else(|| b.agent_id.map(|id| format!("agent:{}", id)))
+                });
+        }
+    }
+
+    markers.sort_by(|a, b| a.file_path.cmp(&b.file_path).then(a.line.cmp(&b.line)));
+    let total = markers.len();
+    let mut by_marker: std::collections::BTreeMap<String, Vec<DebtMarker>> = std::collections::BTreeMap::new();
+    for marker in markers {
+        by_marker.entry(marker.marker.clone()).or_default().push(marker);
+    }
+
+    Ok(Json(DebtResponse { total, by_marker }))
+}
+
+#[derive(Deserialize, Default)]
+pub struct SecurityScanRequest {
+    /// Only scan indexed files under this directory
+    pub path: Option<String>,
+    /// Stream per-file progress as NDJSON, ending with the full report
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// Run the vulnerability scanner across every indexed file
+pub async fn security_scan(
+    Extension(indexer): Extension<Arc<CodebaseIndexer>>,
+    Extension(scanner): Extension<Arc<VulnerabilityScanner>>,
+    payload: Option<Json<SecurityScanRequest>>,
+) -> Response {
+    let Json(payload) = payload.unwrap_or_default();
+    let targets = indexer.indexed_files().await
+        .into_iter()
+        .map(|(path, language)| ScanTarget { path, language })
+        .collect();
+    let targets = scope_targets(targets, payload.path.as_deref());
+
+    if !payload.stream {
+        return Json(scan_codebase(scanner, targets, None).await).into_response();
+    }
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(scan_codebase(scanner, targets, Some(tx)));
+    let body = async_stream::stream! {
+        while let Some(event) = rx.recv().await {
+            let mut line = serde_json::to_vec(&event).unwrap_or_default();
+            line.push(b'\n');
+            yield Ok::<_, std::convert::Infallible>(line);
+        }
+    };
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(body),
+    ).into_response()
+}
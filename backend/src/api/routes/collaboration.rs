@@ -14,8 +14,11 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::services::collaboration::{SessionManager, CollaborationWebSocket};
+use crate::services::collaboration::{SessionManager, CollaborationWebSocket, ConflictStrategy, EditAuditLog};
+use crate::services::collaboration::session::ProjectPathError;
+use crate::middleware::auth::UserId;
 use crate::security::{AuditLogger, AdvancedValidator};
+use crate::types::errors::{ApiError, ApiResult};
 
 #[derive(Debug, Serialize)]
 pub struct SessionResponse {
@@ -25,47 +28,100 @@ pub struct SessionResponse {
 #[derive(Debug, Deserialize)]
 pub struct CreateSessionRequest {
     pub name: String,
-    pub owner_id: Uuid,
+    /// Only used for unauthenticated requests (auth disabled); otherwise the caller owns the session
+    #[serde(default)]
+    pub owner_id: Option<Uuid>,
     pub project_path: String,
+    /// Session options, e.g. `{"conflict_resolution": "crdt"}`
+    #[serde(default)]
+    pub settings: Option<serde_json::Value>,
 }
 
 pub async fn create_session(
     Extension(session_manager): Extension<Arc<SessionManager>>,
+    user: Option<Extension<UserId>>,
     Json(request): Json<CreateSessionRequest>,
-) -> Result<Json<SessionResponse>, StatusCode> {
+) -> ApiResult<Json<SessionResponse>> {
+    let owner_id = match (user, request.owner_id) {
+        (Some(Extension(UserId(user_id))), _) => user_id,
+        (None, Some(owner_id)) => owner_id,
+        (None, None) => {
+            return Err(ApiError::validation_error("owner_id is required for unauthenticated requests".to_string())
+                .with_field("owner_id".to_string()));
+        }
+    };
+    let settings = request.settings.unwrap_or_else(|| serde_json::json!({}));
+    if !settings.is_object() {
+        return Err(ApiError::validation_error("settings must be an object".to_string())
+            .with_field("settings".to_string()));
+    }
+    if let Err(e) = ConflictStrategy::from_settings(&settings) {
+        return Err(ApiError::validation_error(e)
+            .with_field(format!("settings.{}", ConflictStrategy::SETTING)));
+    }
+
     match session_manager.create_session(
         request.name,
-        request.owner_id,
+        owner_id,
         request.project_path,
+        settings,
     ).await {
         Ok(session) => Ok(Json(SessionResponse { session })),
-        Err(e) => {
-            tracing::error!("Failed to create session: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => match e.downcast_ref::<ProjectPathError>() {
+            Some(path_error) => Err(ApiError::validation_error(path_error.to_string())
+                .with_field("project_path".to_string())),
+            None => {
+                tracing::error!("Failed to create session: {}", e);
+                Err(ApiError::internal_error("Failed to create session".to_string()))
+            }
+        },
     }
 }
 
 pub async fn get_session(
     Extension(session_manager): Extension<Arc<SessionManager>>,
     Path(session_id): Path<Uuid>,
-) -> Result<Json<SessionResponse>, StatusCode> {
+) -> ApiResult<Json<SessionResponse>> {
     match session_manager.get_session(session_id).await {
         Some(session) => Ok(Json(SessionResponse { session })),
-        None => Err(StatusCode::NOT_FOUND),
+        None => Err(ApiError::not_found("Session").with_details(format!("No session with id {}", session_id))),
+    }
+}
+
+/// Who a collaboration request acts as
+///
+/// An authenticated caller is its token's subject, and any user id it also
+/// sends must match it; it can't act as an agent. Without auth, the ids the
+/// request carries are taken as given.
+fn caller_identity(
+    user: Option<Extension<UserId>>,
+    user_id: Option<Uuid>,
+    agent_id: Option<Uuid>,
+) -> ApiResult<(Option<Uuid>, Option<Uuid>)> {
+    match user {
+        Some(Extension(UserId(subject))) => {
+            if user_id.is_some_and(|id| id != subject) || agent_id.is_some() {
+                return Err(ApiError::forbidden()
+                    .with_details("Requests can only act as the authenticated user".to_string()));
+            }
+            Ok((Some(subject), None))
+        }
+        None => Ok((user_id, agent_id)),
     }
 }
 
 pub async fn join_session(
     Extension(session_manager): Extension<Arc<SessionManager>>,
+    user: Option<Extension<UserId>>,
     Path(session_id): Path<Uuid>,
     Json(request): Json<JoinSessionRequest>,
 ) -> Result<Json<ParticipantResponse>, StatusCode> {
+    let (user_id, agent_id) = caller_identity(user, request.user_id, request.agent_id)
+        .map_err(|_| StatusCode::FORBIDDEN)?;
     match session_manager.join_session(
         session_id,
-        request.user_id,
-        request.agent_id,
-        request.role,
+        user_id,
+        agent_id,
     ).await {
         Ok(participant) => Ok(Json(ParticipantResponse { participant })),
         Err(e) => {
@@ -75,11 +131,11 @@ pub async fn join_session(
     }
 }
 
+/// The joined role isn't requested; the server assigns it (see `SessionManager::join_session`)
 #[derive(Debug, Deserialize)]
 pub struct JoinSessionRequest {
     pub user_id: Option<Uuid>,
     pub agent_id: Option<Uuid>,
-    pub role: crate::services::collaboration::session::ParticipantRole,
 }
 
 #[derive(Debug, Serialize)]
@@ -92,29 +148,42 @@ pub struct WebSocketQuery {
     participant_id: Option<Uuid>,
     user_id: Option<Uuid>,
     agent_id: Option<Uuid>,
+    /// Collaboration protocol version the client speaks; omitted by pre-versioning clients
+    protocol_version: Option<u32>,
+    /// Last edit version a reconnecting client applied; later edits are replayed to it
+    since_version: Option<usize>,
 }
 
 pub async fn collaboration_websocket_handler(
     ws: WebSocketUpgrade,
     Path(session_id): Path<Uuid>,
     Query(query): Query<WebSocketQuery>,
+    user: Option<Extension<UserId>>,
     Extension(websocket_server): Extension<Arc<CollaborationWebSocket>>,
     Extension(session_manager): Extension<Arc<SessionManager>>,
-) -> Response {
+) -> ApiResult<Response> {
+    if session_manager.get_session(session_id).await.is_none() {
+        return Err(ApiError::not_found("Session").with_details(format!("No session with id {}", session_id)));
+    }
+
+    // Edits are attributed to and authorized for this identity, so it can't be chosen freely
+    let (user_id, agent_id) = caller_identity(user, query.user_id, query.agent_id)?;
+    if session_manager.participant_role(session_id, user_id, agent_id).await.is_none() {
+        return Err(ApiError::forbidden()
+            .with_details("Join the session before connecting".to_string()));
+    }
+
     // Generate participant_id if not provided
-    let participant_id = query.participant_id.unwrap_or_else(Uuid::new_v4());
+    let participant_id = query.participant_id.unwrap_or_else(Uuid::new_v4);
 
-    ws.on_upgrade(move |socket| async move {
-        // Verify session exists
-        if session_manager.get_session(session_id).await.is_none() {
-            tracing::error!("Session {} not found", session_id);
-            return;
-        }
+    Ok(ws.on_upgrade(move |socket| async move {
+        // Resolve edit authorship for this connection
+        websocket_server.register_identity(participant_id, user_id, agent_id).await;
 
-        if let Err(e) = websocket_server.handle_connection(session_id, participant_id, socket).await {
+        if let Err(e) = websocket_server.handle_connection(session_id, participant_id, query.protocol_version, query.since_version, socket).await {
             tracing::error!("WebSocket connection error: {}", e);
         }
-    })
+    }))
 }
 
 pub async fn get_session_by_token(
@@ -139,3 +208,97 @@ pub async fn list_participants(
 pub struct ParticipantsResponse {
     pub participants: Vec<crate::services::collaboration::session::Participant>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct BlameQuery {
+    pub file: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlameResponse {
+    pub session_id: Uuid,
+    pub file_path: String,
+    pub lines: Vec<crate::services::collaboration::edit_audit::BlameLine>,
+}
+
+/// Per-line last author for a file in a live session
+pub async fn get_blame(
+    Extension(session_manager): Extension<Arc<SessionManager>>,
+    Extension(edit_audit): Extension<Arc<EditAuditLog>>,
+    Path(session_id): Path<Uuid>,
+    Query(query): Query<BlameQuery>,
+) -> Result<Json<BlameResponse>, StatusCode> {
+    if session_manager.get_session(session_id).await.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let lines = edit_audit.blame(session_id, &query.file).await;
+    Ok(Json(BlameResponse {
+        session_id,
+        file_path: query.file,
+        lines,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_session_is_404() {
+        let session_manager = SessionManager::new(None, Arc::new(AuditLogger::new(100)), std::env::temp_dir());
+
+        let err = get_session(Extension(session_manager), Path(Uuid::new_v4()))
+            .await
+            .unwrap_err();
+        let (status, body) = err.into_test_response().await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+        assert_eq!(body["error"]["message"], "Session not found");
+    }
+
+    #[test]
+    fn test_authenticated_callers_cannot_act_as_someone_else() {
+        let user = Uuid::new_v4();
+        let auth = || Some(Extension(UserId(user)));
+
+        assert_eq!(caller_identity(auth(), None, None).unwrap(), (Some(user), None));
+        assert_eq!(caller_identity(auth(), Some(user), None).unwrap(), (Some(user), None));
+        let err = caller_identity(auth(), Some(Uuid::new_v4()), None).unwrap_err();
+        assert_eq!(err.error.code, crate::types::errors::error_codes::FORBIDDEN);
+        assert!(caller_identity(auth(), None, Some(Uuid::new_v4())).is_err());
+
+        // With auth off there is nothing to check the ids against
+        let agent = Some(Uuid::new_v4());
+        assert_eq!(caller_identity(None, None, agent).unwrap(), (None, agent));
+    }
+
+    #[tokio::test]
+    async fn test_session_owner_is_the_authenticated_user() {
+        let workspace = std::env::temp_dir().join(format!("bloop-workspace-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("app")).unwrap();
+        let session_manager = SessionManager::new(None, Arc::new(AuditLogger::new(100)), workspace.clone());
+        let request = |owner_id| CreateSessionRequest {
+            name: "pairing".to_string(),
+            owner_id,
+            project_path: "app".to_string(),
+            settings: None,
+        };
+
+        // A body-supplied owner can't override the token's subject
+        let user = Uuid::new_v4();
+        let Json(response) = create_session(
+            Extension(Arc::clone(&session_manager)),
+            Some(Extension(UserId(user))),
+            Json(request(Some(Uuid::new_v4()))),
+        ).await.unwrap();
+        assert_eq!(response.session.owner_id, user);
+
+        let err = create_session(Extension(Arc::clone(&session_manager)), None, Json(request(None)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.error.code, crate::types::errors::error_codes::VALIDATION_ERROR);
+
+        std::fs::remove_dir_all(&workspace).ok();
+    }
+}
@@ -3,13 +3,20 @@
  * Provides endpoints for managing the autonomous agent company
  */
 use axum::{
-    extract::Extension,
-    http::StatusCode,
-    response::Json,
+    extract::{Extension, Path, Query, WebSocketUpgrade},
+    extract::ws::{Message, WebSocket},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
-use serde::Serialize;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use crate::services::company::CompanyOrchestrator;
+use tokio::sync::broadcast::error::RecvError;
+use crate::config::Config;
+use crate::middleware::auth::{is_admin, is_authenticated, UserId};
+use crate::services::agent::{AgentManager, CoordinationFilter};
+use crate::services::company::{CompanyBlueprint, CompanyOrchestrator, ImportSummary, ScalingPolicy};
 use crate::services::company::types::*;
 use crate::types::errors::{ApiError, ApiResult};
 
@@ -39,6 +46,50 @@ pub async fn get_status(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MetricsHistoryQuery {
+    /// Start of the range (RFC 3339); defaults to 24 hours before `to`
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// End of the range (RFC 3339); defaults to now
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    /// minute, hour or day; picked from the range's length if omitted, and
+    /// coarsened when the range would have too many points
+    pub granularity: Option<MetricsGranularity>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricsHistoryResponse {
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub to: chrono::DateTime<chrono::Utc>,
+    pub granularity: MetricsGranularity,
+    pub points: Vec<MetricsHistoryPoint>,
+}
+
+/// Company metrics over time, downsampled for trend charts
+pub async fn get_metrics_history(
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+    Query(query): Query<MetricsHistoryQuery>,
+) -> ApiResult<Json<MetricsHistoryResponse>> {
+    let to = query.to.unwrap_or_else(chrono::Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::hours(24));
+    if from >= to {
+        return Err(ApiError::validation_error("from must be before to".to_string()).with_field("from".to_string()));
+    }
+    let granularity = query.granularity
+        .unwrap_or_else(|| MetricsGranularity::for_range(from, to))
+        .capped(from, to)
+        .ok_or_else(|| ApiError::validation_error(format!(
+            "Range is too long; at most {} days of history can be requested", MAX_HISTORY_POINTS
+        )).with_field("from".to_string()))?;
+
+    let points = orchestrator.metrics_history(from, to, granularity).await.map_err(|e| {
+        tracing::error!("{}", e);
+        ApiError::internal_error("Failed to load company metrics history".to_string())
+    })?;
+
+    Ok(Json(MetricsHistoryResponse { from, to, granularity, points }))
+}
+
 /// Get all company members
 pub async fn get_members(
     Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
@@ -47,6 +98,26 @@ pub async fn get_members(
     Ok(Json(members))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    #[serde(default)]
+    pub sort_by: LeaderboardSort,
+    /// Entries returned (default 20, max 100)
+    pub limit: Option<usize>,
+}
+
+/// Members ranked by score, tasks completed or speed
+pub async fn get_leaderboard(
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+    Query(query): Query<LeaderboardQuery>,
+) -> ApiResult<Json<Vec<LeaderboardEntry>>> {
+    let limit = query.limit.unwrap_or(20);
+    if !(1..=100).contains(&limit) {
+        return Err(ApiError::validation_error("limit must be between 1 and 100".to_string()).with_field("limit".to_string()));
+    }
+    Ok(Json(orchestrator.leaderboard(query.sort_by, limit).await))
+}
+
 /// Get all teams
 pub async fn get_teams(
     Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
@@ -54,3 +125,264 @@ pub async fn get_teams(
     let teams = orchestrator.get_teams().await;
     Ok(Json(teams))
 }
+
+/// Export teams, capacities and role counts as a blueprint
+pub async fn export_company(
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+) -> ApiResult<Json<CompanyBlueprint>> {
+    Ok(Json(orchestrator.export_blueprint().await))
+}
+
+/// Converge the company onto a blueprint, adding and retiring agents as needed (admin only)
+pub async fn import_company(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+    Json(blueprint): Json<CompanyBlueprint>,
+) -> ApiResult<Json<ImportSummary>> {
+    if !is_admin(&headers, &config.admin_api_key) {
+        return Err(ApiError::forbidden());
+    }
+    orchestrator.import_blueprint(&blueprint)
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::validation_error(e.to_string()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunStateChange {
+    pub is_running: bool,
+    pub changed: bool, // False when the company was already in the requested state
+}
+
+/// Resume the company's continuous operation loops
+pub async fn start_company(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+) -> ApiResult<Json<RunStateChange>> {
+    if !is_admin(&headers, &config.admin_api_key) {
+        return Err(ApiError::forbidden());
+    }
+    let changed = orchestrator.start().await;
+    Ok(Json(RunStateChange { is_running: orchestrator.is_running().await, changed }))
+}
+
+/// Pause the company's continuous operation; returns once every loop has exited
+pub async fn stop_company(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+) -> ApiResult<Json<RunStateChange>> {
+    if !is_admin(&headers, &config.admin_api_key) {
+        return Err(ApiError::forbidden());
+    }
+    let changed = orchestrator.stop().await;
+    Ok(Json(RunStateChange { is_running: orchestrator.is_running().await, changed }))
+}
+
+/// Current agent scaling policy
+pub async fn get_scaling_policy(
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+) -> ApiResult<Json<ScalingPolicy>> {
+    Ok(Json(orchestrator.scaling_policy().await))
+}
+
+/// Replace the agent scaling policy; applies from the next scaling pass
+pub async fn update_scaling_policy(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+    Json(policy): Json<ScalingPolicy>,
+) -> ApiResult<Json<ScalingPolicy>> {
+    if !is_admin(&headers, &config.admin_api_key) {
+        return Err(ApiError::forbidden());
+    }
+    orchestrator.set_scaling_policy(policy.clone())
+        .await
+        .map_err(|(field, message)| ApiError::validation_error(message).with_field(field.to_string()))?;
+    Ok(Json(policy))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitTaskRequest {
+    pub description: String,
+    pub task_type: crate::types::TaskType,
+    #[serde(default)]
+    pub priority: Option<crate::types::Priority>,
+}
+
+/// Hand the company a task and report which member and team it was routed to
+pub async fn submit_task(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+    Json(request): Json<SubmitTaskRequest>,
+) -> ApiResult<Json<TaskSubmission>> {
+    if !is_admin(&headers, &config.admin_api_key) {
+        return Err(ApiError::forbidden());
+    }
+    if request.description.trim().is_empty() {
+        return Err(ApiError::validation_error("Task description is required".to_string())
+            .with_field("description".to_string()));
+    }
+
+    orchestrator.submit_task(
+        request.description,
+        request.task_type,
+        request.priority.unwrap_or(crate::types::Priority::Medium),
+    )
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to submit company task: {}", e);
+            ApiError::internal_error(e)
+        })
+}
+
+#[derive(Debug, Serialize)]
+pub struct VisualStatus {
+    #[serde(flatten)]
+    pub queue: crate::services::company::visual::VisualQueueStatus,
+    pub pending: usize,
+    pub in_progress: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+/// Stream agent messages and task assignments, optionally filtered by agent or team.
+/// Task and message content is only included for admins.
+pub async fn company_activity_websocket_handler(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    user: Option<Extension<UserId>>,
+    Query(filter): Query<CoordinationFilter>,
+    Extension(config): Extension<Config>,
+    Extension(agent_manager): Extension<Arc<AgentManager>>,
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+) -> Response {
+    if !is_authenticated(user.as_ref().map(|Extension(user)| user), &headers, &config.admin_api_key) {
+        return ApiError::unauthorized().into_response();
+    }
+    let privileged = is_admin(&headers, &config.admin_api_key);
+    let events = agent_manager.coordination_log().subscribe();
+
+    ws.on_upgrade(move |socket| stream_activity(socket, events, filter, privileged, orchestrator))
+}
+
+async fn stream_activity(
+    socket: WebSocket,
+    mut events: tokio::sync::broadcast::Receiver<crate::services::agent::CoordinationEvent>,
+    filter: CoordinationFilter,
+    privileged: bool,
+    orchestrator: Arc<CompanyOrchestrator>,
+) {
+    let (mut sender, mut receiver) = socket.split();
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Activity viewer lagged, skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                // Team filters need current membership
+                let teams: HashMap<String, String> = if filter.team.is_some() {
+                    orchestrator.get_members().await
+                        .into_iter()
+                        .map(|m| (m.agent.id, m.team))
+                        .collect()
+                } else {
+                    HashMap::new()
+                };
+                if !filter.matches(&event, &teams) {
+                    continue;
+                }
+
+                let event = if privileged { event } else { event.redacted() };
+                let Ok(text) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if sender.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            incoming = receiver.next() => {
+                match incoming {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Get a visual creative request by id
+pub async fn get_visual_request(
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<VisualCreativeRequest>> {
+    orchestrator.visual_engine()
+        .get_request(&id)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Visual request").with_details(format!("No visual request with id {}", id)))
+}
+
+/// Serve an asset file produced by the visual pipeline (e.g. an optimized image)
+pub async fn get_visual_asset(
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+    Path(file): Path<String>,
+) -> ApiResult<Response> {
+    let (bytes, content_type) = orchestrator.visual_engine()
+        .asset_file(&file)
+        .await
+        .ok_or_else(|| ApiError::not_found("Asset").with_details(format!("No asset file {}", file)))?;
+    Ok(([(axum::http::header::CONTENT_TYPE, content_type)], bytes).into_response())
+}
+
+/// Get visual creative queue status
+pub async fn get_visual_status(
+    Extension(orchestrator): Extension<Arc<CompanyOrchestrator>>,
+) -> ApiResult<Json<VisualStatus>> {
+    let engine = orchestrator.visual_engine();
+    let requests = engine.list_requests().await;
+    let count = |status: VisualCreativeStatus| requests.iter().filter(|r| r.status == status).count();
+
+    Ok(Json(VisualStatus {
+        queue: engine.queue_status(),
+        pending: count(VisualCreativeStatus::Pending),
+        in_progress: count(VisualCreativeStatus::InProgress),
+        completed: count(VisualCreativeStatus::Completed),
+        failed: count(VisualCreativeStatus::Failed),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::services::agent::AgentManager;
+    use crate::services::ai::router::ModelRouter;
+
+    #[tokio::test]
+    async fn test_unknown_visual_request_is_404() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let router = Arc::new(ModelRouter::new(&config));
+        let agent_manager = AgentManager::new(Arc::clone(&router), Arc::clone(&config), None);
+        let orchestrator = CompanyOrchestrator::new(agent_manager, router, config, None);
+
+        let err = get_visual_request(Extension(orchestrator), Path(uuid::Uuid::new_v4().to_string()))
+            .await
+            .unwrap_err();
+        let (status, body) = err.into_test_response().await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+        assert_eq!(body["error"]["message"], "Visual request not found");
+    }
+}
@@ -1,24 +1,67 @@
 /**
  * Code Execution API Routes
- * 
+ *
  * Execute code and terminal commands safely
+ *
+ * Off unless EXECUTE_ENABLED is set. Commands run without a shell: the binary
+ * must be on the allowlist, every argument passes `AdvancedValidator` (no
+ * shell metacharacters, no absolute or `..` paths), the working directory must
+ * resolve inside WORKSPACE_ROOT and the child sees a scrubbed environment.
+ * This is a jail on what may be asked for, not an OS-level sandbox, so keep
+ * the allowlist to tools that can't be told to run arbitrary code.
  */
 use axum::{
     extract::Extension,
-    http::StatusCode,
     response::Json,
 };
 use serde::{Deserialize, Serialize};
-use std::process::Command;
-use std::time::Duration;
-use tokio::time::timeout;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
 use crate::config::Config;
+use crate::middleware::auth::UserId;
+use crate::middleware::client_ip::ClientIp;
+use crate::security::{
+    AdvancedValidator, AuditEventType, AuditLog, AuditLogger, AuditResult, InputType, ThreatLevel,
+};
+use crate::types::errors::{ApiError, ApiResult};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const TIMEOUT_EXIT_CODE: i32 = 124; // What `timeout(1)` exits with
+
+/// Interpreters, build tools, shells and wrappers that can be told to run
+/// arbitrary code (scripts, lifecycle hooks, build.rs, `-exec`, aliases).
+/// They are refused even when EXECUTE_ALLOWED_COMMANDS lists them, since
+/// files written through the files API would otherwise become code.
+const CODE_RUNNING_COMMANDS: &[&str] = &[
+    "sh", "bash", "zsh", "dash", "ksh", "fish", "csh", "tcsh",
+    "python", "pip", "ruby", "gem", "perl", "php", "lua", "node", "deno", "bun", "java", "jshell",
+    "npm", "npx", "yarn", "pnpm", "cargo", "rustc", "go", "make", "cmake", "ninja", "gradle", "mvn",
+    "gcc", "g++", "cc", "c++", "clang", "ld",
+    "git", "find", "xargs", "env", "awk", "gawk", "mawk", "sed", "tar", "zip", "rsync", "ssh", "scp",
+    "sudo", "su", "doas", "nice", "nohup", "timeout", "watch", "time", "strace", "gdb",
+    "vi", "vim", "nvim", "emacs", "less", "more", "man", "docker", "podman", "kubectl",
+];
+
+/// Whether `command` is one of CODE_RUNNING_COMMANDS, including versioned names like `python3.12`
+fn runs_arbitrary_code(command: &str) -> bool {
+    let name = command.to_ascii_lowercase();
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-');
+    CODE_RUNNING_COMMANDS.contains(&name.as_str()) || CODE_RUNNING_COMMANDS.contains(&base)
+}
+
+/// Environment variables passed through to commands; everything else
+/// (API keys, database URLs) is withheld
+const PASSED_ENV: [&str; 3] = ["PATH", "LANG", "TZ"];
 
 #[derive(Deserialize)]
 pub struct ExecuteRequest {
     pub command: String,
     pub args: Option<Vec<String>>,
-    pub working_dir: Option<String>,
+    pub working_dir: Option<String>, // Relative to the workspace root
     pub timeout_seconds: Option<u64>,
 }
 
@@ -27,68 +70,372 @@ pub struct ExecuteResponse {
     pub success: bool,
     pub stdout: String,
     pub stderr: String,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub timed_out: bool,
     pub exit_code: Option<i32>,
     pub execution_time_ms: u64,
 }
 
 /// Execute command safely
 pub async fn execute_command(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
+    Extension(validator): Extension<Arc<AdvancedValidator>>,
+    Extension(audit_logger): Extension<Arc<AuditLogger>>,
+    user: Option<Extension<UserId>>,
+    ip: ClientIp,
     Json(payload): Json<ExecuteRequest>,
-) -> Result<Json<ExecuteResponse>, StatusCode> {
-    // Whitelist allowed commands for security
-    let allowed_commands = vec![
-        "npm", "node", "cargo", "rustc", "python", "python3",
-        "git", "ls", "pwd", "cat", "echo", "grep", "find",
-        "cd", "mkdir", "rm", "cp", "mv",
-    ];
-    
-    let command_name = payload.command.split_whitespace().next().unwrap_or("");
-    if !allowed_commands.iter().any(|&cmd| command_name.starts_with(cmd)) {
-        return Ok(Json(ExecuteResponse {
-            success: false,
-            stdout: String::new(),
-            stderr: format!("Command '{}' is not allowed", command_name),
-            exit_code: Some(1),
-            execution_time_ms: 0,
-        }));
-    }
-    
-    let start_time = std::time::Instant::now();
-    let timeout_duration = Duration::from_secs(payload.timeout_seconds.unwrap_or(30));
-    
-    let mut cmd = Command::new(&payload.command);
-    
-    if let Some(args) = payload.args {
-        cmd.args(args);
-    }
-    
-    if let Some(working_dir) = payload.working_dir {
-        cmd.current_dir(working_dir);
-    }
-    
-    // Execute with timeout
-    match timeout(timeout_duration, tokio::task::spawn_blocking(move || {
-        cmd.output()
-    })).await {
-        Ok(Ok(Ok(output))) => {
-            let execution_time = start_time.elapsed().as_millis() as u64;
+) -> ApiResult<Json<ExecuteResponse>> {
+    let args = payload.args.clone().unwrap_or_default();
+    let audit = Invocation {
+        logger: &audit_logger,
+        user_id: user.map(|Extension(UserId(id))| id.to_string()),
+        ip: ip.to_string(),
+        command: &payload.command,
+        args: &args,
+    };
+
+    let working_dir = match check_request(&config, &validator, &payload, &args) {
+        Ok(dir) => dir,
+        Err(err) => {
+            audit.rejected(&err).await;
+            return Err(err);
+        }
+    };
+    let limit = Duration::from_secs(
+        payload.timeout_seconds
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .min(config.execute_max_timeout_secs)
+            .max(1),
+    );
+
+    let start_time = Instant::now();
+    let run = run_sandboxed(&payload.command, &args, &working_dir, limit, config.execute_max_output_bytes).await;
+    let execution_time_ms = start_time.elapsed().as_millis() as u64;
+
+    match run {
+        Ok(output) => {
+            audit.finished(&working_dir, &output, execution_time_ms).await;
             Ok(Json(ExecuteResponse {
-                success: output.status.success(),
-                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                exit_code: output.status.code(),
-                execution_time_ms: execution_time,
+                success: !output.timed_out && output.exit_code == Some(0),
+                exit_code: if output.timed_out { Some(TIMEOUT_EXIT_CODE) } else { output.exit_code },
+                stdout: output.stdout,
+                stderr: if output.timed_out {
+                    "Command execution timed out".to_string()
+                } else {
+                    output.stderr
+                },
+                stdout_truncated: output.stdout_truncated,
+                stderr_truncated: output.stderr_truncated,
+                timed_out: output.timed_out,
+                execution_time_ms,
             }))
         }
-        Ok(Ok(Err(_))) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-        Ok(Err(_)) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-        Err(_) => Ok(Json(ExecuteResponse {
-            success: false,
-            stdout: String::new(),
-            stderr: "Command execution timed out".to_string(),
-            exit_code: Some(124),
-            execution_time_ms: timeout_duration.as_millis() as u64,
-        })),
+        Err(e) => {
+            let err = ApiError::internal_error(format!("Failed to run '{}': {}", payload.command, e));
+            audit.rejected(&err).await;
+            Err(err)
+        }
+    }
+}
+
+/// Enforce the allowlist, argument checks and working-directory jail
+///
+/// Returns the canonical directory to run in.
+fn check_request(
+    config: &Config,
+    validator: &AdvancedValidator,
+    payload: &ExecuteRequest,
+    args: &[String],
+) -> ApiResult<PathBuf> {
+    if !config.execute_enabled {
+        return Err(ApiError::forbidden()
+            .with_details("Command execution is disabled; set EXECUTE_ENABLED=true to enable it".to_string()));
+    }
+
+    if runs_arbitrary_code(&payload.command) {
+        return Err(ApiError::validation_error(format!("Command '{}' can run arbitrary code and is never allowed", payload.command))
+            .with_field("command".to_string()));
+    }
+
+    if !config.execute_allowed_commands.iter().any(|allowed| *allowed == payload.command) {
+        return Err(ApiError::validation_error(format!("Command '{}' is not allowed", payload.command))
+            .with_field("command".to_string())
+            .with_details(format!("Allowed commands: {}", config.execute_allowed_commands.join(", "))));
+    }
+
+    for (index, arg) in args.iter().enumerate() {
+        // Arguments are mostly paths and flags; they never reach a query
+        let result = validator.validate_input(arg, InputType::Path);
+        if !result.is_valid || arg.contains('\0') {
+            let reasons: Vec<_> = result.threats.iter().map(|t| t.description.as_str()).collect();
+            return Err(ApiError::validation_error(format!("Argument {} ('{}') was rejected", index, arg))
+                .with_field("args".to_string())
+                .with_details(if reasons.is_empty() { "Contains a NUL byte".to_string() } else { reasons.join("; ") }));
+        }
+    }
+
+    resolve_working_dir(Path::new(&config.workspace_root), payload.working_dir.as_deref())
+}
+
+/// Canonical working directory, which must be a directory inside the workspace root
+fn resolve_working_dir(workspace_root: &Path, requested: Option<&str>) -> ApiResult<PathBuf> {
+    let root = workspace_root.canonicalize()
+        .map_err(|e| ApiError::internal_error(format!("Workspace root is unavailable: {}", e)))?;
+    let requested = requested.unwrap_or(".");
+    let outside = || ApiError::validation_error(format!("Working directory '{}' is not inside the workspace", requested))
+        .with_field("working_dir".to_string());
+
+    let dir = root.join(requested).canonicalize().map_err(|_| outside())?;
+    if !dir.starts_with(&root) || !dir.is_dir() {
+        return Err(outside());
+    }
+    Ok(dir)
+}
+
+#[derive(Debug)]
+struct SandboxOutput {
+    stdout: String,
+    stderr: String,
+    stdout_truncated: bool,
+    stderr_truncated: bool,
+    exit_code: Option<i32>,
+    timed_out: bool,
+}
+
+/// Run `program` directly (no shell) and capture at most `max_output` bytes per stream
+///
+/// The child is killed if it outlives `limit`.
+async fn run_sandboxed(
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    limit: Duration,
+    max_output: usize,
+) -> std::io::Result<SandboxOutput> {
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .env_clear()
+        .envs(std::env::vars_os().filter(|(key, _)| PASSED_ENV.iter().any(|passed| key == passed)))
+        .env("HOME", dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let finished = tokio::time::timeout(limit, async {
+        tokio::join!(
+            read_capped(stdout, max_output),
+            read_capped(stderr, max_output),
+            child.wait(),
+        )
+    }).await;
+
+    match finished {
+        Ok((stdout, stderr, status)) => {
+            let (stdout, stdout_truncated) = stdout?;
+            let (stderr, stderr_truncated) = stderr?;
+            Ok(SandboxOutput {
+                stdout,
+                stderr,
+                stdout_truncated,
+                stderr_truncated,
+                exit_code: status?.code(),
+                timed_out: false,
+            })
+        }
+        Err(_) => {
+            let _ = child.kill().await;
+            Ok(SandboxOutput {
+                stdout: String::new(),
+                stderr: String::new(),
+                stdout_truncated: false,
+                stderr_truncated: false,
+                exit_code: None,
+                timed_out: true,
+            })
+        }
+    }
+}
+
+/// Read a stream to the end, keeping the first `cap` bytes
+///
+/// The rest is drained rather than left in the pipe so a chatty child can't
+/// block on a full buffer.
+async fn read_capped<R: AsyncRead + Unpin>(mut reader: R, cap: usize) -> std::io::Result<(String, bool)> {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        let room = cap.saturating_sub(kept.len());
+        kept.extend_from_slice(&chunk[..n.min(room)]);
+        truncated |= n > room;
+    }
+    Ok((String::from_utf8_lossy(&kept).into_owned(), truncated))
+}
+
+/// Audit trail for one call to the endpoint
+struct Invocation<'a> {
+    logger: &'a AuditLogger,
+    user_id: Option<String>,
+    ip: String,
+    command: &'a str,
+    args: &'a [String],
+}
+
+impl Invocation<'_> {
+    async fn rejected(&self, err: &ApiError) {
+        self.log(
+            AuditEventType::SecurityViolation,
+            AuditResult::Blocked,
+            ThreatLevel::High,
+            serde_json::json!({
+                "args": self.args,
+                "error": err.error.message,
+                "details": err.error.details,
+            }),
+        ).await;
+    }
+
+    async fn finished(&self, dir: &Path, output: &SandboxOutput, execution_time_ms: u64) {
+        let succeeded = !output.timed_out && output.exit_code == Some(0);
+        self.log(
+            AuditEventType::ApiAccess,
+            if succeeded { AuditResult::Success } else { AuditResult::Failure },
+            ThreatLevel::Medium,
+            serde_json::json!({
+                "args": self.args,
+                "working_dir": dir.display().to_string(),
+                "exit_code": output.exit_code,
+                "timed_out": output.timed_out,
+                "execution_time_ms": execution_time_ms,
+            }),
+        ).await;
+    }
+
+    async fn log(&self, event_type: AuditEventType, result: AuditResult, threat_level: ThreatLevel, details: serde_json::Value) {
+        self.logger.log(AuditLog {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            event_type,
+            user_id: self.user_id.clone(),
+            ip_address: Some(self.ip.clone()),
+            resource: "execute".to_string(),
+            action: self.command.to_string(),
+            result,
+            details: Some(details),
+            threat_level,
+        }).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::errors::error_codes;
+
+    struct Setup {
+        config: Config,
+        validator: Arc<AdvancedValidator>,
+        audit_logger: Arc<AuditLogger>,
+    }
+
+    fn setup() -> Setup {
+        let workspace = std::env::temp_dir().join(format!("bloop-execute-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("project")).unwrap();
+        let mut config = Config::from_env().unwrap();
+        config.workspace_root = workspace.display().to_string();
+        config.execute_enabled = true;
+        // python3 is listed to show that interpreters are refused even when configured
+        config.execute_allowed_commands = ["echo", "pwd", "sleep", "python3"].map(String::from).to_vec();
+        config.execute_max_timeout_secs = 1;
+        config.execute_max_output_bytes = 8;
+        Setup {
+            config,
+            validator: Arc::new(AdvancedValidator::new()),
+            audit_logger: Arc::new(AuditLogger::new(100)),
+        }
+    }
+
+    async fn execute(s: &Setup, command: &str, args: &[&str], working_dir: Option<&str>) -> ApiResult<ExecuteResponse> {
+        execute_command(
+            Extension(s.config.clone()),
+            Extension(Arc::clone(&s.validator)),
+            Extension(Arc::clone(&s.audit_logger)),
+            None,
+            ClientIp("127.0.0.1".parse().unwrap()),
+            Json(ExecuteRequest {
+                command: command.to_string(),
+                args: Some(args.iter().map(|a| a.to_string()).collect()),
+                working_dir: working_dir.map(|d| d.to_string()),
+                timeout_seconds: Some(30),
+            }),
+        ).await.map(|Json(response)| response)
+    }
+
+    #[tokio::test]
+    async fn test_execute_is_disabled_by_default() {
+        let mut s = setup();
+        s.config.execute_enabled = false;
+        let err = execute(&s, "echo", &["hi"], None).await.unwrap_err();
+        assert_eq!(err.error.code, error_codes::FORBIDDEN);
+        assert_eq!(s.audit_logger.get_recent_logs(10).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_execute_runs_allowlisted_commands_inside_the_jail() {
+        let s = setup();
+
+        let ok = execute(&s, "pwd", &[], Some("project")).await.unwrap();
+        assert!(ok.success);
+        assert!(ok.stdout_truncated, "output is capped at 8 bytes");
+        assert_eq!(ok.stdout.len(), 8);
+
+        let ok = execute(&s, "echo", &["hi"], None).await.unwrap();
+        assert_eq!(ok.stdout, "hi\n");
+        assert!(!ok.stdout_truncated);
+
+        // Not on the allowlist, shell metacharacters, and escaping the workspace
+        for (command, args, dir, field) in [
+            ("rm", vec!["-rf", "project"], None, "command"),
+            ("/bin/echo", vec!["hi"], None, "command"),
+            ("python3", vec!["script.py"], None, "command"),
+            ("echo", vec!["hi; rm -rf project"], None, "args"),
+            ("echo", vec!["$(whoami)"], None, "args"),
+            ("echo", vec!["../../etc/passwd"], None, "args"),
+            ("pwd", vec![], Some(".."), "working_dir"),
+            ("pwd", vec![], Some("/tmp"), "working_dir"),
+        ] {
+            let err = execute(&s, command, &args, dir).await.unwrap_err();
+            assert_eq!(err.error.code, error_codes::VALIDATION_ERROR, "{} {:?}", command, args);
+            assert_eq!(err.error.field.as_deref(), Some(field));
+        }
+
+        // Requested timeouts are capped by configuration
+        let slow = execute(&s, "sleep", &["5"], None).await.unwrap();
+        assert!(slow.timed_out && !slow.success);
+        assert_eq!(slow.exit_code, Some(TIMEOUT_EXIT_CODE));
+
+        let logs = s.audit_logger.get_recent_logs(100).await;
+        assert_eq!(logs.len(), 11);
+        assert_eq!(logs.iter().filter(|l| matches!(l.result, AuditResult::Blocked)).count(), 8);
+    }
+
+    #[test]
+    fn test_code_running_commands_are_recognised() {
+        for command in ["python3", "python3.12", "Node", "npm", "cargo", "find", "git", "bash"] {
+            assert!(runs_arbitrary_code(command), "{}", command);
+        }
+        for command in ["ls", "pwd", "cat", "echo", "grep", "sleep"] {
+            assert!(!runs_arbitrary_code(command), "{}", command);
+        }
     }
 }
@@ -4,16 +4,22 @@
  * Real file operations - read, write, create, delete files
  */
 use axum::{
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
     body::Body,
 };
 use serde::{Deserialize, Serialize};
 use std::path::{PathBuf, Path as StdPath};
 use std::fs;
 use std::io::Write;
+use std::sync::{Arc, Mutex};
+use sha2::{Digest, Sha256};
 use crate::config::Config;
+use crate::security::AdvancedValidator;
+
+/// Serializes check-then-write so two conditional writes can't both pass the hash check
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
 
 #[derive(Serialize)]
 pub struct FileContent {
@@ -21,6 +27,7 @@ pub struct FileContent {
     pub content: String,
     pub exists: bool,
     pub size: u64,
+    pub hash: Option<String>, // SHA-256 of the content; send back as expected_hash when writing
 }
 
 #[derive(Deserialize)]
@@ -28,6 +35,38 @@ pub struct WriteFileRequest {
     pub path: String,
     pub content: String,
     pub create_dirs: Option<bool>,
+    /// Hash of the version the client read; the write is rejected if the file changed since
+    pub expected_hash: Option<String>,
+    /// Keep the previous contents next to the file as `<name>.bak`
+    pub overwrite_backup: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct WriteConflict {
+    pub path: String,
+    pub message: String,
+    pub current_hash: Option<String>,
+    pub current_content: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListQuery {
+    pub recursive: Option<bool>,
+    pub max_depth: Option<usize>, // Levels below the listed directory; capped by FILE_LIST_MAX_DEPTH
+}
+
+/// One entry of a recursive listing
+#[derive(Debug, Serialize)]
+pub struct TreeEntry {
+    pub name: String,
+    pub path: String, // Relative to the listed directory
+    #[serde(rename = "type")]
+    pub entry_type: &'static str, // "file" or "directory"
+    pub size: u64,
+    pub modified: Option<String>,
+    /// Contents of a directory; absent at the depth limit and for symlinked directories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<TreeEntry>>,
 }
 
 #[derive(Serialize)]
@@ -39,16 +78,18 @@ pub struct FileOperationResult {
 
 /// Read file content
 pub async fn read_file(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
     Path(file_path): Path<String>,
 ) -> Result<Json<FileContent>, StatusCode> {
     let path = sanitize_path(&file_path)?;
+    check_extension(&config, &path)?;
     
     match fs::read_to_string(&path) {
         Ok(content) => {
             let metadata = fs::metadata(&path).ok();
             Ok(Json(FileContent {
                 path: file_path,
+                hash: Some(content_hash(&content)),
                 content,
                 exists: true,
                 size: metadata.map(|m| m.len()).unwrap_or(0),
@@ -60,6 +101,7 @@ pub async fn read_file(
                 content: String::new(),
                 exists: false,
                 size: 0,
+                hash: None,
             }))
         }
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
@@ -67,11 +109,21 @@ pub async fn read_file(
 }
 
 /// Write file content
+///
+/// With `expected_hash`, the write only happens if the file still matches what the
+/// client read; otherwise 409 Conflict is returned with the current content.
+/// The new content goes to a temp file that is renamed over the target, so a
+/// crash mid-write leaves the old file untouched.
 pub async fn write_file(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
+    Extension(validator): Extension<Arc<AdvancedValidator>>,
     Json(payload): Json<WriteFileRequest>,
-) -> Result<Json<FileOperationResult>, StatusCode> {
+) -> Result<Response, StatusCode> {
+    if !validator.validate_file_path(&payload.path) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
     let path = sanitize_path(&payload.path)?;
+    check_extension(&config, &path)?;
     
     // Create parent directories if needed
     if payload.create_dirs.unwrap_or(false) {
@@ -82,22 +134,141 @@ pub async fn write_file(
         }
     }
     
-    match fs::write(&path, payload.content.as_bytes()) {
-        Ok(_) => Ok(Json(FileOperationResult {
+    let backup = payload.overwrite_backup.unwrap_or(false);
+    match write_if_unchanged(&path, &payload.content, payload.expected_hash.as_deref(), backup) {
+        Ok(()) => Ok(Json(FileOperationResult {
             success: true,
             message: "File written successfully".to_string(),
             path: payload.path,
-        })),
-        Err(e) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }).into_response()),
+        Err(WriteOutcome::Conflict { current_hash, current_content }) => Ok((
+            StatusCode::CONFLICT,
+            Json(WriteConflict {
+                path: payload.path,
+                message: "File was modified since it was read; merge and retry".to_string(),
+                current_hash,
+                current_content,
+            }),
+        ).into_response()),
+        Err(WriteOutcome::NotRegularFile) => {
+            tracing::warn!("Refusing to read {} for a write: not a regular file", path.display());
+            Err(StatusCode::BAD_REQUEST)
+        }
+        Err(WriteOutcome::Io(e)) => {
+            tracing::error!("Failed to write {}: {}", path.display(), e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
+enum WriteOutcome {
+    Conflict {
+        current_hash: Option<String>,
+        current_content: Option<String>,
+    },
+    /// The existing path is a symlink or other non-file whose contents would have been read
+    NotRegularFile,
+    Io(std::io::Error),
+}
+
+/// SHA-256 hex digest of file content
+fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Write `content`, first checking the on-disk file still hashes to `expected_hash` (if given)
+///
+/// With `backup`, an existing file's contents are first saved to `<name>.bak`.
+/// Both read the existing file, so neither follows a symlink: its target could
+/// be outside the workspace.
+fn write_if_unchanged(path: &StdPath, content: &str, expected_hash: Option<&str>, backup: bool) -> Result<(), WriteOutcome> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    
+    if expected_hash.is_some() || backup {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if !metadata.file_type().is_file() => return Err(WriteOutcome::NotRegularFile),
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(WriteOutcome::Io(e)),
+        }
+    }
+
+    if let Some(expected) = expected_hash {
+        let current = match fs::read_to_string(path) {
+            Ok(current) => Some(current),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(WriteOutcome::Io(e)),
+        };
+        let current_hash = current.as_deref().map(content_hash);
+        if current_hash.as_deref() != Some(expected) {
+            return Err(WriteOutcome::Conflict {
+                current_hash,
+                current_content: current,
+            });
+        }
+    }
+    
+    if backup {
+        match fs::read(path) {
+            Ok(previous) => atomic_write(&backup_path(path), &previous).map_err(WriteOutcome::Io)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(WriteOutcome::Io(e)),
+        }
+    }
+
+    atomic_write(path, content.as_bytes()).map_err(WriteOutcome::Io)
+}
+
+/// `<name>.bak` alongside `path`
+fn backup_path(path: &StdPath) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+fn atomic_write(path: &StdPath, content: &[u8]) -> std::io::Result<()> {
+    write_atomically(path, |file| file.write_all(content))
+}
+
+/// Fill a temp file in the target's directory via `write`, then rename it over `path`
+///
+/// The rename is atomic on the same filesystem, so readers see either the old
+/// or the new file. If anything fails the temp file is removed and `path` is
+/// left as it was.
+fn write_atomically(
+    path: &StdPath,
+    write: impl FnOnce(&mut fs::File) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let dir = path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| StdPath::new("."));
+    let name = path.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let temp = dir.join(format!(".{}.{}.tmp", name.to_string_lossy(), uuid::Uuid::new_v4()));
+
+    let result = (|| {
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).open(&temp)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        write(&mut file)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+
+    if result.is_err() {
+        fs::remove_file(&temp).ok();
+    }
+    result
+}
+
 /// Delete file
 pub async fn delete_file(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
     Path(file_path): Path<String>,
 ) -> Result<Json<FileOperationResult>, StatusCode> {
     let path = sanitize_path(&file_path)?;
+    check_extension(&config, &path)?;
     
     match fs::remove_file(&path) {
         Ok(_) => Ok(Json(FileOperationResult {
@@ -110,11 +281,46 @@ pub async fn delete_file(
 }
 
 /// List directory contents
+///
+/// One level by default. With `recursive=true` the whole tree is returned,
+/// nested under `entries`, down to `max_depth` levels and at most
+/// FILE_LIST_MAX_ENTRIES entries (`truncated` is set when the cap cut it short).
 pub async fn list_directory(
-    Extension(_config): Extension<Config>,
+    Extension(config): Extension<Config>,
+    Extension(validator): Extension<Arc<AdvancedValidator>>,
     Path(dir_path): Path<String>,
+    Query(query): Query<ListQuery>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !validator.validate_file_path(&dir_path) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
     let path = sanitize_path(&dir_path)?;
+
+    if query.recursive.unwrap_or(false) {
+        let max_depth = query.max_depth
+            .unwrap_or(config.file_list_max_depth)
+            .clamp(1, config.file_list_max_depth.max(1));
+        let root = path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+        if !root.is_dir() {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        let mut walk = TreeWalk {
+            root: root.clone(),
+            max_depth,
+            max_entries: config.file_list_max_entries,
+            count: 0,
+            truncated: false,
+        };
+        let entries = walk.list(&root, StdPath::new(""), 1);
+        return Ok(Json(serde_json::json!({
+            "path": dir_path,
+            "recursive": true,
+            "max_depth": max_depth,
+            "entries": entries,
+            "total": walk.count,
+            "truncated": walk.truncated,
+        })));
+    }
     
     match fs::read_dir(&path) {
         Ok(entries) => {
@@ -135,6 +341,7 @@ pub async fn list_directory(
                         "path": entry.path().to_string_lossy(),
                         "type": file_type,
                         "size": metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+                        "modified": metadata.as_ref().and_then(modified_time),
                     });
                     
                     if file_type == "directory" {
@@ -155,6 +362,88 @@ pub async fn list_directory(
     }
 }
 
+/// Depth- and size-limited walk below a canonical root
+struct TreeWalk {
+    root: PathBuf,
+    max_depth: usize,
+    max_entries: usize,
+    count: usize,
+    truncated: bool,
+}
+
+impl TreeWalk {
+    /// Entries of `dir` (at `depth`, 1 = the root's children), sorted by name
+    ///
+    /// Symlinks are listed only if they resolve inside the root, and are never
+    /// descended into so a link back up the tree can't loop.
+    fn list(&mut self, dir: &StdPath, relative: &StdPath, depth: usize) -> Vec<TreeEntry> {
+        let Ok(read) = fs::read_dir(dir) else { return Vec::new() };
+        let mut children: Vec<_> = read.filter_map(Result::ok).collect();
+        children.sort_by_key(|entry| entry.file_name());
+
+        let mut entries = Vec::new();
+        for entry in children {
+            if self.count >= self.max_entries {
+                self.truncated = true;
+                break;
+            }
+            let path = entry.path();
+            let Ok(own) = fs::symlink_metadata(&path) else { continue };
+            let is_link = own.file_type().is_symlink();
+            let metadata = if is_link {
+                match path.canonicalize() {
+                    Ok(target) if target.starts_with(&self.root) => fs::metadata(&target).ok(),
+                    _ => {
+                        tracing::debug!("Listing skipped {} (dangling or outside the listed directory)", path.display());
+                        continue;
+                    }
+                }
+            } else {
+                Some(own)
+            };
+            let Some(metadata) = metadata else { continue };
+
+            self.count += 1;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let entry_path = relative.join(&name);
+            let is_dir = metadata.is_dir();
+            let children = (is_dir && !is_link && depth < self.max_depth)
+                .then(|| self.list(&path, &entry_path, depth + 1));
+            entries.push(TreeEntry {
+                name,
+                path: entry_path.to_string_lossy().into_owned(),
+                entry_type: if is_dir { "directory" } else { "file" },
+                size: metadata.len(),
+                modified: modified_time(&metadata),
+                children,
+            });
+        }
+        entries
+    }
+}
+
+/// Last modification time as RFC 3339, where the platform records it
+fn modified_time(metadata: &fs::Metadata) -> Option<String> {
+    metadata.modified().ok().map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339())
+}
+
+/// Refuse files whose extension is denied or not on the allowlist
+///
+/// Files without an extension (Makefile, Dockerfile) are matched by their full name.
+fn check_extension(config: &Config, path: &StdPath) -> Result<(), StatusCode> {
+    let key = path.extension()
+        .or_else(|| path.file_name())
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let denied = config.file_denied_extensions.contains(&key);
+    if denied || !config.file_allowed_extensions.contains(&key) {
+        tracing::warn!("File API refused {} (extension {:?} not allowed)", path.display(), key);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
 /// Sanitize file path to prevent directory traversal
 fn sanitize_path(input: &str) -> Result<PathBuf, StatusCode> {
     // Remove any path traversal attempts
@@ -174,3 +463,237 @@ fn sanitize_path(input: &str) -> Result<PathBuf, StatusCode> {
     
     Ok(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_expected_hash_is_rejected() {
+        let path = std::env::temp_dir().join(format!("bloop-write-{}.txt", uuid::Uuid::new_v4()));
+        fs::write(&path, "original").unwrap();
+        let read_hash = content_hash("original");
+
+        // Another client writes first
+        assert!(write_if_unchanged(&path, "theirs", Some(&read_hash), false).is_ok());
+
+        // Our write is based on the stale read
+        match write_if_unchanged(&path, "ours", Some(&read_hash), false) {
+            Err(WriteOutcome::Conflict { current_hash, current_content }) => {
+                assert_eq!(current_content.as_deref(), Some("theirs"));
+                assert_eq!(current_hash, Some(content_hash("theirs")));
+            }
+            _ => panic!("expected a conflict"),
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theirs");
+
+        // Unconditional writes still overwrite
+        assert!(write_if_unchanged(&path, "ours", None, false).is_ok());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "ours");
+
+        fs::remove_file(&path).ok();
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bloop-atomic-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries(dir: &StdPath) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_write_replaces_file_atomically_with_optional_backup() {
+        let dir = temp_dir();
+        let path = dir.join("notes.md");
+
+        // New file: nothing to back up
+        assert!(write_if_unchanged(&path, "v1", None, true).is_ok());
+        assert_eq!(entries(&dir), ["notes.md"]);
+
+        assert!(write_if_unchanged(&path, "v2", None, true).is_ok());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v2");
+        assert_eq!(fs::read_to_string(dir.join("notes.md.bak")).unwrap(), "v1");
+
+        assert!(write_if_unchanged(&path, "v3", None, false).is_ok());
+        assert_eq!(fs::read_to_string(dir.join("notes.md.bak")).unwrap(), "v1");
+        assert_eq!(entries(&dir), ["notes.md", "notes.md.bak"], "no temp files are left behind");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_not_backed_up_or_hashed() {
+        use std::os::unix::fs::symlink;
+
+        let outside = temp_dir();
+        fs::write(outside.join("secret.env"), "TOKEN=1").unwrap();
+        let dir = temp_dir();
+        let path = dir.join("config.env");
+        symlink(outside.join("secret.env"), &path).unwrap();
+
+        assert!(matches!(write_if_unchanged(&path, "TOKEN=2", None, true), Err(WriteOutcome::NotRegularFile)));
+        assert!(matches!(
+            write_if_unchanged(&path, "TOKEN=2", Some(&content_hash("guess")), false),
+            Err(WriteOutcome::NotRegularFile)
+        ));
+        assert_eq!(entries(&dir), ["config.env"]);
+        assert_eq!(fs::read_to_string(outside.join("secret.env")).unwrap(), "TOKEN=1");
+
+        fs::remove_dir_all(&dir).ok();
+        fs::remove_dir_all(&outside).ok();
+    }
+
+    #[test]
+    fn test_failed_write_leaves_original_intact() {
+        let dir = temp_dir();
+        let path = dir.join("main.rs");
+        fs::write(&path, "original").unwrap();
+
+        // The process "dies" halfway through writing the new content
+        let err = write_atomically(&path, |file| {
+            file.write_all(b"half of the new")?;
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "disk full"))
+        }).unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(entries(&dir), ["main.rs"]);
+
+        // Renaming over a directory fails too, without touching it
+        fs::create_dir(dir.join("src")).unwrap();
+        assert!(atomic_write(&dir.join("src"), b"oops").is_err());
+        assert!(dir.join("src").is_dir());
+        assert_eq!(entries(&dir), ["main.rs", "src"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    fn names(entries: &serde_json::Value) -> Vec<&str> {
+        entries.as_array().unwrap().iter().map(|e| e["name"].as_str().unwrap()).collect()
+    }
+
+    async fn list_tree(config: &Config, dir: &str, max_depth: Option<usize>) -> serde_json::Value {
+        let Json(listing) = list_directory(
+            Extension(config.clone()),
+            Extension(Arc::new(AdvancedValidator::new())),
+            Path(dir.to_string()),
+            Query(ListQuery { recursive: Some(true), max_depth }),
+        ).await.unwrap();
+        listing
+    }
+
+    #[tokio::test]
+    async fn test_recursive_listing_respects_depth_and_entry_caps() {
+        let mut config = Config::from_env().unwrap();
+        config.file_list_max_depth = 3;
+        config.file_list_max_entries = 100;
+        let dir = format!("target/bloop-tree-{}", uuid::Uuid::new_v4());
+        fs::create_dir_all(format!("{}/a/b/c/d", dir)).unwrap();
+        fs::write(format!("{}/a/b/c/d/deep.rs", dir), "").unwrap();
+        fs::write(format!("{}/a/top.rs", dir), "fn top() {}").unwrap();
+
+        let listing = list_tree(&config, &dir, Some(2)).await;
+        let a = &listing["entries"][0];
+        assert_eq!(a["path"], "a");
+        assert_eq!(a["type"], "directory");
+        assert_eq!(names(&a["children"]), ["b", "top.rs"]);
+        let (b, top) = (&a["children"][0], &a["children"][1]);
+        assert_eq!(b["path"], "a/b");
+        assert!(b.get("children").is_none(), "depth 2 stops below a/b");
+        assert_eq!(top["size"], 11);
+        assert!(top["modified"].is_string());
+        assert_eq!(listing["total"], 3);
+
+        // Requests beyond the configured depth are capped
+        let listing = list_tree(&config, &dir, Some(50)).await;
+        assert_eq!(listing["max_depth"], 3);
+        assert!(listing["entries"][0]["children"][0]["children"][0].get("children").is_none());
+
+        // The entry cap truncates the walk
+        config.file_list_max_entries = 2;
+        let listing = list_tree(&config, &dir, None).await;
+        assert_eq!(listing["total"], 2);
+        assert_eq!(listing["truncated"], true);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_recursive_listing_skips_symlinks_that_escape_the_root() {
+        use std::os::unix::fs::symlink;
+
+        let config = Config::from_env().unwrap();
+        let outside = temp_dir();
+        fs::write(outside.join("secret.env"), "TOKEN=1").unwrap();
+        let dir = format!("target/bloop-tree-{}", uuid::Uuid::new_v4());
+        fs::create_dir_all(format!("{}/src", dir)).unwrap();
+        fs::write(format!("{}/src/lib.rs", dir), "").unwrap();
+        let root = fs::canonicalize(&dir).unwrap();
+        symlink(&outside, root.join("escape")).unwrap();
+        symlink(outside.join("secret.env"), root.join("secret.env")).unwrap();
+        symlink(root.join("src"), root.join("alias")).unwrap();
+        symlink(&root, root.join("src/loop")).unwrap();
+
+        let listing = list_tree(&config, &dir, None).await;
+        assert_eq!(names(&listing["entries"]), ["alias", "src"], "links outside the root are not listed");
+
+        // In-root links are listed but not followed, so the loop back to the root ends there
+        let alias = &listing["entries"][0];
+        assert_eq!(alias["type"], "directory");
+        assert!(alias.get("children").is_none());
+        let src = &listing["entries"][1]["children"];
+        assert_eq!(names(src), ["lib.rs", "loop"]);
+        assert!(src[1].get("children").is_none());
+
+        fs::remove_dir_all(&dir).ok();
+        fs::remove_dir_all(&outside).ok();
+    }
+
+    fn write_request(path: &str) -> Json<WriteFileRequest> {
+        Json(WriteFileRequest {
+            path: path.to_string(),
+            content: "fn main() {}\n".to_string(),
+            create_dirs: Some(true),
+            expected_hash: None,
+            overwrite_backup: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_extension_policy_on_write() {
+        let mut config = Config::from_env().unwrap();
+        config.file_allowed_extensions = crate::services::agent::AgentSecurityConfig::default().allowed_file_extensions;
+        config.file_denied_extensions = vec!["exe".to_string(), "sh".to_string()];
+        let dir = format!("target/bloop-files-{}", uuid::Uuid::new_v4());
+        let validator = || Extension(Arc::new(AdvancedValidator::new()));
+
+        let allowed = format!("{}/main.rs", dir);
+        let response = write_file(Extension(config.clone()), validator(), write_request(&allowed)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(fs::read_to_string(&allowed).unwrap(), "fn main() {}\n");
+
+        let denied = format!("{}/install.sh", dir);
+        let status = write_file(Extension(config.clone()), validator(), write_request(&denied)).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(!StdPath::new(&denied).exists());
+
+        // Deny wins over allow, and the check ignores case
+        config.file_allowed_extensions.push("sh".to_string());
+        let status = write_file(Extension(config.clone()), validator(), write_request(&format!("{}/RUN.SH", dir))).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Traversal is refused before the extension check
+        let status = write_file(Extension(config), validator(), write_request("../escape.rs")).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
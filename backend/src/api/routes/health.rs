@@ -8,9 +8,19 @@ use axum::{
     response::Json,
 };
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use crate::config::Config;
 use crate::database::Database;
+use crate::services::agent::AgentManager;
+use crate::services::ai::router::ModelRouter;
+use crate::services::integrations::OpenClawWebSocketClient;
+
+/// How long the readiness probe waits on the database before calling it down
+const DB_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Backpressure saturation at which the agent queue is reported as degraded
+const QUEUE_DEGRADED_SATURATION: f64 = 0.9;
 
 #[derive(Debug, Serialize)]
 pub struct HealthStatus {
@@ -39,7 +49,6 @@ pub async fn health_check(
     Extension(database): Extension<Option<Arc<Database>>>,
 ) -> Json<HealthStatus> {
     use chrono::Utc;
-    use std::time::Instant;
 
     let mut db_status = DatabaseStatus {
         connected: false,
@@ -87,12 +96,178 @@ pub async fn health_check(
     })
 }
 
-/// Simple readiness probe
-pub async fn readiness() -> Result<&'static str, StatusCode> {
-    Ok("ready")
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Degraded,
+    Down,
+    Disabled,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubsystemCheck {
+    pub status: CheckStatus,
+    pub critical: bool, // A critical subsystem that is down makes the instance not ready
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub details: serde_json::Value,
+}
+
+impl SubsystemCheck {
+    fn new(status: CheckStatus, critical: bool, details: serde_json::Value) -> Self {
+        Self { status, critical, message: None, details }
+    }
+
+    fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    fn is_failing(&self) -> bool {
+        self.critical && self.status == CheckStatus::Down
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, SubsystemCheck>,
+    pub timestamp: String,
+}
+
+/// Readiness probe that checks every dependency the instance needs to serve traffic
+///
+/// Responds 200 when no critical subsystem is down and 503 otherwise; the body
+/// breaks the result down per subsystem either way.
+pub async fn readiness(
+    Extension(database): Extension<Option<Arc<Database>>>,
+    Extension(router): Extension<Arc<ModelRouter>>,
+    Extension(agent_manager): Extension<Arc<AgentManager>>,
+    Extension(openclaw): Extension<Arc<OpenClawWebSocketClient>>,
+) -> (StatusCode, Json<ReadinessReport>) {
+    let mut checks = BTreeMap::new();
+    checks.insert("database", check_database(database.as_deref()).await);
+    checks.insert("ai_providers", check_providers(&router));
+    checks.insert("openclaw", check_openclaw(&openclaw).await);
+    checks.insert("agent_queue", check_agent_queue(&agent_manager).await);
+
+    let ready = !checks.values().any(SubsystemCheck::is_failing);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(ReadinessReport {
+        ready,
+        checks,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    }))
+}
+
+/// The database is only critical when one is configured; without it the API runs in-memory
+async fn check_database(database: Option<&Database>) -> SubsystemCheck {
+    let Some(db) = database else {
+        return SubsystemCheck::new(CheckStatus::Disabled, false, serde_json::json!({}))
+            .with_message("No database configured");
+    };
+
+    let start = Instant::now();
+    match tokio::time::timeout(DB_PROBE_TIMEOUT, db.health_check()).await {
+        Ok(Ok(())) => SubsystemCheck::new(CheckStatus::Ok, true, serde_json::json!({
+            "latency_ms": start.elapsed().as_millis() as u64,
+        })),
+        Ok(Err(e)) => SubsystemCheck::new(CheckStatus::Down, true, serde_json::json!({}))
+            .with_message(format!("Health query failed: {}", e)),
+        Err(_) => SubsystemCheck::new(CheckStatus::Down, true, serde_json::json!({}))
+            .with_message(format!("No response within {}s", DB_PROBE_TIMEOUT.as_secs())),
+    }
+}
+
+/// At least one provider must be configured; all of them cooling down after failures is degraded
+fn check_providers(router: &ModelRouter) -> SubsystemCheck {
+    let configured = router.configured_providers();
+    let details = serde_json::json!({ "configured": configured });
+    if configured.is_empty() {
+        SubsystemCheck::new(CheckStatus::Down, true, details).with_message("No AI provider is configured")
+    } else if !router.has_available_provider() {
+        SubsystemCheck::new(CheckStatus::Degraded, true, details)
+            .with_message("Every configured provider is currently unavailable")
+    } else {
+        SubsystemCheck::new(CheckStatus::Ok, true, details)
+    }
 }
 
-/// Simple liveness probe
+/// The gateway is optional, so a lost connection only degrades the instance
+async fn check_openclaw(client: &OpenClawWebSocketClient) -> SubsystemCheck {
+    let enabled = std::env::var("OPENCLAW_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return SubsystemCheck::new(CheckStatus::Disabled, false, serde_json::json!({}));
+    }
+
+    let connected = client.is_connected().await;
+    let details = serde_json::json!({
+        "connected": connected,
+        "uptime_seconds": client.uptime().await.map(|d| d.as_secs()),
+        "queued_messages": client.queued_messages().await,
+    });
+    if connected {
+        SubsystemCheck::new(CheckStatus::Ok, false, details)
+    } else {
+        SubsystemCheck::new(CheckStatus::Degraded, false, details).with_message("Reconnecting to the gateway")
+    }
+}
+
+/// A full queue turns new tasks away, so the instance shouldn't take more traffic
+async fn check_agent_queue(agent_manager: &AgentManager) -> SubsystemCheck {
+    let load = agent_manager.queue_load().await;
+    let saturation = load.saturation();
+    let details = serde_json::json!({
+        "saturation": saturation,
+        "queued": load.queued,
+        "queue_capacity": load.queue_capacity,
+        "running": load.running,
+        "max_concurrent": load.max_concurrent,
+    });
+    if load.is_full() {
+        SubsystemCheck::new(CheckStatus::Down, true, details).with_message("Task queue is full")
+    } else if saturation >= QUEUE_DEGRADED_SATURATION {
+        SubsystemCheck::new(CheckStatus::Degraded, true, details)
+    } else {
+        SubsystemCheck::new(CheckStatus::Ok, true, details)
+    }
+}
+
+/// Liveness probe; never touches dependencies so a slow database can't get the process restarted
 pub async fn liveness() -> Result<&'static str, StatusCode> {
     Ok("alive")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_readiness_fails_without_providers() {
+        std::env::remove_var("OPENCLAW_ENABLED");
+        let mut config = Config::from_env().unwrap();
+        config.ollama_base_url.clear();
+        let config = Arc::new(config);
+        let router = Arc::new(ModelRouter::new(&config));
+        let agent_manager = AgentManager::new(Arc::clone(&router), Arc::clone(&config), None);
+        let openclaw = Arc::new(OpenClawWebSocketClient::new(Arc::clone(&config)));
+
+        let (status, Json(report)) = readiness(
+            Extension(None),
+            Extension(router),
+            Extension(agent_manager),
+            Extension(openclaw),
+        ).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!report.ready);
+        assert_eq!(report.checks["ai_providers"].status, CheckStatus::Down);
+        assert_eq!(report.checks["database"].status, CheckStatus::Disabled);
+        assert_eq!(report.checks["openclaw"].status, CheckStatus::Disabled);
+        assert_eq!(report.checks["agent_queue"].status, CheckStatus::Ok);
+        assert_eq!(report.checks.values().filter(|c| c.is_failing()).count(), 1);
+    }
+}
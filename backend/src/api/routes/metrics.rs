@@ -0,0 +1,147 @@
+/**
+ * Prometheus Metrics Endpoint
+ *
+ * Agent task counters, execution-time histogram, queue depth and
+ * circuit-breaker state in the Prometheus text exposition format.
+ * Served only when METRICS_ENABLED is set.
+ */
+use axum::{
+    extract::Extension,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use std::fmt::Write;
+use std::sync::Arc;
+use crate::config::Config;
+use crate::services::agent::{AgentManager, CircuitState, QueueLoad};
+use crate::services::agent::monitoring::{AgentMetrics, EXECUTION_TIME_BUCKETS_MS};
+use crate::types::errors::{ApiError, ApiResult};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Scrape endpoint for Prometheus
+pub async fn prometheus_metrics(
+    Extension(config): Extension<Config>,
+    Extension(manager): Extension<Arc<AgentManager>>,
+) -> ApiResult<Response> {
+    if !config.metrics_enabled {
+        return Err(ApiError::not_found("Metrics endpoint"));
+    }
+
+    let breaker = manager.circuit_breaker();
+    let body = render(
+        &manager.metrics().get_metrics().await,
+        &manager.queue_load().await,
+        breaker.state().await,
+        breaker.trips(),
+    );
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response())
+}
+
+fn render(metrics: &AgentMetrics, load: &QueueLoad, circuit: CircuitState, trips: u64) -> String {
+    let mut out = String::new();
+
+    family(&mut out, "bloop_agent_tasks_started_total", "counter", "Tasks handed to an agent");
+    sample(&mut out, "bloop_agent_tasks_started_total", "", metrics.total_tasks_executed);
+    family(&mut out, "bloop_agent_tasks_completed_total", "counter", "Tasks finished, by outcome");
+    sample(&mut out, "bloop_agent_tasks_completed_total", "{outcome=\"success\"}", metrics.successful_tasks);
+    sample(&mut out, "bloop_agent_tasks_completed_total", "{outcome=\"failure\"}", metrics.failed_tasks);
+    family(&mut out, "bloop_agent_task_retries_total", "counter", "Task re-executions after a failed attempt");
+    sample(&mut out, "bloop_agent_task_retries_total", "", metrics.task_retries);
+    family(&mut out, "bloop_agent_tokens_used_total", "counter", "Model tokens consumed by agent tasks");
+    sample(&mut out, "bloop_agent_tokens_used_total", "", metrics.total_tokens_used);
+
+    let name = "bloop_agent_task_execution_seconds";
+    family(&mut out, name, "histogram", "Wall-clock time of finished tasks, retries included");
+    for (bound, count) in EXECUTION_TIME_BUCKETS_MS.iter().zip(metrics.execution_time_buckets) {
+        let le = *bound as f64 / 1000.0;
+        sample(&mut out, &format!("{}_bucket", name), &format!("{{le=\"{}\"}}", le), count);
+    }
+    let completed = metrics.successful_tasks + metrics.failed_tasks;
+    sample(&mut out, &format!("{}_bucket", name), "{le=\"+Inf\"}", completed);
+    sample(&mut out, &format!("{}_sum", name), "", metrics.total_execution_time_ms as f64 / 1000.0);
+    sample(&mut out, &format!("{}_count", name), "", completed);
+
+    family(&mut out, "bloop_agents_active", "gauge", "Agents currently working");
+    sample(&mut out, "bloop_agents_active", "", metrics.active_agents);
+    family(&mut out, "bloop_agent_tasks_active", "gauge", "Tasks currently executing");
+    sample(&mut out, "bloop_agent_tasks_active", "", metrics.active_tasks);
+    family(&mut out, "bloop_agent_queue_size", "gauge", "Tasks waiting in the queue");
+    sample(&mut out, "bloop_agent_queue_size", "", load.queued);
+    family(&mut out, "bloop_agent_queue_capacity", "gauge", "Tasks the queue holds before refusing more");
+    sample(&mut out, "bloop_agent_queue_capacity", "", load.queue_capacity);
+    family(&mut out, "bloop_agent_concurrent_tasks", "gauge", "Backpressure slots in use");
+    sample(&mut out, "bloop_agent_concurrent_tasks", "", load.running);
+    family(&mut out, "bloop_agent_max_concurrent_tasks", "gauge", "Backpressure slots available in total");
+    sample(&mut out, "bloop_agent_max_concurrent_tasks", "", load.max_concurrent);
+
+    family(&mut out, "bloop_agent_circuit_breaker_state", "gauge", "1 for the state the task circuit breaker is in");
+    for (label, state) in [("closed", CircuitState::Closed), ("open", CircuitState::Open), ("half_open", CircuitState::HalfOpen)] {
+        sample(&mut out, "bloop_agent_circuit_breaker_state", &format!("{{state=\"{}\"}}", label), u8::from(circuit == state));
+    }
+    family(&mut out, "bloop_agent_circuit_breaker_trips_total", "counter", "Times the task circuit breaker has opened");
+    sample(&mut out, "bloop_agent_circuit_breaker_trips_total", "", trips);
+
+    out
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "{}{} {}", name, labels, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ai::router::ModelRouter;
+    use crate::types::errors::error_codes;
+
+    fn manager(config: &Config) -> Arc<AgentManager> {
+        let config = Arc::new(config.clone());
+        AgentManager::new(Arc::new(ModelRouter::new(&config)), config, None)
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_rendered_when_enabled() {
+        let mut config = Config::from_env().unwrap();
+        config.metrics_enabled = false;
+        let manager = manager(&config);
+        let err = prometheus_metrics(Extension(config.clone()), Extension(Arc::clone(&manager))).await.unwrap_err();
+        assert_eq!(err.error.code, error_codes::NOT_FOUND);
+
+        let collector = manager.metrics();
+        collector.record_task_started("fast").await;
+        collector.record_task_completed("fast", true, 80, Some(10)).await;
+        collector.record_task_started("slow").await;
+        collector.record_task_completed("slow", false, 4_000, None).await;
+        collector.record_retry().await;
+
+        config.metrics_enabled = true;
+        let response = prometheus_metrics(Extension(config), Extension(manager)).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        for line in [
+            "bloop_agent_tasks_started_total 2",
+            "bloop_agent_tasks_completed_total{outcome=\"success\"} 1",
+            "bloop_agent_tasks_completed_total{outcome=\"failure\"} 1",
+            "bloop_agent_task_retries_total 1",
+            "bloop_agent_task_execution_seconds_bucket{le=\"0.1\"} 1",
+            "bloop_agent_task_execution_seconds_bucket{le=\"2.5\"} 1",
+            "bloop_agent_task_execution_seconds_bucket{le=\"5\"} 2",
+            "bloop_agent_task_execution_seconds_bucket{le=\"+Inf\"} 2",
+            "bloop_agent_task_execution_seconds_sum 4.08",
+            "bloop_agent_queue_capacity 2000",
+            "bloop_agent_circuit_breaker_state{state=\"closed\"} 1",
+            "bloop_agent_circuit_breaker_state{state=\"open\"} 0",
+            "bloop_agent_circuit_breaker_trips_total 0",
+        ] {
+            assert!(body.lines().any(|l| l == line), "missing `{}` in:\n{}", line, body);
+        }
+    }
+}
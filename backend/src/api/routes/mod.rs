@@ -8,6 +8,8 @@ pub mod execute;
 pub mod openclaw;
 pub mod moltbook;
 pub mod health;
+pub mod metrics;
 pub mod company;
 pub mod security;
 pub mod collaboration;
+pub mod admin;
@@ -3,11 +3,15 @@
  * Lists all available models and their capabilities
  */
 use axum::{
-    extract::Extension,
+    extract::{Extension, Query},
     http::StatusCode,
     response::Json,
 };
-use crate::services::ai::router::ModelRouter;
+use crate::services::ai::router::{ModelRouter, RoutingDecision};
+use crate::services::ai::{ProviderHealthStore, ProviderHealthSummary, ShadowEvaluator};
+use crate::services::ai::shadow::ShadowReport;
+use crate::types::AIRequest;
+use crate::types::errors::{ApiError, ApiResult};
 use crate::config::Config;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -67,6 +71,7 @@ pub async fn list_models(
         ("qwen", "Qwen", "qwen-plus"),
         ("zeroone", "ZeroOne", "yi-1.5-34b-chat"),
         ("baidu", "Baidu", "ernie-4.0-8k"),
+        ("ollama", "Ollama", "llama3"),
     ];
     
     for (provider_key, provider_name, default_model) in providers {
@@ -85,10 +90,11 @@ pub async fn list_models(
             "qwen" => crate::types::ModelProvider::Qwen,
             "zeroone" => crate::types::ModelProvider::ZeroOne,
             "baidu" => crate::types::ModelProvider::Baidu,
+            "ollama" => crate::types::ModelProvider::Ollama,
             _ => continue,
         };
         
-        let available = router.get_service(provider_enum.clone()).is_some();
+        let available = router.get_service(provider_enum.clone()).is_some_and(|s| s.is_ready());
         
         if let Some(service) = router.get_service(provider_enum) {
             let caps = service.capabilities();
@@ -140,3 +146,73 @@ pub async fn list_models(
         total_providers,
     }))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ProviderHealthQuery {
+    /// How far back the history goes (default 24, max 720)
+    pub hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderHealthResponse {
+    pub current: Vec<ProviderHealthSummary>,
+    pub history: Vec<ProviderHealthSummary>,
+}
+
+/// Live provider health plus persisted snapshots
+pub async fn provider_health(
+    Extension(router): Extension<Arc<ModelRouter>>,
+    Extension(store): Extension<Arc<ProviderHealthStore>>,
+    Query(query): Query<ProviderHealthQuery>,
+) -> ApiResult<Json<ProviderHealthResponse>> {
+    let hours = query.hours.unwrap_or(24);
+    if !(1..=720).contains(&hours) {
+        return Err(ApiError::validation_error("hours must be between 1 and 720".to_string()).with_field("hours".to_string()));
+    }
+
+    let since = chrono::Utc::now() - chrono::Duration::hours(hours);
+    let history = store.history(since).await.map_err(|e| {
+        tracing::error!("{}", e);
+        ApiError::internal_error("Failed to load provider health history".to_string())
+    })?;
+
+    Ok(Json(ProviderHealthResponse {
+        current: router.health().snapshot(),
+        history,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShadowReportQuery {
+    pub hours: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+/// Primary vs. shadow provider comparisons from shadow routing
+pub async fn shadow_report(
+    Extension(shadow): Extension<Arc<ShadowEvaluator>>,
+    Query(query): Query<ShadowReportQuery>,
+) -> ApiResult<Json<ShadowReport>> {
+    let hours = query.hours.unwrap_or(24);
+    if !(1..=720).contains(&hours) {
+        return Err(ApiError::validation_error("hours must be between 1 and 720".to_string()).with_field("hours".to_string()));
+    }
+
+    let since = chrono::Utc::now() - chrono::Duration::hours(hours);
+    let report = shadow.report(since, query.limit.unwrap_or(50).min(500)).await.map_err(|e| {
+        tracing::error!("{}", e);
+        ApiError::internal_error("Failed to load shadow comparisons".to_string())
+    })?;
+
+    Ok(Json(report))
+}
+
+/// The model a request would be routed to and why, without generating anything
+pub async fn explain_route(
+    Extension(router): Extension<Arc<ModelRouter>>,
+    Json(request): Json<AIRequest>,
+) -> ApiResult<Json<RoutingDecision>> {
+    let decision = router.explain_selection(&request)
+        .map_err(|e| ApiError::external_service_error("model router", e.to_string()))?;
+    Ok(Json(decision))
+}
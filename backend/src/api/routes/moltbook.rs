@@ -13,6 +13,7 @@ use std::sync::Arc;
 use crate::config::Config;
 use crate::database::Database;
 use crate::middleware::security::{sanitize_string, MAX_STRING_LENGTH};
+use crate::services::integrations::{MoltbookSync, NewMoltbookPost};
 
 // Types for Moltbook integration
 
@@ -86,6 +87,9 @@ pub struct MoltbookPost {
     pub submolt: String,
     pub karma: i32,
     pub created_at: String,
+    pub sync_status: String, // "local", "pending_sync" or "synced"
+    pub remote_id: Option<String>, // Moltbook's id once published
+    pub url: Option<String>,
 }
 
 /// Get Moltbook integration status
@@ -231,9 +235,16 @@ pub async fn register_agent(
 }
 
 /// Share code to Moltbook
+///
+/// The post is stored as `local`, then published through the Moltbook API when
+/// Moltbook is enabled. Only a failed publish marks the stored post
+/// `pending_sync`, so the background retry never picks up a post that is
+/// still being published here; without a database there is nothing to retry,
+/// so the failure is returned as 502.
 pub async fn share_code(
     Extension(_config): Extension<Config>,
     Extension(database): Extension<Option<Arc<Database>>>,
+    Extension(moltbook): Extension<Arc<MoltbookSync>>,
     Json(request): Json<ShareCodeRequest>,
 ) -> Result<Json<MoltbookPost>, StatusCode> {
     use chrono::Utc;
@@ -259,8 +270,12 @@ pub async fn share_code(
     };
 
     let post_id = Uuid::new_v4().to_string();
+    let enabled = std::env::var("MOLTBOOK_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
 
     // Save to database if available
+    let mut stored = false;
     if let Some(ref db) = database {
         // Get Bloop's agent ID
         if let Ok(Some(agent)) = sqlx::query_as::<_, crate::database::models::MoltbookAgent>(
@@ -271,9 +286,9 @@ pub async fn share_code(
         {
             let mut tx = db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             
-            let _ = sqlx::query(
-                "INSERT INTO moltbook_posts (post_id, author_id, submolt, title, content, content_type, language)
-                 VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            let inserted = sqlx::query(
+                "INSERT INTO moltbook_posts (post_id, author_id, submolt, title, content, content_type, language, sync_status)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
             )
             .bind(&post_id)
             .bind(agent.id)
@@ -282,22 +297,55 @@ pub async fn share_code(
             .bind(&content)
             .bind("code")
             .bind(&language)
+            .bind("local")
             .execute(&mut *tx)
             .await;
 
             tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            match inserted {
+                Ok(_) => stored = true,
+                Err(e) => tracing::warn!("Failed to store Moltbook post {}: {}", post_id, e),
+            }
         }
     }
 
-    // In production, post to Moltbook API
-    Ok(Json(MoltbookPost {
+    let mut post = MoltbookPost {
         id: post_id,
         title,
         content,
         submolt,
         karma: 0,
         created_at: Utc::now().to_rfc3339(),
-    }))
+        sync_status: "local".to_string(),
+        remote_id: None,
+        url: None,
+    };
+    if !enabled {
+        return Ok(Json(post));
+    }
+
+    let new_post = NewMoltbookPost {
+        title: post.title.clone(),
+        content: post.content.clone(),
+        submolt: post.submolt.clone(),
+        language: Some(language),
+    };
+    match moltbook.publish(&post.id, &new_post).await {
+        Ok(published) => {
+            post.sync_status = "synced".to_string();
+            post.remote_id = Some(published.id);
+            post.url = Some(published.url);
+        }
+        Err(e) if stored => {
+            post.sync_status = "pending_sync".to_string();
+            tracing::warn!("Moltbook post {} saved as pending_sync: {}", post.id, e);
+        }
+        Err(e) => {
+            tracing::error!("Failed to publish Moltbook post: {}", e);
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+    }
+    Ok(Json(post))
 }
 
 /// Get trending skills from Moltbook
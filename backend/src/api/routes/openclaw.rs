@@ -7,15 +7,15 @@ use axum::{
     http::StatusCode,
     response::Json,
 };
-use axum::extract::Request;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 use std::sync::Arc;
 use crate::config::Config;
 use crate::database::Database;
+use crate::middleware::auth::UserId;
+use crate::services::integrations::{OpenClawError, OpenClawMessage, OpenClawWebSocketClient};
 use crate::middleware::security::{sanitize_string, validate_skill_name, MAX_STRING_LENGTH};
 use crate::types::errors::{ApiError, ApiResult, error_codes};
-use crate::middleware::request_id::get_request_id;
 
 // Types for OpenClaw integration
 
@@ -69,6 +69,7 @@ pub struct MessageResponse {
     pub content: String,
     pub timestamp: String,
     pub model: Option<String>,
+    pub session_id: Option<String>, // Send back to continue the conversation
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -100,39 +101,39 @@ pub struct SkillResult {
 }
 
 /// Get OpenClaw Gateway status
+///
+/// `connected` and `uptime` (seconds) reflect the live connection kept by the
+/// reconnecting client.
 pub async fn get_status(
-    Extension(config): Extension<Config>,
+    Extension(_config): Extension<Config>,
+    Extension(openclaw): Extension<Arc<OpenClawWebSocketClient>>,
 ) -> Result<Json<OpenClawStatus>, StatusCode> {
-    // Check if OpenClaw is configured
-    let gateway_url = std::env::var("OPENCLAW_GATEWAY_URL")
-        .unwrap_or_else(|_| "ws://127.0.0.1:18789".to_string());
-    
     let enabled = std::env::var("OPENCLAW_ENABLED")
         .map(|v| v == "true")
         .unwrap_or(false);
 
-    // In a real implementation, we would connect to the Gateway
-    // For now, return configured status
     Ok(Json(OpenClawStatus {
         enabled,
-        connected: false, // Would check actual connection
-        gateway_url,
+        connected: openclaw.is_connected().await,
+        gateway_url: openclaw.gateway_url().to_string(),
         sessions: 0,
         skills: get_bloop_skills().len() as u32,
-        uptime: 0,
+        uptime: openclaw.uptime().await.map(|up| up.as_secs()).unwrap_or(0),
     }))
 }
 
-/// List OpenClaw sessions
+/// List the caller's OpenClaw sessions
 pub async fn list_sessions(
     Extension(_config): Extension<Config>,
+    user: Option<Extension<UserId>>,
     Extension(database): Extension<Option<Arc<Database>>>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     // Try to get from database first
     if let Some(ref db) = database {
         match sqlx::query_as::<_, crate::database::models::OpenClawSession>(
-            "SELECT * FROM openclaw_sessions ORDER BY created_at DESC LIMIT 100"
+            "SELECT * FROM openclaw_sessions WHERE user_id IS NOT DISTINCT FROM $1 ORDER BY created_at DESC LIMIT 100"
         )
+        .bind(user.map(|Extension(UserId(id))| id))
         .fetch_all(db.pool())
         .await
         {
@@ -167,30 +168,76 @@ pub async fn list_sessions(
 }
 
 /// Get session history
+///
+/// Other users' sessions are reported as not found.
 pub async fn get_session_history(
     Extension(_config): Extension<Config>,
+    user: Option<Extension<UserId>>,
+    Extension(database): Extension<Option<Arc<Database>>>,
     Path(session_id): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> ApiResult<Json<serde_json::Value>> {
+    // Sessions are only known through the database
+    let Some(db) = database else {
+        return Err(session_not_found(&session_id));
+    };
+    let session = sqlx::query_as::<_, crate::database::models::OpenClawSession>(
+        "SELECT * FROM openclaw_sessions WHERE session_id = $1 AND user_id IS NOT DISTINCT FROM $2"
+    )
+    .bind(&session_id)
+    .bind(user.map(|Extension(UserId(id))| id))
+    .fetch_optional(db.pool())
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch session {}: {}", session_id, e);
+        ApiError::database_error("Failed to fetch session".to_string())
+    })?;
+    if session.is_none() {
+        return Err(session_not_found(&session_id));
+    }
+
+    let messages = sqlx::query_as::<_, crate::database::models::OpenClawHistoryMessage>(
+        "SELECT * FROM openclaw_messages WHERE session_id = $1 ORDER BY created_at, role DESC"
+    )
+    .bind(&session_id)
+    .fetch_all(db.pool())
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch history for session {}: {}", session_id, e);
+        ApiError::database_error("Failed to fetch session history".to_string())
+    })?;
+
     Ok(Json(serde_json::json!({
         "session_id": session_id,
-        "messages": [],
-        "total": 0
+        "total": messages.len(),
+        "messages": messages,
     })))
 }
 
+fn session_not_found(session_id: &str) -> ApiError {
+    ApiError::not_found("Session").with_details(format!("No OpenClaw session with id {}", session_id))
+}
+
 /// Send message to OpenClaw agent
+///
+/// The sanitized message is forwarded to the gateway and the agent's reply is
+/// returned. With a database, both sides are stored under the gateway's
+/// session id, owned by the caller; continuing another user's session is
+/// reported as not found. While the gateway is disconnected this fails with
+/// 503 rather than queueing the message.
 pub async fn send_message(
     Extension(_config): Extension<Config>,
+    user: Option<Extension<UserId>>,
     Extension(database): Extension<Option<Arc<Database>>>,
-    request: Request,
+    Extension(openclaw): Extension<Arc<OpenClawWebSocketClient>>,
+    request_id: Option<Extension<String>>,
     Json(body): Json<SendMessageRequest>,
 ) -> ApiResult<Json<MessageResponse>> {
     use chrono::Utc;
     use uuid::Uuid;
-    use axum::extract::Request;
 
     // Get request ID for error tracking
-    let request_id = get_request_id(&request)
+    let request_id = request_id
+        .map(|Extension(id)| id)
         .unwrap_or_else(|| Uuid::new_v4().to_string());
 
     // Validate input
@@ -209,37 +256,136 @@ pub async fn send_message(
     }
 
     // Sanitize message
-    let sanitized_message = sanitize_string(&request.message, MAX_STRING_LENGTH);
-    
-    // Log to database if available
-    if let Some(ref db) = database {
-        if let Some(ref session_id) = body.session_id {
-            // Could log message to database here
-            if let Err(e) = db.pool().execute(
-                sqlx::query("UPDATE openclaw_sessions SET updated_at = NOW() WHERE session_id = $1")
-                    .bind(session_id)
-            ).await {
-                tracing::error!("Failed to update session: {}", e);
-                return Err(ApiError::database_error("Failed to update session".to_string())
-                    .with_request_id(request_id));
-            }
+    let sanitized_message = sanitize_string(&body.message, MAX_STRING_LENGTH);
+    let user_id = user.map(|Extension(UserId(id))| id);
+
+    if let (Some(db), Some(session_id)) = (&database, &body.session_id) {
+        let foreign = owned_by_other(db, session_id, user_id).await.map_err(|e| {
+            tracing::error!("Failed to check owner of session {}: {}", session_id, e);
+            ApiError::database_error("Failed to fetch session".to_string()).with_request_id(request_id.clone())
+        })?;
+        if foreign {
+            return Err(session_not_found(session_id).with_request_id(request_id));
+        }
+    }
+
+    let thinking_level = body.thinking_level.clone()
+        .or_else(|| std::env::var("OPENCLAW_DEFAULT_THINKING_LEVEL").ok());
+    let sent_at = Utc::now();
+    let reply = openclaw.send_now(OpenClawMessage {
+        id: Some(Uuid::new_v4().to_string()),
+        channel: "webchat".to_string(),
+        message: sanitized_message.clone(),
+        thinking_level: thinking_level.clone(),
+        model: body.model.clone(),
+        session_id: body.session_id.clone(),
+    }).await.map_err(|e| gateway_error(e).with_request_id(request_id.clone()))?;
+    let replied_at = Utc::now();
+
+    let session_id = reply.session_id.clone().or_else(|| body.session_id.clone());
+    if let (Some(db), Some(session_id)) = (&database, &session_id) {
+        let exchange = Exchange {
+            session_id,
+            user_id,
+            model: body.model.as_deref(),
+            thinking_level: thinking_level.as_deref(),
+            message: &sanitized_message,
+            sent_at,
+            reply: &reply.response,
+            replied_at,
+        };
+        if let Err(e) = record_exchange(db, &exchange).await {
+            // The agent already answered; losing history shouldn't lose the reply
+            tracing::error!("Failed to record OpenClaw exchange for session {}: {}", session_id, e);
         }
     }
 
-    // In production, this would send to the Gateway
-    // For now, return a mock response
     Ok(Json(MessageResponse {
-        id: Uuid::new_v4().to_string(),
+        id: reply.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
         role: "assistant".to_string(),
-        content: format!(
-            "OpenClaw integration is configured. Message received: {}",
-            sanitized_message
-        ),
-        timestamp: Utc::now().to_rfc3339(),
+        content: reply.response,
+        timestamp: replied_at.to_rfc3339(),
         model: body.model,
+        session_id,
     }))
 }
 
+/// Map a gateway failure to the status the caller should see
+fn gateway_error(error: OpenClawError) -> ApiError {
+    match error {
+        OpenClawError::Queued | OpenClawError::QueueFull(_) | OpenClawError::ConnectionLost => {
+            ApiError::service_unavailable("OpenClaw Gateway")
+                .with_details(format!("{}; the connection is retried automatically", error))
+        }
+        OpenClawError::ReplyTimeout(_) => {
+            ApiError::new(error_codes::GATEWAY_TIMEOUT.to_string(), error.to_string())
+        }
+        OpenClawError::Send(message) => ApiError::external_service_error("OpenClaw Gateway", message),
+    }
+}
+
+/// One message and the agent's reply, as stored in the session history
+struct Exchange<'a> {
+    session_id: &'a str,
+    user_id: Option<uuid::Uuid>,
+    model: Option<&'a str>,
+    thinking_level: Option<&'a str>,
+    message: &'a str,
+    sent_at: chrono::DateTime<chrono::Utc>,
+    reply: &'a str,
+    replied_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Whether `session_id` exists and belongs to someone other than `user_id`
+async fn owned_by_other(db: &Database, session_id: &str, user_id: Option<uuid::Uuid>) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM openclaw_sessions WHERE session_id = $1 AND user_id IS DISTINCT FROM $2)"
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_one(db.pool())
+    .await
+}
+
+/// Create or touch the session row and append both messages
+///
+/// Nothing is written when the session belongs to another user.
+async fn record_exchange(db: &Database, exchange: &Exchange<'_>) -> Result<(), sqlx::Error> {
+    let mut tx = db.pool().begin().await?;
+    let upserted = sqlx::query(
+        "INSERT INTO openclaw_sessions (session_id, channel, model, thinking_level, user_id)
+         VALUES ($1, 'webchat', $2, $3, $4)
+         ON CONFLICT (session_id) DO UPDATE SET
+             updated_at = NOW(),
+             status = 'active',
+             model = COALESCE(EXCLUDED.model, openclaw_sessions.model),
+             thinking_level = COALESCE(EXCLUDED.thinking_level, openclaw_sessions.thinking_level)
+         WHERE openclaw_sessions.user_id IS NOT DISTINCT FROM EXCLUDED.user_id"
+    )
+    .bind(exchange.session_id)
+    .bind(exchange.model)
+    .bind(exchange.thinking_level)
+    .bind(exchange.user_id)
+    .execute(&mut *tx)
+    .await?;
+    if upserted.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    sqlx::query(
+        "INSERT INTO openclaw_messages (session_id, role, content, model, created_at)
+         VALUES ($1, 'user', $2, NULL, $3), ($1, 'assistant', $4, $5, $6)"
+    )
+    .bind(exchange.session_id)
+    .bind(exchange.message)
+    .bind(exchange.sent_at)
+    .bind(exchange.reply)
+    .bind(exchange.model)
+    .bind(exchange.replied_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
 /// List available skills
 pub async fn list_skills(
     Extension(_config): Extension<Config>,
@@ -445,3 +591,47 @@ fn get_bloop_skills() -> Vec<OpenClawSkill> {
         },
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_session_history_is_404() {
+        let config = Config::from_env().unwrap();
+
+        let err = get_session_history(Extension(config), None, Extension(None), Path(uuid::Uuid::new_v4().to_string()))
+            .await
+            .unwrap_err();
+        let (status, body) = err.into_test_response().await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+        assert_eq!(body["error"]["message"], "Session not found");
+    }
+
+    #[tokio::test]
+    async fn test_send_message_is_503_while_gateway_is_disconnected() {
+        let config = Config::from_env().unwrap();
+        let openclaw = Arc::new(OpenClawWebSocketClient::new(Arc::new(config.clone())));
+
+        let err = send_message(
+            Extension(config),
+            None,
+            Extension(None),
+            Extension(Arc::clone(&openclaw)),
+            Some(Extension("req-1".to_string())),
+            Json(SendMessageRequest {
+                message: "hello".to_string(),
+                thinking_level: None,
+                model: None,
+                session_id: None,
+            }),
+        ).await.unwrap_err();
+        assert_eq!(err.request_id, "req-1");
+        let (status, body) = err.into_test_response().await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["error"]["code"], "SERVICE_UNAVAILABLE");
+        // Rejected, not queued for later delivery
+        assert_eq!(openclaw.queued_messages().await, 0);
+    }
+}
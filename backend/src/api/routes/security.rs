@@ -9,13 +9,27 @@
  */
 use axum::{
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use crate::security::{AuditLogger, VulnerabilityScanner, ThreatDetector, AuditLog, Vulnerability};
+use crate::security::{
+    AdaptiveRateLimiter, AuditLogger, VulnerabilityScanner, ThreatDetector, AuditLog, Vulnerability,
+    RateLimitStatus, ThreatEvent, AuditEventType, AuditQuery, ThreatLevel,
+};
 use crate::config::Config;
+use crate::middleware::auth::{is_admin, UserId};
+use crate::middleware::client_ip::ClientIp;
+use crate::middleware::rate_limit::rate_limit_key;
+use crate::types::errors::{ApiError, ApiResult};
+
+/// Recent events returned per category by the limits endpoint
+const LIMIT_EVENTS: usize = 20;
+
+/// Page size bounds for the audit log endpoint
+const DEFAULT_AUDIT_LIMIT: usize = 100;
+const MAX_AUDIT_LIMIT: usize = 1000;
 
 #[derive(Debug, Serialize)]
 pub struct SecurityEventsResponse {
@@ -35,6 +49,123 @@ pub struct ScanCodeRequest {
     pub language: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LimitsQuery {
+    pub identifier: Option<String>, // Admin only; defaults to the caller
+}
+
+#[derive(Debug, Serialize)]
+pub struct LimitsResponse {
+    pub identifier: String,
+    pub rate_limit: RateLimitStatus,
+    pub threat_events: Vec<ThreatEvent>,
+    pub violations: Vec<AuditLog>,
+}
+
+/// Why a caller is being throttled or blocked: bucket state and recent events.
+/// Callers see their own data; admins can look up any identifier.
+///
+/// Authenticated callers are rate limited as "user:<id>"; threat events and
+/// violations are still recorded by IP, so those are looked up by the
+/// caller's peer address (forwarding headers count only from trusted proxies).
+pub async fn get_limits(
+    Extension(config): Extension<Config>,
+    Extension(rate_limiter): Extension<Arc<AdaptiveRateLimiter>>,
+    Extension(threat_detector): Extension<Arc<ThreatDetector>>,
+    Extension(audit_logger): Extension<Arc<AuditLogger>>,
+    user: Option<Extension<UserId>>,
+    ip: ClientIp,
+    headers: HeaderMap,
+    Query(query): Query<LimitsQuery>,
+) -> ApiResult<Json<LimitsResponse>> {
+    let caller = rate_limit_key(user.as_ref().map(|Extension(user)| user), ip);
+    let (identifier, source) = match query.identifier {
+        Some(other) if other != caller => {
+            if !is_admin(&headers, &config.admin_api_key) {
+                return Err(ApiError::forbidden()
+                    .with_details("Only admins can view another caller's limits".to_string()));
+            }
+            (other.clone(), other)
+        }
+        _ => (caller, ip.to_string()),
+    };
+
+    Ok(Json(LimitsResponse {
+        rate_limit: rate_limiter.status(&identifier).await,
+        threat_events: threat_detector.get_threats_for_source(&source, LIMIT_EVENTS).await,
+        violations: audit_logger.get_violations_for_ip(&source, LIMIT_EVENTS).await,
+        identifier,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    /// e.g. SecurityViolation, Authentication
+    pub event_type: Option<AuditEventType>,
+    /// Minimum threat level: Low, Medium, High or Critical
+    pub severity: Option<ThreatLevel>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    /// Page size (default 100, max 1000)
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogResponse {
+    pub events: Vec<AuditLog>,
+    pub offset: usize,
+    pub has_more: bool,
+    pub next_offset: Option<usize>,
+}
+
+/// Search the audit log, newest first (admin only).
+/// Covers the full persisted history when a database is configured.
+pub async fn query_audit_logs(
+    headers: HeaderMap,
+    Extension(config): Extension<Config>,
+    Extension(audit_logger): Extension<Arc<AuditLogger>>,
+    Query(query): Query<AuditLogQuery>,
+) -> ApiResult<Json<AuditLogResponse>> {
+    if !is_admin(&headers, &config.admin_api_key) {
+        return Err(ApiError::forbidden());
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_AUDIT_LIMIT);
+    if limit == 0 || limit > MAX_AUDIT_LIMIT {
+        return Err(ApiError::validation_error(format!("limit must be between 1 and {}", MAX_AUDIT_LIMIT))
+            .with_field("limit".to_string()));
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(ApiError::validation_error("from must not be after to".to_string())
+                .with_field("from".to_string()));
+        }
+    }
+
+    // One extra row tells us whether another page exists
+    let mut events = audit_logger.query(&AuditQuery {
+        event_type: query.event_type,
+        min_threat_level: query.severity,
+        from: query.from,
+        to: query.to,
+        limit: limit + 1,
+        offset: query.offset,
+    }).await.map_err(|e| {
+        tracing::error!("{}", e);
+        ApiError::internal_error("Failed to query audit logs".to_string())
+    })?;
+    let has_more = events.len() > limit;
+    events.truncate(limit);
+
+    Ok(Json(AuditLogResponse {
+        next_offset: has_more.then(|| query.offset + events.len()),
+        offset: query.offset,
+        has_more,
+        events,
+    }))
+}
+
 /// Get security events
 pub async fn get_security_events(
     Extension(audit_logger): Extension<Arc<AuditLogger>>,
@@ -77,3 +208,122 @@ pub async fn scan_code(
         vulnerabilities,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Services {
+        config: Config,
+        rate_limiter: Arc<AdaptiveRateLimiter>,
+        threat_detector: Arc<ThreatDetector>,
+        audit_logger: Arc<AuditLogger>,
+    }
+
+    fn services() -> Services {
+        let mut config = Config::from_env().unwrap();
+        config.admin_api_key = "admin-key".to_string();
+        Services {
+            config,
+            rate_limiter: Arc::new(AdaptiveRateLimiter::default()),
+            threat_detector: Arc::new(ThreatDetector::new()),
+            audit_logger: Arc::new(AuditLogger::new(100)),
+        }
+    }
+
+    fn headers(api_key: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = api_key {
+            headers.insert("X-API-Key", key.parse().unwrap());
+        }
+        headers
+    }
+
+    async fn limits(s: &Services, ip: &str, api_key: Option<&str>, identifier: Option<&str>) -> ApiResult<Json<LimitsResponse>> {
+        get_limits(
+            Extension(s.config.clone()),
+            Extension(Arc::clone(&s.rate_limiter)),
+            Extension(Arc::clone(&s.threat_detector)),
+            Extension(Arc::clone(&s.audit_logger)),
+            None,
+            ClientIp(ip.parse().unwrap()),
+            headers(api_key),
+            Query(LimitsQuery { identifier: identifier.map(|i| i.to_string()) }),
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_caller_sees_own_bucket_and_violations() {
+        let s = services();
+        for _ in 0..3 {
+            s.rate_limiter.check("10.0.0.1").await;
+        }
+        s.rate_limiter.check("10.0.0.2").await;
+        s.audit_logger.log_violation("path_traversal".to_string(), Some("10.0.0.1".to_string()), None).await;
+        s.audit_logger.log_violation("path_traversal".to_string(), Some("10.0.0.2".to_string()), None).await;
+
+        let Json(response) = limits(&s, "10.0.0.1", None, None).await.unwrap();
+        assert_eq!(response.identifier, "10.0.0.1");
+        assert_eq!(response.rate_limit.remaining, response.rate_limit.limit - 3);
+        assert!(!response.rate_limit.blocked);
+        assert_eq!(response.violations.len(), 1);
+        assert_eq!(response.violations[0].ip_address.as_deref(), Some("10.0.0.1"));
+
+        // Asking for yourself explicitly is allowed
+        assert!(limits(&s, "10.0.0.1", None, Some("10.0.0.1")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_other_callers_require_admin() {
+        let s = services();
+
+        let err = limits(&s, "10.0.0.1", None, Some("10.0.0.2")).await.unwrap_err();
+        assert_eq!(err.error.code, crate::types::errors::error_codes::FORBIDDEN);
+
+        for wrong in ["wrong", "admin-ke", "admin-keyy"] {
+            let err = limits(&s, "10.0.0.1", Some(wrong), Some("10.0.0.2")).await.unwrap_err();
+            assert_eq!(err.error.code, crate::types::errors::error_codes::FORBIDDEN);
+        }
+
+        let Json(response) = limits(&s, "10.0.0.1", Some("admin-key"), Some("10.0.0.2")).await.unwrap();
+        assert_eq!(response.identifier, "10.0.0.2");
+    }
+
+    #[tokio::test]
+    async fn test_audit_query_is_admin_only_and_paginates() {
+        let s = services();
+        for i in 0..3 {
+            s.audit_logger.log_violation(format!("violation_{}", i), Some("10.0.0.1".to_string()), None).await;
+        }
+        s.audit_logger.log_auth(None, Some("10.0.0.1".to_string()), true).await;
+
+        let query = |limit, offset| AuditLogQuery {
+            event_type: Some(AuditEventType::SecurityViolation),
+            severity: None,
+            from: None,
+            to: None,
+            limit: Some(limit),
+            offset,
+        };
+        let audit = |headers, query| query_audit_logs(
+            headers,
+            Extension(s.config.clone()),
+            Extension(Arc::clone(&s.audit_logger)),
+            Query(query),
+        );
+
+        let err = audit(headers(None), query(2, 0)).await.unwrap_err();
+        assert_eq!(err.error.code, crate::types::errors::error_codes::FORBIDDEN);
+
+        let Json(page) = audit(headers(Some("admin-key")), query(2, 0)).await.unwrap();
+        assert_eq!(page.events.len(), 2);
+        assert!(page.has_more);
+        assert_eq!(page.next_offset, Some(2));
+
+        let Json(page) = audit(headers(Some("admin-key")), query(2, 2)).await.unwrap();
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.events[0].action, "violation_0");
+        assert!(!page.has_more);
+        assert_eq!(page.next_offset, None);
+    }
+}
@@ -2,7 +2,15 @@
  * Configuration management
  */
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use crate::types::{ContextOverflowPolicy, ModelProvider};
+use crate::services::ai::HistoryStrategy;
+use crate::services::ai::router::RoutingWeights;
+use crate::services::agent::AgentSecurityConfig;
+use crate::services::codebase::AnalyzerProfiles;
+use crate::services::agent::{ArtifactLimits, ArtifactLimitTable};
+use crate::services::agent::decomposer::DEFAULT_FAST_PATH_THRESHOLD;
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -22,74 +30,620 @@ pub struct Config {
     pub qwen_api_key: String,
     pub zeroone_api_key: String,
     pub baidu_api_key: String,
-    pub jwt_secret: String,
+    pub api_key_pools: HashMap<ModelProvider, Vec<String>>, // Extra keys from *_API_KEYS, rotated per call
+    pub api_key_cooldown_secs: u64, // How long a key that got a 429 is left out
+    pub ollama_base_url: String, // Local Ollama server; empty = don't register it
+    pub ollama_model: String,
+    pub ollama_models: Vec<String>, // Other local model names routed to Ollama besides `ollama_model`
+    pub ollama_context_length: u32, // Context window the local model was pulled with
+    pub jwt_secret: String, // Verifies HS256/384/512 bearer tokens; empty = HMAC tokens refused
+    pub jwt_jwks_url: Option<String>, // JWKS endpoint for RS/ES/PS-signed bearer tokens
+    pub auth_required: bool, // Reject requests to non-public paths that carry no bearer token
+    pub public_paths: Vec<String>, // Path prefixes reachable without authentication
+    pub admin_api_key: String, // X-API-Key value that unlocks admin views; empty = none
+    pub trusted_proxies: Vec<std::net::IpAddr>, // Peers whose X-Forwarded-* headers are believed
     pub cors_origin: String,
-    pub rate_limit_per_minute: u32,
+    pub rate_limit_per_minute: u32, // Anonymous callers, keyed by IP
+    pub rate_limit_plans: Vec<(String, u32)>, // (plan, requests per minute) for authenticated users
+    pub rate_limit_default_plan: String, // Plan for tokens that don't name a known one
     pub database_url: Option<String>,
     pub redis_url: Option<String>,
     // Security settings
     pub max_request_size: usize,
     pub enable_csrf: bool,
     pub allowed_websocket_origins: Vec<String>,
+    // Response security headers
+    pub content_security_policy: String,
+    pub referrer_policy: String,
+    pub permissions_policy: String,
+    pub hsts_max_age_secs: u64, // 0 disables HSTS; never sent over plain HTTP
+    pub disabled_security_headers: Vec<String>, // e.g. CSP for API-only deployments
+    // Request timeouts
+    pub request_timeout_secs: u64,
+    pub endpoint_timeouts: Vec<(String, u64)>, // (path prefix, seconds)
+    pub timeout_exempt_paths: Vec<String>,
+    // Agent settings
+    pub fast_path_complexity_threshold: f64, // 0.0 disables the fast path
+    pub task_budget_usd: f64, // Estimated spend after which a task is aborted; 0.0 = no cap
+    pub agent_stuck_timeout_secs: u64, // Working agents silent this long with no live task are reset
+    pub shutdown_drain_timeout_secs: u64, // How long shutdown waits for running tasks; 0 = don't wait
+    pub shutdown_connection_timeout_secs: u64, // How long open connections may hold up shutdown after the drain starts
+    pub webhook_secret: String, // HMAC key for task callback signatures; empty = unsigned
+    pub webhook_allow_private_urls: bool, // Let callbacks target localhost and private networks
+    pub webhook_timeout_secs: u64, // Per-attempt timeout for a callback POST
+    pub decomposition_model: String, // Model that plans AI-decomposed tasks; empty = router picks
+    pub agent_artifact_limits: ArtifactLimitTable, // Per-agent-type cap on artifacts kept from a task
+    // Routing settings
+    pub context_overflow_policy: ContextOverflowPolicy, // Default when a request doesn't specify one
+    pub max_provider_fallbacks: usize, // Providers tried after the first one fails; 0 = no fallback
+    pub routing_weights: RoutingWeights, // Factor weights when scoring providers
+    pub default_response_language: String, // Locale for AI prose when a request doesn't set one
+    pub context_pruning_enabled: bool, // Drop attached files unrelated to the symbols a chat query names
+    pub context_pruning_extra_files: usize, // Unrelated files kept anyway, in attachment order
+    pub history_strategy: HistoryStrategy, // How chat history over the threshold is shortened
+    pub history_token_threshold: u32, // Estimated history tokens that trigger compaction; 0 = off
+    pub provider_health_snapshot_secs: u64, // How often provider health is written to the DB; 0 = never
+    pub provider_health_half_life_secs: u64, // Age at which a call counts half toward provider health
+    pub shadow_sample_percent: f64, // Chat requests replayed against an alternate provider; 0.0 = off
+    pub shadow_provider: Option<ModelProvider>, // Provider shadow calls go to; None = cheapest other
+    pub shadow_daily_budget_usd: f64, // Estimated shadow spend allowed per UTC day
+    pub response_cache_ttl_secs: u64, // How long a cached chat answer is served; 0 = off
+    pub response_cache_max_entries: usize, // Cached chat answers kept; 0 = off
+    pub response_cache_sampled: bool, // Also cache requests with temperature > 0
+    // Visual pipeline prompt enhancement
+    pub prompt_enhancement_enabled: bool,
+    pub prompt_enhancement_model: String,
+    pub prompt_enhancement_detailed_words: usize, // Prompts this long are used as-is
+    pub max_concurrent_visual_jobs: usize,
+    pub image_cache_ttl_secs: u64, // Reuse a generated image for the same prompt this long; 0 = off
+    pub image_cache_max_entries: usize, // Generated images remembered; 0 = off
+    pub asset_storage_dir: String, // Optimized and other server-produced assets are written here
+    pub asset_optimize_max_dimension: u32, // Default bounding box (px) for AssetOptimization requests
+    pub asset_optimize_format: String, // webp or jpeg
+    pub asset_optimize_quality: u8, // 1-100
+    pub asset_optimize_max_source_bytes: usize, // Larger source images are refused
+    // Codebase analysis
+    pub pattern_min_confidence: f64, // Default for pattern/review endpoints
+    pub debt_markers: Vec<String>, // Comment markers reported by the debt inventory
+    pub analyzer_profiles: AnalyzerProfiles, // Per-language smell thresholds; built-ins plus overrides
+    pub explain_error_model: String,
+    pub review_model: String, // Preferred code review model; empty = router picks by quality
+    pub context_enrichment_token_budget: u32, // Tokens of cross-file definitions added to reviews; 0 = off
+    pub explain_error_max_frames: usize, // Project frames whose source is sent to the model
+    pub embedding_stale_reembed: bool, // Re-embed edited files at query time instead of ranking them lexically
+    // Collaboration
+    pub edit_snapshot_interval: usize, // Edits per file between full-content snapshots
+    pub workspace_root: String, // Session project paths must resolve inside this directory
+    pub collab_send_buffer: usize, // Messages queued per connection before a slow client is disconnected
+    pub collab_max_delivery_failures: u32, // Consecutive failed deliveries before a participant is dropped; 0 = never
+    pub collab_operation_log_len: usize, // Edits kept per session for replay to reconnecting clients
+    pub collab_presence_away_secs: u64, // Silence before a participant is shown as away
+    pub collab_presence_offline_secs: u64, // Silence before a participant's presence is removed
+    pub collab_messages_per_second: u32, // Sustained messages per connection; 0 = unlimited
+    pub collab_message_burst: u32, // Messages a connection may send at once before throttling
+    // Company scaling
+    pub scaling_min_agents_per_role: usize, // Kept even with no demand
+    pub scaling_max_agents_per_role: usize, // Cost cap per role
+    pub scaling_target_utilization: f64, // Share of each agent's task capacity to plan for, 0.0-1.0
+    pub scaling_up_cooldown_secs: u64, // Minimum time between changes to a role before growing it
+    pub scaling_down_cooldown_secs: u64, // Minimum time between changes to a role before shrinking it
+    pub company_metrics_retention_days: u32, // Metrics history kept for trend charts; 0 = forever
+    // File API
+    pub file_allowed_extensions: Vec<String>, // Lowercase, no dot; defaults to the agent security list
+    pub file_denied_extensions: Vec<String>, // Always refused, even if also allowed
+    pub file_list_max_depth: usize, // Deepest level a recursive listing may descend to
+    pub file_list_max_entries: usize, // Recursive listings stop (and report truncated) after this many entries
+    // Secret redaction before AI provider calls
+    pub redact_secrets: bool,
+    pub restore_redacted_secrets: bool, // Map placeholders back in responses
+    // Secret scanning in code review and security scans
+    pub secret_scan_allowlist: Vec<String>, // Known-safe values (e.g. documented example keys) never reported
+    pub secret_scan_allowed_paths: Vec<String>, // Files whose path contains one of these skip the secret scan
+    pub audit_log_retention_days: u32, // Persisted audit logs kept this long; 0 = forever
+    // Command execution (/api/v1/execute)
+    pub execute_enabled: bool, // Off unless explicitly turned on
+    pub execute_allowed_commands: Vec<String>, // Exact binary names; looked up on PATH
+    pub execute_max_timeout_secs: u64, // Cap on the per-request timeout
+    pub execute_max_output_bytes: usize, // Per stream; the rest is discarded and flagged as truncated
+    // Moltbook
+    pub moltbook_sync_retry_secs: u64, // Retry interval for posts that failed to publish; 0 = no retries
+    pub moltbook_timeout_secs: u64, // Per-request timeout for Moltbook API calls
+    // Monitoring
+    pub metrics_enabled: bool, // Serve agent metrics in Prometheus text format at /metrics
 }
 
 impl Config {
     pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_vars(|key| env::var(key).ok())
+    }
+
+    /// Build a config from `lookup`, which finds a setting by its env variable name
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        let var = |key: &str| lookup(key).ok_or(env::VarError::NotPresent);
         Ok(Config {
-            port: env::var("PORT")
+            port: var("PORT")
                 .unwrap_or_else(|_| "3001".to_string())
                 .parse()?,
-            host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-            openai_api_key: env::var("OPENAI_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            anthropic_api_key: env::var("ANTHROPIC_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            google_gemini_api_key: env::var("GOOGLE_GEMINI_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            moonshot_api_key: env::var("MOONSHOT_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            deepseek_api_key: env::var("DEEPSEEK_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            mistral_api_key: env::var("MISTRAL_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            cohere_api_key: env::var("COHERE_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            perplexity_api_key: env::var("PERPLEXITY_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            xai_api_key: env::var("XAI_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            together_api_key: env::var("TOGETHER_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            anyscale_api_key: env::var("ANYSCALE_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            qwen_api_key: env::var("QWEN_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            zeroone_api_key: env::var("ZEROONE_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            baidu_api_key: env::var("BAIDU_API_KEY")
-                .unwrap_or_else(|_| String::new()),
-            jwt_secret: env::var("JWT_SECRET")
+            host: var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
+            openai_api_key: primary_api_key(&var, "OPENAI"),
+            anthropic_api_key: primary_api_key(&var, "ANTHROPIC"),
+            google_gemini_api_key: primary_api_key(&var, "GOOGLE_GEMINI"),
+            moonshot_api_key: primary_api_key(&var, "MOONSHOT"),
+            deepseek_api_key: primary_api_key(&var, "DEEPSEEK"),
+            mistral_api_key: primary_api_key(&var, "MISTRAL"),
+            cohere_api_key: primary_api_key(&var, "COHERE"),
+            perplexity_api_key: primary_api_key(&var, "PERPLEXITY"),
+            xai_api_key: primary_api_key(&var, "XAI"),
+            together_api_key: primary_api_key(&var, "TOGETHER"),
+            anyscale_api_key: primary_api_key(&var, "ANYSCALE"),
+            qwen_api_key: primary_api_key(&var, "QWEN"),
+            zeroone_api_key: primary_api_key(&var, "ZEROONE"),
+            baidu_api_key: primary_api_key(&var, "BAIDU"),
+            api_key_pools: KEYED_PROVIDERS.iter()
+                .map(|(provider, name)| (provider.clone(), api_key_list(&var, name)))
+                .filter(|(_, keys)| !keys.is_empty())
+                .collect(),
+            api_key_cooldown_secs: var("API_KEY_COOLDOWN_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            ollama_base_url: var("OLLAMA_BASE_URL").unwrap_or_default(),
+            ollama_model: var("OLLAMA_MODEL")
+                .unwrap_or_else(|_| "llama3".to_string()),
+            ollama_models: var("OLLAMA_MODELS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            ollama_context_length: var("OLLAMA_CONTEXT_LENGTH")
+                .unwrap_or_else(|_| "8192".to_string())
+                .parse()
+                .unwrap_or(8192),
+            jwt_secret: var("JWT_SECRET")
                 .unwrap_or_else(|_| "change-me-in-production".to_string()),
-            cors_origin: env::var("CORS_ORIGIN")
+            jwt_jwks_url: var("JWT_JWKS_URL").ok().filter(|url| !url.is_empty()),
+            auth_required: var("AUTH_REQUIRED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            public_paths: var("PUBLIC_PATHS")
+                .unwrap_or_else(|_| "/health,/api/v1/auth/".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            admin_api_key: var("ADMIN_API_KEY").unwrap_or_default(),
+            trusted_proxies: var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|s| s.trim().parse().ok())
+                .collect(),
+            cors_origin: var("CORS_ORIGIN")
                 .unwrap_or_else(|_| "http://localhost:5173".to_string()),
-            rate_limit_per_minute: env::var("RATE_LIMIT_PER_MINUTE")
+            rate_limit_per_minute: var("RATE_LIMIT_PER_MINUTE")
                 .unwrap_or_else(|_| "100".to_string())
                 .parse()
                 .unwrap_or(100),
-            database_url: env::var("DATABASE_URL").ok(),
-            redis_url: env::var("REDIS_URL").ok(),
-            max_request_size: env::var("MAX_REQUEST_SIZE")
+            rate_limit_plans: var("RATE_LIMIT_PLANS")
+                .unwrap_or_else(|_| "free=60,pro=600".to_string())
+                .split(',')
+                .filter_map(|entry| {
+                    let (plan, limit) = entry.trim().split_once('=')?;
+                    Some((plan.trim().to_string(), limit.trim().parse().ok()?))
+                })
+                .collect(),
+            rate_limit_default_plan: var("RATE_LIMIT_DEFAULT_PLAN")
+                .unwrap_or_else(|_| "free".to_string()),
+            database_url: var("DATABASE_URL").ok(),
+            redis_url: var("REDIS_URL").ok(),
+            max_request_size: var("MAX_REQUEST_SIZE")
                 .unwrap_or_else(|_| "10485760".to_string()) // 10MB default
                 .parse()
                 .unwrap_or(10 * 1024 * 1024),
-            enable_csrf: env::var("ENABLE_CSRF")
+            enable_csrf: var("ENABLE_CSRF")
                 .map(|v| v == "true")
                 .unwrap_or(false),
-            allowed_websocket_origins: env::var("ALLOWED_WS_ORIGINS")
+            allowed_websocket_origins: var("ALLOWED_WS_ORIGINS")
                 .unwrap_or_else(|_| "http://localhost:5173,ws://localhost:5173".to_string())
                 .split(',')
                 .map(|s| s.trim().to_string())
                 .collect(),
+            content_security_policy: var("CONTENT_SECURITY_POLICY")
+                .unwrap_or_else(|_| "default-src 'self'; script-src 'self' 'unsafe-inline'; style-src 'self' 'unsafe-inline'".to_string()),
+            referrer_policy: var("REFERRER_POLICY")
+                .unwrap_or_else(|_| "strict-origin-when-cross-origin".to_string()),
+            permissions_policy: var("PERMISSIONS_POLICY")
+                .unwrap_or_else(|_| "camera=(), microphone=(), geolocation=()".to_string()),
+            hsts_max_age_secs: var("HSTS_MAX_AGE_SECS")
+                .unwrap_or_else(|_| "31536000".to_string())
+                .parse()
+                .unwrap_or(31536000),
+            disabled_security_headers: var("DISABLED_SECURITY_HEADERS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            request_timeout_secs: var("REQUEST_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            // Format: "/prefix=seconds,/other=seconds"
+            endpoint_timeouts: var("ENDPOINT_TIMEOUTS")
+                .unwrap_or_else(|_| "/api/v1/chat=120,/api/v1/codebase=180,/health=5,/api/v1/files=10".to_string())
+                .split(',')
+                .filter_map(|entry| {
+                    let (prefix, secs) = entry.trim().split_once('=')?;
+                    Some((prefix.trim().to_string(), secs.trim().parse().ok()?))
+                })
+                .collect(),
+            timeout_exempt_paths: var("TIMEOUT_EXEMPT_PATHS")
+                .unwrap_or_else(|_| "/api/v1/collaboration/ws,/api/v1/company/activity/ws".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            fast_path_complexity_threshold: var("FAST_PATH_COMPLEXITY_THRESHOLD")
+                .unwrap_or_else(|_| DEFAULT_FAST_PATH_THRESHOLD.to_string())
+                .parse()
+                .unwrap_or(DEFAULT_FAST_PATH_THRESHOLD),
+            task_budget_usd: var("TASK_BUDGET_USD")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0.0),
+            agent_stuck_timeout_secs: var("AGENT_STUCK_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            shutdown_drain_timeout_secs: var("SHUTDOWN_DRAIN_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            shutdown_connection_timeout_secs: var("SHUTDOWN_CONNECTION_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "45".to_string())
+                .parse()
+                .unwrap_or(45),
+            webhook_secret: var("WEBHOOK_SECRET").unwrap_or_default(),
+            webhook_allow_private_urls: var("WEBHOOK_ALLOW_PRIVATE_URLS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            webhook_timeout_secs: var("WEBHOOK_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            decomposition_model: var("DECOMPOSITION_MODEL").unwrap_or_default(),
+            agent_artifact_limits: ArtifactLimitTable::parse(
+                ArtifactLimits {
+                    max_count: var("AGENT_MAX_ARTIFACTS")
+                        .unwrap_or_else(|_| "20".to_string())
+                        .parse()
+                        .unwrap_or(20),
+                    max_bytes: var("AGENT_MAX_ARTIFACT_BYTES")
+                        .unwrap_or_else(|_| "1048576".to_string())
+                        .parse()
+                        .unwrap_or(1_048_576),
+                },
+                &var("AGENT_ARTIFACT_LIMITS").unwrap_or_default(),
+            )?,
+            context_overflow_policy: var("CONTEXT_OVERFLOW_POLICY")
+                .ok()
+                .and_then(|v| ContextOverflowPolicy::parse(&v))
+                .unwrap_or(ContextOverflowPolicy::Truncate),
+            max_provider_fallbacks: var("MAX_PROVIDER_FALLBACKS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            routing_weights: RoutingWeights {
+                context: var("ROUTING_WEIGHT_CONTEXT")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .unwrap_or(10.0),
+                vision: var("ROUTING_WEIGHT_VISION")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5.0),
+                speed: var("ROUTING_WEIGHT_SPEED")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5.0),
+                quality: var("ROUTING_WEIGHT_QUALITY")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5.0),
+                cost: var("ROUTING_WEIGHT_COST")
+                    .unwrap_or_else(|_| "2".to_string())
+                    .parse()
+                    .unwrap_or(2.0),
+            },
+            default_response_language: var("DEFAULT_RESPONSE_LANGUAGE")
+                .unwrap_or_else(|_| "en".to_string()),
+            context_pruning_enabled: var("CONTEXT_PRUNING_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            context_pruning_extra_files: var("CONTEXT_PRUNING_EXTRA_FILES")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .unwrap_or(2),
+            history_strategy: var("HISTORY_STRATEGY")
+                .ok()
+                .and_then(|v| HistoryStrategy::parse(&v))
+                .unwrap_or(HistoryStrategy::Truncate),
+            history_token_threshold: var("HISTORY_TOKEN_THRESHOLD")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            provider_health_snapshot_secs: var("PROVIDER_HEALTH_SNAPSHOT_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            provider_health_half_life_secs: var("PROVIDER_HEALTH_HALF_LIFE_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            shadow_sample_percent: var("SHADOW_SAMPLE_PERCENT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0.0),
+            shadow_provider: match var("SHADOW_PROVIDER").unwrap_or_default().trim() {
+                "" => None,
+                name => Some(serde_json::from_value(serde_json::json!(name.to_lowercase()))
+                    .map_err(|_| anyhow::anyhow!("SHADOW_PROVIDER '{}' is not a known provider", name))?),
+            },
+            shadow_daily_budget_usd: var("SHADOW_DAILY_BUDGET_USD")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .unwrap_or(1.0),
+            response_cache_ttl_secs: var("RESPONSE_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            response_cache_max_entries: var("RESPONSE_CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .unwrap_or(1000),
+            response_cache_sampled: var("RESPONSE_CACHE_SAMPLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            prompt_enhancement_enabled: var("PROMPT_ENHANCEMENT_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            prompt_enhancement_model: var("PROMPT_ENHANCEMENT_MODEL")
+                .unwrap_or_else(|_| "gpt-4-turbo-preview".to_string()),
+            prompt_enhancement_detailed_words: var("PROMPT_ENHANCEMENT_DETAILED_WORDS")
+                .unwrap_or_else(|_| "40".to_string())
+                .parse()
+                .unwrap_or(40),
+            max_concurrent_visual_jobs: var("MAX_CONCURRENT_VISUAL_JOBS")
+                .unwrap_or_else(|_| "4".to_string())
+                .parse()
+                .unwrap_or(4),
+            image_cache_ttl_secs: var("IMAGE_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "3000".to_string())
+                .parse()
+                .unwrap_or(3000),
+            image_cache_max_entries: var("IMAGE_CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "500".to_string())
+                .parse()
+                .unwrap_or(500),
+            asset_storage_dir: var("ASSET_STORAGE_DIR")
+                .unwrap_or_else(|_| "./data/assets".to_string()),
+            asset_optimize_max_dimension: var("ASSET_OPTIMIZE_MAX_DIMENSION")
+                .unwrap_or_else(|_| "1920".to_string())
+                .parse()
+                .unwrap_or(1920),
+            asset_optimize_format: var("ASSET_OPTIMIZE_FORMAT")
+                .unwrap_or_else(|_| "webp".to_string()),
+            asset_optimize_quality: var("ASSET_OPTIMIZE_QUALITY")
+                .unwrap_or_else(|_| "80".to_string())
+                .parse()
+                .unwrap_or(80),
+            asset_optimize_max_source_bytes: var("ASSET_OPTIMIZE_MAX_SOURCE_BYTES")
+                .unwrap_or_else(|_| "20971520".to_string())
+                .parse()
+                .unwrap_or(20 * 1024 * 1024),
+            pattern_min_confidence: var("PATTERN_MIN_CONFIDENCE")
+                .unwrap_or_else(|_| "0.5".to_string())
+                .parse()
+                .unwrap_or(0.5),
+            analyzer_profiles: AnalyzerProfiles::parse(&var("ANALYZER_PROFILES").unwrap_or_default())?,
+            debt_markers: var("DEBT_MARKERS")
+                .unwrap_or_else(|_| "TODO,FIXME,HACK,XXX".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            explain_error_model: var("EXPLAIN_ERROR_MODEL")
+                .unwrap_or_else(|_| "deepseek-reasoner".to_string()),
+            review_model: var("REVIEW_MODEL").unwrap_or_default(),
+            context_enrichment_token_budget: var("CONTEXT_ENRICHMENT_TOKEN_BUDGET")
+                .unwrap_or_else(|_| "2000".to_string())
+                .parse()
+                .unwrap_or(2000),
+            explain_error_max_frames: var("EXPLAIN_ERROR_MAX_FRAMES")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            embedding_stale_reembed: var("EMBEDDING_STALE_REEMBED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            edit_snapshot_interval: var("EDIT_SNAPSHOT_INTERVAL")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .unwrap_or(200),
+            workspace_root: var("WORKSPACE_ROOT")
+                .unwrap_or_else(|_| ".".to_string()),
+            collab_send_buffer: var("COLLAB_SEND_BUFFER")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .unwrap_or(1000),
+            collab_max_delivery_failures: var("COLLAB_MAX_DELIVERY_FAILURES")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            collab_operation_log_len: var("COLLAB_OPERATION_LOG_LEN")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .unwrap_or(1000),
+            collab_presence_away_secs: var("COLLAB_PRESENCE_AWAY_SECS")
+                .unwrap_or_else(|_| "120".to_string())
+                .parse()
+                .unwrap_or(120),
+            collab_presence_offline_secs: var("COLLAB_PRESENCE_OFFLINE_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            collab_messages_per_second: var("COLLAB_MESSAGES_PER_SECOND")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            collab_message_burst: var("COLLAB_MESSAGE_BURST")
+                .unwrap_or_else(|_| "40".to_string())
+                .parse()
+                .unwrap_or(40),
+            scaling_min_agents_per_role: var("SCALING_MIN_AGENTS_PER_ROLE")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .unwrap_or(1),
+            scaling_max_agents_per_role: var("SCALING_MAX_AGENTS_PER_ROLE")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            scaling_target_utilization: var("SCALING_TARGET_UTILIZATION")
+                .unwrap_or_else(|_| "0.8".to_string())
+                .parse()
+                .unwrap_or(0.8),
+            scaling_up_cooldown_secs: var("SCALING_UP_COOLDOWN_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            scaling_down_cooldown_secs: var("SCALING_DOWN_COOLDOWN_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            company_metrics_retention_days: var("COMPANY_METRICS_RETENTION_DAYS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .unwrap_or(90),
+            file_allowed_extensions: Some(extension_list(&var("FILE_ALLOWED_EXTENSIONS").unwrap_or_default()))
+                .filter(|list| !list.is_empty())
+                .unwrap_or_else(|| AgentSecurityConfig::default().allowed_file_extensions),
+            file_denied_extensions: extension_list(
+                &var("FILE_DENIED_EXTENSIONS")
+                    .unwrap_or_else(|_| "exe,dll,so,dylib,bin,sh,bat,cmd,ps1".to_string()),
+            ),
+            file_list_max_depth: var("FILE_LIST_MAX_DEPTH")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()
+                .unwrap_or(8),
+            file_list_max_entries: var("FILE_LIST_MAX_ENTRIES")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse()
+                .unwrap_or(5000),
+            redact_secrets: var("REDACT_SECRETS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            restore_redacted_secrets: var("RESTORE_REDACTED_SECRETS")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            secret_scan_allowlist: var("SECRET_SCAN_ALLOWLIST")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            secret_scan_allowed_paths: var("SECRET_SCAN_ALLOWED_PATHS")
+                .unwrap_or_else(|_| "fixtures/,testdata/".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            audit_log_retention_days: var("AUDIT_LOG_RETENTION_DAYS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .unwrap_or(90),
+            execute_enabled: var("EXECUTE_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            execute_allowed_commands: var("EXECUTE_ALLOWED_COMMANDS")
+                .unwrap_or_else(|_| "ls,pwd,cat,echo,grep".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            execute_max_timeout_secs: var("EXECUTE_MAX_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            execute_max_output_bytes: var("EXECUTE_MAX_OUTPUT_BYTES")
+                .unwrap_or_else(|_| "1048576".to_string())
+                .parse()
+                .unwrap_or(1048576),
+            moltbook_sync_retry_secs: var("MOLTBOOK_SYNC_RETRY_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            moltbook_timeout_secs: var("MOLTBOOK_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            metrics_enabled: var("METRICS_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
         })
     }
 }
+
+/// Providers that take API keys, with the prefix of their `*_API_KEY(S)` variables
+const KEYED_PROVIDERS: [(ModelProvider, &str); 14] = [
+    (ModelProvider::OpenAI, "OPENAI"),
+    (ModelProvider::Anthropic, "ANTHROPIC"),
+    (ModelProvider::Google, "GOOGLE_GEMINI"),
+    (ModelProvider::Moonshot, "MOONSHOT"),
+    (ModelProvider::DeepSeek, "DEEPSEEK"),
+    (ModelProvider::Mistral, "MISTRAL"),
+    (ModelProvider::Cohere, "COHERE"),
+    (ModelProvider::Perplexity, "PERPLEXITY"),
+    (ModelProvider::XAI, "XAI"),
+    (ModelProvider::Together, "TOGETHER"),
+    (ModelProvider::Anyscale, "ANYSCALE"),
+    (ModelProvider::Qwen, "QWEN"),
+    (ModelProvider::ZeroOne, "ZEROONE"),
+    (ModelProvider::Baidu, "BAIDU"),
+];
+
+/// Keys listed in `{name}_API_KEYS`, comma-separated
+fn api_key_list(var: &impl Fn(&str) -> Result<String, env::VarError>, name: &str) -> Vec<String> {
+    var(&format!("{}_API_KEYS", name))
+        .unwrap_or_default()
+        .split(',')
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect()
+}
+
+/// `{name}_API_KEY`, or the first of `{name}_API_KEYS` when only the list is set
+fn primary_api_key(var: &impl Fn(&str) -> Result<String, env::VarError>, name: &str) -> String {
+    var(&format!("{}_API_KEY", name))
+        .ok()
+        .filter(|key| !key.is_empty())
+        .or_else(|| api_key_list(var, name).into_iter().next())
+        .unwrap_or_default()
+}
+
+/// Comma-separated extensions, lowercased and without leading dots
+fn extension_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|s| s.trim().trim_start_matches('.').to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
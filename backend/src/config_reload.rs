@@ -0,0 +1,228 @@
+/**
+ * Configuration hot reload
+ * Applies a subset of settings at runtime (SIGHUP or the admin endpoint) without a restart
+ */
+use arc_swap::ArcSwap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use crate::config::Config;
+use crate::config_validation::validate_config;
+use crate::security::AdaptiveRateLimiter;
+use crate::services::agent::AgentManager;
+use crate::services::company::CompanyOrchestrator;
+
+/// Settings picked up by a reload; anything else still needs a restart
+pub const HOT_RELOADABLE_SETTINGS: &[&str] = &[
+    "RATE_LIMIT_PER_MINUTE",
+    "FAST_PATH_COMPLEXITY_THRESHOLD",
+    "PATTERN_MIN_CONFIDENCE",
+    "MAX_CONCURRENT_VISUAL_JOBS",
+];
+
+/// The current configuration, swapped atomically on reload
+pub struct LiveConfig {
+    current: ArcSwap<Config>,
+    reload_lock: Mutex<()>,
+}
+
+impl LiveConfig {
+    pub fn new(config: Config) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(config),
+            reload_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn load(&self) -> Arc<Config> {
+        self.current.load_full()
+    }
+
+    /// Validate `candidate` and take its hot-reloadable settings
+    ///
+    /// On a validation error nothing is swapped and the current config stays in place.
+    /// Returns the env names of the settings that changed.
+    pub fn reload(&self, candidate: &Config) -> anyhow::Result<Vec<&'static str>> {
+        validate_config(candidate)?;
+
+        let _guard = self.reload_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut next = (*self.load()).clone();
+        let mut changed = Vec::new();
+
+        macro_rules! take {
+            ($field:ident, $name:literal) => {
+                if next.$field != candidate.$field {
+                    next.$field = candidate.$field;
+                    changed.push($name);
+                }
+            };
+        }
+        take!(rate_limit_per_minute, "RATE_LIMIT_PER_MINUTE");
+        take!(fast_path_complexity_threshold, "FAST_PATH_COMPLEXITY_THRESHOLD");
+        take!(pattern_min_confidence, "PATTERN_MIN_CONFIDENCE");
+        take!(max_concurrent_visual_jobs, "MAX_CONCURRENT_VISUAL_JOBS");
+
+        if !changed.is_empty() {
+            self.current.store(Arc::new(next));
+        }
+        Ok(changed)
+    }
+}
+
+/// Re-reads the environment and pushes new values into services that cache them
+pub struct ConfigReloader {
+    live_config: Arc<LiveConfig>,
+    agent_manager: Arc<AgentManager>,
+    company_orchestrator: Arc<CompanyOrchestrator>,
+    rate_limiter: Arc<AdaptiveRateLimiter>,
+    process_env: HashSet<String>, // Variables set before `.env` was loaded at startup
+}
+
+impl ConfigReloader {
+    pub fn new(
+        live_config: Arc<LiveConfig>,
+        agent_manager: Arc<AgentManager>,
+        company_orchestrator: Arc<CompanyOrchestrator>,
+        rate_limiter: Arc<AdaptiveRateLimiter>,
+        process_env: HashSet<String>,
+    ) -> Self {
+        Self {
+            live_config,
+            agent_manager,
+            company_orchestrator,
+            rate_limiter,
+            process_env,
+        }
+    }
+
+    /// Reload from `.env` and the process environment
+    ///
+    /// Same precedence as startup: variables the process was started with win,
+    /// `.env` supplies the rest. `.env` is read into a map rather than loaded, since
+    /// changing the environment while the runtime's threads may read it is unsound.
+    pub async fn reload_from_env(&self) -> anyhow::Result<Vec<&'static str>> {
+        let dotenv_vars: HashMap<String, String> = dotenv::dotenv_iter()
+            .map(|vars| vars.flatten().collect())
+            .unwrap_or_default();
+
+        let candidate = Config::from_vars(layered(&self.process_env, &dotenv_vars))?;
+        let changed = self.live_config.reload(&candidate)?;
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+
+        let config = self.live_config.load();
+        self.agent_manager.set_fast_path_threshold(config.fast_path_complexity_threshold);
+        self.company_orchestrator.visual_engine().set_max_concurrent(config.max_concurrent_visual_jobs);
+        self.rate_limiter.set_limit(config.rate_limit_per_minute).await;
+
+        tracing::info!("Configuration reloaded: {}", changed.join(", "));
+        Ok(changed)
+    }
+}
+
+/// Look a setting up as startup did: variables in `process_env` from the
+/// environment, others from `dotenv_vars` first
+///
+/// A variable since removed from `.env` keeps the value loaded at startup.
+fn layered<'a>(
+    process_env: &'a HashSet<String>,
+    dotenv_vars: &'a HashMap<String, String>,
+) -> impl Fn(&str) -> Option<String> + 'a {
+    move |key| {
+        let from_dotenv = (!process_env.contains(key)).then(|| dotenv_vars.get(key)).flatten();
+        from_dotenv.cloned().or_else(|| std::env::var(key).ok())
+    }
+}
+
+/// Reload the configuration whenever the process receives SIGHUP
+#[cfg(unix)]
+pub fn spawn_sighup_listener(reloader: Arc<ConfigReloader>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!("Config reload on SIGHUP unavailable: {}", e);
+                return;
+            }
+        };
+
+        while hangups.recv().await.is_some() {
+            if let Err(e) = reloader.reload_from_env().await {
+                tracing::error!("Config reload rejected, keeping current settings: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_listener(_reloader: Arc<ConfigReloader>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Extension, Json};
+    use crate::api::routes::codebase::{detect_patterns, DetectPatternsRequest};
+
+    fn pattern_request() -> DetectPatternsRequest {
+        DetectPatternsRequest {
+            code: "fn main() {}".to_string(),
+            language: "rust".to_string(),
+            min_confidence: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handlers_observe_reloaded_threshold() {
+        let config = Config::from_env().unwrap();
+        let live = Arc::new(LiveConfig::new(config.clone()));
+
+        let mut candidate = config.clone();
+        candidate.pattern_min_confidence = 0.8;
+        candidate.port = config.port.wrapping_add(1).max(1);
+        assert_eq!(live.reload(&candidate).unwrap(), vec!["PATTERN_MIN_CONFIDENCE"]);
+
+        let Json(response) = detect_patterns(Extension(Arc::clone(&live)), Json(pattern_request()))
+            .await
+            .unwrap();
+        assert_eq!(response.min_confidence, 0.8);
+        // Settings outside the hot-reloadable set are left alone
+        assert_eq!(live.load().port, config.port);
+    }
+
+    #[test]
+    fn test_startup_environment_takes_precedence_over_dotenv() {
+        let path = std::env::var("PATH").unwrap();
+        let process_env = HashSet::from(["PATH".to_string()]);
+        let dotenv_vars = HashMap::from([
+            ("PATH".to_string(), "/from/dotenv".to_string()),
+            ("BLOOP_RELOAD_TEST_ONLY_IN_DOTENV".to_string(), "dotenv".to_string()),
+        ]);
+        let lookup = layered(&process_env, &dotenv_vars);
+
+        assert_eq!(lookup("PATH"), Some(path));
+        assert_eq!(lookup("BLOOP_RELOAD_TEST_ONLY_IN_DOTENV").as_deref(), Some("dotenv"));
+        assert_eq!(lookup("BLOOP_RELOAD_TEST_UNSET"), None);
+
+        // Edits to .env win over what startup loaded from it
+        let dotenv_vars = HashMap::from([("PATH".to_string(), "/edited".to_string())]);
+        assert_eq!(layered(&HashSet::new(), &dotenv_vars)("PATH").as_deref(), Some("/edited"));
+
+        let config = Config::from_vars(|key| {
+            (key == "PATTERN_MIN_CONFIDENCE").then(|| "0.7".to_string())
+        }).unwrap();
+        assert_eq!(config.pattern_min_confidence, 0.7);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_reload_keeps_current_config() {
+        let config = Config::from_env().unwrap();
+        let live = LiveConfig::new(config.clone());
+
+        let mut candidate = config.clone();
+        candidate.pattern_min_confidence = 1.5;
+        assert!(live.reload(&candidate).is_err());
+        assert_eq!(live.load().pattern_min_confidence, config.pattern_min_confidence);
+    }
+}
@@ -11,9 +11,19 @@ pub fn validate_config(config: &Config) -> Result<()> {
         anyhow::bail!("Invalid port number: {}", config.port);
     }
 
-    // Validate JWT secret in production
-    if config.jwt_secret == "change-me-in-production" {
-        tracing::warn!("Using default JWT secret. Change JWT_SECRET in production!");
+    // A guessable JWT secret would let anyone mint tokens, so JwtAuth refuses HMAC tokens outright
+    if crate::middleware::auth::is_weak_jwt_secret(&config.jwt_secret) {
+        tracing::warn!(
+            "JWT_SECRET is unset, a placeholder or shorter than 32 bytes; HMAC-signed tokens will be rejected. \
+             Set it to a random value (openssl rand -hex 64)."
+        );
+        if config.auth_required && config.jwt_jwks_url.is_none() {
+            tracing::warn!("No usable JWT_SECRET or JWT_JWKS_URL: only ADMIN_API_KEY can authenticate requests");
+        }
+    }
+
+    if !config.admin_api_key.is_empty() && config.admin_api_key.len() < 32 {
+        tracing::warn!("ADMIN_API_KEY is shorter than 32 characters. Use a longer random key.");
     }
 
     // Validate database URL format if provided
@@ -44,6 +54,100 @@ pub fn validate_config(config: &Config) -> Result<()> {
         tracing::warn!("MAX_REQUEST_SIZE is very large ({}MB). Consider reducing it.", config.max_request_size / 1024 / 1024);
     }
 
+    // Header values must be sendable as-is
+    for (name, value) in [
+        ("CONTENT_SECURITY_POLICY", &config.content_security_policy),
+        ("REFERRER_POLICY", &config.referrer_policy),
+        ("PERMISSIONS_POLICY", &config.permissions_policy),
+    ] {
+        if axum::http::HeaderValue::from_str(value).is_err() {
+            anyhow::bail!("{} contains characters that aren't valid in a header value", name);
+        }
+    }
+
+    // Validate request timeouts
+    if config.request_timeout_secs == 0 {
+        anyhow::bail!("REQUEST_TIMEOUT_SECS must be greater than 0");
+    }
+
+    if let Some((prefix, _)) = config.endpoint_timeouts.iter().find(|(_, secs)| *secs == 0) {
+        anyhow::bail!("ENDPOINT_TIMEOUTS entry for {} must be greater than 0", prefix);
+    }
+
+    if config.rate_limit_per_minute == 0 {
+        anyhow::bail!("RATE_LIMIT_PER_MINUTE must be greater than 0");
+    }
+
+    // Validate agent fast path threshold
+    if !(0.0..=1.0).contains(&config.fast_path_complexity_threshold) {
+        anyhow::bail!("FAST_PATH_COMPLEXITY_THRESHOLD must be between 0.0 and 1.0");
+    }
+
+    if crate::services::ai::localization::normalize_response_language(&config.default_response_language).is_none() {
+        anyhow::bail!("DEFAULT_RESPONSE_LANGUAGE '{}' is not a supported locale", config.default_response_language);
+    }
+
+    if config.max_concurrent_visual_jobs == 0 {
+        anyhow::bail!("MAX_CONCURRENT_VISUAL_JOBS must be at least 1");
+    }
+
+    if !(0.0..=1.0).contains(&config.pattern_min_confidence) {
+        anyhow::bail!("PATTERN_MIN_CONFIDENCE must be between 0.0 and 1.0");
+    }
+
+    if config.explain_error_max_frames == 0 {
+        anyhow::bail!("EXPLAIN_ERROR_MAX_FRAMES must be at least 1");
+    }
+
+    if config.edit_snapshot_interval == 0 {
+        anyhow::bail!("EDIT_SNAPSHOT_INTERVAL must be at least 1");
+    }
+
+    if !config.task_budget_usd.is_finite() || config.task_budget_usd < 0.0 {
+        anyhow::bail!("TASK_BUDGET_USD must be 0 (no cap) or a positive amount");
+    }
+
+    let weights = &config.routing_weights;
+    for (name, weight) in [
+        ("ROUTING_WEIGHT_CONTEXT", weights.context),
+        ("ROUTING_WEIGHT_VISION", weights.vision),
+        ("ROUTING_WEIGHT_SPEED", weights.speed),
+        ("ROUTING_WEIGHT_QUALITY", weights.quality),
+        ("ROUTING_WEIGHT_COST", weights.cost),
+    ] {
+        if !weight.is_finite() || weight < 0.0 {
+            anyhow::bail!("{} must be 0 or a positive number", name);
+        }
+    }
+
+    if !(0.0..=100.0).contains(&config.shadow_sample_percent) {
+        anyhow::bail!("SHADOW_SAMPLE_PERCENT must be between 0 and 100");
+    }
+
+    if !config.shadow_daily_budget_usd.is_finite() || config.shadow_daily_budget_usd < 0.0 {
+        anyhow::bail!("SHADOW_DAILY_BUDGET_USD must be 0 or a positive amount");
+    }
+
+    if config.provider_health_half_life_secs == 0 {
+        anyhow::bail!("PROVIDER_HEALTH_HALF_LIFE_SECS must be greater than 0");
+    }
+
+    if config.agent_stuck_timeout_secs == 0 {
+        anyhow::bail!("AGENT_STUCK_TIMEOUT_SECS must be greater than 0");
+    }
+
+    if config.webhook_timeout_secs == 0 {
+        anyhow::bail!("WEBHOOK_TIMEOUT_SECS must be greater than 0");
+    }
+
+    if config.collab_send_buffer == 0 {
+        anyhow::bail!("COLLAB_SEND_BUFFER must be at least 1");
+    }
+
+    if !std::path::Path::new(&config.workspace_root).is_dir() {
+        anyhow::bail!("WORKSPACE_ROOT '{}' is not a directory", config.workspace_root);
+    }
+
     // Check if at least one AI provider is configured
     let has_provider = !config.openai_api_key.is_empty()
         || !config.anthropic_api_key.is_empty()
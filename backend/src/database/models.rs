@@ -19,6 +19,7 @@ pub struct OpenClawSession {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: Option<serde_json::Value>,
+    pub user_id: Option<Uuid>, // Owner; None for sessions created without authentication
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -48,6 +49,16 @@ pub struct OpenClawExecution {
     pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OpenClawHistoryMessage {
+    pub id: Uuid,
+    pub session_id: String,
+    pub role: String, // "user" or "assistant"
+    pub content: String,
+    pub model: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 // Moltbook Models
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -84,6 +95,11 @@ pub struct MoltbookPost {
     pub tags: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub remote_id: Option<String>, // Moltbook's id once published
+    pub remote_url: Option<String>,
+    pub sync_status: String, // "local", "pending_sync" or "synced"
+    pub sync_attempts: i32,
+    pub last_sync_error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
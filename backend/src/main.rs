@@ -8,6 +8,7 @@ use axum::{
     routing::{get, post},
     Router,
 };
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use tower::ServiceBuilder;
 use tower_http::{
@@ -20,6 +21,8 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod api;
 mod config;
+mod config_reload;
+mod shutdown;
 mod config_validation;
 mod middleware;
 mod services;
@@ -33,7 +36,7 @@ use services::ai::router::ModelRouter;
 use services::agent::AgentManager;
 use services::codebase::CodebaseIndexer;
 use services::company::CompanyOrchestrator;
-use services::collaboration::{SessionManager, CollaborationWebSocket, PresenceTracker, ConflictResolver};
+use services::collaboration::{SessionManager, CollaborationWebSocket, PresenceTracker, ConflictResolver, CrdtResolver, EditAuditLog, OperationLog};
 use std::sync::Arc;
 
 #[tokio::main]
@@ -47,7 +50,10 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Load configuration
+    // Load configuration; a config reload keeps giving variables set before .env precedence
+    let process_env: std::collections::HashSet<String> = std::env::vars_os()
+        .filter_map(|(key, _)| key.into_string().ok())
+        .collect();
     dotenv::dotenv().ok();
     let config = Config::from_env()?;
 
@@ -60,25 +66,6 @@ async fn main() -> anyhow::Result<()> {
     info!("Starting Bloop Backend v{}", env!("CARGO_PKG_VERSION"));
     info!("Listening on {}:{}", config.host, config.port);
 
-    // Initialize model router
-    let router = Arc::new(ModelRouter::new(&config));
-    
-    // Initialize agent manager
-    let config_arc = Arc::new(config.clone());
-    let agent_manager = Arc::new(AgentManager::new(Arc::clone(&router), Arc::clone(&config_arc)));
-    
-    // Initialize codebase indexer
-    let codebase_indexer = Arc::new(CodebaseIndexer::new());
-
-    // Initialize security services
-    let validator = Arc::new(security::AdvancedValidator::new());
-    let audit_logger = Arc::new(security::AuditLogger::new(10000));
-    let vulnerability_scanner = Arc::new(security::VulnerabilityScanner::new());
-    let threat_detector = Arc::new(security::ThreatDetector::new());
-    let rate_limiter = Arc::new(security::AdaptiveRateLimiter::default());
-    
-    info!("Security services initialized");
-
     // Initialize database if URL is provided
     let database = if let Some(ref db_url) = config.database_url {
         info!("Connecting to database...");
@@ -97,6 +84,67 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
+    // Initialize security services
+    let validator = Arc::new(security::AdvancedValidator::new());
+    let audit_logger = Arc::new(security::AuditLogger::new(10000).with_database(database.clone(), config.audit_log_retention_days));
+    let secret_redactor = Arc::new(security::SecretRedactor::new(
+        config.redact_secrets,
+        config.restore_redacted_secrets,
+        Some(Arc::clone(&audit_logger)),
+    ));
+    let vulnerability_scanner = Arc::new(security::VulnerabilityScanner::new().with_secret_allowlist(
+        security::SecretAllowlist::new(config.secret_scan_allowlist.clone(), config.secret_scan_allowed_paths.clone()),
+    ));
+    let threat_detector = Arc::new(security::ThreatDetector::new());
+    let rate_limiter = Arc::new(security::AdaptiveRateLimiter::new(security::RateLimitConfig {
+        limit: config.rate_limit_per_minute,
+        window: std::time::Duration::from_secs(60),
+        burst_limit: 10,
+    }));
+    
+    info!("Security services initialized");
+
+    // Initialize model router
+    let router = Arc::new(ModelRouter::new(&config).with_secret_redactor(Arc::clone(&secret_redactor)));
+
+    // Initialize agent manager
+    let config_arc = Arc::new(config.clone());
+    let agent_manager = AgentManager::new(Arc::clone(&router), Arc::clone(&config_arc), database.clone());
+    
+    // Initialize codebase indexer
+    let codebase_indexer = Arc::new(CodebaseIndexer::new().with_debt_markers(&config.debt_markers));
+
+    // Restore provider health from the last run and keep snapshotting it
+    let provider_health_store = Arc::new(services::ai::ProviderHealthStore::new(database.clone()));
+    tokio::spawn(Arc::clone(&provider_health_store).restore_and_snapshot(
+        Arc::clone(&router),
+        std::time::Duration::from_secs(config.provider_health_snapshot_secs),
+        std::time::Duration::from_secs(config.provider_health_half_life_secs),
+    ));
+
+    // Chat history compaction keeps its summary cache for the life of the process
+    let history_compactor = Arc::new(services::ai::HistoryCompactor::new(
+        config.history_strategy,
+        config.history_token_threshold,
+    ));
+
+    // Sampled chat requests are replayed against an alternate provider for comparison
+    let shadow_evaluator = Arc::new(services::ai::ShadowEvaluator::new(
+        services::ai::ShadowSettings {
+            sample_percent: config.shadow_sample_percent,
+            provider: config.shadow_provider.clone(),
+            daily_budget_usd: config.shadow_daily_budget_usd,
+        },
+        database.clone(),
+    ));
+
+    // Repeated chat requests are answered from memory while fresh
+    let response_cache = Arc::new(services::ai::ResponseCache::new(
+        std::time::Duration::from_secs(config.response_cache_ttl_secs),
+        config.response_cache_max_entries,
+        config.response_cache_sampled,
+    ));
+
     // Initialize agent company orchestrator (after database)
     let company_orchestrator = CompanyOrchestrator::new(
         Arc::clone(&agent_manager),
@@ -106,39 +154,106 @@ async fn main() -> anyhow::Result<()> {
     );
     info!("Agent Company initialized");
 
+    // Publish shared code to Moltbook, retrying posts that failed to sync
+    let moltbook_sync = Arc::new(services::integrations::MoltbookSync::new(
+        company_orchestrator.moltbook_client(),
+        database.clone(),
+    ));
+    let moltbook_enabled = std::env::var("MOLTBOOK_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    if moltbook_enabled {
+        moltbook_sync.spawn_retry(std::time::Duration::from_secs(config.moltbook_sync_retry_secs));
+    }
+
+    // Hot-reloadable settings (SIGHUP or POST /api/v1/admin/config/reload)
+    let live_config = Arc::new(config_reload::LiveConfig::new(config.clone()));
+    let config_reloader = Arc::new(config_reload::ConfigReloader::new(
+        Arc::clone(&live_config),
+        Arc::clone(&agent_manager),
+        Arc::clone(&company_orchestrator),
+        Arc::clone(&rate_limiter),
+        process_env,
+    ));
+    config_reload::spawn_sighup_listener(Arc::clone(&config_reloader));
+
     // Initialize collaboration services (Phase 4)
     let session_manager = SessionManager::new(
         database.clone(),
         Arc::clone(&audit_logger),
+        std::path::PathBuf::from(&config.workspace_root),
     );
     let presence_tracker = PresenceTracker::new();
     let conflict_resolver = ConflictResolver::new(
         Arc::clone(&codebase_indexer),
         database.clone(),
     );
+    let crdt_resolver = CrdtResolver::new();
+    let edit_audit = EditAuditLog::new(database.clone(), config.edit_snapshot_interval);
+    let operation_log = OperationLog::new(database.clone(), config.collab_operation_log_len);
     let collaboration_websocket = CollaborationWebSocket::new(
         Arc::clone(&session_manager),
         Arc::clone(&presence_tracker),
         Arc::clone(&conflict_resolver),
+        Arc::clone(&crdt_resolver),
         Arc::clone(&agent_manager),
         Arc::clone(&codebase_indexer),
         Arc::clone(&validator),
+        Arc::clone(&edit_audit),
+        Arc::clone(&operation_log),
+        Arc::new(services::collaboration::rate_limit::MessageRateLimiter::new(
+            config.collab_messages_per_second,
+            config.collab_message_burst,
+        )),
+        services::collaboration::broadcast::DeliveryConfig {
+            send_buffer: config.collab_send_buffer,
+            max_consecutive_failures: config.collab_max_delivery_failures,
+        },
+    );
+    collaboration_websocket.spawn_presence_monitor(
+        std::time::Duration::from_secs(config.collab_presence_away_secs),
+        std::time::Duration::from_secs(config.collab_presence_offline_secs),
     );
     info!("Collaboration services initialized");
 
+    // Closes collaboration sockets and drains the agent queue when a shutdown signal arrives
+    let connections = collaboration_websocket.shutdown_token();
+    {
+        let router = Arc::clone(&router);
+        let stopping = connections.clone();
+        tokio::spawn(async move {
+            stopping.cancelled().await;
+            router.shutdown();
+        });
+    }
+    let shutdown = shutdown::drain_on_signal(
+        Arc::clone(&agent_manager),
+        std::time::Duration::from_secs(config.shutdown_drain_timeout_secs),
+        connections.clone(),
+    );
+
     // Build application
     let app = create_app(
         config.clone(), 
+        live_config,
+        config_reloader,
         router, 
         agent_manager, 
         codebase_indexer, 
         database, 
         company_orchestrator,
+        moltbook_sync,
         audit_logger,
         vulnerability_scanner,
         threat_detector,
+        rate_limiter,
         session_manager,
         collaboration_websocket,
+        edit_audit,
+        provider_health_store,
+        history_compactor,
+        shadow_evaluator,
+        response_cache,
     ).await?;
 
     // Start server
@@ -147,23 +262,47 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Server ready at http://{}", addr);
 
-    axum::serve(listener, app).await?;
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown)
+        .into_future();
+    let deadline = shutdown::connection_deadline(
+        connections,
+        std::time::Duration::from_secs(config.shutdown_connection_timeout_secs),
+    );
+    tokio::select! {
+        result = server => result?,
+        _ = deadline => tracing::warn!(
+            "Connections still open {}s after shutdown started; stopping anyway",
+            config.shutdown_connection_timeout_secs
+        ),
+    }
+
+    info!("Server stopped");
 
     Ok(())
 }
 
 async fn create_app(
     config: Config, 
+    live_config: Arc<config_reload::LiveConfig>,
+    config_reloader: Arc<config_reload::ConfigReloader>,
     router: Arc<ModelRouter>,
     agent_manager: Arc<AgentManager>,
     codebase_indexer: Arc<CodebaseIndexer>,
     database: Option<Arc<database::Database>>,
     company_orchestrator: Arc<CompanyOrchestrator>,
+    moltbook_sync: Arc<services::integrations::MoltbookSync>,
     audit_logger: Arc<security::AuditLogger>,
     vulnerability_scanner: Arc<security::VulnerabilityScanner>,
     threat_detector: Arc<security::ThreatDetector>,
+    rate_limiter: Arc<security::AdaptiveRateLimiter>,
     session_manager: Arc<SessionManager>,
     collaboration_websocket: Arc<CollaborationWebSocket>,
+    edit_audit: Arc<EditAuditLog>,
+    provider_health_store: Arc<services::ai::ProviderHealthStore>,
+    history_compactor: Arc<services::ai::HistoryCompactor>,
+    shadow_evaluator: Arc<services::ai::ShadowEvaluator>,
+    response_cache: Arc<services::ai::ResponseCache>,
 ) -> anyhow::Result<Router> {
     // CORS layer
     let cors = CorsLayer::new()
@@ -171,27 +310,57 @@ async fn create_app(
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
         .allow_headers(Any);
 
+    // Per-endpoint request timeouts
+    let request_timeouts = Arc::new(middleware::timeout::RequestTimeouts::from_config(&config));
+    let security_headers = Arc::new(middleware::security::SecurityHeaders::from_config(&config));
+    let jwt_auth = Arc::new(middleware::auth::JwtAuth::from_config(&config));
+    let trusted_proxies = Arc::new(middleware::client_ip::TrustedProxies::from_config(&config));
+    let request_rate_limits = Arc::new(middleware::rate_limit::RequestRateLimits::new(
+        Arc::clone(&rate_limiter),
+        security::RateLimitPlans::from_config(&config),
+    ));
+    let openclaw_client = company_orchestrator.openclaw_client();
+
     // Build router
     let app = Router::new()
         .route("/health", get(api::routes::health::health_check))
         .route("/health/ready", get(api::routes::health::readiness))
         .route("/health/live", get(api::routes::health::liveness))
+        .route("/metrics", get(api::routes::metrics::prometheus_metrics))
         .route("/api/v1/chat", post(api::routes::chat::handle_chat))
         .route("/api/v1/models", get(api::routes::models::list_models))
+        .route("/api/v1/models/health", get(api::routes::models::provider_health))
+        .route("/api/v1/models/shadow-report", get(api::routes::models::shadow_report))
+        .route("/api/v1/models/route", post(api::routes::models::explain_route))
+        .route("/api/v1/limits", get(api::routes::security::get_limits))
+        .route("/api/v1/security/audit", get(api::routes::security::query_audit_logs))
         .route("/api/v1/agents", get(api::routes::agents::list_agents))
         .route("/api/v1/agents/create", post(api::routes::agents::create_agent))
+        .route("/api/v1/agents/bulk", post(api::routes::agents::create_agents_bulk))
         .route("/api/v1/agents/:id", get(api::routes::agents::get_agent_status))
+        .route("/api/v1/agents/:id", axum::routing::delete(api::routes::agents::delete_agent))
         .route("/api/v1/agents/tasks", post(api::routes::agents::create_task))
         .route("/api/v1/agents/tasks", get(api::routes::agents::list_tasks))
         .route("/api/v1/agents/tasks/:id", get(api::routes::agents::get_task_status))
+        .route("/api/v1/agents/tasks/:id", axum::routing::delete(api::routes::agents::cancel_task))
+        .route("/api/v1/agents/tasks/:id/webhook", get(api::routes::agents::get_task_webhook))
+        .route("/api/v1/agents/deadletter", get(api::routes::agents::list_dead_letters))
+        .route("/api/v1/agents/deadletter/:id/requeue", post(api::routes::agents::requeue_dead_letter))
         .route("/api/v1/agents/metrics", get(api::routes::agents::get_metrics))
         .route("/api/v1/agents/queue/status", get(api::routes::agents::get_queue_status))
         .route("/api/v1/agents/health", get(api::routes::agents::get_health_status))
         .route("/api/v1/context/analyze", post(api::routes::context::analyze_context))
         .route("/api/v1/codebase/search", get(api::routes::codebase::search_codebase))
+        .route("/api/v1/codebase/search/unified", post(api::routes::codebase::unified_search))
         .route("/api/v1/codebase/review", post(api::routes::codebase::review_code))
+        .route("/api/v1/codebase/review/diff", post(api::routes::codebase::review_diff))
+        .route("/api/v1/codebase/patterns", post(api::routes::codebase::detect_patterns))
         .route("/api/v1/codebase/tests", post(api::routes::codebase::generate_tests))
         .route("/api/v1/codebase/docs", post(api::routes::codebase::generate_docs))
+        .route("/api/v1/codebase/impact", post(api::routes::codebase::analyze_impact))
+        .route("/api/v1/codebase/debt", get(api::routes::codebase::get_debt))
+        .route("/api/v1/codebase/explain-error", post(api::routes::codebase::explain_error))
+        .route("/api/v1/codebase/security-scan", post(api::routes::codebase::security_scan))
         .route("/api/v1/codebase/dependencies/:file_path", get(api::routes::codebase::get_dependencies))
         .route("/api/v1/files/read/:file_path", get(api::routes::files::read_file))
         .route("/api/v1/files/write", post(api::routes::files::write_file))
@@ -214,13 +383,33 @@ async fn create_app(
         .route("/api/v1/moltbook/feed", get(api::routes::moltbook::get_feed))
         // Company routes
         .route("/api/v1/company/status", get(api::routes::company::get_status))
+        .route("/api/v1/company/metrics/history", get(api::routes::company::get_metrics_history))
         .route("/api/v1/company/members", get(api::routes::company::get_members))
         .route("/api/v1/company/teams", get(api::routes::company::get_teams))
+        .route("/api/v1/company/leaderboard", get(api::routes::company::get_leaderboard))
+        .route("/api/v1/company/export", get(api::routes::company::export_company))
+        .route("/api/v1/company/import", post(api::routes::company::import_company))
+        .route("/api/v1/company/tasks", post(api::routes::company::submit_task))
+        .route("/api/v1/company/start", post(api::routes::company::start_company))
+        .route("/api/v1/company/stop", post(api::routes::company::stop_company))
+        .route(
+            "/api/v1/company/scaling/policy",
+            get(api::routes::company::get_scaling_policy).post(api::routes::company::update_scaling_policy),
+        )
+        .route("/api/v1/company/visual/status", get(api::routes::company::get_visual_status))
+        .route("/api/v1/company/activity/ws", get(api::routes::company::company_activity_websocket_handler))
+        .route("/api/v1/company/visual/requests/:id", get(api::routes::company::get_visual_request))
+        .route("/api/v1/company/visual/assets/:file", get(api::routes::company::get_visual_asset))
+        // Admin routes
+        .route("/api/v1/admin/config/reload", post(api::routes::admin::reload_config))
+        .route("/api/v1/admin/metrics/reset", post(api::routes::admin::reset_agent_metrics))
+        .route("/api/v1/admin/cache/clear", post(api::routes::admin::clear_response_cache))
         // Collaboration routes (Phase 4)
         .route("/api/v1/collaboration/sessions", axum::routing::post(api::routes::collaboration::create_session))
         .route("/api/v1/collaboration/sessions/:id", get(api::routes::collaboration::get_session))
         .route("/api/v1/collaboration/sessions/:id/join", axum::routing::post(api::routes::collaboration::join_session))
         .route("/api/v1/collaboration/sessions/:id/participants", get(api::routes::collaboration::list_participants))
+        .route("/api/v1/collaboration/sessions/:id/blame", get(api::routes::collaboration::get_blame))
         .route("/api/v1/collaboration/sessions/token/:token", get(api::routes::collaboration::get_session_by_token))
         .route("/api/v1/collaboration/ws/:session_id", get(api::routes::collaboration::collaboration_websocket_handler))
         .layer(
@@ -228,20 +417,50 @@ async fn create_app(
                 .layer(TraceLayer::new_for_http())
                 .layer(CompressionLayer::new())
                 .layer(axum::middleware::from_fn(middleware::request_id::request_id_middleware))
-                .layer(axum::middleware::from_fn(middleware::security::security_headers_middleware))
+                .layer(axum::middleware::from_fn_with_state(
+                    trusted_proxies,
+                    middleware::client_ip::client_ip_middleware,
+                ))
+                .layer(axum::middleware::from_fn(middleware::error_body::json_error_body_middleware))
+                .layer(axum::middleware::from_fn_with_state(
+                    request_timeouts,
+                    middleware::timeout::request_timeout_middleware,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    security_headers,
+                    middleware::security::security_headers_middleware,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    jwt_auth,
+                    middleware::auth::jwt_auth_middleware,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    request_rate_limits,
+                    middleware::rate_limit::rate_limit_middleware,
+                ))
                 .layer(axum::middleware::from_fn(middleware::security::validate_payload_size))
                 .layer(cors)
                 .layer(Extension(config))
+                .layer(Extension(live_config))
+                .layer(Extension(config_reloader))
                 .layer(Extension(router))
                 .layer(Extension(agent_manager))
                 .layer(Extension(codebase_indexer))
                 .layer(Extension(database))
                 .layer(Extension(company_orchestrator))
+                .layer(Extension(openclaw_client))
+                .layer(Extension(moltbook_sync))
                 .layer(Extension(audit_logger))
                 .layer(Extension(vulnerability_scanner))
                 .layer(Extension(threat_detector))
+                .layer(Extension(rate_limiter))
                 .layer(Extension(session_manager))
                 .layer(Extension(collaboration_websocket))
+                .layer(Extension(edit_audit))
+                .layer(Extension(provider_health_store))
+                .layer(Extension(history_compactor))
+                .layer(Extension(shadow_evaluator))
+                .layer(Extension(response_cache))
                 .layer(Extension(validator))
                 .into_inner(),
         );
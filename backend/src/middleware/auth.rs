@@ -1,16 +1,243 @@
 /**
  * Authentication Middleware
  * 
- * Basic authentication for agent endpoints
- * (Full JWT auth can be added later)
+ * JWT bearer-token authentication for API routes, plus API key helpers.
+ * HMAC-signed tokens are verified with the configured secret, asymmetric ones
+ * with the matching key from a JWKS endpoint. A verified token's subject is
+ * made available to handlers as a `UserId` request extension.
  */
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use axum::{
-    extract::Request,
-    http::{StatusCode, HeaderMap},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode, HeaderMap},
     middleware::Next,
     response::Response,
 };
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
 use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::types::errors::ApiError;
+use super::error_body::error_response;
+use super::request_id::get_request_id;
+use super::timeout::{is_websocket_upgrade, under_prefix};
+
+/// Fetched JWKS keys are trusted this long before being fetched again
+const JWKS_CACHE_TTL: Duration = Duration::from_secs(300);
+/// An unknown key id triggers a refetch at most this often
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+const JWKS_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Shorter HMAC secrets can be brute-forced offline from a single token
+const MIN_HMAC_SECRET_LEN: usize = 32;
+
+/// Whether `secret` is too weak to verify HMAC tokens with: empty, short, or a placeholder
+pub fn is_weak_jwt_secret(secret: &str) -> bool {
+    secret.len() < MIN_HMAC_SECRET_LEN || secret.starts_with("change-me")
+}
+
+/// The authenticated caller, inserted into request extensions by `jwt_auth_middleware`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct UserId(pub Uuid);
+
+/// The caller's plan (e.g. "free", "pro") from the token's `plan` claim, when present
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserPlan(pub String);
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String, // Expiry is checked by `Validation`
+    #[serde(default)]
+    plan: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing bearer token")]
+    MissingToken,
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+    #[error("Token subject is not a user id")]
+    InvalidSubject,
+}
+
+/// Bearer-token verification settings and the cached JWKS
+pub struct JwtAuth {
+    required: bool,
+    secret: Option<DecodingKey>,
+    jwks_url: Option<String>,
+    jwks: RwLock<Option<(Instant, JwkSet)>>,
+    http: reqwest::Client,
+    public_paths: Vec<String>,
+    admin_api_key: String,
+}
+
+impl JwtAuth {
+    pub fn new(
+        required: bool,
+        secret: &str,
+        jwks_url: Option<String>,
+        public_paths: Vec<String>,
+        admin_api_key: String,
+    ) -> Self {
+        Self {
+            required,
+            // Anyone could forge tokens for a guessable secret, so HMAC tokens are refused instead
+            secret: (!is_weak_jwt_secret(secret)).then(|| DecodingKey::from_secret(secret.as_bytes())),
+            jwks_url,
+            jwks: RwLock::new(None),
+            http: reqwest::Client::new(),
+            public_paths,
+            admin_api_key,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.auth_required,
+            &config.jwt_secret,
+            config.jwt_jwks_url.clone(),
+            config.public_paths.clone(),
+            config.admin_api_key.clone(),
+        )
+    }
+
+    /// Whether `path` is one of the public paths or beneath one (`/health` covers `/health/ready`, not `/healthz`)
+    pub fn is_public(&self, path: &str) -> bool {
+        self.public_paths.iter().any(|prefix| under_prefix(path, prefix))
+    }
+
+    /// Verify a token's signature and expiry and return its subject and plan
+    pub async fn authenticate(&self, token: &str) -> Result<(UserId, Option<UserPlan>), AuthError> {
+        let header = decode_header(token).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        let key = match header.alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => self.secret.clone()
+                .ok_or_else(|| AuthError::InvalidToken("HMAC-signed tokens are not accepted".to_string()))?,
+            _ => self.jwks_key(header.kid.as_deref()).await?,
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.validate_aud = false;
+        let data = decode::<Claims>(token, &key, &validation)
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        let user = Uuid::parse_str(&data.claims.sub)
+            .map(UserId)
+            .map_err(|_| AuthError::InvalidSubject)?;
+        Ok((user, data.claims.plan.map(UserPlan)))
+    }
+
+    /// Key for `kid` from the JWKS, refetching when the cache is stale or lacks it
+    async fn jwks_key(&self, kid: Option<&str>) -> Result<DecodingKey, AuthError> {
+        let Some(url) = &self.jwks_url else {
+            return Err(AuthError::InvalidToken("asymmetrically signed tokens are not accepted".to_string()));
+        };
+        let kid = kid.ok_or_else(|| AuthError::InvalidToken("token has no key id".to_string()))?;
+
+        if let Some((fetched_at, keys)) = self.jwks.read().await.as_ref() {
+            let age = fetched_at.elapsed();
+            match keys.find(kid) {
+                Some(jwk) if age < JWKS_CACHE_TTL => return key_from_jwk(jwk),
+                None if age < JWKS_MIN_REFRESH => return Err(unknown_key(kid)),
+                _ => {}
+            }
+        }
+
+        let keys = self.fetch_jwks(url).await.map_err(|e| {
+            warn!("Failed to fetch JWKS from {}: {}", url, e);
+            AuthError::InvalidToken("signing keys are unavailable".to_string())
+        })?;
+        let key = keys.find(kid).map(key_from_jwk);
+        *self.jwks.write().await = Some((Instant::now(), keys));
+        key.unwrap_or_else(|| Err(unknown_key(kid)))
+    }
+
+    async fn fetch_jwks(&self, url: &str) -> reqwest::Result<JwkSet> {
+        self.http.get(url)
+            .timeout(JWKS_FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+}
+
+fn key_from_jwk(jwk: &jsonwebtoken::jwk::Jwk) -> Result<DecodingKey, AuthError> {
+    DecodingKey::from_jwk(jwk).map_err(|e| AuthError::InvalidToken(format!("unusable signing key: {}", e)))
+}
+
+fn unknown_key(kid: &str) -> AuthError {
+    AuthError::InvalidToken(format!("unknown key id '{}'", kid))
+}
+
+/// Token from `Authorization: Bearer`, or the `access_token` query parameter
+/// for WebSocket upgrades, which browsers can't attach headers to
+///
+/// Other requests can't use the query parameter, so tokens don't end up in
+/// access logs and browser history for ordinary API calls.
+fn bearer_token(request: &Request) -> Option<String> {
+    let from_header = request.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim().to_string());
+    from_header.or_else(|| {
+        if !is_websocket_upgrade(request) {
+            return None;
+        }
+        request.uri().query()?
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("access_token="))
+            .map(str::to_string)
+    })
+    .filter(|token| !token.is_empty())
+}
+
+/// Authenticate requests to non-public paths, answering 401 on failure
+///
+/// A valid token's subject is inserted as `UserId`, and its plan, if any, as
+/// `UserPlan`. Requests carrying the admin API key and CORS preflights pass
+/// through without one. When auth isn't required, requests without a token
+/// pass too; bad tokens never do.
+pub async fn jwt_auth_middleware(
+    State(auth): State<Arc<JwtAuth>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if request.method() == Method::OPTIONS
+        || auth.is_public(request.uri().path())
+        || is_admin(request.headers(), &auth.admin_api_key)
+    {
+        return next.run(request).await;
+    }
+
+    let result = match bearer_token(&request) {
+        Some(token) => auth.authenticate(&token).await,
+        None if !auth.required => return next.run(request).await,
+        None => Err(AuthError::MissingToken),
+    };
+    match result {
+        Ok((user, plan)) => {
+            request.extensions_mut().insert(user);
+            if let Some(plan) = plan {
+                request.extensions_mut().insert(plan);
+            }
+            next.run(request).await
+        }
+        Err(e) => {
+            let mut response = error_response(
+                StatusCode::UNAUTHORIZED,
+                ApiError::unauthorized().with_details(e.to_string()),
+                get_request_id(&request),
+            );
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+    }
+}
 
 /// API Key authentication (simple for now, can upgrade to JWT later)
 pub async fn api_key_auth_middleware(
@@ -39,7 +266,112 @@ pub async fn api_key_auth_middleware(
     Ok(next.run(request).await)
 }
 
-/// Optional: Check if request has valid authentication
-pub fn is_authenticated(headers: &HeaderMap) -> bool {
-    headers.contains_key("X-API-Key")
+/// Whether `jwt_auth_middleware` verified a token for the request, or it carries the admin key
+pub fn is_authenticated(user: Option<&UserId>, headers: &HeaderMap, admin_api_key: &str) -> bool {
+    user.is_some() || is_admin(headers, admin_api_key)
+}
+
+/// Whether the request carries the configured admin key (no key configured = no admins)
+///
+/// Compared in constant time so response timing doesn't leak how much of a guess matched.
+pub fn is_admin(headers: &HeaderMap, admin_api_key: &str) -> bool {
+    use subtle::ConstantTimeEq;
+
+    !admin_api_key.is_empty()
+        && headers.get("X-API-Key")
+            .is_some_and(|key| bool::from(key.as_bytes().ct_eq(admin_api_key.as_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Extension, routing::get, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use tower::ServiceExt;
+
+    const SECRET: &str = "test-secret-that-is-at-least-32-bytes";
+
+    fn token(sub: &str, expires_in: i64, secret: &str) -> String {
+        let claims = serde_json::json!({ "sub": sub, "exp": chrono::Utc::now().timestamp() + expires_in });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    fn app(required: bool) -> Router {
+        app_with_secret(required, SECRET)
+    }
+
+    fn app_with_secret(required: bool, secret: &str) -> Router {
+        let auth = JwtAuth::new(required, secret, None, vec!["/health".to_string()], "admin-key".to_string());
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .route("/healthz", get(|| async { "ok" }))
+            .route("/me", get(|user: Option<Extension<UserId>>| async move {
+                user.map(|Extension(UserId(id))| id.to_string()).unwrap_or_default()
+            }))
+            .layer(axum::middleware::from_fn_with_state(Arc::new(auth), jwt_auth_middleware))
+    }
+
+    async fn call(app: Router, uri: &str, bearer: Option<&str>) -> (StatusCode, String) {
+        call_with(app, Request::builder().uri(uri), bearer).await
+    }
+
+    async fn call_with(app: Router, mut request: axum::http::request::Builder, bearer: Option<&str>) -> (StatusCode, String) {
+        if let Some(token) = bearer {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_bearer_tokens_are_verified_and_expose_the_user() {
+        let user = Uuid::new_v4();
+
+        assert_eq!(call(app(true), "/health", None).await.0, StatusCode::OK);
+        assert_eq!(call(app(true), "/me", None).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(app(true), "/me", Some(&token(&user.to_string(), -3600, SECRET))).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(app(true), "/me", Some(&token(&user.to_string(), 3600, "forged"))).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(app(true), "/me", Some(&token("not-a-uuid", 3600, SECRET))).await.0, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call(app(true), "/me", Some(&token(&user.to_string(), 3600, SECRET))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, user.to_string());
+
+        // WebSocket clients pass the token in the query string; other requests can't
+        let uri = format!("/me?access_token={}", token(&user.to_string(), 3600, SECRET));
+        let upgrade = Request::builder().uri(&uri).header(header::UPGRADE, "websocket");
+        assert_eq!(call_with(app(true), upgrade, None).await, (StatusCode::OK, user.to_string()));
+        assert_eq!(call(app(true), &uri, None).await.0, StatusCode::UNAUTHORIZED);
+
+        // Public paths match whole segments
+        assert_eq!(call(app(true), "/healthz", None).await.0, StatusCode::UNAUTHORIZED);
+
+        // With auth optional, anonymous requests pass but bad tokens still don't
+        assert_eq!(call(app(false), "/me", None).await, (StatusCode::OK, String::new()));
+        assert_eq!(call(app(false), "/me", Some("garbage")).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_weak_secrets_refuse_hmac_tokens() {
+        for weak in ["short", "change-me-in-production-use-strong-random-secret"] {
+            assert!(is_weak_jwt_secret(weak));
+            let forged = token(&Uuid::new_v4().to_string(), 3600, weak);
+            assert_eq!(call(app_with_secret(true, weak), "/me", Some(&forged)).await.0, StatusCode::UNAUTHORIZED);
+        }
+        assert!(!is_weak_jwt_secret(SECRET));
+    }
+
+    #[test]
+    fn test_is_authenticated_needs_a_verified_user_or_admin_key() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer not-checked".parse().unwrap());
+        headers.insert("X-API-Key", "anything".parse().unwrap());
+        assert!(!is_authenticated(None, &headers, "admin-key"));
+        assert!(is_authenticated(Some(&UserId(Uuid::new_v4())), &headers, "admin-key"));
+
+        headers.insert("X-API-Key", "admin-key".parse().unwrap());
+        assert!(is_authenticated(None, &headers, "admin-key"));
+    }
 }
@@ -0,0 +1,143 @@
+/**
+ * Client IP Resolution
+ *
+ * Works out who is on the other end of a request. The TCP peer address is
+ * used unless the peer is one of TRUSTED_PROXIES, in which case the
+ * forwarding headers it set are believed. Headers from anyone else are
+ * ignored, so a client can't pick its own rate-limit bucket or pose as
+ * an HTTPS connection.
+ */
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use crate::config::Config;
+
+/// The caller's address, resolved by `client_ip_middleware`
+///
+/// As an extractor it falls back to loopback when the middleware didn't run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Marks requests that arrived through a trusted proxy, whose `X-Forwarded-*` headers can be believed
+#[derive(Debug, Clone, Copy)]
+pub struct ViaTrustedProxy;
+
+impl ClientIp {
+    const UNKNOWN: ClientIp = ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST));
+}
+
+impl std::fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<ClientIp>().copied().unwrap_or(ClientIp::UNKNOWN))
+    }
+}
+
+/// Proxies allowed to report the original client address
+pub struct TrustedProxies {
+    proxies: Vec<IpAddr>,
+}
+
+impl TrustedProxies {
+    pub fn new(proxies: Vec<IpAddr>) -> Self {
+        Self { proxies }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.trusted_proxies.clone())
+    }
+
+    pub fn is_trusted(&self, peer: IpAddr) -> bool {
+        self.proxies.contains(&peer)
+    }
+
+    /// Caller address for a connection from `peer`
+    ///
+    /// For a trusted peer this is the right-most `X-Forwarded-For` hop that isn't
+    /// itself a trusted proxy (earlier hops are client-supplied), then `X-Real-IP`.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+        let forwarded = headers.get_all("X-Forwarded-For")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+            .collect::<Vec<_>>();
+        forwarded.into_iter()
+            .rev()
+            .find(|hop| !self.is_trusted(*hop))
+            .or_else(|| headers.get("X-Real-IP")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok()))
+            .unwrap_or(peer)
+    }
+}
+
+/// Attach `ClientIp` (and `ViaTrustedProxy` when applicable) to every request
+///
+/// Needs the server to run with `into_make_service_with_connect_info::<SocketAddr>()`;
+/// without connect info the peer is taken to be loopback.
+pub async fn client_ip_middleware(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(ClientIp::UNKNOWN.0);
+    let ip = proxies.resolve(peer, request.headers());
+    if proxies.is_trusted(peer) {
+        request.extensions_mut().insert(ViaTrustedProxy);
+    }
+    request.extensions_mut().insert(ClientIp(ip));
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(forwarded_for: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", forwarded_for.parse().unwrap());
+        headers.insert("X-Real-IP", "198.51.100.9".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_forwarding_headers_only_count_from_trusted_proxies() {
+        let proxies = TrustedProxies::new(vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()]);
+        let client: IpAddr = "203.0.113.5".parse().unwrap();
+
+        // A direct client can't claim another address
+        assert_eq!(proxies.resolve(client, &headers("192.0.2.1")), client);
+
+        // Through two proxies, the spoofed left-most hop is skipped
+        let resolved = proxies.resolve("10.0.0.2".parse().unwrap(), &headers("192.0.2.1, 203.0.113.5, 10.0.0.1"));
+        assert_eq!(resolved, client);
+
+        // A trusted proxy that sent no X-Forwarded-For falls back to X-Real-IP
+        let mut real_ip_only = headers("10.0.0.2");
+        real_ip_only.remove("X-Forwarded-For");
+        let resolved = proxies.resolve("10.0.0.1".parse().unwrap(), &real_ip_only);
+        assert_eq!(resolved, "198.51.100.9".parse::<IpAddr>().unwrap());
+    }
+}
@@ -0,0 +1,147 @@
+/**
+ * Structured error bodies for rejections
+ *
+ * Extractor rejections (malformed JSON, wrong content type) and middleware that
+ * short-circuits with a bare status code would otherwise reach clients as plain
+ * text or an empty body. This rewrites those client errors into the same
+ * `ApiError` envelope handlers return, tagged with the request id.
+ */
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use crate::types::errors::{error_codes, ApiError};
+use super::request_id::get_request_id;
+
+/// Rejection bodies are short messages; anything bigger is passed through untouched
+const MAX_REJECTION_BODY: usize = 16 * 1024;
+
+fn error_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => error_codes::VALIDATION_ERROR,
+        StatusCode::UNAUTHORIZED => error_codes::UNAUTHORIZED,
+        StatusCode::FORBIDDEN => error_codes::FORBIDDEN,
+        StatusCode::NOT_FOUND => error_codes::NOT_FOUND,
+        StatusCode::CONFLICT => error_codes::CONFLICT,
+        StatusCode::PAYLOAD_TOO_LARGE => error_codes::PAYLOAD_TOO_LARGE,
+        StatusCode::TOO_MANY_REQUESTS => error_codes::RATE_LIMIT_EXCEEDED,
+        _ => error_codes::INVALID_INPUT,
+    }
+}
+
+/// Build an error response that keeps `status` and carries the request's id
+pub fn error_response(status: StatusCode, error: ApiError, request_id: Option<String>) -> Response {
+    let error = match request_id {
+        Some(id) => error.with_request_id(id),
+        None => error,
+    };
+    tracing::warn!("Rejected request: {}", error);
+    (status, Json(error)).into_response()
+}
+
+pub async fn json_error_body_middleware(request: Request, next: Next) -> Response {
+    let request_id = get_request_id(&request);
+    let response = next.run(request).await;
+
+    let status = response.status();
+    let is_json = response.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !status.is_client_error() || is_json {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let reason = match to_bytes(body, MAX_REJECTION_BODY).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).trim().to_string(),
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+
+    let message = status.canonical_reason().unwrap_or("Request rejected").to_string();
+    let mut error = ApiError::new(error_code(status).to_string(), message);
+    if !reason.is_empty() {
+        error = error.with_details(reason);
+    }
+
+    let mut rewritten = error_response(status, error, request_id);
+    // Keep headers like Retry-After or WWW-Authenticate from the original rejection
+    for (name, value) in parts.headers.iter() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            rewritten.headers_mut().insert(name.clone(), value.clone());
+        }
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+    use crate::middleware::{request_id::request_id_middleware, security::validate_payload_size};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[derive(serde::Deserialize)]
+    struct Payload {
+        #[allow(dead_code)]
+        name: String,
+    }
+
+    fn test_app() -> Router {
+        Router::new()
+            .route("/items", post(|Json(_): Json<Payload>| async { "created" }))
+            .layer(axum::middleware::from_fn(validate_payload_size))
+            .layer(axum::middleware::from_fn(json_error_body_middleware))
+            .layer(axum::middleware::from_fn(request_id_middleware))
+    }
+
+    async fn json_body(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_request_gets_json_error() {
+        let response = test_app()
+            .oneshot(
+                Request::post("/items")
+                    .header("content-type", "application/json")
+                    .header("content-length", (20 * 1024 * 1024).to_string())
+                    .header("x-request-id", "req-oversized")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = json_body(response).await;
+        assert_eq!(body["error"]["code"], "PAYLOAD_TOO_LARGE");
+        assert!(body["error"]["details"].as_str().unwrap().contains("limit: 10485760 bytes"));
+        assert_eq!(body["request_id"], "req-oversized");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_gets_json_error() {
+        let response = test_app()
+            .oneshot(
+                Request::post("/items")
+                    .header("content-type", "application/json")
+                    .header("x-request-id", "req-malformed")
+                    .body(Body::from("{\"name\": "))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = json_body(response).await;
+        assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+        assert!(!body["error"]["details"].as_str().unwrap().is_empty());
+        assert_eq!(body["request_id"], "req-malformed");
+    }
+}
@@ -3,9 +3,15 @@ pub mod logging;
 pub mod auth;
 pub mod security;
 pub mod request_id;
+pub mod timeout;
+pub mod error_body;
+pub mod client_ip;
 
 pub use rate_limit::*;
 pub use logging::*;
 pub use auth::*;
 pub use security::*;
 pub use request_id::*;
+pub use timeout::*;
+pub use error_body::*;
+pub use client_ip::*;
@@ -2,20 +2,108 @@
  * Rate Limiting Middleware
  * 
  * Prevents abuse by limiting request rates per IP/user
+ *
+ * Authenticated requests are limited per user, with limits from the user's
+ * plan, so users sharing a NAT don't throttle each other and rotating IPs
+ * doesn't reset a user's budget. Anonymous requests are limited per IP, as
+ * resolved by `client_ip_middleware`. Health probes and metrics scrapes are
+ * never limited.
  */
 use axum::{
-    extract::Request,
-    http::StatusCode,
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
     middleware::Next,
     response::Response,
 };
 use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use std::collections::HashMap;
 use chrono::Utc;
 
+use crate::security::{AdaptiveRateLimiter, RateLimitPlans, RateLimitResult};
+use crate::types::errors::ApiError;
+use super::auth::{UserId, UserPlan};
+use super::client_ip::ClientIp;
+use super::error_body::error_response;
+use super::request_id::get_request_id;
+
+const X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+/// State for `rate_limit_middleware`
+pub struct RequestRateLimits {
+    limiter: Arc<AdaptiveRateLimiter>,
+    plans: RateLimitPlans,
+}
+
+impl RequestRateLimits {
+    pub fn new(limiter: Arc<AdaptiveRateLimiter>, plans: RateLimitPlans) -> Self {
+        Self { limiter, plans }
+    }
+}
+
+/// Limiter key for a caller: their user id when authenticated, otherwise their IP
+pub fn rate_limit_key(user: Option<&UserId>, ip: ClientIp) -> String {
+    match user {
+        Some(UserId(id)) => format!("user:{}", id),
+        None => ip.to_string(),
+    }
+}
+
+/// Probe and scrape endpoints, which must answer however busy the caller's bucket is
+fn is_exempt(path: &str) -> bool {
+    path == "/health" || path.starts_with("/health/") || path == "/metrics"
+}
+
+/// Count each request against its caller's budget; 429 with `Retry-After` once it's spent
+///
+/// Runs after authentication so the `UserId`/`UserPlan` extensions are set.
+/// Every response carries `X-RateLimit-Limit` and `X-RateLimit-Remaining`.
+pub async fn rate_limit_middleware(
+    State(limits): State<Arc<RequestRateLimits>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() == Method::OPTIONS || is_exempt(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let user = request.extensions().get::<UserId>().copied();
+    let ip = request.extensions().get::<ClientIp>().copied().unwrap_or(ClientIp(IpAddr::from([127, 0, 0, 1])));
+    let key = rate_limit_key(user.as_ref(), ip);
+    let plan = user.and_then(|_| {
+        limits.plans.config_for(request.extensions().get::<UserPlan>().map(|UserPlan(plan)| plan.as_str()))
+    });
+    let result = match plan {
+        Some(config) => limits.limiter.check_with_config(&key, config).await,
+        None => limits.limiter.check(&key).await,
+    };
+
+    if !result.allowed {
+        let retry_after = result.reset_at.saturating_duration_since(Instant::now()).as_secs_f64().ceil().max(1.0) as u64;
+        tracing::warn!("Rate limit exceeded for {}: {}", key, result.reason.as_deref().unwrap_or("limit reached"));
+        let mut error = ApiError::rate_limit_exceeded();
+        if let Some(reason) = &result.reason {
+            error = error.with_details(reason.clone());
+        }
+        let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, error, get_request_id(&request));
+        set_limit_headers(response.headers_mut(), &result);
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    set_limit_headers(response.headers_mut(), &result);
+    response
+}
+
+fn set_limit_headers(headers: &mut HeaderMap, result: &RateLimitResult) {
+    headers.insert(X_RATELIMIT_LIMIT, HeaderValue::from(result.limit));
+    headers.insert(X_RATELIMIT_REMAINING, HeaderValue::from(result.remaining));
+}
+
 /// Simple in-memory rate limiter
 struct RateLimiter {
     requests: HashMap<String, Vec<chrono::DateTime<Utc>>>,
@@ -109,3 +197,111 @@ pub async fn task_rate_limit_middleware(
     
     Ok(next.run(request).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+    use crate::security::RateLimitConfig;
+
+    fn app(limiter: Arc<AdaptiveRateLimiter>) -> Router {
+        let plans = RateLimitPlans::new(&[("free".to_string(), 2), ("pro".to_string(), 5)], "free".to_string(), 10);
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route("/health/ready", get(|| async { "ok" }))
+            .route("/metrics", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(RequestRateLimits::new(limiter, plans)),
+                rate_limit_middleware,
+            ))
+    }
+
+    fn request(ip: &str, user: Option<(Uuid, Option<&str>)>) -> Request {
+        request_to("/", ip, user)
+    }
+
+    fn request_to(uri: &str, ip: &str, user: Option<(Uuid, Option<&str>)>) -> Request {
+        let mut request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ClientIp(ip.parse().unwrap()));
+        if let Some((id, plan)) = user {
+            request.extensions_mut().insert(UserId(id));
+            if let Some(plan) = plan {
+                request.extensions_mut().insert(UserPlan(plan.to_string()));
+            }
+        }
+        request
+    }
+
+    fn remaining(response: &Response) -> u32 {
+        response.headers()[X_RATELIMIT_REMAINING].to_str().unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_users_are_limited_by_plan_across_ips() {
+        let limiter = Arc::new(AdaptiveRateLimiter::default());
+        let user = Uuid::new_v4();
+
+        // No plan claim: the default (free) plan, whichever IP the user comes from
+        let first = app(Arc::clone(&limiter)).oneshot(request("10.0.0.1", Some((user, None)))).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()[X_RATELIMIT_LIMIT], "2");
+        assert_eq!(remaining(&first), 1);
+        let second = app(Arc::clone(&limiter)).oneshot(request("10.0.0.2", Some((user, None)))).await.unwrap();
+        assert_eq!(remaining(&second), 0);
+
+        let limited = app(Arc::clone(&limiter)).oneshot(request("10.0.0.3", Some((user, None)))).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(remaining(&limited), 0);
+        let retry_after: u64 = limited.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!(retry_after >= 1);
+
+        // Another user behind the same IP is unaffected, and a pro user gets the bigger budget
+        let pro = app(Arc::clone(&limiter)).oneshot(request("10.0.0.1", Some((Uuid::new_v4(), Some("pro"))))).await.unwrap();
+        assert_eq!(pro.status(), StatusCode::OK);
+        assert_eq!(remaining(&pro), 4);
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_requests_are_limited_by_ip() {
+        let limiter = Arc::new(AdaptiveRateLimiter::new(RateLimitConfig {
+            limit: 1,
+            window: Duration::from_secs(60),
+            burst_limit: 10,
+        }));
+
+        let first = app(Arc::clone(&limiter)).oneshot(request("10.0.0.1", None)).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(remaining(&first), 0);
+        assert_eq!(limiter.status("10.0.0.1").await.remaining, 0);
+
+        let limited = app(Arc::clone(&limiter)).oneshot(request("10.0.0.1", None)).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().contains_key(header::RETRY_AFTER));
+
+        let other_ip = app(Arc::clone(&limiter)).oneshot(request("10.0.0.2", None)).await.unwrap();
+        assert_eq!(other_ip.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_health_and_metrics_are_not_limited() {
+        let limiter = Arc::new(AdaptiveRateLimiter::new(RateLimitConfig {
+            limit: 1,
+            window: Duration::from_secs(60),
+            burst_limit: 10,
+        }));
+
+        let first = app(Arc::clone(&limiter)).oneshot(request("10.0.0.1", None)).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        for uri in ["/health/ready", "/metrics", "/health/ready"] {
+            let probe = app(Arc::clone(&limiter)).oneshot(request_to(uri, "10.0.0.1", None)).await.unwrap();
+            assert_eq!(probe.status(), StatusCode::OK, "{} was limited", uri);
+        }
+        // /healthz is an ordinary path, not a probe
+        assert!(!is_exempt("/healthz"));
+    }
+}
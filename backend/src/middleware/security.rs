@@ -3,12 +3,17 @@
  * Input validation, sanitization, CSRF protection, and security headers
  */
 use axum::{
-    extract::Request,
-    http::{HeaderMap, HeaderValue, StatusCode},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
     middleware::Next,
     response::Response,
 };
 use std::sync::Arc;
+use crate::config::Config;
+use crate::types::errors::{error_codes, ApiError};
+use super::client_ip::ViaTrustedProxy;
+use super::error_body::error_response;
+use super::request_id::get_request_id;
 use validator::{Validate, ValidationError};
 use serde::{Deserialize, Serialize};
 
@@ -20,41 +25,68 @@ const MAX_STRING_LENGTH: usize = 10000;
 const MAX_SKILL_NAME_LENGTH: usize = 255;
 const MAX_SESSION_ID_LENGTH: usize = 255;
 
+/// Headers added to every response, resolved once from config
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+    hsts: Option<HeaderValue>, // Only sent on HTTPS requests so plain-HTTP dev isn't pinned
+}
+
+impl SecurityHeaders {
+    pub fn from_config(config: &Config) -> Self {
+        let disabled = |name: &str| {
+            config.disabled_security_headers.iter().any(|d| d.eq_ignore_ascii_case(name))
+        };
+
+        // An empty value also turns a header off
+        let headers = [
+            ("x-content-type-options", "nosniff".to_string()),
+            ("x-frame-options", "DENY".to_string()),
+            ("x-xss-protection", "1; mode=block".to_string()),
+            ("content-security-policy", config.content_security_policy.clone()),
+            ("referrer-policy", config.referrer_policy.clone()),
+            ("permissions-policy", config.permissions_policy.clone()),
+        ]
+        .into_iter()
+        .filter(|(name, value)| !value.is_empty() && !disabled(name))
+        .filter_map(|(name, value)| Some((HeaderName::from_static(name), HeaderValue::from_str(&value).ok()?)))
+        .collect();
+
+        let hsts = (config.hsts_max_age_secs > 0 && !disabled("strict-transport-security"))
+            .then(|| format!("max-age={}; includeSubDomains", config.hsts_max_age_secs))
+            .and_then(|value| HeaderValue::from_str(&value).ok());
+
+        Self { headers, hsts }
+    }
+
+    pub fn apply(&self, headers: &mut HeaderMap, https: bool) {
+        for (name, value) in &self.headers {
+            headers.insert(name.clone(), value.clone());
+        }
+        if let (true, Some(hsts)) = (https, &self.hsts) {
+            headers.insert("strict-transport-security", hsts.clone());
+        }
+    }
+}
+
+/// Whether the client connected over TLS, directly or through a trusted proxy
+fn is_https(request: &Request) -> bool {
+    request.uri().scheme_str() == Some("https")
+        || request.extensions().get::<ViaTrustedProxy>().is_some() && request.headers()
+            .get("x-forwarded-proto")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|proto| proto.eq_ignore_ascii_case("https"))
+}
+
 /// Security headers middleware
 pub async fn security_headers_middleware(
+    State(security_headers): State<Arc<SecurityHeaders>>,
     request: Request,
     next: Next,
 ) -> Response {
+    let https = is_https(&request);
     let mut response = next.run(request).await;
-    
-    let headers = response.headers_mut();
-    
-    // Security headers
-    headers.insert(
-        "X-Content-Type-Options",
-        HeaderValue::from_static("nosniff"),
-    );
-    headers.insert(
-        "X-Frame-Options",
-        HeaderValue::from_static("DENY"),
-    );
-    headers.insert(
-        "X-XSS-Protection",
-        HeaderValue::from_static("1; mode=block"),
-    );
-    headers.insert(
-        "Strict-Transport-Security",
-        HeaderValue::from_static("max-age=31536000; includeSubDomains"),
-    );
-    headers.insert(
-        "Content-Security-Policy",
-        HeaderValue::from_static("default-src 'self'; script-src 'self' 'unsafe-inline'; style-src 'self' 'unsafe-inline'"),
-    );
-    headers.insert(
-        "Referrer-Policy",
-        HeaderValue::from_static("strict-origin-when-cross-origin"),
-    );
-    
+    security_headers.apply(response.headers_mut(), https);
     response
 }
 
@@ -180,20 +212,24 @@ pub fn validate_skill_name(name: &str) -> Result<String, ValidationError> {
 pub async fn validate_payload_size(
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Response {
     // Check Content-Length header
-    if let Some(content_length) = request.headers().get("content-length") {
-        if let Ok(length_str) = content_length.to_str() {
-            if let Ok(length) = length_str.parse::<usize>() {
-                if length > MAX_BODY_SIZE {
-                    tracing::warn!("Request body too large: {} bytes", length);
-                    return Err(StatusCode::PAYLOAD_TOO_LARGE);
-                }
-            }
-        }
+    let length = request.headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+
+    if let Some(length) = length.filter(|&length| length > MAX_BODY_SIZE) {
+        tracing::warn!("Request body too large: {} bytes", length);
+        let error = ApiError::new(
+            error_codes::PAYLOAD_TOO_LARGE.to_string(),
+            "Request body is too large".to_string(),
+        )
+        .with_details(format!("limit: {} bytes, received: {} bytes", MAX_BODY_SIZE, length));
+        return error_response(StatusCode::PAYLOAD_TOO_LARGE, error, get_request_id(&request));
     }
-    
-    Ok(next.run(request).await)
+
+    next.run(request).await
 }
 
 /// CSRF token validation (for state-changing operations)
@@ -212,3 +248,58 @@ pub fn validate_websocket_origin(origin: &str, allowed_origins: &[String]) -> bo
         origin == allowed || origin.starts_with(&format!("{}://", allowed))
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn test_app(config: &Config) -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(SecurityHeaders::from_config(config)),
+                security_headers_middleware,
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_are_applied() {
+        let mut config = Config::from_env().unwrap();
+        config.permissions_policy = "camera=()".to_string();
+        config.hsts_max_age_secs = 3600;
+        config.disabled_security_headers = vec!["X-Frame-Options".to_string()];
+
+        let response = test_app(&config)
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(headers["referrer-policy"], config.referrer_policy.as_str());
+        assert_eq!(headers["permissions-policy"], "camera=()");
+        assert!(headers.contains_key("content-security-policy"));
+        assert!(!headers.contains_key("x-frame-options"));
+        // Plain HTTP never gets HSTS
+        assert!(!headers.contains_key("strict-transport-security"));
+
+        let forwarded_https = || Request::builder()
+            .uri("/")
+            .header("x-forwarded-proto", "https")
+            .body(Body::empty())
+            .unwrap();
+
+        // A client claiming HTTPS itself is ignored
+        let response = test_app(&config).oneshot(forwarded_https()).await.unwrap();
+        assert!(!response.headers().contains_key("strict-transport-security"));
+
+        let mut request = forwarded_https();
+        request.extensions_mut().insert(ViaTrustedProxy);
+        let response = test_app(&config).oneshot(request).await.unwrap();
+        assert_eq!(
+            response.headers()["strict-transport-security"],
+            "max-age=3600; includeSubDomains"
+        );
+    }
+}
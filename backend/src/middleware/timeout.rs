@@ -0,0 +1,209 @@
+/**
+ * Request Timeout Middleware
+ *
+ * Bounds how long a handler may run before the server gives up on it.
+ * Durations are configured per path prefix (longest prefix wins) so that
+ * slow AI/codebase routes get more headroom than health or file routes.
+ * Prefixes match whole path segments, so `/health` covers `/health/ready`
+ * but not `/healthz`. WebSocket upgrades are always exempt, and further
+ * streaming routes can be exempted by prefix.
+ */
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::types::errors::{error_codes, ApiError};
+use super::error_body::error_response;
+use super::request_id::get_request_id;
+
+/// Resolved timeout policy for incoming requests
+#[derive(Debug, Clone)]
+pub struct RequestTimeouts {
+    default: Duration,
+    overrides: Vec<(String, Duration)>,
+    exempt: Vec<String>,
+}
+
+impl RequestTimeouts {
+    pub fn new(default: Duration, overrides: Vec<(String, Duration)>, exempt: Vec<String>) -> Self {
+        Self { default, overrides, exempt }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            Duration::from_secs(config.request_timeout_secs),
+            config.endpoint_timeouts
+                .iter()
+                .map(|(prefix, secs)| (prefix.clone(), Duration::from_secs(*secs)))
+                .collect(),
+            config.timeout_exempt_paths.clone(),
+        )
+    }
+
+    /// Timeout for a path, or None if the path is exempt
+    pub fn timeout_for(&self, path: &str) -> Option<Duration> {
+        if self.exempt.iter().any(|prefix| under_prefix(path, prefix)) {
+            return None;
+        }
+
+        self.overrides
+            .iter()
+            .filter(|(prefix, _)| under_prefix(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, duration)| *duration)
+            .or(Some(self.default))
+    }
+}
+
+/// Whether `path` is `prefix` itself or lies beneath it
+pub(crate) fn under_prefix(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'),
+        None => false,
+    }
+}
+
+/// A WebSocket handshake; the connection lives on well past the response head
+pub(crate) fn is_websocket_upgrade(request: &Request) -> bool {
+    request.headers()
+        .get(header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+}
+
+/// Abort requests that exceed their configured timeout with 504 Gateway Timeout
+///
+/// Only the time until the response head is produced is bounded; bodies that
+/// are already streaming are left alone.
+pub async fn request_timeout_middleware(
+    State(timeouts): State<Arc<RequestTimeouts>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let request_id = get_request_id(&request);
+
+    let Some(limit) = timeouts.timeout_for(&path).filter(|_| !is_websocket_upgrade(&request)) else {
+        return next.run(request).await;
+    };
+
+    match tokio::time::timeout(limit, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("Request to {} timed out after {}ms", path, limit.as_millis());
+            let error = ApiError::new(
+                error_codes::GATEWAY_TIMEOUT.to_string(),
+                format!("Request did not complete within {}ms", limit.as_millis()),
+            )
+            .with_details(format!("path: {}", path));
+            error_response(StatusCode::GATEWAY_TIMEOUT, error, request_id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn test_app(timeouts: RequestTimeouts) -> Router {
+        Router::new()
+            .route("/slow", get(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "done"
+            }))
+            .route("/stream/slow", get(|| async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                "streamed"
+            }))
+            .route("/fast", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(timeouts),
+                request_timeout_middleware,
+            ))
+    }
+
+    fn timeouts() -> RequestTimeouts {
+        RequestTimeouts::new(
+            Duration::from_secs(5),
+            vec![
+                ("/slow".to_string(), Duration::from_millis(50)),
+                ("/stream".to_string(), Duration::from_millis(10)),
+            ],
+            vec!["/stream".to_string()],
+        )
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let timeouts = RequestTimeouts::new(
+            Duration::from_secs(30),
+            vec![
+                ("/api/v1/codebase".to_string(), Duration::from_secs(180)),
+                ("/api/v1/codebase/search".to_string(), Duration::from_secs(20)),
+            ],
+            vec![],
+        );
+
+        assert_eq!(timeouts.timeout_for("/api/v1/codebase/review"), Some(Duration::from_secs(180)));
+        assert_eq!(timeouts.timeout_for("/api/v1/codebase/search"), Some(Duration::from_secs(20)));
+        assert_eq!(timeouts.timeout_for("/api/v1/models"), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_prefixes_match_whole_segments() {
+        let timeouts = RequestTimeouts::new(
+            Duration::from_secs(30),
+            vec![("/health".to_string(), Duration::from_secs(5))],
+            vec!["/api/v1/collaboration/ws".to_string()],
+        );
+
+        assert_eq!(timeouts.timeout_for("/health"), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.timeout_for("/health/ready"), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.timeout_for("/healthz"), Some(Duration::from_secs(30)));
+        assert_eq!(timeouts.timeout_for("/api/v1/collaboration/ws/abc"), None);
+        assert_eq!(timeouts.timeout_for("/api/v1/collaboration/wsx"), Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn test_handler_exceeding_timeout_returns_504() {
+        let response = test_app(timeouts())
+            .oneshot(Request::builder().uri("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.error.code, error_codes::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_fast_and_exempt_routes_complete() {
+        let response = test_app(timeouts())
+            .oneshot(Request::builder().uri("/fast").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = test_app(timeouts())
+            .oneshot(Request::builder().uri("/stream/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Upgrades are exempt whatever their path
+        let response = test_app(timeouts())
+            .oneshot(Request::builder().uri("/slow").header(header::UPGRADE, "websocket").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
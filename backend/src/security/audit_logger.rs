@@ -7,11 +7,18 @@
  * - Security violations
  * - Configuration changes
  * - Threat detection events
+ *
+ * Persisted events are queued to a background writer that inserts them in
+ * batches, so logging never waits on the database. When the queue is full
+ * the event is still kept in memory but not persisted.
  */
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use chrono::Utc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, RwLock};
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
+use sqlx::Row;
+use crate::database::Database;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditLog {
@@ -27,7 +34,7 @@ pub struct AuditLog {
     pub threat_level: ThreatLevel,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum AuditEventType {
     Authentication,
     Authorization,
@@ -54,9 +61,42 @@ pub enum ThreatLevel {
     Critical,
 }
 
+impl ThreatLevel {
+    const ALL: [ThreatLevel; 4] = [ThreatLevel::Low, ThreatLevel::Medium, ThreatLevel::High, ThreatLevel::Critical];
+}
+
+/// Filters for `AuditLogger::query`; newest matches first
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub event_type: Option<AuditEventType>,
+    pub min_threat_level: Option<ThreatLevel>, // This level and above
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+impl AuditQuery {
+    fn matches(&self, log: &AuditLog) -> bool {
+        self.event_type.as_ref().map_or(true, |t| &log.event_type == t)
+            && self.min_threat_level.as_ref().map_or(true, |l| &log.threat_level >= l)
+            && self.from.map_or(true, |from| log.timestamp >= from)
+            && self.to.map_or(true, |to| log.timestamp <= to)
+    }
+}
+
+/// Events waiting for the database writer
+const WRITE_QUEUE_CAPACITY: usize = 10_000;
+/// Most events inserted in one statement
+const WRITE_BATCH: usize = 200;
+/// How often persisted logs past retention are deleted
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
 pub struct AuditLogger {
-    logs: Arc<RwLock<Vec<AuditLog>>>,
+    logs: Arc<RwLock<Vec<AuditLog>>>, // Recent events, also the whole history without a database
     max_logs: usize,
+    database: Option<Arc<Database>>,
+    writer: Option<mpsc::Sender<AuditLog>>, // Queue to the batch writer when persisted
 }
 
 impl AuditLogger {
@@ -64,11 +104,31 @@ impl AuditLogger {
         Self {
             logs: Arc::new(RwLock::new(Vec::new())),
             max_logs,
+            database: None,
+            writer: None,
+        }
+    }
+
+    /// Also write every event to the `audit_logs` table, deleting rows older
+    /// than `retention_days` (0 keeps them forever)
+    pub fn with_database(mut self, database: Option<Arc<Database>>, retention_days: u32) -> Self {
+        if let Some(db) = &database {
+            let (tx, rx) = mpsc::channel(WRITE_QUEUE_CAPACITY);
+            tokio::spawn(write_batches(Arc::clone(db), rx, retention_days));
+            self.writer = Some(tx);
         }
+        self.database = database;
+        self
     }
 
     /// Log a security event
     pub async fn log(&self, event: AuditLog) {
+        if let Some(writer) = &self.writer {
+            if let Err(e) = writer.try_send(event.clone()) {
+                tracing::warn!("Audit log {} not persisted: {}", event.id, e);
+            }
+        }
+
         let mut logs = self.logs.write().await;
         logs.push(event);
         
@@ -78,6 +138,61 @@ impl AuditLogger {
         }
     }
 
+    /// Search the full history when persisted, otherwise the in-memory logs
+    pub async fn query(&self, query: &AuditQuery) -> anyhow::Result<Vec<AuditLog>> {
+        let Some(db) = &self.database else {
+            let logs = self.logs.read().await;
+            return Ok(logs.iter()
+                .rev()
+                .filter(|log| query.matches(log))
+                .skip(query.offset)
+                .take(query.limit)
+                .cloned()
+                .collect());
+        };
+
+        let threat_levels: Option<Vec<String>> = query.min_threat_level.as_ref().map(|min| {
+            ThreatLevel::ALL.iter().filter(|level| *level >= min).map(enum_name).collect()
+        });
+        let rows = sqlx::query(
+            "SELECT id, timestamp, event_type, user_id, ip_address,
+                    resource, action, result, details, threat_level
+             FROM audit_logs
+             WHERE ($1::TEXT IS NULL OR event_type = $1)
+               AND ($2::TEXT[] IS NULL OR threat_level = ANY($2))
+               AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+               AND ($4::TIMESTAMPTZ IS NULL OR timestamp <= $4)
+             ORDER BY timestamp DESC, id
+             LIMIT $5 OFFSET $6"
+        )
+        .bind(query.event_type.as_ref().map(enum_name))
+        .bind(threat_levels)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.limit as i64)
+        .bind(query.offset as i64)
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to query audit logs: {}", e))?;
+
+        rows.iter()
+            .map(|row| -> anyhow::Result<AuditLog> {
+                Ok(AuditLog {
+                    id: row.try_get("id")?,
+                    timestamp: row.try_get("timestamp")?,
+                    event_type: parse_enum(row.try_get("event_type")?)?,
+                    user_id: row.try_get("user_id")?,
+                    ip_address: row.try_get("ip_address")?,
+                    resource: row.try_get("resource")?,
+                    action: row.try_get("action")?,
+                    result: parse_enum(row.try_get("result")?)?,
+                    details: row.try_get("details")?,
+                    threat_level: parse_enum(row.try_get("threat_level")?)?,
+                })
+            })
+            .collect()
+    }
+
     /// Log authentication attempt
     pub async fn log_auth(&self, user_id: Option<String>, ip: Option<String>, success: bool) {
         self.log(AuditLog {
@@ -120,6 +235,18 @@ impl AuditLogger {
             .collect()
     }
 
+    /// Most recent blocked or violating events from one IP, newest first
+    pub async fn get_violations_for_ip(&self, ip: &str, limit: usize) -> Vec<AuditLog> {
+        let logs = self.logs.read().await;
+        logs.iter()
+            .rev()
+            .filter(|log| log.ip_address.as_deref() == Some(ip))
+            .filter(|log| matches!(log.event_type, AuditEventType::SecurityViolation) || matches!(log.result, AuditResult::Blocked))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
     /// Get logs by threat level
     pub async fn get_logs_by_threat(&self, threat_level: ThreatLevel) -> Vec<AuditLog> {
         let logs = self.logs.read().await;
@@ -135,3 +262,101 @@ impl Default for AuditLogger {
         Self::new(10000)
     }
 }
+
+/// Insert queued events in batches until every sender is dropped
+async fn write_batches(db: Arc<Database>, mut rx: mpsc::Receiver<AuditLog>, retention_days: u32) {
+    let mut last_prune: Option<Instant> = None;
+    let mut batch = Vec::with_capacity(WRITE_BATCH);
+    while rx.recv_many(&mut batch, WRITE_BATCH).await > 0 {
+        if let Err(e) = insert_batch(&db, &batch).await {
+            tracing::warn!("Failed to persist {} audit logs: {}", batch.len(), e);
+        }
+        batch.clear();
+
+        if retention_days > 0 && last_prune.map_or(true, |at| at.elapsed() >= PRUNE_INTERVAL) {
+            last_prune = Some(Instant::now());
+            if let Err(e) = sqlx::query("DELETE FROM audit_logs WHERE timestamp < NOW() - make_interval(days => $1)")
+                .bind(retention_days as i32)
+                .execute(db.pool())
+                .await
+            {
+                tracing::warn!("Failed to prune audit logs: {}", e);
+            }
+        }
+    }
+}
+
+async fn insert_batch(db: &Database, events: &[AuditLog]) -> Result<(), sqlx::Error> {
+    let mut query = sqlx::QueryBuilder::new(
+        "INSERT INTO audit_logs (
+            id, timestamp, event_type, user_id, ip_address,
+            resource, action, result, details, threat_level
+        ) "
+    );
+    query.push_values(events, |mut row, event| {
+        row.push_bind(&event.id)
+            .push_bind(event.timestamp)
+            .push_bind(enum_name(&event.event_type))
+            .push_bind(&event.user_id)
+            .push_bind(&event.ip_address)
+            .push_bind(&event.resource)
+            .push_bind(&event.action)
+            .push_bind(enum_name(&event.result))
+            .push_bind(&event.details)
+            .push_bind(enum_name(&event.threat_level));
+    });
+    query.build().execute(db.pool()).await?;
+    Ok(())
+}
+
+/// Stored form of the unit enums above: their serde name, e.g. "SecurityViolation"
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn parse_enum<T: serde::de::DeserializeOwned>(name: String) -> anyhow::Result<T> {
+    serde_json::from_value(serde_json::Value::String(name.clone()))
+        .map_err(|_| anyhow::anyhow!("Unknown audit log value '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_query_filters_and_pages_newest_first() {
+        let logger = AuditLogger::new(100);
+        let start = Utc::now();
+        logger.log_auth(Some("alice".to_string()), None, true).await;
+        for i in 0..3 {
+            logger.log_violation(format!("violation_{}", i), Some("10.0.0.1".to_string()), None).await;
+        }
+        logger.log_auth(Some("bob".to_string()), None, false).await;
+
+        let violations = AuditQuery {
+            event_type: Some(AuditEventType::SecurityViolation),
+            limit: 2,
+            ..Default::default()
+        };
+        let page = logger.query(&violations).await.unwrap();
+        let actions: Vec<_> = page.iter().map(|log| log.action.as_str()).collect();
+        assert_eq!(actions, ["violation_2", "violation_1"]);
+        let next = logger.query(&AuditQuery { offset: 2, ..violations }).await.unwrap();
+        assert_eq!(next.len(), 1);
+        assert_eq!(next[0].action, "violation_0");
+
+        // Minimum threat level: the failed login is Medium, the successful one Low
+        let medium_up = AuditQuery { min_threat_level: Some(ThreatLevel::Medium), limit: 10, ..Default::default() };
+        assert_eq!(logger.query(&medium_up).await.unwrap().len(), 4);
+
+        let future = AuditQuery { from: Some(Utc::now() + chrono::Duration::hours(1)), limit: 10, ..Default::default() };
+        assert!(logger.query(&future).await.unwrap().is_empty());
+        let since_start = AuditQuery { from: Some(start), to: Some(Utc::now()), limit: 10, ..Default::default() };
+        assert_eq!(logger.query(&since_start).await.unwrap().len(), 5);
+
+        assert_eq!(enum_name(&AuditEventType::SecurityViolation), "SecurityViolation");
+        assert_eq!(parse_enum::<ThreatLevel>("High".to_string()).unwrap(), ThreatLevel::High);
+    }
+}
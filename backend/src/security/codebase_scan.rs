@@ -0,0 +1,188 @@
+/**
+ * Codebase Security Scan
+ *
+ * Runs the vulnerability scanner over a set of files with bounded concurrency
+ * and folds the per-file results into one report: each finding appears once,
+ * listing every file it was seen in, ranked by severity.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use super::vulnerability_scanner::{Vulnerability, VulnerabilityScanner};
+
+/// Files read and scanned at once
+pub const SCAN_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct ScanTarget {
+    pub path: String,
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityScanReport {
+    pub files_scanned: usize,
+    /// Files that couldn't be read (deleted or moved since indexing)
+    pub files_skipped: Vec<String>,
+    pub findings: Vec<Vulnerability>,
+}
+
+/// Streamed while a scan runs
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScanProgress {
+    File {
+        path: String,
+        findings: usize,
+        completed: usize,
+        total: usize,
+    },
+    Done {
+        report: SecurityScanReport,
+    },
+}
+
+/// Keep only targets inside `scope` (a path prefix matched on component boundaries)
+pub fn scope_targets(targets: Vec<ScanTarget>, scope: Option<&str>) -> Vec<ScanTarget> {
+    let Some(scope) = scope.map(|s| s.trim_end_matches('/')).filter(|s| !s.is_empty()) else {
+        return targets;
+    };
+    targets.into_iter()
+        .filter(|t| t.path == scope || t.path.starts_with(&format!("{}/", scope)))
+        .collect()
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity.to_uppercase().as_str() {
+        "CRITICAL" => 0,
+        "HIGH" => 1,
+        "MEDIUM" => 2,
+        "LOW" => 3,
+        _ => 4,
+    }
+}
+
+/// Scan every target; per-file progress is sent to `progress` if given
+pub async fn scan_codebase(
+    scanner: Arc<VulnerabilityScanner>,
+    targets: Vec<ScanTarget>,
+    progress: Option<mpsc::UnboundedSender<ScanProgress>>,
+) -> SecurityScanReport {
+    let total = targets.len();
+    let mut results = stream::iter(targets)
+        .map(|target| {
+            let scanner = Arc::clone(&scanner);
+            async move {
+                let findings = tokio::fs::read_to_string(&target.path)
+                    .await
+                    .ok()
+                    .map(|code| scanner.scan_file(&target.path, &code, &target.language));
+                (target.path, findings)
+            }
+        })
+        .buffer_unordered(SCAN_CONCURRENCY);
+
+    // (finding id, description, evidence) -> finding with every affected file
+    let mut merged: HashMap<(String, String, Option<String>), Vulnerability> = HashMap::new();
+    let mut files_skipped = Vec::new();
+    let mut completed = 0;
+
+    while let Some((path, findings)) = results.next().await {
+        completed += 1;
+        let Some(findings) = findings else {
+            tracing::debug!("Security scan skipped unreadable file {}", path);
+            files_skipped.push(path);
+            continue;
+        };
+
+        if let Some(progress) = &progress {
+            let _ = progress.send(ScanProgress::File {
+                path: path.clone(),
+                findings: findings.len(),
+                completed,
+                total,
+            });
+        }
+
+        for finding in findings {
+            let key = (finding.id.clone(), finding.description.clone(), finding.evidence.clone());
+            let entry = merged.entry(key)
+                .or_insert_with(|| Vulnerability { affected_files: Vec::new(), ..finding });
+            if !entry.affected_files.contains(&path) {
+                entry.affected_files.push(path.clone());
+            }
+        }
+    }
+
+    let mut findings: Vec<Vulnerability> = merged.into_values().collect();
+    for finding in &mut findings {
+        finding.affected_files.sort();
+    }
+    findings.sort_by(|a, b| {
+        severity_rank(&a.severity).cmp(&severity_rank(&b.severity))
+            .then(b.affected_files.len().cmp(&a.affected_files.len()))
+            .then(a.id.cmp(&b.id))
+    });
+    files_skipped.sort();
+
+    let report = SecurityScanReport {
+        files_scanned: total - files_skipped.len(),
+        files_skipped,
+        findings,
+    };
+    if let Some(progress) = &progress {
+        let _ = progress.send(ScanProgress::Done { report: report.clone() });
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_planted_vulnerability_is_reported_once() {
+        let root = std::env::temp_dir().join(format!("bloop-scan-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src/db")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        let write = |rel: &str, content: &str| {
+            let path = root.join(rel);
+            std::fs::write(&path, content).unwrap();
+            ScanTarget { path: path.to_string_lossy().into_owned(), language: "python".to_string() }
+        };
+
+        let targets = vec![
+            write("src/db/users.py", "def find(name):\n    return db.execute(\"SELECT * FROM users WHERE name = '\" + name + \"'\")\n"),
+            write("src/db/orders.py", "def find(order_id):\n    return db.execute(\"SELECT * FROM orders WHERE id = \" + order_id)\n"),
+            write("src/app.py", "def main():\n    print('hello')\n"),
+            write("docs/example.py", "cursor.execute(\"DELETE FROM t WHERE id = \" + x)\n"),
+        ];
+        let scope = root.join("src").to_string_lossy().into_owned();
+        let targets = scope_targets(targets, Some(&scope));
+        assert_eq!(targets.len(), 3);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let report = scan_codebase(Arc::new(VulnerabilityScanner::new()), targets, Some(tx)).await;
+
+        assert_eq!(report.files_scanned, 3);
+        let injections: Vec<_> = report.findings.iter().filter(|f| f.id == "SQL_INJECTION").collect();
+        assert_eq!(injections.len(), 1);
+        assert_eq!(injections[0].affected_files.len(), 2);
+        assert!(injections[0].affected_files.iter().all(|f| f.contains("src/db/")));
+        assert_eq!(report.findings[0].severity, "CRITICAL");
+
+        let mut file_events = 0;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                ScanProgress::File { .. } => file_events += 1,
+                ScanProgress::Done { report } => assert_eq!(report.files_scanned, 3),
+            }
+        }
+        assert_eq!(file_events, 3);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
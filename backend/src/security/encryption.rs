@@ -1,62 +1,200 @@
 /**
  * Encryption Service
- * 
+ *
  * 10x encryption enhancements:
  * - AES-256 encryption for sensitive data
  * - Key rotation
  * - Secure key storage
  * - Data encryption at rest
  * - Transport encryption
+ *
+ * Ciphertext is `[key id][nonce][AES-256-GCM output]`. The key id names the
+ * key that encrypted it, so after a rotation data written under a retired key
+ * still decrypts, and can be moved to the active key with `reencrypt`.
  */
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+struct KeyRing {
+    active: u8,
+    keys: HashMap<u8, Aes256Gcm>, // Active key plus retired ones kept for decryption
+    rotated_at: Instant,
+}
 
 pub struct EncryptionService {
-    key_rotation_interval: std::time::Duration,
-    last_key_rotation: Arc<RwLock<std::time::Instant>>,
+    key_rotation_interval: Duration,
+    ring: RwLock<KeyRing>,
 }
 
 impl EncryptionService {
-    pub fn new(_master_key: &[u8; 32]) -> Self {
+    /// Start with `master_key` as the active key, id 0
+    pub fn new(master_key: &[u8; 32]) -> Self {
         Self {
-            key_rotation_interval: std::time::Duration::from_secs(86400 * 7), // 7 days
-            last_key_rotation: Arc::new(RwLock::new(std::time::Instant::now())),
+            key_rotation_interval: Duration::from_secs(86400 * 7), // 7 days
+            ring: RwLock::new(KeyRing {
+                active: 0,
+                keys: HashMap::from([(0, cipher(master_key))]),
+                rotated_at: Instant::now(),
+            }),
         }
     }
 
-    /// Encrypt sensitive data (simplified for now - would use AES-256 in production)
+    /// Id of the key new data is encrypted with
+    pub fn active_key_id(&self) -> u8 {
+        self.ring.read().unwrap().active
+    }
+
+    /// Make `new_key` the active key; the previous one is kept for decryption
+    ///
+    /// Returns the new key's id. Fails once all 256 ids are in use; remove
+    /// retired keys whose data has been re-encrypted to free them.
+    pub fn rotate_key(&self, new_key: &[u8; 32]) -> Result<u8, String> {
+        let mut ring = self.ring.write().unwrap();
+        let id = ring.active.wrapping_add(1);
+        if ring.keys.contains_key(&id) {
+            return Err(format!("Key id {} is still held by a retired key", id));
+        }
+        ring.keys.insert(id, cipher(new_key));
+        ring.active = id;
+        ring.rotated_at = Instant::now();
+        tracing::info!("Encryption key rotated to version {}", id);
+        Ok(id)
+    }
+
+    /// Register a retired key (e.g. loaded from secure storage after a restart)
+    pub fn add_retired_key(&self, key_id: u8, key: &[u8; 32]) -> Result<(), String> {
+        let mut ring = self.ring.write().unwrap();
+        if ring.keys.contains_key(&key_id) {
+            return Err(format!("Key id {} is already in use", key_id));
+        }
+        ring.keys.insert(key_id, cipher(key));
+        Ok(())
+    }
+
+    /// Forget a retired key once nothing encrypted with it remains
+    ///
+    /// The active key can't be removed. Returns whether a key was removed.
+    pub fn remove_retired_key(&self, key_id: u8) -> bool {
+        let mut ring = self.ring.write().unwrap();
+        key_id != ring.active && ring.keys.remove(&key_id).is_some()
+    }
+
+    /// Encrypt sensitive data with the active key (AES-256-GCM, random nonce)
     pub fn encrypt(&self, plaintext: &str) -> Result<Vec<u8>, String> {
-        // In production, use AES-256-GCM encryption
-        // For now, return base64 encoded (NOT secure - placeholder)
-        Ok(base64::encode(plaintext.as_bytes()).into_bytes())
+        let ring = self.ring.read().unwrap();
+        let cipher = &ring.keys[&ring.active];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let sealed = cipher.encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| "Encryption failed".to_string())?;
+
+        let mut ciphertext = Vec::with_capacity(1 + NONCE_LEN + sealed.len());
+        ciphertext.push(ring.active);
+        ciphertext.extend_from_slice(&nonce);
+        ciphertext.extend_from_slice(&sealed);
+        Ok(ciphertext)
     }
 
-    /// Decrypt sensitive data
+    /// Decrypt sensitive data with whichever key encrypted it
     pub fn decrypt(&self, ciphertext: &[u8]) -> Result<String, String> {
-        // In production, use AES-256-GCM decryption
-        // For now, decode base64 (NOT secure - placeholder)
-        let decoded = base64::decode(ciphertext)
-            .map_err(|e| format!("Decode failed: {}", e))?;
-        String::from_utf8(decoded)
+        if ciphertext.len() < 1 + NONCE_LEN + TAG_LEN {
+            return Err("Ciphertext is too short".to_string());
+        }
+        let (key_id, rest) = (ciphertext[0], &ciphertext[1..]);
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+        let ring = self.ring.read().unwrap();
+        let cipher = ring.keys.get(&key_id)
+            .ok_or_else(|| format!("Unknown encryption key version {}", key_id))?;
+        let plaintext = cipher.decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| "Decryption failed: wrong key or tampered data".to_string())?;
+        String::from_utf8(plaintext)
             .map_err(|e| format!("Invalid UTF-8: {}", e))
     }
 
+    /// Key id a ciphertext was encrypted with
+    pub fn key_id_of(ciphertext: &[u8]) -> Option<u8> {
+        ciphertext.first().copied()
+    }
+
+    /// Move data to the active key; already-current data is returned unchanged
+    pub fn reencrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        if Self::key_id_of(ciphertext) == Some(self.active_key_id()) {
+            return Ok(ciphertext.to_vec());
+        }
+        let plaintext = self.decrypt(ciphertext)?;
+        self.encrypt(&plaintext)
+    }
+
     /// Encrypt API keys and secrets
     pub fn encrypt_secret(&self, secret: &str) -> Result<String, String> {
         let encrypted = self.encrypt(secret)?;
-        Ok(base64::encode(encrypted))
+        Ok(STANDARD.encode(encrypted))
     }
 
     /// Decrypt API keys and secrets
     pub fn decrypt_secret(&self, encrypted_secret: &str) -> Result<String, String> {
-        let decoded = base64::decode(encrypted_secret)
+        let decoded = STANDARD.decode(encrypted_secret)
             .map_err(|e| format!("Base64 decode failed: {}", e))?;
         self.decrypt(&decoded)
     }
 
+    /// `reencrypt` for values produced by `encrypt_secret`
+    pub fn reencrypt_secret(&self, encrypted_secret: &str) -> Result<String, String> {
+        let decoded = STANDARD.decode(encrypted_secret)
+            .map_err(|e| format!("Base64 decode failed: {}", e))?;
+        Ok(STANDARD.encode(self.reencrypt(&decoded)?))
+    }
+
     /// Check if key rotation is needed
     pub async fn check_key_rotation(&self) -> bool {
-        let last_rotation = self.last_key_rotation.read().await;
-        last_rotation.elapsed() >= self.key_rotation_interval
+        self.ring.read().unwrap().rotated_at.elapsed() >= self.key_rotation_interval
+    }
+}
+
+fn cipher(key: &[u8; 32]) -> Aes256Gcm {
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_data_decrypts_after_rotation_and_can_be_migrated() {
+        let service = EncryptionService::new(&[1u8; 32]);
+        let old = service.encrypt("sk-live-original").unwrap();
+        let old_secret = service.encrypt_secret("db-password").unwrap();
+        assert_eq!(EncryptionService::key_id_of(&old), Some(0));
+
+        assert_eq!(service.rotate_key(&[2u8; 32]).unwrap(), 1);
+        let new = service.encrypt("sk-live-original").unwrap();
+        assert_eq!(EncryptionService::key_id_of(&new), Some(1));
+
+        // Both generations decrypt
+        assert_eq!(service.decrypt(&old).unwrap(), "sk-live-original");
+        assert_eq!(service.decrypt(&new).unwrap(), "sk-live-original");
+        assert_eq!(service.decrypt_secret(&old_secret).unwrap(), "db-password");
+
+        // Migrate, then the retired key can go
+        let migrated = service.reencrypt(&old).unwrap();
+        assert_eq!(EncryptionService::key_id_of(&migrated), Some(1));
+        let migrated_secret = service.reencrypt_secret(&old_secret).unwrap();
+        assert!(!service.remove_retired_key(1), "active key must stay");
+        assert!(service.remove_retired_key(0));
+        assert!(service.decrypt(&old).unwrap_err().contains("Unknown encryption key version 0"));
+        assert_eq!(service.decrypt(&migrated).unwrap(), "sk-live-original");
+        assert_eq!(service.decrypt_secret(&migrated_secret).unwrap(), "db-password");
+
+        // Tampering is detected
+        let mut tampered = new.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(service.decrypt(&tampered).is_err());
     }
 }
@@ -15,10 +15,14 @@ pub mod vulnerability_scanner;
 pub mod audit_logger;
 pub mod threat_detection;
 pub mod rate_limiter;
+pub mod secret_redaction;
+pub mod codebase_scan;
 
-pub use validation::{AdvancedValidator, ValidationResult, Threat, ThreatType, Severity};
+pub use validation::{AdvancedValidator, InputType, ValidationResult, Threat, ThreatType, Severity, is_non_public_ip, resolve_public_host, validate_outbound_url};
 pub use encryption::EncryptionService;
-pub use vulnerability_scanner::{VulnerabilityScanner, Vulnerability};
-pub use audit_logger::{AuditLogger, AuditLog, AuditEventType, AuditResult, ThreatLevel};
+pub use vulnerability_scanner::{VulnerabilityScanner, Vulnerability, SecretAllowlist};
+pub use audit_logger::{AuditLogger, AuditLog, AuditEventType, AuditQuery, AuditResult, ThreatLevel};
 pub use threat_detection::{ThreatDetector, ThreatAnalysis, ThreatEvent, ThreatType as ThreatEventType, ThreatSeverity};
-pub use rate_limiter::{AdaptiveRateLimiter, RateLimitResult, RateLimitConfig};
+pub use rate_limiter::{AdaptiveRateLimiter, RateLimitResult, RateLimitConfig, RateLimitPlans, RateLimitStatus, RateLimitTier};
+pub use secret_redaction::{SecretRedactor, RedactionMap};
+pub use codebase_scan::{scan_codebase, ScanProgress, ScanTarget, SecurityScanReport};
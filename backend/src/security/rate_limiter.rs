@@ -9,12 +9,16 @@
  */
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use serde::Serialize;
+use crate::config::Config;
 
 pub struct AdaptiveRateLimiter {
     limits: Arc<RwLock<HashMap<String, RateLimitInfo>>>,
     default_limit: RateLimitConfig,
+    limit: AtomicU32, // Requests per window; starts at default_limit.limit, changed on config reload
 }
 
 #[derive(Debug, Clone)]
@@ -22,8 +26,10 @@ struct RateLimitInfo {
     requests: Vec<Instant>,
     limit: u32,
     window: Duration,
+    burst_limit: u32,
     blocked_until: Option<Instant>,
     violation_count: u32,
+    plan_limited: bool, // Limits come from the caller's plan, not the reloadable default
 }
 
 #[derive(Debug, Clone)]
@@ -37,28 +43,48 @@ impl AdaptiveRateLimiter {
     pub fn new(default_limit: RateLimitConfig) -> Self {
         Self {
             limits: Arc::new(RwLock::new(HashMap::new())),
+            limit: AtomicU32::new(default_limit.limit),
             default_limit,
         }
     }
 
     /// Check if request is allowed
     pub async fn check(&self, identifier: &str) -> RateLimitResult {
+        self.check_with(identifier, None).await
+    }
+
+    /// `check` against specific limits (e.g. the caller's plan) instead of the default
+    pub async fn check_with_config(&self, identifier: &str, config: &RateLimitConfig) -> RateLimitResult {
+        self.check_with(identifier, Some(config)).await
+    }
+
+    async fn check_with(&self, identifier: &str, config: Option<&RateLimitConfig>) -> RateLimitResult {
         let mut limits = self.limits.write().await;
         
         let info = limits.entry(identifier.to_string())
             .or_insert_with(|| RateLimitInfo {
                 requests: Vec::new(),
-                limit: self.default_limit.limit,
+                limit: self.limit.load(Ordering::Relaxed),
                 window: self.default_limit.window,
+                burst_limit: self.default_limit.burst_limit,
                 blocked_until: None,
                 violation_count: 0,
+                plan_limited: false,
             });
+        // A caller's plan can change between requests (e.g. an upgrade), so apply it every time
+        if let Some(config) = config {
+            info.limit = config.limit;
+            info.window = config.window;
+            info.burst_limit = config.burst_limit;
+            info.plan_limited = true;
+        }
 
         // Check if currently blocked
         if let Some(blocked_until) = info.blocked_until {
             if Instant::now() < blocked_until {
                 return RateLimitResult {
                     allowed: false,
+                    limit: info.limit,
                     remaining: 0,
                     reset_at: blocked_until,
                     reason: Some("Rate limit exceeded - temporarily blocked".to_string()),
@@ -83,6 +109,7 @@ impl AdaptiveRateLimiter {
 
             return RateLimitResult {
                 allowed: false,
+                limit: info.limit,
                 remaining: 0,
                 reset_at: info.blocked_until.unwrap(),
                 reason: Some(format!("Rate limit exceeded ({} violations)", info.violation_count)),
@@ -94,13 +121,14 @@ impl AdaptiveRateLimiter {
             .filter(|&time| now.duration_since(*time) < Duration::from_secs(1))
             .collect();
 
-        if recent_requests.len() >= self.default_limit.burst_limit as usize {
+        if recent_requests.len() >= info.burst_limit as usize {
             info.violation_count += 1;
             let block_duration = Duration::from_secs(30 * info.violation_count.min(5));
             info.blocked_until = Some(now + block_duration);
 
             return RateLimitResult {
                 allowed: false,
+                limit: info.limit,
                 remaining: 0,
                 reset_at: info.blocked_until.unwrap(),
                 reason: Some("Burst limit exceeded".to_string()),
@@ -113,12 +141,66 @@ impl AdaptiveRateLimiter {
 
         RateLimitResult {
             allowed: true,
+            limit: info.limit,
             remaining,
             reset_at: now + info.window,
             reason: None,
         }
     }
 
+    /// Change the per-window limit for new and already tracked identifiers
+    ///
+    /// Identifiers limited by a plan keep their plan's limit.
+    pub async fn set_limit(&self, limit: u32) {
+        self.limit.store(limit, Ordering::Relaxed);
+        for info in self.limits.write().await.values_mut().filter(|info| !info.plan_limited) {
+            info.limit = limit;
+        }
+    }
+
+    /// Current bucket state for an identifier, without counting a request
+    pub async fn status(&self, identifier: &str) -> RateLimitStatus {
+        let limits = self.limits.read().await;
+        let now = Instant::now();
+        let limit = self.limit.load(Ordering::Relaxed);
+
+        let Some(info) = limits.get(identifier) else {
+            return RateLimitStatus {
+                limit,
+                remaining: limit,
+                window_secs: self.default_limit.window.as_secs(),
+                reset_in_secs: 0,
+                blocked: false,
+                violation_count: 0,
+                tier: RateLimitTier::Normal,
+            };
+        };
+
+        let in_window: Vec<&Instant> = info.requests.iter()
+            .filter(|&&time| now.duration_since(time) < info.window)
+            .collect();
+        let blocked_for = info.blocked_until
+            .filter(|&until| until > now)
+            .map(|until| until - now);
+        // The window frees up once its oldest request ages out
+        let reset_in = blocked_for.unwrap_or_else(|| {
+            in_window.iter()
+                .min()
+                .map(|&&oldest| info.window.saturating_sub(now.duration_since(oldest)))
+                .unwrap_or_default()
+        });
+
+        RateLimitStatus {
+            limit: info.limit,
+            remaining: if blocked_for.is_some() { 0 } else { info.limit.saturating_sub(in_window.len() as u32) },
+            window_secs: info.window.as_secs(),
+            reset_in_secs: reset_in.as_secs_f64().ceil() as u64,
+            blocked: blocked_for.is_some(),
+            violation_count: info.violation_count,
+            tier: if info.violation_count == 0 { RateLimitTier::Normal } else { RateLimitTier::Penalized },
+        }
+    }
+
     /// Reset rate limit for identifier (for testing/admin)
     pub async fn reset(&self, identifier: &str) {
         let mut limits = self.limits.write().await;
@@ -129,11 +211,65 @@ impl AdaptiveRateLimiter {
 #[derive(Debug, Clone)]
 pub struct RateLimitResult {
     pub allowed: bool,
+    pub limit: u32,
     pub remaining: u32,
     pub reset_at: Instant,
     pub reason: Option<String>,
 }
 
+/// Snapshot of an identifier's bucket, for diagnostics
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    pub window_secs: u64,
+    pub reset_in_secs: u64,
+    pub blocked: bool,
+    pub violation_count: u32,
+    pub tier: RateLimitTier,
+}
+
+/// Penalized identifiers get longer blocks on each further violation
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitTier {
+    Normal,
+    Penalized,
+}
+
+/// Limits for authenticated users by plan (the token's `plan` claim)
+#[derive(Debug, Clone)]
+pub struct RateLimitPlans {
+    plans: HashMap<String, RateLimitConfig>,
+    default_plan: String, // For tokens that name no plan or an unknown one
+}
+
+impl RateLimitPlans {
+    /// Burst allowance scales with the plan: a 30th of the per-minute limit, never below `min_burst`
+    pub fn new(per_minute: &[(String, u32)], default_plan: String, min_burst: u32) -> Self {
+        let plans = per_minute.iter()
+            .map(|(plan, limit)| {
+                (plan.clone(), RateLimitConfig {
+                    limit: *limit,
+                    window: Duration::from_secs(60),
+                    burst_limit: (limit / 30).max(min_burst),
+                })
+            })
+            .collect();
+        Self { plans, default_plan }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.rate_limit_plans, config.rate_limit_default_plan.clone(), 10)
+    }
+
+    /// Limits for a user on `plan`; None means the default (anonymous) limits apply
+    pub fn config_for(&self, plan: Option<&str>) -> Option<&RateLimitConfig> {
+        plan.and_then(|plan| self.plans.get(plan))
+            .or_else(|| self.plans.get(&self.default_plan))
+    }
+}
+
 impl Default for AdaptiveRateLimiter {
     fn default() -> Self {
         Self::new(RateLimitConfig {
@@ -11,6 +11,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use chrono::Utc;
+use serde::Serialize;
 
 pub struct ThreatDetector {
     behavior_profiles: Arc<RwLock<HashMap<String, BehaviorProfile>>>,
@@ -39,7 +40,7 @@ struct NormalBehavior {
     typical_time_range: (u8, u8),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ThreatEvent {
     pub id: String,
     pub timestamp: chrono::DateTime<Utc>,
@@ -50,7 +51,7 @@ pub struct ThreatEvent {
     pub action_taken: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum ThreatType {
     BruteForce,
     DDoS,
@@ -59,7 +60,7 @@ pub enum ThreatType {
     MaliciousRequest,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize)]
 pub enum ThreatSeverity {
     Low,
     Medium,
@@ -143,6 +144,17 @@ impl ThreatDetector {
             .cloned()
             .collect()
     }
+
+    /// Most recent threat events raised for one source, newest first
+    pub async fn get_threats_for_source(&self, source: &str, limit: usize) -> Vec<ThreatEvent> {
+        let history = self.threat_history.read().await;
+        history.iter()
+            .rev()
+            .filter(|event| event.source == source)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
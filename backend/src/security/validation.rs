@@ -10,6 +10,7 @@
  * - Malicious pattern detection
  */
 use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
 use regex::Regex;
 
 fn create_regex(pattern: &str) -> Regex {
@@ -17,8 +18,9 @@ fn create_regex(pattern: &str) -> Regex {
 }
 
 pub struct AdvancedValidator {
-    sql_injection_patterns: Vec<Regex>,
+    sql_injection_patterns: Vec<Regex>, // Only applied to input that may reach a query
     xss_patterns: Vec<Regex>,
+    shell_metacharacters: Regex, // Ordinary syntax in code, so skipped for InputType::Code
     command_injection_patterns: Vec<Regex>,
     malicious_patterns: Vec<Regex>,
     dangerous_functions: HashSet<String>,
@@ -29,6 +31,7 @@ impl AdvancedValidator {
         let mut validator = Self {
             sql_injection_patterns: Vec::new(),
             xss_patterns: Vec::new(),
+            shell_metacharacters: create_regex(r"[;&|`$(){}]"),
             command_injection_patterns: Vec::new(),
             malicious_patterns: Vec::new(),
             dangerous_functions: HashSet::new(),
@@ -39,10 +42,11 @@ impl AdvancedValidator {
     }
 
     fn init_patterns(&mut self) {
-        // SQL Injection patterns
-        self.sql_injection_patterns.push(create_regex(r"(?i)(union|select|insert|update|delete|drop|create|alter|exec|execute).*from"));
-        self.sql_injection_patterns.push(create_regex(r"(?i)(or|and).*\d+\s*=\s*\d+"));
-        self.sql_injection_patterns.push(create_regex(r"(?i)(;|--|\#|\/\*|\*\/)"));
+        // SQL Injection patterns. Bare `;`, `--` and `/*` are everywhere in code
+        // and prose, so metacharacters only count next to a SQL keyword.
+        self.sql_injection_patterns.push(create_regex(r"(?i)\b(union|select|insert|update|delete|drop|create|alter|exec|execute)\b.*\bfrom\b"));
+        self.sql_injection_patterns.push(create_regex(r"(?i)\b(or|and)\s+('[^']*'|\d+)\s*=\s*('[^']*'|\d+)"));
+        self.sql_injection_patterns.push(create_regex(r"(?i)(;|--|#|/\*)\s*(select|union|insert|update|delete|drop|create|alter|truncate|exec|execute|shutdown)\b"));
         
         // XSS patterns
         self.xss_patterns.push(create_regex(r"(?i)<script[^>]*>"));
@@ -51,7 +55,6 @@ impl AdvancedValidator {
         self.xss_patterns.push(create_regex(r"(?i)<iframe[^>]*>"));
         
         // Command injection patterns
-        self.command_injection_patterns.push(create_regex(r"[;&|`$(){}]"));
         self.command_injection_patterns.push(create_regex(r"(?i)(exec|system|shell_exec|passthru|proc_open)"));
         
         // Malicious patterns
@@ -74,8 +77,9 @@ impl AdvancedValidator {
         let mut threats = Vec::new();
         let mut sanitized = input.to_string();
 
-        // Check SQL injection
-        for pattern in &self.sql_injection_patterns {
+        // Check SQL injection; code, paths and URLs never reach a query as-is
+        let may_reach_query = matches!(input_type, InputType::Text | InputType::Json);
+        for pattern in self.sql_injection_patterns.iter().filter(|_| may_reach_query) {
             if pattern.is_match(input) {
                 threats.push(Threat {
                     threat_type: ThreatType::SqlInjection,
@@ -101,6 +105,16 @@ impl AdvancedValidator {
         }
 
         // Check command injection
+        if input_type != InputType::Code {
+            if let Some(m) = self.shell_metacharacters.find(input) {
+                threats.push(Threat {
+                    threat_type: ThreatType::CommandInjection,
+                    severity: Severity::Critical,
+                    description: "Potential command injection detected".to_string(),
+                    location: m.start(),
+                });
+            }
+        }
         for pattern in &self.command_injection_patterns {
             if pattern.is_match(input) {
                 threats.push(Threat {
@@ -160,6 +174,27 @@ impl AdvancedValidator {
         }
     }
 
+    /// Make model output safe to drop into an HTML context
+    ///
+    /// Every `&`, `<`, `>`, `"` and `'` is entity-escaped, code spans included:
+    /// markup can't be told apart from a code sample reliably (backticks may be
+    /// unbalanced, attributes and entities vary endlessly), and escaped text
+    /// still renders as the same characters.
+    pub fn sanitize_output(&self, text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#x27;"),
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
     /// Validate file path
     pub fn validate_file_path(&self, path: &str) -> bool {
         // Check for path traversal
@@ -180,6 +215,16 @@ impl AdvancedValidator {
         true
     }
 
+    /// Check a URL the server will call out to (e.g. a task callback) against SSRF
+    ///
+    /// Only http(s) without embedded credentials is accepted. Unless
+    /// `allow_private` is set, localhost names and loopback, private,
+    /// link-local and other non-public IP literals are refused. Hostnames are
+    /// checked again after DNS resolution at delivery time.
+    pub fn validate_callback_url(&self, url: &str, allow_private: bool) -> Result<reqwest::Url, String> {
+        validate_outbound_url(url, allow_private)
+    }
+
     /// Validate code for security issues
     pub fn validate_code(&self, code: &str, language: &str) -> ValidationResult {
         let mut threats = Vec::new();
@@ -237,7 +282,80 @@ impl AdvancedValidator {
     }
 }
 
-#[derive(Debug, Clone)]
+/// The checks behind `AdvancedValidator::validate_callback_url`, for any URL the server fetches
+pub fn validate_outbound_url(url: &str, allow_private: bool) -> Result<reqwest::Url, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme '{}'; use http or https", parsed.scheme()));
+    }
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err("URLs with embedded credentials are not allowed".to_string());
+    }
+    let host = parsed.host_str().ok_or("URL has no host")?;
+    if allow_private {
+        return Ok(parsed);
+    }
+
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+    if host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local") || host.ends_with(".internal") {
+        return Err(format!("Host '{}' is not publicly reachable", host));
+    }
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if is_non_public_ip(ip) {
+            return Err(format!("Address {} is not publicly reachable", ip));
+        }
+    }
+    Ok(parsed)
+}
+
+/// Resolve the host of an outbound URL, refusing it if any address is non-public
+///
+/// Returns the host and the address to connect to. Pin that address with
+/// `ClientBuilder::resolve` so a second lookup at connect time (or on a
+/// retry) can't send the request somewhere else.
+pub async fn resolve_public_host(url: &reqwest::Url) -> Result<(String, SocketAddr), String> {
+    let host = url.host_str().ok_or("URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addresses = tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+        .await
+        .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+        .collect::<Vec<_>>();
+    if let Some(address) = addresses.iter().find(|address| is_non_public_ip(address.ip())) {
+        return Err(format!("{} resolves to non-public address {}", host, address.ip()));
+    }
+    let address = addresses.first().copied().ok_or_else(|| format!("{} did not resolve", host))?;
+    Ok((host.to_string(), address))
+}
+
+/// Loopback, private, link-local, unspecified, multicast and similar ranges
+pub fn is_non_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || v4.octets()[0] == 0
+                || (v4.octets()[0] == 100 && (64..128).contains(&v4.octets()[1])) // Carrier-grade NAT
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_non_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00 // Unique local
+                || (first & 0xffc0) == 0xfe80 // Link-local
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputType {
     Text,
     Code,
@@ -285,3 +403,73 @@ impl Default for AdvancedValidator {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sql_threats(result: &ValidationResult) -> usize {
+        result.threats.iter().filter(|t| matches!(t.threat_type, ThreatType::SqlInjection)).count()
+    }
+
+    #[test]
+    fn test_sql_injection_checks_are_context_aware() {
+        let validator = AdvancedValidator::new();
+
+        let rust = "fn total(items: &[u32]) -> u32 {\n    // Sum every item; -- empty slices give 0\n    let mut sum = 0;\n    for item in items {\n        sum += item; /* no overflow check */\n    }\n    sum\n}";
+        let result = validator.validate_input(rust, InputType::Code);
+        assert!(result.is_valid, "unexpected threats: {:?}", result.threats);
+
+        // Prose with semicolons and dashes is not SQL injection either
+        let prose = "Renamed the helper; the old name -- and its /* comment */ -- is gone";
+        assert_eq!(sql_threats(&validator.validate_input(prose, InputType::Text)), 0);
+
+        let injection = "admin' OR 1=1 --";
+        assert!(sql_threats(&validator.validate_input(injection, InputType::Text)) > 0);
+        assert!(sql_threats(&validator.validate_input("x'; DROP TABLE users", InputType::Json)) > 0);
+        assert!(sql_threats(&validator.validate_input("1 UNION SELECT password FROM users", InputType::Text)) > 0);
+
+        // Code never reaches a query, so the same string isn't flagged there
+        assert_eq!(sql_threats(&validator.validate_input(injection, InputType::Code)), 0);
+    }
+
+    #[test]
+    fn test_sanitized_output_has_no_live_markup() {
+        let validator = AdvancedValidator::new();
+        let bypasses = [
+            "`code` <script>alert(1)</script>",
+            "`<script>alert(1)</script>`",
+            "unterminated ` <script>alert(1)</script>",
+            "```\n<script>alert(1)</script>\n```",
+            "<img src=x onerror=alert(1)>",
+            "<object data=\"evil.swf\"></object>",
+            "<embed src='evil.swf'>",
+            "<a href=\"&#106;avascript&#58;alert(1)\">x</a>",
+            "<svg/onload=alert(1)>",
+        ];
+        for input in bypasses {
+            let output = validator.sanitize_output(input);
+            assert!(!output.contains('<') && !output.contains('>'), "{} -> {}", input, output);
+            assert!(!output.contains('"') && !output.contains('\''), "{} -> {}", input, output);
+        }
+
+        // Entities in the input are escaped too, so they can't decode into markup
+        assert_eq!(
+            validator.sanitize_output("&#106;avascript&#58; & <b>\"hi\"</b>"),
+            "&amp;#106;avascript&amp;#58; &amp; &lt;b&gt;&quot;hi&quot;&lt;/b&gt;"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolved_hosts_must_be_public() {
+        for url in ["http://127.0.0.1/", "http://localhost:8080/", "http://[::1]/", "http://10.1.2.3/"] {
+            let url = reqwest::Url::parse(url).unwrap();
+            assert!(resolve_public_host(&url).await.is_err(), "{}", url);
+        }
+
+        let url = reqwest::Url::parse("https://93.184.216.34/image.png").unwrap();
+        let (host, address) = resolve_public_host(&url).await.unwrap();
+        assert_eq!(host, "93.184.216.34");
+        assert_eq!(address, "93.184.216.34:443".parse().unwrap());
+    }
+}
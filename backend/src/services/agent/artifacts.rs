@@ -0,0 +1,173 @@
+/**
+ * Artifact Limits
+ *
+ * Caps how many artifacts a task may produce and how many bytes they may hold
+ * in total, per agent type. Output past the cap is cut rather than kept, so a
+ * model answering with a huge or endlessly repeated artifact set can't exhaust
+ * memory; the result is flagged so callers know they got partial output.
+ */
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::types::{AgentType, Artifact};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactLimits {
+    /// Artifacts kept per task; 0 = unlimited
+    pub max_count: usize,
+    /// Combined content bytes kept per task; 0 = unlimited
+    pub max_bytes: usize,
+}
+
+impl Default for ArtifactLimits {
+    fn default() -> Self {
+        Self {
+            max_count: 20,
+            max_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// Limits for every agent type: one default plus configured overrides
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactLimitTable {
+    default: ArtifactLimits,
+    overrides: HashMap<AgentType, ArtifactLimits>,
+}
+
+impl ArtifactLimitTable {
+    /// Parse overrides such as `documenter:max_bytes=4194304;tester:max_count=50,max_bytes=2097152`
+    ///
+    /// Settings an entry leaves out keep the value from `default`.
+    pub fn parse(default: ArtifactLimits, spec: &str) -> anyhow::Result<Self> {
+        let mut overrides = HashMap::new();
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (agent_type, settings) = entry.split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Artifact limit '{}' must look like agent_type:setting=value", entry))?;
+            let agent_type: AgentType = serde_json::from_value(serde_json::json!(agent_type.trim()))
+                .map_err(|_| anyhow::anyhow!("Unknown agent type '{}' in artifact limits", agent_type.trim()))?;
+            let mut limits = default;
+
+            for setting in settings.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                let (name, value) = setting.split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("Artifact limit '{}' needs a value", setting))?;
+                let value: usize = value.trim().parse()
+                    .map_err(|_| anyhow::anyhow!("Artifact limit {} must be a whole number", name.trim()))?;
+                match name.trim() {
+                    "max_count" => limits.max_count = value,
+                    "max_bytes" => limits.max_bytes = value,
+                    other => anyhow::bail!("Unknown artifact limit '{}'", other),
+                }
+            }
+            overrides.insert(agent_type, limits);
+        }
+        Ok(Self { default, overrides })
+    }
+
+    pub fn for_agent(&self, agent_type: &AgentType) -> ArtifactLimits {
+        self.overrides.get(agent_type).copied().unwrap_or(self.default)
+    }
+}
+
+/// Longest prefix of `content` within `max_bytes` that ends on a char boundary
+fn cut_at(content: &str, max_bytes: usize) -> &str {
+    let mut end = max_bytes.min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    &content[..end]
+}
+
+/// Keep the artifacts that fit `limits`; true if anything was cut or dropped
+///
+/// The artifact that crosses the byte cap is shortened and marked `truncated`
+/// in its metadata; everything after it is dropped.
+pub fn enforce_limits(artifacts: Vec<Artifact>, limits: ArtifactLimits) -> (Vec<Artifact>, bool) {
+    let total = artifacts.len();
+    let mut kept = Vec::new();
+    let mut bytes_left = if limits.max_bytes == 0 { usize::MAX } else { limits.max_bytes };
+
+    for mut artifact in artifacts {
+        if (limits.max_count > 0 && kept.len() >= limits.max_count) || bytes_left == 0 {
+            break;
+        }
+        if artifact.content.len() > bytes_left {
+            artifact.content = cut_at(&artifact.content, bytes_left).to_string();
+            artifact.metadata.get_or_insert_with(HashMap::new)
+                .insert("truncated".to_string(), serde_json::json!(true));
+        }
+        bytes_left -= artifact.content.len();
+        let cut = bytes_left == 0;
+        kept.push(artifact);
+        if cut {
+            break;
+        }
+    }
+
+    let truncated = kept.len() < total
+        || kept.last().and_then(|a| a.metadata.as_ref()).is_some_and(|m| m.contains_key("truncated"));
+    if truncated {
+        tracing::warn!("Task output exceeded its artifact limits; kept {} of {} artifacts", kept.len(), total);
+    }
+    (kept, truncated)
+}
+
+/// The task's text result cut to the byte cap; true if it was shortened
+pub fn truncate_result(content: String, limits: ArtifactLimits) -> (String, bool) {
+    if limits.max_bytes == 0 || content.len() <= limits.max_bytes {
+        return (content, false);
+    }
+    tracing::warn!("Task result of {} bytes exceeded its limit of {}", content.len(), limits.max_bytes);
+    (cut_at(&content, limits.max_bytes).to_string(), true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::ArtifactType;
+
+    fn artifact(content: &str) -> Artifact {
+        Artifact {
+            artifact_type: ArtifactType::Code,
+            content: content.to_string(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_artifacts_beyond_cap_are_dropped_and_flagged() {
+        let table = ArtifactLimitTable::parse(ArtifactLimits::default(), "tester:max_count=2,max_bytes=10").unwrap();
+        let limits = table.for_agent(&AgentType::Tester);
+        assert_eq!(limits, ArtifactLimits { max_count: 2, max_bytes: 10 });
+        assert_eq!(table.for_agent(&AgentType::Documenter), ArtifactLimits::default());
+
+        // Too many artifacts: the extras are dropped
+        let (kept, truncated) = enforce_limits(vec![artifact("a"), artifact("b"), artifact("c")], limits);
+        assert!(truncated);
+        assert_eq!(kept.iter().map(|a| a.content.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+
+        // Too many bytes: the artifact crossing the cap is cut and marked
+        let (kept, truncated) = enforce_limits(vec![artifact("0123456"), artifact("789abcdef")], limits);
+        assert!(truncated);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[1].content, "789");
+        assert_eq!(kept[1].metadata.as_ref().unwrap()["truncated"], serde_json::json!(true));
+
+        // Output within the limits passes through untouched
+        let (kept, truncated) = enforce_limits(vec![artifact("short")], limits);
+        assert!(!truncated);
+        assert!(kept[0].metadata.is_none());
+
+        assert!(ArtifactLimitTable::parse(ArtifactLimits::default(), "wizard:max_count=1").is_err());
+    }
+
+    #[test]
+    fn test_result_is_cut_to_the_byte_cap() {
+        let limits = ArtifactLimits { max_count: 0, max_bytes: 4 };
+        assert_eq!(truncate_result("abc".to_string(), limits), ("abc".to_string(), false));
+        // Never splits a multi-byte char
+        assert_eq!(truncate_result("abcé".to_string(), limits), ("abc".to_string(), true));
+        let unlimited = ArtifactLimits { max_count: 0, max_bytes: 0 };
+        assert_eq!(truncate_result("abcdef".to_string(), unlimited), ("abcdef".to_string(), false));
+    }
+}
@@ -0,0 +1,220 @@
+/**
+ * Task Budget
+ *
+ * Accumulates tokens and estimated cost per task across every model call its
+ * subtasks make, retries included, and stops further work on a task once its
+ * spend passes the configured cap. Usage of finished tasks is kept for the
+ * most recent `RETAINED_FINISHED_TASKS` only.
+ */
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use serde::Serialize;
+
+use crate::types::{CostPer1kTokens, TokenUsage};
+
+/// Finished tasks whose usage stays available before the oldest is dropped
+pub const RETAINED_FINISHED_TASKS: usize = 10_000;
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct TaskUsage {
+    pub model_calls: u32,
+    pub total_tokens: u64,
+    pub estimated_cost_usd: f64,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("BudgetExceeded: task {task_id} spent ${spent_usd:.4} of its ${cap_usd:.4} budget")]
+pub struct BudgetExceeded {
+    pub task_id: String,
+    pub spent_usd: f64,
+    pub cap_usd: f64,
+}
+
+#[derive(Default)]
+struct LedgerState {
+    usage: HashMap<String, TaskUsage>,
+    parents: HashMap<String, String>, // subtask id -> task id
+    finished: VecDeque<String>, // Oldest first
+}
+
+pub struct TaskBudgetLedger {
+    cap_usd: f64, // 0.0 = no cap
+    retained: usize,
+    state: Mutex<LedgerState>,
+}
+
+impl TaskBudgetLedger {
+    pub fn new(cap_usd: f64) -> Self {
+        Self::with_retention(cap_usd, RETAINED_FINISHED_TASKS)
+    }
+
+    pub fn with_retention(cap_usd: f64, retained: usize) -> Self {
+        Self {
+            cap_usd,
+            retained,
+            state: Mutex::new(LedgerState::default()),
+        }
+    }
+
+    /// Charge a subtask's calls to its parent task
+    pub fn register_subtask(&self, subtask_id: &str, parent_id: &str) {
+        if subtask_id != parent_id {
+            self.state.lock().unwrap().parents.insert(subtask_id.to_string(), parent_id.to_string());
+        }
+    }
+
+    fn root<'a>(state: &'a LedgerState, task_id: &'a str) -> &'a str {
+        state.parents.get(task_id).map(|p| p.as_str()).unwrap_or(task_id)
+    }
+
+    /// Record one model call; errors once the task has gone over its cap
+    pub fn record(&self, task_id: &str, usage: &TokenUsage, cost: &CostPer1kTokens) -> Result<TaskUsage, BudgetExceeded> {
+        let mut state = self.state.lock().unwrap();
+        let root = Self::root(&state, task_id).to_string();
+        let entry = state.usage.entry(root.clone()).or_default();
+        entry.model_calls += 1;
+        entry.total_tokens += usage.total_tokens as u64;
+        entry.estimated_cost_usd += usage.prompt_tokens as f64 / 1000.0 * cost.input
+            + usage.completion_tokens as f64 / 1000.0 * cost.output;
+
+        let spent = entry.clone();
+        drop(state);
+        self.over_cap(&root, &spent)?;
+        Ok(spent)
+    }
+
+    /// Whether the task may make another model call
+    pub fn check(&self, task_id: &str) -> Result<(), BudgetExceeded> {
+        let state = self.state.lock().unwrap();
+        let root = Self::root(&state, task_id);
+        match state.usage.get(root) {
+            Some(usage) => self.over_cap(root, usage),
+            None => Ok(()),
+        }
+    }
+
+    fn over_cap(&self, task_id: &str, usage: &TaskUsage) -> Result<(), BudgetExceeded> {
+        if self.cap_usd > 0.0 && usage.estimated_cost_usd >= self.cap_usd {
+            return Err(BudgetExceeded {
+                task_id: task_id.to_string(),
+                spent_usd: usage.estimated_cost_usd,
+                cap_usd: self.cap_usd,
+            });
+        }
+        Ok(())
+    }
+
+    /// Subtasks registered under `parent_id`
+    pub fn subtasks_of(&self, parent_id: &str) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.parents.iter()
+            .filter(|(_, parent)| parent.as_str() == parent_id)
+            .map(|(subtask, _)| subtask.clone())
+            .collect()
+    }
+
+    /// Task a subtask was registered under
+    pub fn parent_of(&self, subtask_id: &str) -> Option<String> {
+        self.state.lock().unwrap().parents.get(subtask_id).cloned()
+    }
+
+    /// Accumulated usage of a task and all its subtasks
+    pub fn usage(&self, task_id: &str) -> Option<TaskUsage> {
+        let state = self.state.lock().unwrap();
+        state.usage.get(Self::root(&state, task_id)).cloned()
+    }
+
+    /// Note that a top-level task is done, dropping the usage and subtasks of
+    /// the oldest finished tasks past the retention limit
+    pub fn finish(&self, task_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.finished.iter().any(|id| id == task_id) {
+            return;
+        }
+        state.finished.push_back(task_id.to_string());
+        while state.finished.len() > self.retained {
+            let Some(expired) = state.finished.pop_front() else { break };
+            state.usage.remove(&expired);
+            state.parents.retain(|_, parent| *parent != expired);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::config::Config;
+    use crate::services::agent::executor::AgentExecutor;
+    use crate::services::agent::types::{Agent, AgentType};
+    use crate::services::ai::router::ModelRouter;
+    use crate::types::{AgentTask, CodebaseContext, Priority, TaskStatus, TaskType};
+
+    fn usage(prompt: u32, completion: u32) -> TokenUsage {
+        TokenUsage { prompt_tokens: prompt, completion_tokens: completion, total_tokens: prompt + completion }
+    }
+
+    #[tokio::test]
+    async fn test_task_aborts_once_budget_is_consumed() {
+        let mut config = Config::from_env().unwrap();
+        config.task_budget_usd = 0.05;
+        let config = Arc::new(config);
+        let executor = AgentExecutor::new(Arc::new(ModelRouter::new(&config)), config);
+        let ledger = executor.budget();
+        let cost = CostPer1kTokens { input: 0.01, output: 0.03 };
+
+        ledger.register_subtask("task-1-a", "task-1");
+        ledger.register_subtask("task-1-b", "task-1");
+
+        // 1k in + 1k out = $0.04, still under the cap
+        assert!(ledger.record("task-1-a", &usage(1000, 1000), &cost).is_ok());
+        // A retry pushes the task to $0.08
+        let err = ledger.record("task-1-a", &usage(1000, 1000), &cost).unwrap_err();
+        assert_eq!(err.task_id, "task-1");
+
+        let spent = ledger.usage("task-1").unwrap();
+        assert_eq!(spent.model_calls, 2);
+        assert_eq!(spent.total_tokens, 4000);
+
+        // The sibling subtask is refused before it reaches a provider
+        let task = AgentTask {
+            id: "task-1-b".to_string(),
+            r#type: TaskType::CodeGeneration,
+            description: "Write the handler".to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::Medium,
+            status: TaskStatus::Pending,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        let agent = Agent::new("agent-1".to_string(), "CodeGen".to_string(), AgentType::CodeGenerator);
+        let result = executor.execute_task(agent, task, tokio_util::sync::CancellationToken::new()).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().starts_with("BudgetExceeded"));
+
+        // Unrelated tasks are unaffected
+        assert!(ledger.check("task-2").is_ok());
+    }
+
+    #[test]
+    fn test_only_recent_finished_tasks_are_retained() {
+        let ledger = TaskBudgetLedger::with_retention(0.0, 2);
+        let cost = CostPer1kTokens { input: 0.01, output: 0.03 };
+        ledger.register_subtask("task-1-a", "task-1");
+        for task_id in ["task-1-a", "task-2", "task-3"] {
+            ledger.record(task_id, &usage(10, 10), &cost).unwrap();
+        }
+
+        ledger.finish("task-1");
+        ledger.finish("task-2");
+        ledger.finish("task-2");
+        assert!(ledger.usage("task-1").is_some());
+
+        ledger.finish("task-3");
+        assert!(ledger.usage("task-1").is_none());
+        assert!(ledger.subtasks_of("task-1").is_empty());
+        assert!(ledger.usage("task-2").is_some() && ledger.usage("task-3").is_some());
+    }
+}
@@ -0,0 +1,203 @@
+/**
+ * Coordination Log
+ *
+ * Live feed of inter-agent messages, task assignments and task results. The
+ * agent manager publishes every routed message and finished task, the company
+ * orchestrator publishes routing decisions, and activity viewers subscribe
+ * over WebSocket.
+ */
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::types::AgentMessage;
+
+/// Events buffered per subscriber before slow viewers start skipping
+const CHANNEL_CAPACITY: usize = 1024;
+
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CoordinationEvent {
+    Message {
+        message: AgentMessage,
+    },
+    TaskAssigned {
+        task_id: String,
+        agent_id: String,
+        team: String,
+        description: String,
+        timestamp: DateTime<Utc>,
+    },
+    /// A task ran to completion or failure; cancelled tasks are reported as `TaskCancelled`
+    TaskFinished {
+        task_id: String,
+        agent_id: String, // Agent that executed it
+        success: bool,
+        execution_time_ms: u64,
+        timestamp: DateTime<Utc>,
+    },
+    TaskCancelled {
+        task_id: String,
+        agent_id: Option<String>, // Agent that was running it; None if it was still queued
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl CoordinationEvent {
+    /// Agents the event involves
+    pub fn agent_ids(&self) -> Vec<&str> {
+        match self {
+            CoordinationEvent::Message { message } => {
+                std::iter::once(message.from.as_str())
+                    .chain(message.to.as_deref())
+                    .collect()
+            }
+            CoordinationEvent::TaskAssigned { agent_id, .. }
+            | CoordinationEvent::TaskFinished { agent_id, .. } => vec![agent_id.as_str()],
+            CoordinationEvent::TaskCancelled { agent_id, .. } => agent_id.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// Same event with task and message content removed
+    pub fn redacted(&self) -> Self {
+        match self {
+            CoordinationEvent::Message { message } => CoordinationEvent::Message {
+                message: AgentMessage {
+                    content: REDACTED.to_string(),
+                    data: None,
+                    ..message.clone()
+                },
+            },
+            CoordinationEvent::TaskAssigned { task_id, agent_id, team, timestamp, .. } => {
+                CoordinationEvent::TaskAssigned {
+                    task_id: task_id.clone(),
+                    agent_id: agent_id.clone(),
+                    team: team.clone(),
+                    description: REDACTED.to_string(),
+                    timestamp: *timestamp,
+                }
+            }
+            CoordinationEvent::TaskFinished { .. } | CoordinationEvent::TaskCancelled { .. } => self.clone(),
+        }
+    }
+}
+
+/// Viewer-side filter, from the WebSocket query string
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CoordinationFilter {
+    pub agent_id: Option<String>,
+    pub team: Option<String>,
+}
+
+impl CoordinationFilter {
+    /// `teams` maps agent id to team, for events that don't carry one
+    pub fn matches(&self, event: &CoordinationEvent, teams: &HashMap<String, String>) -> bool {
+        let agents = event.agent_ids();
+        if let Some(agent_id) = &self.agent_id {
+            if !agents.contains(&agent_id.as_str()) {
+                return false;
+            }
+        }
+        if let Some(team) = &self.team {
+            let in_team = match event {
+                CoordinationEvent::TaskAssigned { team: event_team, .. } => event_team == team,
+                CoordinationEvent::Message { .. }
+                | CoordinationEvent::TaskFinished { .. }
+                | CoordinationEvent::TaskCancelled { .. } => agents.iter()
+                    .any(|id| teams.get(*id).is_some_and(|t| t == team)),
+            };
+            if !in_team {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub struct CoordinationLog {
+    sender: broadcast::Sender<CoordinationEvent>,
+}
+
+impl CoordinationLog {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publish to current subscribers; nothing is kept when nobody is watching
+    pub fn publish(&self, event: CoordinationEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<CoordinationEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for CoordinationLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::config::Config;
+    use crate::services::agent::AgentManager;
+    use crate::services::agent::security::AgentSecurityConfig;
+    use crate::services::agent::types::MessageType;
+    use crate::services::ai::router::ModelRouter;
+
+    fn message(from: &str, to: &str) -> AgentMessage {
+        AgentMessage {
+            from: from.to_string(),
+            to: Some(to.to_string()),
+            message_type: MessageType::Coordination,
+            content: "schema is ready".to_string(),
+            data: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routed_message_appears_on_stream() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let router = Arc::new(ModelRouter::new(&config));
+        let manager = AgentManager::with_security_config(router, config, AgentSecurityConfig::default(), None);
+        let mut stream = manager.coordination_log().subscribe();
+
+        manager.send_message(message("backend-1", "frontend-1")).await.unwrap();
+
+        match stream.recv().await.unwrap() {
+            CoordinationEvent::Message { message } => {
+                assert_eq!(message.from, "backend-1");
+                assert_eq!(message.content, "schema is ready");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_filter_and_redaction() {
+        let event = CoordinationEvent::Message { message: message("backend-1", "frontend-1") };
+        let teams = HashMap::from([
+            ("backend-1".to_string(), "Engineering".to_string()),
+            ("frontend-1".to_string(), "Engineering".to_string()),
+        ]);
+
+        let by_agent = CoordinationFilter { agent_id: Some("frontend-1".to_string()), team: None };
+        assert!(by_agent.matches(&event, &teams));
+        let by_team = CoordinationFilter { agent_id: None, team: Some("Creative".to_string()) };
+        assert!(!by_team.matches(&event, &teams));
+
+        match event.redacted() {
+            CoordinationEvent::Message { message } => assert_eq!(message.content, REDACTED),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
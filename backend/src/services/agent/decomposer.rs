@@ -4,13 +4,256 @@
  * Breaks complex tasks into smaller, manageable subtasks
  * that can be assigned to specialized agents.
  */
-use crate::types::{AgentTask, TaskType, Priority, CodebaseContext};
+use std::collections::{HashMap, HashSet};
+use serde::Deserialize;
+use crate::types::{AgentTask, TaskType, Priority, CodebaseContext, AIMessage, AIRequest, MessageRole};
+use crate::services::ai::router::ModelRouter;
 use super::types::{DecomposedTask, SubTask, TaskDependency, DependencyType, AgentType};
 use uuid::Uuid;
 
+/// Default complexity below which tasks skip decomposition
+pub const DEFAULT_FAST_PATH_THRESHOLD: f64 = 0.35;
+
+/// Most subtasks a model-proposed plan may contain
+pub const MAX_AI_SUBTASKS: usize = 10;
+
+/// Longest dependency chain allowed in a plan, counted in subtasks
+pub const MAX_DEPENDENCY_DEPTH: usize = 5;
+
+/// Plan shape the model is asked to return
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProposedPlan {
+    subtasks: Vec<ProposedSubtask>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProposedSubtask {
+    key: String,
+    description: String,
+    task_type: TaskType,
+    #[serde(default)]
+    agent_type: Option<AgentType>,
+    #[serde(default)]
+    priority: Option<Priority>,
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
 pub struct TaskDecomposer;
 
 impl TaskDecomposer {
+    /// Decompose a task, or run it as a single subtask when it is simple enough
+    ///
+    /// A threshold of 0.0 disables the fast path.
+    pub fn decompose_with_threshold(task: AgentTask, fast_path_threshold: f64) -> DecomposedTask {
+        if Self::complexity_score(&task) < fast_path_threshold {
+            tracing::debug!("Task {} below complexity threshold, skipping decomposition", task.id);
+            let subtasks = vec![Self::single_subtask(&task)];
+            return DecomposedTask {
+                original_task: task,
+                subtasks,
+                dependencies: vec![],
+            };
+        }
+
+        Self::decompose(task)
+    }
+
+    /// Estimate task complexity in the range 0.0..=1.0
+    ///
+    /// Combines description length, attached context size, and task type.
+    pub fn complexity_score(task: &AgentTask) -> f64 {
+        let words = task.description.split_whitespace().count() as f64;
+        let description_score = (words / 60.0).min(1.0);
+
+        let (file_count, context_bytes) = task.context.files.as_ref()
+            .map(|files| (files.len(), files.iter().map(|f| f.content.len()).sum::<usize>()))
+            .unwrap_or((0, 0));
+        let context_score = (file_count as f64 * 0.1 + context_bytes as f64 / 20_000.0).min(1.0);
+
+        let type_score = match task.r#type {
+            TaskType::CodeAnalysis | TaskType::Documentation => 0.0,
+            TaskType::Testing => 0.1,
+            TaskType::Refactoring | TaskType::CodeGeneration => 0.2,
+            TaskType::Debugging => 0.3,
+        };
+
+        0.4 * description_score + 0.3 * context_score + type_score
+    }
+
+    fn single_subtask(task: &AgentTask) -> SubTask {
+        let agent_type = match task.r#type {
+            TaskType::CodeGeneration => AgentType::CodeGenerator,
+            TaskType::CodeAnalysis => AgentType::CodeAnalyzer,
+            TaskType::Refactoring => AgentType::Refactorer,
+            TaskType::Debugging => AgentType::Debugger,
+            TaskType::Documentation => AgentType::Documenter,
+            TaskType::Testing => AgentType::Tester,
+        };
+
+        SubTask {
+            id: Uuid::new_v4().to_string(),
+            parent_id: task.id.clone(),
+            description: task.description.clone(),
+            task_type: task.r#type.clone(),
+            priority: task.priority.clone(),
+            assigned_agent_type: Some(agent_type),
+            dependencies: vec![],
+            context: task.context.clone(),
+        }
+    }
+
+    /// Ask a model to plan the subtasks, keeping the static templates as a fallback
+    ///
+    /// The plan is only used if it parses against the subtask schema and its
+    /// dependency graph is acyclic and no deeper than `MAX_DEPENDENCY_DEPTH`.
+    pub async fn decompose_with_ai(task: AgentTask, router: &ModelRouter, model: Option<String>) -> DecomposedTask {
+        match Self::request_ai_plan(&task, router, model).await {
+            Ok(decomposed) => {
+                tracing::debug!("Task {} decomposed by model into {} subtasks", task.id, decomposed.subtasks.len());
+                decomposed
+            }
+            Err(e) => {
+                tracing::warn!("AI decomposition of task {} rejected, using templates: {}", task.id, e);
+                Self::decompose(task)
+            }
+        }
+    }
+
+    async fn request_ai_plan(task: &AgentTask, router: &ModelRouter, model: Option<String>) -> anyhow::Result<DecomposedTask> {
+        let prompt = format!(
+            "Break the following {:?} task into at most {} subtasks for specialized agents.\n\n\
+             Task: {}\n\n\
+             Respond with a JSON object only, shaped as:\n\
+             {{\"subtasks\": [{{\"key\": \"short-unique-key\", \"description\": \"...\", \
+             \"task_type\": \"code_generation|code_analysis|refactoring|debugging|documentation|testing\", \
+             \"agent_type\": \"code_generator|code_analyzer|refactorer|debugger|documenter|tester|reviewer|optimizer|security|migrator\", \
+             \"priority\": \"low|medium|high|urgent\", \"depends_on\": [\"key of an earlier subtask\"]}}]}}\n\
+             Dependency chains may be at most {} subtasks long and must not form cycles.",
+            task.r#type, MAX_AI_SUBTASKS, task.description, MAX_DEPENDENCY_DEPTH,
+        );
+        let request = AIRequest {
+            messages: vec![AIMessage {
+                role: MessageRole::User,
+                content: prompt,
+                timestamp: None,
+                metadata: None,
+            }],
+            model,
+            temperature: Some(0.2),
+            max_tokens: Some(2000),
+            stream: Some(false),
+            context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: true,
+        };
+
+        let model_info = router.select_best_model(&request)?;
+        let service = router.get_service(model_info.provider)
+            .ok_or_else(|| anyhow::anyhow!("No AI service available"))?;
+        let response = router.generate_with(&service, request).await?;
+        Self::parse_ai_plan(task, &response.content)
+    }
+
+    /// Turn a model's JSON plan into subtasks, rejecting anything malformed
+    pub fn parse_ai_plan(task: &AgentTask, raw: &str) -> anyhow::Result<DecomposedTask> {
+        // Models without a native JSON mode tend to wrap the object in prose or fences
+        let start = raw.find('{').ok_or_else(|| anyhow::anyhow!("No JSON object in response"))?;
+        let end = raw.rfind('}').filter(|&end| end > start)
+            .ok_or_else(|| anyhow::anyhow!("No JSON object in response"))?;
+        let plan: ProposedPlan = serde_json::from_str(&raw[start..=end])?;
+
+        if plan.subtasks.is_empty() || plan.subtasks.len() > MAX_AI_SUBTASKS {
+            anyhow::bail!("Plan has {} subtasks (expected 1 to {})", plan.subtasks.len(), MAX_AI_SUBTASKS);
+        }
+
+        let mut ids: HashMap<&str, String> = HashMap::new();
+        for proposed in &plan.subtasks {
+            if proposed.key.trim().is_empty() || proposed.description.trim().is_empty() {
+                anyhow::bail!("Subtasks need a key and a description");
+            }
+            if ids.insert(proposed.key.as_str(), Uuid::new_v4().to_string()).is_some() {
+                anyhow::bail!("Subtask key '{}' is used more than once", proposed.key);
+            }
+        }
+
+        let mut subtasks = Vec::with_capacity(plan.subtasks.len());
+        for proposed in &plan.subtasks {
+            let dependencies = proposed.depends_on.iter()
+                .map(|key| ids.get(key.as_str()).cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Subtask '{}' depends on unknown subtask '{}'", proposed.key, key)))
+                .collect::<anyhow::Result<Vec<String>>>()?;
+            subtasks.push(SubTask {
+                id: ids[proposed.key.as_str()].clone(),
+                parent_id: task.id.clone(),
+                description: proposed.description.trim().to_string(),
+                task_type: proposed.task_type.clone(),
+                priority: proposed.priority.clone().unwrap_or_else(|| task.priority.clone()),
+                assigned_agent_type: proposed.agent_type.clone(),
+                dependencies,
+                context: task.context.clone(),
+            });
+        }
+
+        Self::validate_dependency_graph(&subtasks)?;
+
+        let dependencies = subtasks.iter()
+            .filter(|st| !st.dependencies.is_empty())
+            .map(|st| TaskDependency {
+                task_id: st.id.clone(),
+                depends_on: st.dependencies.clone(),
+                dependency_type: DependencyType::Sequential,
+            })
+            .collect();
+
+        Ok(DecomposedTask {
+            original_task: task.clone(),
+            subtasks,
+            dependencies,
+        })
+    }
+
+    /// Reject dependency cycles and chains longer than `MAX_DEPENDENCY_DEPTH`
+    pub fn validate_dependency_graph(subtasks: &[SubTask]) -> anyhow::Result<()> {
+        let by_id: HashMap<&str, &SubTask> = subtasks.iter().map(|st| (st.id.as_str(), st)).collect();
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+
+        fn depth<'a>(
+            id: &'a str,
+            by_id: &HashMap<&'a str, &'a SubTask>,
+            depths: &mut HashMap<&'a str, usize>,
+            visiting: &mut HashSet<&'a str>,
+        ) -> anyhow::Result<usize> {
+            if let Some(&d) = depths.get(id) {
+                return Ok(d);
+            }
+            if !visiting.insert(id) {
+                anyhow::bail!("Subtask dependencies form a cycle");
+            }
+            let mut deepest = 0;
+            for dep in &by_id[id].dependencies {
+                let dep = by_id.get(dep.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("Unknown dependency {}", dep))?;
+                deepest = deepest.max(depth(dep.id.as_str(), by_id, depths, visiting)?);
+            }
+            visiting.remove(id);
+            depths.insert(id, deepest + 1);
+            Ok(deepest + 1)
+        }
+
+        for subtask in subtasks {
+            let d = depth(subtask.id.as_str(), &by_id, &mut depths, &mut HashSet::new())?;
+            if d > MAX_DEPENDENCY_DEPTH {
+                anyhow::bail!("Dependency chain of {} subtasks exceeds the limit of {}", d, MAX_DEPENDENCY_DEPTH);
+            }
+        }
+        Ok(())
+    }
+
     /// Decompose a complex task into subtasks
     pub fn decompose(task: AgentTask) -> DecomposedTask {
         let subtasks = match task.r#type {
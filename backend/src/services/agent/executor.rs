@@ -6,14 +6,21 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::collections::HashMap;
+use tokio_util::sync::CancellationToken;
 
 use crate::types::{AgentTask, TaskType, TaskStatus, AIMessage, MessageRole};
 use crate::services::ai::router::ModelRouter;
 use crate::config::Config;
 use super::types::{Agent, AgentStatus, AgentExecutionResult, Artifact, ArtifactType};
+use super::budget::TaskBudgetLedger;
+use super::artifacts::{enforce_limits, truncate_result};
+use super::fault_tolerance::{CheckpointManager, ExecutionProgress, TaskCheckpoint};
+
 pub struct AgentExecutor {
     router: Arc<ModelRouter>,
     config: Arc<Config>,
+    budget: Arc<TaskBudgetLedger>,
+    checkpoints: Option<Arc<CheckpointManager>>,
 }
 
 impl AgentExecutor {
@@ -21,17 +28,38 @@ impl AgentExecutor {
         router: Arc<ModelRouter>,
         config: Arc<Config>,
     ) -> Self {
+        let budget = Arc::new(TaskBudgetLedger::new(config.task_budget_usd));
         Self {
             router,
             config,
+            budget,
+            checkpoints: None,
         }
     }
 
+    /// Record progress as tasks run, and pick up from it after an interruption
+    pub fn with_checkpoints(mut self, checkpoints: Arc<CheckpointManager>) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Per-task token and cost accounting
+    pub fn budget(&self) -> Arc<TaskBudgetLedger> {
+        Arc::clone(&self.budget)
+    }
+
     /// Execute a task with an agent
+    ///
+    /// Cancelling `cancel` abandons the in-flight model call and returns a
+    /// result marked `cancelled`. With checkpoints enabled, progress is saved
+    /// when the run starts and when the model answers; a task whose checkpoint
+    /// already holds the answer reuses it instead of calling the model again.
+    /// The checkpoint is dropped once the task succeeds or is cancelled.
     pub async fn execute_task(
         &self,
         agent: Agent,
         mut task: AgentTask,
+        cancel: CancellationToken,
     ) -> AgentExecutionResult {
         let start_time = std::time::Instant::now();
 
@@ -44,23 +72,75 @@ impl AgentExecutor {
         // Select appropriate model for this task
         let model_selection = self.select_model_for_task(&task, &agent);
 
-        // Execute with AI
-        let result = match self.execute_with_ai(&prompt, model_selection).await {
+        let mut progress = self.load_progress(&task.id).await.unwrap_or_default();
+        progress.attempts += 1;
+
+        // Execute with AI, unless the task has already spent its budget
+        let outcome = match (progress.output.clone(), self.budget.check(&task.id)) {
+            (Some(output), _) => {
+                tracing::info!("Resuming task {} from its checkpoint (attempt {})", task.id, progress.attempts);
+                Ok(crate::types::AIResponse {
+                    content: output,
+                    model: progress.model.clone().unwrap_or_default(),
+                    usage: progress.usage.clone(),
+                    finish_reason: None,
+                    metadata: None,
+                })
+            }
+            (None, Ok(())) => {
+                self.save_progress(&agent, &task, &progress).await;
+                let outcome = self.run_cancellable(&task.id, &prompt, model_selection, &cancel).await;
+                if let Some(Ok(response)) = &outcome {
+                    progress.model = Some(response.model.clone());
+                    progress.output = Some(response.content.clone());
+                    progress.usage = response.usage.clone();
+                    self.save_progress(&agent, &task, &progress).await;
+                }
+                match outcome {
+                    Some(outcome) => outcome,
+                    None => {
+                        task.status = TaskStatus::Cancelled;
+                        task.completed_at = Some(chrono::Utc::now());
+                        self.clear_progress(&task.id).await;
+                        return AgentExecutionResult {
+                            agent_id: agent.id.clone(),
+                            task_id: task.id.clone(),
+                            success: false,
+                            result: None,
+                            error: Some("Task was cancelled".to_string()),
+                            artifacts: vec![],
+                            artifacts_truncated: false,
+                            cancelled: true,
+                            execution_time_ms: start_time.elapsed().as_millis() as u64,
+                            tokens_used: None,
+                        };
+                    }
+                }
+            }
+            (None, Err(exceeded)) => Err(exceeded.to_string()),
+        };
+        match outcome {
             Ok(response) => {
                 task.status = TaskStatus::Completed;
-                task.result = Some(response.content.clone());
                 task.completed_at = Some(chrono::Utc::now());
+                self.clear_progress(&task.id).await;
 
-                // Create artifacts from result
+                // Create artifacts from result, capped for this agent type
                 let artifacts = self.create_artifacts(&task, &response.content);
+                let limits = self.config.agent_artifact_limits.for_agent(&agent.agent_type);
+                let (artifacts, artifacts_truncated) = enforce_limits(artifacts, limits);
+                let (content, result_truncated) = truncate_result(response.content, limits);
+                task.result = Some(content.clone());
 
                 AgentExecutionResult {
                     agent_id: agent.id.clone(),
                     task_id: task.id.clone(),
                     success: true,
-                    result: Some(response.content),
+                    result: Some(content),
                     error: None,
                     artifacts,
+                    artifacts_truncated: artifacts_truncated || result_truncated,
+                    cancelled: false,
                     execution_time_ms: start_time.elapsed().as_millis() as u64,
                     tokens_used: response.usage.map(|u| u.total_tokens),
                 }
@@ -77,6 +157,8 @@ impl AgentExecutor {
                     result: None,
                     error: Some(e),
                     artifacts: vec![],
+                    artifacts_truncated: false,
+                    cancelled: false,
                     execution_time_ms: start_time.elapsed().as_millis() as u64,
                     tokens_used: None,
                 }
@@ -84,6 +166,46 @@ impl AgentExecutor {
         }
     }
 
+    /// Model call that gives up when `cancel` fires; None means cancelled
+    async fn run_cancellable(
+        &self,
+        task_id: &str,
+        prompt: &str,
+        model: Option<String>,
+        cancel: &CancellationToken,
+    ) -> Option<Result<crate::types::AIResponse, String>> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => None,
+            outcome = self.execute_with_ai(task_id, prompt, model) => Some(outcome),
+        }
+    }
+
+    async fn load_progress(&self, task_id: &str) -> Option<ExecutionProgress> {
+        let checkpoint = self.checkpoints.as_ref()?.load_checkpoint(task_id).await?;
+        serde_json::from_value(checkpoint.checkpoint_data)
+            .map_err(|e| tracing::warn!("Ignoring unreadable checkpoint for task {}: {}", task_id, e))
+            .ok()
+    }
+
+    async fn save_progress(&self, agent: &Agent, task: &AgentTask, progress: &ExecutionProgress) {
+        let Some(checkpoints) = &self.checkpoints else {
+            return;
+        };
+        checkpoints.save_checkpoint(TaskCheckpoint {
+            task_id: task.id.clone(),
+            agent_id: agent.id.clone(),
+            checkpoint_data: serde_json::to_value(progress).unwrap_or_default(),
+            created_at: chrono::Utc::now(),
+        }).await;
+    }
+
+    async fn clear_progress(&self, task_id: &str) {
+        if let Some(checkpoints) = &self.checkpoints {
+            checkpoints.remove_checkpoint(task_id).await;
+        }
+    }
+
     fn build_prompt(&self, agent: &Agent, task: &AgentTask) -> String {
         let agent_role = match agent.agent_type {
             super::types::AgentType::CodeGenerator => "You are a code generation agent. Generate clean, efficient, and well-documented code.",
@@ -115,6 +237,7 @@ impl AgentExecutor {
 
     async fn execute_with_ai(
         &self,
+        task_id: &str,
         prompt: &str,
         model: Option<String>,
     ) -> Result<crate::types::AIResponse, String> {
@@ -134,21 +257,30 @@ impl AgentExecutor {
             max_tokens: Some(4000),
             stream: Some(false),
             context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
         };
 
         // Use the router to get the best service
         use crate::services::ai::base::AIService;
         
-        let model_info = self.router.select_best_model(&request)
+        let routing = self.router.select_with_context_policy(&request)
             .map_err(|e| format!("Model selection failed: {}", e))?;
         
-        let service = self.router.get_service(model_info.provider)
+        let service = self.router.get_service(routing.model.provider)
             .ok_or_else(|| "No service available".to_string())?;
         
-        match service.generate(&request).await {
-            Ok(response) => Ok(response),
-            Err(e) => Err(format!("AI execution failed: {}", e)),
+        let response = self.router.generate_with(&service, routing.request).await
+            .map_err(|e| format!("AI execution failed: {}", e))?;
+
+        // The call is paid for either way; a task that went over is failed here
+        if let Some(usage) = &response.usage {
+            self.budget.record(task_id, usage, &service.capabilities().cost_per_1k_tokens)
+                .map_err(|e| e.to_string())?;
         }
+        Ok(response)
     }
 
     fn create_artifacts(&self, task: &AgentTask, result: &str) -> Vec<Artifact> {
@@ -181,6 +313,57 @@ impl Clone for AgentExecutor {
         Self {
             router: Arc::clone(&self.router),
             config: Arc::clone(&self.config),
+            budget: Arc::clone(&self.budget),
+            checkpoints: self.checkpoints.clone(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CodebaseContext, Priority};
+    use super::super::types::AgentType;
+
+    #[tokio::test]
+    async fn test_interrupted_task_resumes_from_checkpoint() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let checkpoints = Arc::new(CheckpointManager::new());
+        let executor = AgentExecutor::new(Arc::new(ModelRouter::new(&config)), config)
+            .with_checkpoints(Arc::clone(&checkpoints));
+
+        // The previous run got the model's answer but died before finishing
+        let progress = ExecutionProgress {
+            attempts: 1,
+            model: Some("gpt-4o".to_string()),
+            output: Some("fn add(a: i32, b: i32) -> i32 { a + b }".to_string()),
+            usage: None,
+        };
+        checkpoints.save_checkpoint(TaskCheckpoint {
+            task_id: "task-1".to_string(),
+            agent_id: "agent-1".to_string(),
+            checkpoint_data: serde_json::to_value(&progress).unwrap(),
+            created_at: chrono::Utc::now(),
+        }).await;
+
+        let task = AgentTask {
+            id: "task-1".to_string(),
+            r#type: TaskType::CodeGeneration,
+            description: "Write an add function".to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::Medium,
+            status: TaskStatus::Pending,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        let agent = Agent::new("agent-1".to_string(), "CodeGen".to_string(), AgentType::CodeGenerator);
+        let result = executor.execute_task(agent, task, CancellationToken::new()).await;
+
+        // No provider is configured, so this only succeeds by reusing the saved output
+        assert!(result.success);
+        assert_eq!(result.result.as_deref(), progress.output.as_deref());
+        assert!(checkpoints.load_checkpoint("task-1").await.is_none());
+    }
+}
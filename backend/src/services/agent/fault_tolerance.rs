@@ -9,15 +9,22 @@
  * - Checkpointing
  */
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::RwLock;
+use std::future::Future;
 use std::time::Duration;
 use std::collections::HashMap;
 use chrono::Utc;
+use tokio_util::sync::CancellationToken;
 use serde::{Serialize, Deserialize};
+use crate::types::TokenUsage;
+use super::persistence::AgentStore;
 
 /// Circuit breaker state
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CircuitState {
+    #[default]
     Closed,   // Normal operation
     Open,     // Failing, reject requests
     HalfOpen, // Testing if recovered
@@ -32,6 +39,7 @@ pub struct CircuitBreaker {
     failure_threshold: u32,
     success_threshold: u32,
     timeout: Duration,
+    trips: AtomicU64, // Times the circuit has opened
 }
 
 impl CircuitBreaker {
@@ -44,6 +52,7 @@ impl CircuitBreaker {
             failure_threshold,
             success_threshold: 3, // Need 3 successes to close circuit
             timeout,
+            trips: AtomicU64::new(0),
         }
     }
     
@@ -53,6 +62,15 @@ impl CircuitBreaker {
         matches!(*state, CircuitState::Open)
     }
     
+    pub async fn state(&self) -> CircuitState {
+        *self.state.read().await
+    }
+    
+    /// How many times the circuit has opened since startup
+    pub fn trips(&self) -> u64 {
+        self.trips.load(Ordering::Relaxed)
+    }
+    
     /// Record success
     pub async fn record_success(&self) {
         let mut state = self.state.write().await;
@@ -87,8 +105,9 @@ impl CircuitBreaker {
         *failure_count += 1;
         *last_failure = Some(Utc::now());
         
-        if *failure_count >= self.failure_threshold {
+        if *failure_count >= self.failure_threshold && *state != CircuitState::Open {
             *state = CircuitState::Open;
+            self.trips.fetch_add(1, Ordering::Relaxed);
             tracing::warn!("Circuit breaker opened - too many failures");
         }
     }
@@ -133,6 +152,57 @@ impl Default for RetryConfig {
     }
 }
 
+impl RetryConfig {
+    /// Delay before retry number `attempt` (0-based), with jitter
+    ///
+    /// Grows by `backoff_multiplier` per attempt up to `max_delay`, then a
+    /// random 50-100% of that is used so many clients retrying the same
+    /// service don't all reconnect at once.
+    pub fn backoff_with_jitter(&self, attempt: u32) -> Duration {
+        use rand::Rng;
+
+        let base = self.initial_delay.as_secs_f64() * self.backoff_multiplier.powi(attempt.min(64) as i32);
+        let capped = base.min(self.max_delay.as_secs_f64());
+        Duration::from_secs_f64(capped * rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Run `operation` (given the 0-based attempt number) until `retryable`
+/// rejects its outcome or the retries run out, backing off between attempts
+///
+/// Returns the last outcome and how many attempts were made. Once `cancel`
+/// fires, no further attempt is started.
+pub async fn retry_with_backoff<T, F, Fut>(
+    config: &RetryConfig,
+    cancel: Option<&CancellationToken>,
+    mut operation: F,
+    retryable: impl Fn(&T) -> bool,
+) -> (T, u32)
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = T>,
+{
+    let mut delay = config.initial_delay;
+    let mut attempts = 0;
+    loop {
+        let outcome = operation(attempts).await;
+        attempts += 1;
+        if attempts > config.max_retries || !retryable(&outcome) {
+            return (outcome, attempts);
+        }
+
+        tracing::debug!("Attempt {}/{} failed; retrying in {:?}", attempts, config.max_retries + 1, delay);
+        match cancel {
+            Some(cancel) => tokio::select! {
+                _ = cancel.cancelled() => return (outcome, attempts),
+                _ = tokio::time::sleep(delay) => {}
+            },
+            None => tokio::time::sleep(delay).await,
+        }
+        delay = delay.mul_f64(config.backoff_multiplier).min(config.max_delay);
+    }
+}
+
 /// Execute with retry logic
 pub async fn execute_with_retry<F, T, E>(
     operation: F,
@@ -142,39 +212,22 @@ where
     F: Fn() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<T, E>> + Send>>,
     E: std::fmt::Display + Clone,
 {
-    let mut delay = config.initial_delay;
-    let mut last_error = None;
-    
-    for attempt in 0..=config.max_retries {
-        match operation().await {
-            Ok(result) => {
-                if attempt > 0 {
-                    tracing::info!("Operation succeeded after {} retries", attempt);
-                }
-                return Ok(result);
-            }
+    let (outcome, attempts) = retry_with_backoff(&config, None, |_| operation(), |outcome: &Result<T, E>| {
+        match outcome {
+            Ok(_) => false,
             Err(e) => {
-                last_error = Some(e.clone());
-                if attempt < config.max_retries {
-                    tracing::warn!(
-                        "Operation failed (attempt {}/{}): {}. Retrying in {:?}...",
-                        attempt + 1,
-                        config.max_retries + 1,
-                        e,
-                        delay
-                    );
-                    tokio::time::sleep(delay).await;
-                    delay = Duration::from_millis(
-                        (delay.as_millis() as f64 * config.backoff_multiplier) as u64
-                    ).min(config.max_delay);
-                } else {
-                    tracing::error!("Operation failed after {} retries: {}", config.max_retries + 1, e);
-                }
+                tracing::warn!("Operation failed: {}", e);
+                true
             }
         }
+    }).await;
+
+    match &outcome {
+        Ok(_) if attempts > 1 => tracing::info!("Operation succeeded after {} retries", attempts - 1),
+        Err(e) => tracing::error!("Operation failed after {} attempt(s): {}", attempts, e),
+        Ok(_) => {}
     }
-    
-    Err(last_error.expect("Should have error after retries"))
+    outcome
 }
 
 /// Agent health status
@@ -269,23 +322,50 @@ pub struct TaskCheckpoint {
     pub created_at: chrono::DateTime<Utc>,
 }
 
+/// What an execution had got through, kept in `TaskCheckpoint::checkpoint_data`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionProgress {
+    pub attempts: u32, // Executions started, the interrupted one included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Model output received before the interruption; a resumed run uses it
+    /// instead of asking the model again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
 /// Checkpoint manager for fault recovery
 pub struct CheckpointManager {
     checkpoints: Arc<RwLock<HashMap<String, TaskCheckpoint>>>,
+    store: Option<Arc<AgentStore>>, // Writes through so checkpoints survive restarts
 }
 
 impl CheckpointManager {
     pub fn new() -> Self {
         Self {
             checkpoints: Arc::new(RwLock::new(HashMap::new())),
+            store: None,
         }
     }
     
+    pub fn with_store(mut self, store: Arc<AgentStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+    
     /// Save checkpoint
     pub async fn save_checkpoint(&self, checkpoint: TaskCheckpoint) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_checkpoint(&checkpoint).await {
+                tracing::warn!("{}", e);
+            }
+        }
+        let task_id = checkpoint.task_id.clone();
         let mut checkpoints = self.checkpoints.write().await;
-        checkpoints.insert(checkpoint.task_id.clone(), checkpoint);
-        tracing::debug!("Checkpoint saved for task: {}", checkpoint.task_id);
+        checkpoints.insert(task_id.clone(), checkpoint);
+        tracing::debug!("Checkpoint saved for task: {}", task_id);
     }
     
     /// Load checkpoint
@@ -296,8 +376,31 @@ impl CheckpointManager {
     
     /// Remove checkpoint
     pub async fn remove_checkpoint(&self, task_id: &str) {
+        let removed = self.checkpoints.write().await.remove(task_id);
+        if let (Some(_), Some(store)) = (removed, &self.store) {
+            if let Err(e) = store.delete_checkpoint(task_id).await {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+    
+    /// Load the checkpoints left by the previous run; returns how many there were
+    pub async fn restore(&self) -> anyhow::Result<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let loaded = store.load_checkpoints().await?;
+        let count = loaded.len();
         let mut checkpoints = self.checkpoints.write().await;
-        checkpoints.remove(task_id);
+        for checkpoint in loaded {
+            checkpoints.insert(checkpoint.task_id.clone(), checkpoint);
+        }
+        Ok(count)
+    }
+    
+    /// IDs of every task with a checkpoint
+    pub async fn task_ids(&self) -> Vec<String> {
+        self.checkpoints.read().await.keys().cloned().collect()
     }
 }
 
@@ -0,0 +1,126 @@
+/**
+ * Agent Mailboxes
+ *
+ * Each agent gets a bounded inbox that other agents can post to, so work can
+ * be handed back and forth (a reviewer's findings going to the generator that
+ * wrote the code, for instance). A full inbox drops new messages rather than
+ * letting one slow agent hold up the sender.
+ */
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use super::types::AgentMessage;
+
+/// Messages an agent can have waiting before new ones are dropped
+pub const DEFAULT_INBOX_CAPACITY: usize = 256;
+
+struct Inbox {
+    sender: mpsc::Sender<AgentMessage>,
+    receiver: mpsc::Receiver<AgentMessage>,
+}
+
+pub struct AgentMailboxes {
+    capacity: usize,
+    inboxes: Mutex<HashMap<String, Inbox>>,
+}
+
+impl AgentMailboxes {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inboxes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Post `message` to each of `recipients`; returns how many accepted it
+    pub fn deliver<'a>(&self, message: &AgentMessage, recipients: impl IntoIterator<Item = &'a str>) -> usize {
+        let mut inboxes = self.inboxes.lock().unwrap();
+        let mut delivered = 0;
+        for recipient in recipients {
+            let inbox = inboxes.entry(recipient.to_string())
+                .or_insert_with(|| new_inbox(self.capacity));
+            match inbox.sender.try_send(message.clone()) {
+                Ok(()) => delivered += 1,
+                Err(TrySendError::Full(_)) => {
+                    tracing::warn!(
+                        "Inbox of agent {} is full ({} messages); dropping message from {}",
+                        recipient, self.capacity, message.from
+                    );
+                }
+                Err(TrySendError::Closed(_)) => {
+                    tracing::warn!("Inbox of agent {} is closed; dropping message", recipient);
+                }
+            }
+        }
+        delivered
+    }
+
+    /// Take every message waiting for `agent_id`, oldest first
+    pub fn drain(&self, agent_id: &str) -> Vec<AgentMessage> {
+        let mut inboxes = self.inboxes.lock().unwrap();
+        let Some(inbox) = inboxes.get_mut(agent_id) else {
+            return Vec::new();
+        };
+        let mut messages = Vec::new();
+        while let Ok(message) = inbox.receiver.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Drop `agent_id`'s inbox along with anything still in it; returns how
+    /// many messages were discarded
+    pub fn remove(&self, agent_id: &str) -> usize {
+        let Some(mut inbox) = self.inboxes.lock().unwrap().remove(agent_id) else {
+            return 0;
+        };
+        inbox.receiver.close();
+        let mut discarded = 0;
+        while inbox.receiver.try_recv().is_ok() {
+            discarded += 1;
+        }
+        discarded
+    }
+}
+
+fn new_inbox(capacity: usize) -> Inbox {
+    let (sender, receiver) = mpsc::channel(capacity);
+    Inbox { sender, receiver }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::MessageType;
+
+    fn message(from: &str, content: &str) -> AgentMessage {
+        AgentMessage {
+            from: from.to_string(),
+            to: None,
+            message_type: MessageType::TaskResult,
+            content: content.to_string(),
+            data: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_inboxes_are_bounded_and_drained_in_order() {
+        let mailboxes = AgentMailboxes::new(2);
+        assert_eq!(mailboxes.deliver(&message("reviewer", "null check missing"), ["generator"]), 1);
+        assert_eq!(mailboxes.deliver(&message("reviewer", "rename handler"), ["generator", "tester"]), 2);
+        // Third message doesn't fit in the generator's inbox
+        assert_eq!(mailboxes.deliver(&message("reviewer", "dropped"), ["generator", "tester"]), 1);
+
+        let inbox: Vec<_> = mailboxes.drain("generator").into_iter().map(|m| m.content).collect();
+        assert_eq!(inbox, vec!["null check missing", "rename handler"]);
+        assert!(mailboxes.drain("generator").is_empty());
+        assert_eq!(mailboxes.drain("tester").len(), 2);
+        assert!(mailboxes.drain("nobody").is_empty());
+
+        mailboxes.deliver(&message("reviewer", "left behind"), ["tester"]);
+        assert_eq!(mailboxes.remove("tester"), 1);
+        assert_eq!(mailboxes.remove("tester"), 0);
+        assert!(!mailboxes.inboxes.lock().unwrap().contains_key("tester"));
+    }
+}
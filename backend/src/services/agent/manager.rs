@@ -4,41 +4,145 @@
  * Manages agent lifecycle, task assignment, and coordination
  */
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::types::{AgentTask, TaskType, TaskStatus};
-use super::types::{Agent, AgentType, AgentStatus, AgentMessage, MessageType};
+use super::types::{
+    Agent, AgentType, AgentStatus, AgentMessage, MessageType, BulkAgentSpec, DecompositionMode, DependencyType,
+};
 use super::decomposer::TaskDecomposer;
 use super::executor::AgentExecutor;
 use super::security::{
     AgentSecurityConfig, validate_task_description, validate_context,
-    validate_agent_count, validate_task_count, sanitize_task_description,
+    validate_agent_count, validate_bulk_agent_count, validate_task_count, sanitize_task_description,
+    AgentSecurityError,
 };
 use super::monitoring::MetricsCollector;
-use super::fault_tolerance::{CircuitBreaker, HealthMonitor, CheckpointManager, RetryConfig, execute_with_retry};
-use super::queue::{TaskQueue, BackpressureManager};
+use super::fault_tolerance::{CircuitBreaker, HealthMonitor, CheckpointManager, RetryConfig, execute_with_retry, retry_with_backoff};
+use super::queue::{TaskQueue, BackpressureManager, DeadLetterQueue, DeadLetter};
+use super::coordination::{CoordinationLog, CoordinationEvent};
+use super::mailbox::{AgentMailboxes, DEFAULT_INBOX_CAPACITY};
+use super::budget::TaskUsage;
+use super::persistence::AgentStore;
+use super::webhooks::{WebhookNotifier, WebhookDelivery};
 use crate::services::ai::router::ModelRouter;
 use crate::config::Config;
+use crate::database::Database;
+
+/// Failed tasks kept for inspection before the oldest are dropped
+const DEAD_LETTER_CAPACITY: usize = 1000;
+
+/// Queue depth and concurrency slots in use at one moment
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct QueueLoad {
+    pub queued: usize,
+    pub queue_capacity: usize,
+    pub running: usize,
+    pub max_concurrent: usize,
+}
+
+impl QueueLoad {
+    /// Share of concurrent-task slots taken, 0.0 to 1.0
+    pub fn saturation(&self) -> f64 {
+        if self.max_concurrent == 0 {
+            return 1.0;
+        }
+        (self.running as f64 / self.max_concurrent as f64).min(1.0)
+    }
+
+    /// Whether new tasks would be turned away rather than queued
+    pub fn is_full(&self) -> bool {
+        self.queued >= self.queue_capacity
+    }
+}
 
 pub struct AgentManager {
     agents: Arc<RwLock<HashMap<String, Agent>>>,
     tasks: Arc<RwLock<HashMap<String, AgentTask>>>,
+    dependencies: Arc<RwLock<HashMap<String, Vec<String>>>>, // Queued subtask ID -> subtasks it waits for
     executor: Arc<AgentExecutor>,
+    router: Arc<ModelRouter>,
+    decomposition_model: Option<String>,
     security_config: AgentSecurityConfig,
     metrics: Arc<MetricsCollector>,
     task_queue: Arc<TaskQueue>,
+    dead_letters: Arc<DeadLetterQueue>, // Tasks that failed after every retry
     backpressure: Arc<BackpressureManager>,
     circuit_breaker: Arc<CircuitBreaker>,
     health_monitor: Arc<HealthMonitor>,
     checkpoint_manager: Arc<CheckpointManager>,
+    fast_path_threshold: AtomicU64, // f64 bits, updated on config reload
+    coordination_log: Arc<CoordinationLog>,
+    mailboxes: AgentMailboxes,
+    webhooks: WebhookNotifier, // Completion callbacks by task ID
+    in_flight: Arc<Mutex<HashMap<String, CancellationToken>>>, // Live executions by task ID
+    stuck_agent_timeout: std::time::Duration,
+    store: Arc<AgentStore>, // No-op without a database
+    shutting_down: AtomicBool, // Set once; stops new tasks and the queue processor
+}
+
+/// What happened to outstanding work during shutdown
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShutdownReport {
+    pub drained: usize, // Running tasks that finished within the timeout
+    pub still_running: usize, // Running tasks abandoned at the timeout
+    pub persisted: usize, // Queued tasks saved for the next start
+    pub unsaved: usize, // Queued tasks lost because there is no database
+}
+
+/// Marks a task as executing for as long as its spawned future is alive,
+/// including when that future panics and unwinds
+struct InFlightGuard {
+    in_flight: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    task_id: String,
+    cancel: CancellationToken,
+}
+
+impl InFlightGuard {
+    fn new(in_flight: &Arc<Mutex<HashMap<String, CancellationToken>>>, task_id: &str) -> Self {
+        let cancel = CancellationToken::new();
+        in_flight.lock().unwrap().insert(task_id.to_string(), cancel.clone());
+        Self { in_flight: Arc::clone(in_flight), task_id: task_id.to_string(), cancel }
+    }
+
+    /// Cancelled when someone asks for the task to stop
+    fn token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.task_id);
+        }
+    }
 }
 
 impl AgentManager {
-    pub fn new(router: Arc<ModelRouter>, config: Arc<Config>) -> Self {
-        let executor = Arc::new(AgentExecutor::new(router, config));
-        let security_config = AgentSecurityConfig::default();
+    pub fn new(router: Arc<ModelRouter>, config: Arc<Config>, database: Option<Arc<Database>>) -> Arc<Self> {
+        Self::with_security_config(router, config, AgentSecurityConfig::default(), database)
+    }
+    
+    pub fn with_security_config(
+        router: Arc<ModelRouter>,
+        config: Arc<Config>,
+        security_config: AgentSecurityConfig,
+        database: Option<Arc<Database>>,
+    ) -> Arc<Self> {
+        let fast_path_threshold = config.fast_path_complexity_threshold;
+        let stuck_agent_timeout = std::time::Duration::from_secs(config.agent_stuck_timeout_secs);
+        let decomposition_model = Some(config.decomposition_model.clone()).filter(|m| !m.is_empty());
+        let webhooks = WebhookNotifier::new(&config);
+        let store = Arc::new(AgentStore::new(database));
+        let checkpoint_manager = Arc::new(CheckpointManager::new().with_store(Arc::clone(&store)));
+        let executor = Arc::new(
+            AgentExecutor::new(Arc::clone(&router), config).with_checkpoints(Arc::clone(&checkpoint_manager)),
+        );
         
         // Initialize fault tolerance systems
         let task_queue = Arc::new(TaskQueue::new(2000)); // 2x capacity for buffer
@@ -48,21 +152,37 @@ impl AgentManager {
             std::time::Duration::from_secs(60), // Timeout 60 seconds
         ));
         let health_monitor = Arc::new(HealthMonitor::new(3)); // Unhealthy after 3 failures
-        let checkpoint_manager = Arc::new(CheckpointManager::new());
         
         let manager = Arc::new(Self {
             agents: Arc::new(RwLock::new(HashMap::new())),
             tasks: Arc::new(RwLock::new(HashMap::new())),
+            dependencies: Arc::new(RwLock::new(HashMap::new())),
             executor,
+            router,
+            decomposition_model,
             security_config,
             metrics: Arc::new(MetricsCollector::new()),
             task_queue,
+            dead_letters: Arc::new(DeadLetterQueue::new(DEAD_LETTER_CAPACITY)),
             backpressure,
             circuit_breaker,
             health_monitor,
             checkpoint_manager,
+            fast_path_threshold: AtomicU64::new(fast_path_threshold.to_bits()),
+            coordination_log: Arc::new(CoordinationLog::new()),
+            mailboxes: AgentMailboxes::new(DEFAULT_INBOX_CAPACITY),
+            webhooks,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            stuck_agent_timeout,
+            store,
+            shutting_down: AtomicBool::new(false),
         });
         
+        // Bring back what was persisted before the last shutdown
+        if manager.store.is_enabled() {
+            tokio::spawn(Self::restore_state(Arc::clone(&manager)));
+        }
+        
         // Start queue processor
         let manager_for_processor = Arc::clone(&manager);
         tokio::spawn(Self::queue_processor(manager_for_processor));
@@ -74,47 +194,148 @@ impl AgentManager {
         manager
     }
     
-    pub fn with_security_config(
-        router: Arc<ModelRouter>,
-        config: Arc<Config>,
-        security_config: AgentSecurityConfig,
-    ) -> Arc<Self> {
-        let executor = Arc::new(AgentExecutor::new(router, config));
-        
-        // Initialize fault tolerance systems
-        let task_queue = Arc::new(TaskQueue::new(2000));
-        let backpressure = Arc::new(BackpressureManager::new(200));
-        let circuit_breaker = Arc::new(CircuitBreaker::new(5, std::time::Duration::from_secs(60)));
-        let health_monitor = Arc::new(HealthMonitor::new(3));
-        let checkpoint_manager = Arc::new(CheckpointManager::new());
+    /// Complexity below which tasks skip decomposition
+    pub fn fast_path_threshold(&self) -> f64 {
+        f64::from_bits(self.fast_path_threshold.load(Ordering::Relaxed))
+    }
+    
+    pub fn set_fast_path_threshold(&self, threshold: f64) {
+        self.fast_path_threshold.store(threshold.to_bits(), Ordering::Relaxed);
+    }
+    
+    /// Load persisted agents, tasks and dead letters, re-queueing work that never finished
+    ///
+    /// Interrupted tasks with a checkpoint resume from it when they run again;
+    /// checkpoints of tasks that won't run again are dropped.
+    async fn restore_state(manager: Arc<AgentManager>) {
+        match manager.checkpoint_manager.restore().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Loaded {} task checkpoint(s) from the database", count),
+            Err(e) => tracing::error!("Failed to load task checkpoints: {}", e),
+        }
         
-        let manager = Arc::new(Self {
-            agents: Arc::new(RwLock::new(HashMap::new())),
-            tasks: Arc::new(RwLock::new(HashMap::new())),
-            executor,
-            security_config,
-            metrics: Arc::new(MetricsCollector::new()),
-            task_queue,
-            backpressure,
-            circuit_breaker,
-            health_monitor,
-            checkpoint_manager,
-        });
+        match manager.store.load_dead_letters(DEAD_LETTER_CAPACITY).await {
+            Ok(dead_letters) => manager.dead_letters.restore(dead_letters).await,
+            Err(e) => tracing::error!("Failed to load dead-lettered tasks: {}", e),
+        }
         
-        // Start queue processor
-        let manager_for_processor = Arc::clone(&manager);
-        tokio::spawn(Self::queue_processor(manager_for_processor));
+        let restored = match manager.store.load().await {
+            Ok(restored) => restored,
+            Err(e) => {
+                tracing::error!("Failed to restore agents and tasks: {}", e);
+                return;
+            }
+        };
+        tracing::info!(
+            "Restored {} agent(s) and {} task(s) from the database, re-queueing {}",
+            restored.agents.len(),
+            restored.tasks.len(),
+            restored.requeue.len()
+        );
         
-        // Start health recovery monitor
-        let manager_for_health = Arc::clone(&manager);
-        tokio::spawn(Self::health_recovery_monitor(manager_for_health));
+        {
+            let mut agents = manager.agents.write().await;
+            for agent in restored.agents {
+                agents.entry(agent.id.clone()).or_insert(agent);
+            }
+        }
+        {
+            let mut tasks = manager.tasks.write().await;
+            for task in restored.tasks {
+                tasks.entry(task.id.clone()).or_insert(task);
+            }
+        }
+        let requeued: std::collections::HashSet<String> = restored.requeue.iter().map(|t| t.id.clone()).collect();
+        for task_id in manager.checkpoint_manager.task_ids().await {
+            if requeued.contains(&task_id) {
+                tracing::info!("Task {} was interrupted mid-run; it will resume from its checkpoint", task_id);
+            } else {
+                manager.checkpoint_manager.remove_checkpoint(&task_id).await;
+            }
+        }
         
-        manager
+        manager.dependencies.write().await.extend(restored.dependencies);
+        for task in restored.requeue {
+            manager.persist_task(&task, true).await;
+            let task_id = task.id.clone();
+            if let Err(e) = manager.task_queue.enqueue(task).await {
+                tracing::error!("Failed to re-queue restored task {}: {}", task_id, e);
+            }
+        }
+    }
+    
+    /// Write a task through to the store; failures are logged, not returned
+    async fn persist_task(&self, task: &AgentTask, queued: bool) {
+        if let Err(e) = self.store.save_task(task, queued).await {
+            tracing::warn!("{}", e);
+        }
+    }
+    
+    async fn persist_agent(&self, agent: &Agent) {
+        if let Err(e) = self.store.save_agent(agent).await {
+            tracing::warn!("{}", e);
+        }
+    }
+
+    /// Follow-up once a task reaches a final state: fire its webhook and, for
+    /// the last subtask of a decomposed task, settle the parent
+    async fn on_task_finished(&self, task: &AgentTask) {
+        self.webhooks.notify(task, self.task_usage(&task.id));
+        if self.executor.budget().parent_of(&task.id).is_none() {
+            self.executor.budget().finish(&task.id);
+        }
+        if let Some(parent) = self.settle_parent(&task.id).await {
+            self.webhooks.notify(&parent, self.task_usage(&parent.id));
+            self.executor.budget().finish(&parent.id);
+        }
+    }
+
+    /// Finish a parent task once all of its subtasks have
+    ///
+    /// Any failed subtask fails the parent, otherwise any cancelled one cancels
+    /// it; when all completed, their results are joined as the parent's result.
+    async fn settle_parent(&self, subtask_id: &str) -> Option<AgentTask> {
+        let parent_id = self.executor.budget().parent_of(subtask_id)?;
+        let subtask_ids = self.executor.budget().subtasks_of(&parent_id);
+
+        let settled = {
+            let mut tasks = self.tasks.write().await;
+            let mut subtasks: Vec<&AgentTask> = subtask_ids.iter().filter_map(|id| tasks.get(id)).collect();
+            if !subtasks.iter().all(|t| is_finished(&t.status)) {
+                return None;
+            }
+            subtasks.sort_by_key(|t| t.created_at);
+
+            let failed = subtasks.iter().find(|t| matches!(t.status, TaskStatus::Failed));
+            let (status, result, error) = if let Some(failed) = failed {
+                let error = failed.error.clone().unwrap_or_else(|| "Subtask failed".to_string());
+                (TaskStatus::Failed, None, Some(format!("Subtask {} failed: {}", failed.id, error)))
+            } else if subtasks.iter().any(|t| matches!(t.status, TaskStatus::Cancelled)) {
+                (TaskStatus::Cancelled, None, Some("Task was cancelled".to_string()))
+            } else {
+                let results: Vec<&str> = subtasks.iter().filter_map(|t| t.result.as_deref()).collect();
+                (TaskStatus::Completed, Some(results.join("\n\n")), None)
+            };
+
+            let parent = tasks.get_mut(&parent_id).filter(|t| !is_finished(&t.status))?;
+            parent.status = status;
+            parent.result = result;
+            parent.error = error;
+            parent.completed_at = Some(chrono::Utc::now());
+            parent.clone()
+        };
+        self.persist_task(&settled, false).await;
+        Some(settled)
     }
     
     /// Queue processor - continuously processes queued tasks
     async fn queue_processor(manager: Arc<AgentManager>) {
         loop {
+            if manager.is_shutting_down() {
+                tracing::info!("Queue processor stopped for shutdown");
+                return;
+            }
+            
             // Check backpressure
             if !manager.backpressure.can_accept().await {
                 tokio::time::sleep(std::time::Duration::from_millis(100)).await;
@@ -130,7 +351,7 @@ impl AgentManager {
             }
             
             // Dequeue task
-            if let Some(task) = manager.task_queue.dequeue().await {
+            if let Some(task) = manager.next_ready_task().await {
                 // Reserve slot
                 if let Err(e) = manager.backpressure.reserve().await {
                     tracing::warn!("Failed to reserve slot: {}", e);
@@ -155,6 +376,41 @@ impl AgentManager {
                         }
                     };
                     
+                    let in_flight = InFlightGuard::new(&manager_clone.in_flight, &task_id);
+                    
+                    // Cancelled between leaving the queue and getting here
+                    let cancelled = matches!(
+                        manager_clone.tasks.read().await.get(&task_id).map(|t| &t.status),
+                        Some(TaskStatus::Cancelled)
+                    );
+                    if cancelled {
+                        manager_clone.backpressure.release().await;
+                        return;
+                    }
+                    
+                    let working = {
+                        let mut agents = manager_clone.agents.write().await;
+                        agents.get_mut(&agent.id).map(|agent| {
+                            agent.status = AgentStatus::Working;
+                            agent.current_task = Some(task_id.clone());
+                            agent.last_heartbeat = chrono::Utc::now();
+                            agent.clone()
+                        })
+                    };
+                    let processing = {
+                        let mut tasks = manager_clone.tasks.write().await;
+                        tasks.get_mut(&task_id).map(|task| {
+                            task.status = TaskStatus::Processing;
+                            task.clone()
+                        })
+                    };
+                    if let Some(agent) = working {
+                        manager_clone.persist_agent(&agent).await;
+                    }
+                    if let Some(task) = processing {
+                        manager_clone.persist_task(&task, true).await;
+                    }
+                    
                     // Execute with retry and fault tolerance
                     let retry_config = RetryConfig {
                         max_retries: 3,
@@ -163,39 +419,121 @@ impl AgentManager {
                         backoff_multiplier: 2.0,
                     };
                     
-                    let executor_clone = Arc::clone(&manager_clone.executor);
-                    let agent_clone = agent.clone();
-                    let task_clone = task.clone();
-                    
-                    let execution_result = executor_clone.execute_task(agent_clone.clone(), task_clone.clone()).await;
+                    // Cancellation and an exhausted budget end the retries at once,
+                    // since another attempt could only fail the same way
+                    let cancel = in_flight.token();
+                    let (execution_result, attempts) = retry_with_backoff(
+                        &retry_config,
+                        Some(&cancel),
+                        |attempt| {
+                            let (manager, agent, task, cancel) = (&manager_clone, agent.clone(), task.clone(), cancel.clone());
+                            async move {
+                                if attempt > 0 {
+                                    manager.metrics.record_retry().await;
+                                }
+                                manager.executor.execute_task(agent, task, cancel).await
+                            }
+                        },
+                        |result: &super::types::AgentExecutionResult| {
+                            let retryable = !result.success
+                                && !result.cancelled
+                                && manager_clone.executor.budget().check(&task_id).is_ok();
+                            if retryable {
+                                tracing::warn!(
+                                    "Task {} failed: {}; retrying",
+                                    task_id,
+                                    result.error.as_deref().unwrap_or("unknown error")
+                                );
+                            }
+                            retryable
+                        },
+                    ).await;
                     let success = execution_result.success;
                     
                     // Update task status in manager
-                    {
+                    let (cancelled, finished, failed_parent) = {
                         let mut tasks = manager_clone.tasks.write().await;
-                        if let Some(task) = tasks.get_mut(&task_id) {
-                            task.status = if success {
+                        // A result that raced a cancel request doesn't undo it
+                        let cancelled = execution_result.cancelled || matches!(
+                            tasks.get(&task_id).map(|t| &t.status),
+                            Some(TaskStatus::Cancelled)
+                        );
+                        let finished = tasks.get_mut(&task_id).map(|task| {
+                            task.status = if cancelled {
+                                TaskStatus::Cancelled
+                            } else if success {
                                 TaskStatus::Completed
                             } else {
                                 TaskStatus::Failed
                             };
                             task.result = execution_result.result.clone();
-                            task.error = execution_result.error.clone();
+                            task.error = if cancelled {
+                                Some("Task was cancelled".to_string())
+                            } else {
+                                execution_result.error.clone()
+                            };
                             task.completed_at = Some(chrono::Utc::now());
+                            task.clone()
+                        });
+
+                        // Over budget: no more work on the parent task either
+                        let mut failed_parent = None;
+                        if let Err(exceeded) = manager_clone.executor.budget().check(&task_id) {
+                            if let Some(parent) = tasks.get_mut(&exceeded.task_id) {
+                                parent.status = TaskStatus::Failed;
+                                parent.error = Some(exceeded.to_string());
+                                parent.completed_at.get_or_insert_with(chrono::Utc::now);
+                                failed_parent = Some(parent.clone());
+                            }
+                        }
+                        (cancelled, finished, failed_parent)
+                    };
+                    if let Some(parent) = failed_parent {
+                        manager_clone.persist_task(&parent, false).await;
+                        manager_clone.webhooks.notify(&parent, manager_clone.task_usage(&parent.id));
+                    }
+                    if let Some(task) = finished {
+                        manager_clone.persist_task(&task, true).await;
+                        manager_clone.on_task_finished(&task).await;
+                        if !success && !cancelled {
+                            let error = task.error.clone().unwrap_or_else(|| "Task failed".to_string());
+                            tracing::warn!("Task {} failed after {} attempt(s); moved to the dead-letter queue", task_id, attempts);
+                            manager_clone.checkpoint_manager.remove_checkpoint(&task_id).await;
+                            let dead_letter = manager_clone.dead_letters.push(task, error, attempts).await;
+                            if let Err(e) = manager_clone.store.save_dead_letter(&dead_letter, DEAD_LETTER_CAPACITY).await {
+                                tracing::warn!("{}", e);
+                            }
                         }
                     }
                     
                     // Update agent status
-                    {
+                    let updated = {
                         let mut agents = manager_clone.agents.write().await;
-                        if let Some(agent) = agents.get_mut(&agent.id) {
-                            agent.status = if success {
+                        agents.get_mut(&agent.id).map(|agent| {
+                            agent.status = if success || cancelled {
                                 AgentStatus::Idle
                             } else {
                                 AgentStatus::Failed
                             };
                             agent.current_task = None;
-                        }
+                            agent.last_heartbeat = chrono::Utc::now();
+                            agent.clone()
+                        })
+                    };
+                    if let Some(agent) = updated {
+                        manager_clone.persist_agent(&agent).await;
+                    }
+                    
+                    // A cancellation says nothing about the agent or the providers
+                    if cancelled {
+                        manager_clone.metrics.record_task_completed(
+                            &task_id,
+                            false,
+                            execution_result.execution_time_ms,
+                            execution_result.tokens_used,
+                        ).await;
+                        manager_clone.backpressure.release().await;
+                        return;
                     }
                     
                     // Record health and metrics
@@ -218,6 +556,13 @@ impl AgentManager {
                             execution_result.tokens_used,
                         ).await;
                     }
+                    manager_clone.coordination_log.publish(CoordinationEvent::TaskFinished {
+                        task_id: task_id.clone(),
+                        agent_id: agent.id.clone(),
+                        success,
+                        execution_time_ms: execution_result.execution_time_ms,
+                        timestamp: chrono::Utc::now(),
+                    });
                     
                     // Release backpressure slot
                     manager_clone.backpressure.release().await;
@@ -229,6 +574,161 @@ impl AgentManager {
         }
     }
     
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+    
+    /// Stop taking work, wait up to `timeout` for running tasks, then save
+    /// whatever is still queued so the next start picks it up
+    pub async fn shutdown(&self, timeout: std::time::Duration) -> ShutdownReport {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        
+        let running = self.backpressure.current_count().await;
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut remaining = running;
+        while remaining > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            remaining = self.backpressure.current_count().await;
+        }
+        
+        let mut report = ShutdownReport {
+            drained: running.saturating_sub(remaining),
+            still_running: remaining,
+            ..Default::default()
+        };
+        while let Some(task) = self.task_queue.dequeue().await {
+            if !self.store.is_enabled() {
+                report.unsaved += 1;
+                continue;
+            }
+            match self.store.save_task(&task, true).await {
+                Ok(()) => report.persisted += 1,
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    report.unsaved += 1;
+                }
+            }
+        }
+        report
+    }
+    
+    /// Drop what a task waited for once it has left the queue
+    async fn forget_dependencies(&self, task_id: &str) {
+        if self.dependencies.write().await.remove(task_id).is_some() {
+            if let Err(e) = self.store.delete_dependencies(task_id).await {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+    
+    /// Highest priority queued task whose dependencies have all completed
+    ///
+    /// Tasks whose dependency failed or was cancelled are failed here instead of
+    /// being handed out, which in turn unblocks (and fails) their own dependents.
+    async fn next_ready_task(&self) -> Option<AgentTask> {
+        loop {
+            let (task, state) = {
+                let tasks = self.tasks.read().await;
+                let dependencies = self.dependencies.read().await;
+                let task = self.task_queue
+                    .dequeue_where(|task| dependency_state(&dependencies, &tasks, &task.id) != DependencyState::Waiting)
+                    .await?;
+                let state = dependency_state(&dependencies, &tasks, &task.id);
+                (task, state)
+            };
+            self.forget_dependencies(&task.id).await;
+            
+            let DependencyState::Failed(reason) = state else {
+                return Some(task);
+            };
+            let failed = {
+                let mut tasks = self.tasks.write().await;
+                tasks.get_mut(&task.id)
+                    .filter(|task| !is_finished(&task.status))
+                    .map(|task| {
+                        task.status = TaskStatus::Failed;
+                        task.error = Some(reason.clone());
+                        task.completed_at = Some(chrono::Utc::now());
+                        task.clone()
+                    })
+            };
+            tracing::warn!("Not running task {}: {}", task.id, reason);
+            if let Some(task) = failed {
+                self.metrics.record_task_completed(&task.id, false, 0, None).await;
+                self.persist_task(&task, true).await;
+                self.on_task_finished(&task).await;
+            }
+        }
+    }
+    
+    /// Periodically reclaim agents left `Working` by a task that died mid-run
+    async fn health_recovery_monitor(manager: Arc<AgentManager>) {
+        let interval = (manager.stuck_agent_timeout / 2)
+            .clamp(std::time::Duration::from_secs(1), std::time::Duration::from_secs(30));
+        loop {
+            tokio::time::sleep(interval).await;
+            let recovered = manager.recover_stuck_agents().await;
+            if !recovered.is_empty() {
+                tracing::warn!("Recovered {} stuck agent(s): {:?}", recovered.len(), recovered);
+            }
+        }
+    }
+    
+    /// Reset agents that have been `Working` past the timeout with no live task
+    ///
+    /// The task they held is failed and its concurrency slot released, since the
+    /// execution that would have done both never finished. Returns the agent IDs.
+    pub async fn recover_stuck_agents(&self) -> Vec<String> {
+        let now = chrono::Utc::now();
+        let timeout = chrono::Duration::from_std(self.stuck_agent_timeout)
+            .unwrap_or_else(|_| chrono::Duration::seconds(600));
+        
+        let mut stuck = Vec::new();
+        {
+            let in_flight = self.in_flight.lock().unwrap().clone();
+            let mut agents = self.agents.write().await;
+            for agent in agents.values_mut() {
+                let is_live = agent.current_task.as_ref().is_some_and(|t| in_flight.contains_key(t));
+                if agent.status != AgentStatus::Working || is_live || now - agent.last_heartbeat < timeout {
+                    continue;
+                }
+                let task_id = agent.current_task.take();
+                agent.status = AgentStatus::Idle;
+                agent.last_heartbeat = now;
+                stuck.push((agent.clone(), task_id));
+            }
+        }
+        
+        for (agent, task_id) in &stuck {
+            let agent_id = &agent.id;
+            self.health_monitor.record_execution(agent_id, false).await;
+            self.persist_agent(agent).await;
+            let Some(task_id) = task_id else {
+                continue;
+            };
+            
+            let failed = {
+                let mut tasks = self.tasks.write().await;
+                tasks.get_mut(task_id)
+                    .filter(|task| !is_finished(&task.status))
+                    .map(|task| {
+                        task.status = TaskStatus::Failed;
+                        task.error = Some(format!("Agent {} stopped responding", agent_id));
+                        task.completed_at = Some(now);
+                        task.clone()
+                    })
+            };
+            if let Some(task) = failed {
+                self.persist_task(&task, true).await;
+                self.on_task_finished(&task).await;
+            }
+            self.metrics.record_task_completed(task_id, false, 0, None).await;
+            self.backpressure.release().await;
+        }
+        
+        stuck.into_iter().map(|(agent, _)| agent.id).collect()
+    }
+    
     /// Find or create agent for task
     async fn find_or_create_agent_for_task(
         &self,
@@ -276,6 +776,16 @@ impl AgentManager {
         })
     }
     
+    /// Current queue depth and backpressure slot usage
+    pub async fn queue_load(&self) -> QueueLoad {
+        QueueLoad {
+            queued: self.task_queue.size().await,
+            queue_capacity: self.task_queue.capacity(),
+            running: self.backpressure.current_count().await,
+            max_concurrent: self.backpressure.max_concurrent_tasks,
+        }
+    }
+    
     /// Get health status
     pub async fn get_health_status(&self) -> serde_json::Value {
         let unhealthy = self.health_monitor.get_unhealthy_agents().await;
@@ -289,6 +799,11 @@ impl AgentManager {
     pub fn metrics(&self) -> Arc<MetricsCollector> {
         Arc::clone(&self.metrics)
     }
+    
+    /// Breaker that pauses the queue after repeated task failures
+    pub fn circuit_breaker(&self) -> Arc<CircuitBreaker> {
+        Arc::clone(&self.circuit_breaker)
+    }
 
     /// Create a new agent of specified type
     pub async fn create_agent(
@@ -313,18 +828,62 @@ impl AgentManager {
         // Record metrics
         self.metrics.record_agent_created().await;
         
-        let mut agents = self.agents.write().await;
-        agents.insert(id.clone(), agent.clone());
+        {
+            let mut agents = self.agents.write().await;
+            agents.insert(id.clone(), agent.clone());
+        }
+        self.persist_agent(&agent).await;
         
         Ok(agent)
     }
 
+    /// Create several agents at once; either all are created or none
+    pub async fn create_agents_bulk(&self, specs: &[BulkAgentSpec]) -> Result<Vec<Agent>, String> {
+        // Hold the write lock across the check so concurrent creates can't overshoot the cap
+        let mut agents = self.agents.write().await;
+        let created = insert_bulk_agents(&mut agents, specs, &self.security_config)
+            .map_err(|e| e.to_string())?;
+        drop(agents);
+        
+        for agent in &created {
+            self.metrics.record_agent_created().await;
+            self.persist_agent(agent).await;
+        }
+        
+        Ok(created)
+    }
+
     /// Get agent by ID
     pub async fn get_agent(&self, id: &str) -> Option<Agent> {
         let agents = self.agents.read().await;
         agents.get(id).cloned()
     }
 
+    /// Delete an idle agent along with its inbox
+    ///
+    /// Returns `Ok(None)` for an unknown agent and an error while the agent is
+    /// still working on a task.
+    pub async fn delete_agent(&self, id: &str) -> Result<Option<Agent>, String> {
+        let agent = {
+            let mut agents = self.agents.write().await;
+            match agents.get(id) {
+                None => return Ok(None),
+                Some(agent) if agent.current_task.is_some() => {
+                    return Err(format!("Agent {} is busy with a task", id));
+                }
+                Some(_) => agents.remove(id),
+            }
+        };
+        let discarded = self.mailboxes.remove(id);
+        if discarded > 0 {
+            tracing::info!("Discarded {} unread message(s) for deleted agent {}", discarded, id);
+        }
+        if let Err(e) = self.store.delete_agent(id).await {
+            tracing::warn!("{}", e);
+        }
+        Ok(agent)
+    }
+
     /// List all agents
     pub async fn list_agents(&self) -> Vec<Agent> {
         let agents = self.agents.read().await;
@@ -332,7 +891,16 @@ impl AgentManager {
     }
 
     /// Create and assign a task to appropriate agents
-    pub async fn create_task(&self, mut task: AgentTask) -> Result<AgentTask, String> {
+    pub async fn create_task(&self, task: AgentTask) -> Result<AgentTask, String> {
+        self.create_task_with_mode(task, DecompositionMode::Static).await
+    }
+
+    /// Create a task, choosing how it is broken into subtasks
+    pub async fn create_task_with_mode(&self, mut task: AgentTask, mode: DecompositionMode) -> Result<AgentTask, String> {
+        if self.is_shutting_down() {
+            return Err("Server is shutting down; not accepting new tasks".to_string());
+        }
+        
         // Security validation
         validate_task_description(&task.description, &self.security_config)
             .map_err(|e| e.to_string())?;
@@ -371,12 +939,41 @@ impl AgentManager {
             let mut tasks = self.tasks.write().await;
             tasks.insert(task_id.clone(), task.clone());
         }
+        self.persist_task(&task, false).await;
+
+        // Decompose task if complex; simple tasks run as a single subtask
+        let simple = TaskDecomposer::complexity_score(&task) < self.fast_path_threshold();
+        let decomposed = if mode == DecompositionMode::Ai && !simple {
+            TaskDecomposer::decompose_with_ai(task.clone(), &self.router, self.decomposition_model.clone()).await
+        } else {
+            TaskDecomposer::decompose_with_threshold(task.clone(), self.fast_path_threshold())
+        };
 
-        // Decompose task if complex
-        let decomposed = TaskDecomposer::decompose(task.clone());
+        // The parent only tracks its subtasks from here on, so nothing should pick it up as runnable
+        task.status = TaskStatus::Waiting;
+        if let Some(stored) = self.tasks.write().await.get_mut(&task_id) {
+            stored.status = TaskStatus::Waiting;
+        }
+        self.persist_task(&task, false).await;
+        
+        // Subtasks stay in the queue until what they depend on has completed;
+        // parallel edges don't hold anything back
+        for dependency in decomposed.dependencies {
+            if matches!(dependency.dependency_type, DependencyType::Parallel) || dependency.depends_on.is_empty() {
+                continue;
+            }
+            if let Err(e) = self.store.save_dependencies(&dependency.task_id, &dependency.depends_on).await {
+                tracing::warn!("{}", e);
+            }
+            self.dependencies.write().await
+                .entry(dependency.task_id)
+                .or_default()
+                .extend(dependency.depends_on);
+        }
         
         // Enqueue subtasks instead of immediate execution
         for subtask in decomposed.subtasks {
+            self.executor.budget().register_subtask(&subtask.id, &subtask.parent_id);
             let agent_task = AgentTask {
                 id: subtask.id.clone(),
                 r#type: subtask.task_type,
@@ -395,6 +992,7 @@ impl AgentManager {
                 let mut tasks = self.tasks.write().await;
                 tasks.insert(subtask.id.clone(), agent_task.clone());
             }
+            self.persist_task(&agent_task, true).await;
             
             // Enqueue for processing
             if let Err(e) = self.task_queue.enqueue(agent_task).await {
@@ -557,6 +1155,118 @@ impl AgentManager {
         tasks.get(task_id).cloned()
     }
 
+    /// Cancel a task and, for a decomposed task, all of its subtasks
+    ///
+    /// Queued work is pulled from the queue; running work is signalled and stops
+    /// at its next await point. Returns None for an unknown task and an error
+    /// if it has already finished.
+    pub async fn cancel_task(&self, task_id: &str) -> Result<Option<AgentTask>, String> {
+        match self.tasks.read().await.get(task_id) {
+            None => return Ok(None),
+            Some(task) if is_finished(&task.status) => {
+                return Err(format!("Task {} has already finished", task_id));
+            }
+            Some(_) => {}
+        }
+        
+        let mut ids = vec![task_id.to_string()];
+        ids.extend(self.executor.budget().subtasks_of(task_id));
+        
+        let now = chrono::Utc::now();
+        let mut cancelled = Vec::new();
+        for id in &ids {
+            let dequeued = self.task_queue.remove(id).await.is_some();
+            if dequeued {
+                self.forget_dependencies(id).await;
+            }
+            let running = self.in_flight.lock().unwrap().get(id).cloned();
+            if let Some(token) = &running {
+                token.cancel();
+            }
+            
+            let mut tasks = self.tasks.write().await;
+            if let Some(task) = tasks.get_mut(id).filter(|task| !is_finished(&task.status)) {
+                task.status = TaskStatus::Cancelled;
+                task.error = Some("Task was cancelled".to_string());
+                task.completed_at = Some(now);
+                cancelled.push(task.clone());
+            }
+            if dequeued || running.is_some() {
+                tracing::debug!("Stopped {} task {}", if dequeued { "queued" } else { "running" }, id);
+            }
+        }
+        tracing::info!("Cancelled task {} ({} task(s) stopped)", task_id, cancelled.len());
+        
+        for task in &cancelled {
+            self.persist_task(task, false).await;
+        }
+        for task in &cancelled {
+            let agent_id = self.agents.read().await.values()
+                .find(|agent| agent.current_task.as_deref() == Some(task.id.as_str()))
+                .map(|agent| agent.id.clone());
+            self.coordination_log.publish(CoordinationEvent::TaskCancelled {
+                task_id: task.id.clone(),
+                agent_id,
+                timestamp: now,
+            });
+        }
+        for task in &cancelled {
+            self.on_task_finished(task).await;
+        }
+        Ok(self.tasks.read().await.get(task_id).cloned())
+    }
+
+    /// POST the task's final state to `url` once it finishes
+    ///
+    /// A task that already finished is delivered straight away. Returns false
+    /// for an unknown task.
+    pub async fn register_webhook(&self, task_id: &str, url: String) -> bool {
+        let Some(task) = self.get_task_status(task_id).await else {
+            return false;
+        };
+        self.webhooks.register(task_id, url);
+        if is_finished(&task.status) {
+            self.webhooks.notify(&task, self.task_usage(task_id));
+        }
+        true
+    }
+
+    /// Callback registered for a task and how its delivery went
+    pub fn webhook_delivery(&self, task_id: &str) -> Option<WebhookDelivery> {
+        self.webhooks.delivery(task_id)
+    }
+
+    /// Tasks that failed after exhausting their retries
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.list().await
+    }
+
+    /// Give a dead-lettered task another run
+    ///
+    /// Returns None if the task isn't in the dead-letter queue.
+    pub async fn requeue_dead_letter(&self, task_id: &str) -> Result<Option<AgentTask>, String> {
+        if self.task_queue.is_full().await {
+            return Err("Task queue is full. Please try again later.".to_string());
+        }
+        let Some(task) = self.dead_letters.requeue(task_id).await else {
+            return Ok(None);
+        };
+        if let Err(e) = self.store.delete_dead_letter(task_id).await {
+            tracing::warn!("{}", e);
+        }
+        
+        self.tasks.write().await.insert(task.id.clone(), task.clone());
+        self.persist_task(&task, true).await;
+        self.task_queue.enqueue(task.clone()).await?;
+        tracing::info!("Requeued dead-lettered task {}", task_id);
+        Ok(Some(task))
+    }
+
+    /// Tokens and estimated cost spent on a task, subtasks included
+    pub fn task_usage(&self, task_id: &str) -> Option<TaskUsage> {
+        self.executor.budget().usage(task_id)
+    }
+
     /// List all tasks
     pub async fn list_tasks(&self) -> Vec<AgentTask> {
         let tasks = self.tasks.read().await;
@@ -564,17 +1274,46 @@ impl AgentManager {
     }
 
     /// Send message between agents
+    ///
+    /// A directed message goes to the `to` agent's inbox; one without a target
+    /// is broadcast to every other agent. Messages for unknown agents still
+    /// reach the coordination log but no inbox.
     pub async fn send_message(&self, message: AgentMessage) -> Result<(), String> {
-        // For now, just log the message
-        // In future, implement message routing
-        tracing::info!("Agent message: {:?} -> {:?}: {:?}", 
-            message.from, 
-            message.to, 
-            message.message_type
+        let recipients: Vec<String> = {
+            let agents = self.agents.read().await;
+            match &message.to {
+                Some(to) if agents.contains_key(to) => vec![to.clone()],
+                Some(to) => {
+                    tracing::warn!("Message from {} is addressed to unknown agent {}", message.from, to);
+                    Vec::new()
+                }
+                None => agents.keys().filter(|id| **id != message.from).cloned().collect(),
+            }
+        };
+        let delivered = self.mailboxes.deliver(&message, recipients.iter().map(String::as_str));
+        tracing::info!("Agent message: {:?} -> {:?}: {:?} (delivered to {} inbox(es))",
+            message.from,
+            message.to,
+            message.message_type,
+            delivered
         );
+        self.coordination_log.publish(CoordinationEvent::Message { message });
         Ok(())
     }
 
+    /// Take the messages waiting in an agent's inbox, oldest first
+    pub async fn receive_messages(&self, agent_id: &str) -> Result<Vec<AgentMessage>, String> {
+        if !self.agents.read().await.contains_key(agent_id) {
+            return Err(format!("Agent {} not found", agent_id));
+        }
+        Ok(self.mailboxes.drain(agent_id))
+    }
+
+    /// Live feed of agent messages and task assignments
+    pub fn coordination_log(&self) -> Arc<CoordinationLog> {
+        Arc::clone(&self.coordination_log)
+    }
+
     /// Get agents by type
     pub async fn get_agents_by_type(&self, agent_type: AgentType) -> Vec<Agent> {
         let agents = self.agents.read().await;
@@ -593,3 +1332,273 @@ impl AgentManager {
             .collect()
     }
 }
+
+#[derive(Debug, PartialEq)]
+enum DependencyState {
+    Ready,
+    Waiting,
+    Failed(String),
+}
+
+/// Whether `task_id` can run given the status of the tasks it depends on
+///
+/// A dependency that isn't tracked (for example a finished task too old to be
+/// restored after a restart) doesn't hold the task back.
+fn dependency_state(
+    dependencies: &HashMap<String, Vec<String>>,
+    tasks: &HashMap<String, AgentTask>,
+    task_id: &str,
+) -> DependencyState {
+    let mut waiting = false;
+    for dependency in dependencies.get(task_id).into_iter().flatten() {
+        match tasks.get(dependency).map(|t| &t.status) {
+            Some(TaskStatus::Failed) => {
+                return DependencyState::Failed(format!("Dependency {} failed", dependency));
+            }
+            Some(TaskStatus::Cancelled) => {
+                return DependencyState::Failed(format!("Dependency {} was cancelled", dependency));
+            }
+            Some(TaskStatus::Pending | TaskStatus::Waiting | TaskStatus::Processing) => waiting = true,
+            Some(TaskStatus::Completed) | None => {}
+        }
+    }
+    if waiting {
+        DependencyState::Waiting
+    } else {
+        DependencyState::Ready
+    }
+}
+
+fn is_finished(status: &TaskStatus) -> bool {
+    matches!(status, TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled)
+}
+
+/// Validate a bulk request against the cap, then insert every agent it describes
+pub(crate) fn insert_bulk_agents(
+    agents: &mut HashMap<String, Agent>,
+    specs: &[BulkAgentSpec],
+    security_config: &AgentSecurityConfig,
+) -> Result<Vec<Agent>, AgentSecurityError> {
+    // Counts are caller-supplied; a sum that overflows is over the cap
+    let requested = specs.iter()
+        .try_fold(0usize, |total, spec| total.checked_add(spec.count))
+        .unwrap_or(usize::MAX);
+    validate_bulk_agent_count(agents.len(), requested, security_config)?;
+    
+    let mut created = Vec::with_capacity(requested);
+    for spec in specs {
+        let base_name = spec.name.clone().unwrap_or_else(|| format!("{:?}", spec.agent_type));
+        for i in 0..spec.count {
+            let name = if spec.count > 1 {
+                format!("{}-{}", base_name, i + 1)
+            } else {
+                base_name.clone()
+            };
+            let agent = Agent::new(
+                Uuid::new_v4().to_string(),
+                sanitize_task_description(&name),
+                spec.agent_type.clone(),
+            );
+            agents.insert(agent.id.clone(), agent.clone());
+            created.push(agent);
+        }
+    }
+    
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CodebaseContext, Priority};
+
+    #[tokio::test]
+    async fn test_stuck_agent_is_recovered() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let manager = AgentManager::with_security_config(
+            Arc::new(ModelRouter::new(&config)),
+            Arc::clone(&config),
+            AgentSecurityConfig::default(),
+            None,
+        );
+
+        let stuck = manager.create_agent(AgentType::CodeGenerator, None).await.unwrap();
+        let busy = manager.create_agent(AgentType::Tester, None).await.unwrap();
+        let task = AgentTask {
+            id: "task-panicked".to_string(),
+            r#type: TaskType::CodeGeneration,
+            description: "Write the handler".to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::Medium,
+            status: TaskStatus::Processing,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        // It was one subtask of a decomposed task
+        let parent = AgentTask { id: "task-parent".to_string(), status: TaskStatus::Waiting, ..task.clone() };
+        manager.tasks.write().await.insert(parent.id.clone(), parent);
+        manager.executor.budget().register_subtask(&task.id, "task-parent");
+        manager.tasks.write().await.insert(task.id.clone(), task);
+        manager.backpressure.reserve().await.unwrap();
+
+        // The first agent's task panicked long ago; the second is still running its task
+        let long_ago = chrono::Utc::now() - chrono::Duration::seconds(config.agent_stuck_timeout_secs as i64 + 60);
+        let _live = InFlightGuard::new(&manager.in_flight, "task-running");
+        {
+            let mut agents = manager.agents.write().await;
+            for (id, task_id) in [(&stuck.id, "task-panicked"), (&busy.id, "task-running")] {
+                let agent = agents.get_mut(id).unwrap();
+                agent.status = AgentStatus::Working;
+                agent.current_task = Some(task_id.to_string());
+                agent.last_heartbeat = long_ago;
+            }
+        }
+
+        let recovered = manager.recover_stuck_agents().await;
+        assert_eq!(recovered, vec![stuck.id.clone()]);
+
+        let agent = manager.get_agent(&stuck.id).await.unwrap();
+        assert_eq!(agent.status, AgentStatus::Idle);
+        assert!(agent.current_task.is_none());
+        assert_eq!(manager.get_agent(&busy.id).await.unwrap().status, AgentStatus::Working);
+
+        let task = manager.get_task_status("task-panicked").await.unwrap();
+        assert!(matches!(task.status, TaskStatus::Failed));
+        assert_eq!(manager.backpressure.current_count().await, 0);
+        // Reclaiming the subtask settles its parent
+        let parent = manager.get_task_status("task-parent").await.unwrap();
+        assert!(matches!(parent.status, TaskStatus::Failed));
+
+        // Already reset, so a second sweep finds nothing
+        assert!(manager.recover_stuck_agents().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_signals_running_subtasks() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let manager = AgentManager::with_security_config(
+            Arc::new(ModelRouter::new(&config)),
+            Arc::clone(&config),
+            AgentSecurityConfig::default(),
+            None,
+        );
+
+        let task = |id: &str, status: TaskStatus| AgentTask {
+            id: id.to_string(),
+            r#type: TaskType::Documentation,
+            description: "Document the module".to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::Low,
+            status,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        {
+            let mut tasks = manager.tasks.write().await;
+            tasks.insert("parent".to_string(), task("parent", TaskStatus::Pending));
+            tasks.insert("parent-1".to_string(), task("parent-1", TaskStatus::Processing));
+            tasks.insert("parent-2".to_string(), task("parent-2", TaskStatus::Completed));
+        }
+        for subtask in ["parent-1", "parent-2"] {
+            manager.executor.budget().register_subtask(subtask, "parent");
+        }
+        let running = InFlightGuard::new(&manager.in_flight, "parent-1");
+
+        let cancelled = manager.cancel_task("parent").await.unwrap().unwrap();
+        assert!(matches!(cancelled.status, TaskStatus::Cancelled));
+        assert!(running.token().is_cancelled());
+        let subtask = manager.get_task_status("parent-1").await.unwrap();
+        assert!(matches!(subtask.status, TaskStatus::Cancelled));
+        // Finished subtasks keep their outcome
+        let done = manager.get_task_status("parent-2").await.unwrap();
+        assert!(matches!(done.status, TaskStatus::Completed));
+
+        assert!(manager.cancel_task("parent").await.is_err());
+        assert!(manager.cancel_task("missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subtasks_wait_for_their_dependencies() {
+        let task = |id: &str, priority: Priority, status: TaskStatus| AgentTask {
+            id: id.to_string(),
+            r#type: TaskType::CodeGeneration,
+            description: id.to_string(),
+            context: CodebaseContext::default(),
+            priority,
+            status,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        let mut tasks: HashMap<_, _> = [
+            task("generate", Priority::Low, TaskStatus::Pending),
+            task("review", Priority::Urgent, TaskStatus::Pending),
+            task("docs", Priority::Medium, TaskStatus::Pending),
+        ].into_iter().map(|t| (t.id.clone(), t)).collect();
+        let dependencies = HashMap::from([
+            ("review".to_string(), vec!["generate".to_string()]),
+            ("docs".to_string(), vec!["generate".to_string(), "untracked".to_string()]),
+        ]);
+
+        // The urgent review is passed over until generation is done
+        let queue = TaskQueue::new(10);
+        for t in tasks.values() {
+            queue.enqueue(t.clone()).await.unwrap();
+        }
+        let next = queue.dequeue_where(|t| dependency_state(&dependencies, &tasks, &t.id) != DependencyState::Waiting).await;
+        assert_eq!(next.unwrap().id, "generate");
+        assert_eq!(queue.size().await, 2);
+        assert_eq!(dependency_state(&dependencies, &tasks, "review"), DependencyState::Waiting);
+
+        tasks.get_mut("generate").unwrap().status = TaskStatus::Completed;
+        assert_eq!(dependency_state(&dependencies, &tasks, "review"), DependencyState::Ready);
+        assert_eq!(dependency_state(&dependencies, &tasks, "docs"), DependencyState::Ready);
+
+        tasks.get_mut("generate").unwrap().status = TaskStatus::Failed;
+        assert_eq!(
+            dependency_state(&dependencies, &tasks, "review"),
+            DependencyState::Failed("Dependency generate failed".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_running_tasks_and_reports_queued_ones() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let manager = AgentManager::with_security_config(
+            Arc::new(ModelRouter::new(&config)),
+            Arc::clone(&config),
+            AgentSecurityConfig::default(),
+            None,
+        );
+
+        // One task is mid-run and finishes shortly; another is still queued
+        manager.backpressure.reserve().await.unwrap();
+        let backpressure = Arc::clone(&manager.backpressure);
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            backpressure.release().await;
+        });
+        manager.task_queue.enqueue(AgentTask {
+            id: "queued".to_string(),
+            r#type: TaskType::Testing,
+            description: "Add tests".to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::Medium,
+            status: TaskStatus::Pending,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        }).await.unwrap();
+
+        let report = manager.shutdown(std::time::Duration::from_secs(5)).await;
+        assert_eq!(report, ShutdownReport { drained: 1, still_running: 0, persisted: 0, unsaved: 1 });
+        assert!(manager.is_shutting_down());
+        assert_eq!(manager.task_queue.size().await, 0);
+    }
+}
@@ -18,6 +18,12 @@ pub mod timeout;
 pub mod monitoring;
 pub mod fault_tolerance;
 pub mod queue;
+pub mod coordination;
+pub mod budget;
+pub mod artifacts;
+pub mod persistence;
+pub mod mailbox;
+pub mod webhooks;
 
 #[cfg(test)]
 mod tests;
@@ -25,9 +31,15 @@ mod tests;
 pub use fault_tolerance::*;
 pub use queue::*;
 
-pub use manager::AgentManager;
+pub use manager::{AgentManager, QueueLoad, ShutdownReport};
 pub use executor::AgentExecutor;
 pub use decomposer::TaskDecomposer;
+pub use coordination::{CoordinationLog, CoordinationEvent, CoordinationFilter};
+pub use budget::{TaskBudgetLedger, TaskUsage, BudgetExceeded};
+pub use artifacts::{ArtifactLimits, ArtifactLimitTable};
+pub use persistence::AgentStore;
+pub use mailbox::AgentMailboxes;
+pub use webhooks::{WebhookNotifier, WebhookDelivery, DeliveryStatus};
 pub use types::*;
 pub use security::*;
 pub use timeout::*;
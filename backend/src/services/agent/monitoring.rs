@@ -5,8 +5,18 @@
  */
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use std::collections::HashMap;
-use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Recent windows reported next to the lifetime totals (label, length in seconds)
+pub const METRIC_WINDOWS: [(&str, i64); 2] = [("5m", 300), ("1h", 3600)];
+
+/// Completed-task samples kept for windowed stats, oldest dropped first
+const MAX_SAMPLES: usize = 10_000;
+
+/// Upper bounds (ms) of the execution-time histogram buckets; +Inf is implied
+pub const EXECUTION_TIME_BUCKETS_MS: [u64; 10] = [100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 300_000];
 
 /// Agent metrics
 #[derive(Debug, Clone)]
@@ -17,6 +27,9 @@ pub struct AgentMetrics {
     pub failed_tasks: u64,
     pub total_execution_time_ms: u64,
     pub total_tokens_used: u64,
+    pub task_retries: u64, // Re-executions after a failed attempt
+    /// Completed tasks at or under each bound in EXECUTION_TIME_BUCKETS_MS (cumulative)
+    pub execution_time_buckets: [u64; EXECUTION_TIME_BUCKETS_MS.len()],
     pub active_agents: usize,
     pub active_tasks: usize,
 }
@@ -30,16 +43,40 @@ impl Default for AgentMetrics {
             failed_tasks: 0,
             total_execution_time_ms: 0,
             total_tokens_used: 0,
+            task_retries: 0,
+            execution_time_buckets: [0; EXECUTION_TIME_BUCKETS_MS.len()],
             active_agents: 0,
             active_tasks: 0,
         }
     }
 }
 
+/// One completed task, for rolling-window stats
+#[derive(Debug, Clone)]
+struct TaskSample {
+    completed_at: DateTime<Utc>,
+    success: bool,
+    execution_time_ms: u64,
+    tokens_used: u64,
+}
+
+/// Task figures over a recent window
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowStats {
+    pub window_secs: i64,
+    pub tasks_completed: u64,
+    pub successful_tasks: u64,
+    pub failed_tasks: u64,
+    pub success_rate: f64,
+    pub average_execution_time_ms: f64,
+    pub tokens_used: u64,
+}
+
 /// Metrics collector
 pub struct MetricsCollector {
     metrics: Arc<RwLock<AgentMetrics>>,
     agent_start_times: Arc<RwLock<HashMap<String, chrono::DateTime<Utc>>>>,
+    samples: Arc<RwLock<VecDeque<TaskSample>>>, // Oldest first, at most the longest window old
 }
 
 impl MetricsCollector {
@@ -47,6 +84,7 @@ impl MetricsCollector {
         Self {
             metrics: Arc::new(RwLock::new(AgentMetrics::default())),
             agent_start_times: Arc::new(RwLock::new(HashMap::new())),
+            samples: Arc::new(RwLock::new(VecDeque::new())),
         }
     }
     
@@ -81,6 +119,11 @@ impl MetricsCollector {
         }
         
         metrics.total_execution_time_ms += execution_time_ms;
+        for (count, bound) in metrics.execution_time_buckets.iter_mut().zip(EXECUTION_TIME_BUCKETS_MS) {
+            if execution_time_ms <= bound {
+                *count += 1;
+            }
+        }
         if let Some(tokens) = tokens_used {
             metrics.total_tokens_used += tokens as u64;
         }
@@ -91,6 +134,32 @@ impl MetricsCollector {
         
         let mut start_times = self.agent_start_times.write().await;
         start_times.remove(task_id);
+        drop(start_times);
+        drop(metrics);
+        
+        self.record_sample(TaskSample {
+            completed_at: Utc::now(),
+            success,
+            execution_time_ms,
+            tokens_used: tokens_used.unwrap_or(0) as u64,
+        }).await;
+    }
+    
+    async fn record_sample(&self, sample: TaskSample) {
+        let longest = METRIC_WINDOWS.iter().map(|(_, secs)| *secs).max().unwrap_or(0);
+        let cutoff = sample.completed_at - Duration::seconds(longest);
+        
+        let mut samples = self.samples.write().await;
+        samples.push_back(sample);
+        while samples.len() > MAX_SAMPLES
+            || samples.front().map(|s| s.completed_at < cutoff).unwrap_or(false)
+        {
+            samples.pop_front();
+        }
+    }
+    
+    pub async fn record_retry(&self) {
+        self.metrics.write().await.task_retries += 1;
     }
     
     pub async fn record_agent_idle(&self) {
@@ -114,6 +183,65 @@ impl MetricsCollector {
         }
     }
     
+    /// Stats for tasks completed within the last `window_secs`
+    pub async fn window_stats(&self, window_secs: i64) -> WindowStats {
+        self.window_stats_at(Utc::now(), window_secs).await
+    }
+    
+    async fn window_stats_at(&self, now: DateTime<Utc>, window_secs: i64) -> WindowStats {
+        let since = now - Duration::seconds(window_secs);
+        let samples = self.samples.read().await;
+        let mut stats = WindowStats {
+            window_secs,
+            tasks_completed: 0,
+            successful_tasks: 0,
+            failed_tasks: 0,
+            success_rate: 0.0,
+            average_execution_time_ms: 0.0,
+            tokens_used: 0,
+        };
+        let mut execution_time_ms = 0u64;
+        
+        for sample in samples.iter().rev().take_while(|s| s.completed_at >= since) {
+            stats.tasks_completed += 1;
+            if sample.success {
+                stats.successful_tasks += 1;
+            } else {
+                stats.failed_tasks += 1;
+            }
+            execution_time_ms += sample.execution_time_ms;
+            stats.tokens_used += sample.tokens_used;
+        }
+        
+        if stats.tasks_completed > 0 {
+            stats.success_rate = stats.successful_tasks as f64 / stats.tasks_completed as f64;
+            stats.average_execution_time_ms = execution_time_ms as f64 / stats.tasks_completed as f64;
+        }
+        stats
+    }
+    
+    /// Stats for every window in METRIC_WINDOWS, keyed by label
+    pub async fn recent_stats(&self) -> HashMap<&'static str, WindowStats> {
+        let mut recent = HashMap::new();
+        for (label, secs) in METRIC_WINDOWS {
+            recent.insert(label, self.window_stats(secs).await);
+        }
+        recent
+    }
+    
+    /// Zero the lifetime totals and drop all samples
+    ///
+    /// Active agent and task counts describe the present, so they are kept.
+    pub async fn reset(&self) {
+        let mut metrics = self.metrics.write().await;
+        *metrics = AgentMetrics {
+            active_agents: metrics.active_agents,
+            active_tasks: metrics.active_tasks,
+            ..AgentMetrics::default()
+        };
+        self.samples.write().await.clear();
+    }
+    
     pub async fn get_success_rate(&self) -> f64 {
         let metrics = self.metrics.read().await;
         if metrics.total_tasks_executed > 0 {
@@ -129,3 +257,43 @@ impl Default for MetricsCollector {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(completed_at: DateTime<Utc>, success: bool, execution_time_ms: u64) -> TaskSample {
+        TaskSample { completed_at, success, execution_time_ms, tokens_used: 100 }
+    }
+
+    #[tokio::test]
+    async fn test_recent_window_ignores_old_samples() {
+        let collector = MetricsCollector::new();
+        let now = Utc::now();
+
+        // A slow, failing spike 50 minutes ago, then two quick successes
+        for _ in 0..3 {
+            collector.record_sample(sample(now - Duration::minutes(50), false, 60_000)).await;
+        }
+        collector.record_sample(sample(now - Duration::minutes(2), true, 200)).await;
+        collector.record_sample(sample(now - Duration::minutes(1), true, 400)).await;
+
+        let five_minutes = collector.window_stats_at(now, 300).await;
+        assert_eq!(five_minutes.tasks_completed, 2);
+        assert_eq!(five_minutes.failed_tasks, 0);
+        assert_eq!(five_minutes.success_rate, 1.0);
+        assert_eq!(five_minutes.average_execution_time_ms, 300.0);
+
+        let hour = collector.window_stats_at(now, 3600).await;
+        assert_eq!(hour.tasks_completed, 5);
+        assert_eq!(hour.failed_tasks, 3);
+
+        // Samples older than the longest window are dropped as new ones arrive
+        collector.record_sample(sample(now + Duration::minutes(55), true, 1)).await;
+        assert_eq!(collector.samples.read().await.len(), 3);
+
+        collector.reset().await;
+        assert_eq!(collector.window_stats_at(now, 3600).await.tasks_completed, 0);
+        assert_eq!(collector.get_metrics().await.total_tasks_executed, 0);
+    }
+}
@@ -0,0 +1,530 @@
+/**
+ * Agent Persistence
+ *
+ * Mirrors agents, tasks (with the subtasks each one waits for) and
+ * dead-lettered tasks into Postgres so a restart doesn't lose them.
+ * Every write is an upsert of the whole row; on startup the rows are read back
+ * and any queued task that hadn't finished is put back on the queue. Without a
+ * database every call is a no-op and the manager runs purely in memory.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use crate::database::Database;
+use crate::types::{AgentTask, TaskStatus};
+use super::types::{Agent, AgentStatus};
+use super::fault_tolerance::TaskCheckpoint;
+use super::queue::DeadLetter;
+
+/// Finished tasks brought back at startup; unfinished ones are always restored
+const RESTORED_FINISHED_TASKS: i64 = 200;
+
+/// Row shape of `agents`
+#[derive(Debug, Clone, FromRow)]
+pub struct AgentRecord {
+    pub id: String,
+    pub name: String,
+    pub agent_type: String,
+    pub status: String,
+    pub agent_data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Row shape of `agent_tasks`
+#[derive(Debug, Clone, FromRow)]
+pub struct TaskRecord {
+    pub id: String,
+    pub task_type: String,
+    pub description: String,
+    pub priority: String,
+    pub status: String,
+    pub context: serde_json::Value,
+    pub result: Option<String>,
+    pub error: Option<String>,
+    pub queued: bool,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl AgentRecord {
+    pub fn from_agent(agent: &Agent) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: agent.id.clone(),
+            name: agent.name.clone(),
+            agent_type: enum_to_db(&agent.agent_type)?,
+            status: enum_to_db(&agent.status)?,
+            agent_data: serde_json::to_value(agent)?,
+            created_at: agent.created_at,
+        })
+    }
+
+    pub fn into_agent(self) -> anyhow::Result<Agent> {
+        Ok(serde_json::from_value(self.agent_data)?)
+    }
+}
+
+impl TaskRecord {
+    pub fn from_task(task: &AgentTask, queued: bool) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: task.id.clone(),
+            task_type: enum_to_db(&task.r#type)?,
+            description: task.description.clone(),
+            priority: enum_to_db(&task.priority)?,
+            status: enum_to_db(&task.status)?,
+            context: serde_json::to_value(&task.context)?,
+            result: task.result.clone(),
+            error: task.error.clone(),
+            queued,
+            created_at: task.created_at,
+            completed_at: task.completed_at,
+        })
+    }
+
+    pub fn into_task(self) -> anyhow::Result<AgentTask> {
+        let text = serde_json::Value::String;
+        Ok(AgentTask {
+            id: self.id,
+            r#type: serde_json::from_value(text(self.task_type))?,
+            description: self.description,
+            context: serde_json::from_value(self.context)?,
+            priority: serde_json::from_value(text(self.priority))?,
+            status: serde_json::from_value(text(self.status))?,
+            result: self.result,
+            error: self.error,
+            created_at: self.created_at,
+            completed_at: self.completed_at,
+        })
+    }
+}
+
+/// Enum as stored in the database (its serde name)
+fn enum_to_db<T: Serialize>(value: &T) -> anyhow::Result<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(s) => Ok(s),
+        other => anyhow::bail!("Unexpected enum encoding: {}", other),
+    }
+}
+
+/// Persisted state, ready to be put back into the manager
+#[derive(Debug, Default)]
+pub struct RestoredState {
+    pub agents: Vec<Agent>,
+    pub tasks: Vec<AgentTask>,
+    /// Queued tasks that never finished, reset to pending
+    pub requeue: Vec<AgentTask>,
+    /// Re-queued task ID -> tasks it still waits for
+    pub dependencies: HashMap<String, Vec<String>>,
+}
+
+/// Undo the effects of the process stopping mid-work
+///
+/// Nothing is running after a restart, so busy agents go back to idle and
+/// unfinished queued tasks are reset to pending for another run. Parents
+/// waiting on subtasks keep waiting.
+pub fn prepare_restore(agents: Vec<Agent>, tasks: Vec<(AgentTask, bool)>) -> RestoredState {
+    let agents = agents.into_iter()
+        .map(|mut agent| {
+            if matches!(agent.status, AgentStatus::Working | AgentStatus::Waiting) {
+                agent.status = AgentStatus::Idle;
+                agent.current_task = None;
+            }
+            agent
+        })
+        .collect();
+
+    let mut restored = Vec::with_capacity(tasks.len());
+    let mut requeue = Vec::new();
+    for (mut task, queued) in tasks {
+        if !matches!(task.status, TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled | TaskStatus::Waiting) {
+            task.status = TaskStatus::Pending;
+            if queued {
+                requeue.push(task.clone());
+            }
+        }
+        restored.push(task);
+    }
+
+    RestoredState { agents, tasks: restored, requeue, dependencies: HashMap::new() }
+}
+
+pub struct AgentStore {
+    database: Option<Arc<Database>>,
+}
+
+impl AgentStore {
+    pub fn new(database: Option<Arc<Database>>) -> Self {
+        Self { database }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.database.is_some()
+    }
+
+    /// Upsert one agent
+    pub async fn save_agent(&self, agent: &Agent) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+        let record = AgentRecord::from_agent(agent)?;
+
+        sqlx::query(
+            "INSERT INTO agents (id, name, agent_type, status, agent_data, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (id) DO UPDATE SET
+                name = EXCLUDED.name,
+                status = EXCLUDED.status,
+                agent_data = EXCLUDED.agent_data,
+                updated_at = NOW()"
+        )
+        .bind(&record.id)
+        .bind(&record.name)
+        .bind(&record.agent_type)
+        .bind(&record.status)
+        .bind(&record.agent_data)
+        .bind(record.created_at)
+        .execute(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to save agent {}: {}", record.id, e))?;
+        Ok(())
+    }
+
+    /// Remove an agent's row
+    pub async fn delete_agent(&self, agent_id: &str) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+        sqlx::query("DELETE FROM agents WHERE id = $1")
+            .bind(agent_id)
+            .execute(db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to delete agent {}: {}", agent_id, e))?;
+        Ok(())
+    }
+
+    /// Upsert one task; `queued` marks tasks handed to the execution queue
+    pub async fn save_task(&self, task: &AgentTask, queued: bool) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+        let record = TaskRecord::from_task(task, queued)?;
+
+        sqlx::query(
+            "INSERT INTO agent_tasks (
+                id, task_type, description, priority, status, context,
+                result, error, queued, created_at, completed_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (id) DO UPDATE SET
+                status = EXCLUDED.status,
+                result = EXCLUDED.result,
+                error = EXCLUDED.error,
+                queued = agent_tasks.queued OR EXCLUDED.queued,
+                completed_at = EXCLUDED.completed_at,
+                updated_at = NOW()"
+        )
+        .bind(&record.id)
+        .bind(&record.task_type)
+        .bind(&record.description)
+        .bind(&record.priority)
+        .bind(&record.status)
+        .bind(&record.context)
+        .bind(&record.result)
+        .bind(&record.error)
+        .bind(record.queued)
+        .bind(record.created_at)
+        .bind(record.completed_at)
+        .execute(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to save task {}: {}", record.id, e))?;
+        Ok(())
+    }
+
+    /// Upsert the checkpoint of a running task
+    pub async fn save_checkpoint(&self, checkpoint: &TaskCheckpoint) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+
+        sqlx::query(
+            "INSERT INTO task_checkpoints (task_id, agent_id, checkpoint_data, created_at)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (task_id) DO UPDATE SET
+                agent_id = EXCLUDED.agent_id,
+                checkpoint_data = EXCLUDED.checkpoint_data,
+                created_at = EXCLUDED.created_at"
+        )
+        .bind(&checkpoint.task_id)
+        .bind(&checkpoint.agent_id)
+        .bind(&checkpoint.checkpoint_data)
+        .bind(checkpoint.created_at)
+        .execute(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to save checkpoint for task {}: {}", checkpoint.task_id, e))?;
+        Ok(())
+    }
+
+    pub async fn delete_checkpoint(&self, task_id: &str) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+
+        sqlx::query("DELETE FROM task_checkpoints WHERE task_id = $1")
+            .bind(task_id)
+            .execute(db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to delete checkpoint for task {}: {}", task_id, e))?;
+        Ok(())
+    }
+
+    pub async fn load_checkpoints(&self) -> anyhow::Result<Vec<TaskCheckpoint>> {
+        let Some(ref db) = self.database else {
+            return Ok(Vec::new());
+        };
+
+        let rows = sqlx::query_as::<_, (String, String, serde_json::Value, DateTime<Utc>)>(
+            "SELECT task_id, agent_id, checkpoint_data, created_at FROM task_checkpoints"
+        )
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load checkpoints: {}", e))?;
+
+        Ok(rows.into_iter()
+            .map(|(task_id, agent_id, checkpoint_data, created_at)| TaskCheckpoint {
+                task_id,
+                agent_id,
+                checkpoint_data,
+                created_at,
+            })
+            .collect())
+    }
+
+    /// Record the tasks `task_id` waits for before it may run
+    pub async fn save_dependencies(&self, task_id: &str, depends_on: &[String]) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+
+        sqlx::query(
+            "INSERT INTO agent_task_dependencies (task_id, depends_on)
+             SELECT $1, UNNEST($2::VARCHAR[])
+             ON CONFLICT DO NOTHING"
+        )
+        .bind(task_id)
+        .bind(depends_on)
+        .execute(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to save dependencies of task {}: {}", task_id, e))?;
+        Ok(())
+    }
+
+    /// Forget what `task_id` waits for, once it has left the queue
+    pub async fn delete_dependencies(&self, task_id: &str) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+
+        sqlx::query("DELETE FROM agent_task_dependencies WHERE task_id = $1")
+            .bind(task_id)
+            .execute(db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to delete dependencies of task {}: {}", task_id, e))?;
+        Ok(())
+    }
+
+    /// Upsert a dead-lettered task, dropping the oldest rows beyond `capacity`
+    pub async fn save_dead_letter(&self, dead_letter: &DeadLetter, capacity: usize) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+        let task_id = &dead_letter.task.id;
+
+        sqlx::query(
+            "INSERT INTO agent_dead_letters (task_id, task_data, error, attempts, failed_at)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (task_id) DO UPDATE SET
+                task_data = EXCLUDED.task_data,
+                error = EXCLUDED.error,
+                attempts = EXCLUDED.attempts,
+                failed_at = EXCLUDED.failed_at"
+        )
+        .bind(task_id)
+        .bind(serde_json::to_value(&dead_letter.task)?)
+        .bind(&dead_letter.error)
+        .bind(dead_letter.attempts as i32)
+        .bind(dead_letter.failed_at)
+        .execute(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to save dead letter for task {}: {}", task_id, e))?;
+
+        sqlx::query(
+            "DELETE FROM agent_dead_letters WHERE task_id NOT IN (
+                SELECT task_id FROM agent_dead_letters ORDER BY failed_at DESC LIMIT $1
+            )"
+        )
+        .bind(capacity as i64)
+        .execute(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to prune dead letters: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn delete_dead_letter(&self, task_id: &str) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+
+        sqlx::query("DELETE FROM agent_dead_letters WHERE task_id = $1")
+            .bind(task_id)
+            .execute(db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to delete dead letter for task {}: {}", task_id, e))?;
+        Ok(())
+    }
+
+    /// The newest `limit` dead letters, oldest first
+    pub async fn load_dead_letters(&self, limit: usize) -> anyhow::Result<Vec<DeadLetter>> {
+        let Some(ref db) = self.database else {
+            return Ok(Vec::new());
+        };
+
+        let rows = sqlx::query_as::<_, (String, serde_json::Value, String, i32, DateTime<Utc>)>(
+            "SELECT task_id, task_data, error, attempts, failed_at FROM (
+                SELECT * FROM agent_dead_letters ORDER BY failed_at DESC LIMIT $1
+             ) newest
+             ORDER BY failed_at"
+        )
+        .bind(limit as i64)
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load dead letters: {}", e))?;
+
+        Ok(rows.into_iter()
+            .filter_map(|(task_id, task_data, error, attempts, failed_at)| {
+                let task = serde_json::from_value(task_data)
+                    .map_err(|e| tracing::warn!("Skipping unreadable dead letter {}: {}", task_id, e))
+                    .ok()?;
+                Some(DeadLetter { task, error, attempts: attempts.max(0) as u32, failed_at })
+            })
+            .collect())
+    }
+
+    /// Every agent, all unfinished tasks and the most recently finished ones
+    pub async fn load(&self) -> anyhow::Result<RestoredState> {
+        let Some(ref db) = self.database else {
+            return Ok(RestoredState::default());
+        };
+
+        let agents = sqlx::query_as::<_, AgentRecord>(
+            "SELECT id, name, agent_type, status, agent_data, created_at FROM agents ORDER BY created_at"
+        )
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load agents: {}", e))?;
+
+        let tasks = sqlx::query_as::<_, TaskRecord>(
+            "SELECT id, task_type, description, priority, status, context,
+                    result, error, queued, created_at, completed_at
+             FROM agent_tasks
+             WHERE status NOT IN ('completed', 'failed', 'cancelled')
+                OR id IN (
+                    SELECT id FROM agent_tasks
+                    WHERE status IN ('completed', 'failed', 'cancelled')
+                    ORDER BY completed_at DESC NULLS LAST
+                    LIMIT $1
+                )
+             ORDER BY created_at"
+        )
+        .bind(RESTORED_FINISHED_TASKS)
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load tasks: {}", e))?;
+
+        // A row that no longer parses is skipped rather than blocking startup
+        let agents = agents.into_iter()
+            .filter_map(|record| {
+                let id = record.id.clone();
+                record.into_agent()
+                    .map_err(|e| tracing::warn!("Skipping unreadable agent {}: {}", id, e))
+                    .ok()
+            })
+            .collect();
+        let tasks = tasks.into_iter()
+            .filter_map(|record| {
+                let (id, queued) = (record.id.clone(), record.queued);
+                record.into_task()
+                    .map(|task| (task, queued))
+                    .map_err(|e| tracing::warn!("Skipping unreadable task {}: {}", id, e))
+                    .ok()
+            })
+            .collect();
+
+        let edges = sqlx::query_as::<_, (String, String)>(
+            "SELECT task_id, depends_on FROM agent_task_dependencies ORDER BY task_id"
+        )
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load task dependencies: {}", e))?;
+
+        let mut restored = prepare_restore(agents, tasks);
+        for (task_id, depends_on) in edges {
+            if restored.requeue.iter().any(|task| task.id == task_id) {
+                restored.dependencies.entry(task_id).or_default().push(depends_on);
+            }
+        }
+        Ok(restored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CodebaseContext, Priority, TaskType};
+    use super::super::types::AgentType;
+
+    fn task(id: &str, status: TaskStatus) -> AgentTask {
+        AgentTask {
+            id: id.to_string(),
+            r#type: TaskType::Testing,
+            description: "Cover the parser".to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::High,
+            status,
+            result: None,
+            error: None,
+            created_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_restore_requeues_unfinished_work_and_frees_agents() {
+        let mut busy = Agent::new("agent-1".to_string(), "Tester".to_string(), AgentType::Tester);
+        busy.status = AgentStatus::Working;
+        busy.current_task = Some("sub-1".to_string());
+
+        // Records round-trip through their database representation
+        let record = TaskRecord::from_task(&task("sub-1", TaskStatus::Processing), true).unwrap();
+        assert_eq!(record.status, "processing");
+        assert_eq!(record.task_type, "testing");
+        let running = record.into_task().unwrap();
+        let agent = AgentRecord::from_agent(&busy).unwrap();
+        assert_eq!(agent.agent_type, "tester");
+        let busy = agent.into_agent().unwrap();
+
+        let state = prepare_restore(vec![busy], vec![
+            (task("parent", TaskStatus::Waiting), false),
+            (running, true),
+            (task("sub-2", TaskStatus::Completed), true),
+        ]);
+
+        assert_eq!(state.agents[0].status, AgentStatus::Idle);
+        assert!(state.agents[0].current_task.is_none());
+        assert_eq!(state.tasks.len(), 3);
+        // Only the interrupted subtask runs again; the parent was never queued itself
+        let requeued: Vec<_> = state.requeue.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(requeued, vec!["sub-1"]);
+        assert!(matches!(state.requeue[0].status, TaskStatus::Pending));
+        assert!(matches!(state.tasks[0].status, TaskStatus::Waiting));
+    }
+}
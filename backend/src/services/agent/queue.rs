@@ -5,9 +5,11 @@
  * Handles 10x capacity with zero faults
  */
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use tokio::sync::RwLock;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, VecDeque};
 use std::cmp::Ordering;
+use serde::Serialize;
 use crate::types::{AgentTask, Priority, TaskStatus};
 use chrono::Utc;
 
@@ -17,11 +19,12 @@ pub struct QueuedTask {
     pub task: AgentTask,
     pub priority_score: u64,
     pub queued_at: chrono::DateTime<Utc>,
+    sequence: u64, // Enqueue order, for ties on created_at
 }
 
 impl PartialEq for QueuedTask {
     fn eq(&self, other: &Self) -> bool {
-        self.priority_score == other.priority_score
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -34,10 +37,12 @@ impl PartialOrd for QueuedTask {
 }
 
 impl Ord for QueuedTask {
+    /// `BinaryHeap` pops the greatest item: the highest priority, and within a
+    /// priority the task created first
     fn cmp(&self, other: &Self) -> Ordering {
-        // Higher priority score = higher priority
-        other.priority_score.cmp(&self.priority_score)
-            .then_with(|| self.queued_at.cmp(&other.queued_at))
+        self.priority_score.cmp(&other.priority_score)
+            .then_with(|| other.task.created_at.cmp(&self.task.created_at))
+            .then_with(|| other.sequence.cmp(&self.sequence))
     }
 }
 
@@ -46,6 +51,7 @@ pub struct TaskQueue {
     queue: Arc<RwLock<BinaryHeap<QueuedTask>>>,
     max_size: usize,
     current_size: Arc<RwLock<usize>>,
+    next_sequence: AtomicU64,
 }
 
 impl TaskQueue {
@@ -54,25 +60,21 @@ impl TaskQueue {
             queue: Arc::new(RwLock::new(BinaryHeap::new())),
             max_size,
             current_size: Arc::new(RwLock::new(0)),
+            next_sequence: AtomicU64::new(0),
         }
     }
     
     /// Calculate priority score
+    ///
+    /// Strictly by priority: any urgent task runs before any high one, however
+    /// long the high one has waited.
     fn calculate_priority_score(task: &AgentTask) -> u64 {
-        let priority_multiplier = match task.priority {
+        match task.priority {
             Priority::Urgent => 1000,
             Priority::High => 500,
             Priority::Medium => 100,
             Priority::Low => 10,
-        };
-        
-        // Age bonus (older tasks get slight priority boost)
-        let age_seconds = Utc::now()
-            .signed_duration_since(task.created_at)
-            .num_seconds();
-        let age_bonus = (age_seconds / 60) as u64; // 1 point per minute
-        
-        priority_multiplier + age_bonus
+        }
     }
     
     /// Enqueue task
@@ -88,6 +90,7 @@ impl TaskQueue {
             task,
             priority_score,
             queued_at: Utc::now(),
+            sequence: self.next_sequence.fetch_add(1, AtomicOrdering::Relaxed),
         };
         
         let mut queue = self.queue.write().await;
@@ -110,6 +113,53 @@ impl TaskQueue {
         }
     }
     
+    /// Dequeue the highest priority task that `eligible` accepts
+    ///
+    /// Tasks passed over keep their place in the queue.
+    pub async fn dequeue_where<F>(&self, mut eligible: F) -> Option<AgentTask>
+    where
+        F: FnMut(&AgentTask) -> bool,
+    {
+        let mut queue = self.queue.write().await;
+        let mut current_size = self.current_size.write().await;
+        
+        let mut skipped = Vec::new();
+        let mut found = None;
+        while let Some(queued_task) = queue.pop() {
+            if eligible(&queued_task.task) {
+                found = Some(queued_task.task);
+                break;
+            }
+            skipped.push(queued_task);
+        }
+        queue.extend(skipped);
+        
+        if found.is_some() {
+            *current_size -= 1;
+        }
+        found
+    }
+    
+    /// Take a task out of the queue before it runs
+    pub async fn remove(&self, task_id: &str) -> Option<AgentTask> {
+        let mut queue = self.queue.write().await;
+        let mut current_size = self.current_size.write().await;
+        
+        let mut removed = None;
+        queue.retain(|queued| {
+            if removed.is_none() && queued.task.id == task_id {
+                removed = Some(queued.task.clone());
+                false
+            } else {
+                true
+            }
+        });
+        if removed.is_some() {
+            *current_size -= 1;
+        }
+        removed
+    }
+    
     /// Get queue size
     pub async fn size(&self) -> usize {
         *self.current_size.read().await
@@ -134,6 +184,83 @@ impl TaskQueue {
     }
 }
 
+/// A task that failed for good, kept for inspection
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub task: AgentTask,
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: chrono::DateTime<Utc>,
+}
+
+/// Tasks that exhausted their retries, newest last
+///
+/// Bounded: once full, the oldest entry is dropped to make room.
+pub struct DeadLetterQueue {
+    entries: RwLock<VecDeque<DeadLetter>>,
+    max_size: usize,
+}
+
+impl DeadLetterQueue {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: RwLock::new(VecDeque::new()),
+            max_size,
+        }
+    }
+    
+    /// Add a failed task, replacing an older entry for it; returns the entry
+    pub async fn push(&self, task: AgentTask, error: String, attempts: u32) -> DeadLetter {
+        let dead_letter = DeadLetter {
+            task,
+            error,
+            attempts,
+            failed_at: Utc::now(),
+        };
+        let mut entries = self.entries.write().await;
+        entries.retain(|entry| entry.task.id != dead_letter.task.id);
+        while entries.len() >= self.max_size.max(1) {
+            entries.pop_front();
+        }
+        entries.push_back(dead_letter.clone());
+        dead_letter
+    }
+
+    /// Put back entries loaded from the database, oldest first
+    pub async fn restore(&self, restored: Vec<DeadLetter>) {
+        let mut entries = self.entries.write().await;
+        for dead_letter in restored {
+            if entries.iter().any(|entry| entry.task.id == dead_letter.task.id) {
+                continue;
+            }
+            while entries.len() >= self.max_size.max(1) {
+                entries.pop_front();
+            }
+            entries.push_back(dead_letter);
+        }
+    }
+    
+    pub async fn list(&self) -> Vec<DeadLetter> {
+        self.entries.read().await.iter().cloned().collect()
+    }
+    
+    /// Take a task back out, reset to pending so it can be queued again
+    pub async fn requeue(&self, task_id: &str) -> Option<AgentTask> {
+        let mut entries = self.entries.write().await;
+        let index = entries.iter().position(|entry| entry.task.id == task_id)?;
+        let mut task = entries.remove(index)?.task;
+        task.status = TaskStatus::Pending;
+        task.result = None;
+        task.error = None;
+        task.completed_at = None;
+        Some(task)
+    }
+    
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+}
+
 /// Backpressure manager
 pub struct BackpressureManager {
     pub max_concurrent_tasks: usize,
@@ -176,3 +303,69 @@ impl BackpressureManager {
         *self.current_concurrent.read().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CodebaseContext, TaskType};
+
+    fn task(id: &str, priority: Priority, created_at: chrono::DateTime<Utc>) -> AgentTask {
+        AgentTask {
+            id: id.to_string(),
+            r#type: TaskType::Documentation,
+            description: id.to_string(),
+            context: CodebaseContext::default(),
+            priority,
+            status: TaskStatus::Pending,
+            result: None,
+            error: None,
+            created_at,
+            completed_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_follows_priority_then_creation_order() {
+        let queue = TaskQueue::new(10);
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+
+        // An hour-old low priority task still waits behind everything else
+        queue.enqueue(task("docs-old", Priority::Low, start)).await.unwrap();
+        queue.enqueue(task("feature", Priority::Medium, at(5))).await.unwrap();
+        queue.enqueue(task("debug-2", Priority::Urgent, at(20))).await.unwrap();
+        queue.enqueue(task("review", Priority::High, at(10))).await.unwrap();
+        queue.enqueue(task("debug-1", Priority::Urgent, at(15))).await.unwrap();
+        queue.enqueue(task("docs-new", Priority::Low, at(30))).await.unwrap();
+        queue.enqueue(task("docs-tie", Priority::Low, at(30))).await.unwrap();
+
+        let mut order = Vec::new();
+        while let Some(task) = queue.dequeue().await {
+            order.push(task.id);
+        }
+        assert_eq!(order, vec!["debug-1", "debug-2", "review", "feature", "docs-old", "docs-new", "docs-tie"]);
+        assert_eq!(queue.size().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_are_bounded_and_requeued_as_pending() {
+        let dead_letters = DeadLetterQueue::new(2);
+        for id in ["a", "b", "c"] {
+            let mut failed = task(id, Priority::Medium, Utc::now());
+            failed.status = TaskStatus::Failed;
+            failed.error = Some("provider timed out".to_string());
+            dead_letters.push(failed, "provider timed out".to_string(), 4).await;
+        }
+
+        // The oldest entry made room for the newest
+        let ids: Vec<_> = dead_letters.list().await.into_iter().map(|d| d.task.id).collect();
+        assert_eq!(ids, vec!["b", "c"]);
+        assert_eq!(dead_letters.list().await[0].attempts, 4);
+
+        let task = dead_letters.requeue("b").await.unwrap();
+        assert!(matches!(task.status, TaskStatus::Pending));
+        assert!(task.error.is_none());
+        assert_eq!(dead_letters.len().await, 1);
+        assert!(dead_letters.requeue("b").await.is_none());
+    }
+}
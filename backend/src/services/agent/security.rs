@@ -238,6 +238,24 @@ pub fn validate_agent_count(
     Ok(())
 }
 
+/// Validate that `requested` more agents fit under the cap
+pub fn validate_bulk_agent_count(
+    current_count: usize,
+    requested: usize,
+    config: &AgentSecurityConfig,
+) -> Result<(), AgentSecurityError> {
+    // A total past usize::MAX is over any cap
+    let total = current_count.checked_add(requested).unwrap_or(usize::MAX);
+    if total > config.max_agents_per_user {
+        return Err(AgentSecurityError::TooManyAgents(
+            total,
+            config.max_agents_per_user,
+        ));
+    }
+    
+    Ok(())
+}
+
 /// Validate task count
 pub fn validate_task_count(
     current_count: usize,
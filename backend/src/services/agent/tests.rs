@@ -59,6 +59,140 @@ mod tests {
         assert!(!decomposed.dependencies.is_empty());
     }
     
+    #[test]
+    fn test_fast_path_skips_decomposition_for_simple_tasks() {
+        use crate::services::agent::decomposer::{TaskDecomposer, DEFAULT_FAST_PATH_THRESHOLD};
+        use crate::types::AgentTask;
+        use uuid::Uuid;
+        
+        let make_task = |task_type: TaskType, description: &str| AgentTask {
+            id: Uuid::new_v4().to_string(),
+            r#type: task_type,
+            description: description.to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::Medium,
+            status: crate::types::TaskStatus::Pending,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        
+        let simple = make_task(TaskType::Refactoring, "Rename variable foo to bar");
+        let decomposed = TaskDecomposer::decompose_with_threshold(simple, DEFAULT_FAST_PATH_THRESHOLD);
+        assert_eq!(decomposed.subtasks.len(), 1);
+        assert!(decomposed.dependencies.is_empty());
+        assert!(matches!(decomposed.subtasks[0].task_type, TaskType::Refactoring));
+        
+        let complex = make_task(
+            TaskType::CodeGeneration,
+            &"Build a paginated REST API with authentication, validation and caching ".repeat(6),
+        );
+        let decomposed = TaskDecomposer::decompose_with_threshold(complex, DEFAULT_FAST_PATH_THRESHOLD);
+        assert_eq!(decomposed.subtasks.len(), 3);
+        
+        // A zero threshold disables the fast path entirely
+        let simple = make_task(TaskType::Refactoring, "Rename variable foo to bar");
+        let decomposed = TaskDecomposer::decompose_with_threshold(simple, 0.0);
+        assert_eq!(decomposed.subtasks.len(), 3);
+    }
+    
+    #[test]
+    fn test_ai_decomposition_must_pass_schema_and_cycle_checks() {
+        use crate::services::agent::decomposer::TaskDecomposer;
+        use crate::types::AgentTask;
+        
+        let task = AgentTask {
+            id: "task-ai".to_string(),
+            r#type: TaskType::CodeGeneration,
+            description: "Add OAuth login with refresh tokens".to_string(),
+            context: CodebaseContext::default(),
+            priority: Priority::Medium,
+            status: crate::types::TaskStatus::Pending,
+            result: None,
+            error: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        
+        // Fenced JSON as a model without native JSON mode would return it
+        let valid = r#"Here is the plan:
+```json
+{"subtasks": [
+  {"key": "design", "description": "Design the token flow", "task_type": "code_analysis", "agent_type": "code_analyzer"},
+  {"key": "impl", "description": "Implement the OAuth callback", "task_type": "code_generation", "depends_on": ["design"]},
+  {"key": "tests", "description": "Test token refresh", "task_type": "testing", "priority": "high", "depends_on": ["impl"]}
+]}
+```"#;
+        let decomposed = TaskDecomposer::parse_ai_plan(&task, valid).unwrap();
+        assert_eq!(decomposed.subtasks.len(), 3);
+        assert_eq!(decomposed.dependencies.len(), 2);
+        assert_eq!(decomposed.subtasks[2].dependencies, vec![decomposed.subtasks[1].id.clone()]);
+        assert!(decomposed.subtasks.iter().all(|st| st.parent_id == "task-ai"));
+        
+        let cyclic = r#"{"subtasks": [
+            {"key": "a", "description": "First", "task_type": "code_analysis", "depends_on": ["b"]},
+            {"key": "b", "description": "Second", "task_type": "code_generation", "depends_on": ["a"]}
+        ]}"#;
+        assert!(TaskDecomposer::parse_ai_plan(&task, cyclic).is_err());
+        
+        let unknown_dependency = r#"{"subtasks": [
+            {"key": "a", "description": "First", "task_type": "code_analysis", "depends_on": ["missing"]}
+        ]}"#;
+        assert!(TaskDecomposer::parse_ai_plan(&task, unknown_dependency).is_err());
+        
+        let bad_schema = r#"{"subtasks": [{"key": "a", "description": "First", "task_type": "deploy"}]}"#;
+        assert!(TaskDecomposer::parse_ai_plan(&task, bad_schema).is_err());
+        
+        let chain: Vec<String> = (0..7)
+            .map(|i| {
+                let deps = if i == 0 { String::new() } else { format!("\"s{}\"", i - 1) };
+                format!(r#"{{"key": "s{}", "description": "Step {}", "task_type": "code_generation", "depends_on": [{}]}}"#, i, i, deps)
+            })
+            .collect();
+        let too_deep = format!(r#"{{"subtasks": [{}]}}"#, chain.join(","));
+        assert!(TaskDecomposer::parse_ai_plan(&task, &too_deep).is_err());
+    }
+    
+    #[test]
+    fn test_bulk_agent_creation_is_all_or_nothing() {
+        use crate::services::agent::manager::insert_bulk_agents;
+        use crate::services::agent::types::BulkAgentSpec;
+        use std::collections::HashMap;
+        
+        let config = AgentSecurityConfig {
+            max_agents_per_user: 5,
+            ..AgentSecurityConfig::default()
+        };
+        let mut agents = HashMap::new();
+        
+        let specs = vec![
+            BulkAgentSpec { agent_type: AgentType::Tester, name: Some("qa".to_string()), count: 2 },
+            BulkAgentSpec { agent_type: AgentType::Reviewer, name: None, count: 1 },
+        ];
+        let created = insert_bulk_agents(&mut agents, &specs, &config).unwrap();
+        assert_eq!(created.len(), 3);
+        assert_eq!(agents.len(), 3);
+        assert_eq!(created[1].name, "qa-2");
+        
+        // 3 existing + 3 requested exceeds the cap of 5: nothing is created
+        let over_cap = vec![
+            BulkAgentSpec { agent_type: AgentType::CodeGenerator, name: None, count: 3 },
+        ];
+        assert!(insert_bulk_agents(&mut agents, &over_cap, &config).is_err());
+        assert_eq!(agents.len(), 3);
+        
+        // Counts that overflow when summed are rejected, not wrapped under the cap
+        let overflowing = vec![
+            BulkAgentSpec { agent_type: AgentType::Tester, name: None, count: usize::MAX },
+            BulkAgentSpec { agent_type: AgentType::Tester, name: None, count: 2 },
+        ];
+        assert!(insert_bulk_agents(&mut agents, &overflowing, &config).is_err());
+        let huge = vec![BulkAgentSpec { agent_type: AgentType::Tester, name: None, count: usize::MAX - 1 }];
+        assert!(insert_bulk_agents(&mut agents, &huge, &config).is_err());
+        assert_eq!(agents.len(), 3);
+    }
+    
     #[test]
     fn test_security_validation() {
         let config = AgentSecurityConfig::default();
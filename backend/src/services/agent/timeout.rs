@@ -4,6 +4,7 @@
  * Prevents agents from running indefinitely
  */
 use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
 use crate::services::agent::executor::AgentExecutor;
 use crate::services::agent::types::{Agent, AgentExecutionResult, AgentTask};
 
@@ -17,7 +18,7 @@ pub async fn execute_with_timeout(
     task: AgentTask,
     timeout_duration: Duration,
 ) -> Result<AgentExecutionResult, String> {
-    match timeout(timeout_duration, executor.execute_task(agent, task)).await {
+    match timeout(timeout_duration, executor.execute_task(agent, task, CancellationToken::new())).await {
         Ok(result) => Ok(result),
         Err(_) => {
             tracing::warn!("Agent execution timed out after {:?}", timeout_duration);
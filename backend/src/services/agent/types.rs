@@ -15,10 +15,26 @@ pub struct Agent {
     pub current_task: Option<String>, // Task ID
     pub capabilities: Vec<Capability>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Last time the agent was assigned work or reported progress
+    #[serde(default = "chrono::Utc::now")]
+    pub last_heartbeat: chrono::DateTime<chrono::Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// One entry of a bulk agent-creation request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkAgentSpec {
+    pub agent_type: AgentType,
+    pub name: Option<String>,
+    #[serde(default = "default_bulk_count")]
+    pub count: usize,
+}
+
+fn default_bulk_count() -> usize {
+    1
+}
+
 /// Specialized agent types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
@@ -91,6 +107,17 @@ pub enum MessageType {
     Error,
 }
 
+/// How a task is broken into subtasks
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecompositionMode {
+    /// Fixed per-task-type templates
+    #[default]
+    Static,
+    /// Ask a model to plan subtasks from the task content, falling back to the templates
+    Ai,
+}
+
 /// Task decomposition result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DecomposedTask {
@@ -137,6 +164,10 @@ pub struct AgentExecutionResult {
     pub result: Option<String>,
     pub error: Option<String>,
     pub artifacts: Vec<Artifact>,
+    #[serde(default)]
+    pub artifacts_truncated: bool, // Result or artifacts went over the agent type's artifact limits
+    #[serde(default)]
+    pub cancelled: bool, // Stopped on request; neither a success nor a fault
     pub execution_time_ms: u64,
     pub tokens_used: Option<u32>,
 }
@@ -170,6 +201,7 @@ impl Agent {
             current_task: None,
             capabilities: Self::capabilities_for_type(&agent_type),
             created_at: chrono::Utc::now(),
+            last_heartbeat: chrono::Utc::now(),
             metadata: None,
         }
     }
@@ -0,0 +1,337 @@
+/**
+ * Provider adapters
+ *
+ * Everything provider-specific about a chat completion lives behind one trait:
+ * endpoint and auth, request serialization, response parsing and usage extraction.
+ * The HTTP round trip is shared, so adding a provider is a single impl.
+ */
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use crate::types::{AIRequest, AIResponse, ModelProvider, TokenUsage, MessageRole};
+use super::base::AIService;
+use super::key_pool::ApiKeyPool;
+
+pub trait ProviderAdapter: AIService {
+    fn provider(&self) -> ModelProvider;
+
+    /// Name used in error messages ("OpenAI", "Google Gemini", ...)
+    fn display_name(&self) -> &str;
+
+    /// Model used when the request doesn't pin one
+    fn default_model(&self) -> &str;
+
+    fn endpoint(&self, model: &str) -> String;
+
+    /// Auth and protocol headers for a call made with `api_key`; Content-Type is always JSON
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)>;
+
+    /// Keys calls rotate through; None for providers that don't authenticate
+    fn api_keys(&self) -> Option<&ApiKeyPool> {
+        None
+    }
+
+    fn build_request(&self, request: &AIRequest, model: &str) -> Value;
+
+    fn parse_response(&self, body: &Value, model: &str) -> anyhow::Result<AIResponse>;
+
+    fn extract_usage(&self, body: &Value) -> Option<TokenUsage>;
+
+    /// Whether the provider can take requests right now; self-hosted ones may be down
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    /// Stop background work such as health probes; called once shutdown starts
+    fn shutdown(&self) {}
+}
+
+/// Run a completion through an adapter
+pub async fn send_request<A: ProviderAdapter + ?Sized>(
+    adapter: &A,
+    client: &Client,
+    request: AIRequest,
+) -> anyhow::Result<AIResponse> {
+    adapter.validate_request(&request)?;
+
+    let model = request.model.clone().unwrap_or_else(|| adapter.default_model().to_string());
+    let body = adapter.build_request(&request, &model);
+    let response = post_with_key(adapter, client, &adapter.endpoint(&model), &body).await?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow::anyhow!("{} API error: {}", adapter.display_name(), error_text));
+    }
+
+    let json: Value = response.json().await?;
+    adapter.parse_response(&json, &model)
+}
+
+/// POST `body` using a key from the adapter's pool, moving to the next key on a 429
+///
+/// The 429 response is returned as-is once no other key is free.
+async fn post_with_key<A: ProviderAdapter + ?Sized>(
+    adapter: &A,
+    client: &Client,
+    url: &str,
+    body: &Value,
+) -> anyhow::Result<reqwest::Response> {
+    let pool = adapter.api_keys();
+    loop {
+        let lease = match pool {
+            Some(pool) => Some(pool.acquire().ok_or_else(|| {
+                anyhow::anyhow!("{} API error: every API key is rate limited", adapter.display_name())
+            })?),
+            None => None,
+        };
+
+        let mut http = client
+            .post(url)
+            .header("Content-Type", "application/json");
+        for (name, value) in adapter.headers(lease.as_ref().map_or("", |lease| lease.key())) {
+            http = http.header(name, value);
+        }
+        let response = http.json(body).send().await?;
+
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            if let (Some(pool), Some(lease)) = (pool, &lease) {
+                pool.mark_rate_limited(lease);
+                if pool.has_available() {
+                    continue;
+                }
+            }
+        }
+        return Ok(response);
+    }
+}
+
+/// Inputs sent per embeddings call; providers cap the batch size
+const EMBEDDING_BATCH_SIZE: usize = 128;
+
+/// Embed `texts` through an OpenAI-compatible `/v1/embeddings` endpoint, in batches
+pub async fn openai_compatible_embed<A: ProviderAdapter + ?Sized>(
+    adapter: &A,
+    client: &Client,
+    url: &str,
+    model: &str,
+    texts: Vec<String>,
+) -> anyhow::Result<Vec<Vec<f32>>> {
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBEDDING_BATCH_SIZE) {
+        let response = post_with_key(adapter, client, url, &json!({ "model": model, "input": batch })).await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("{} embeddings API error: {}", adapter.display_name(), error_text));
+        }
+
+        let json: Value = response.json().await?;
+        vectors.extend(parse_openai_embeddings(&json, batch.len())?);
+    }
+    Ok(vectors)
+}
+
+/// Vectors from a `/v1/embeddings` response, reordered by their `index`
+pub fn parse_openai_embeddings(body: &Value, expected: usize) -> anyhow::Result<Vec<Vec<f32>>> {
+    let data = body["data"].as_array()
+        .ok_or_else(|| anyhow::anyhow!("Invalid embeddings response format"))?;
+
+    let mut indexed = data.iter()
+        .enumerate()
+        .map(|(position, item)| {
+            let vector = item["embedding"].as_array()
+                .ok_or_else(|| anyhow::anyhow!("No embedding in response item {}", position))?
+                .iter()
+                .map(|x| x.as_f64().map(|x| x as f32))
+                .collect::<Option<Vec<f32>>>()
+                .ok_or_else(|| anyhow::anyhow!("Non-numeric embedding in response item {}", position))?;
+            let index = item["index"].as_u64().map(|i| i as usize).unwrap_or(position);
+            Ok((index, vector))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if indexed.len() != expected {
+        anyhow::bail!("Expected {} embeddings, got {}", expected, indexed.len());
+    }
+    indexed.sort_by_key(|(index, _)| *index);
+    Ok(indexed.into_iter().map(|(_, vector)| vector).collect())
+}
+
+/// Messages with the lowercase user/assistant/system roles most providers use
+pub fn chat_messages(request: &AIRequest) -> Vec<Value> {
+    request.messages
+        .iter()
+        .map(|msg| {
+            json!({
+                "role": match msg.role {
+                    MessageRole::User => "user",
+                    MessageRole::Assistant => "assistant",
+                    MessageRole::System => "system",
+                },
+                "content": msg.content
+            })
+        })
+        .collect()
+}
+
+/// The first system message, for providers that take it outside the conversation
+pub fn system_message(request: &AIRequest) -> Option<String> {
+    request.messages
+        .iter()
+        .find(|m| matches!(m.role, MessageRole::System))
+        .map(|m| m.content.clone())
+}
+
+/// `/v1/chat/completions` request body
+pub fn openai_compatible_body(request: &AIRequest, model: &str) -> Value {
+    let mut body = json!({
+        "model": model,
+        "messages": chat_messages(request),
+        "temperature": request.temperature.unwrap_or(0.7),
+        "max_tokens": request.max_tokens.unwrap_or(4000),
+    });
+    if request.json_output {
+        body["response_format"] = json!({ "type": "json_object" });
+    }
+    body
+}
+
+/// `usage` block of a `/v1/chat/completions` response
+pub fn openai_compatible_usage(body: &Value) -> Option<TokenUsage> {
+    body["usage"].as_object().map(|u| TokenUsage {
+        prompt_tokens: u["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+        completion_tokens: u["completion_tokens"].as_u64().unwrap_or(0) as u32,
+        total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as u32,
+    })
+}
+
+/// Parse a `/v1/chat/completions` response
+pub fn parse_openai_compatible(
+    body: &Value,
+    model: &str,
+    usage: Option<TokenUsage>,
+    metadata: &[(&str, &str)],
+) -> anyhow::Result<AIResponse> {
+    let choice = body["choices"][0].as_object()
+        .ok_or_else(|| anyhow::anyhow!("Invalid response format"))?;
+
+    let message = choice["message"].as_object()
+        .ok_or_else(|| anyhow::anyhow!("Invalid message format"))?;
+
+    let content = message["content"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("No content in response"))?
+        .to_string();
+
+    Ok(AIResponse {
+        content,
+        model: body["model"].as_str().unwrap_or(model).to_string(),
+        usage,
+        finish_reason: choice["finish_reason"].as_str().map(|s| s.to_string()),
+        metadata: Some(response_metadata(metadata)),
+    })
+}
+
+/// Response metadata from static key/value pairs ("provider", "specialization", ...)
+pub fn response_metadata(pairs: &[(&str, &str)]) -> HashMap<String, Value> {
+    pairs.iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value.to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::services::ai::{AnthropicService, CohereService, OpenAIService};
+    use crate::services::ai::base::EmbeddingsUnsupported;
+    use crate::types::AIMessage;
+
+    fn request() -> AIRequest {
+        let message = |role, content: &str| AIMessage {
+            role,
+            content: content.to_string(),
+            timestamp: None,
+            metadata: None,
+        };
+        AIRequest {
+            messages: vec![
+                message(MessageRole::System, "Be brief"),
+                message(MessageRole::User, "Hi"),
+            ],
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            stream: None,
+            context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
+        }
+    }
+
+    #[test]
+    fn test_request_serialization_is_per_provider() {
+        let config = Config::from_env().unwrap();
+
+        let openai = OpenAIService::new(&config).build_request(&request(), "gpt-4o");
+        assert_eq!(openai["messages"][0]["role"], "system");
+        assert_eq!(openai["max_tokens"], 4000);
+
+        // Anthropic takes the system prompt outside the conversation
+        let anthropic = AnthropicService::new(&config).build_request(&request(), "claude-3-5-sonnet-20241022");
+        assert_eq!(anthropic["system"], "Be brief");
+        assert_eq!(anthropic["messages"].as_array().unwrap().len(), 1);
+
+        let cohere = CohereService::new(&config).build_request(&request(), "command-r-plus");
+        assert_eq!(cohere["preamble"], "Be brief");
+        assert_eq!(cohere["message"], "Hi");
+    }
+
+    #[test]
+    fn test_usage_extraction_is_per_provider() {
+        let config = Config::from_env().unwrap();
+
+        let anthropic = AnthropicService::new(&config);
+        let body = json!({
+            "content": [{"text": "Hello"}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 12, "output_tokens": 3},
+        });
+        let response = anthropic.parse_response(&body, "claude-3-5-sonnet-20241022").unwrap();
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.usage.unwrap().total_tokens, 15);
+
+        let openai = OpenAIService::new(&config);
+        let body = json!({
+            "choices": [{"message": {"content": "Hello"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7},
+        });
+        let response = openai.parse_response(&body, "gpt-4o").unwrap();
+        assert_eq!(response.model, "gpt-4o");
+        assert_eq!(response.usage.unwrap().total_tokens, 7);
+        assert_eq!(response.metadata.unwrap()["provider"], "openai");
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_are_ordered_and_unsupported_is_reported() {
+        let body = json!({
+            "data": [
+                {"index": 1, "embedding": [0.5, -0.5]},
+                {"index": 0, "embedding": [1.0, 0.0]},
+            ],
+        });
+        let vectors = parse_openai_embeddings(&body, 2).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.5, -0.5]]);
+        assert!(parse_openai_embeddings(&body, 3).is_err());
+
+        let config = Config::from_env().unwrap();
+        assert!(OpenAIService::new(&config).supports_embeddings());
+        let anthropic = AnthropicService::new(&config);
+        assert!(!anthropic.supports_embeddings());
+        let error = anthropic.embed(vec!["fn main() {}".to_string()]).await.unwrap_err();
+        assert!(error.downcast_ref::<EmbeddingsUnsupported>().is_some());
+    }
+}
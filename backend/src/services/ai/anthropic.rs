@@ -4,13 +4,15 @@
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::json;
-use crate::types::{AIRequest, AIResponse, ModelCapabilities, TokenUsage, MessageRole};
+use crate::types::{AIRequest, AIResponse, ModelCapabilities, ModelProvider, TokenUsage, MessageRole};
 use crate::services::ai::base::AIService;
+use crate::services::ai::key_pool::ApiKeyPool;
+use crate::services::ai::adapter::{ProviderAdapter, send_request, system_message, response_metadata};
 use crate::config::Config;
 
 pub struct AnthropicService {
     client: Client,
-    api_key: String,
+    api_keys: ApiKeyPool,
     capabilities: ModelCapabilities,
 }
 
@@ -18,7 +20,7 @@ impl AnthropicService {
     pub fn new(config: &Config) -> Self {
         Self {
             client: Client::new(),
-            api_key: config.anthropic_api_key.clone(),
+            api_keys: ApiKeyPool::for_provider(config, ModelProvider::Anthropic, &config.anthropic_api_key),
             capabilities: ModelCapabilities {
                 supports_vision: true,
                 supports_function_calling: true,
@@ -46,16 +48,40 @@ impl AIService for AnthropicService {
     }
     
     async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
-        self.validate_request(&request)?;
-        
-        let model = request.model.as_deref().unwrap_or("claude-3-5-sonnet-20241022");
-        
-        // Separate system message from conversation
-        let system_message = request.messages
-            .iter()
-            .find(|m| matches!(m.role, MessageRole::System))
-            .map(|m| m.content.clone());
-        
+        send_request(self, &self.client, request).await
+    }
+}
+
+impl ProviderAdapter for AnthropicService {
+    fn provider(&self) -> ModelProvider {
+        ModelProvider::Anthropic
+    }
+    
+    fn display_name(&self) -> &str {
+        "Anthropic"
+    }
+    
+    fn default_model(&self) -> &str {
+        "claude-3-5-sonnet-20241022"
+    }
+    
+    fn endpoint(&self, _model: &str) -> String {
+        "https://api.anthropic.com/v1/messages".to_string()
+    }
+    
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("x-api-key", api_key.to_string()),
+            ("anthropic-version", "2023-06-01".to_string()),
+        ]
+    }
+    
+    fn api_keys(&self) -> Option<&ApiKeyPool> {
+        Some(&self.api_keys)
+    }
+    
+    fn build_request(&self, request: &AIRequest, model: &str) -> serde_json::Value {
+        // System prompt goes outside the conversation
         let messages: Vec<serde_json::Value> = request.messages
             .iter()
             .filter(|m| !matches!(m.role, MessageRole::System))
@@ -78,27 +104,15 @@ impl AIService for AnthropicService {
             "messages": messages,
         });
         
-        if let Some(system) = system_message {
+        if let Some(system) = system_message(request) {
             body["system"] = json!(system);
         }
         
-        let response = self.client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Anthropic API error: {}", error_text));
-        }
-        
-        let json: serde_json::Value = response.json().await?;
-        
-        let content_block = json["content"][0].as_object()
+        body
+    }
+    
+    fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+        let content_block = body["content"][0].as_object()
             .ok_or_else(|| anyhow::anyhow!("Invalid response format"))?;
         
         let content = content_block["text"]
@@ -106,23 +120,21 @@ impl AIService for AnthropicService {
             .ok_or_else(|| anyhow::anyhow!("No text in response"))?
             .to_string();
         
-        let usage = json["usage"].as_object().map(|u| TokenUsage {
+        Ok(AIResponse {
+            content,
+            model: body["model"].as_str().unwrap_or(model).to_string(),
+            usage: self.extract_usage(body),
+            finish_reason: body["stop_reason"].as_str().map(|s| s.to_string()),
+            metadata: Some(response_metadata(&[("provider", "anthropic")])),
+        })
+    }
+    
+    fn extract_usage(&self, body: &serde_json::Value) -> Option<TokenUsage> {
+        body["usage"].as_object().map(|u| TokenUsage {
             prompt_tokens: u["input_tokens"].as_u64().unwrap_or(0) as u32,
             completion_tokens: u["output_tokens"].as_u64().unwrap_or(0) as u32,
             total_tokens: u["input_tokens"].as_u64().unwrap_or(0) as u32 + 
                          u["output_tokens"].as_u64().unwrap_or(0) as u32,
-        });
-        
-        Ok(AIResponse {
-            content,
-            model: json["model"].as_str().unwrap_or(model).to_string(),
-            usage,
-            finish_reason: json["stop_reason"].as_str().map(|s| s.to_string()),
-            metadata: Some({
-                let mut meta = std::collections::HashMap::new();
-                meta.insert("provider".to_string(), serde_json::Value::String("anthropic".to_string()));
-                meta
-            }),
         })
     }
 }
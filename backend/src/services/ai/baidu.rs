@@ -5,13 +5,15 @@
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::json;
-use crate::types::{AIRequest, AIResponse, ModelCapabilities, TokenUsage, MessageRole};
+use crate::types::{AIRequest, AIResponse, ModelCapabilities, ModelProvider, TokenUsage};
 use crate::services::ai::base::AIService;
+use crate::services::ai::key_pool::ApiKeyPool;
+use crate::services::ai::adapter::{ProviderAdapter, send_request, chat_messages, response_metadata};
 use crate::config::Config;
 
 pub struct BaiduService {
     client: Client,
-    api_key: String,
+    api_keys: ApiKeyPool,
     capabilities: ModelCapabilities,
 }
 
@@ -19,7 +21,7 @@ impl BaiduService {
     pub fn new(config: &Config) -> Self {
         Self {
             client: Client::new(),
-            api_key: config.baidu_api_key.clone(),
+            api_keys: ApiKeyPool::for_provider(config, ModelProvider::Baidu, &config.baidu_api_key),
             capabilities: ModelCapabilities {
                 supports_vision: true,
                 supports_function_calling: true,
@@ -47,68 +49,63 @@ impl AIService for BaiduService {
     }
     
     async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
-        self.validate_request(&request)?;
-        
-        let model = request.model.as_deref().unwrap_or("ernie-4.0-8k");
-        
-        // Baidu uses a different format
-        let messages: Vec<serde_json::Value> = request.messages
-            .iter()
-            .map(|msg| {
-                json!({
-                    "role": match msg.role {
-                        MessageRole::User => "user",
-                        MessageRole::Assistant => "assistant",
-                        MessageRole::System => "system",
-                    },
-                    "content": msg.content
-                })
-            })
-            .collect();
-        
-        let body = json!({
+        send_request(self, &self.client, request).await
+    }
+}
+
+impl ProviderAdapter for BaiduService {
+    fn provider(&self) -> ModelProvider {
+        ModelProvider::Baidu
+    }
+    
+    fn display_name(&self) -> &str {
+        "Baidu"
+    }
+    
+    fn default_model(&self) -> &str {
+        "ernie-4.0-8k"
+    }
+    
+    fn endpoint(&self, _model: &str) -> String {
+        "https://aip.baidubce.com/rpc/2.0/ai_custom/v1/wenxinworkshop/chat/completions".to_string()
+    }
+    
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![("Authorization", format!("Bearer {}", api_key))]
+    }
+    
+    fn api_keys(&self) -> Option<&ApiKeyPool> {
+        Some(&self.api_keys)
+    }
+    
+    fn build_request(&self, request: &AIRequest, model: &str) -> serde_json::Value {
+        json!({
             "model": model,
-            "messages": messages,
+            "messages": chat_messages(request),
             "temperature": request.temperature.unwrap_or(0.7),
             "max_output_tokens": request.max_tokens.unwrap_or(4000),
-        });
-        
-        let response = self.client
-            .post("https://aip.baidubce.com/rpc/2.0/ai_custom/v1/wenxinworkshop/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Baidu API error: {}", error_text));
-        }
-        
-        let json: serde_json::Value = response.json().await?;
-        
-        let result = json["result"].as_str()
+        })
+    }
+    
+    fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+        let result = body["result"].as_str()
             .ok_or_else(|| anyhow::anyhow!("No result in response"))?
             .to_string();
         
-        let usage = json["usage"].as_object().map(|u| TokenUsage {
+        Ok(AIResponse {
+            content: result,
+            model: body["model"].as_str().unwrap_or(model).to_string(),
+            usage: self.extract_usage(body),
+            finish_reason: body["finish_reason"].as_str().map(|s| s.to_string()),
+            metadata: Some(response_metadata(&[("provider", "baidu"), ("specialization", "chinese")])),
+        })
+    }
+    
+    fn extract_usage(&self, body: &serde_json::Value) -> Option<TokenUsage> {
+        body["usage"].as_object().map(|u| TokenUsage {
             prompt_tokens: u["prompt_tokens"].as_u64().unwrap_or(0) as u32,
             completion_tokens: u["completion_tokens"].as_u64().unwrap_or(0) as u32,
             total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as u32,
-        });
-        
-        Ok(AIResponse {
-            content: result,
-            model: json["model"].as_str().unwrap_or(model).to_string(),
-            usage,
-            finish_reason: json["finish_reason"].as_str().map(|s| s.to_string()),
-            metadata: Some({
-                let mut meta = std::collections::HashMap::new();
-                meta.insert("provider".to_string(), serde_json::Value::String("baidu".to_string()));
-                meta.insert("specialization".to_string(), serde_json::Value::String("chinese".to_string()));
-                meta
-            }),
         })
     }
 }
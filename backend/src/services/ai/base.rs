@@ -4,6 +4,13 @@
 use async_trait::async_trait;
 use crate::types::{AIRequest, AIResponse, ModelCapabilities};
 
+/// Returned by `embed` for providers without an embeddings API
+#[derive(Debug, thiserror::Error)]
+#[error("Embeddings are unsupported by {provider}")]
+pub struct EmbeddingsUnsupported {
+    pub provider: String,
+}
+
 #[async_trait]
 pub trait AIService: Send + Sync {
     fn name(&self) -> &str;
@@ -11,6 +18,17 @@ pub trait AIService: Send + Sync {
     
     async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse>;
     
+    /// One embedding vector per input text, in input order
+    async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let _ = texts;
+        Err(EmbeddingsUnsupported { provider: self.name().to_string() }.into())
+    }
+    
+    /// Whether `embed` is implemented for this provider
+    fn supports_embeddings(&self) -> bool {
+        false
+    }
+    
     fn estimate_tokens(&self, text: &str) -> u32 {
         // Rough estimation: ~4 characters per token
         (text.len() as f32 / 4.0).ceil() as u32
@@ -5,13 +5,15 @@
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::json;
-use crate::types::{AIRequest, AIResponse, ModelCapabilities, TokenUsage, MessageRole};
+use crate::types::{AIRequest, AIResponse, ModelCapabilities, ModelProvider, TokenUsage, MessageRole};
 use crate::services::ai::base::AIService;
+use crate::services::ai::key_pool::ApiKeyPool;
+use crate::services::ai::adapter::{ProviderAdapter, send_request, system_message, response_metadata};
 use crate::config::Config;
 
 pub struct CohereService {
     client: Client,
-    api_key: String,
+    api_keys: ApiKeyPool,
     capabilities: ModelCapabilities,
 }
 
@@ -19,7 +21,7 @@ impl CohereService {
     pub fn new(config: &Config) -> Self {
         Self {
             client: Client::new(),
-            api_key: config.cohere_api_key.clone(),
+            api_keys: ApiKeyPool::for_provider(config, ModelProvider::Cohere, &config.cohere_api_key),
             capabilities: ModelCapabilities {
                 supports_vision: false,
                 supports_function_calling: true,
@@ -47,10 +49,36 @@ impl AIService for CohereService {
     }
     
     async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
-        self.validate_request(&request)?;
-        
-        let model = request.model.as_deref().unwrap_or("command-r-plus");
-        
+        send_request(self, &self.client, request).await
+    }
+}
+
+impl ProviderAdapter for CohereService {
+    fn provider(&self) -> ModelProvider {
+        ModelProvider::Cohere
+    }
+    
+    fn display_name(&self) -> &str {
+        "Cohere"
+    }
+    
+    fn default_model(&self) -> &str {
+        "command-r-plus"
+    }
+    
+    fn endpoint(&self, _model: &str) -> String {
+        "https://api.cohere.ai/v1/chat".to_string()
+    }
+    
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![("Authorization", format!("Bearer {}", api_key))]
+    }
+    
+    fn api_keys(&self) -> Option<&ApiKeyPool> {
+        Some(&self.api_keys)
+    }
+    
+    fn build_request(&self, request: &AIRequest, model: &str) -> serde_json::Value {
         // Cohere uses a different format - convert messages to chat format
         let chat_history: Vec<serde_json::Value> = request.messages
             .iter()
@@ -67,11 +95,6 @@ impl AIService for CohereService {
             })
             .collect();
         
-        let system_message = request.messages
-            .iter()
-            .find(|m| matches!(m.role, MessageRole::System))
-            .map(|m| m.content.clone());
-        
         let mut body = json!({
             "model": model,
             "chat_history": chat_history,
@@ -80,50 +103,36 @@ impl AIService for CohereService {
             "max_tokens": request.max_tokens.unwrap_or(4000),
         });
         
-        if let Some(system) = system_message {
+        if let Some(system) = system_message(request) {
             body["preamble"] = json!(system);
         }
         
-        let response = self.client
-            .post("https://api.cohere.ai/v1/chat")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Cohere API error: {}", error_text));
-        }
-        
-        let json: serde_json::Value = response.json().await?;
-        
-        let content = json["text"]
+        body
+    }
+    
+    fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+        let content = body["text"]
             .as_str()
             .ok_or_else(|| anyhow::anyhow!("No text in response"))?
             .to_string();
         
-        let usage = json["meta"].as_object()
+        Ok(AIResponse {
+            content,
+            model: body["generation_id"].as_str().unwrap_or(model).to_string(),
+            usage: self.extract_usage(body),
+            finish_reason: body["finish_reason"].as_str().map(|s| s.to_string()),
+            metadata: Some(response_metadata(&[("provider", "cohere"), ("specialization", "enterprise")])),
+        })
+    }
+    
+    fn extract_usage(&self, body: &serde_json::Value) -> Option<TokenUsage> {
+        body["meta"].as_object()
             .and_then(|m| m["tokens"].as_object())
             .map(|t| TokenUsage {
                 prompt_tokens: t["input_tokens"].as_u64().unwrap_or(0) as u32,
                 completion_tokens: t["output_tokens"].as_u64().unwrap_or(0) as u32,
                 total_tokens: t["input_tokens"].as_u64().unwrap_or(0) as u32 + 
                              t["output_tokens"].as_u64().unwrap_or(0) as u32,
-            });
-        
-        Ok(AIResponse {
-            content,
-            model: json["generation_id"].as_str().unwrap_or(model).to_string(),
-            usage,
-            finish_reason: json["finish_reason"].as_str().map(|s| s.to_string()),
-            metadata: Some({
-                let mut meta = std::collections::HashMap::new();
-                meta.insert("provider".to_string(), serde_json::Value::String("cohere".to_string()));
-                meta.insert("specialization".to_string(), serde_json::Value::String("enterprise".to_string()));
-                meta
-            }),
-        })
+            })
     }
 }
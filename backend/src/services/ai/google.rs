@@ -4,13 +4,15 @@
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::json;
-use crate::types::{AIRequest, AIResponse, ModelCapabilities, TokenUsage, MessageRole};
+use crate::types::{AIRequest, AIResponse, ModelCapabilities, ModelProvider, TokenUsage, MessageRole};
 use crate::services::ai::base::AIService;
+use crate::services::ai::key_pool::ApiKeyPool;
+use crate::services::ai::adapter::{ProviderAdapter, send_request, system_message, response_metadata};
 use crate::config::Config;
 
 pub struct GoogleService {
     client: Client,
-    api_key: String,
+    api_keys: ApiKeyPool,
     capabilities: ModelCapabilities,
 }
 
@@ -18,7 +20,7 @@ impl GoogleService {
     pub fn new(config: &Config) -> Self {
         Self {
             client: Client::new(),
-            api_key: config.google_gemini_api_key.clone(),
+            api_keys: ApiKeyPool::for_provider(config, ModelProvider::Google, &config.google_gemini_api_key),
             capabilities: ModelCapabilities {
                 supports_vision: true,
                 supports_function_calling: true,
@@ -46,18 +48,40 @@ impl AIService for GoogleService {
     }
     
     async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
-        self.validate_request(&request)?;
-        
-        let model = request.model.as_deref().unwrap_or("gemini-1.5-pro");
-        
+        send_request(self, &self.client, request).await
+    }
+}
+
+impl ProviderAdapter for GoogleService {
+    fn provider(&self) -> ModelProvider {
+        ModelProvider::Google
+    }
+    
+    fn display_name(&self) -> &str {
+        "Google Gemini"
+    }
+    
+    fn default_model(&self) -> &str {
+        "gemini-1.5-pro"
+    }
+    
+    fn endpoint(&self, model: &str) -> String {
+        format!("https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent", model)
+    }
+    
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        // Sent as a header rather than `?key=` so the endpoint doesn't depend on which key is used
+        vec![("x-goog-api-key", api_key.to_string())]
+    }
+    
+    fn api_keys(&self) -> Option<&ApiKeyPool> {
+        Some(&self.api_keys)
+    }
+    
+    fn build_request(&self, request: &AIRequest, _model: &str) -> serde_json::Value {
         // Build prompt from messages
-        let system_message = request.messages
-            .iter()
-            .find(|m| matches!(m.role, MessageRole::System))
-            .map(|m| m.content.clone());
-        
         let mut prompt = String::new();
-        if let Some(system) = system_message {
+        if let Some(system) = system_message(request) {
             prompt.push_str(&format!("System: {}\n\n", system));
         }
         
@@ -71,7 +95,7 @@ impl AIService for GoogleService {
         }
         prompt.push_str("Assistant:");
         
-        let body = json!({
+        let mut body = json!({
             "contents": [{
                 "parts": [{
                     "text": prompt
@@ -82,27 +106,14 @@ impl AIService for GoogleService {
                 "maxOutputTokens": request.max_tokens.unwrap_or(4096),
             }
         });
-        
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model, self.api_key
-        );
-        
-        let response = self.client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Google Gemini API error: {}", error_text));
+        if request.json_output {
+            body["generationConfig"]["responseMimeType"] = json!("application/json");
         }
-        
-        let json: serde_json::Value = response.json().await?;
-        
-        let candidate = json["candidates"][0].as_object()
+        body
+    }
+    
+    fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+        let candidate = body["candidates"][0].as_object()
             .ok_or_else(|| anyhow::anyhow!("Invalid response format"))?;
         
         let content = candidate["content"]["parts"][0]["text"]
@@ -110,22 +121,20 @@ impl AIService for GoogleService {
             .ok_or_else(|| anyhow::anyhow!("No text in response"))?
             .to_string();
         
-        let usage = json["usageMetadata"].as_object().map(|u| TokenUsage {
-            prompt_tokens: u["promptTokenCount"].as_u64().unwrap_or(0) as u32,
-            completion_tokens: u["candidatesTokenCount"].as_u64().unwrap_or(0) as u32,
-            total_tokens: u["totalTokenCount"].as_u64().unwrap_or(0) as u32,
-        });
-        
         Ok(AIResponse {
             content,
             model: model.to_string(),
-            usage,
+            usage: self.extract_usage(body),
             finish_reason: candidate["finishReason"].as_str().map(|s| s.to_string()),
-            metadata: Some({
-                let mut meta = std::collections::HashMap::new();
-                meta.insert("provider".to_string(), serde_json::Value::String("google".to_string()));
-                meta
-            }),
+            metadata: Some(response_metadata(&[("provider", "google")])),
+        })
+    }
+    
+    fn extract_usage(&self, body: &serde_json::Value) -> Option<TokenUsage> {
+        body["usageMetadata"].as_object().map(|u| TokenUsage {
+            prompt_tokens: u["promptTokenCount"].as_u64().unwrap_or(0) as u32,
+            completion_tokens: u["candidatesTokenCount"].as_u64().unwrap_or(0) as u32,
+            total_tokens: u["totalTokenCount"].as_u64().unwrap_or(0) as u32,
         })
     }
 }
@@ -0,0 +1,329 @@
+/**
+ * Provider health tracking
+ *
+ * Counts consecutive failures per provider. A provider that keeps failing is
+ * marked unavailable for a cooldown, after which it gets another try; a single
+ * failure on that retry puts it straight back into cooldown. In circuit breaker
+ * terms the cooldown is the open state and the retry is half-open.
+ *
+ * Success and failure counts decay with a half-life, so the success rate
+ * reflects roughly the last half-life of calls rather than everything since
+ * startup. They and the latency average are snapshotted and used to seed a
+ * fresh tracker after a restart.
+ */
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::types::ModelProvider;
+use crate::services::agent::CircuitState;
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+const DEFAULT_HALF_LIFE: Duration = Duration::from_secs(3600);
+
+/// Weight of the newest call in the latency moving average
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+#[derive(Default)]
+struct ProviderState {
+    consecutive_failures: u32,
+    unavailable_until: Option<Instant>,
+    last_failure: Option<Instant>,
+    successes: f64, // Fractional once decayed
+    failures: f64,
+    decayed_at: Option<Instant>,
+    avg_latency_ms: Option<f64>,
+}
+
+impl ProviderState {
+    /// Halve the call counts for every `half_life` since they were last decayed
+    fn decay(&mut self, now: Instant, half_life: Duration) {
+        if let Some(since) = self.decayed_at {
+            let weight = decay_weight(now.saturating_duration_since(since), half_life);
+            self.successes *= weight;
+            self.failures *= weight;
+        }
+        self.decayed_at = Some(now);
+    }
+}
+
+/// Share of a count left after `age`; nothing survives a zero half-life
+fn decay_weight(age: Duration, half_life: Duration) -> f64 {
+    if half_life.is_zero() {
+        0.0
+    } else {
+        0.5_f64.powf(age.as_secs_f64() / half_life.as_secs_f64())
+    }
+}
+
+/// Point-in-time view of one provider's health, as persisted and served
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderHealthSummary {
+    pub provider: ModelProvider,
+    pub calls: f64,
+    pub success_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+    pub consecutive_failures: u32,
+    pub available: bool,
+    /// Open while cooling down, half-open while the next call decides
+    #[serde(default)]
+    pub circuit: CircuitState,
+    pub recorded_at: DateTime<Utc>,
+}
+
+pub struct ProviderHealthTracker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    half_life: Duration,
+    states: Mutex<HashMap<ModelProvider, ProviderState>>,
+}
+
+impl ProviderHealthTracker {
+    pub fn new() -> Self {
+        Self::with_limits(DEFAULT_FAILURE_THRESHOLD, DEFAULT_COOLDOWN)
+    }
+
+    pub fn with_limits(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            half_life: DEFAULT_HALF_LIFE,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Age at which a call counts half toward the success rate
+    pub fn with_half_life(mut self, half_life: Duration) -> Self {
+        self.half_life = half_life;
+        self
+    }
+
+    pub fn record_success(&self, provider: &ModelProvider) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(provider.clone()).or_default();
+        state.consecutive_failures = 0;
+        state.unavailable_until = None;
+        state.decay(Instant::now(), self.half_life);
+        state.successes += 1.0;
+    }
+
+    pub fn record_failure(&self, provider: &ModelProvider) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(provider.clone()).or_default();
+        state.consecutive_failures += 1;
+        state.decay(Instant::now(), self.half_life);
+        state.failures += 1.0;
+        state.last_failure = Some(Instant::now());
+        if state.consecutive_failures >= self.failure_threshold {
+            if !state.unavailable_until.is_some_and(|until| Instant::now() < until) {
+                tracing::warn!(
+                    "Provider {:?} unavailable after {} consecutive failures",
+                    provider,
+                    state.consecutive_failures
+                );
+            }
+            state.unavailable_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// False while the provider is cooling down after repeated failures
+    pub fn is_available(&self, provider: &ModelProvider) -> bool {
+        !self.states.lock().unwrap()
+            .get(provider)
+            .and_then(|state| state.unavailable_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Breaker state of one provider; providers never called are closed
+    pub fn circuit_state(&self, provider: &ModelProvider) -> CircuitState {
+        self.states.lock().unwrap()
+            .get(provider)
+            .map(|state| self.circuit_of(state, Instant::now()))
+            .unwrap_or(CircuitState::Closed)
+    }
+
+    fn circuit_of(&self, state: &ProviderState, now: Instant) -> CircuitState {
+        if state.unavailable_until.is_some_and(|until| now < until) {
+            CircuitState::Open
+        } else if state.consecutive_failures >= self.failure_threshold {
+            CircuitState::HalfOpen
+        } else {
+            CircuitState::Closed
+        }
+    }
+
+    /// The provider among `providers` whose last failure is oldest
+    ///
+    /// Used when every breaker is open: the one that failed longest ago is the
+    /// likeliest to have recovered. Providers that never failed come first.
+    pub fn least_recently_failed(&self, providers: &[ModelProvider]) -> Option<ModelProvider> {
+        let states = self.states.lock().unwrap();
+        providers.iter()
+            .min_by_key(|provider| states.get(provider).and_then(|state| state.last_failure))
+            .cloned()
+    }
+
+    /// Fold one call's round-trip time into the provider's moving average
+    pub fn record_latency(&self, provider: &ModelProvider, latency: Duration) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(provider.clone()).or_default();
+        let ms = latency.as_secs_f64() * 1000.0;
+        state.avg_latency_ms = Some(match state.avg_latency_ms {
+            Some(avg) => avg + LATENCY_SMOOTHING * (ms - avg),
+            None => ms,
+        });
+    }
+
+    /// Current health of every provider that has been called
+    pub fn snapshot(&self) -> Vec<ProviderHealthSummary> {
+        let now = Instant::now();
+        let recorded_at = Utc::now();
+        let mut states = self.states.lock().unwrap();
+        let mut summaries: Vec<ProviderHealthSummary> = states.iter_mut()
+            .map(|(provider, state)| {
+                state.decay(now, self.half_life);
+                let calls = state.successes + state.failures;
+                ProviderHealthSummary {
+                    provider: provider.clone(),
+                    calls,
+                    success_rate: if calls > 0.0 { state.successes / calls } else { 1.0 },
+                    avg_latency_ms: state.avg_latency_ms,
+                    consecutive_failures: state.consecutive_failures,
+                    available: !state.unavailable_until.is_some_and(|until| now < until),
+                    circuit: self.circuit_of(state, now),
+                    recorded_at,
+                }
+            })
+            .collect();
+        summaries.sort_by_key(|s| format!("{:?}", s.provider));
+        summaries
+    }
+
+    /// Seed state from persisted summaries, discounting them by age
+    ///
+    /// Call counts are halved every `half_life`. A provider that was cooling down
+    /// goes straight back into cooldown, unless its snapshot is older than one half-life.
+    pub fn seed(&self, summaries: &[ProviderHealthSummary], half_life: Duration) {
+        let now = Utc::now();
+        let mut states = self.states.lock().unwrap();
+        for summary in summaries {
+            let age = (now - summary.recorded_at).to_std().unwrap_or_default();
+            let calls = summary.calls * decay_weight(age, half_life);
+            let fresh = age < half_life;
+
+            let state = states.entry(summary.provider.clone()).or_default();
+            state.successes = calls * summary.success_rate;
+            state.failures = calls - state.successes;
+            state.decayed_at = Some(Instant::now());
+            state.avg_latency_ms = summary.avg_latency_ms;
+            state.consecutive_failures = if fresh { summary.consecutive_failures } else { 0 };
+            if fresh && state.consecutive_failures >= self.failure_threshold {
+                state.unavailable_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+}
+
+impl Default for ProviderHealthTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_recovers_after_cooldown() {
+        let tracker = ProviderHealthTracker::with_limits(2, Duration::from_millis(20));
+        let provider = ModelProvider::OpenAI;
+
+        tracker.record_failure(&provider);
+        assert!(tracker.is_available(&provider));
+        tracker.record_failure(&provider);
+        assert!(!tracker.is_available(&provider));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(tracker.is_available(&provider));
+
+        // Still past the threshold, so one more failure trips it again
+        tracker.record_failure(&provider);
+        assert!(!tracker.is_available(&provider));
+
+        tracker.record_success(&provider);
+        assert!(tracker.is_available(&provider));
+    }
+
+    #[test]
+    fn test_circuit_states_and_least_recently_failed() {
+        let tracker = ProviderHealthTracker::with_limits(1, Duration::from_millis(20));
+        tracker.record_failure(&ModelProvider::OpenAI);
+        std::thread::sleep(Duration::from_millis(2));
+        tracker.record_failure(&ModelProvider::Anthropic);
+
+        assert_eq!(tracker.circuit_state(&ModelProvider::OpenAI), CircuitState::Open);
+        assert_eq!(tracker.circuit_state(&ModelProvider::Google), CircuitState::Closed);
+        let both = [ModelProvider::Anthropic, ModelProvider::OpenAI];
+        assert_eq!(tracker.least_recently_failed(&both), Some(ModelProvider::OpenAI));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(tracker.circuit_state(&ModelProvider::OpenAI), CircuitState::HalfOpen);
+        let openai = tracker.snapshot().into_iter().find(|s| s.provider == ModelProvider::OpenAI).unwrap();
+        assert_eq!(openai.circuit, CircuitState::HalfOpen);
+        tracker.record_success(&ModelProvider::OpenAI);
+        assert_eq!(tracker.circuit_state(&ModelProvider::OpenAI), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_seeding_restores_degraded_providers() {
+        let tracker = ProviderHealthTracker::with_limits(2, Duration::from_secs(60));
+        for _ in 0..6 {
+            tracker.record_success(&ModelProvider::Anthropic);
+        }
+        tracker.record_latency(&ModelProvider::Anthropic, Duration::from_millis(400));
+        tracker.record_success(&ModelProvider::OpenAI);
+        tracker.record_failure(&ModelProvider::OpenAI);
+        tracker.record_failure(&ModelProvider::OpenAI);
+
+        let summaries = tracker.snapshot();
+        let restarted = ProviderHealthTracker::with_limits(2, Duration::from_secs(60));
+        restarted.seed(&summaries, Duration::from_secs(3600));
+        assert!(!restarted.is_available(&ModelProvider::OpenAI));
+        assert!(restarted.is_available(&ModelProvider::Anthropic));
+
+        let reseeded = restarted.snapshot();
+        let openai = reseeded.iter().find(|s| s.provider == ModelProvider::OpenAI).unwrap();
+        assert!((openai.success_rate - 1.0 / 3.0).abs() < 1e-6);
+        let anthropic = reseeded.iter().find(|s| s.provider == ModelProvider::Anthropic).unwrap();
+        assert_eq!(anthropic.avg_latency_ms, Some(400.0));
+
+        // Two half-lives later the outage no longer counts
+        let stale: Vec<ProviderHealthSummary> = summaries.into_iter()
+            .map(|s| ProviderHealthSummary { recorded_at: s.recorded_at - chrono::Duration::hours(2), ..s })
+            .collect();
+        let later = ProviderHealthTracker::with_limits(2, Duration::from_secs(60));
+        later.seed(&stale, Duration::from_secs(3600));
+        assert!(later.is_available(&ModelProvider::OpenAI));
+        let openai = later.snapshot().into_iter().find(|s| s.provider == ModelProvider::OpenAI).unwrap();
+        assert!((openai.calls - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_call_counts_decay_over_the_half_life() {
+        let tracker = ProviderHealthTracker::with_limits(10, Duration::from_secs(60))
+            .with_half_life(Duration::from_millis(50));
+        for _ in 0..4 {
+            tracker.record_failure(&ModelProvider::OpenAI);
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        tracker.record_success(&ModelProvider::OpenAI);
+
+        // The old failures have mostly decayed away; the recent success dominates
+        let openai = tracker.snapshot().into_iter().find(|s| s.provider == ModelProvider::OpenAI).unwrap();
+        assert!(openai.calls < 1.5, "{}", openai.calls);
+        assert!(openai.success_rate > 0.75, "{}", openai.success_rate);
+    }
+}
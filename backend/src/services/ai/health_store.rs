@@ -0,0 +1,225 @@
+/**
+ * Provider health persistence
+ *
+ * Writes the health tracker's summaries to `provider_health_snapshots` on an
+ * interval and reads the newest ones back at startup, so a restarted router
+ * remembers which providers were failing.
+ */
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use crate::database::Database;
+use crate::services::agent::CircuitState;
+use super::health::ProviderHealthSummary;
+use super::router::ModelRouter;
+
+/// Snapshots older than this are pruned on each save
+const RETENTION_DAYS: i64 = 30;
+
+/// Row shape of `provider_health_snapshots`
+#[derive(Debug, Clone, FromRow)]
+pub struct ProviderHealthRecord {
+    pub provider: String,
+    pub calls: f64,
+    pub success_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+    pub consecutive_failures: i32,
+    pub available: bool,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl ProviderHealthRecord {
+    pub fn from_summary(summary: &ProviderHealthSummary) -> anyhow::Result<Self> {
+        let provider = match serde_json::to_value(&summary.provider)? {
+            serde_json::Value::String(s) => s,
+            other => anyhow::bail!("Unexpected provider encoding: {}", other),
+        };
+        Ok(Self {
+            provider,
+            calls: summary.calls,
+            success_rate: summary.success_rate,
+            avg_latency_ms: summary.avg_latency_ms,
+            consecutive_failures: summary.consecutive_failures as i32,
+            available: summary.available,
+            recorded_at: summary.recorded_at,
+        })
+    }
+
+    pub fn into_summary(self) -> anyhow::Result<ProviderHealthSummary> {
+        Ok(ProviderHealthSummary {
+            provider: serde_json::from_value(serde_json::Value::String(self.provider))?,
+            calls: self.calls.max(0.0),
+            success_rate: self.success_rate.clamp(0.0, 1.0),
+            avg_latency_ms: self.avg_latency_ms,
+            consecutive_failures: self.consecutive_failures.max(0) as u32,
+            available: self.available,
+            circuit: if self.available { CircuitState::Closed } else { CircuitState::Open },
+            recorded_at: self.recorded_at,
+        })
+    }
+}
+
+fn into_summaries(rows: Vec<ProviderHealthRecord>) -> Vec<ProviderHealthSummary> {
+    rows.into_iter()
+        .filter_map(|row| {
+            let provider = row.provider.clone();
+            row.into_summary()
+                .map_err(|e| tracing::warn!("Skipping unreadable health snapshot for {}: {}", provider, e))
+                .ok()
+        })
+        .collect()
+}
+
+pub struct ProviderHealthStore {
+    database: Option<Arc<Database>>,
+}
+
+impl ProviderHealthStore {
+    pub fn new(database: Option<Arc<Database>>) -> Self {
+        Self { database }
+    }
+
+    /// Append one snapshot row per provider
+    pub async fn save(&self, summaries: &[ProviderHealthSummary]) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+
+        let mut tx = db.pool().begin().await
+            .map_err(|e| anyhow::anyhow!("Failed to start health snapshot transaction: {}", e))?;
+
+        for summary in summaries {
+            let record = ProviderHealthRecord::from_summary(summary)?;
+            sqlx::query(
+                "INSERT INTO provider_health_snapshots (
+                    provider, calls, success_rate, avg_latency_ms,
+                    consecutive_failures, available, recorded_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            )
+            .bind(&record.provider)
+            .bind(record.calls)
+            .bind(record.success_rate)
+            .bind(record.avg_latency_ms)
+            .bind(record.consecutive_failures)
+            .bind(record.available)
+            .bind(record.recorded_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to save health of {}: {}", record.provider, e))?;
+        }
+
+        sqlx::query("DELETE FROM provider_health_snapshots WHERE recorded_at < NOW() - make_interval(days => $1)")
+            .bind(RETENTION_DAYS as i32)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to prune health snapshots: {}", e))?;
+
+        tx.commit().await
+            .map_err(|e| anyhow::anyhow!("Failed to commit health snapshot: {}", e))?;
+        Ok(())
+    }
+
+    /// Newest snapshot of each provider, or None without a database
+    pub async fn load_latest(&self) -> anyhow::Result<Option<Vec<ProviderHealthSummary>>> {
+        let Some(ref db) = self.database else {
+            return Ok(None);
+        };
+
+        let rows = sqlx::query_as::<_, ProviderHealthRecord>(
+            "SELECT DISTINCT ON (provider)
+                    provider, calls, success_rate, avg_latency_ms,
+                    consecutive_failures, available, recorded_at
+             FROM provider_health_snapshots
+             ORDER BY provider, recorded_at DESC"
+        )
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load provider health: {}", e))?;
+
+        Ok(Some(into_summaries(rows)))
+    }
+
+    /// Snapshots recorded since `since`, oldest first
+    pub async fn history(&self, since: DateTime<Utc>) -> anyhow::Result<Vec<ProviderHealthSummary>> {
+        let Some(ref db) = self.database else {
+            return Ok(Vec::new());
+        };
+
+        let rows = sqlx::query_as::<_, ProviderHealthRecord>(
+            "SELECT provider, calls, success_rate, avg_latency_ms,
+                    consecutive_failures, available, recorded_at
+             FROM provider_health_snapshots
+             WHERE recorded_at >= $1
+             ORDER BY recorded_at, provider"
+        )
+        .bind(since)
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load provider health history: {}", e))?;
+
+        Ok(into_summaries(rows))
+    }
+
+    /// Seed the router's tracker from the database, then keep snapshotting it
+    pub async fn restore_and_snapshot(self: Arc<Self>, router: Arc<ModelRouter>, interval: Duration, half_life: Duration) {
+        match self.load_latest().await {
+            Ok(Some(summaries)) => {
+                tracing::info!("Seeded provider health from {} stored snapshots", summaries.len());
+                router.health().seed(&summaries, half_life);
+            }
+            Ok(None) => return, // No database: nothing to restore or save
+            Err(e) => tracing::warn!("Could not restore provider health: {}", e),
+        }
+
+        if interval.is_zero() {
+            return;
+        }
+        loop {
+            tokio::time::sleep(interval).await;
+            let summaries = router.health().snapshot();
+            if summaries.is_empty() {
+                continue;
+            }
+            if let Err(e) = self.save(&summaries).await {
+                tracing::warn!("Failed to snapshot provider health: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ai::health::ProviderHealthTracker;
+    use crate::types::ModelProvider;
+
+    #[test]
+    fn test_health_round_trips_through_records() {
+        let tracker = ProviderHealthTracker::with_limits(2, Duration::from_secs(60));
+        tracker.record_success(&ModelProvider::XAI);
+        tracker.record_latency(&ModelProvider::XAI, Duration::from_millis(250));
+        tracker.record_failure(&ModelProvider::XAI);
+        tracker.record_failure(&ModelProvider::XAI);
+
+        let summaries = tracker.snapshot();
+        let records: Vec<ProviderHealthRecord> = summaries.iter()
+            .map(|s| ProviderHealthRecord::from_summary(s).unwrap())
+            .collect();
+        assert_eq!(records[0].provider, "xai");
+
+        let restored = into_summaries(records);
+        assert_eq!(restored, summaries);
+
+        let restarted = ProviderHealthTracker::with_limits(2, Duration::from_secs(60));
+        restarted.seed(&restored, Duration::from_secs(3600));
+        assert!(!restarted.is_available(&ModelProvider::XAI));
+    }
+
+    #[tokio::test]
+    async fn test_without_database_nothing_is_loaded() {
+        let store = ProviderHealthStore::new(None);
+        assert!(store.load_latest().await.unwrap().is_none());
+        assert!(store.history(Utc::now()).await.unwrap().is_empty());
+    }
+}
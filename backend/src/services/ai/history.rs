@@ -0,0 +1,314 @@
+/**
+ * Conversation History Compaction
+ *
+ * Shortens long threads before routing once they pass a token threshold.
+ * The truncate strategy drops the oldest turns; the summarize strategy folds
+ * them into one summary message written by the cheapest configured model and
+ * keeps recent turns verbatim. Summaries are cached by the turns they cover,
+ * so a growing thread reuses its summary instead of paying for one per turn.
+ */
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{AIMessage, AIRequest, MessageRole};
+use super::router::ModelRouter;
+
+/// Opens the summary message that replaces the oldest turns
+pub const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:";
+
+/// Share of the threshold kept as verbatim recent turns
+const RECENT_SHARE: f64 = 0.5;
+/// Upper bound on the summary the model is asked for
+const SUMMARY_MAX_TOKENS: u32 = 512;
+/// Summaries remembered before the cache is cleared
+const MAX_CACHED_SUMMARIES: usize = 512;
+
+/// How history over the threshold is shortened
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStrategy {
+    /// Drop the oldest turns
+    Truncate,
+    /// Replace the oldest turns with an AI-written summary
+    Summarize,
+}
+
+impl HistoryStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "truncate" => Some(Self::Truncate),
+            "summarize" => Some(Self::Summarize),
+            _ => None,
+        }
+    }
+}
+
+/// What compaction did, returned in the response metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactionReport {
+    /// Strategy actually applied; a failed summary falls back to truncation
+    pub strategy: HistoryStrategy,
+    pub compacted_turns: usize,
+    pub summary_cached: bool,
+    pub tokens_before: u32,
+    pub tokens_after: u32,
+}
+
+pub struct HistoryCompactor {
+    strategy: HistoryStrategy,
+    threshold_tokens: u32, // 0 disables compaction
+    summaries: Mutex<HashMap<u64, String>>, // hash of the covered turns -> summary
+}
+
+fn message_tokens(message: &AIMessage) -> u32 {
+    (message.content.len() as f32 / 4.0).ceil() as u32
+}
+
+fn total_tokens(messages: &[AIMessage]) -> u32 {
+    messages.iter().map(message_tokens).sum()
+}
+
+fn role_name(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "user",
+        MessageRole::Assistant => "assistant",
+        MessageRole::System => "system",
+    }
+}
+
+/// Hash of `turns[..k]` for every k, so any cached prefix can be looked up
+fn prefix_hashes(turns: &[AIMessage]) -> Vec<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut hashes = vec![hasher.finish()];
+    for turn in turns {
+        role_name(&turn.role).hash(&mut hasher);
+        turn.content.hash(&mut hasher);
+        hashes.push(hasher.finish());
+    }
+    hashes
+}
+
+/// The summary as an ordinary conversation turn
+///
+/// It is made of user and model text, so it must not carry system-prompt
+/// authority. Its role is the opposite of the turn that follows so that roles
+/// keep alternating.
+fn summary_message(summary: &str, next: Option<&AIMessage>) -> AIMessage {
+    let role = match next.map(|m| &m.role) {
+        Some(MessageRole::User) => MessageRole::Assistant,
+        _ => MessageRole::User,
+    };
+    AIMessage {
+        role,
+        content: format!("{}\n{}", SUMMARY_PREFIX, summary.trim()),
+        timestamp: None,
+        metadata: None,
+    }
+}
+
+fn summary_prompt(previous: Option<&str>, turns: &[AIMessage]) -> String {
+    let mut prompt = String::from(
+        "Summarize the conversation below for an assistant that will continue it. Keep every fact, \
+         decision, requirement, name, file path and code identifier later turns may depend on. \
+         Leave out greetings and repetition. Answer with the summary only, as plain prose.\n\n",
+    );
+    if let Some(previous) = previous {
+        prompt.push_str(&format!("Summary of what came before:\n{}\n\n", previous));
+    }
+    prompt.push_str("Conversation:\n");
+    for turn in turns {
+        prompt.push_str(&format!("{}: {}\n", role_name(&turn.role), turn.content));
+    }
+    prompt
+}
+
+impl HistoryCompactor {
+    pub fn new(strategy: HistoryStrategy, threshold_tokens: u32) -> Self {
+        Self {
+            strategy,
+            threshold_tokens,
+            summaries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Shorten `messages` in place if they exceed the threshold
+    ///
+    /// Leading system messages are never touched and the newest turn is always
+    /// kept. `summarize` is only called when no cached summary still fits.
+    /// Returns None when nothing was changed.
+    pub async fn compact<F, Fut>(&self, messages: &mut Vec<AIMessage>, summarize: F) -> Option<CompactionReport>
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = anyhow::Result<String>>,
+    {
+        let tokens_before = total_tokens(messages);
+        if self.threshold_tokens == 0 || tokens_before <= self.threshold_tokens {
+            return None;
+        }
+
+        let head = messages.iter().take_while(|m| matches!(m.role, MessageRole::System)).count();
+        let turns = &messages[head..];
+        let head_tokens = total_tokens(&messages[..head]);
+
+        // Newest turns that fit in the recent share stay verbatim
+        let recent_budget = (self.threshold_tokens as f64 * RECENT_SHARE) as u32;
+        let mut cut = turns.len().checked_sub(1)?;
+        let mut recent_tokens = message_tokens(&turns[cut]);
+        while cut > 0 && recent_tokens + message_tokens(&turns[cut - 1]) <= recent_budget {
+            cut -= 1;
+            recent_tokens += message_tokens(&turns[cut]);
+        }
+        if cut == 0 {
+            return None;
+        }
+
+        let (applied, covered, summary, summary_cached) = match self.strategy {
+            HistoryStrategy::Truncate => (HistoryStrategy::Truncate, cut, None, false),
+            HistoryStrategy::Summarize => {
+                let hashes = prefix_hashes(turns);
+                let cached = {
+                    let summaries = self.summaries.lock().unwrap();
+                    (1..turns.len()).rev()
+                        .find_map(|k| summaries.get(&hashes[k]).map(|s| (k, s.clone())))
+                };
+
+                let reusable = cached.as_ref().filter(|(k, summary)| {
+                    head_tokens + message_tokens(&summary_message(summary, None)) + total_tokens(&turns[*k..])
+                        <= self.threshold_tokens
+                });
+                if let Some((k, summary)) = reusable {
+                    (HistoryStrategy::Summarize, *k, Some(summary.clone()), true)
+                } else {
+                    // Extend the newest summary that ends before the cut rather than starting over
+                    let (start, previous) = match cached.filter(|(k, _)| *k <= cut) {
+                        Some((k, summary)) => (k, Some(summary)),
+                        None => (0, None),
+                    };
+                    match summarize(summary_prompt(previous.as_deref(), &turns[start..cut])).await {
+                        Ok(summary) if !summary.trim().is_empty() => {
+                            let mut summaries = self.summaries.lock().unwrap();
+                            if summaries.len() >= MAX_CACHED_SUMMARIES {
+                                summaries.clear();
+                            }
+                            summaries.insert(hashes[cut], summary.clone());
+                            (HistoryStrategy::Summarize, cut, Some(summary), false)
+                        }
+                        Ok(_) => {
+                            tracing::warn!("History summary came back empty; truncating instead");
+                            (HistoryStrategy::Truncate, cut, None, false)
+                        }
+                        Err(e) => {
+                            tracing::warn!("History summarization failed, truncating instead: {}", e);
+                            (HistoryStrategy::Truncate, cut, None, false)
+                        }
+                    }
+                }
+            }
+        };
+
+        messages.drain(head..head + covered);
+        if let Some(summary) = summary {
+            let message = summary_message(&summary, messages.get(head));
+            messages.insert(head, message);
+        }
+
+        Some(CompactionReport {
+            strategy: applied,
+            compacted_turns: covered,
+            summary_cached,
+            tokens_before,
+            tokens_after: total_tokens(messages),
+        })
+    }
+}
+
+/// Write a history summary with the cheapest configured provider
+pub async fn summarize_with_router(router: &ModelRouter, prompt: String) -> anyhow::Result<String> {
+    let service = router.cheapest_service()
+        .ok_or_else(|| anyhow::anyhow!("No AI services available"))?;
+
+    let request = AIRequest {
+        messages: vec![AIMessage {
+            role: MessageRole::User,
+            content: prompt,
+            timestamp: Some(chrono::Utc::now()),
+            metadata: None,
+        }],
+        model: None,
+        temperature: Some(0.2),
+        max_tokens: Some(SUMMARY_MAX_TOKENS),
+        stream: Some(false),
+        context: None,
+        context_overflow: None,
+        response_language: None,
+        sanitize_output: false,
+        json_output: false,
+    };
+
+    Ok(router.generate_with(&service, request).await?.content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn turn(index: usize) -> AIMessage {
+        AIMessage {
+            role: if index % 2 == 0 { MessageRole::User } else { MessageRole::Assistant },
+            // ~100 tokens each
+            content: format!("turn {} {}", index, "x".repeat(390)),
+            timestamp: None,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_long_thread_gets_summary_and_keeps_recent_turns() {
+        let compactor = HistoryCompactor::new(HistoryStrategy::Summarize, 1000);
+        let calls = AtomicUsize::new(0);
+        let summarize = |prompt: String| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            assert!(prompt.contains("turn 0 "));
+            async { Ok("The user is writing a tokenizer in Rust and wants it to handle UTF-8.".to_string()) }
+        };
+
+        let mut thread: Vec<AIMessage> = (0..20).map(turn).collect();
+        let mut messages = thread.clone();
+        let report = compactor.compact(&mut messages, summarize).await.unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(report.strategy, HistoryStrategy::Summarize);
+        assert!(!report.summary_cached);
+        assert!(report.tokens_after <= 1000);
+        // The summary is a conversation turn, never a system message
+        assert!(!matches!(messages[0].role, MessageRole::System));
+        assert_ne!(role_name(&messages[0].role), role_name(&messages[1].role));
+        assert!(messages[0].content.starts_with(SUMMARY_PREFIX));
+        assert!(messages[0].content.contains("tokenizer"));
+        // Recent turns are kept verbatim and in order
+        let recent = &thread[report.compacted_turns..];
+        assert_eq!(messages.len(), recent.len() + 1);
+        assert!(messages[1..].iter().zip(recent).all(|(a, b)| a.content == b.content));
+        assert_eq!(messages.last().unwrap().content, thread.last().unwrap().content);
+
+        // The next turn reuses the cached summary instead of asking again
+        thread.extend([turn(20), turn(21)]);
+        let mut messages = thread.clone();
+        let report = compactor.compact(&mut messages, |_| async {
+            Err(anyhow::anyhow!("summary should have come from the cache"))
+        }).await.unwrap();
+        assert!(report.summary_cached);
+        assert!(messages[0].content.contains("tokenizer"));
+        assert_eq!(messages.last().unwrap().content, thread.last().unwrap().content);
+
+        // Short threads are left alone
+        let mut short: Vec<AIMessage> = (0..3).map(turn).collect();
+        assert!(compactor.compact(&mut short, |_| async { Ok(String::new()) }).await.is_none());
+        assert_eq!(short.len(), 3);
+    }
+}
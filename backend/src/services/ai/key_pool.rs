@@ -0,0 +1,128 @@
+/**
+ * API key pools
+ *
+ * A provider can be configured with several API keys so traffic isn't capped
+ * by one key's rate limit. Each call takes the least recently used key; a key
+ * that answers 429 sits out a cooldown while the others carry the load.
+ */
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::config::Config;
+use crate::types::ModelProvider;
+
+#[derive(Default)]
+struct KeyState {
+    last_used: Option<Instant>,
+    cooling_until: Option<Instant>,
+}
+
+pub struct ApiKeyPool {
+    keys: Vec<String>,
+    cooldown: Duration, // How long a rate-limited key is left out
+    states: Mutex<Vec<KeyState>>,
+}
+
+/// A key handed out for one request
+#[derive(Debug, Clone)]
+pub struct ApiKeyLease {
+    index: usize,
+    key: String,
+}
+
+impl ApiKeyLease {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl ApiKeyPool {
+    pub fn new(keys: Vec<String>, cooldown: Duration) -> Self {
+        let states = keys.iter().map(|_| KeyState::default()).collect();
+        Self {
+            keys,
+            cooldown,
+            states: Mutex::new(states),
+        }
+    }
+
+    /// Keys for `provider`: its primary key followed by any extra pooled keys
+    pub fn for_provider(config: &Config, provider: ModelProvider, primary: &str) -> Self {
+        let mut keys = vec![primary.to_string()];
+        for key in config.api_key_pools.get(&provider).into_iter().flatten() {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        Self::new(keys, Duration::from_secs(config.api_key_cooldown_secs))
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Least recently used key that isn't cooling down; None if all of them are
+    pub fn acquire(&self) -> Option<ApiKeyLease> {
+        let now = Instant::now();
+        let mut states = self.states.lock().unwrap();
+        let index = states.iter()
+            .enumerate()
+            .filter(|(_, state)| !state.cooling_until.is_some_and(|until| now < until))
+            .min_by_key(|(_, state)| state.last_used)
+            .map(|(index, _)| index)?;
+        states[index].last_used = Some(now);
+        Some(ApiKeyLease {
+            index,
+            key: self.keys[index].clone(),
+        })
+    }
+
+    /// Leave a key out for the cooldown after the provider rate-limited it
+    pub fn mark_rate_limited(&self, lease: &ApiKeyLease) {
+        if let Some(state) = self.states.lock().unwrap().get_mut(lease.index) {
+            state.cooling_until = Some(Instant::now() + self.cooldown);
+        }
+        tracing::warn!("API key #{} was rate limited; cooling down for {:?}", lease.index + 1, self.cooldown);
+    }
+
+    /// Whether any key can be handed out right now
+    pub fn has_available(&self) -> bool {
+        let now = Instant::now();
+        self.states.lock().unwrap()
+            .iter()
+            .any(|state| !state.cooling_until.is_some_and(|until| now < until))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_rotate_and_rate_limited_keys_sit_out() {
+        let pool = ApiKeyPool::new(
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            Duration::from_millis(20),
+        );
+        let order: Vec<_> = (0..4).map(|_| pool.acquire().unwrap().key().to_string()).collect();
+        assert_eq!(order, vec!["a", "b", "c", "a"]);
+
+        let b = pool.acquire().unwrap();
+        assert_eq!(b.key(), "b");
+        pool.mark_rate_limited(&b);
+        let order: Vec<_> = (0..3).map(|_| pool.acquire().unwrap().key().to_string()).collect();
+        assert_eq!(order, vec!["c", "a", "c"]);
+
+        for lease in [pool.acquire().unwrap(), pool.acquire().unwrap()] {
+            pool.mark_rate_limited(&lease);
+        }
+        assert!(!pool.has_available());
+        assert!(pool.acquire().is_none());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(pool.acquire().is_some());
+    }
+}
@@ -0,0 +1,129 @@
+/**
+ * Response Localization
+ *
+ * Lets callers ask for AI output in a language other than English.
+ * The response language is independent of the programming language of the code.
+ */
+use crate::types::{AIMessage, AIRequest, MessageRole};
+
+pub const DEFAULT_RESPONSE_LANGUAGE: &str = "en";
+
+/// Supported locale codes and the language name given to the model
+pub const SUPPORTED_RESPONSE_LANGUAGES: &[(&str, &str)] = &[
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("de", "German"),
+    ("it", "Italian"),
+    ("pt", "Portuguese"),
+    ("nl", "Dutch"),
+    ("sv", "Swedish"),
+    ("pl", "Polish"),
+    ("uk", "Ukrainian"),
+    ("ru", "Russian"),
+    ("tr", "Turkish"),
+    ("ar", "Arabic"),
+    ("hi", "Hindi"),
+    ("id", "Indonesian"),
+    ("vi", "Vietnamese"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("zh", "Simplified Chinese"),
+    ("zh-tw", "Traditional Chinese"),
+];
+
+/// Normalize a locale code to a supported one ("es-MX" -> "es"), or None if unsupported
+pub fn normalize_response_language(code: &str) -> Option<&'static str> {
+    let code = code.trim().to_lowercase().replace('_', "-");
+    let lookup = |c: &str| {
+        SUPPORTED_RESPONSE_LANGUAGES.iter()
+            .find(|(supported, _)| *supported == c)
+            .map(|(supported, _)| *supported)
+    };
+
+    lookup(&code).or_else(|| lookup(code.split('-').next().unwrap_or_default()))
+}
+
+/// Requested locale, falling back to `default`; None if the result is unsupported
+pub fn resolve_response_language(requested: Option<&str>, default: &str) -> Option<&'static str> {
+    normalize_response_language(requested.unwrap_or(default))
+}
+
+/// Instruction prepended to the conversation, or None for English
+pub fn response_language_instruction(code: &str) -> Option<String> {
+    let code = normalize_response_language(code)?;
+    if code == DEFAULT_RESPONSE_LANGUAGE {
+        return None;
+    }
+
+    let name = SUPPORTED_RESPONSE_LANGUAGES.iter()
+        .find(|(supported, _)| *supported == code)
+        .map(|(_, name)| *name)?;
+
+    Some(format!(
+        "Respond in {}. Keep code, identifiers, file paths and JSON keys exactly as they are; only translate prose.",
+        name
+    ))
+}
+
+/// Prepend the response-language instruction for `request.response_language`, if any
+pub fn localize_request(request: &mut AIRequest) {
+    let Some(instruction) = request.response_language.as_deref().and_then(response_language_instruction) else {
+        return;
+    };
+
+    request.messages.insert(0, AIMessage {
+        role: MessageRole::System,
+        content: instruction,
+        timestamp: None,
+        metadata: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(response_language: Option<&str>) -> AIRequest {
+        AIRequest {
+            messages: vec![AIMessage {
+                role: MessageRole::User,
+                content: "Review this Rust function".to_string(),
+                timestamp: None,
+                metadata: None,
+            }],
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            stream: None,
+            context: None,
+            context_overflow: None,
+            response_language: response_language.map(|l| l.to_string()),
+            sanitize_output: false,
+            json_output: false,
+        }
+    }
+
+    #[test]
+    fn test_instruction_injected_for_non_english_locale() {
+        let mut req = request(Some("ja-JP"));
+        localize_request(&mut req);
+
+        assert_eq!(req.messages.len(), 2);
+        assert!(matches!(req.messages[0].role, MessageRole::System));
+        assert!(req.messages[0].content.contains("Respond in Japanese"));
+        // The user's prompt (and its code language) is untouched
+        assert_eq!(req.messages[1].content, "Review this Rust function");
+    }
+
+    #[test]
+    fn test_english_and_unknown_locales_add_nothing() {
+        let mut req = request(Some("en"));
+        localize_request(&mut req);
+        assert_eq!(req.messages.len(), 1);
+
+        assert_eq!(normalize_response_language("xx"), None);
+        assert_eq!(normalize_response_language("pt_BR"), Some("pt"));
+        assert_eq!(normalize_response_language("zh-TW"), Some("zh-tw"));
+    }
+}
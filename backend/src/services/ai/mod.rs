@@ -1,4 +1,5 @@
 pub mod base;
+pub mod adapter;
 pub mod openai;
 pub mod anthropic;
 pub mod google;
@@ -13,9 +14,19 @@ pub mod anyscale;
 pub mod qwen;
 pub mod zeroone;
 pub mod baidu;
+pub mod ollama;
 pub mod router;
+pub mod health;
+pub mod health_store;
+pub mod localization;
+pub mod history;
+pub mod shadow;
+pub mod tokens;
+pub mod key_pool;
+pub mod response_cache;
 
-pub use base::AIService;
+pub use base::{AIService, EmbeddingsUnsupported};
+pub use adapter::ProviderAdapter;
 pub use openai::OpenAIService;
 pub use anthropic::AnthropicService;
 pub use google::GoogleService;
@@ -30,4 +41,12 @@ pub use anyscale::AnyscaleService;
 pub use qwen::QwenService;
 pub use zeroone::ZeroOneService;
 pub use baidu::BaiduService;
+pub use ollama::OllamaService;
 pub use router::{ModelRouter, AIServiceEnum};
+pub use health::ProviderHealthSummary;
+pub use health_store::ProviderHealthStore;
+pub use history::{HistoryCompactor, HistoryStrategy};
+pub use shadow::{ShadowEvaluator, ShadowSettings};
+pub use tokens::TokenCounter;
+pub use key_pool::ApiKeyPool;
+pub use response_cache::ResponseCache;
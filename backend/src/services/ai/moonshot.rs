@@ -4,14 +4,17 @@
  */
 use async_trait::async_trait;
 use reqwest::Client;
-use serde_json::json;
-use crate::types::{AIRequest, AIResponse, ModelCapabilities, TokenUsage, MessageRole};
+use crate::types::{AIRequest, AIResponse, ModelCapabilities, ModelProvider, TokenUsage};
 use crate::services::ai::base::AIService;
+use crate::services::ai::key_pool::ApiKeyPool;
+use crate::services::ai::adapter::{
+    ProviderAdapter, send_request, openai_compatible_body, openai_compatible_usage, parse_openai_compatible,
+};
 use crate::config::Config;
 
 pub struct MoonshotService {
     client: Client,
-    api_key: String,
+    api_keys: ApiKeyPool,
     capabilities: ModelCapabilities,
 }
 
@@ -19,7 +22,7 @@ impl MoonshotService {
     pub fn new(config: &Config) -> Self {
         Self {
             client: Client::new(),
-            api_key: config.moonshot_api_key.clone(),
+            api_keys: ApiKeyPool::for_provider(config, ModelProvider::Moonshot, &config.moonshot_api_key),
             capabilities: ModelCapabilities {
                 supports_vision: true,
                 supports_function_calling: true,
@@ -47,75 +50,44 @@ impl AIService for MoonshotService {
     }
     
     async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
-        self.validate_request(&request)?;
-        
-        let model = request.model.as_deref().unwrap_or("kimi-k2.5");
-        
-        // Convert messages to OpenAI-compatible format (Moonshot uses OpenAI format)
-        let messages: Vec<serde_json::Value> = request.messages
-            .iter()
-            .map(|msg| {
-                json!({
-                    "role": match msg.role {
-                        MessageRole::User => "user",
-                        MessageRole::Assistant => "assistant",
-                        MessageRole::System => "system",
-                    },
-                    "content": msg.content
-                })
-            })
-            .collect();
-        
-        let body = json!({
-            "model": model,
-            "messages": messages,
-            "temperature": request.temperature.unwrap_or(0.7),
-            "max_tokens": request.max_tokens.unwrap_or(4000),
-        });
-        
-        let response = self.client
-            .post("https://api.moonshot.cn/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Moonshot API error: {}", error_text));
-        }
-        
-        let json: serde_json::Value = response.json().await?;
-        
-        let choice = json["choices"][0].as_object()
-            .ok_or_else(|| anyhow::anyhow!("Invalid response format"))?;
-        
-        let message = choice["message"].as_object()
-            .ok_or_else(|| anyhow::anyhow!("Invalid message format"))?;
-        
-        let content = message["content"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("No content in response"))?
-            .to_string();
-        
-        let usage = json["usage"].as_object().map(|u| TokenUsage {
-            prompt_tokens: u["prompt_tokens"].as_u64().unwrap_or(0) as u32,
-            completion_tokens: u["completion_tokens"].as_u64().unwrap_or(0) as u32,
-            total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as u32,
-        });
-        
-        Ok(AIResponse {
-            content,
-            model: json["model"].as_str().unwrap_or(model).to_string(),
-            usage,
-            finish_reason: choice["finish_reason"].as_str().map(|s| s.to_string()),
-            metadata: Some({
-                let mut meta = std::collections::HashMap::new();
-                meta.insert("provider".to_string(), serde_json::Value::String("moonshot".to_string()));
-                meta.insert("model_type".to_string(), serde_json::Value::String("kimi-k2.5".to_string()));
-                meta
-            }),
-        })
+        send_request(self, &self.client, request).await
+    }
+}
+
+impl ProviderAdapter for MoonshotService {
+    fn provider(&self) -> ModelProvider {
+        ModelProvider::Moonshot
+    }
+    
+    fn display_name(&self) -> &str {
+        "Moonshot"
+    }
+    
+    fn default_model(&self) -> &str {
+        "kimi-k2.5"
+    }
+    
+    fn endpoint(&self, _model: &str) -> String {
+        "https://api.moonshot.cn/v1/chat/completions".to_string()
+    }
+    
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![("Authorization", format!("Bearer {}", api_key))]
+    }
+    
+    fn api_keys(&self) -> Option<&ApiKeyPool> {
+        Some(&self.api_keys)
+    }
+    
+    fn build_request(&self, request: &AIRequest, model: &str) -> serde_json::Value {
+        openai_compatible_body(request, model)
+    }
+    
+    fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+        parse_openai_compatible(body, model, self.extract_usage(body), &[("provider", "moonshot"), ("model_type", "kimi-k2.5")])
+    }
+    
+    fn extract_usage(&self, body: &serde_json::Value) -> Option<TokenUsage> {
+        openai_compatible_usage(body)
     }
 }
@@ -0,0 +1,226 @@
+/**
+ * Ollama service integration
+ * Locally hosted models through Ollama's OpenAI-compatible API; no key, no cost
+ */
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use reqwest::Client;
+use tokio_util::sync::CancellationToken;
+use crate::types::{AIRequest, AIResponse, ModelCapabilities, ModelProvider, TokenUsage};
+use crate::services::ai::base::AIService;
+use crate::services::ai::adapter::{
+    ProviderAdapter, send_request, openai_compatible_body, openai_compatible_usage, parse_openai_compatible,
+};
+use crate::config::Config;
+
+/// How often the server is checked for being up
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct OllamaService {
+    client: Client,
+    base_url: String,
+    model: String,
+    online: Arc<AtomicBool>, // Last probe result; offline servers are left out of routing
+    probe_stop: CancellationToken,
+    capabilities: ModelCapabilities,
+}
+
+/// Whether a model name refers to a local Ollama model
+///
+/// Matches `ollama/<name>` and the configured `local_models`, with or without
+/// a `:tag`. Other names are left to the hosted providers, which share model
+/// families like llama and qwen.
+pub fn is_ollama_model(model: &str, local_models: &[String]) -> bool {
+    let model = model.to_lowercase();
+    if model.starts_with("ollama/") {
+        return true;
+    }
+    let name = model.split(':').next().unwrap_or_default();
+    local_models.iter().any(|local| {
+        let local = local.to_lowercase();
+        // An untagged entry covers every tag of that model
+        local == model || (!local.contains(':') && local == name)
+    })
+}
+
+/// Model names routed to Ollama under `config`; none when it isn't configured
+pub fn local_models(config: &Config) -> Vec<String> {
+    if config.ollama_base_url.is_empty() {
+        return Vec::new();
+    }
+    std::iter::once(config.ollama_model.clone())
+        .chain(config.ollama_models.iter().cloned())
+        .filter(|model| !model.is_empty())
+        .collect()
+}
+
+impl OllamaService {
+    pub fn new(config: &Config) -> Self {
+        let service = Self {
+            client: Client::new(),
+            base_url: config.ollama_base_url.trim_end_matches('/').to_string(),
+            model: config.ollama_model.clone(),
+            online: Arc::new(AtomicBool::new(false)),
+            probe_stop: CancellationToken::new(),
+            capabilities: ModelCapabilities {
+                supports_vision: false,
+                supports_function_calling: false,
+                max_context_length: config.ollama_context_length,
+                supports_streaming: true,
+                cost_per_1k_tokens: crate::types::CostPer1kTokens {
+                    input: 0.0,
+                    output: 0.0,
+                },
+                speed: crate::types::Speed::Medium,
+                quality: crate::types::Quality::Medium,
+            },
+        };
+        service.spawn_probe();
+        service
+    }
+
+    /// Keep `online` current in the background, if there is a runtime to do it on
+    fn spawn_probe(&self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let client = self.client.clone();
+        let url = format!("{}/api/tags", self.base_url);
+        let online = Arc::clone(&self.online);
+        let stop = self.probe_stop.clone();
+        runtime.spawn(async move {
+            while !stop.is_cancelled() {
+                let reachable = client.get(&url)
+                    .timeout(PROBE_TIMEOUT)
+                    .send()
+                    .await
+                    .is_ok_and(|response| response.status().is_success());
+                if online.swap(reachable, Ordering::Relaxed) != reachable {
+                    if reachable {
+                        tracing::info!("Ollama at {} is up", url);
+                    } else {
+                        tracing::warn!("Ollama at {} is unreachable; skipping it in routing", url);
+                    }
+                }
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = tokio::time::sleep(PROBE_INTERVAL) => {}
+                }
+            }
+        });
+    }
+}
+
+impl Drop for OllamaService {
+    fn drop(&mut self) {
+        self.probe_stop.cancel();
+    }
+}
+
+#[async_trait]
+impl AIService for OllamaService {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn capabilities(&self) -> &ModelCapabilities {
+        &self.capabilities
+    }
+
+    async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
+        if !self.is_ready() {
+            anyhow::bail!("Ollama at {} is not reachable", self.base_url);
+        }
+        send_request(self, &self.client, request).await
+    }
+}
+
+impl ProviderAdapter for OllamaService {
+    fn provider(&self) -> ModelProvider {
+        ModelProvider::Ollama
+    }
+
+    fn display_name(&self) -> &str {
+        "Ollama"
+    }
+
+    fn default_model(&self) -> &str {
+        &self.model
+    }
+
+    fn endpoint(&self, _model: &str) -> String {
+        format!("{}/v1/chat/completions", self.base_url)
+    }
+
+    fn headers(&self, _api_key: &str) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    fn build_request(&self, request: &AIRequest, model: &str) -> serde_json::Value {
+        openai_compatible_body(request, model.trim_start_matches("ollama/"))
+    }
+
+    fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+        parse_openai_compatible(body, model, self.extract_usage(body), &[("provider", "ollama"), ("hosting", "local")])
+    }
+
+    fn extract_usage(&self, body: &serde_json::Value) -> Option<TokenUsage> {
+        openai_compatible_usage(body)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    fn shutdown(&self) {
+        self.probe_stop.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_model_names_route_to_ollama() {
+        let local = vec!["llama3".to_string(), "mixtral:8x7b".to_string()];
+        for model in ["llama3", "llama3:70b", "Mixtral:8x7b", "ollama/deepseek-coder-v2"] {
+            assert!(is_ollama_model(model, &local), "{}", model);
+        }
+        for model in ["qwen2", "llama3.1:8b", "mixtral:8x22b", "meta-llama/Meta-Llama-3-70B-Instruct-Turbo", "gpt-4o"] {
+            assert!(!is_ollama_model(model, &local), "{}", model);
+        }
+        // Without a configured server only the explicit prefix counts
+        assert!(!is_ollama_model("llama3", &[]));
+
+        let mut config = Config::from_env().unwrap();
+        config.ollama_base_url.clear();
+        assert!(local_models(&config).is_empty());
+        config.ollama_base_url = "http://localhost:11434".to_string();
+        config.ollama_models = vec!["qwen2".to_string()];
+        assert_eq!(local_models(&config), [config.ollama_model.clone(), "qwen2".to_string()]);
+
+        // Without a probe having succeeded the server counts as offline
+        let service = OllamaService::new(&Config::from_env().unwrap());
+        assert!(!service.is_ready());
+        let body = service.build_request(&AIRequest {
+            messages: Vec::new(),
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            stream: None,
+            context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
+        }, "ollama/llama3");
+        assert_eq!(body["model"], "llama3");
+        assert_eq!(service.capabilities().cost_per_1k_tokens.input, 0.0);
+        service.shutdown();
+        assert!(service.probe_stop.is_cancelled());
+    }
+}
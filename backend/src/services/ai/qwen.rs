@@ -5,13 +5,15 @@
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::json;
-use crate::types::{AIRequest, AIResponse, ModelCapabilities, TokenUsage, MessageRole};
+use crate::types::{AIRequest, AIResponse, ModelCapabilities, ModelProvider, TokenUsage};
 use crate::services::ai::base::AIService;
+use crate::services::ai::key_pool::ApiKeyPool;
+use crate::services::ai::adapter::{ProviderAdapter, send_request, chat_messages, response_metadata};
 use crate::config::Config;
 
 pub struct QwenService {
     client: Client,
-    api_key: String,
+    api_keys: ApiKeyPool,
     capabilities: ModelCapabilities,
 }
 
@@ -19,7 +21,7 @@ impl QwenService {
     pub fn new(config: &Config) -> Self {
         Self {
             client: Client::new(),
-            api_key: config.qwen_api_key.clone(),
+            api_keys: ApiKeyPool::for_provider(config, ModelProvider::Qwen, &config.qwen_api_key),
             capabilities: ModelCapabilities {
                 supports_vision: true,
                 supports_function_calling: true,
@@ -47,51 +49,50 @@ impl AIService for QwenService {
     }
     
     async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
-        self.validate_request(&request)?;
-        
-        let model = request.model.as_deref().unwrap_or("qwen-plus");
-        
-        let messages: Vec<serde_json::Value> = request.messages
-            .iter()
-            .map(|msg| {
-                json!({
-                    "role": match msg.role {
-                        MessageRole::User => "user",
-                        MessageRole::Assistant => "assistant",
-                        MessageRole::System => "system",
-                    },
-                    "content": msg.content
-                })
-            })
-            .collect();
-        
-        let body = json!({
+        send_request(self, &self.client, request).await
+    }
+}
+
+impl ProviderAdapter for QwenService {
+    fn provider(&self) -> ModelProvider {
+        ModelProvider::Qwen
+    }
+    
+    fn display_name(&self) -> &str {
+        "Qwen"
+    }
+    
+    fn default_model(&self) -> &str {
+        "qwen-plus"
+    }
+    
+    fn endpoint(&self, _model: &str) -> String {
+        "https://dashscope.aliyuncs.com/api/v1/services/aigc/text-generation/generation".to_string()
+    }
+    
+    fn headers(&self, api_key: &str) -> Vec<(&'static str, String)> {
+        vec![("Authorization", format!("Bearer {}", api_key))]
+    }
+    
+    fn api_keys(&self) -> Option<&ApiKeyPool> {
+        Some(&self.api_keys)
+    }
+    
+    fn build_request(&self, request: &AIRequest, model: &str) -> serde_json::Value {
+        json!({
             "model": model,
             "input": {
-                "messages": messages
+                "messages": chat_messages(request)
             },
             "parameters": {
                 "temperature": request.temperature.unwrap_or(0.7),
                 "max_tokens": request.max_tokens.unwrap_or(4000),
             }
-        });
-        
-        let response = self.client
-            .post("https://dashscope.aliyuncs.com/api/v1/services/aigc/text-generation/generation")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Qwen API error: {}", error_text));
-        }
-        
-        let json: serde_json::Value = response.json().await?;
-        
-        let output = json["output"].as_object()
+        })
+    }
+    
+    fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+        let output = body["output"].as_object()
             .ok_or_else(|| anyhow::anyhow!("Invalid response format"))?;
         
         let content = output["text"]
@@ -99,23 +100,20 @@ impl AIService for QwenService {
             .ok_or_else(|| anyhow::anyhow!("No text in response"))?
             .to_string();
         
-        let usage = json["usage"].as_object().map(|u| TokenUsage {
+        Ok(AIResponse {
+            content,
+            model: body["model"].as_str().unwrap_or(model).to_string(),
+            usage: self.extract_usage(body),
+            finish_reason: body["output"]["finish_reason"].as_str().map(|s| s.to_string()),
+            metadata: Some(response_metadata(&[("provider", "qwen"), ("specialization", "multilingual")])),
+        })
+    }
+    
+    fn extract_usage(&self, body: &serde_json::Value) -> Option<TokenUsage> {
+        body["usage"].as_object().map(|u| TokenUsage {
             prompt_tokens: u["input_tokens"].as_u64().unwrap_or(0) as u32,
             completion_tokens: u["output_tokens"].as_u64().unwrap_or(0) as u32,
             total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as u32,
-        });
-        
-        Ok(AIResponse {
-            content,
-            model: json["model"].as_str().unwrap_or(model).to_string(),
-            usage,
-            finish_reason: json["output"]["finish_reason"].as_str().map(|s| s.to_string()),
-            metadata: Some({
-                let mut meta = std::collections::HashMap::new();
-                meta.insert("provider".to_string(), serde_json::Value::String("qwen".to_string()));
-                meta.insert("specialization".to_string(), serde_json::Value::String("multilingual".to_string()));
-                meta
-            }),
         })
     }
 }
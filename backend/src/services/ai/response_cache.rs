@@ -0,0 +1,213 @@
+/**
+ * Response Cache
+ *
+ * Answers repeated chat requests from memory instead of paying a provider
+ * again. Entries are keyed on the exact conversation plus the settings
+ * that change what a model says, and expire after a fixed TTL. Only
+ * deterministic (temperature 0) requests are cached unless sampled answers
+ * are explicitly allowed, since replaying one sample would hide the variety
+ * a caller asked for.
+ */
+use std::collections::HashMap;
+use std::sync::Mutex;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use crate::types::{AIRequest, AIResponse};
+
+struct CachedResponse {
+    response: AIResponse,
+    stored_at: Instant,
+}
+
+pub struct ResponseCache {
+    ttl: Duration,
+    max_entries: usize, // 0 = caching off
+    cache_sampled: bool, // Also cache requests with temperature > 0
+    entries: Mutex<HashMap<CacheKey, CachedResponse>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration, max_entries: usize, cache_sampled: bool) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            cache_sampled,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `request` may be answered from (and stored in) the cache
+    ///
+    /// Streaming requests never are. A missing temperature counts as sampled,
+    /// because providers fall back to a non-zero default.
+    pub fn is_cacheable(&self, request: &AIRequest) -> bool {
+        if self.max_entries == 0 || self.ttl.is_zero() || request.stream == Some(true) {
+            return false;
+        }
+        self.cache_sampled || request.temperature == Some(0.0)
+    }
+
+    /// Cached answer for `request`, marked `cached: true` in its metadata
+    pub fn get(&self, request: &AIRequest) -> Option<AIResponse> {
+        if !self.is_cacheable(request) {
+            return None;
+        }
+        let key = cache_key(request);
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some(entry) if entry.stored_at.elapsed() < self.ttl => {
+                let mut response = entry.response.clone();
+                response.metadata.get_or_insert_with(HashMap::new)
+                    .insert("cached".to_string(), serde_json::json!(true));
+                Some(response)
+            }
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Remember a successful answer to `request`
+    ///
+    /// When the cache is full, expired entries go first, then the oldest one.
+    pub fn put(&self, request: &AIRequest, response: &AIResponse) {
+        if !self.is_cacheable(request) {
+            return;
+        }
+        let key = cache_key(request);
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.stored_at.elapsed() < self.ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries.iter()
+                    .min_by_key(|(_, entry)| entry.stored_at)
+                    .map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, CachedResponse {
+            response: response.clone(),
+            stored_at: Instant::now(),
+        });
+    }
+
+    /// Drop every entry; returns how many there were
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let cleared = entries.len();
+        entries.clear();
+        cleared
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+type CacheKey = [u8; 32];
+
+/// SHA-256 of everything that decides the answer
+///
+/// Message text is hashed exactly as sent: whitespace is significant in code,
+/// so prompts that differ only in indentation must not share an answer.
+/// Attached context, language, sanitizing and JSON mode are included because
+/// they change the returned content.
+fn cache_key(request: &AIRequest) -> CacheKey {
+    let messages: Vec<_> = request.messages.iter()
+        .map(|m| serde_json::json!([m.role, m.content]))
+        .collect();
+    let identity = serde_json::json!({
+        "messages": messages,
+        "model": request.model,
+        "temperature": request.temperature,
+        "max_tokens": request.max_tokens,
+        "context": request.context,
+        "response_language": request.response_language,
+        "sanitize_output": request.sanitize_output,
+        "json_output": request.json_output,
+    });
+
+    Sha256::digest(identity.to_string().as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AIMessage, MessageRole};
+
+    fn request(content: &str, temperature: Option<f32>) -> AIRequest {
+        AIRequest {
+            messages: vec![AIMessage {
+                role: MessageRole::User,
+                content: content.to_string(),
+                timestamp: None,
+                metadata: None,
+            }],
+            model: Some("gpt-4o".to_string()),
+            temperature,
+            max_tokens: Some(256),
+            stream: None,
+            context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
+        }
+    }
+
+    fn response(content: &str) -> AIResponse {
+        AIResponse {
+            content: content.to_string(),
+            model: "gpt-4o".to_string(),
+            usage: None,
+            finish_reason: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_deterministic_requests_are_served_from_cache() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 2, false);
+        cache.put(&request("What is a  monad?", Some(0.0)), &response("A monoid..."));
+
+        let hit = cache.get(&request("What is a  monad?", Some(0.0))).unwrap();
+        assert_eq!(hit.content, "A monoid...");
+        assert_eq!(hit.metadata.unwrap()["cached"], serde_json::json!(true));
+        assert!(cache.get(&request("What is a functor?", Some(0.0))).is_none());
+        // Whitespace is part of the prompt: re-indented code is a different question
+        assert!(cache.get(&request("What is a monad?", Some(0.0))).is_none());
+        cache.put(&request("fix:\nif x:\n    y()", Some(0.0)), &response("indented"));
+        assert!(cache.get(&request("fix:\nif x:\ny()", Some(0.0))).is_none());
+        cache.clear();
+        cache.put(&request("What is a  monad?", Some(0.0)), &response("A monoid..."));
+
+        // Sampled requests are left alone unless the override is on
+        cache.put(&request("Tell me a joke", Some(0.9)), &response("..."));
+        assert!(cache.get(&request("Tell me a joke", Some(0.9))).is_none());
+        let sampled = ResponseCache::new(Duration::from_secs(60), 2, true);
+        sampled.put(&request("Tell me a joke", Some(0.9)), &response("..."));
+        assert!(sampled.get(&request("Tell me a joke", Some(0.9))).is_some());
+
+        // The oldest entry makes room once the cache is full
+        cache.put(&request("second", Some(0.0)), &response("2"));
+        cache.put(&request("third", Some(0.0)), &response("3"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&request("What is a monad?", Some(0.0))).is_none());
+
+        assert_eq!(cache.clear(), 2);
+        assert!(cache.get(&request("third", Some(0.0))).is_none());
+
+        let expired = ResponseCache::new(Duration::from_millis(1), 2, false);
+        expired.put(&request("stale", Some(0.0)), &response("old"));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(expired.get(&request("stale", Some(0.0))).is_none());
+    }
+}
@@ -3,326 +3,553 @@
  * This is what makes Bloop superior to KIMI and Claude
  * Supports 15+ AI providers with intelligent selection
  */
-use crate::types::{AIRequest, ModelProvider, ModelInfo, ModelCapabilities};
+use crate::types::{AIRequest, AIResponse, ModelProvider, ModelInfo, ModelCapabilities, ContextOverflowPolicy, MessageRole};
 use crate::services::ai::{
     OpenAIService, AnthropicService, GoogleService, MoonshotService,
     DeepSeekService, MistralService, CohereService, PerplexityService,
     XAIService, TogetherService, AnyscaleService, QwenService,
-    ZeroOneService, BaiduService
+    ZeroOneService, BaiduService, OllamaService
 };
+use crate::services::ai::ollama::{is_ollama_model, local_models};
 use crate::services::ai::base::AIService;
+use crate::services::ai::adapter::ProviderAdapter;
+use crate::services::ai::health::ProviderHealthTracker;
+use crate::services::agent::CircuitState;
+use crate::services::ai::tokens::{RequestTokens, TokenCounter};
 use crate::config::Config;
-use std::sync::Arc;
+use crate::security::SecretRedactor;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
 
-pub struct ModelRouter {
-    openai: Option<Arc<OpenAIService>>,
-    anthropic: Option<Arc<AnthropicService>>,
-    google: Option<Arc<GoogleService>>,
-    moonshot: Option<Arc<MoonshotService>>,
-    deepseek: Option<Arc<DeepSeekService>>,
-    mistral: Option<Arc<MistralService>>,
-    cohere: Option<Arc<CohereService>>,
-    perplexity: Option<Arc<PerplexityService>>,
-    xai: Option<Arc<XAIService>>,
-    together: Option<Arc<TogetherService>>,
-    anyscale: Option<Arc<AnyscaleService>>,
-    qwen: Option<Arc<QwenService>>,
-    zeroone: Option<Arc<ZeroOneService>>,
-    baidu: Option<Arc<BaiduService>>,
+/// Model selection after applying the context overflow policy
+#[derive(Debug, Clone)]
+pub struct ContextRouting {
+    pub model: ModelInfo,
+    pub request: AIRequest, // Possibly truncated
+    pub metadata: HashMap<String, serde_json::Value>, // Merge into the response metadata
+}
+
+/// Why `select_best_model` chose the model it did
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingDecision {
+    pub selected: ModelInfo,
+    /// The request named a model of a configured provider, so scores weren't used
+    pub pinned: bool,
+    /// Request tokens as the selected provider counts them
+    pub context_length: u32,
+    pub token_counter: &'static str,
+    pub requires_vision: bool,
+    pub requires_speed: bool,
+    pub requires_quality: bool,
+    /// Every ready provider, highest score first
+    pub scores: Vec<ProviderScore>,
+    /// Configured providers left out because they aren't ready or their circuit is open
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unavailable: Vec<ModelProvider>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderScore {
+    pub provider: ModelProvider,
+    pub score: f64,
+    /// Request tokens as this provider counts them
+    pub context_tokens: u32,
+    pub fits_context: bool,
 }
 
-/// Helper enum to hold different service types
-#[derive(Clone)]
-pub enum AIServiceEnum {
-    OpenAI(Arc<OpenAIService>),
-    Anthropic(Arc<AnthropicService>),
-    Google(Arc<GoogleService>),
-    Moonshot(Arc<MoonshotService>),
-    DeepSeek(Arc<DeepSeekService>),
-    Mistral(Arc<MistralService>),
-    Cohere(Arc<CohereService>),
-    Perplexity(Arc<PerplexityService>),
-    XAI(Arc<XAIService>),
-    Together(Arc<TogetherService>),
-    Anyscale(Arc<AnyscaleService>),
-    Qwen(Arc<QwenService>),
-    ZeroOne(Arc<ZeroOneService>),
-    Baidu(Arc<BaiduService>),
+/// How much each factor counts when scoring providers for a request
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RoutingWeights {
+    /// Added when the request fits the context window; twice this is subtracted when it doesn't
+    pub context: f64,
+    /// Added for vision support when the request needs it
+    pub vision: f64,
+    /// Added for fast models on quick tasks (medium-speed models get 40%)
+    pub speed: f64,
+    /// Added for high-quality models on demanding tasks (medium-quality models get 40%)
+    pub quality: f64,
+    /// Multiplier on the cost-efficiency term, `0.01 / average cost per 1k tokens`
+    pub cost: f64,
 }
 
-impl AIService for AIServiceEnum {
-    fn name(&self) -> &str {
-        match self {
-            AIServiceEnum::OpenAI(s) => s.name(),
-            AIServiceEnum::Anthropic(s) => s.name(),
-            AIServiceEnum::Google(s) => s.name(),
-            AIServiceEnum::Moonshot(s) => s.name(),
-            AIServiceEnum::DeepSeek(s) => s.name(),
-            AIServiceEnum::Mistral(s) => s.name(),
-            AIServiceEnum::Cohere(s) => s.name(),
-            AIServiceEnum::Perplexity(s) => s.name(),
-            AIServiceEnum::XAI(s) => s.name(),
-            AIServiceEnum::Together(s) => s.name(),
-            AIServiceEnum::Anyscale(s) => s.name(),
-            AIServiceEnum::Qwen(s) => s.name(),
-            AIServiceEnum::ZeroOne(s) => s.name(),
-            AIServiceEnum::Baidu(s) => s.name(),
+impl Default for RoutingWeights {
+    fn default() -> Self {
+        Self {
+            context: 10.0,
+            vision: 5.0,
+            speed: 5.0,
+            quality: 5.0,
+            cost: 2.0,
         }
     }
-    
-    fn capabilities(&self) -> &ModelCapabilities {
-        match self {
-            AIServiceEnum::OpenAI(s) => s.capabilities(),
-            AIServiceEnum::Anthropic(s) => s.capabilities(),
-            AIServiceEnum::Google(s) => s.capabilities(),
-            AIServiceEnum::Moonshot(s) => s.capabilities(),
-            AIServiceEnum::DeepSeek(s) => s.capabilities(),
-            AIServiceEnum::Mistral(s) => s.capabilities(),
-            AIServiceEnum::Cohere(s) => s.capabilities(),
-            AIServiceEnum::Perplexity(s) => s.capabilities(),
-            AIServiceEnum::XAI(s) => s.capabilities(),
-            AIServiceEnum::Together(s) => s.capabilities(),
-            AIServiceEnum::Anyscale(s) => s.capabilities(),
-            AIServiceEnum::Qwen(s) => s.capabilities(),
-            AIServiceEnum::ZeroOne(s) => s.capabilities(),
-            AIServiceEnum::Baidu(s) => s.capabilities(),
-        }
+}
+
+/// Every provider tried by `generate_with_fallback` failed
+#[derive(Debug, thiserror::Error)]
+#[error("All providers failed ({}){}", describe_attempts(.attempts), describe_skipped(.skipped))]
+pub struct FallbackError {
+    /// Providers called, in order, with the error each returned
+    pub attempts: Vec<(ModelProvider, String)>,
+    /// Providers passed over because their context window was too small
+    pub skipped: Vec<ModelProvider>,
+}
+
+fn describe_attempts(attempts: &[(ModelProvider, String)]) -> String {
+    if attempts.is_empty() {
+        return "none could serve the request".to_string();
     }
-    
-    async fn generate(&self, request: AIRequest) -> anyhow::Result<crate::types::AIResponse> {
-        match self {
-            AIServiceEnum::OpenAI(s) => s.generate(request).await,
-            AIServiceEnum::Anthropic(s) => s.generate(request).await,
-            AIServiceEnum::Google(s) => s.generate(request).await,
-            AIServiceEnum::Moonshot(s) => s.generate(request).await,
-            AIServiceEnum::DeepSeek(s) => s.generate(request).await,
-            AIServiceEnum::Mistral(s) => s.generate(request).await,
-            AIServiceEnum::Cohere(s) => s.generate(request).await,
-            AIServiceEnum::Perplexity(s) => s.generate(request).await,
-            AIServiceEnum::XAI(s) => s.generate(request).await,
-            AIServiceEnum::Together(s) => s.generate(request).await,
-            AIServiceEnum::Anyscale(s) => s.generate(request).await,
-            AIServiceEnum::Qwen(s) => s.generate(request).await,
-            AIServiceEnum::ZeroOne(s) => s.generate(request).await,
-            AIServiceEnum::Baidu(s) => s.generate(request).await,
-        }
+    attempts.iter()
+        .map(|(provider, error)| format!("{:?}: {}", provider, error))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn describe_skipped(skipped: &[ModelProvider]) -> String {
+    if skipped.is_empty() {
+        String::new()
+    } else {
+        format!(", skipped for context length: {:?}", skipped)
     }
 }
 
+pub struct ModelRouter {
+    adapters: Vec<AIServiceEnum>, // Configured providers, in fallback preference order
+    context_overflow_policy: ContextOverflowPolicy,
+    secret_redactor: Option<Arc<SecretRedactor>>,
+    health: ProviderHealthTracker, // Fed by every call through `generate_with`
+    max_fallbacks: usize, // Providers tried after the first by `generate_with_fallback`
+    weights: RoutingWeights,
+    in_flight: Mutex<HashMap<u64, broadcast::Sender<FlightResult>>>, // Coalescing key -> waiting callers
+    local_models: Vec<String>, // Model names served by Ollama
+}
+
+/// Outcome handed from the caller that made a coalesced request to everyone waiting on it
+type FlightResult = Result<AIResponse, String>;
+
+/// Removes a coalescing entry even if its leader is cancelled; waiters then retry on their own
+struct FlightGuard<'a> {
+    in_flight: &'a Mutex<HashMap<u64, broadcast::Sender<FlightResult>>>,
+    key: u64,
+}
+
+impl FlightGuard<'_> {
+    fn take(&self) -> Option<broadcast::Sender<FlightResult>> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.key)
+    }
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+/// A configured provider; all provider-specific behavior sits behind the adapter
+pub type AIServiceEnum = Arc<dyn ProviderAdapter>;
+
+/// Price floor (USD per 1k tokens) in the cost-efficiency score, so free local models score high but finite
+const MIN_SCORED_COST: f64 = 0.0001;
+
+type AdapterConstructor = fn(&Config) -> AIServiceEnum;
+
 impl ModelRouter {
     pub fn new(config: &Config) -> Self {
+        // (API key, constructor) in fallback preference order; Ollama is keyed by its URL
+        let registry: [(&str, AdapterConstructor); 15] = [
+            (config.openai_api_key.as_str(), |c| Arc::new(OpenAIService::new(c))),
+            (config.anthropic_api_key.as_str(), |c| Arc::new(AnthropicService::new(c))),
+            (config.google_gemini_api_key.as_str(), |c| Arc::new(GoogleService::new(c))),
+            (config.moonshot_api_key.as_str(), |c| Arc::new(MoonshotService::new(c))),
+            (config.deepseek_api_key.as_str(), |c| Arc::new(DeepSeekService::new(c))),
+            (config.mistral_api_key.as_str(), |c| Arc::new(MistralService::new(c))),
+            (config.cohere_api_key.as_str(), |c| Arc::new(CohereService::new(c))),
+            (config.perplexity_api_key.as_str(), |c| Arc::new(PerplexityService::new(c))),
+            (config.xai_api_key.as_str(), |c| Arc::new(XAIService::new(c))),
+            (config.together_api_key.as_str(), |c| Arc::new(TogetherService::new(c))),
+            (config.anyscale_api_key.as_str(), |c| Arc::new(AnyscaleService::new(c))),
+            (config.qwen_api_key.as_str(), |c| Arc::new(QwenService::new(c))),
+            (config.zeroone_api_key.as_str(), |c| Arc::new(ZeroOneService::new(c))),
+            (config.baidu_api_key.as_str(), |c| Arc::new(BaiduService::new(c))),
+            (config.ollama_base_url.as_str(), |c| Arc::new(OllamaService::new(c))),
+        ];
+        
         Self {
-            openai: if !config.openai_api_key.is_empty() {
-                Some(Arc::new(OpenAIService::new(config)))
-            } else {
-                None
-            },
-            anthropic: if !config.anthropic_api_key.is_empty() {
-                Some(Arc::new(AnthropicService::new(config)))
-            } else {
-                None
-            },
-            google: if !config.google_gemini_api_key.is_empty() {
-                Some(Arc::new(GoogleService::new(config)))
-            } else {
-                None
-            },
-            moonshot: if !config.moonshot_api_key.is_empty() {
-                Some(Arc::new(MoonshotService::new(config)))
-            } else {
-                None
-            },
-            deepseek: if !config.deepseek_api_key.is_empty() {
-                Some(Arc::new(DeepSeekService::new(config)))
-            } else {
-                None
-            },
-            mistral: if !config.mistral_api_key.is_empty() {
-                Some(Arc::new(MistralService::new(config)))
-            } else {
-                None
-            },
-            cohere: if !config.cohere_api_key.is_empty() {
-                Some(Arc::new(CohereService::new(config)))
-            } else {
-                None
-            },
-            perplexity: if !config.perplexity_api_key.is_empty() {
-                Some(Arc::new(PerplexityService::new(config)))
-            } else {
-                None
-            },
-            xai: if !config.xai_api_key.is_empty() {
-                Some(Arc::new(XAIService::new(config)))
-            } else {
-                None
-            },
-            together: if !config.together_api_key.is_empty() {
-                Some(Arc::new(TogetherService::new(config)))
-            } else {
-                None
-            },
-            anyscale: if !config.anyscale_api_key.is_empty() {
-                Some(Arc::new(AnyscaleService::new(config)))
-            } else {
-                None
-            },
-            qwen: if !config.qwen_api_key.is_empty() {
-                Some(Arc::new(QwenService::new(config)))
-            } else {
-                None
-            },
-            zeroone: if !config.zeroone_api_key.is_empty() {
-                Some(Arc::new(ZeroOneService::new(config)))
-            } else {
-                None
-            },
-            baidu: if !config.baidu_api_key.is_empty() {
-                Some(Arc::new(BaiduService::new(config)))
-            } else {
-                None
-            },
+            adapters: registry.iter()
+                .filter(|(api_key, _)| !api_key.is_empty())
+                .map(|(_, construct)| construct(config))
+                .collect(),
+            context_overflow_policy: config.context_overflow_policy,
+            secret_redactor: None,
+            health: ProviderHealthTracker::new()
+                .with_half_life(std::time::Duration::from_secs(config.provider_health_half_life_secs)),
+            max_fallbacks: config.max_provider_fallbacks,
+            weights: config.routing_weights,
+            in_flight: Mutex::new(HashMap::new()),
+            local_models: local_models(config),
         }
     }
-    
-    /// Intelligently selects the best model for a given request
-    /// Considers: context length, cost, speed, quality, task type
-    pub fn select_best_model(&self, request: &AIRequest) -> anyhow::Result<ModelInfo> {
-        // Check if specific model requested
-        if let Some(model_str) = &request.model {
-            if let Some(provider) = self.parse_provider_from_model(model_str) {
-                if let Some(service) = self.get_service(provider) {
-                    return Ok(ModelInfo {
-                        provider,
-                        model: model_str.clone(),
-                        capabilities: service.capabilities().clone(),
-                    });
+
+    /// Stop adapters' background work once the server is shutting down
+    pub fn shutdown(&self) {
+        for adapter in &self.adapters {
+            adapter.shutdown();
+        }
+    }
+
+    /// Register an adapter, replacing any existing one for the same provider
+    pub fn with_adapter(mut self, adapter: AIServiceEnum) -> Self {
+        let provider = adapter.provider();
+        match self.adapters.iter().position(|a| a.provider() == provider) {
+            Some(index) => self.adapters[index] = adapter,
+            None => self.adapters.push(adapter),
+        }
+        self
+    }
+
+    /// Redact secrets from every request sent through `generate_with`
+    pub fn with_secret_redactor(mut self, redactor: Arc<SecretRedactor>) -> Self {
+        self.secret_redactor = Some(redactor);
+        self
+    }
+
+    /// Send a request to a provider, recording the outcome in the health tracker
+    ///
+    /// Identical deterministic requests to the same provider that are already in
+    /// flight share that call instead of making their own.
+    pub async fn generate_with(&self, service: &AIServiceEnum, request: AIRequest) -> anyhow::Result<AIResponse> {
+        let Some(key) = coalescing_key(&service.provider(), &request) else {
+            return self.generate_recorded(service, request).await;
+        };
+
+        let waiting = {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            match in_flight.get(&key) {
+                Some(tx) => Some(tx.subscribe()),
+                None => {
+                    in_flight.insert(key, broadcast::channel(1).0);
+                    None
                 }
             }
+        };
+
+        if let Some(mut rx) = waiting {
+            return match rx.recv().await {
+                Ok(Ok(mut response)) => {
+                    response.metadata.get_or_insert_with(Default::default)
+                        .insert("coalesced".to_string(), serde_json::json!(true));
+                    Ok(response)
+                }
+                Ok(Err(e)) => Err(anyhow::anyhow!(e)),
+                // The leading call was dropped before it finished
+                Err(_) => self.generate_recorded(service, request).await,
+            };
         }
-        
-        // Auto-select based on request characteristics
-        let context_length = self.estimate_context_length(request);
-        let requires_vision = self.requires_vision(request);
-        let requires_speed = self.requires_speed(request);
-        let requires_quality = self.requires_quality(request);
-        
-        // Score each available service
-        let mut scores: Vec<(ModelProvider, f64, ModelCapabilities)> = Vec::new();
-        
-        if let Some(ref service) = self.openai {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::OpenAI, score, service.capabilities().clone()));
+
+        let guard = FlightGuard { in_flight: &self.in_flight, key };
+        let result = self.generate_recorded(service, request).await;
+        if let Some(tx) = guard.take() {
+            let _ = tx.send(result.as_ref().cloned().map_err(|e| e.to_string()));
         }
-        
-        if let Some(ref service) = self.anthropic {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Anthropic, score, service.capabilities().clone()));
+        result
+    }
+
+    /// Send a request to the best provider, moving down the ranking on errors
+    ///
+    /// A provider pinned through `request.model` is tried first, then the rest
+    /// by descending score. Providers whose context window can't hold the
+    /// request, or whose circuit is open, are passed over. At most
+    /// `max_fallbacks` providers are tried after the first.
+    pub async fn generate_with_fallback(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
+        let tokens = TokenCounter::shared().measure(&request);
+        let pinned = request.model.as_deref()
+            .and_then(|model| self.parse_provider_from_model(model));
+
+        let mut candidates = self.ranked_adapters(&request);
+        if let Some(index) = pinned.as_ref().and_then(|p| candidates.iter().position(|a| a.provider() == *p)) {
+            let adapter = candidates.remove(index);
+            candidates.insert(0, adapter);
         }
-        
-        if let Some(ref service) = self.google {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Google, score, service.capabilities().clone()));
+
+        let mut error = FallbackError { attempts: Vec::new(), skipped: Vec::new() };
+        for service in candidates {
+            let provider = service.provider();
+            if service.capabilities().max_context_length < tokens.for_provider(&provider) {
+                error.skipped.push(provider);
+                continue;
+            }
+            if error.attempts.len() > self.max_fallbacks {
+                break;
+            }
+
+            // Only the pinned provider understands the pinned model name
+            let attempt = if pinned.as_ref() == Some(&provider) {
+                request.clone()
+            } else {
+                request.clone_for_fallback()
+            };
+            match self.generate_with(&service, attempt).await {
+                Ok(mut response) => {
+                    if !error.attempts.is_empty() {
+                        tracing::info!("{:?} served the request after {} failed provider(s)", provider, error.attempts.len());
+                        let failed: Vec<_> = error.attempts.iter().map(|(p, _)| p).collect();
+                        response.metadata.get_or_insert_with(Default::default)
+                            .insert("fallback_from".to_string(), serde_json::json!(failed));
+                    }
+                    return Ok(response);
+                }
+                Err(e) => {
+                    tracing::warn!("Provider {:?} failed, trying the next one: {}", provider, e);
+                    error.attempts.push((provider, e.to_string()));
+                }
+            }
         }
-        
-        if let Some(ref service) = self.moonshot {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Moonshot, score, service.capabilities().clone()));
+        Err(error.into())
+    }
+
+    async fn generate_recorded(&self, service: &AIServiceEnum, request: AIRequest) -> anyhow::Result<AIResponse> {
+        let started = std::time::Instant::now();
+        let result = self.send(service, request).await;
+        self.health.record_latency(&service.provider(), started.elapsed());
+        match &result {
+            Ok(_) => self.health.record_success(&service.provider()),
+            Err(_) => self.health.record_failure(&service.provider()),
         }
-        
-        if let Some(ref service) = self.deepseek {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::DeepSeek, score, service.capabilities().clone()));
+        result
+    }
+
+    /// Redact secrets on the way out if enabled
+    async fn send(&self, service: &AIServiceEnum, request: AIRequest) -> anyhow::Result<AIResponse> {
+        let Some(redactor) = self.secret_redactor.as_ref().filter(|r| r.is_enabled()) else {
+            return service.generate(request).await;
+        };
+
+        let (redacted, map) = redactor.redact_request(&request).await;
+        let mut response = service.generate(redacted).await?;
+        if !map.is_empty() {
+            response.content = redactor.restore(&response.content, &map);
+            response.metadata.get_or_insert_with(Default::default)
+                .insert("secrets_redacted".to_string(), serde_json::json!(map.len()));
         }
+        Ok(response)
+    }
+    
+    /// Select a model and make sure the request fits its context window
+    ///
+    /// Uses the request's `context_overflow` policy, falling back to the configured default.
+    pub fn select_with_context_policy(&self, request: &AIRequest) -> anyhow::Result<ContextRouting> {
+        let selected = self.select_best_model(request)?;
+        let tokens = TokenCounter::shared().measure(request);
+        let context_length = tokens.for_provider(&selected.provider);
+        let window = selected.capabilities.max_context_length;
         
-        if let Some(ref service) = self.mistral {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Mistral, score, service.capabilities().clone()));
-        }
+        // Reported with every response so routing decisions can be debugged
+        let mut metadata = HashMap::new();
+        metadata.insert("context_tokens".to_string(), serde_json::json!(context_length));
+        metadata.insert("token_counter".to_string(), serde_json::json!(tokens.method()));
         
-        if let Some(ref service) = self.cohere {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Cohere, score, service.capabilities().clone()));
+        if context_length <= window {
+            return Ok(ContextRouting {
+                model: selected,
+                request: request.clone(),
+                metadata,
+            });
         }
         
-        if let Some(ref service) = self.perplexity {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Perplexity, score, service.capabilities().clone()));
-        }
+        let policy = request.context_overflow.unwrap_or(self.context_overflow_policy);
+        metadata.insert("estimated_context_tokens".to_string(), serde_json::json!(context_length));
         
-        if let Some(ref service) = self.xai {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::XAI, score, service.capabilities().clone()));
+        let mut model = selected;
+        if policy == ContextOverflowPolicy::Escalate {
+            if let Some((provider, capabilities)) = escalation_target(&self.available_models(), context_length) {
+                if provider != model.provider {
+                    tracing::info!(
+                        "Escalating from {:?} to {:?} for {} token context",
+                        model.provider, provider, context_length
+                    );
+                    metadata.insert("context_overflow".to_string(), serde_json::json!("escalated"));
+                    metadata.insert("escalated_from".to_string(), serde_json::json!(model.provider));
+                    model = ModelInfo {
+                        model: self.get_default_model(&provider),
+                        provider,
+                        capabilities,
+                    };
+                }
+            }
         }
         
-        if let Some(ref service) = self.together {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Together, score, service.capabilities().clone()));
+        // Truncate whatever still doesn't fit (always the case for the truncate policy)
+        let mut routed_request = request.clone();
+        let context_length = tokens.for_provider(&model.provider);
+        if context_length > model.capabilities.max_context_length {
+            let budget = model.capabilities.max_context_length
+                .saturating_sub(request.max_tokens.unwrap_or(0));
+            // Truncation works in cheap length-based units; scale the budget into them
+            let units_per_token = estimate_request_tokens(request) as f64 / context_length.max(1) as f64;
+            routed_request = truncate_to_fit(request, (budget as f64 * units_per_token) as u32);
+            metadata.insert("context_overflow".to_string(), serde_json::json!("truncated"));
+            metadata.insert(
+                "truncated_context_tokens".to_string(),
+                serde_json::json!(self.estimate_context_length(&routed_request, &model.provider)),
+            );
         }
         
-        if let Some(ref service) = self.anyscale {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Anyscale, score, service.capabilities().clone()));
-        }
+        // Pin the routed model so downstream selection doesn't undo the decision
+        routed_request.model = Some(model.model.clone());
+        metadata.insert("context_window".to_string(), serde_json::json!(model.capabilities.max_context_length));
         
-        if let Some(ref service) = self.qwen {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Qwen, score, service.capabilities().clone()));
-        }
+        Ok(ContextRouting {
+            model,
+            request: routed_request,
+            metadata,
+        })
+    }
+    
+    /// Providers with a configured service and their capabilities
+    fn available_models(&self) -> Vec<(ModelProvider, ModelCapabilities)> {
+        self.adapters.iter()
+            .map(|adapter| (adapter.provider(), adapter.capabilities().clone()))
+            .collect()
+    }
+    
+    /// Intelligently selects the best model for a given request
+    /// Considers: context length, cost, speed, quality, task type
+    pub fn select_best_model(&self, request: &AIRequest) -> anyhow::Result<ModelInfo> {
+        self.explain_selection(request).map(|decision| decision.selected)
+    }
+    
+    /// Select a model and report everything that went into the choice
+    pub fn explain_selection(&self, request: &AIRequest) -> anyhow::Result<RoutingDecision> {
+        let tokens = TokenCounter::shared().measure(request);
+        let scored = self.scored_adapters(request, &tokens);
+        let routable: Vec<_> = scored.iter().map(|(score, _)| score.provider.clone()).collect();
         
-        if let Some(ref service) = self.zeroone {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::ZeroOne, score, service.capabilities().clone()));
-        }
+        // Check if specific model requested (and its provider can take it)
+        let pinned = request.model.as_ref().and_then(|model_str| {
+            let provider = self.parse_provider_from_model(model_str)
+                .filter(|provider| routable.contains(provider))?;
+            let service = self.get_service(provider.clone())?;
+            Some(ModelInfo {
+                provider,
+                model: model_str.clone(),
+                capabilities: service.capabilities().clone(),
+            })
+        });
         
-        if let Some(ref service) = self.baidu {
-            let score = self.score_service(service.as_ref(), context_length, requires_vision, requires_speed, requires_quality);
-            scores.push((ModelProvider::Baidu, score, service.capabilities().clone()));
-        }
+        // Otherwise auto-select based on request characteristics
+        let (selected, is_pinned) = match pinned {
+            Some(model) => (model, true),
+            None => {
+                let (_, best) = scored.first()
+                    .ok_or_else(|| anyhow::anyhow!("No AI services available"))?;
+                let provider = best.provider();
+                (ModelInfo {
+                    model: self.get_default_model(&provider),
+                    provider,
+                    capabilities: best.capabilities().clone(),
+                }, false)
+            }
+        };
         
-        if scores.is_empty() {
-            return Err(anyhow::anyhow!("No AI services available"));
+        Ok(RoutingDecision {
+            context_length: tokens.for_provider(&selected.provider),
+            token_counter: tokens.method(),
+            selected,
+            pinned: is_pinned,
+            requires_vision: self.requires_vision(request),
+            requires_speed: self.requires_speed(request),
+            requires_quality: self.requires_quality(request),
+            scores: scored.into_iter().map(|(score, _)| score).collect(),
+            unavailable: self.adapters.iter()
+                .map(|adapter| adapter.provider())
+                .filter(|provider| !routable.contains(provider))
+                .collect(),
+        })
+    }
+    
+    /// Configured adapters, best score for this request first
+    fn ranked_adapters(&self, request: &AIRequest) -> Vec<AIServiceEnum> {
+        let tokens = TokenCounter::shared().measure(request);
+        self.scored_adapters(request, &tokens).into_iter().map(|(_, adapter)| adapter).collect()
+    }
+    
+    /// Ready adapters whose circuit isn't open
+    ///
+    /// When every ready adapter's circuit is open, the one that failed longest
+    /// ago is returned alone rather than refusing the request outright.
+    fn routable_adapters(&self) -> Vec<&AIServiceEnum> {
+        let ready: Vec<&AIServiceEnum> = self.adapters.iter()
+            .filter(|adapter| adapter.is_ready())
+            .collect();
+        let closed: Vec<&AIServiceEnum> = ready.iter()
+            .copied()
+            .filter(|adapter| self.health.circuit_state(&adapter.provider()) != CircuitState::Open)
+            .collect();
+        if !closed.is_empty() || ready.is_empty() {
+            return closed;
         }
         
-        // Sort by score and select best
-        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        let (provider, _, capabilities) = &scores[0];
+        let providers: Vec<_> = ready.iter().map(|adapter| adapter.provider()).collect();
+        let retry = self.health.least_recently_failed(&providers);
+        tracing::warn!("Every provider circuit is open; retrying {:?}, which failed longest ago", retry);
+        ready.into_iter()
+            .filter(|adapter| Some(adapter.provider()) == retry)
+            .collect()
+    }
+    
+    /// Routable adapters with their scores for this request, best first
+    fn scored_adapters(&self, request: &AIRequest, tokens: &RequestTokens) -> Vec<(ProviderScore, AIServiceEnum)> {
+        let requires_vision = self.requires_vision(request);
+        let requires_speed = self.requires_speed(request);
+        let requires_quality = self.requires_quality(request);
         
-        Ok(ModelInfo {
-            provider: provider.clone(),
-            model: self.get_default_model(provider),
-            capabilities: capabilities.clone(),
-        })
+        let mut scores: Vec<(ProviderScore, AIServiceEnum)> = self.routable_adapters().into_iter()
+            .map(|adapter| {
+                let provider = adapter.provider();
+                let context_tokens = tokens.for_provider(&provider);
+                let score = ProviderScore {
+                    score: self.score_service(adapter.as_ref(), context_tokens, requires_vision, requires_speed, requires_quality),
+                    fits_context: adapter.capabilities().max_context_length >= context_tokens,
+                    context_tokens,
+                    provider,
+                };
+                (score, Arc::clone(adapter))
+            })
+            .collect();
+        scores.sort_by(|a, b| b.0.score.partial_cmp(&a.0.score).unwrap_or(std::cmp::Ordering::Equal));
+        scores
     }
     
     fn score_service(
         &self,
-        service: &dyn AIService,
+        service: &dyn ProviderAdapter,
         context_length: u32,
         requires_vision: bool,
         requires_speed: bool,
         requires_quality: bool,
     ) -> f64 {
         let caps = service.capabilities();
+        let weights = &self.weights;
         let mut score = 0.0;
         
         // Context length match (higher is better)
         if caps.max_context_length >= context_length {
-            score += 10.0;
+            score += weights.context;
         } else {
-            score -= 20.0; // Penalty for insufficient context
+            score -= weights.context * 2.0; // Penalty for insufficient context
         }
         
         // Vision support
         if requires_vision && caps.supports_vision {
-            score += 5.0;
+            score += weights.vision;
         }
         
         // Speed preference
         if requires_speed {
             match caps.speed {
-                crate::types::Speed::Fast => score += 5.0,
-                crate::types::Speed::Medium => score += 2.0,
+                crate::types::Speed::Fast => score += weights.speed,
+                crate::types::Speed::Medium => score += weights.speed * 0.4,
                 crate::types::Speed::Slow => {},
             }
         }
@@ -330,37 +557,25 @@ impl ModelRouter {
         // Quality preference
         if requires_quality {
             match caps.quality {
-                crate::types::Quality::High => score += 5.0,
-                crate::types::Quality::Medium => score += 2.0,
+                crate::types::Quality::High => score += weights.quality,
+                crate::types::Quality::Medium => score += weights.quality * 0.4,
                 crate::types::Quality::Low => {},
             }
         }
         
-        // Cost efficiency (lower cost = higher score)
+        // Cost efficiency (lower cost = higher score). Free local models only win on
+        // price when the request doesn't call for quality.
         let avg_cost = (caps.cost_per_1k_tokens.input + caps.cost_per_1k_tokens.output) / 2.0;
-        score += (0.01 / avg_cost) * 2.0;
+        if avg_cost > 0.0 || !requires_quality {
+            score += (0.01 / avg_cost.max(MIN_SCORED_COST)) * weights.cost;
+        }
         
         score
     }
     
-    fn estimate_context_length(&self, request: &AIRequest) -> u32 {
-        let mut length = 0u32;
-        
-        // Messages
-        for msg in &request.messages {
-            length += (msg.content.len() as f32 / 4.0).ceil() as u32;
-        }
-        
-        // Context files
-        if let Some(ref context) = request.context {
-            if let Some(ref files) = context.files {
-                for file in files {
-                    length += (file.content.len() as f32 / 4.0).ceil() as u32;
-                }
-            }
-        }
-        
-        length
+    /// Tokens in the request as `provider` counts them
+    fn estimate_context_length(&self, request: &AIRequest, provider: &ModelProvider) -> u32 {
+        TokenCounter::shared().measure(request).for_provider(provider)
     }
     
     fn requires_vision(&self, request: &AIRequest) -> bool {
@@ -407,7 +622,9 @@ impl ModelRouter {
     
     fn parse_provider_from_model(&self, model: &str) -> Option<ModelProvider> {
         let model_lower = model.to_lowercase();
-        if model_lower.starts_with("gpt") || model_lower.starts_with("openai") {
+        if is_ollama_model(&model_lower, &self.local_models) {
+            Some(ModelProvider::Ollama)
+        } else if model_lower.starts_with("gpt") || model_lower.starts_with("openai") {
             Some(ModelProvider::OpenAI)
         } else if model_lower.starts_with("claude") || model_lower.starts_with("anthropic") {
             Some(ModelProvider::Anthropic)
@@ -456,27 +673,518 @@ impl ModelRouter {
             ModelProvider::Qwen => "qwen-plus".to_string(),
             ModelProvider::ZeroOne => "yi-1.5-34b-chat".to_string(),
             ModelProvider::Baidu => "ernie-4.0-8k".to_string(),
+            ModelProvider::Ollama => self.get_service(ModelProvider::Ollama)
+                .map(|service| service.default_model().to_string())
+                .unwrap_or_else(|| "llama3".to_string()),
             ModelProvider::Auto => "gpt-4-turbo-preview".to_string(),
         }
     }
     
+    /// Embed `texts` with the first ready provider that has an embeddings API
+    ///
+    /// There is deliberately no fallback: vectors from different models live in
+    /// different spaces, so mixing providers would make similarity scores meaningless.
+    pub async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let service = self.embedding_service()
+            .ok_or_else(|| anyhow::anyhow!("No configured provider supports embeddings"))?;
+        let texts = match &self.secret_redactor {
+            Some(redactor) => redactor.redact_texts(texts).await,
+            None => texts,
+        };
+        service.embed(texts).await
+    }
+    
+    /// Provider `embed` would use; store it alongside vectors to detect a switch
+    pub fn embedding_service(&self) -> Option<AIServiceEnum> {
+        self.adapters.iter()
+            .find(|adapter| adapter.is_ready() && adapter.supports_embeddings())
+            .cloned()
+    }
+    
     pub fn get_service(&self, provider: ModelProvider) -> Option<AIServiceEnum> {
-        match provider {
-            ModelProvider::OpenAI => self.openai.as_ref().map(|s| AIServiceEnum::OpenAI(s.clone())),
-            ModelProvider::Anthropic => self.anthropic.as_ref().map(|s| AIServiceEnum::Anthropic(s.clone())),
-            ModelProvider::Google => self.google.as_ref().map(|s| AIServiceEnum::Google(s.clone())),
-            ModelProvider::Moonshot => self.moonshot.as_ref().map(|s| AIServiceEnum::Moonshot(s.clone())),
-            ModelProvider::DeepSeek => self.deepseek.as_ref().map(|s| AIServiceEnum::DeepSeek(s.clone())),
-            ModelProvider::Mistral => self.mistral.as_ref().map(|s| AIServiceEnum::Mistral(s.clone())),
-            ModelProvider::Cohere => self.cohere.as_ref().map(|s| AIServiceEnum::Cohere(s.clone())),
-            ModelProvider::Perplexity => self.perplexity.as_ref().map(|s| AIServiceEnum::Perplexity(s.clone())),
-            ModelProvider::XAI => self.xai.as_ref().map(|s| AIServiceEnum::XAI(s.clone())),
-            ModelProvider::Together => self.together.as_ref().map(|s| AIServiceEnum::Together(s.clone())),
-            ModelProvider::Anyscale => self.anyscale.as_ref().map(|s| AIServiceEnum::Anyscale(s.clone())),
-            ModelProvider::Qwen => self.qwen.as_ref().map(|s| AIServiceEnum::Qwen(s.clone())),
-            ModelProvider::ZeroOne => self.zeroone.as_ref().map(|s| AIServiceEnum::ZeroOne(s.clone())),
-            ModelProvider::Baidu => self.baidu.as_ref().map(|s| AIServiceEnum::Baidu(s.clone())),
-            ModelProvider::Auto => None,
+        self.adapters.iter()
+            .find(|adapter| adapter.provider() == provider)
+            .cloned()
+    }
+
+    /// Available provider with the lowest per-token price, for housekeeping calls like history summaries
+    pub fn cheapest_service(&self) -> Option<AIServiceEnum> {
+        let price = |adapter: &AIServiceEnum| {
+            let cost = &adapter.capabilities().cost_per_1k_tokens;
+            cost.input + cost.output
+        };
+        self.adapters.iter()
+            .filter(|adapter| adapter.is_ready() && self.health.is_available(&adapter.provider()))
+            .min_by(|a, b| price(a).partial_cmp(&price(b)).unwrap_or(std::cmp::Ordering::Equal))
+            .cloned()
+    }
+
+    pub fn health(&self) -> &ProviderHealthTracker {
+        &self.health
+    }
+
+    /// Whether any configured provider is outside its failure cooldown
+    pub fn has_available_provider(&self) -> bool {
+        self.adapters.iter().any(|adapter| adapter.is_ready() && self.health.is_available(&adapter.provider()))
+    }
+
+    pub fn configured_providers(&self) -> Vec<ModelProvider> {
+        self.adapters.iter().map(|adapter| adapter.provider()).collect()
+    }
+}
+
+/// Key shared by requests whose responses are interchangeable, or None if they may differ
+///
+/// Only non-streaming requests at temperature 0 qualify. Message timestamps and
+/// metadata don't reach the model, so they are left out of the key.
+fn coalescing_key(provider: &ModelProvider, request: &AIRequest) -> Option<u64> {
+    use std::hash::{Hash, Hasher};
+
+    if request.stream == Some(true) || request.temperature != Some(0.0) {
+        return None;
+    }
+    let messages: Vec<_> = request.messages.iter()
+        .map(|m| serde_json::json!([m.role, m.content]))
+        .collect();
+    let identity = serde_json::json!({
+        "provider": provider,
+        "model": request.model,
+        "messages": messages,
+        "max_tokens": request.max_tokens,
+        "context": request.context,
+        "response_language": request.response_language,
+        "sanitize_output": request.sanitize_output,
+        "json_output": request.json_output,
+    });
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    identity.to_string().hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Rough length-based token estimate for a request (messages + context files)
+///
+/// Cheap enough to call in loops; routing decisions use `TokenCounter` instead.
+pub(crate) fn estimate_request_tokens(request: &AIRequest) -> u32 {
+    let mut length = 0u32;
+    
+    // Messages
+    for msg in &request.messages {
+        length += (msg.content.len() as f32 / 4.0).ceil() as u32;
+    }
+    
+    // Context files
+    if let Some(ref context) = request.context {
+        if let Some(ref files) = context.files {
+            for file in files {
+                length += (file.content.len() as f32 / 4.0).ceil() as u32;
+            }
+        }
+    }
+    
+    length
+}
+
+/// Pick the provider to escalate to: the smallest window that fits, else the largest window
+fn escalation_target(
+    available: &[(ModelProvider, ModelCapabilities)],
+    context_length: u32,
+) -> Option<(ModelProvider, ModelCapabilities)> {
+    available.iter()
+        .filter(|(_, caps)| caps.max_context_length >= context_length)
+        .min_by_key(|(_, caps)| caps.max_context_length)
+        .or_else(|| available.iter().max_by_key(|(_, caps)| caps.max_context_length))
+        .cloned()
+}
+
+/// Shrink a request to fit `budget` tokens
+///
+/// Drops context files last-to-first, then the oldest non-system messages, and finally
+/// keeps only the tail of the newest message.
+fn truncate_to_fit(request: &AIRequest, budget: u32) -> AIRequest {
+    let mut truncated = request.clone();
+    
+    while estimate_request_tokens(&truncated) > budget {
+        let files = truncated.context.as_mut().and_then(|c| c.files.as_mut());
+        match files {
+            Some(files) if !files.is_empty() => {
+                files.pop();
+            }
+            _ => break,
+        }
+    }
+    
+    while estimate_request_tokens(&truncated) > budget {
+        let non_system = truncated.messages.iter()
+            .filter(|m| !matches!(m.role, MessageRole::System))
+            .count();
+        if non_system <= 1 {
+            break;
+        }
+        if let Some(index) = truncated.messages.iter().position(|m| !matches!(m.role, MessageRole::System)) {
+            truncated.messages.remove(index);
+        }
+    }
+    
+    let overflow = estimate_request_tokens(&truncated).saturating_sub(budget);
+    if overflow > 0 {
+        if let Some(last) = truncated.messages.last_mut() {
+            let drop_chars = (overflow as usize) * 4;
+            let chars: Vec<char> = last.content.chars().collect();
+            last.content = chars[drop_chars.min(chars.len())..].iter().collect();
+        }
+    }
+    
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AIMessage, CostPer1kTokens, Quality, Speed};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Adapter that answers locally with its own name and counts its calls
+    struct FakeAdapter {
+        provider: ModelProvider,
+        name: &'static str,
+        capabilities: ModelCapabilities,
+        calls: Arc<AtomicUsize>,
+        fails: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl AIService for FakeAdapter {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn capabilities(&self) -> &ModelCapabilities {
+            &self.capabilities
+        }
+
+        async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            // Stay in flight long enough for concurrent callers to overlap
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            if self.fails {
+                anyhow::bail!("{} returned 503", self.name);
+            }
+            let body = self.build_request(&request, self.default_model());
+            self.parse_response(&body, self.default_model())
+        }
+    }
+
+    impl ProviderAdapter for FakeAdapter {
+        fn provider(&self) -> ModelProvider {
+            self.provider.clone()
         }
+
+        fn display_name(&self) -> &str {
+            self.name
+        }
+
+        fn default_model(&self) -> &str {
+            "fake-model"
+        }
+
+        fn endpoint(&self, _model: &str) -> String {
+            "http://localhost".to_string()
+        }
+
+        fn headers(&self, _api_key: &str) -> Vec<(&'static str, String)> {
+            Vec::new()
+        }
+
+        fn build_request(&self, _request: &AIRequest, _model: &str) -> serde_json::Value {
+            serde_json::json!({ "handled_by": self.name })
+        }
+
+        fn parse_response(&self, body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+            Ok(AIResponse {
+                content: body["handled_by"].as_str().unwrap_or_default().to_string(),
+                model: model.to_string(),
+                usage: self.extract_usage(body),
+                finish_reason: None,
+                metadata: None,
+            })
+        }
+
+        fn extract_usage(&self, _body: &serde_json::Value) -> Option<crate::types::TokenUsage> {
+            None
+        }
+    }
+
+    fn fake(provider: ModelProvider, name: &'static str) -> AIServiceEnum {
+        Arc::new(FakeAdapter { provider, name, capabilities: caps(128_000), calls: Arc::default(), fails: false })
+    }
+
+    /// Config without the local Ollama server, so a developer's running instance can't join the tests
+    fn config() -> Config {
+        let mut config = Config::from_env().unwrap();
+        config.ollama_base_url.clear();
+        config
+    }
+
+    fn caps(max_context_length: u32) -> ModelCapabilities {
+        ModelCapabilities {
+            supports_vision: false,
+            supports_function_calling: false,
+            max_context_length,
+            supports_streaming: true,
+            cost_per_1k_tokens: CostPer1kTokens { input: 0.001, output: 0.002 },
+            speed: Speed::Fast,
+            quality: Quality::Medium,
+        }
+    }
+
+    fn oversized_request(policy: ContextOverflowPolicy) -> AIRequest {
+        let message = |role: MessageRole, content: String| AIMessage {
+            role,
+            content,
+            timestamp: None,
+            metadata: None,
+        };
+        AIRequest {
+            messages: vec![
+                message(MessageRole::System, "You are helpful".to_string()),
+                message(MessageRole::User, "a".repeat(200_000)),
+                message(MessageRole::User, "b".repeat(200_000)),
+            ],
+            model: None,
+            temperature: None,
+            max_tokens: Some(1000),
+            stream: None,
+            context: None,
+            context_overflow: Some(policy),
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
+        }
+    }
+
+    #[test]
+    fn test_escalate_policy_promotes_to_long_context_provider() {
+        let request = oversized_request(ContextOverflowPolicy::Escalate);
+        let needed = estimate_request_tokens(&request);
+        assert!(needed > 64_000);
+
+        let available = vec![
+            (ModelProvider::DeepSeek, caps(64_000)),
+            (ModelProvider::Anthropic, caps(200_000)),
+            (ModelProvider::Google, caps(1_000_000)),
+        ];
+        let (provider, capabilities) = escalation_target(&available, needed).unwrap();
+
+        // Smallest window that still fits
+        assert_eq!(provider, ModelProvider::Anthropic);
+        assert!(capabilities.max_context_length >= needed);
+    }
+
+    #[test]
+    fn test_truncate_policy_fits_budget_and_keeps_latest_message() {
+        let request = oversized_request(ContextOverflowPolicy::Truncate);
+        let truncated = truncate_to_fit(&request, 32_000);
+
+        assert!(estimate_request_tokens(&truncated) <= 32_000);
+        assert!(matches!(truncated.messages[0].role, MessageRole::System));
+        assert!(truncated.messages.last().unwrap().content.starts_with('b'));
+    }
+
+    #[tokio::test]
+    async fn test_registry_dispatches_by_provider() {
+        let router = ModelRouter::new(&config())
+            .with_adapter(fake(ModelProvider::Anthropic, "fake-anthropic"))
+            .with_adapter(fake(ModelProvider::DeepSeek, "fake-deepseek"));
+
+        let request = oversized_request(ContextOverflowPolicy::Truncate);
+        let expected = [
+            (ModelProvider::Anthropic, "fake-anthropic"),
+            (ModelProvider::DeepSeek, "fake-deepseek"),
+        ];
+        for (provider, name) in expected {
+            let adapter = router.get_service(provider.clone()).unwrap();
+            assert_eq!(adapter.provider(), provider);
+            let response = adapter.generate(request.clone()).await.unwrap();
+            assert_eq!(response.content, name);
+        }
+
+        // Model names route to the matching adapter
+        let mut pinned = request;
+        pinned.messages.truncate(2);
+        pinned.model = Some("claude-3-5-sonnet-20241022".to_string());
+        assert_eq!(router.select_best_model(&pinned).unwrap().provider, ModelProvider::Anthropic);
+        assert!(router.get_service(ModelProvider::Auto).is_none());
+    }
+
+    #[test]
+    fn test_cost_weight_can_flip_premium_to_cheap() {
+        let adapter = |provider, name, input, output, quality| -> AIServiceEnum {
+            Arc::new(FakeAdapter {
+                provider,
+                name,
+                capabilities: ModelCapabilities {
+                    cost_per_1k_tokens: CostPer1kTokens { input, output },
+                    quality,
+                    ..caps(128_000)
+                },
+                calls: Arc::default(),
+                fails: false,
+            })
+        };
+        let route = |config: &Config| {
+            let router = ModelRouter::new(config)
+                .with_adapter(adapter(ModelProvider::DeepSeek, "cheap", 0.003, 0.005, Quality::Low))
+                .with_adapter(adapter(ModelProvider::Anthropic, "premium", 0.005, 0.015, Quality::High));
+            let mut request = oversized_request(ContextOverflowPolicy::Truncate);
+            request.messages.truncate(1);
+            request.messages[0].content = "Harden this production deployment".to_string();
+            router.select_best_model(&request).unwrap().provider
+        };
+
+        let mut config = config();
+        assert_eq!(config.routing_weights, RoutingWeights::default());
+        assert_eq!(route(&config), ModelProvider::Anthropic);
+
+        config.routing_weights.cost = 4.0;
+        assert_eq!(route(&config), ModelProvider::DeepSeek);
+    }
+
+    #[test]
+    fn test_open_circuits_are_skipped_until_all_are_open() {
+        let router = ModelRouter::new(&config())
+            .with_adapter(fake(ModelProvider::Anthropic, "fake-anthropic"))
+            .with_adapter(fake(ModelProvider::DeepSeek, "fake-deepseek"));
+        let mut request = oversized_request(ContextOverflowPolicy::Truncate);
+        request.messages.truncate(2);
+        assert_eq!(router.select_best_model(&request).unwrap().provider, ModelProvider::Anthropic);
+
+        for _ in 0..3 {
+            router.health().record_failure(&ModelProvider::Anthropic);
+        }
+        let decision = router.explain_selection(&request).unwrap();
+        assert_eq!(decision.selected.provider, ModelProvider::DeepSeek);
+        assert_eq!(decision.unavailable, vec![ModelProvider::Anthropic]);
+
+        // With every circuit open, the provider that failed longest ago gets the request
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        for _ in 0..3 {
+            router.health().record_failure(&ModelProvider::DeepSeek);
+        }
+        assert_eq!(router.select_best_model(&request).unwrap().provider, ModelProvider::Anthropic);
+    }
+
+    #[test]
+    fn test_routing_decision_explains_scores() {
+        let router = ModelRouter::new(&config())
+            .with_adapter(fake(ModelProvider::Anthropic, "fake-anthropic"))
+            .with_adapter(Arc::new(FakeAdapter {
+                provider: ModelProvider::Google,
+                name: "fake-google",
+                capabilities: caps(100),
+                calls: Arc::default(),
+                fails: false,
+            }));
+
+        let mut request = oversized_request(ContextOverflowPolicy::Truncate);
+        request.messages.truncate(1);
+        request.messages[0].content = "Review this security-critical design: ".to_string() + &"x".repeat(2_000);
+
+        let decision = router.explain_selection(&request).unwrap();
+        assert!(!decision.pinned);
+        assert!(decision.requires_quality && decision.requires_vision && !decision.requires_speed);
+        assert_eq!(decision.selected.provider, ModelProvider::Anthropic);
+        assert_eq!(decision.context_length, decision.scores[0].context_tokens);
+        let order: Vec<_> = decision.scores.iter().map(|s| (s.provider.clone(), s.fits_context)).collect();
+        assert_eq!(order, vec![(ModelProvider::Anthropic, true), (ModelProvider::Google, false)]);
+        assert!(decision.scores[0].score > decision.scores[1].score);
+
+        // A pinned model is reported as such, with the scores still attached
+        request.model = Some("gemini-1.5-pro".to_string());
+        let decision = router.explain_selection(&request).unwrap();
+        assert!(decision.pinned);
+        assert_eq!(decision.selected.provider, ModelProvider::Google);
+        assert_eq!(decision.scores.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_identical_deterministic_requests_share_one_call() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service: AIServiceEnum = Arc::new(FakeAdapter {
+            provider: ModelProvider::Anthropic,
+            name: "fake-anthropic",
+            capabilities: caps(128_000),
+            calls: Arc::clone(&calls),
+            fails: false,
+        });
+        let router = ModelRouter::new(&config()).with_adapter(Arc::clone(&service));
+
+        let mut request = oversized_request(ContextOverflowPolicy::Truncate);
+        request.messages.truncate(2);
+        request.temperature = Some(0.0);
+
+        let responses = futures::future::join_all(
+            (0..8).map(|_| router.generate_with(&service, request.clone()))
+        ).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(responses.iter().all(|r| r.as_ref().unwrap().content == "fake-anthropic"));
+        let coalesced = responses.iter()
+            .filter(|r| r.as_ref().unwrap().metadata.as_ref().is_some_and(|m| m.contains_key("coalesced")))
+            .count();
+        assert_eq!(coalesced, 7);
+
+        // Sampled requests may legitimately differ, so each gets its own call
+        calls.store(0, Ordering::SeqCst);
+        request.temperature = Some(0.7);
+        futures::future::join_all((0..3).map(|_| router.generate_with(&service, request.clone()))).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_moves_past_failing_and_undersized_providers() {
+        let failing = |provider, name| -> AIServiceEnum {
+            Arc::new(FakeAdapter { provider, name, capabilities: caps(128_000), calls: Arc::default(), fails: true })
+        };
+        let small: AIServiceEnum = Arc::new(FakeAdapter {
+            provider: ModelProvider::Google,
+            name: "fake-google",
+            capabilities: caps(100),
+            calls: Arc::default(),
+            fails: false,
+        });
+        let router = ModelRouter::new(&config())
+            .with_adapter(failing(ModelProvider::Anthropic, "fake-anthropic"))
+            .with_adapter(small)
+            .with_adapter(fake(ModelProvider::DeepSeek, "fake-deepseek"));
+
+        let mut request = oversized_request(ContextOverflowPolicy::Truncate);
+        request.messages.truncate(1);
+        request.messages[0].content = "x".repeat(2_000);
+        request.model = Some("claude-3-5-sonnet-20241022".to_string());
+
+        let response = router.generate_with_fallback(request.clone()).await.unwrap();
+        assert_eq!(response.content, "fake-deepseek");
+        assert_eq!(response.metadata.unwrap()["fallback_from"], serde_json::json!(["anthropic"]));
+
+        // With every provider failing, the error lists what was tried and skipped
+        let router = ModelRouter::new(&config())
+            .with_adapter(failing(ModelProvider::Anthropic, "fake-anthropic"))
+            .with_adapter(failing(ModelProvider::DeepSeek, "fake-deepseek"))
+            .with_adapter(Arc::new(FakeAdapter {
+                provider: ModelProvider::Google,
+                name: "fake-google",
+                capabilities: caps(100),
+                calls: Arc::default(),
+                fails: false,
+            }));
+        let error = router.generate_with_fallback(request).await.unwrap_err();
+        let error = error.downcast_ref::<FallbackError>().unwrap();
+        let tried: Vec<_> = error.attempts.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(tried, vec![ModelProvider::Anthropic, ModelProvider::DeepSeek]);
+        assert_eq!(error.skipped, vec![ModelProvider::Google]);
+        assert!(error.to_string().contains("fake-deepseek returned 503"));
     }
 }
@@ -0,0 +1,465 @@
+/**
+ * Shadow Routing
+ *
+ * Replays a sample of chat requests against an alternate (by default the
+ * cheapest) provider after the user already has their answer, and records how
+ * the two calls compared on latency, tokens and cost. Shadow calls run in the
+ * background, spend from their own daily budget, and never touch the primary
+ * response. Comparisons back GET /api/v1/models/shadow-report.
+ */
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::database::Database;
+use crate::types::{AIRequest, AIResponse, CostPer1kTokens, ModelProvider};
+use super::router::{estimate_request_tokens, AIServiceEnum, ModelRouter};
+
+/// Comparisons kept in memory for the report when there is no database
+const MAX_RECENT: usize = 1000;
+/// Completion length assumed when reserving budget for a request without max_tokens
+const DEFAULT_COMPLETION_TOKENS: u32 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct ShadowSettings {
+    /// Share of chat requests replayed, 0-100; 0 disables shadowing
+    pub sample_percent: f64,
+    /// Provider to compare against; None picks the cheapest other available one
+    pub provider: Option<ModelProvider>,
+    /// Most estimated USD spent on shadow calls per UTC day
+    pub daily_budget_usd: f64,
+}
+
+/// One side of a comparison
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowCall {
+    pub provider: ModelProvider,
+    pub model: String,
+    pub latency_ms: u64,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowComparison {
+    pub primary: ShadowCall,
+    /// None when the shadow call failed
+    pub shadow: Option<ShadowCall>,
+    pub shadow_provider: ModelProvider,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Aggregate for one primary -> shadow provider pair
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowPairSummary {
+    pub primary_provider: ModelProvider,
+    pub shadow_provider: ModelProvider,
+    pub comparisons: usize,
+    pub shadow_failures: usize,
+    pub avg_primary_latency_ms: f64,
+    pub avg_shadow_latency_ms: Option<f64>,
+    /// Summed over comparisons where the shadow call succeeded
+    pub primary_cost_usd: f64,
+    pub shadow_cost_usd: f64,
+    /// Share of the primary cost the shadow provider would have saved; negative if it costs more
+    pub savings_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowReport {
+    pub enabled: bool,
+    pub sample_percent: f64,
+    pub daily_budget_usd: f64,
+    pub spent_today_usd: f64,
+    pub summary: Vec<ShadowPairSummary>,
+    pub comparisons: Vec<ShadowComparison>,
+}
+
+/// Row shape of `shadow_comparisons`
+#[derive(Debug, FromRow)]
+struct ShadowComparisonRecord {
+    primary_provider: String,
+    primary_model: String,
+    primary_latency_ms: i64,
+    primary_prompt_tokens: i32,
+    primary_completion_tokens: i32,
+    primary_cost_usd: f64,
+    shadow_provider: String,
+    shadow_model: Option<String>,
+    shadow_latency_ms: Option<i64>,
+    shadow_prompt_tokens: Option<i32>,
+    shadow_completion_tokens: Option<i32>,
+    shadow_cost_usd: Option<f64>,
+    error: Option<String>,
+    recorded_at: DateTime<Utc>,
+}
+
+fn provider_name(provider: &ModelProvider) -> String {
+    match serde_json::to_value(provider) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => format!("{:?}", provider).to_lowercase(),
+    }
+}
+
+fn parse_provider(name: &str) -> Option<ModelProvider> {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+}
+
+impl ShadowComparisonRecord {
+    fn into_comparison(self) -> Option<ShadowComparison> {
+        let shadow_provider = parse_provider(&self.shadow_provider)?;
+        let shadow = match (self.shadow_model, self.shadow_latency_ms) {
+            (Some(model), Some(latency_ms)) => Some(ShadowCall {
+                provider: shadow_provider.clone(),
+                model,
+                latency_ms: latency_ms.max(0) as u64,
+                prompt_tokens: self.shadow_prompt_tokens.unwrap_or(0).max(0) as u32,
+                completion_tokens: self.shadow_completion_tokens.unwrap_or(0).max(0) as u32,
+                cost_usd: self.shadow_cost_usd.unwrap_or(0.0),
+            }),
+            _ => None,
+        };
+        Some(ShadowComparison {
+            primary: ShadowCall {
+                provider: parse_provider(&self.primary_provider)?,
+                model: self.primary_model,
+                latency_ms: self.primary_latency_ms.max(0) as u64,
+                prompt_tokens: self.primary_prompt_tokens.max(0) as u32,
+                completion_tokens: self.primary_completion_tokens.max(0) as u32,
+                cost_usd: self.primary_cost_usd,
+            },
+            shadow,
+            shadow_provider,
+            error: self.error,
+            recorded_at: self.recorded_at,
+        })
+    }
+}
+
+fn cost_usd(prompt_tokens: u32, completion_tokens: u32, price: &CostPer1kTokens) -> f64 {
+    prompt_tokens as f64 / 1000.0 * price.input + completion_tokens as f64 / 1000.0 * price.output
+}
+
+/// Measure one call, estimating tokens from text when the provider reports no usage
+fn measure(service: &AIServiceEnum, request: &AIRequest, response: &AIResponse, latency: Duration) -> ShadowCall {
+    let (prompt_tokens, completion_tokens) = match &response.usage {
+        Some(usage) => (usage.prompt_tokens, usage.completion_tokens),
+        None => (
+            estimate_request_tokens(request),
+            (response.content.len() as f32 / 4.0).ceil() as u32,
+        ),
+    };
+    ShadowCall {
+        provider: service.provider(),
+        model: response.model.clone(),
+        latency_ms: latency.as_millis() as u64,
+        prompt_tokens,
+        completion_tokens,
+        cost_usd: cost_usd(prompt_tokens, completion_tokens, &service.capabilities().cost_per_1k_tokens),
+    }
+}
+
+/// Per-pair totals, most compared pair first
+pub fn summarize(comparisons: &[ShadowComparison]) -> Vec<ShadowPairSummary> {
+    let mut pairs: HashMap<(ModelProvider, ModelProvider), Vec<&ShadowComparison>> = HashMap::new();
+    for comparison in comparisons {
+        pairs.entry((comparison.primary.provider.clone(), comparison.shadow_provider.clone()))
+            .or_default()
+            .push(comparison);
+    }
+
+    let mut summaries: Vec<ShadowPairSummary> = pairs.into_iter()
+        .map(|((primary_provider, shadow_provider), group)| {
+            let succeeded: Vec<(&ShadowCall, &ShadowCall)> = group.iter()
+                .filter_map(|c| c.shadow.as_ref().map(|s| (&c.primary, s)))
+                .collect();
+            let primary_cost_usd: f64 = succeeded.iter().map(|(p, _)| p.cost_usd).sum();
+            let shadow_cost_usd: f64 = succeeded.iter().map(|(_, s)| s.cost_usd).sum();
+            ShadowPairSummary {
+                primary_provider,
+                shadow_provider,
+                comparisons: group.len(),
+                shadow_failures: group.len() - succeeded.len(),
+                avg_primary_latency_ms: group.iter().map(|c| c.primary.latency_ms as f64).sum::<f64>() / group.len() as f64,
+                avg_shadow_latency_ms: (!succeeded.is_empty()).then(|| {
+                    succeeded.iter().map(|(_, s)| s.latency_ms as f64).sum::<f64>() / succeeded.len() as f64
+                }),
+                primary_cost_usd,
+                shadow_cost_usd,
+                savings_percent: (primary_cost_usd > 0.0)
+                    .then(|| (primary_cost_usd - shadow_cost_usd) / primary_cost_usd * 100.0),
+            }
+        })
+        .collect();
+    summaries.sort_by(|a, b| b.comparisons.cmp(&a.comparisons)
+        .then_with(|| provider_name(&a.primary_provider).cmp(&provider_name(&b.primary_provider))));
+    summaries
+}
+
+pub struct ShadowEvaluator {
+    settings: ShadowSettings,
+    database: Option<Arc<Database>>,
+    recent: Mutex<VecDeque<ShadowComparison>>,
+    spend: Mutex<(NaiveDate, f64)>, // UTC day -> spent plus reserved
+}
+
+impl ShadowEvaluator {
+    pub fn new(settings: ShadowSettings, database: Option<Arc<Database>>) -> Self {
+        Self {
+            settings,
+            database,
+            recent: Mutex::new(VecDeque::new()),
+            spend: Mutex::new((Utc::now().date_naive(), 0.0)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.sample_percent > 0.0
+    }
+
+    fn sampled(&self) -> bool {
+        self.is_enabled() && rand::random::<f64>() * 100.0 < self.settings.sample_percent
+    }
+
+    /// Set aside `usd` of today's budget; false if it doesn't fit
+    fn reserve(&self, usd: f64) -> bool {
+        let mut spend = self.spend.lock().unwrap();
+        let today = Utc::now().date_naive();
+        if spend.0 != today {
+            *spend = (today, 0.0);
+        }
+        if spend.1 + usd > self.settings.daily_budget_usd {
+            return false;
+        }
+        spend.1 += usd;
+        true
+    }
+
+    /// Replace a reservation with what the call actually cost
+    fn settle(&self, reserved: f64, actual: f64) {
+        let mut spend = self.spend.lock().unwrap();
+        if spend.0 == Utc::now().date_naive() {
+            spend.1 = (spend.1 - reserved + actual).max(0.0);
+        }
+    }
+
+    pub fn spent_today(&self) -> f64 {
+        let spend = self.spend.lock().unwrap();
+        if spend.0 == Utc::now().date_naive() { spend.1 } else { 0.0 }
+    }
+
+    /// Provider to shadow `primary` with, if one is configured and available
+    fn alternate(&self, router: &ModelRouter, primary: &ModelProvider) -> Option<AIServiceEnum> {
+        let candidate = match &self.settings.provider {
+            Some(provider) => router.get_service(provider.clone())
+                .filter(|_| router.health().is_available(provider)),
+            None => router.cheapest_service(),
+        };
+        candidate.filter(|service| service.provider() != *primary)
+    }
+
+    /// Maybe replay a served chat request against the alternate provider
+    ///
+    /// Returns immediately; the shadow call and its bookkeeping run on a
+    /// spawned task.
+    pub fn observe(
+        self: &Arc<Self>,
+        router: &Arc<ModelRouter>,
+        primary: &AIServiceEnum,
+        request: &AIRequest,
+        response: &AIResponse,
+        latency: Duration,
+    ) {
+        if request.stream == Some(true) || !self.sampled() {
+            return;
+        }
+        let Some(alternate) = self.alternate(router, &primary.provider()) else {
+            return;
+        };
+
+        let price = &alternate.capabilities().cost_per_1k_tokens;
+        let reserved = cost_usd(
+            estimate_request_tokens(request),
+            request.max_tokens.unwrap_or(DEFAULT_COMPLETION_TOKENS),
+            price,
+        );
+        if !self.reserve(reserved) {
+            tracing::debug!("Shadow budget for today is used up; skipping comparison");
+            return;
+        }
+
+        let primary_call = measure(primary, request, response, latency);
+        let evaluator = Arc::clone(self);
+        let router = Arc::clone(router);
+        let request = request.clone();
+        tokio::spawn(async move {
+            let started = std::time::Instant::now();
+            let outcome = router.generate_with(&alternate, request.clone()).await;
+            let (shadow, error) = match outcome {
+                Ok(response) => (Some(measure(&alternate, &request, &response, started.elapsed())), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            evaluator.settle(reserved, shadow.as_ref().map_or(0.0, |s| s.cost_usd));
+            evaluator.record(ShadowComparison {
+                primary: primary_call,
+                shadow,
+                shadow_provider: alternate.provider(),
+                error,
+                recorded_at: Utc::now(),
+            }).await;
+        });
+    }
+
+    async fn record(&self, comparison: ShadowComparison) {
+        tracing::info!(
+            "Shadow comparison {:?} -> {:?}: {}ms/{:.5} USD vs {}",
+            comparison.primary.provider,
+            comparison.shadow_provider,
+            comparison.primary.latency_ms,
+            comparison.primary.cost_usd,
+            comparison.shadow.as_ref()
+                .map(|s| format!("{}ms/{:.5} USD", s.latency_ms, s.cost_usd))
+                .unwrap_or_else(|| "failed".to_string()),
+        );
+        if let Err(e) = self.save(&comparison).await {
+            tracing::warn!("Failed to store shadow comparison: {}", e);
+        }
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() >= MAX_RECENT {
+            recent.pop_front();
+        }
+        recent.push_back(comparison);
+    }
+
+    async fn save(&self, comparison: &ShadowComparison) -> anyhow::Result<()> {
+        let Some(ref db) = self.database else {
+            return Ok(());
+        };
+        let shadow = comparison.shadow.as_ref();
+        sqlx::query(
+            "INSERT INTO shadow_comparisons (
+                primary_provider, primary_model, primary_latency_ms, primary_prompt_tokens,
+                primary_completion_tokens, primary_cost_usd, shadow_provider, shadow_model,
+                shadow_latency_ms, shadow_prompt_tokens, shadow_completion_tokens, shadow_cost_usd,
+                error, recorded_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"
+        )
+        .bind(provider_name(&comparison.primary.provider))
+        .bind(&comparison.primary.model)
+        .bind(comparison.primary.latency_ms as i64)
+        .bind(comparison.primary.prompt_tokens as i32)
+        .bind(comparison.primary.completion_tokens as i32)
+        .bind(comparison.primary.cost_usd)
+        .bind(provider_name(&comparison.shadow_provider))
+        .bind(shadow.map(|s| s.model.clone()))
+        .bind(shadow.map(|s| s.latency_ms as i64))
+        .bind(shadow.map(|s| s.prompt_tokens as i32))
+        .bind(shadow.map(|s| s.completion_tokens as i32))
+        .bind(shadow.map(|s| s.cost_usd))
+        .bind(&comparison.error)
+        .bind(comparison.recorded_at)
+        .execute(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to save shadow comparison: {}", e))?;
+        Ok(())
+    }
+
+    /// Comparisons recorded since `since`, newest first
+    async fn comparisons_since(&self, since: DateTime<Utc>) -> anyhow::Result<Vec<ShadowComparison>> {
+        let Some(ref db) = self.database else {
+            let recent = self.recent.lock().unwrap();
+            return Ok(recent.iter().rev().filter(|c| c.recorded_at >= since).cloned().collect());
+        };
+
+        let rows = sqlx::query_as::<_, ShadowComparisonRecord>(
+            "SELECT primary_provider, primary_model, primary_latency_ms, primary_prompt_tokens,
+                    primary_completion_tokens, primary_cost_usd, shadow_provider, shadow_model,
+                    shadow_latency_ms, shadow_prompt_tokens, shadow_completion_tokens, shadow_cost_usd,
+                    error, recorded_at
+             FROM shadow_comparisons
+             WHERE recorded_at >= $1
+             ORDER BY recorded_at DESC"
+        )
+        .bind(since)
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to load shadow comparisons: {}", e))?;
+
+        Ok(rows.into_iter().filter_map(ShadowComparisonRecord::into_comparison).collect())
+    }
+
+    /// Summary over everything since `since`, plus the newest `limit` comparisons
+    pub async fn report(&self, since: DateTime<Utc>, limit: usize) -> anyhow::Result<ShadowReport> {
+        let mut comparisons = self.comparisons_since(since).await?;
+        let summary = summarize(&comparisons);
+        comparisons.truncate(limit);
+        Ok(ShadowReport {
+            enabled: self.is_enabled(),
+            sample_percent: self.settings.sample_percent,
+            daily_budget_usd: self.settings.daily_budget_usd,
+            spent_today_usd: self.spent_today(),
+            summary,
+            comparisons,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(provider: ModelProvider, latency_ms: u64, cost_usd: f64) -> ShadowCall {
+        ShadowCall {
+            provider,
+            model: "m".to_string(),
+            latency_ms,
+            prompt_tokens: 100,
+            completion_tokens: 50,
+            cost_usd,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_budget_caps_shadow_spend_and_report_summarizes_pairs() {
+        let evaluator = ShadowEvaluator::new(
+            ShadowSettings { sample_percent: 100.0, provider: None, daily_budget_usd: 0.10 },
+            None,
+        );
+
+        // Reservations stop at the daily cap; settling returns the unused part
+        assert!(evaluator.reserve(0.06));
+        assert!(!evaluator.reserve(0.06));
+        evaluator.settle(0.06, 0.01);
+        assert!(evaluator.reserve(0.06));
+        assert!((evaluator.spent_today() - 0.07).abs() < 1e-9);
+
+        for (shadow, error) in [
+            (Some(call(ModelProvider::DeepSeek, 300, 0.001)), None),
+            (Some(call(ModelProvider::DeepSeek, 500, 0.003)), None),
+            (None, Some("timeout".to_string())),
+        ] {
+            evaluator.record(ShadowComparison {
+                primary: call(ModelProvider::Anthropic, 800, 0.02),
+                shadow,
+                shadow_provider: ModelProvider::DeepSeek,
+                error,
+                recorded_at: Utc::now(),
+            }).await;
+        }
+
+        let report = evaluator.report(Utc::now() - chrono::Duration::hours(1), 2).await.unwrap();
+        assert_eq!(report.comparisons.len(), 2);
+        let pair = &report.summary[0];
+        assert_eq!((pair.comparisons, pair.shadow_failures), (3, 1));
+        assert_eq!(pair.avg_shadow_latency_ms, Some(400.0));
+        assert!((pair.primary_cost_usd - 0.04).abs() < 1e-9);
+        assert!((pair.savings_percent.unwrap() - 90.0).abs() < 1e-6);
+    }
+}
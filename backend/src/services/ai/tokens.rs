@@ -0,0 +1,162 @@
+/**
+ * Token Counting
+ *
+ * Counts request tokens with a real BPE tokenizer (cl100k_base) instead of
+ * assuming four bytes per token, which undercounts code and most non-English
+ * text. Providers whose tokenizers aren't public get the cl100k count scaled
+ * by how their vocabularies tend to compare. The byte heuristic is only used
+ * if the tokenizer can't be loaded.
+ */
+use std::sync::OnceLock;
+use tiktoken_rs::CoreBPE;
+
+use crate::types::{AIRequest, ModelProvider};
+
+/// Text is encoded in pieces of at most this many bytes. BPE merging is
+/// quadratic in the length of a single unbroken word, so one long minified
+/// line or base64 blob would otherwise stall routing.
+const CHUNK_BYTES: usize = 4096;
+
+pub struct TokenCounter {
+    bpe: Option<CoreBPE>,
+}
+
+/// Tokens in one request, convertible to any provider's count
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTokens {
+    base: u32,
+    exact: bool, // false when `base` came from the byte heuristic
+}
+
+/// Tokens per cl100k token, roughly, for providers with their own tokenizers
+fn provider_factor(provider: &ModelProvider) -> f64 {
+    match provider {
+        // Smaller vocabularies split code and identifiers into more pieces
+        ModelProvider::Anthropic | ModelProvider::Mistral => 1.15,
+        ModelProvider::Cohere => 1.05,
+        _ => 1.0,
+    }
+}
+
+fn heuristic_tokens(text: &str) -> u32 {
+    (text.len() as f32 / 4.0).ceil() as u32
+}
+
+impl RequestTokens {
+    /// Estimated tokens as `provider` would count them
+    pub fn for_provider(&self, provider: &ModelProvider) -> u32 {
+        if !self.exact {
+            return self.base;
+        }
+        (self.base as f64 * provider_factor(provider)).ceil() as u32
+    }
+
+    pub fn method(&self) -> &'static str {
+        if self.exact { "cl100k_base" } else { "heuristic" }
+    }
+}
+
+impl TokenCounter {
+    pub fn new() -> Self {
+        let bpe = tiktoken_rs::cl100k_base()
+            .map_err(|e| tracing::warn!("BPE tokenizer unavailable, estimating tokens from length: {}", e))
+            .ok();
+        Self { bpe }
+    }
+
+    /// Counter that always uses the byte-length heuristic
+    pub fn heuristic() -> Self {
+        Self { bpe: None }
+    }
+
+    /// Process-wide counter; the tokenizer tables are loaded once
+    pub fn shared() -> &'static TokenCounter {
+        static COUNTER: OnceLock<TokenCounter> = OnceLock::new();
+        COUNTER.get_or_init(TokenCounter::new)
+    }
+
+    pub fn count_text(&self, text: &str) -> u32 {
+        match &self.bpe {
+            Some(bpe) => {
+                let mut tokens = 0;
+                let mut rest = text;
+                while !rest.is_empty() {
+                    let mut end = rest.len().min(CHUNK_BYTES);
+                    while !rest.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    tokens += bpe.encode_ordinary(&rest[..end]).len() as u32;
+                    rest = &rest[end..];
+                }
+                tokens
+            }
+            None => heuristic_tokens(text),
+        }
+    }
+
+    /// Tokens in the request's messages and attached context files
+    pub fn measure(&self, request: &AIRequest) -> RequestTokens {
+        let files = request.context.iter()
+            .flat_map(|c| c.files.iter().flatten())
+            .map(|f| f.content.as_str());
+        let base = request.messages.iter()
+            .map(|m| m.content.as_str())
+            .chain(files)
+            .map(|text| self.count_text(text))
+            .sum();
+        RequestTokens { base, exact: self.bpe.is_some() }
+    }
+}
+
+impl Default for TokenCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AIMessage, MessageRole};
+
+    fn request(content: &str) -> AIRequest {
+        AIRequest {
+            messages: vec![AIMessage {
+                role: MessageRole::User,
+                content: content.to_string(),
+                timestamp: None,
+                metadata: None,
+            }],
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            stream: None,
+            context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
+        }
+    }
+
+    #[test]
+    fn test_bpe_count_differs_from_length_heuristic() {
+        let counter = TokenCounter::new();
+        assert_eq!(counter.count_text("hello world"), 2);
+
+        // Dense code and CJK text carry far more tokens than len/4 suggests
+        let code = "fn f(a:&[u8])->u8{a[0]^a[1]}".repeat(20);
+        let cjk = "東京都の天気予報によると明日は雨です。".repeat(20);
+        for text in [code.as_str(), cjk.as_str()] {
+            let tokens = counter.measure(&request(text));
+            assert_eq!(tokens.method(), "cl100k_base");
+            assert_ne!(tokens.for_provider(&ModelProvider::OpenAI), heuristic_tokens(text));
+            assert!(tokens.for_provider(&ModelProvider::Anthropic) > tokens.for_provider(&ModelProvider::OpenAI));
+        }
+
+        // Without a tokenizer the old estimate is used for every provider
+        let fallback = TokenCounter::heuristic().measure(&request(&code));
+        assert_eq!(fallback.method(), "heuristic");
+        assert_eq!(fallback.for_provider(&ModelProvider::Anthropic), heuristic_tokens(&code));
+    }
+}
@@ -0,0 +1,150 @@
+/**
+ * Per-Language Analyzer Profiles
+ *
+ * Thresholds for the code-smell detectors differ by language: a 50-line Go
+ * function is ordinary, and functional code nests expressions far deeper
+ * than imperative code nests blocks. Each language gets a built-in profile,
+ * and individual thresholds can be overridden through configuration.
+ */
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzerProfile {
+    /// Methods longer than this are flagged as Long Method
+    pub long_method_lines: u32,
+    /// Nesting beyond this depth is flagged as Deep Nesting
+    pub max_nesting_depth: usize,
+    /// Types with more methods than this are flagged as God Object
+    pub max_methods_per_type: usize,
+    /// Shortest run of statements reported as Duplicate Code
+    pub min_duplicate_statements: usize,
+    /// Share of statements two blocks must have in common to count as clones
+    pub duplicate_similarity_percent: u32,
+    /// Functions with a higher cyclomatic complexity are flagged as High Complexity
+    pub max_cyclomatic_complexity: u32,
+}
+
+impl Default for AnalyzerProfile {
+    fn default() -> Self {
+        Self {
+            long_method_lines: 50,
+            max_nesting_depth: 4,
+            max_methods_per_type: 20,
+            min_duplicate_statements: 6,
+            duplicate_similarity_percent: 85,
+            max_cyclomatic_complexity: 10,
+        }
+    }
+}
+
+/// Canonical name for a language or file extension ("rs" -> "rust")
+pub fn normalize_language(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    let canonical = match language.as_str() {
+        "rs" => "rust",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "py" => "python",
+        "golang" => "go",
+        "kt" | "kts" => "kotlin",
+        "cs" | "c#" => "csharp",
+        "cc" | "cxx" | "hpp" | "c++" => "cpp",
+        "rb" => "ruby",
+        "hs" => "haskell",
+        "ex" | "exs" => "elixir",
+        "erl" => "erlang",
+        "ml" => "ocaml",
+        "fs" | "f#" => "fsharp",
+        "clj" | "cljs" => "clojure",
+        other => other,
+    };
+    canonical.to_string()
+}
+
+impl AnalyzerProfile {
+    /// Shipped thresholds for `language`; unknown languages get the global default
+    pub fn builtin(language: &str) -> Self {
+        let profile = |long_method_lines, max_nesting_depth, max_methods_per_type| Self {
+            long_method_lines,
+            max_nesting_depth,
+            max_methods_per_type,
+            ..Self::default()
+        };
+        match normalize_language(language).as_str() {
+            "python" | "ruby" => profile(40, 4, 20),
+            "go" => profile(70, 4, 25),
+            "rust" => profile(60, 5, 25),
+            "java" | "csharp" | "kotlin" => profile(60, 5, 30),
+            "c" | "cpp" => profile(80, 5, 30),
+            // Pattern matching and pipelines nest deeply; modules export many small functions
+            "haskell" | "elixir" | "erlang" | "ocaml" | "fsharp" | "clojure" | "scala" => profile(40, 8, 40),
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Built-in profiles plus configured overrides
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzerProfiles {
+    overrides: HashMap<String, AnalyzerProfile>,
+}
+
+impl AnalyzerProfiles {
+    /// Parse overrides such as `rust:long_method_lines=80,max_nesting_depth=6;haskell:max_nesting_depth=10`
+    ///
+    /// Thresholds not mentioned keep the language's built-in value.
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut overrides = HashMap::new();
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (language, settings) = entry.split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Analyzer profile '{}' must look like language:setting=value", entry))?;
+            let language = normalize_language(language);
+            let mut profile = AnalyzerProfile::builtin(&language);
+
+            for setting in settings.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                let (name, value) = setting.split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("Analyzer setting '{}' for {} needs a value", setting, language))?;
+                let value: u32 = value.trim().parse()
+                    .map_err(|_| anyhow::anyhow!("Analyzer setting {} for {} must be a whole number", name.trim(), language))?;
+                match name.trim() {
+                    "long_method_lines" => profile.long_method_lines = value,
+                    "max_nesting_depth" => profile.max_nesting_depth = value as usize,
+                    "max_methods_per_type" => profile.max_methods_per_type = value as usize,
+                    "min_duplicate_statements" => profile.min_duplicate_statements = value.max(1) as usize,
+                    "duplicate_similarity_percent" => profile.duplicate_similarity_percent = value.min(100),
+                    "max_cyclomatic_complexity" => profile.max_cyclomatic_complexity = value,
+                    other => anyhow::bail!("Unknown analyzer setting '{}' for {}", other, language),
+                }
+            }
+            overrides.insert(language, profile);
+        }
+        Ok(Self { overrides })
+    }
+
+    pub fn for_language(&self, language: &str) -> AnalyzerProfile {
+        let language = normalize_language(language);
+        self.overrides.get(&language)
+            .copied()
+            .unwrap_or_else(|| AnalyzerProfile::builtin(&language))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_only_change_named_thresholds() {
+        let profiles = AnalyzerProfiles::parse("rs: long_method_lines=90; python:max_nesting_depth=3").unwrap();
+
+        let rust = profiles.for_language("rust");
+        assert_eq!(rust.long_method_lines, 90);
+        assert_eq!(rust.max_nesting_depth, AnalyzerProfile::builtin("rust").max_nesting_depth);
+        assert_eq!(profiles.for_language("py").max_nesting_depth, 3);
+        assert_eq!(profiles.for_language("cobol"), AnalyzerProfile::default());
+
+        assert!(AnalyzerProfiles::parse("rust:max_lines=3").is_err());
+        assert!(AnalyzerProfiles::parse("rust").is_err());
+    }
+}
@@ -27,7 +27,7 @@ pub struct Location {
     pub end_byte: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedSymbol {
     pub name: String,
     pub kind: SymbolKind,
@@ -37,7 +37,7 @@ pub struct ParsedSymbol {
     pub children: Vec<ParsedSymbol>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SymbolKind {
     Function,
     Class,
@@ -61,13 +61,8 @@ pub struct ASTParser {
 
 impl ASTParser {
     pub fn new() -> Self {
-        let mut parsers = HashMap::new();
-        
-        // Initialize parsers for supported languages
-        // Note: In production, these would be loaded from tree-sitter grammars
-        // For now, we'll create a structure that can be extended
-        
-        Self { parsers }
+        // Parsers are created per language on first use
+        Self { parsers: HashMap::new() }
     }
 
     /// Parse code into AST
@@ -146,14 +141,15 @@ impl ASTParser {
     fn traverse_for_symbols(&self, node: &ASTNode, source: &str, symbols: &mut Vec<ParsedSymbol>) {
         // Extract symbols based on node type
         let symbol_kind = match node.node_type.as_str() {
-            "function_declaration" | "function" | "method_definition" => Some(SymbolKind::Function),
+            "function_declaration" | "function" | "method_definition"
+            | "function_item" | "function_definition" => Some(SymbolKind::Function),
             "class_declaration" | "class_definition" => Some(SymbolKind::Class),
             "struct_item" | "struct_definition" => Some(SymbolKind::Struct),
             "interface_declaration" | "interface_definition" => Some(SymbolKind::Interface),
             "type_alias_declaration" | "type_definition" => Some(SymbolKind::Type),
             "variable_declaration" | "let_declaration" => Some(SymbolKind::Variable),
-            "const_declaration" => Some(SymbolKind::Constant),
-            "module" => Some(SymbolKind::Module),
+            "const_declaration" | "const_item" => Some(SymbolKind::Constant),
+            "module" | "mod_item" => Some(SymbolKind::Module),
             "enum_item" | "enum_declaration" => Some(SymbolKind::Enum),
             "trait_item" | "trait_definition" => Some(SymbolKind::Trait),
             _ => None,
@@ -185,7 +181,8 @@ impl ASTParser {
         }
     }
 
-    fn extract_imports_from_ast(&self, ast: &ASTNode, source: &str) -> Vec<ImportInfo> {
+    /// Imports in an already parsed AST
+    pub fn extract_imports_from_ast(&self, ast: &ASTNode, source: &str) -> Vec<ImportInfo> {
         let mut imports = Vec::new();
         self.traverse_for_imports(ast, source, &mut imports);
         imports
@@ -193,6 +190,7 @@ impl ASTParser {
 
     fn traverse_for_imports(&self, node: &ASTNode, source: &str, imports: &mut Vec<ImportInfo>) {
         if node.node_type == "import_statement" || 
+           node.node_type == "import_from_statement" ||
            node.node_type == "import_declaration" ||
            node.node_type == "use_declaration" {
             if let Some(value) = &node.value {
@@ -260,13 +258,13 @@ impl ASTParser {
     }
 
     fn get_parser(&mut self, language: &str) -> Result<&mut Parser, String> {
-        // In production, this would load the appropriate tree-sitter grammar
-        // For now, create a basic parser
         if !self.parsers.contains_key(language) {
-            let parser = Parser::new();
-            // Note: In production, you would set the language here:
-            // parser.set_language(language).map_err(|e| format!("Failed to set language: {}", e))?;
-            self.parsers.insert(language.to_string(), (unsafe { std::mem::zeroed() }, parser));
+            let grammar = grammar_for(language)
+                .ok_or_else(|| format!("Unsupported language: {}", language))?;
+            let mut parser = Parser::new();
+            parser.set_language(&grammar)
+                .map_err(|e| format!("Failed to load {} grammar: {}", language, e))?;
+            self.parsers.insert(language.to_string(), (grammar, parser));
         }
         
         self.parsers.get_mut(language)
@@ -275,6 +273,18 @@ impl ASTParser {
     }
 }
 
+/// Tree-sitter grammar for a language name or file extension
+fn grammar_for(language: &str) -> Option<Language> {
+    match language {
+        "rust" | "rs" => Some(tree_sitter_rust::language()),
+        "javascript" | "js" | "jsx" | "mjs" | "cjs" => Some(tree_sitter_javascript::language()),
+        "typescript" | "ts" => Some(tree_sitter_typescript::language_typescript()),
+        "tsx" => Some(tree_sitter_typescript::language_tsx()),
+        "python" | "py" => Some(tree_sitter_python::language()),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportInfo {
     pub path: String,
@@ -287,3 +297,36 @@ impl Default for ASTParser {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function_names(symbols: &[ParsedSymbol]) -> Vec<String> {
+        symbols.iter()
+            .filter(|s| s.kind == SymbolKind::Function)
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_parses_rust_and_python_functions() {
+        let mut parser = ASTParser::new();
+
+        let rust = "use std::fmt;\n\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn main() {\n    let x = add(1, 2);\n}\n";
+        let ast = parser.parse(rust, "rust").unwrap();
+        assert_eq!(ast.node_type, "source_file");
+        assert_eq!(function_names(&parser.extract_symbols(rust, "rust")), vec!["add", "main"]);
+        assert_eq!(parser.extract_imports(rust, "rust").len(), 1);
+
+        let python = "import os\nfrom typing import List\n\ndef greet(name):\n    return 'hi ' + name\n\nclass Greeter:\n    def wave(self):\n        pass\n";
+        let symbols = parser.extract_symbols(python, "python");
+        assert_eq!(function_names(&symbols), vec!["greet"]);
+        let class = symbols.iter().find(|s| s.kind == SymbolKind::Class).unwrap();
+        assert_eq!(class.name, "Greeter");
+        assert_eq!(function_names(&class.children), vec!["wave"]);
+        assert_eq!(parser.extract_imports(python, "python").len(), 2);
+
+        assert!(parser.parse("x = 1", "cobol").unwrap_err().contains("Unsupported language"));
+    }
+}
@@ -11,8 +11,15 @@
  */
 use serde::{Serialize, Deserialize};
 use crate::services::ai::router::ModelRouter;
+use super::ast_parser::ASTParser;
+use super::complexity;
+use super::diff::{self, DiffHunk};
+use futures::stream::{self, StreamExt};
 use std::sync::Arc;
 
+/// Files reviewed at once by `review_codebase`
+pub const REVIEW_CONCURRENCY: usize = 4;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeReviewIssue {
     pub severity: IssueSeverity,
@@ -52,11 +59,18 @@ pub struct CodeReviewResult {
     pub score: f64, // 0-100
     pub summary: String,
     pub metrics: CodeMetrics,
+    /// Model that produced the review
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeMetrics {
+    /// Average cyclomatic complexity per function
     pub complexity: f64,
+    /// Cyclomatic complexity of the most complex function
+    #[serde(default)]
+    pub max_complexity: f64,
     pub maintainability_index: f64,
     pub test_coverage: f64,
     pub documentation_coverage: f64,
@@ -65,23 +79,12 @@ pub struct CodeMetrics {
 
 pub struct CodeReviewer {
     router: Arc<ModelRouter>,
+    response_language: Option<String>,
+    model: Option<String>,
+    definitions: Option<String>, // Cross-file definitions appended to the prompt
 }
 
-impl CodeReviewer {
-    pub fn new(router: Arc<ModelRouter>) -> Self {
-        Self { router }
-    }
-    
-    /// Review code file
-    pub async fn review_code(
-        &self,
-        file_path: &str,
-        code: &str,
-        language: &str,
-    ) -> Result<CodeReviewResult, String> {
-        // Build review prompt
-        let prompt = format!(
-            r#"You are an expert code reviewer. Review the following {} code for:
+const REVIEW_CHECKLIST: &str = "\
 1. Security vulnerabilities (SQL injection, XSS, path traversal, etc.)
 2. Performance issues (inefficient algorithms, memory leaks, etc.)
 3. Code quality and best practices
@@ -89,17 +92,12 @@ impl CodeReviewer {
 5. Documentation completeness
 6. Test coverage concerns
 7. Maintainability issues
+";
 
-File: {}
-Code:
-```{}
-{}
-```
-
-Provide a JSON response with this structure:
-{{
+const REVIEW_RESPONSE_FORMAT: &str = r#"Provide a JSON response with this structure:
+{
   "issues": [
-    {{
+    {
       "severity": "Critical|High|Medium|Low|Info",
       "category": "Security|Performance|CodeQuality|BestPractices|Style|Documentation|Testing|Maintainability",
       "message": "Description of the issue",
@@ -107,134 +105,457 @@ Provide a JSON response with this structure:
       "column": 10,
       "suggestion": "How to fix it",
       "code_snippet": "The problematic code"
-    }}
+    }
   ],
   "score": 85.0,
   "summary": "Overall assessment",
-  "metrics": {{
+  "metrics": {
     "complexity": 5.0,
     "maintainability_index": 75.0,
     "test_coverage": 0.0,
     "documentation_coverage": 50.0,
     "security_score": 90.0
-  }}
-}}"#,
+  }
+}"#;
+
+/// Review with no findings that carries the model's text as its summary
+fn unstructured_review(content: &str) -> CodeReviewResult {
+    CodeReviewResult {
+        issues: vec![],
+        score: 75.0,
+        summary: content.to_string(),
+        metrics: CodeMetrics {
+            complexity: 0.0,
+            max_complexity: 0.0,
+            maintainability_index: 0.0,
+            test_coverage: 0.0,
+            documentation_coverage: 0.0,
+            security_score: 0.0,
+        },
+        model: None,
+    }
+}
+
+/// Parse the model's review, tolerating a fenced or prose-wrapped JSON object
+fn parse_review(content: &str) -> CodeReviewResult {
+    if let Ok(result) = serde_json::from_str::<CodeReviewResult>(content) {
+        return result;
+    }
+    
+    let json = content.find('{')
+        .and_then(|start| content.rfind('}').filter(|&end| end > start).map(|end| &content[start..=end]));
+    json.and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_else(|| unstructured_review(content))
+}
+
+/// Replace the model's complexity estimate with one measured from the AST
+///
+/// Left alone when the code doesn't parse or has no functions.
+fn apply_measured_complexity(metrics: &mut CodeMetrics, code: &str, language: &str) {
+    let Ok(ast) = ASTParser::new().parse(code, language) else {
+        return;
+    };
+    if let Some(summary) = complexity::summarize(&complexity::function_complexities(&ast)) {
+        metrics.complexity = summary.average;
+        metrics.max_complexity = summary.max;
+    }
+}
+
+impl CodeReviewer {
+    pub fn new(router: Arc<ModelRouter>) -> Self {
+        Self { router, response_language: None, model: None, definitions: None }
+    }
+
+    /// Preferred review model; ignored (router picks) if its provider isn't configured
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        self.model = model.filter(|m| !m.trim().is_empty());
+        self
+    }
+
+    /// Definitions the code uses from other files, rendered for the prompt
+    pub fn with_definitions(mut self, definitions: Option<String>) -> Self {
+        self.definitions = definitions;
+        self
+    }
+
+    /// Locale for review messages, suggestions and summary
+    pub fn with_response_language(mut self, response_language: Option<String>) -> Self {
+        self.response_language = response_language;
+        self
+    }
+    
+    /// Review code file
+    pub async fn review_code(
+        &self,
+        file_path: &str,
+        code: &str,
+        language: &str,
+    ) -> Result<CodeReviewResult, String> {
+        // Build review prompt
+        let prompt = format!(
+            r#"You are an expert code reviewer. Review the following {} code for:
+{}
+File: {}
+Code:
+```{}
+{}
+```
+{}
+{}"#,
             language,
+            REVIEW_CHECKLIST,
             file_path,
             language,
-            code
+            code,
+            self.definitions.as_deref().map(|d| format!("\n{}", d)).unwrap_or_default(),
+            REVIEW_RESPONSE_FORMAT
         );
         
+        let mut result = self.run_review(prompt).await?;
+        apply_measured_complexity(&mut result.metrics, code, language);
+        Ok(result)
+    }
+
+    /// Review only what changed between two versions of a file
+    ///
+    /// Issues the model reports outside the changed lines are dropped.
+    pub async fn review_diff(
+        &self,
+        file_path: &str,
+        old_code: &str,
+        new_code: &str,
+        language: &str,
+    ) -> Result<CodeReviewResult, String> {
+        let hunks = diff::diff_hunks(old_code, new_code, diff::DEFAULT_CONTEXT_LINES);
+        let mut result = self.review_hunks(file_path, &hunks, language).await?;
+        apply_measured_complexity(&mut result.metrics, new_code, language);
+        Ok(result)
+    }
+
+    /// Review the changed lines of `hunks`, e.g. from a unified diff
+    pub async fn review_hunks(
+        &self,
+        file_path: &str,
+        hunks: &[DiffHunk],
+        language: &str,
+    ) -> Result<CodeReviewResult, String> {
+        let ranges = diff::changed_ranges(hunks);
+        if ranges.is_empty() {
+            let mut result = unstructured_review("No changes to review");
+            result.score = 100.0;
+            return Ok(result);
+        }
+
+        // Prompt size follows the change, not the file
+        let mut rendered = String::new();
+        for hunk in hunks {
+            rendered.push_str("...\n");
+            for line in &hunk.lines {
+                let marker = if line.changed { '+' } else { ' ' };
+                rendered.push_str(&format!("{}{:>5} | {}\n", marker, line.number, line.text));
+            }
+        }
+        let prompt = format!(
+            r#"You are an expert code reviewer. Review a change to this {} file.
+Lines marked "+" were added or modified; the others are unchanged context.
+Only report issues on the changed lines, using the line numbers shown. Check for:
+{}
+File: {}
+Changed hunks:
+```{}
+{}```
+{}
+{}"#,
+            language,
+            REVIEW_CHECKLIST,
+            file_path,
+            language,
+            rendered,
+            self.definitions.as_deref().map(|d| format!("\n{}", d)).unwrap_or_default(),
+            REVIEW_RESPONSE_FORMAT
+        );
+
+        let mut result = self.run_review(prompt).await?;
+        result.issues.retain(|issue| ranges.iter().any(|range| range.contains(issue.line)));
+        Ok(result)
+    }
+
+    /// Send a review prompt, falling back across configured providers
+    async fn run_review(&self, prompt: String) -> Result<CodeReviewResult, String> {
         // Use AI router to get review
         use crate::types::{AIMessage, MessageRole, AIRequest};
-        use crate::services::ai::router::AIService;
         
         let messages = vec![AIMessage {
             role: MessageRole::User,
             content: prompt,
+            timestamp: Some(chrono::Utc::now()),
+            metadata: None,
         }];
         
-        let request = AIRequest {
+        let mut request = AIRequest {
             messages,
-            model: Some("claude-3-5-sonnet-20241022".to_string()), // Use Claude for reviews
+            model: self.model.clone(), // Configured preference, if any
             temperature: Some(0.3), // Lower temperature for consistent reviews
             max_tokens: Some(4000),
             stream: Some(false),
+            context: None,
+            context_overflow: None,
+            response_language: self.response_language.clone(),
+            sanitize_output: false,
+            json_output: false,
         };
+        crate::services::ai::localization::localize_request(&mut request);
         
-        // Select Claude for code review (best quality)
-        use crate::types::ModelProvider;
-        let service = self.router.get_service(ModelProvider::Anthropic)
-            .ok_or("Claude service not available")?;
+        // The prompt's security focus steers selection toward high-quality models
+        let selected = self.router.select_best_model(&request)
+            .map_err(|e| format!("No model available for review: {}", e))?;
+        let mut providers = vec![selected.provider.clone()];
+        for provider in self.router.configured_providers() {
+            if !providers.contains(&provider) && self.router.health().is_available(&provider) {
+                providers.push(provider);
+            }
+        }
         
-        match service.generate(request).await {
-            Ok(response) => {
-                // Parse JSON response
-                match serde_json::from_str::<CodeReviewResult>(&response.content) {
-                    Ok(result) => Ok(result),
-                    Err(_) => {
-                        // Fallback: try to extract JSON from markdown code blocks
-                        let json_start = response.content.find("```json").or_else(|| response.content.find("{"));
-                        let json_end = response.content.rfind("```").or_else(|| response.content.rfind("}"));
-                        
-                        if let (Some(start), Some(end)) = (json_start, json_end) {
-                            let json_str = &response.content[start..=end]
-                                .trim_start_matches("```json")
-                                .trim_start_matches("```")
-                                .trim_end_matches("```")
-                                .trim();
-                            
-                            serde_json::from_str(json_str)
-                                .unwrap_or_else(|_| CodeReviewResult {
-                                    issues: vec![],
-                                    score: 75.0,
-                                    summary: response.content,
-                                    metrics: CodeMetrics {
-                                        complexity: 0.0,
-                                        maintainability_index: 0.0,
-                                        test_coverage: 0.0,
-                                        documentation_coverage: 0.0,
-                                        security_score: 0.0,
-                                    },
-                                })
-                        } else {
-                            Ok(CodeReviewResult {
-                                issues: vec![],
-                                score: 75.0,
-                                summary: response.content,
-                                metrics: CodeMetrics {
-                                    complexity: 0.0,
-                                    maintainability_index: 0.0,
-                                    test_coverage: 0.0,
-                                    documentation_coverage: 0.0,
-                                    security_score: 0.0,
-                                },
-                            })
-                        }
-                    }
+        let mut last_error = None;
+        for (attempt, provider) in providers.into_iter().enumerate() {
+            let Some(service) = self.router.get_service(provider.clone()) else {
+                continue;
+            };
+            let mut attempt_request = request.clone();
+            attempt_request.model = if attempt == 0 { Some(selected.model.clone()) } else { None };
+            
+            match self.router.generate_with(&service, attempt_request).await {
+                Ok(response) => {
+                    let mut result = parse_review(&response.content);
+                    result.model = Some(response.model);
+                    return Ok(result);
+                }
+                Err(e) => {
+                    tracing::warn!("Review with {:?} failed: {}", provider, e);
+                    last_error = Some(e);
                 }
             }
-            Err(e) => Err(format!("AI review failed: {}", e)),
         }
+        
+        Err(match last_error {
+            Some(e) => format!("AI review failed: {}", e),
+            None => "No model available for review".to_string(),
+        })
     }
     
-    /// Review entire codebase
+    /// Review entire codebase, a few files at a time
     pub async fn review_codebase(
         &self,
         files: Vec<(String, String, String)>, // (path, content, language)
     ) -> Result<CodeReviewResult, String> {
+        let file_count = files.len();
+        let mut reviews = stream::iter(files)
+            .map(|(path, content, language)| async move {
+                let result = self.review_code(&path, &content, &language).await;
+                (path, result)
+            })
+            .buffer_unordered(REVIEW_CONCURRENCY);
+
         let mut all_issues = Vec::new();
         let mut total_score = 0.0;
-        
-        for (path, content, language) in files {
-            match self.review_code(&path, &content, &language).await {
+        let mut complexities = Vec::new();
+        let mut max_complexity: f64 = 0.0;
+        while let Some((path, result)) = reviews.next().await {
+            match result {
                 Ok(result) => {
-                    all_issues.extend(result.issues);
+                    all_issues.extend(result.issues.into_iter().map(|mut issue| {
+                        issue.file_path = path.clone();
+                        issue
+                    }));
                     total_score += result.score;
+                    if result.metrics.max_complexity > 0.0 {
+                        complexities.push(result.metrics.complexity);
+                        max_complexity = max_complexity.max(result.metrics.max_complexity);
+                    }
                 }
                 Err(e) => {
                     tracing::warn!("Failed to review {}: {}", path, e);
                 }
             }
         }
+        // Reviews finish in any order; keep the report stable
+        all_issues.sort_by(|a, b| a.file_path.cmp(&b.file_path).then(a.line.cmp(&b.line)));
         
-        let avg_score = if !files.is_empty() {
-            total_score / files.len() as f64
+        let avg_score = if file_count > 0 {
+            total_score / file_count as f64
         } else {
             0.0
         };
+        let avg_complexity = if complexities.is_empty() {
+            0.0
+        } else {
+            complexities.iter().sum::<f64>() / complexities.len() as f64
+        };
         
         Ok(CodeReviewResult {
+            summary: format!("Reviewed {} files, found {} issues", file_count, all_issues.len()),
             issues: all_issues,
             score: avg_score,
-            summary: format!("Reviewed {} files, found {} issues", files.len(), all_issues.len()),
             metrics: CodeMetrics {
-                complexity: 0.0,
+                complexity: avg_complexity,
+                max_complexity,
                 maintainability_index: 0.0,
                 test_coverage: 0.0,
                 documentation_coverage: 0.0,
                 security_score: 0.0,
             },
+            model: None,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::services::ai::adapter::ProviderAdapter;
+    use crate::services::ai::base::AIService;
+    use crate::types::{AIRequest, AIResponse, CostPer1kTokens, ModelCapabilities, ModelProvider, Quality, Speed};
+
+    /// DeepSeek stand-in that answers every review with one finding
+    struct FakeDeepSeek {
+        capabilities: ModelCapabilities,
+    }
+
+    #[async_trait::async_trait]
+    impl AIService for FakeDeepSeek {
+        fn name(&self) -> &str {
+            "fake-deepseek"
+        }
+
+        fn capabilities(&self) -> &ModelCapabilities {
+            &self.capabilities
+        }
+
+        async fn generate(&self, request: AIRequest) -> anyhow::Result<AIResponse> {
+            let model = request.model.unwrap_or_else(|| self.default_model().to_string());
+            self.parse_response(&serde_json::json!({}), &model)
+        }
+    }
+
+    impl ProviderAdapter for FakeDeepSeek {
+        fn provider(&self) -> ModelProvider {
+            ModelProvider::DeepSeek
+        }
+
+        fn display_name(&self) -> &str {
+            "Fake DeepSeek"
+        }
+
+        fn default_model(&self) -> &str {
+            "deepseek-chat"
+        }
+
+        fn endpoint(&self, _model: &str) -> String {
+            "http://localhost".to_string()
+        }
+
+        fn headers(&self, _api_key: &str) -> Vec<(&'static str, String)> {
+            Vec::new()
+        }
+
+        fn build_request(&self, _request: &AIRequest, _model: &str) -> serde_json::Value {
+            serde_json::json!({})
+        }
+
+        fn parse_response(&self, _body: &serde_json::Value, model: &str) -> anyhow::Result<AIResponse> {
+            let review = r#"Here is my review:
+```json
+{"issues": [{"severity": "High", "category": "Security", "message": "Query built from user input",
+  "file_path": "", "line": 2, "column": 5, "suggestion": "Use a bound parameter", "code_snippet": "execute"}],
+ "score": 60.0, "summary": "One injection risk",
+ "metrics": {"complexity": 1.0, "maintainability_index": 80.0, "test_coverage": 0.0,
+  "documentation_coverage": 0.0, "security_score": 40.0}}
+```"#;
+            Ok(AIResponse {
+                content: review.to_string(),
+                model: model.to_string(),
+                usage: None,
+                finish_reason: None,
+                metadata: None,
+            })
+        }
+
+        fn extract_usage(&self, _body: &serde_json::Value) -> Option<crate::types::TokenUsage> {
+            None
+        }
+    }
+
+    /// Router whose only provider is the fake DeepSeek
+    fn fake_router() -> ModelRouter {
+        let mut config = Config::from_env().unwrap();
+        config.anthropic_api_key.clear();
+        ModelRouter::new(&config).with_adapter(Arc::new(FakeDeepSeek {
+            capabilities: ModelCapabilities {
+                supports_vision: false,
+                supports_function_calling: false,
+                max_context_length: 64_000,
+                supports_streaming: true,
+                cost_per_1k_tokens: CostPer1kTokens { input: 0.0001, output: 0.0002 },
+                speed: Speed::Fast,
+                quality: Quality::High,
+            },
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_review_without_anthropic_uses_another_provider() {
+        let router = fake_router();
+        assert!(router.get_service(ModelProvider::Anthropic).is_none());
+
+        // A preference for an unconfigured provider falls back to router selection
+        let reviewer = CodeReviewer::new(Arc::new(router))
+            .with_model(Some("claude-3-5-sonnet-20241022".to_string()));
+        let review = reviewer
+            .review_code("src/db.py", "def find(name):\n    db.execute(\"SELECT \" + name)\n", "python")
+            .await
+            .unwrap();
+
+        assert_eq!(review.model.as_deref(), Some("deepseek-chat"));
+        assert_eq!(review.issues.len(), 1);
+        assert!(matches!(review.issues[0].category, IssueCategory::Security));
+        assert_eq!(review.score, 60.0);
+        // The model's answer has no max_complexity; it is measured from the code
+        assert_eq!(review.metrics.complexity, 1.0);
+        assert_eq!(review.metrics.max_complexity, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_diff_review_keeps_issues_on_changed_lines() {
+        let reviewer = CodeReviewer::new(Arc::new(fake_router()));
+        let old = "def find(name):\n    db.execute(\"SELECT * FROM users WHERE name = ?\", name)\n    return None\n";
+
+        // The fake flags line 2, which this change rewrites
+        let touched = "def find(name):\n    db.execute(\"SELECT \" + name)\n    return None\n";
+        let review = reviewer.review_diff("src/db.py", old, touched, "python").await.unwrap();
+        assert_eq!(review.issues.len(), 1);
+
+        // ...but not when only line 3 changed
+        let untouched = "def find(name):\n    db.execute(\"SELECT * FROM users WHERE name = ?\", name)\n    return []\n";
+        let review = reviewer.review_diff("src/db.py", old, untouched, "python").await.unwrap();
+        assert!(review.issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_codebase_review_aggregates_files() {
+        let reviewer = CodeReviewer::new(Arc::new(fake_router()));
+        let files = vec![
+            ("src/a.py".to_string(), "def a(x):\n    return x\n".to_string(), "python".to_string()),
+            ("src/b.py".to_string(), "def b(x):\n    if x:\n        return 1\n    return 0\n".to_string(), "python".to_string()),
+        ];
+        let review = reviewer.review_codebase(files).await.unwrap();
+
+        // The fake reports one issue per file
+        let paths: Vec<&str> = review.issues.iter().map(|i| i.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["src/a.py", "src/b.py"]);
+        assert_eq!(review.score, 60.0);
+        assert_eq!(review.summary, "Reviewed 2 files, found 2 issues");
+        assert_eq!(review.metrics.max_complexity, 2.0);
+        assert_eq!(review.metrics.complexity, 1.5);
+    }
+}
@@ -0,0 +1,148 @@
+/**
+ * Cyclomatic Complexity
+ *
+ * McCabe complexity per function, counted from the parsed AST: one for the
+ * function itself plus one for every branch, loop, handler, extra match arm
+ * and short-circuiting boolean operator. Nested functions are scored on
+ * their own rather than inflating the function that contains them.
+ */
+use serde::{Serialize, Deserialize};
+use super::ast_parser::{ASTNode, Location};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionComplexity {
+    pub name: String,
+    pub complexity: u32,
+    pub location: Location,
+}
+
+/// Max and mean over every function in a file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ComplexitySummary {
+    pub max: f64,
+    pub average: f64,
+}
+
+const FUNCTION_NODES: &[&str] = &[
+    "function_item", "function_declaration", "function_definition", "method_definition",
+    "function", "function_expression", "generator_function_declaration",
+];
+
+const DECISION_NODES: &[&str] = &[
+    "if_expression", "if_statement", "elif_clause", "if_clause",
+    "for_expression", "for_statement", "for_in_statement", "for_in_clause",
+    "while_expression", "while_statement", "do_statement",
+    "catch_clause", "except_clause", "switch_case", "case_clause",
+    "ternary_expression", "conditional_expression",
+];
+
+const SHORT_CIRCUIT_OPERATORS: &[&str] = &["&&", "||", "??", "and", "or"];
+
+/// Complexity of every function in `ast`, in source order
+pub fn function_complexities(ast: &ASTNode) -> Vec<FunctionComplexity> {
+    let mut functions = Vec::new();
+    collect_functions(ast, &mut functions);
+    functions
+}
+
+pub fn summarize(functions: &[FunctionComplexity]) -> Option<ComplexitySummary> {
+    if functions.is_empty() {
+        return None;
+    }
+    let total: u32 = functions.iter().map(|f| f.complexity).sum();
+    Some(ComplexitySummary {
+        max: functions.iter().map(|f| f.complexity).max().unwrap_or(0) as f64,
+        average: total as f64 / functions.len() as f64,
+    })
+}
+
+fn collect_functions(node: &ASTNode, functions: &mut Vec<FunctionComplexity>) {
+    if FUNCTION_NODES.contains(&node.node_type.as_str()) {
+        let name = node.children.iter()
+            .find(|c| matches!(c.node_type.as_str(), "identifier" | "property_identifier"))
+            .and_then(|c| c.value.clone())
+            .unwrap_or_else(|| "<anonymous>".to_string());
+        functions.push(FunctionComplexity {
+            name,
+            complexity: 1 + decision_points(node),
+            location: node.location.clone(),
+        });
+    }
+    for child in &node.children {
+        collect_functions(child, functions);
+    }
+}
+
+/// Decision points under `node`, stopping at nested functions
+fn decision_points(node: &ASTNode) -> u32 {
+    node.children.iter()
+        .filter(|child| !FUNCTION_NODES.contains(&child.node_type.as_str()))
+        .map(|child| own_decisions(child) + decision_points(child))
+        .sum()
+}
+
+fn own_decisions(node: &ASTNode) -> u32 {
+    let kind = node.node_type.as_str();
+    if DECISION_NODES.contains(&kind) {
+        return 1;
+    }
+    match kind {
+        // A match with n arms has n - 1 decisions
+        "match_block" => (node.children.iter().filter(|c| c.node_type == "match_arm").count() as u32).saturating_sub(1),
+        "binary_expression" | "boolean_operator" => node.children.iter()
+            .filter(|c| c.children.is_empty() && SHORT_CIRCUIT_OPERATORS.contains(&c.node_type.as_str()))
+            .count() as u32,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ast_parser::ASTParser;
+    use super::super::analyzer_profile::AnalyzerProfile;
+    use super::super::pattern_detector::PatternDetector;
+
+    #[test]
+    fn test_counts_decision_points_per_function() {
+        let rust = "\
+fn classify(values: &[i32], strict: bool) -> &'static str {
+    let mut label = \"none\";
+    for v in values {
+        if *v > 10 && strict {
+            label = match v {
+                11 => \"eleven\",
+                12 => \"twelve\",
+                _ => \"big\",
+            };
+        }
+    }
+    label
+}
+
+fn identity(x: i32) -> i32 {
+    x
+}
+";
+        let ast = ASTParser::new().parse(rust, "rust").unwrap();
+        let functions = function_complexities(&ast);
+        let scores: Vec<(&str, u32)> = functions.iter().map(|f| (f.name.as_str(), f.complexity)).collect();
+        // 1 + for + if + && + (3 arms - 1)
+        assert_eq!(scores, vec![("classify", 6), ("identity", 1)]);
+        assert_eq!(summarize(&functions), Some(ComplexitySummary { max: 6.0, average: 3.5 }));
+
+        let python = "def check(x):\n    if x and x > 1:\n        return 1\n    elif x < 0:\n        return -1\n    return 0\n";
+        let ast = ASTParser::new().parse(python, "python").unwrap();
+        assert_eq!(function_complexities(&ast)[0].complexity, 4);
+
+        // Functions past the profile's limit are reported as a smell
+        let ast = ASTParser::new().parse(rust, "rust").unwrap();
+        let strict = AnalyzerProfile { max_cyclomatic_complexity: 5, ..AnalyzerProfile::builtin("rust") };
+        let flagged: Vec<_> = PatternDetector::new().with_profile(strict).detect_patterns(&ast, rust)
+            .into_iter()
+            .filter(|p| p.name == "High Complexity")
+            .collect();
+        assert_eq!(flagged.len(), 1);
+        assert!(flagged[0].description.contains("classify"));
+    }
+}
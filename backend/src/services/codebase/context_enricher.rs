@@ -0,0 +1,211 @@
+/**
+ * Cross-File Context Enrichment
+ *
+ * Finds the types and functions a file uses from elsewhere in the project and
+ * pulls in their definitions, so a review or explanation sees the shapes the
+ * code depends on without every dependency being attached by hand. Snippets
+ * are added most-used first until the token budget runs out.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::services::ai::tokens::TokenCounter;
+use crate::types::FileContext;
+use super::reference_tracker::{ReferenceTracker, SymbolDefinition};
+
+/// Longest definition attached in full; longer ones are cut after this many lines
+const MAX_SNIPPET_LINES: u32 = 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachedDefinition {
+    pub symbol: String,
+    pub file_path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub tokens: u32,
+}
+
+/// Which definitions were attached, returned with the result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnrichmentReport {
+    pub attached: Vec<AttachedDefinition>,
+    /// Resolved definitions left out because they didn't fit the budget
+    pub skipped_for_budget: Vec<String>,
+    pub tokens_used: u32,
+}
+
+pub struct ContextEnricher {
+    tracker: Arc<ReferenceTracker>,
+    token_budget: u32,
+}
+
+fn same_file(a: &str, b: &str) -> bool {
+    a.trim_start_matches("./") == b.trim_start_matches("./")
+}
+
+impl ContextEnricher {
+    pub fn new(tracker: Arc<ReferenceTracker>, token_budget: u32) -> Self {
+        Self { tracker, token_budget }
+    }
+
+    /// Definitions from other files that `code` (the content of `file_path`) uses
+    pub async fn enrich(&self, file_path: &str, code: &str) -> (Vec<FileContext>, EnrichmentReport) {
+        let mut report = EnrichmentReport::default();
+        if self.token_budget == 0 {
+            return (Vec::new(), report);
+        }
+
+        // Symbols used by the code as given, plus any the index recorded for the file
+        let identifier = Regex::new(r"[A-Za-z_][A-Za-z0-9_]{2,}").unwrap();
+        let mut uses: HashMap<String, usize> = HashMap::new();
+        for word in identifier.find_iter(code) {
+            *uses.entry(word.as_str().to_string()).or_insert(0) += 1;
+        }
+        for reference in self.tracker.get_file_references(file_path).await {
+            *uses.entry(reference.to_symbol).or_insert(0) += 1;
+        }
+
+        let mut candidates: Vec<(usize, SymbolDefinition)> = Vec::new();
+        for (name, count) in uses {
+            if let Some(definition) = self.tracker.find_definition(&name).await {
+                if !same_file(&definition.file_path, file_path) {
+                    candidates.push((count, definition));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.symbol.name.cmp(&b.1.symbol.name)));
+
+        let mut sources: HashMap<String, Option<String>> = HashMap::new();
+        let mut files = Vec::new();
+        for (_, definition) in candidates {
+            let source = match sources.get(&definition.file_path) {
+                Some(source) => source.clone(),
+                None => {
+                    let source = tokio::fs::read_to_string(&definition.file_path).await.ok();
+                    sources.insert(definition.file_path.clone(), source.clone());
+                    source
+                }
+            };
+            let Some((start_line, end_line, content)) = snippet(&definition, source.as_deref()) else {
+                continue;
+            };
+            let tokens = TokenCounter::shared().count_text(&content);
+            if report.tokens_used + tokens > self.token_budget {
+                report.skipped_for_budget.push(definition.symbol.name.clone());
+                continue;
+            }
+            report.tokens_used += tokens;
+            report.attached.push(AttachedDefinition {
+                symbol: definition.symbol.name.clone(),
+                file_path: definition.file_path.clone(),
+                start_line,
+                end_line,
+                tokens,
+            });
+            files.push(FileContext {
+                language: std::path::Path::new(&definition.file_path)
+                    .extension()
+                    .map(|e| e.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path: definition.file_path,
+                content,
+                start_line: Some(start_line),
+                end_line: Some(end_line),
+            });
+        }
+
+        (files, report)
+    }
+}
+
+/// Source lines of a definition, or its signature when the file can't be read
+fn snippet(definition: &SymbolDefinition, source: Option<&str>) -> Option<(u32, u32, String)> {
+    let start = definition.location.start_line.max(1);
+    let end = definition.location.end_line.max(start).min(start + MAX_SNIPPET_LINES - 1);
+
+    if let Some(source) = source {
+        let lines: Vec<&str> = source.lines()
+            .skip(start as usize - 1)
+            .take((end - start + 1) as usize)
+            .collect();
+        if !lines.is_empty() {
+            return Some((start, start + lines.len() as u32 - 1, lines.join("\n")));
+        }
+    }
+    definition.symbol.signature.clone().map(|signature| (start, start, signature))
+}
+
+/// Prompt section listing attached definitions, or None if there are none
+pub fn render_definitions(files: &[FileContext]) -> Option<String> {
+    if files.is_empty() {
+        return None;
+    }
+    let mut section = String::from("Definitions used by this file from elsewhere in the project:\n");
+    for file in files {
+        section.push_str(&format!(
+            "\n{} (lines {}-{}):\n```{}\n{}\n```\n",
+            file.path,
+            file.start_line.unwrap_or(1),
+            file.end_line.unwrap_or(1),
+            file.language,
+            file.content,
+        ));
+    }
+    Some(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::codebase::ast_parser::Location;
+    use crate::services::codebase::indexer::{CodeSymbol, SymbolKind};
+
+    async fn define(tracker: &ReferenceTracker, name: &str, kind: SymbolKind, path: &str, lines: (u32, u32)) {
+        let symbol = CodeSymbol {
+            name: name.to_string(),
+            kind,
+            file_path: path.to_string(),
+            line: lines.0,
+            column: 0,
+            signature: None,
+            documentation: None,
+            references: Vec::new(),
+        };
+        let location = Location { start_line: lines.0, start_column: 0, end_line: lines.1, end_column: 1, start_byte: 0, end_byte: 0 };
+        tracker.register_definition(symbol, path.to_string(), location).await;
+    }
+
+    #[tokio::test]
+    async fn test_referenced_struct_definition_is_attached() {
+        let root = std::env::temp_dir().join(format!("bloop-enrich-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let models = root.join("models.rs").to_string_lossy().into_owned();
+        let handler = root.join("handler.rs").to_string_lossy().into_owned();
+        std::fs::write(&models, "use chrono::Utc;\n\npub struct Invoice {\n    pub id: u64,\n    pub total_cents: i64,\n}\n\npub struct Unused;\n").unwrap();
+
+        let tracker = Arc::new(ReferenceTracker::new());
+        define(&tracker, "Invoice", SymbolKind::Struct, &models, (3, 6)).await;
+        define(&tracker, "Unused", SymbolKind::Struct, &models, (8, 8)).await;
+        define(&tracker, "handle", SymbolKind::Function, &handler, (1, 3)).await;
+
+        let code = "fn handle(invoice: &Invoice) -> i64 {\n    invoice.total_cents\n}\n";
+        let (files, report) = ContextEnricher::new(Arc::clone(&tracker), 500).enrich(&handler, code).await;
+
+        // Only the used struct from another file is attached, with its full body
+        assert_eq!(report.attached.len(), 1);
+        assert_eq!(report.attached[0].symbol, "Invoice");
+        assert_eq!((report.attached[0].start_line, report.attached[0].end_line), (3, 6));
+        assert!(files[0].content.starts_with("pub struct Invoice {"));
+        assert!(files[0].content.contains("total_cents: i64"));
+        assert!(render_definitions(&files).unwrap().contains("pub struct Invoice"));
+
+        // Nothing is attached past the budget
+        let (files, report) = ContextEnricher::new(tracker, 5).enrich(&handler, code).await;
+        assert!(files.is_empty());
+        assert_eq!(report.skipped_for_budget, vec!["Invoice".to_string()]);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
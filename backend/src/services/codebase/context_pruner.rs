@@ -0,0 +1,206 @@
+/**
+ * Context Relevance Pruning
+ *
+ * Drops attached context that has nothing to do with the question. Symbols
+ * named in the query anchor the search; files defining or using them are kept,
+ * followed through the reference graph for a couple of hops. Everything else
+ * is dropped except for a small allowance of extra files.
+ */
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::types::CodebaseContext;
+use super::reference_tracker::ReferenceTracker;
+
+/// How far from an anchor file the reference graph is followed
+const MAX_HOPS: usize = 2;
+
+/// What pruning kept and dropped, returned alongside the response
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub anchor_symbols: Vec<String>,
+    pub kept_files: Vec<String>,
+    pub pruned_files: Vec<String>,
+    pub pruned_symbols: usize,
+    pub pruned_dependencies: usize,
+}
+
+pub struct ContextPruner {
+    tracker: Arc<ReferenceTracker>,
+    extra_files: usize,
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./")
+}
+
+impl ContextPruner {
+    pub fn new(tracker: Arc<ReferenceTracker>, extra_files: usize) -> Self {
+        Self { tracker, extra_files }
+    }
+
+    /// Prune `context` to what `query` is about
+    ///
+    /// Returns None and leaves the context untouched when the query names no
+    /// known symbol, since there is then nothing to judge relevance against.
+    pub async fn prune(&self, query: &str, context: &mut CodebaseContext) -> Option<PruneReport> {
+        let files = context.files.as_ref().filter(|f| !f.is_empty())?;
+
+        let identifier = Regex::new(r"[A-Za-z_][A-Za-z0-9_]{2,}").unwrap();
+        let words: HashSet<&str> = identifier.find_iter(query).map(|m| m.as_str()).collect();
+        let mut anchors: Vec<String> = Vec::new();
+        let mut relevant: HashSet<String> = HashSet::new();
+
+        for word in &words {
+            let mut known = false;
+            if let Some(definition) = self.tracker.find_definition(word).await {
+                relevant.insert(normalize(&definition.file_path).to_string());
+                known = true;
+            }
+            for symbol in context.symbols.iter().flatten().filter(|s| s.name == *word) {
+                relevant.insert(normalize(&symbol.file).to_string());
+                known = true;
+            }
+            if known {
+                for file in self.tracker.get_referencing_files(word).await {
+                    relevant.insert(normalize(&file).to_string());
+                }
+                anchors.push(word.to_string());
+            }
+        }
+        if anchors.is_empty() {
+            return None;
+        }
+        anchors.sort();
+
+        // Follow what the anchor files use
+        let mut queue: VecDeque<(String, usize)> = relevant.iter().map(|f| (f.clone(), 0)).collect();
+        while let Some((file, hops)) = queue.pop_front() {
+            if hops >= MAX_HOPS {
+                continue;
+            }
+            for reference in self.tracker.get_file_references(&file).await {
+                let target = if reference.to_file != "unknown" {
+                    Some(reference.to_file)
+                } else {
+                    self.tracker.find_definition(&reference.to_symbol).await.map(|d| d.file_path)
+                };
+                if let Some(target) = target {
+                    let target = normalize(&target).to_string();
+                    if relevant.insert(target.clone()) {
+                        queue.push_back((target, hops + 1));
+                    }
+                }
+            }
+        }
+
+        let mut extras_left = self.extra_files;
+        let mut kept = Vec::new();
+        let mut report = PruneReport { anchor_symbols: anchors, ..Default::default() };
+        for file in files {
+            let path = normalize(&file.path);
+            if relevant.contains(path) {
+                kept.push(file.clone());
+            } else if extras_left > 0 {
+                extras_left -= 1;
+                kept.push(file.clone());
+            } else {
+                report.pruned_files.push(file.path.clone());
+                continue;
+            }
+            report.kept_files.push(file.path.clone());
+        }
+
+        let kept_paths: HashSet<&str> = kept.iter().map(|f| normalize(&f.path)).collect();
+        if let Some(symbols) = context.symbols.as_mut() {
+            let before = symbols.len();
+            symbols.retain(|s| kept_paths.contains(normalize(&s.file)));
+            report.pruned_symbols = before - symbols.len();
+        }
+        if let Some(dependencies) = context.dependencies.as_mut() {
+            let before = dependencies.len();
+            dependencies.retain(|d| kept_paths.contains(normalize(&d.file)));
+            report.pruned_dependencies = before - dependencies.len();
+        }
+        context.files = Some(kept);
+
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::codebase::ast_parser::Location;
+    use crate::services::codebase::indexer::{CodeSymbol, SymbolKind};
+    use crate::services::codebase::reference_tracker::ReferenceType;
+    use crate::types::FileContext;
+
+    fn location() -> Location {
+        Location { start_line: 1, start_column: 0, end_line: 1, end_column: 10, start_byte: 0, end_byte: 10 }
+    }
+
+    fn symbol(name: &str, file: &str) -> CodeSymbol {
+        CodeSymbol {
+            name: name.to_string(),
+            kind: SymbolKind::Function,
+            file_path: file.to_string(),
+            line: 1,
+            column: 0,
+            signature: None,
+            documentation: None,
+            references: Vec::new(),
+        }
+    }
+
+    fn file(path: &str) -> FileContext {
+        FileContext {
+            path: path.to_string(),
+            content: "// ...".to_string(),
+            language: "rust".to_string(),
+            start_line: None,
+            end_line: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unrelated_files_are_pruned() {
+        let tracker = Arc::new(ReferenceTracker::new());
+        for (name, path) in [
+            ("validate_token", "src/auth.rs"),
+            ("DbPool", "src/db.rs"),
+            ("render_chart", "src/charts.rs"),
+            ("format_axis", "src/axis.rs"),
+        ] {
+            tracker.register_definition(symbol(name, path), path.to_string(), location()).await;
+        }
+        // handler -> validate_token (auth) -> DbPool (db); charts -> format_axis (axis)
+        for (from, to) in [
+            ("src/handler.rs", "validate_token"),
+            ("src/auth.rs", "DbPool"),
+            ("src/charts.rs", "format_axis"),
+        ] {
+            tracker.register_reference(from.to_string(), location(), to.to_string(), ReferenceType::Call, String::new()).await;
+        }
+
+        let mut context = CodebaseContext {
+            files: Some(["./src/handler.rs", "src/auth.rs", "src/db.rs", "src/charts.rs", "src/axis.rs"]
+                .into_iter().map(file).collect()),
+            ..CodebaseContext::default()
+        };
+
+        let pruner = ContextPruner::new(Arc::clone(&tracker), 0);
+        let report = pruner.prune("Why does validate_token reject fresh tokens?", &mut context).await.unwrap();
+
+        assert_eq!(report.anchor_symbols, vec!["validate_token".to_string()]);
+        assert_eq!(report.kept_files, vec!["./src/handler.rs", "src/auth.rs", "src/db.rs"]);
+        assert_eq!(report.pruned_files, vec!["src/charts.rs", "src/axis.rs"]);
+        assert_eq!(context.files.as_ref().unwrap().len(), 3);
+
+        // A query naming no known symbol leaves the context alone
+        assert!(pruner.prune("Summarize this project", &mut context).await.is_none());
+        assert_eq!(context.files.unwrap().len(), 3);
+    }
+}
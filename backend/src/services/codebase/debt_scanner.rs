@@ -0,0 +1,227 @@
+/**
+ * Technical Debt Scanner
+ *
+ * Extracts TODO/FIXME/HACK/XXX style markers from comments:
+ * - Uses comment nodes from the AST when available
+ * - Falls back to a string-aware lexical scan otherwise
+ * - Picks up `TODO(name):` style authors
+ */
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use super::ast_parser::ASTNode;
+
+pub const DEFAULT_DEBT_MARKERS: &[&str] = &["TODO", "FIXME", "HACK", "XXX"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebtMarker {
+    pub file_path: String,
+    pub line: u32, // 1-based
+    pub marker: String,
+    pub text: String,
+    pub author: Option<String>,
+}
+
+pub struct DebtScanner {
+    pattern: Option<Regex>,
+}
+
+impl DebtScanner {
+    pub fn new(markers: &[String]) -> Self {
+        let alternatives: Vec<String> = markers.iter()
+            .map(|m| m.trim())
+            .filter(|m| !m.is_empty())
+            .map(regex::escape)
+            .collect();
+
+        let pattern = if alternatives.is_empty() {
+            None
+        } else {
+            Regex::new(&format!(
+                r"\b({})\b(?:\(([^)]*)\))?:?\s*(.*)",
+                alternatives.join("|")
+            )).ok()
+        };
+
+        Self { pattern }
+    }
+
+    /// Find markers in the comments of a file
+    pub fn scan(&self, file_path: &str, code: &str, language: &str, ast: Option<&ASTNode>) -> Vec<DebtMarker> {
+        let Some(pattern) = &self.pattern else {
+            return Vec::new();
+        };
+
+        let mut comments = Vec::new();
+        if let Some(ast) = ast {
+            collect_comment_nodes(ast, &mut comments);
+        }
+        if comments.is_empty() {
+            comments = lexical_comments(code, language);
+        }
+
+        let mut markers = Vec::new();
+        for (line, text) in comments {
+            for captures in pattern.captures_iter(&text) {
+                let text = captures.get(3)
+                    .map(|m| m.as_str().trim().trim_end_matches("*/").trim().to_string())
+                    .unwrap_or_default();
+                markers.push(DebtMarker {
+                    file_path: file_path.to_string(),
+                    line,
+                    marker: captures[1].to_string(),
+                    text,
+                    author: captures.get(2)
+                        .map(|m| m.as_str().trim().to_string())
+                        .filter(|a| !a.is_empty()),
+                });
+            }
+        }
+
+        markers.sort_by_key(|m| m.line);
+        markers
+    }
+}
+
+/// Comment nodes from a tree-sitter AST, one entry per line
+fn collect_comment_nodes(node: &ASTNode, out: &mut Vec<(u32, String)>) {
+    if node.node_type.contains("comment") {
+        if let Some(value) = &node.value {
+            for (offset, line) in value.lines().enumerate() {
+                out.push((node.location.start_line + offset as u32, line.to_string()));
+            }
+        }
+        return;
+    }
+    for child in &node.children {
+        collect_comment_nodes(child, out);
+    }
+}
+
+/// Comment syntax per language: (line comment prefixes, block comment delimiters)
+fn comment_syntax(language: &str) -> (&'static [&'static str], Option<(&'static str, &'static str)>) {
+    match language.to_lowercase().as_str() {
+        "python" | "ruby" | "shell" | "bash" | "sh" | "yaml" | "toml" | "perl" | "r" => (&["#"], None),
+        "sql" | "lua" | "haskell" => (&["--"], None),
+        "php" => (&["//", "#"], Some(("/*", "*/"))),
+        "html" | "xml" => (&[], Some(("<!--", "-->"))),
+        _ => (&["//"], Some(("/*", "*/"))),
+    }
+}
+
+/// Quote characters that open string literals
+fn string_quotes(language: &str) -> &'static [char] {
+    match language.to_lowercase().as_str() {
+        // ' starts lifetimes in Rust; char literals can't hold a marker anyway
+        "rust" => &['"'],
+        "javascript" | "typescript" | "go" => &['"', '\'', '`'],
+        _ => &['"', '\''],
+    }
+}
+
+/// Comment text with line numbers, skipping anything inside string literals
+fn lexical_comments(code: &str, language: &str) -> Vec<(u32, String)> {
+    let (line_prefixes, block) = comment_syntax(language);
+    let quotes = string_quotes(language);
+    let chars: Vec<char> = code.chars().collect();
+    let starts_with = |i: usize, token: &str| {
+        token.chars().enumerate().all(|(k, c)| chars.get(i + k) == Some(&c))
+    };
+
+    let mut comments = Vec::new();
+    let mut line = 1u32;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+
+        // String literal: skip to the closing quote, honouring escapes
+        if quotes.contains(&c) {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&'\n') {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        if let Some(prefix) = line_prefixes.iter().find(|p| starts_with(i, p)) {
+            let start = i + prefix.chars().count();
+            let end = chars[start..].iter().position(|c| *c == '\n').map(|p| start + p).unwrap_or(chars.len());
+            comments.push((line, chars[start..end].iter().collect()));
+            i = end;
+            continue;
+        }
+
+        if let Some((open, close)) = block {
+            if starts_with(i, open) {
+                let mut j = i + open.chars().count();
+                let mut current = String::new();
+                while j < chars.len() && !starts_with(j, close) {
+                    if chars[j] == '\n' {
+                        comments.push((line, std::mem::take(&mut current)));
+                        line += 1;
+                    } else {
+                        current.push(chars[j]);
+                    }
+                    j += 1;
+                }
+                comments.push((line, current));
+                i = j + close.chars().count();
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    comments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanner() -> DebtScanner {
+        DebtScanner::new(&DEFAULT_DEBT_MARKERS.iter().map(|m| m.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_markers_in_strings_are_ignored() {
+        let code = r#"fn main() {
+    // TODO(alice): handle the empty case
+    let msg = "TODO: this is just text";
+    let other = "// FIXME not a comment either";
+    /* FIXME: leaks the handle
+       HACK around the borrow checker */
+    println!("{}", msg);
+}
+"#;
+        let markers = scanner().scan("src/main.rs", code, "rust", None);
+
+        let found: Vec<(u32, &str)> = markers.iter().map(|m| (m.line, m.marker.as_str())).collect();
+        assert_eq!(found, vec![(2, "TODO"), (5, "FIXME"), (6, "HACK")]);
+        assert_eq!(markers[0].author.as_deref(), Some("alice"));
+        assert_eq!(markers[0].text, "handle the empty case");
+    }
+
+    #[test]
+    fn test_marker_set_is_configurable() {
+        let scanner = DebtScanner::new(&["DEBT".to_string()]);
+        let markers = scanner.scan("app.py", "x = 1  # DEBT: remove after v2\n# TODO: ignored\n", "python", None);
+
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].marker, "DEBT");
+        assert_eq!(markers[0].text, "remove after v2");
+    }
+}
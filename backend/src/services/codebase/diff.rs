@@ -0,0 +1,246 @@
+/**
+ * Line Diffs
+ *
+ * Works out which lines of a file a change touched, either by comparing the
+ * before and after text or by reading a unified diff, and groups them into
+ * hunks with a little surrounding context for diff-scoped reviews.
+ */
+use serde::{Serialize, Deserialize};
+
+/// Unchanged lines shown around each change
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// Past this many line pairs the changed middle of a file is taken as rewritten
+/// rather than aligned line by line
+const MAX_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// Inclusive 1-based line range in the new version of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl LineRange {
+    pub fn contains(&self, line: u32) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub number: u32, // Line number in the new file
+    pub text: String,
+    pub changed: bool,
+}
+
+/// Run of new-file lines around one or more changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    pub lines: Vec<DiffLine>,
+}
+
+/// Changed line ranges of the new file
+pub fn changed_ranges(hunks: &[DiffHunk]) -> Vec<LineRange> {
+    let mut ranges: Vec<LineRange> = Vec::new();
+    for line in hunks.iter().flat_map(|h| &h.lines).filter(|l| l.changed) {
+        match ranges.last_mut() {
+            Some(range) if range.end + 1 >= line.number => range.end = range.end.max(line.number),
+            _ => ranges.push(LineRange { start: line.number, end: line.number }),
+        }
+    }
+    ranges
+}
+
+/// Hunks turning `old` into `new`
+///
+/// Lines only removed have no place in the new file; the line that now sits
+/// where they were is marked changed instead, so deletions still get reviewed.
+pub fn diff_hunks(old: &str, new: &str, context: usize) -> Vec<DiffHunk> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+    let suffix = old_lines[prefix..].iter().rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old_lines[prefix..old_lines.len() - suffix];
+    let new_middle = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut changed = vec![false; new_lines.len()];
+    for (i, is_changed) in changed_in_middle(old_middle, new_middle).into_iter().enumerate() {
+        changed[prefix + i] = is_changed;
+    }
+    // Pure deletion: flag the line after the gap (or before it, at the end of the file)
+    if new_middle.is_empty() && !old_middle.is_empty() && !new_lines.is_empty() {
+        changed[prefix.min(new_lines.len() - 1)] = true;
+    }
+
+    group_hunks(&new_lines, &changed, context)
+}
+
+/// Which lines of `new` are not part of a longest common subsequence with `old`
+fn changed_in_middle(old: &[&str], new: &[&str]) -> Vec<bool> {
+    if old.is_empty() || new.is_empty() || old.len() * new.len() > MAX_ALIGNMENT_CELLS {
+        return vec![true; new.len()];
+    }
+
+    // lcs[i][j] = LCS length of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lcs = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut changed = vec![true; new.len()];
+    let (mut i, mut j) = (0, 0);
+    let mut deleted = false;
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            // Lines removed just before an unchanged line are attributed to it
+            changed[j] = deleted;
+            deleted = false;
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            deleted = true;
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    changed
+}
+
+fn group_hunks(lines: &[&str], changed: &[bool], context: usize) -> Vec<DiffHunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<(usize, usize)> = None; // Line index range, inclusive
+    for index in (0..lines.len()).filter(|&i| changed[i]) {
+        let start = index.saturating_sub(context);
+        let end = (index + context).min(lines.len() - 1);
+        current = match current {
+            Some((s, e)) if start <= e + 1 => Some((s, end.max(e))),
+            Some(range) => {
+                hunks.push(hunk(lines, changed, range));
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+    if let Some(range) = current {
+        hunks.push(hunk(lines, changed, range));
+    }
+    hunks
+}
+
+fn hunk(lines: &[&str], changed: &[bool], (start, end): (usize, usize)) -> DiffHunk {
+    DiffHunk {
+        lines: (start..=end)
+            .map(|i| DiffLine { number: i as u32 + 1, text: lines[i].to_string(), changed: changed[i] })
+            .collect(),
+    }
+}
+
+/// Hunks of `file_path` in a unified diff
+///
+/// A diff covering several files is narrowed to the one whose `+++` path ends
+/// with `file_path`; a single-file diff is used whatever its header says.
+pub fn parse_unified_diff(diff: &str, file_path: &str) -> Result<Vec<DiffHunk>, String> {
+    let mut files: Vec<(String, Vec<DiffHunk>)> = Vec::new();
+    let mut next_line = 0u32;
+    let mut pending_deletion = false;
+
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            let path = path.split('\t').next().unwrap_or(path).trim();
+            files.push((path.trim_start_matches("b/").to_string(), Vec::new()));
+            continue;
+        }
+        if line.starts_with("--- ") || line.starts_with("diff ") || line.starts_with("index ") {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("@@ ") {
+            // @@ -old_start,old_len +new_start,new_len @@
+            let new_range = header.split_whitespace()
+                .find_map(|part| part.strip_prefix('+'))
+                .ok_or_else(|| format!("Malformed hunk header: {}", line))?;
+            next_line = new_range.split(',').next().unwrap_or("").parse()
+                .map_err(|_| format!("Malformed hunk header: {}", line))?;
+            if files.is_empty() {
+                files.push((file_path.to_string(), Vec::new()));
+            }
+            files.last_mut().unwrap().1.push(DiffHunk { lines: Vec::new() });
+            pending_deletion = false;
+            continue;
+        }
+        let Some(hunk) = files.last_mut().and_then(|(_, hunks)| hunks.last_mut()) else {
+            continue; // Preamble before the first hunk
+        };
+        match line.chars().next() {
+            Some('+') | Some(' ') | None => {
+                let added = line.starts_with('+');
+                hunk.lines.push(DiffLine {
+                    number: next_line,
+                    text: line.get(1..).unwrap_or("").to_string(),
+                    changed: added || pending_deletion,
+                });
+                pending_deletion = false;
+                next_line += 1;
+            }
+            Some('-') => pending_deletion = true,
+            _ => {} // "\ No newline at end of file"
+        }
+    }
+
+    if files.len() == 1 {
+        return Ok(files.pop().unwrap().1);
+    }
+    files.into_iter()
+        .find(|(path, _)| path.ends_with(file_path) || file_path.ends_with(path.as_str()))
+        .map(|(_, hunks)| hunks)
+        .ok_or_else(|| format!("Diff has no changes for {}", file_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_lines_from_pair_and_unified_diff() {
+        let old = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a);\n}\n";
+        let new = "fn main() {\n    let a = 1;\n    let b = 3;\n    let c = 4;\n    println!(\"{}\", a);\n}\n";
+        let hunks = diff_hunks(old, new, 1);
+        assert_eq!(changed_ranges(&hunks), vec![LineRange { start: 3, end: 4 }]);
+        assert_eq!(hunks[0].lines.first().unwrap().number, 2);
+        assert_eq!(hunks[0].lines.last().unwrap().number, 5);
+
+        // Removing a line flags the one that took its place
+        let removed = "fn main() {\n    let a = 1;\n    println!(\"{}\", a);\n}\n";
+        assert_eq!(changed_ranges(&diff_hunks(old, removed, 0)), vec![LineRange { start: 3, end: 3 }]);
+        assert!(diff_hunks(old, old, 3).is_empty());
+
+        let unified = "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,5 +1,6 @@
+ fn main() {
+     let a = 1;
+-    let b = 2;
++    let b = 3;
++    let c = 4;
+     println!(\"{}\", a);
+ }
+";
+        let hunks = parse_unified_diff(unified, "src/main.rs").unwrap();
+        assert_eq!(changed_ranges(&hunks), vec![LineRange { start: 3, end: 4 }]);
+        assert_eq!(hunks, diff_hunks(old, new, 2));
+    }
+}
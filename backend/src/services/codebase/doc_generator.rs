@@ -158,11 +158,18 @@ pub struct CodeExample {
 
 pub struct DocGenerator {
     router: Arc<ModelRouter>,
+    response_language: Option<String>,
 }
 
 impl DocGenerator {
     pub fn new(router: Arc<ModelRouter>) -> Self {
-        Self { router }
+        Self { router, response_language: None }
+    }
+
+    /// Write generated documentation in this locale
+    pub fn with_response_language(mut self, response_language: Option<String>) -> Self {
+        self.response_language = response_language;
+        self
     }
     
     /// Generate documentation for code
@@ -201,20 +208,26 @@ Generate:
             content: prompt,
         }];
         
-        let request = AIRequest {
+        let mut request = AIRequest {
             messages,
             model: None,
             temperature: Some(0.5),
             max_tokens: Some(4000),
             stream: Some(false),
+            context: None,
+            context_overflow: None,
+            response_language: self.response_language.clone(),
+            sanitize_output: false,
+            json_output: false,
         };
+        crate::services::ai::localization::localize_request(&mut request);
         
         // Use Claude for documentation (best quality)
         let service = self.router.get_service(ModelProvider::Anthropic)
             .or_else(|| self.router.get_service(ModelProvider::OpenAI))
             .ok_or("No AI service available")?;
         
-        match service.generate(request).await {
+        match self.router.generate_with(&service, request).await {
             Ok(response) => {
                 // Parse documentation from response
                 Ok(parse_documentation(&response.content, code, language))
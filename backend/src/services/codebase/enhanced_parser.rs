@@ -16,9 +16,16 @@ use tokio::sync::RwLock;
 use serde::{Serialize, Deserialize};
 use super::ast_parser::{ASTNode, ParsedSymbol, SymbolKind, Location};
 
+/// Parses kept by default before the least recently used are evicted
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 1000;
+/// Serialized size the cache may hold by default
+pub const DEFAULT_CACHE_MAX_BYTES: usize = 64 * 1024 * 1024;
+
 pub struct EnhancedParser {
     parsers: Arc<RwLock<HashMap<String, ParserState>>>,
-    cache: Arc<RwLock<HashMap<String, CachedParse>>>,
+    cache: Arc<RwLock<ParseCache>>,
+    max_entries: usize,
+    max_bytes: usize,
     supported_languages: Vec<String>,
 }
 
@@ -36,16 +43,59 @@ struct CachedParse {
     timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug)]
+struct CacheEntry {
+    parse: CachedParse,
+    size_bytes: usize, // Serialized size of `parse`
+    last_used: u64,    // Value of the cache's use counter at the last hit
+}
+
+#[derive(Debug, Default)]
+struct ParseCache {
+    entries: HashMap<String, CacheEntry>,
+    total_bytes: usize,
+    uses: u64, // Bumped on every insert and hit; orders entries by recency
+}
+
+impl ParseCache {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= entry.size_bytes;
+        }
+    }
+
+    /// Drop least recently used entries until both limits hold
+    fn evict_to(&mut self, max_entries: usize, max_bytes: usize) {
+        while self.entries.len() > max_entries || self.total_bytes > max_bytes {
+            let lru_key = self.entries.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match lru_key {
+                Some(key) => self.remove(&key),
+                None => break,
+            }
+        }
+    }
+}
+
 impl EnhancedParser {
     pub fn new() -> Self {
+        Self::with_cache_limits(DEFAULT_CACHE_MAX_ENTRIES, DEFAULT_CACHE_MAX_BYTES)
+    }
+
+    /// Parser whose cache holds at most `max_entries` parses and `max_bytes`
+    /// of serialized parse data, evicting the least recently used past either
+    pub fn with_cache_limits(max_entries: usize, max_bytes: usize) -> Self {
         Self {
             parsers: Arc::new(RwLock::new(HashMap::new())),
-            cache: Arc::new(RwLock::new(HashMap::new())),
-            supported_languages: vec![
+            cache: Arc::new(RwLock::new(ParseCache::default())),
+            max_entries,
+            max_bytes,
+            supported_languages: [
                 "rust", "javascript", "typescript", "python", "java", "go",
                 "cpp", "c", "csharp", "php", "ruby", "swift", "kotlin",
                 "scala", "haskell", "elixir", "clojure", "lua", "r", "sql"
-            ],
+            ].into_iter().map(String::from).collect(),
         }
     }
 
@@ -54,19 +104,23 @@ impl EnhancedParser {
         // Check cache first
         let cache_key = format!("{}:{}", file_path, self.hash_code(code));
         {
-            let cache = self.cache.read().await;
-            if let Some(cached) = cache.get(&cache_key) {
+            let mut cache = self.cache.write().await;
+            cache.uses += 1;
+            let uses = cache.uses;
+            if let Some(entry) = cache.entries.get_mut(&cache_key) {
                 // Check if cache is still valid (within 1 hour)
-                if cached.timestamp > chrono::Utc::now() - chrono::Duration::hours(1) {
+                if entry.parse.timestamp > chrono::Utc::now() - chrono::Duration::hours(1) {
+                    entry.last_used = uses;
                     return ParseResult {
-                        ast: cached.ast.clone(),
-                        symbols: cached.symbols.clone(),
+                        ast: entry.parse.ast.clone(),
+                        symbols: entry.parse.symbols.clone(),
                         imports: vec![],
                         errors: vec![],
                         parse_time_ms: 0,
                         cached: true,
                     };
                 }
+                cache.remove(&cache_key);
             }
         }
 
@@ -114,14 +168,22 @@ impl EnhancedParser {
         let parse_time = start_time.elapsed().as_millis() as u64;
 
         // Cache result
-        {
+        let parse = CachedParse {
+            ast: ast.clone(),
+            symbols: symbols.clone(),
+            hash: self.hash_code(code),
+            timestamp: chrono::Utc::now(),
+        };
+        let size_bytes = serde_json::to_vec(&parse).map(|bytes| bytes.len()).unwrap_or(0);
+        // A parse bigger than the whole budget would only evict everything else
+        if size_bytes <= self.max_bytes {
             let mut cache = self.cache.write().await;
-            cache.insert(cache_key, CachedParse {
-                ast: ast.clone(),
-                symbols: symbols.clone(),
-                hash: self.hash_code(code),
-                timestamp: chrono::Utc::now(),
-            });
+            cache.remove(&cache_key);
+            cache.uses += 1;
+            let last_used = cache.uses;
+            cache.entries.insert(cache_key, CacheEntry { parse, size_bytes, last_used });
+            cache.total_bytes += size_bytes;
+            cache.evict_to(self.max_entries, self.max_bytes);
         }
 
         ParseResult {
@@ -316,15 +378,16 @@ impl EnhancedParser {
     /// Clear cache
     pub async fn clear_cache(&self) {
         let mut cache = self.cache.write().await;
-        cache.clear();
+        cache.entries.clear();
+        cache.total_bytes = 0;
     }
 
     /// Get cache stats
     pub async fn cache_stats(&self) -> CacheStats {
         let cache = self.cache.read().await;
         CacheStats {
-            entries: cache.len(),
-            total_size_bytes: 0, // Would calculate actual size
+            entries: cache.entries.len(),
+            total_size_bytes: cache.total_bytes,
         }
     }
 }
@@ -350,3 +413,31 @@ impl Default for EnhancedParser {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cache_evicts_least_recently_used() {
+        let parser = EnhancedParser::with_cache_limits(2, DEFAULT_CACHE_MAX_BYTES);
+        parser.parse_enhanced("fn a() {}", "rust", "a.rs").await;
+        parser.parse_enhanced("fn b() {}", "rust", "b.rs").await;
+        // Touch a.rs so b.rs becomes the oldest
+        assert!(parser.parse_enhanced("fn a() {}", "rust", "a.rs").await.cached);
+
+        parser.parse_enhanced("fn c() {}", "rust", "c.rs").await;
+        let stats = parser.cache_stats().await;
+        assert_eq!(stats.entries, 2);
+        assert!(stats.total_size_bytes > 0);
+        assert!(parser.parse_enhanced("fn a() {}", "rust", "a.rs").await.cached);
+        assert!(!parser.parse_enhanced("fn b() {}", "rust", "b.rs").await.cached);
+
+        // The byte budget evicts as well: room for one parse only
+        let parser = EnhancedParser::with_cache_limits(100, stats.total_size_bytes * 3 / 4);
+        parser.parse_enhanced("fn a() {}", "rust", "a.rs").await;
+        parser.parse_enhanced("fn b() {}", "rust", "b.rs").await;
+        assert_eq!(parser.cache_stats().await.entries, 1);
+        assert!(!parser.parse_enhanced("fn a() {}", "rust", "a.rs").await.cached);
+    }
+}
@@ -0,0 +1,379 @@
+/**
+ * Error Explainer
+ *
+ * Turns a pasted stack trace into a root cause and a suggested fix:
+ * - Parses Rust, Python, JavaScript/TypeScript, Go and Java traces
+ * - Pulls the source around failing project frames (pasted files first, then the index)
+ * - Asks a reasoning model for the cause, a fix and a unified diff
+ */
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use crate::services::ai::router::ModelRouter;
+use super::indexer::{CodebaseIndexer, SymbolKind};
+
+/// Lines of source shown on each side of a failing line
+const CONTEXT_LINES: u32 = 8;
+
+/// Path fragments of frames in toolchains, dependencies and runtimes
+const EXTERNAL_PATH_MARKERS: &[&str] = &[
+    "/rustc/", "library/std/", "library/core/", "library/alloc/", ".cargo/registry", ".rustup/",
+    "node_modules", "node:internal", "site-packages", "dist-packages", "/lib/python",
+    "/usr/lib/", "/usr/local/go/", "<frozen", "<anonymous>",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackFrame {
+    pub function: Option<String>,
+    pub file_path: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub in_project: bool, // false for std/runtime/dependency frames
+}
+
+/// A file the client pasted alongside the trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextFile {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub file_path: String,
+    pub line: u32,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub snippet: String,
+    pub symbol: Option<String>, // Enclosing function/type when the file is indexed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorExplanation {
+    pub frames: Vec<StackFrame>,
+    pub locations: Vec<SourceLocation>,
+    pub root_cause: String,
+    pub suggested_fix: String,
+    pub diff: Option<String>,
+    pub model: String,
+}
+
+/// Parse a stack trace into frames, outermost panic/exception site first
+pub fn parse_stack_trace(trace: &str) -> Vec<StackFrame> {
+    let python = Regex::new(r#"File "(?P<file>[^"]+)", line (?P<line>\d+)(?:, in (?P<func>[^\s]+))?"#).unwrap();
+    let java = Regex::new(r"at (?P<func>[\w$.<>]+)\((?P<file>[\w$.-]+\.\w+):(?P<line>\d+)\)").unwrap();
+    let javascript = Regex::new(r"at (?:(?P<func>[^\s(]+) \()?(?P<file>[^\s()]+?):(?P<line>\d+):(?P<col>\d+)\)?").unwrap();
+    let generic = Regex::new(r"(?P<file>(?:[A-Za-z]:)?[\w./\\@-]*\.[A-Za-z]{1,5}):(?P<line>\d+)(?::(?P<col>\d+))?").unwrap();
+    // Rust backtraces ("  3: app::config::load") and Go goroutine dumps ("main.divide(...)")
+    // name the function on the line before its location
+    let function_line = Regex::new(r"^\s*(?:\d+:\s+(?P<rust>\S+)|(?P<go>[\w./*()]+\.\w+)\(.*\)\s*$)").unwrap();
+
+    let mut frames: Vec<StackFrame> = Vec::new();
+    let mut pending_function: Option<String> = None;
+
+    for line in trace.lines() {
+        let captures = python.captures(line)
+            .or_else(|| java.captures(line))
+            .or_else(|| javascript.captures(line))
+            .or_else(|| generic.captures(line));
+
+        let Some(captures) = captures else {
+            pending_function = function_line.captures(line)
+                .and_then(|c| c.name("rust").or_else(|| c.name("go")))
+                .map(|m| m.as_str().to_string());
+            continue;
+        };
+
+        let file_path = captures["file"].to_string();
+        let Ok(line_number) = captures["line"].parse::<u32>() else {
+            continue;
+        };
+        let function = captures.name("func")
+            .map(|m| m.as_str().to_string())
+            .or_else(|| pending_function.take());
+        pending_function = None;
+
+        // A Rust panic message and its backtrace both point at the panic site
+        let key = file_path.trim_start_matches("./").to_string();
+        if let Some(existing) = frames.iter_mut().find(|f| f.file_path.trim_start_matches("./") == key && f.line == line_number) {
+            if existing.function.is_none() {
+                existing.function = function;
+            }
+            continue;
+        }
+        frames.push(StackFrame {
+            in_project: is_project_path(&file_path),
+            function,
+            file_path,
+            line: line_number,
+            column: captures.name("col").and_then(|m| m.as_str().parse().ok()),
+        });
+    }
+
+    frames
+}
+
+fn is_project_path(path: &str) -> bool {
+    let normalized = path.replace('\\', "/");
+    !EXTERNAL_PATH_MARKERS.iter().any(|marker| normalized.contains(marker))
+}
+
+/// Source around `line` (1-based), with line numbers
+fn source_range(file_path: &str, content: &str, line: u32) -> Option<SourceLocation> {
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line as usize > lines.len() {
+        return None;
+    }
+
+    let start_line = line.saturating_sub(CONTEXT_LINES).max(1);
+    let end_line = (line + CONTEXT_LINES).min(lines.len() as u32);
+    let snippet = (start_line..=end_line)
+        .map(|n| format!("{:>5} | {}", n, lines[n as usize - 1]))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(SourceLocation {
+        file_path: file_path.to_string(),
+        line,
+        start_line,
+        end_line,
+        snippet,
+        symbol: None,
+    })
+}
+
+fn find_context_file<'a>(context_files: &'a [ContextFile], path: &str) -> Option<&'a ContextFile> {
+    let wanted = path.replace('\\', "/");
+    let wanted = wanted.trim_start_matches("./");
+    context_files.iter().find(|f| {
+        let pasted = f.path.trim_start_matches("./");
+        wanted == pasted || wanted.ends_with(&format!("/{}", pasted)) || pasted.ends_with(&format!("/{}", wanted))
+    })
+}
+
+/// Split the model's answer into root cause, fix and diff
+fn parse_explanation(content: &str) -> (String, String, Option<String>) {
+    let diff = content.find("```diff").and_then(|start| {
+        let body = &content[start + "```diff".len()..];
+        body.find("```").map(|end| body[..end].trim().to_string())
+    });
+    let prose = match content.find("```diff") {
+        Some(start) => &content[..start],
+        None => content,
+    };
+
+    let lower = prose.to_lowercase();
+    let fix_start = ["## suggested fix", "## fix"].iter().filter_map(|h| lower.find(h)).min();
+    let (cause, fix) = match fix_start {
+        Some(i) => (&prose[..i], &prose[i..]),
+        None => (prose, ""),
+    };
+    let strip_heading = |section: &str| {
+        section.trim()
+            .lines()
+            .skip_while(|l| l.trim_start().starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    };
+
+    (strip_heading(cause), strip_heading(fix), diff)
+}
+
+pub struct ErrorExplainer {
+    router: Arc<ModelRouter>,
+    indexer: Arc<CodebaseIndexer>,
+    model: String,
+    max_frames: usize,
+    response_language: Option<String>,
+}
+
+impl ErrorExplainer {
+    pub fn new(router: Arc<ModelRouter>, indexer: Arc<CodebaseIndexer>, model: String, max_frames: usize) -> Self {
+        Self { router, indexer, model, max_frames, response_language: None }
+    }
+
+    /// Explain the error in this locale
+    pub fn with_response_language(mut self, response_language: Option<String>) -> Self {
+        self.response_language = response_language;
+        self
+    }
+
+    /// Source ranges for the failing project frames
+    pub async fn locate_sources(&self, frames: &[StackFrame], context_files: &[ContextFile]) -> Vec<SourceLocation> {
+        let mut locations = Vec::new();
+
+        for frame in frames.iter().filter(|f| f.in_project).take(self.max_frames) {
+            if let Some(file) = find_context_file(context_files, &frame.file_path) {
+                locations.extend(source_range(&file.path, &file.content, frame.line));
+                continue;
+            }
+
+            // Only read files the indexer knows about, never arbitrary paths from the trace
+            let Some(indexed) = self.indexer.find_file(&frame.file_path).await else {
+                continue;
+            };
+            let Ok(content) = tokio::fs::read_to_string(&indexed.path).await else {
+                continue;
+            };
+            if let Some(mut location) = source_range(&indexed.path, &content, frame.line) {
+                location.symbol = indexed.symbols.iter()
+                    .filter(|s| matches!(s.kind, SymbolKind::Function | SymbolKind::Class | SymbolKind::Struct | SymbolKind::Interface))
+                    .filter(|s| s.line <= frame.line)
+                    .max_by_key(|s| s.line)
+                    .map(|s| s.name.clone());
+                locations.push(location);
+            }
+        }
+
+        locations
+    }
+
+    /// Locate the failing code and ask the model for a root cause and fix
+    pub async fn explain(
+        &self,
+        stack_trace: &str,
+        language: &str,
+        context_files: &[ContextFile],
+    ) -> Result<ErrorExplanation, String> {
+        let frames = parse_stack_trace(stack_trace);
+        let locations = self.locate_sources(&frames, context_files).await;
+
+        let sources = if locations.is_empty() {
+            "(source for the failing frames is not available)".to_string()
+        } else {
+            locations.iter()
+                .map(|l| format!("{}:{}{}\n```{}\n{}\n```",
+                    l.file_path,
+                    l.line,
+                    l.symbol.as_ref().map(|s| format!(" (in {})", s)).unwrap_or_default(),
+                    language,
+                    l.snippet))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
+
+        let prompt = format!(
+            r#"Explain the following {} error and how to fix it.
+
+Stack trace:
+```
+{}
+```
+
+Source at the failing frames:
+{}
+
+Answer with:
+## Root cause
+What went wrong and why, referring to the exact lines.
+
+## Suggested fix
+The change to make.
+
+Then a unified diff of the fix in a ```diff block, using the file paths above."#,
+            language,
+            stack_trace.trim(),
+            sources
+        );
+
+        use crate::types::{AIMessage, MessageRole, AIRequest};
+
+        let mut request = AIRequest {
+            messages: vec![AIMessage {
+                role: MessageRole::User,
+                content: prompt,
+                timestamp: None,
+                metadata: None,
+            }],
+            model: Some(self.model.clone()),
+            temperature: Some(0.2),
+            max_tokens: Some(3000),
+            stream: Some(false),
+            context: None,
+            context_overflow: None,
+            response_language: self.response_language.clone(),
+            sanitize_output: false,
+            json_output: false,
+        };
+        crate::services::ai::localization::localize_request(&mut request);
+
+        let model_info = self.router.select_best_model(&request).map_err(|e| e.to_string())?;
+        let service = self.router.get_service(model_info.provider)
+            .ok_or("No AI service available")?;
+
+        let response = self.router.generate_with(&service, request)
+            .await
+            .map_err(|e| format!("Error explanation failed: {}", e))?;
+        let (root_cause, suggested_fix, diff) = parse_explanation(&response.content);
+
+        Ok(ErrorExplanation {
+            frames,
+            locations,
+            root_cause,
+            suggested_fix,
+            diff,
+            model: response.model,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_PANIC: &str = r#"thread 'main' panicked at src/config.rs:42:27:
+called `Option::unwrap()` on a `None` value
+stack backtrace:
+   0: rust_begin_unwind
+             at /rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/panicking.rs:645:5
+   1: core::panicking::panic
+             at /rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/core/src/panicking.rs:144:5
+   2: bloop_backend::config::load_port
+             at ./src/config.rs:42:27
+   3: bloop_backend::main
+             at ./src/main.rs:12:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace."#;
+
+    #[test]
+    fn test_rust_panic_frames() {
+        let frames = parse_stack_trace(RUST_PANIC);
+        let project: Vec<(&str, u32, Option<&str>)> = frames.iter()
+            .filter(|f| f.in_project)
+            .map(|f| (f.file_path.as_str(), f.line, f.function.as_deref()))
+            .collect();
+
+        // The panic message and backtrace frame for the panic site are merged
+        assert_eq!(project, vec![
+            ("src/config.rs", 42, Some("bloop_backend::config::load_port")),
+            ("./src/main.rs", 12, Some("bloop_backend::main")),
+        ]);
+        assert!(frames.iter().any(|f| !f.in_project && f.function.as_deref() == Some("core::panicking::panic")));
+        assert_eq!(frames[0].column, Some(27));
+    }
+
+    #[tokio::test]
+    async fn test_locates_source_from_pasted_files() {
+        let router = Arc::new(ModelRouter::new(&crate::config::Config::from_env().unwrap()));
+        let explainer = ErrorExplainer::new(router, Arc::new(CodebaseIndexer::new()), "deepseek-reasoner".to_string(), 5);
+
+        let content: String = (1..=60).map(|n| format!("line {}\n", n)).collect();
+        let files = vec![ContextFile { path: "src/config.rs".to_string(), content }];
+        let locations = explainer.locate_sources(&parse_stack_trace(RUST_PANIC), &files).await;
+
+        assert_eq!(locations.len(), 1);
+        assert_eq!((locations[0].start_line, locations[0].end_line), (34, 50));
+        assert!(locations[0].snippet.contains("   42 | line 42"));
+    }
+
+    #[test]
+    fn test_explanation_sections() {
+        let content = "## Root cause\nThe PORT lookup returns None.\n\n## Suggested fix\nFall back to a default.\n\n```diff\n-    let port = lookup().unwrap();\n+    let port = lookup().unwrap_or(3001);\n```";
+        let (cause, fix, diff) = parse_explanation(content);
+
+        assert_eq!(cause, "The PORT lookup returns None.");
+        assert_eq!(fix, "Fall back to a default.");
+        assert!(diff.unwrap().starts_with("-    let port"));
+    }
+}
@@ -0,0 +1,286 @@
+/**
+ * Change Impact Analyzer
+ *
+ * Answers "what does this proposed change touch?" before it is merged:
+ * - Diffs the proposed symbols against the indexed version of the file
+ * - Walks the reverse dependency graph for direct and transitive dependents
+ * - Uses the reference tracker to find files using changed symbols
+ * - Flags test files that cover the affected code
+ */
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use super::ast_parser::{ASTParser, ParsedSymbol};
+use super::indexer::{CodeSymbol, CodebaseIndexer};
+
+/// Maximum depth followed through the reverse dependency graph
+const MAX_IMPACT_DEPTH: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolChange {
+    pub name: String,
+    pub change: SymbolChangeKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactedFile {
+    pub file_path: String,
+    pub depth: usize, // 1 = direct dependent
+    pub via: Vec<String>, // Changed symbols or intermediate files that lead here
+    pub is_test: bool,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeImpactReport {
+    pub file_path: String,
+    pub indexed: bool,
+    pub symbol_changes: Vec<SymbolChange>,
+    pub impacted: Vec<ImpactedFile>,
+    pub covering_tests: Vec<String>,
+}
+
+pub struct ImpactAnalyzer {
+    indexer: Arc<CodebaseIndexer>,
+}
+
+impl ImpactAnalyzer {
+    pub fn new(indexer: Arc<CodebaseIndexer>) -> Self {
+        Self { indexer }
+    }
+
+    /// Analyze the impact of replacing `file_path` with `new_content`
+    pub async fn analyze(&self, file_path: &str, new_content: &str, language: Option<&str>) -> ChangeImpactReport {
+        let existing = self.indexer.get_file_index(file_path).await;
+        let language = language
+            .map(|l| l.to_string())
+            .or_else(|| existing.as_ref().map(|f| f.language.clone()))
+            .unwrap_or_default();
+
+        // Re-extract symbols from the proposed content
+        let mut parser = ASTParser::new();
+        let proposed = parser.extract_symbols(new_content, &language);
+        let previous = existing.as_ref().map(|f| f.symbols.clone()).unwrap_or_default();
+        let symbol_changes = Self::diff_symbols(&previous, &proposed);
+
+        // Files using each changed symbol (only removals/modifications can break callers)
+        let tracker = self.indexer.reference_tracker();
+        let mut symbol_users: HashMap<String, HashSet<String>> = HashMap::new();
+        for change in symbol_changes.iter().filter(|c| c.change != SymbolChangeKind::Added) {
+            let mut users = tracker.get_referencing_files(&change.name).await;
+            for symbol in self.indexer.find_symbol(&change.name).await {
+                users.extend(symbol.references.into_iter().map(|r| r.file_path));
+            }
+            symbol_users.insert(change.name.clone(), users);
+        }
+
+        let dependency_map = self.indexer.dependency_snapshot().await;
+        let impacted = Self::rank_impact(file_path, &dependency_map, &symbol_users);
+        let covering_tests = impacted.iter()
+            .filter(|f| f.is_test)
+            .map(|f| f.file_path.clone())
+            .collect();
+
+        ChangeImpactReport {
+            file_path: file_path.to_string(),
+            indexed: existing.is_some(),
+            symbol_changes,
+            impacted,
+            covering_tests,
+        }
+    }
+
+    /// Compare indexed symbols with freshly parsed ones
+    pub fn diff_symbols(previous: &[CodeSymbol], proposed: &[ParsedSymbol]) -> Vec<SymbolChange> {
+        let old: HashMap<&str, &Option<String>> = previous.iter()
+            .map(|s| (s.name.as_str(), &s.signature))
+            .collect();
+        let new: HashMap<&str, &Option<String>> = proposed.iter()
+            .map(|s| (s.name.as_str(), &s.signature))
+            .collect();
+
+        let mut changes = Vec::new();
+        for (name, signature) in &old {
+            match new.get(name) {
+                None => changes.push(SymbolChange { name: name.to_string(), change: SymbolChangeKind::Removed }),
+                Some(new_signature) if new_signature != signature => {
+                    changes.push(SymbolChange { name: name.to_string(), change: SymbolChangeKind::Modified })
+                }
+                _ => {}
+            }
+        }
+        for name in new.keys().filter(|name| !old.contains_key(*name)) {
+            changes.push(SymbolChange { name: name.to_string(), change: SymbolChangeKind::Added });
+        }
+
+        changes.sort_by(|a, b| a.name.cmp(&b.name));
+        changes
+    }
+
+    /// Rank files affected by a change to `changed_file`
+    ///
+    /// `dependency_map` is file -> imports; `symbol_users` is changed symbol -> files using it.
+    pub fn rank_impact(
+        changed_file: &str,
+        dependency_map: &HashMap<String, Vec<String>>,
+        symbol_users: &HashMap<String, HashSet<String>>,
+    ) -> Vec<ImpactedFile> {
+        let mut impacted: HashMap<String, ImpactedFile> = HashMap::new();
+
+        // Symbol usages are always direct impact
+        for (symbol, users) in symbol_users {
+            for user in users.iter().filter(|u| u.as_str() != changed_file) {
+                let entry = impacted.entry(user.clone()).or_insert_with(|| ImpactedFile {
+                    file_path: user.clone(),
+                    depth: 1,
+                    via: vec![],
+                    is_test: is_test_file(user),
+                    score: 0.0,
+                });
+                entry.depth = 1;
+                entry.via.push(symbol.clone());
+            }
+        }
+
+        // Breadth-first walk over reverse dependencies
+        let mut visited: HashSet<String> = HashSet::from([changed_file.to_string()]);
+        let mut queue = VecDeque::from([(changed_file.to_string(), 0usize)]);
+        while let Some((current, depth)) = queue.pop_front() {
+            if depth >= MAX_IMPACT_DEPTH {
+                continue;
+            }
+            for (file, imports) in dependency_map {
+                if visited.contains(file) || !imports.iter().any(|i| import_refers_to(i, &current)) {
+                    continue;
+                }
+                visited.insert(file.clone());
+                let entry = impacted.entry(file.clone()).or_insert_with(|| ImpactedFile {
+                    file_path: file.clone(),
+                    depth: depth + 1,
+                    via: vec![],
+                    is_test: is_test_file(file),
+                    score: 0.0,
+                });
+                entry.depth = entry.depth.min(depth + 1);
+                if current != changed_file {
+                    entry.via.push(current.clone());
+                }
+                queue.push_back((file.clone(), depth + 1));
+            }
+        }
+
+        let mut ranked: Vec<ImpactedFile> = impacted.into_values()
+            .map(|mut f| {
+                f.via.sort();
+                f.via.dedup();
+                // Closer files and files using more changed symbols rank higher
+                let symbol_hits = f.via.iter().filter(|v| symbol_users.contains_key(*v)).count();
+                f.score = 1.0 / f.depth as f64 + 0.5 * symbol_hits as f64;
+                f
+            })
+            .collect();
+
+        ranked.sort_by(|a, b| {
+            b.score.partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.file_path.cmp(&b.file_path))
+        });
+        ranked
+    }
+}
+
+/// Whether an import string refers to the given file
+fn import_refers_to(import: &str, file_path: &str) -> bool {
+    if import == file_path {
+        return true;
+    }
+
+    let mut segments = file_path.rsplit('/');
+    let file_name = segments.next().unwrap_or(file_path);
+    let mut stem = file_name.split('.').next().unwrap_or(file_name);
+    // mod.rs / index.ts are imported by their directory name
+    if stem == "mod" || stem == "index" || stem == "__init__" {
+        stem = segments.next().unwrap_or(stem);
+    }
+    if stem.is_empty() {
+        return false;
+    }
+
+    import
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .any(|token| token == stem)
+}
+
+/// Heuristic test-file detection based on common naming conventions
+fn is_test_file(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+
+    lower.contains("/tests/")
+        || lower.starts_with("tests/")
+        || lower.contains("/test/")
+        || lower.contains("__tests__")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+        || file_name.contains("_test.")
+        || file_name.starts_with("test_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> HashMap<String, Vec<String>> {
+        // core.ts <- service.ts <- api.ts <- tests/api.test.ts
+        HashMap::from([
+            ("src/core.ts".to_string(), vec![]),
+            ("src/service.ts".to_string(), vec!["./core".to_string()]),
+            ("src/api.ts".to_string(), vec!["./service".to_string()]),
+            ("tests/api.test.ts".to_string(), vec!["../src/api".to_string()]),
+            ("src/unrelated.ts".to_string(), vec!["lodash".to_string()]),
+        ])
+    }
+
+    #[test]
+    fn test_transitive_dependents_are_ranked_by_distance() {
+        let impacted = ImpactAnalyzer::rank_impact("src/core.ts", &chain(), &HashMap::new());
+        let paths: Vec<&str> = impacted.iter().map(|f| f.file_path.as_str()).collect();
+
+        assert_eq!(paths, vec!["src/service.ts", "src/api.ts", "tests/api.test.ts"]);
+        assert_eq!(impacted[0].depth, 1);
+        assert_eq!(impacted[1].depth, 2);
+        assert_eq!(impacted[1].via, vec!["src/service.ts".to_string()]);
+        assert!(impacted[2].is_test);
+    }
+
+    #[test]
+    fn test_symbol_users_are_direct_impact() {
+        let symbol_users = HashMap::from([(
+            "parseConfig".to_string(),
+            HashSet::from(["src/api.ts".to_string(), "src/core.ts".to_string()]),
+        )]);
+        let impacted = ImpactAnalyzer::rank_impact("src/core.ts", &chain(), &symbol_users);
+
+        // api.ts uses the changed symbol directly, so it outranks the import-only dependent
+        assert_eq!(impacted[0].file_path, "src/api.ts");
+        assert_eq!(impacted[0].depth, 1);
+        assert!(impacted.iter().all(|f| f.file_path != "src/core.ts"));
+    }
+
+    #[test]
+    fn test_import_matching() {
+        assert!(import_refers_to("./core", "src/core.ts"));
+        assert!(import_refers_to("use crate::services::agent", "src/services/agent/mod.rs"));
+        assert!(!import_refers_to("./core-utils", "src/core.ts"));
+        assert!(is_test_file("src/__tests__/core.ts"));
+        assert!(!is_test_file("src/contest.ts"));
+    }
+}
@@ -14,6 +14,8 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Serialize, Deserialize};
 use chrono::Utc;
+use super::reference_tracker::ReferenceTracker;
+use super::debt_scanner::{DebtMarker, DebtScanner, DEFAULT_DEBT_MARKERS};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeSymbol {
@@ -59,12 +61,16 @@ pub struct FileIndex {
     pub dependencies: Vec<String>,
     pub last_modified: chrono::DateTime<Utc>,
     pub content_hash: String,
+    #[serde(default)]
+    pub debt_markers: Vec<DebtMarker>,
 }
 
 pub struct CodebaseIndexer {
     files: Arc<RwLock<HashMap<String, FileIndex>>>,
     symbols: Arc<RwLock<HashMap<String, Vec<CodeSymbol>>>>, // name -> symbols
     file_dependencies: Arc<RwLock<HashMap<String, Vec<String>>>>, // file -> dependencies
+    reference_tracker: Arc<ReferenceTracker>,
+    debt_scanner: Arc<DebtScanner>,
 }
 
 impl CodebaseIndexer {
@@ -73,19 +79,27 @@ impl CodebaseIndexer {
             files: Arc::new(RwLock::new(HashMap::new())),
             symbols: Arc::new(RwLock::new(HashMap::new())),
             file_dependencies: Arc::new(RwLock::new(HashMap::new())),
+            reference_tracker: Arc::new(ReferenceTracker::new()),
+            debt_scanner: Arc::new(DebtScanner::new(
+                &DEFAULT_DEBT_MARKERS.iter().map(|m| m.to_string()).collect::<Vec<_>>(),
+            )),
         }
     }
+
+    /// Use a custom set of technical-debt markers (e.g. TODO, FIXME)
+    pub fn with_debt_markers(mut self, markers: &[String]) -> Self {
+        self.debt_scanner = Arc::new(DebtScanner::new(markers));
+        self
+    }
     
     /// Index a file with full code intelligence
     pub async fn index_file(&self, path: String, content: String, language: String) {
         use super::ast_parser::ASTParser;
         use super::symbol_extractor::SymbolExtractor;
-        use super::reference_tracker::ReferenceTracker;
         use super::dependency_analyzer::DependencyAnalyzer;
-        use std::sync::Arc;
         
-        // Create parser and extractor
-        let reference_tracker = Arc::new(ReferenceTracker::new());
+        // Create parser and extractor (references go to the shared tracker)
+        let reference_tracker = Arc::clone(&self.reference_tracker);
         let mut parser = ASTParser::new();
         let mut extractor = SymbolExtractor::new(Arc::clone(&reference_tracker));
         
@@ -98,6 +112,9 @@ impl CodebaseIndexer {
             }
         };
         
+        // The file's old definitions and references are replaced, not added to
+        reference_tracker.remove_file(&path).await;
+
         // Extract symbols
         let symbols = extractor.extract(&content, &language, &path).await;
         
@@ -113,6 +130,9 @@ impl CodebaseIndexer {
             .map(|s| s.name.clone())
             .collect();
         
+        // Collect TODO/FIXME style markers from comments
+        let debt_markers = self.debt_scanner.scan(&path, &content, &language, Some(&ast));
+        
         // Calculate content hash
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
@@ -129,6 +149,7 @@ impl CodebaseIndexer {
             dependencies,
             last_modified: Utc::now(),
             content_hash,
+            debt_markers,
         };
         
         // Store in index
@@ -148,6 +169,62 @@ impl CodebaseIndexer {
         deps_map.insert(path, imports);
     }
     
+    /// Technical-debt markers across all indexed files
+    pub async fn debt_markers(&self) -> Vec<DebtMarker> {
+        let files = self.files.read().await;
+        files.values()
+            .flat_map(|f| f.debt_markers.iter().cloned())
+            .collect()
+    }
+    
+    /// Path and language of every indexed file
+    pub async fn indexed_files(&self) -> Vec<(String, String)> {
+        let files = self.files.read().await;
+        files.values()
+            .map(|f| (f.path.clone(), f.language.clone()))
+            .collect()
+    }
+    
+    /// Get the indexed version of a file
+    pub async fn get_file_index(&self, path: &str) -> Option<FileIndex> {
+        let files = self.files.read().await;
+        files.get(path).cloned()
+    }
+    
+    /// Find the indexed file a path from elsewhere refers to
+    ///
+    /// Stack traces use absolute or `./`-prefixed paths, so fall back to the
+    /// longest indexed path that matches on a path-component boundary.
+    pub async fn find_file(&self, path: &str) -> Option<FileIndex> {
+        let files = self.files.read().await;
+        if let Some(file) = files.get(path) {
+            return Some(file.clone());
+        }
+        
+        let wanted = path.replace('\\', "/");
+        let wanted = wanted.trim_start_matches("./");
+        files.values()
+            .filter(|f| {
+                let indexed = f.path.trim_start_matches("./");
+                wanted == indexed
+                    || wanted.ends_with(&format!("/{}", indexed))
+                    || indexed.ends_with(&format!("/{}", wanted))
+            })
+            .max_by_key(|f| f.path.len())
+            .cloned()
+    }
+    
+    /// Snapshot of file -> dependencies for graph walks
+    pub async fn dependency_snapshot(&self) -> HashMap<String, Vec<String>> {
+        let deps = self.file_dependencies.read().await;
+        deps.clone()
+    }
+    
+    /// Shared cross-file reference tracker
+    pub fn reference_tracker(&self) -> Arc<ReferenceTracker> {
+        Arc::clone(&self.reference_tracker)
+    }
+    
     /// Find symbol by name
     pub async fn find_symbol(&self, name: &str) -> Vec<CodeSymbol> {
         let symbols = self.symbols.read().await;
@@ -189,3 +266,21 @@ impl Default for CodebaseIndexer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reindexing_replaces_a_files_references() {
+        let indexer = CodebaseIndexer::new();
+        let path = "src/lib.rs".to_string();
+        indexer.index_file(path.clone(), "use std::fmt;\nuse std::io;\nfn render() {}\n".to_string(), "rust".to_string()).await;
+        indexer.index_file(path.clone(), "use std::fmt;\nfn render() {}\n".to_string(), "rust".to_string()).await;
+
+        let tracker = indexer.reference_tracker();
+        let references = tracker.get_file_references(&path).await;
+        assert_eq!(references.len(), 1, "{:?}", references);
+        assert!(tracker.find_usages("use std::io;").await.is_empty());
+    }
+}
@@ -26,12 +26,21 @@ pub mod pattern_detector;
 pub mod reference_tracker;
 pub mod enhanced_parser;
 pub mod performance;
+pub mod impact_analyzer;
+pub mod debt_scanner;
+pub mod error_explainer;
+pub mod context_pruner;
+pub mod context_enricher;
+pub mod analyzer_profile;
+pub mod unified_search;
+pub mod complexity;
+pub mod diff;
 
 pub use indexer::CodebaseIndexer;
 pub use ast_parser::{ASTParser, ParsedSymbol, SymbolKind};
 pub use symbol_extractor::SymbolExtractor;
 pub use dependency_analyzer::DependencyAnalyzer;
-pub use semantic_search::SemanticSearch;
+pub use semantic_search::{SemanticSearch, Embedder, EmbeddingIndex, EmbeddingCacheStats};
 pub use code_reviewer::CodeReviewer;
 pub use test_generator::TestGenerator;
 pub use doc_generator::DocGenerator;
@@ -39,3 +48,10 @@ pub use performance_analyzer::PerformanceAnalyzer;
 pub use refactoring_suggestions::RefactoringSuggestions;
 pub use pattern_detector::{PatternDetector, DetectedPattern, PatternType, PatternSeverity};
 pub use reference_tracker::ReferenceTracker;
+pub use impact_analyzer::{ImpactAnalyzer, ChangeImpactReport};
+pub use debt_scanner::{DebtScanner, DebtMarker};
+pub use error_explainer::{ErrorExplainer, ErrorExplanation, ContextFile};
+pub use context_pruner::{ContextPruner, PruneReport};
+pub use context_enricher::{ContextEnricher, EnrichmentReport};
+pub use analyzer_profile::{AnalyzerProfile, AnalyzerProfiles};
+pub use unified_search::{UnifiedSearch, SearchHit, SearchKind};
@@ -3,9 +3,16 @@
  * 
  * Detects common code patterns, anti-patterns, and design patterns
  */
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 use serde::{Serialize, Deserialize};
-use super::ast_parser::{ASTNode, ParsedSymbol};
+use regex::Regex;
+use super::ast_parser::{ASTNode, ASTParser, ParsedSymbol};
+use super::analyzer_profile::{normalize_language, AnalyzerProfile};
+use super::complexity::function_complexities;
+
+/// Detections below this confidence are dropped unless a caller overrides it
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DetectedPattern {
@@ -16,6 +23,9 @@ pub struct DetectedPattern {
     pub confidence: f64,
     pub severity: PatternSeverity,
     pub suggestion: Option<String>,
+    /// Other places the same finding occurs, e.g. the rest of a clone group
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_locations: Vec<super::ast_parser::Location>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -36,15 +46,45 @@ pub enum PatternSeverity {
     Critical,
 }
 
-pub struct PatternDetector;
+pub struct PatternDetector {
+    min_confidence: f64,
+    profile: Option<AnalyzerProfile>, // None = built-in profile for the AST's language
+}
 
 impl PatternDetector {
     pub fn new() -> Self {
-        Self
+        Self {
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+            profile: None,
+        }
+    }
+
+    pub fn with_min_confidence(min_confidence: f64) -> Self {
+        Self {
+            min_confidence: min_confidence.clamp(0.0, 1.0),
+            profile: None,
+        }
+    }
+
+    /// Use these thresholds instead of the built-in profile for the code's language
+    pub fn with_profile(mut self, profile: AnalyzerProfile) -> Self {
+        self.profile = Some(profile);
+        self
     }
 
-    /// Detect patterns in AST
+    fn profile_for(&self, ast: &ASTNode) -> AnalyzerProfile {
+        self.profile.unwrap_or_else(|| AnalyzerProfile::builtin(&ast.language))
+    }
+
+    /// Detect patterns in AST, dropping detections below the minimum confidence
     pub fn detect_patterns(&self, ast: &ASTNode, code: &str) -> Vec<DetectedPattern> {
+        self.detect_all_patterns(ast, code)
+            .into_iter()
+            .filter(|p| p.confidence >= self.min_confidence)
+            .collect()
+    }
+
+    fn detect_all_patterns(&self, ast: &ASTNode, code: &str) -> Vec<DetectedPattern> {
         let mut patterns = Vec::new();
         
         // Detect various patterns
@@ -57,6 +97,7 @@ impl PatternDetector {
         patterns.extend(self.detect_unused_imports(ast, code));
         patterns.extend(self.detect_magic_numbers(ast, code));
         patterns.extend(self.detect_deep_nesting(ast, code));
+        patterns.extend(self.detect_complex_functions(ast));
         patterns.extend(self.detect_security_issues(ast, code));
         
         patterns
@@ -75,6 +116,7 @@ impl PatternDetector {
                 confidence: 0.8,
                 severity: PatternSeverity::Info,
                 suggestion: Some("Consider if singleton is necessary. Consider dependency injection instead.".to_string()),
+                related_locations: Vec::new(),
             });
         }
         
@@ -84,18 +126,35 @@ impl PatternDetector {
     fn detect_factory_pattern(&self, ast: &ASTNode, code: &str) -> Vec<DetectedPattern> {
         let mut patterns = Vec::new();
         
-        // Look for factory methods
-        if code.contains("create") || code.contains("Factory") {
-            patterns.push(DetectedPattern {
-                pattern_type: PatternType::DesignPattern,
-                name: "Factory Pattern".to_string(),
-                description: "Possible factory pattern detected".to_string(),
-                location: ast.location.clone(),
-                confidence: 0.6,
-                severity: PatternSeverity::Info,
-                suggestion: None,
-            });
-        }
+        // A bare "create"/"Factory" substring is weak evidence; require a factory type
+        // or a create/make/build function that actually constructs something
+        static FACTORY_TYPE: OnceLock<Regex> = OnceLock::new();
+        static CREATOR_FN: OnceLock<Regex> = OnceLock::new();
+        let factory_type = FACTORY_TYPE.get_or_init(|| {
+            Regex::new(r"(?:class|struct|interface|trait|impl)\s+\w*Factory\b").unwrap()
+        });
+        let creator_fn = CREATOR_FN.get_or_init(|| {
+            Regex::new(r"(?:fn|function|def|public|private|protected|static)\s+(?:create|make|build)[A-Z_]\w*\s*\(").unwrap()
+        });
+        let constructs = code.contains("new ") || code.contains("::new(") || code.contains("return ");
+
+        let confidence = match (factory_type.is_match(code), creator_fn.is_match(code) && constructs) {
+            (true, true) => 0.85,
+            (true, false) | (false, true) => 0.65,
+            _ if code.contains("create") || code.contains("Factory") => 0.3,
+            _ => return patterns,
+        };
+
+        patterns.push(DetectedPattern {
+            pattern_type: PatternType::DesignPattern,
+            name: "Factory Pattern".to_string(),
+            description: "Possible factory pattern detected".to_string(),
+            location: ast.location.clone(),
+            confidence,
+            severity: PatternSeverity::Info,
+            suggestion: None,
+            related_locations: Vec::new(),
+        });
         
         patterns
     }
@@ -103,18 +162,41 @@ impl PatternDetector {
     fn detect_observer_pattern(&self, ast: &ASTNode, code: &str) -> Vec<DetectedPattern> {
         let mut patterns = Vec::new();
         
-        // Look for observer/subscriber patterns
-        if code.contains("subscribe") || code.contains("notify") || code.contains("Observer") {
-            patterns.push(DetectedPattern {
-                pattern_type: PatternType::DesignPattern,
-                name: "Observer Pattern".to_string(),
-                description: "Possible observer pattern detected".to_string(),
-                location: ast.location.clone(),
-                confidence: 0.6,
-                severity: PatternSeverity::Info,
-                suggestion: None,
-            });
-        }
+        // Observers need both a registration and a notification side
+        static REGISTERS: OnceLock<Regex> = OnceLock::new();
+        static NOTIFIES: OnceLock<Regex> = OnceLock::new();
+        static HOLDS_LISTENERS: OnceLock<Regex> = OnceLock::new();
+        let registers = REGISTERS.get_or_init(|| {
+            Regex::new(r"(?i)\b(?:subscribe|add_?listener|add_?observer|attach|on)\s*\(").unwrap()
+        });
+        let notifies = NOTIFIES.get_or_init(|| {
+            Regex::new(r"(?i)\b(?:notify\w*|emit|publish|dispatch)\s*\(").unwrap()
+        });
+        let holds_listeners = HOLDS_LISTENERS.get_or_init(|| {
+            Regex::new(r"(?i)(?:listeners|observers|subscribers|handlers)\b").unwrap()
+        });
+
+        let evidence = [registers.is_match(code), notifies.is_match(code), holds_listeners.is_match(code)]
+            .iter()
+            .filter(|e| **e)
+            .count();
+        let confidence = match evidence {
+            3 => 0.85,
+            2 => 0.65,
+            _ if code.contains("subscribe") || code.contains("notify") || code.contains("Observer") => 0.3,
+            _ => return patterns,
+        };
+
+        patterns.push(DetectedPattern {
+            pattern_type: PatternType::DesignPattern,
+            name: "Observer Pattern".to_string(),
+            description: "Possible observer pattern detected".to_string(),
+            location: ast.location.clone(),
+            confidence,
+            severity: PatternSeverity::Info,
+            suggestion: None,
+            related_locations: Vec::new(),
+        });
         
         patterns
     }
@@ -124,7 +206,7 @@ impl PatternDetector {
         
         // Detect classes with too many methods/properties
         let method_count = self.count_methods(ast);
-        if method_count > 20 {
+        if method_count > self.profile_for(ast).max_methods_per_type {
             patterns.push(DetectedPattern {
                 pattern_type: PatternType::AntiPattern,
                 name: "God Object".to_string(),
@@ -133,6 +215,7 @@ impl PatternDetector {
                 confidence: 0.7,
                 severity: PatternSeverity::Warning,
                 suggestion: Some("Split this class into smaller, focused classes".to_string()),
+                related_locations: Vec::new(),
             });
         }
         
@@ -144,7 +227,7 @@ impl PatternDetector {
         
         // Detect methods with too many lines
         let line_count = ast.location.end_line - ast.location.start_line;
-        if line_count > 50 {
+        if line_count > self.profile_for(ast).long_method_lines {
             patterns.push(DetectedPattern {
                 pattern_type: PatternType::CodeSmell,
                 name: "Long Method".to_string(),
@@ -153,6 +236,7 @@ impl PatternDetector {
                 confidence: 0.8,
                 severity: PatternSeverity::Warning,
                 suggestion: Some("Extract smaller methods from this long method".to_string()),
+                related_locations: Vec::new(),
             });
         }
         
@@ -161,18 +245,116 @@ impl PatternDetector {
 
     fn detect_duplicate_code(&self, ast: &ASTNode, code: &str) -> Vec<DetectedPattern> {
         let mut patterns = Vec::new();
-        
-        // Simple duplicate detection (in production, use more sophisticated algorithms)
-        // This is a placeholder for actual duplicate detection
+        let profile = self.profile_for(ast);
+        let window = profile.min_duplicate_statements.max(1);
+        let min_similarity = profile.duplicate_similarity_percent as f64 / 100.0;
+        let started = std::time::Instant::now();
+
+        let statements = normalized_statements(code);
+        if statements.len() < window * 2 {
+            return patterns;
+        }
+
+        // Rolling hash over each run of `window` statements; equal hashes are clone candidates
+        const BASE: u64 = 1_000_003;
+        let high = (1..window).fold(1u64, |acc, _| acc.wrapping_mul(BASE));
+        let mut windows: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut hash = statements[..window].iter().fold(0u64, |acc, s| acc.wrapping_mul(BASE).wrapping_add(s.hash));
+        windows.entry(hash).or_default().push(0);
+        for start in 1..=statements.len() - window {
+            hash = hash.wrapping_sub(statements[start - 1].hash.wrapping_mul(high))
+                .wrapping_mul(BASE)
+                .wrapping_add(statements[start + window - 1].hash);
+            windows.entry(hash).or_default().push(start);
+        }
+
+        let mut starts: Vec<&Vec<usize>> = windows.values().filter(|s| s.len() > 1).collect();
+        starts.sort_by_key(|s| s[0]);
+        let mut reported: Vec<(usize, usize, usize)> = Vec::new(); // (offset, first start, first end)
+        'candidates: for group in starts {
+            // Large groups are boilerplate (getters, match arms) rather than copy-paste
+            for (i, &a) in group.iter().enumerate().take(MAX_CLONES_PER_GROUP) {
+                for &b in group[i + 1..].iter().take(MAX_CLONES_PER_GROUP) {
+                    if started.elapsed() > DUPLICATE_TIME_BUDGET {
+                        tracing::debug!("Duplicate detection stopped at its time budget");
+                        break 'candidates;
+                    }
+                    let offset = b - a;
+                    if offset < window
+                        || reported.iter().any(|&(o, start, end)| o == offset && (start..end).contains(&a))
+                        || statements[a..a + window].iter().zip(&statements[b..b + window]).any(|(x, y)| x.hash != y.hash)
+                    {
+                        continue;
+                    }
+
+                    // Grow the clone while enough statements still line up
+                    let (mut len, mut matched, mut best) = (window, window, (window, window));
+                    while a + len < b && b + len < statements.len() {
+                        if statements[a + len].hash == statements[b + len].hash {
+                            matched += 1;
+                        }
+                        len += 1;
+                        if (matched as f64) < min_similarity * len as f64 {
+                            break;
+                        }
+                        if statements[a + len - 1].hash == statements[b + len - 1].hash {
+                            best = (len, matched);
+                        }
+                    }
+                    let (len, matched) = best;
+                    let similarity = matched as f64 / len as f64;
+                    reported.push((offset, a, a + len));
+
+                    let first = statements_location(code, &statements[a..a + len]);
+                    let second = statements_location(code, &statements[b..b + len]);
+                    patterns.push(DetectedPattern {
+                        pattern_type: PatternType::CodeSmell,
+                        name: "Duplicate Code".to_string(),
+                        description: format!(
+                            "Lines {}-{} and {}-{} repeat {} statements ({:.0}% alike)",
+                            first.start_line, first.end_line, second.start_line, second.end_line,
+                            len, similarity * 100.0
+                        ),
+                        location: first,
+                        confidence: 0.6 + 0.3 * similarity,
+                        severity: PatternSeverity::Warning,
+                        suggestion: Some("Extract the repeated block into a shared function".to_string()),
+                        related_locations: vec![second],
+                    });
+                }
+            }
+        }
         
         patterns
     }
 
     fn detect_unused_imports(&self, ast: &ASTNode, code: &str) -> Vec<DetectedPattern> {
         let mut patterns = Vec::new();
-        
-        // Detect unused imports (would need symbol usage tracking)
-        // Placeholder
+        let language = normalize_language(&ast.language);
+        // Names listed in __all__ are re-exported without being referenced
+        if language == "python" && code.contains("__all__") {
+            return patterns;
+        }
+
+        let mut used = HashSet::new();
+        collect_identifiers(ast, &mut used);
+        for import in ASTParser::new().extract_imports_from_ast(ast, code) {
+            let Some(names) = imported_names(&import.path, &language) else {
+                continue;
+            };
+            for name in names.into_iter().filter(|name| !used.contains(name.as_str())) {
+                patterns.push(DetectedPattern {
+                    pattern_type: PatternType::CodeSmell,
+                    name: "Unused Import".to_string(),
+                    description: format!("`{}` is imported but never used", name),
+                    location: import.location.clone(),
+                    confidence: 0.8,
+                    severity: PatternSeverity::Info,
+                    suggestion: Some(format!("Remove the unused import `{}`", name)),
+                    related_locations: Vec::new(),
+                });
+            }
+        }
         
         patterns
     }
@@ -190,7 +372,7 @@ impl PatternDetector {
         let mut patterns = Vec::new();
         
         let max_depth = self.max_nesting_depth(ast, 0);
-        if max_depth > 4 {
+        if max_depth > self.profile_for(ast).max_nesting_depth {
             patterns.push(DetectedPattern {
                 pattern_type: PatternType::CodeSmell,
                 name: "Deep Nesting".to_string(),
@@ -199,12 +381,31 @@ impl PatternDetector {
                 confidence: 0.7,
                 severity: PatternSeverity::Warning,
                 suggestion: Some("Extract nested blocks into separate methods".to_string()),
+                related_locations: Vec::new(),
             });
         }
         
         patterns
     }
 
+    fn detect_complex_functions(&self, ast: &ASTNode) -> Vec<DetectedPattern> {
+        let threshold = self.profile_for(ast).max_cyclomatic_complexity;
+        function_complexities(ast)
+            .into_iter()
+            .filter(|f| f.complexity > threshold)
+            .map(|f| DetectedPattern {
+                pattern_type: PatternType::CodeSmell,
+                name: "High Complexity".to_string(),
+                description: format!("Function `{}` has cyclomatic complexity {} (limit {})", f.name, f.complexity, threshold),
+                location: f.location,
+                confidence: 0.9,
+                severity: PatternSeverity::Warning,
+                suggestion: Some("Split the function or replace branching with lookups and early returns".to_string()),
+                related_locations: Vec::new(),
+            })
+            .collect()
+    }
+
     fn detect_security_issues(&self, ast: &ASTNode, code: &str) -> Vec<DetectedPattern> {
         let mut patterns = Vec::new();
         
@@ -227,6 +428,7 @@ impl PatternDetector {
                     confidence: 0.5,
                     severity: PatternSeverity::Warning,
                     suggestion: Some("Review this code for security vulnerabilities".to_string()),
+                    related_locations: Vec::new(),
                 });
             }
         }
@@ -270,8 +472,348 @@ impl PatternDetector {
     }
 }
 
+const IMPORT_NODES: &[&str] = &["import_statement", "import_from_statement", "import_declaration", "use_declaration"];
+
+/// Identifiers referenced anywhere outside import statements
+fn collect_identifiers<'a>(node: &'a ASTNode, used: &mut HashSet<&'a str>) {
+    if IMPORT_NODES.contains(&node.node_type.as_str()) {
+        return;
+    }
+    if node.children.is_empty() && node.node_type.ends_with("identifier") {
+        if let Some(value) = &node.value {
+            used.insert(value.as_str());
+        }
+    }
+    for child in &node.children {
+        collect_identifiers(child, used);
+    }
+}
+
+/// Names an import statement binds in the file
+///
+/// None for statements that can't be judged from this file alone: re-exports,
+/// and languages without a parser here. Glob imports bind nothing checkable
+/// and are left out.
+fn imported_names(statement: &str, language: &str) -> Option<Vec<String>> {
+    let statement = statement.trim().trim_end_matches(';').trim();
+    let mut names = Vec::new();
+    match language {
+        "rust" => {
+            let tree = statement.strip_prefix("use ")?; // `pub use` re-exports
+            rust_use_names(tree, &mut names);
+        }
+        "javascript" | "typescript" => {
+            let clause = statement.strip_prefix("import")?.trim_start();
+            let clause = clause.strip_prefix("type ").unwrap_or(clause);
+            // `import "./polyfill"` is kept for its side effects
+            let (clause, _) = clause.rsplit_once(" from ")?;
+            let (default, named) = match clause.split_once('{') {
+                Some((default, named)) => (default, named.trim_end().trim_end_matches('}')),
+                None => (clause, ""),
+            };
+            let default = default.trim().trim_end_matches(',').trim();
+            if !default.is_empty() && !default.starts_with('*') {
+                names.push(default.to_string());
+            }
+            for specifier in named.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                let specifier = specifier.strip_prefix("type ").unwrap_or(specifier);
+                names.push(alias_or_name(specifier, |name| name));
+            }
+        }
+        "python" => {
+            if let Some(modules) = statement.strip_prefix("import ") {
+                for module in modules.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+                    // `import os.path` binds `os`
+                    names.push(alias_or_name(module, |name| name.split('.').next().unwrap_or(name)));
+                }
+            } else {
+                let (_, imported) = statement.strip_prefix("from ")?.split_once(" import ")?;
+                let imported = imported.trim().trim_start_matches('(').trim_end_matches(')');
+                for item in imported.split(',').map(str::trim).filter(|i| !i.is_empty() && *i != "*") {
+                    names.push(alias_or_name(item, |name| name));
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(names)
+}
+
+/// `a as b` binds `b`; otherwise `bound` picks the name from `a`
+fn alias_or_name(item: &str, bound: impl Fn(&str) -> &str) -> String {
+    match item.split_once(" as ") {
+        Some((_, alias)) => alias.trim().to_string(),
+        None => bound(item.trim()).to_string(),
+    }
+}
+
+/// Bound names of a Rust use tree such as `std::{fmt, io::{self, Read as R}}`
+fn rust_use_names(tree: &str, names: &mut Vec<String>) {
+    let tree = tree.trim();
+    if let (Some(open), true) = (tree.find('{'), tree.ends_with('}')) {
+        let prefix = tree[..open].trim_end_matches("::");
+        let inner = &tree[open + 1..tree.len() - 1];
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in inner.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    rust_use_item(&inner[start..i], prefix, names);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        rust_use_item(&inner[start..], prefix, names);
+        return;
+    }
+    if tree.ends_with('*') {
+        return;
+    }
+    let name = alias_or_name(tree, |path| path.rsplit("::").next().unwrap_or(path));
+    // `use Trait as _` only brings methods into scope
+    if name != "_" && name != "self" {
+        names.push(name);
+    }
+}
+
+fn rust_use_item(item: &str, prefix: &str, names: &mut Vec<String>) {
+    let item = item.trim();
+    if item.is_empty() {
+        return;
+    }
+    if item == "self" {
+        names.push(prefix.rsplit("::").next().unwrap_or(prefix).to_string());
+    } else {
+        rust_use_names(item, names);
+    }
+}
+
+/// Statements beyond this are not checked for duplicates
+const MAX_DUPLICATE_STATEMENTS: usize = 20_000;
+/// Clone groups are compared pairwise; only this many members of each are used
+const MAX_CLONES_PER_GROUP: usize = 8;
+const DUPLICATE_TIME_BUDGET: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Keywords kept verbatim when normalizing; every other identifier becomes `v`
+const KEYWORDS: &[&str] = &[
+    "fn", "function", "def", "class", "struct", "impl", "if", "else", "elif", "for", "while",
+    "loop", "match", "switch", "case", "return", "let", "const", "var", "mut", "pub", "async",
+    "await", "new", "try", "catch", "except", "finally", "in", "of", "import", "from", "use",
+    "true", "false", "null", "None", "self", "this", "break", "continue", "yield", "lambda",
+];
+
+/// One source line reduced to its shape
+struct Statement {
+    hash: u64,
+    line: usize, // 0-based
+}
+
+/// Non-trivial lines with identifiers, literals and whitespace normalized away,
+/// so a copy with renamed variables hashes the same as the original
+fn normalized_statements(code: &str) -> Vec<Statement> {
+    use std::hash::{Hash, Hasher};
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    let token = TOKEN.get_or_init(|| {
+        Regex::new(r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'|[A-Za-z_]\w*|\d[\w.]*|\S"#).unwrap()
+    });
+
+    code.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !["//", "#", "/*", "*", "--"].iter().any(|c| line.starts_with(c))
+        })
+        .filter_map(|(line, text)| {
+            let tokens: Vec<&str> = token.find_iter(text)
+                .map(|t| {
+                    let t = t.as_str();
+                    match t.chars().next() {
+                        Some('"') | Some('\'') => "s",
+                        Some(c) if c.is_ascii_digit() => "0",
+                        Some(c) if c.is_alphabetic() || c == '_' => if KEYWORDS.contains(&t) { t } else { "v" },
+                        _ => t,
+                    }
+                })
+                .collect();
+            // Lines of bare punctuation (closing braces and the like) match everywhere
+            if !tokens.iter().any(|t| t.chars().any(|c| c.is_alphanumeric())) {
+                return None;
+            }
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            tokens.hash(&mut hasher);
+            Some(Statement { hash: hasher.finish(), line })
+        })
+        .take(MAX_DUPLICATE_STATEMENTS)
+        .collect()
+}
+
+/// Span from the first to the last of `statements`
+fn statements_location(code: &str, statements: &[Statement]) -> super::ast_parser::Location {
+    let first = statements.first().map(|s| s.line).unwrap_or(0);
+    let last = statements.last().map(|s| s.line).unwrap_or(first);
+    let mut start_byte = 0;
+    let mut end_byte = 0;
+    let mut end_column = 1;
+    let mut offset = 0;
+    for (index, line) in code.split_inclusive('\n').enumerate() {
+        if index == first {
+            start_byte = offset;
+        }
+        if index == last {
+            let text = line.trim_end_matches(['\r', '\n']);
+            end_byte = offset + text.len();
+            end_column = text.chars().count() as u32 + 1;
+            break;
+        }
+        offset += line.len();
+    }
+    super::ast_parser::Location {
+        start_line: first as u32 + 1,
+        start_column: 1,
+        end_line: last as u32 + 1,
+        end_column,
+        start_byte,
+        end_byte,
+    }
+}
+
 impl Default for PatternDetector {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ast_parser::Location;
+
+    fn root() -> ASTNode {
+        ASTNode {
+            node_type: "program".to_string(),
+            value: None,
+            children: vec![],
+            location: Location {
+                start_line: 1,
+                start_column: 0,
+                end_line: 3,
+                end_column: 0,
+                start_byte: 0,
+                end_byte: 0,
+            },
+            language: "typescript".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_low_confidence_patterns_are_dropped() {
+        // Only substring evidence for a factory: "createdAt" is not a factory
+        let code = "const record = { createdAt: Date.now() };";
+
+        let lenient = PatternDetector::with_min_confidence(0.0).detect_patterns(&root(), code);
+        assert!(lenient.iter().any(|p| p.name == "Factory Pattern"));
+
+        let strict = PatternDetector::with_min_confidence(0.5).detect_patterns(&root(), code);
+        assert!(strict.iter().all(|p| p.confidence >= 0.5));
+        assert!(strict.iter().all(|p| p.name != "Factory Pattern"));
+    }
+
+    #[test]
+    fn test_corroborated_factory_is_kept() {
+        let code = "class ShapeFactory {\n  static createShape(kind) { return new Circle(); }\n}";
+        let patterns = PatternDetector::new().detect_patterns(&root(), code);
+
+        let factory = patterns.iter().find(|p| p.name == "Factory Pattern").unwrap();
+        assert!(factory.confidence > DEFAULT_MIN_CONFIDENCE);
+    }
+
+    /// Six nested `if` blocks in `language`
+    fn nested(language: &str) -> ASTNode {
+        let mut node = root();
+        node.language = language.to_string();
+        for _ in 0..6 {
+            let mut parent = root();
+            parent.node_type = "if_statement".to_string();
+            parent.language = language.to_string();
+            parent.children = vec![node];
+            node = parent;
+        }
+        node
+    }
+
+    #[test]
+    fn test_language_profile_replaces_global_nesting_threshold() {
+        let flags_nesting = |ast: &ASTNode, detector: &PatternDetector| {
+            detector.detect_patterns(ast, "").iter().any(|p| p.name == "Deep Nesting")
+        };
+        let detector = PatternDetector::new();
+
+        // Depth 6 is past the global limit of 4 but normal for Haskell
+        assert!(flags_nesting(&nested("python"), &detector));
+        assert!(!flags_nesting(&nested("haskell"), &detector));
+
+        // A configured profile wins over the built-in one
+        let strict = AnalyzerProfile { max_nesting_depth: 5, ..AnalyzerProfile::builtin("haskell") };
+        assert!(flags_nesting(&nested("haskell"), &PatternDetector::new().with_profile(strict)));
+    }
+
+    #[test]
+    fn test_copy_pasted_function_is_flagged() {
+        let code = "\
+function totalPrice(items) {
+  let total = 0;
+  for (const item of items) {
+    const price = item.price * item.quantity;
+    total += price;
+  }
+  const tax = total * 0.2;
+  return total + tax;
+}
+
+function totalCost(lines) {
+  let sum = 0;
+  for (const line of lines) {
+    const cost = line.cost * line.count;
+    sum += cost;
+  }
+  const vat = sum * 0.2;
+  return sum + vat;
+}
+";
+        let patterns = PatternDetector::new().detect_patterns(&root(), code);
+        let duplicates: Vec<_> = patterns.iter().filter(|p| p.name == "Duplicate Code").collect();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].pattern_type, PatternType::CodeSmell);
+        assert_eq!(duplicates[0].location.start_line, 1);
+        assert_eq!(duplicates[0].related_locations.len(), 1);
+        assert_eq!(duplicates[0].related_locations[0].start_line, 11);
+
+        // Different code doesn't match
+        let distinct = "let a = 1;\nlet b = a + 2;\nif (b > 2) {\n  log(b);\n}\nreturn b;\n";
+        assert!(PatternDetector::new().detect_patterns(&root(), distinct).iter().all(|p| p.name != "Duplicate Code"));
+    }
+
+    #[test]
+    fn test_unused_imports_are_flagged() {
+        let unused = |code: &str, language: &str| -> Vec<String> {
+            let ast = ASTParser::new().parse(code, language).unwrap();
+            PatternDetector::new().detect_patterns(&ast, code)
+                .into_iter()
+                .filter(|p| p.name == "Unused Import")
+                .map(|p| p.description)
+                .collect()
+        };
+
+        let rust = "use std::collections::HashMap;\nuse std::fmt;\nuse std::io::*;\npub use crate::types::Task;\n\nfn main() {\n    let m: HashMap<u8, u8> = HashMap::new();\n}\n";
+        assert_eq!(unused(rust, "rust"), vec!["`fmt` is imported but never used"]);
+
+        let typescript = "import { useState, useEffect } from 'react';\nimport * as path from 'path';\n\nexport function counter() {\n  const [n, setN] = useState(0);\n  return n;\n}\n";
+        assert_eq!(unused(typescript, "typescript"), vec!["`useEffect` is imported but never used"]);
+
+        let python = "import os\nimport sys as system\nfrom typing import *\n\nprint(os.getcwd())\n";
+        assert_eq!(unused(python, "python"), vec!["`system` is imported but never used"]);
+    }
+}
@@ -8,10 +8,15 @@
  * - Multi-file relationship understanding
  * - Code pattern matching
  */
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Serialize, Deserialize};
-use super::indexer::{CodeSymbol, CodebaseIndexer};
+use crate::services::ai::router::ModelRouter;
+use super::indexer::{CodeSymbol, CodebaseIndexer, FileIndex};
+
+/// Stale files re-embedded per query when re-embedding is on; the rest fall back to lexical ranking
+const MAX_REEMBED_PER_QUERY: usize = 5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -179,3 +184,239 @@ impl SemanticSearch {
         0.0
     }
 }
+
+/// Turns text into vectors for `EmbeddingIndex`
+#[async_trait::async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>>;
+}
+
+#[async_trait::async_trait]
+impl Embedder for ModelRouter {
+    async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        ModelRouter::embed(self, texts).await
+    }
+}
+
+/// Vectors for one file's symbols, and the content they were computed from
+struct FileEmbeddings {
+    content_hash: String, // `FileIndex::content_hash` at embedding time
+    symbols: Vec<(CodeSymbol, Vec<f32>)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingCacheStats {
+    pub files: usize,
+    pub symbols: usize,
+    pub stale_files: usize, // Edited or removed since their embeddings were computed
+}
+
+/// Symbol embeddings for indexed files, ranked by cosine similarity
+///
+/// Each file's vectors remember the content hash they came from. A file
+/// edited since then is stale: its old vectors are ignored and its current
+/// symbols are ranked by name match instead, or re-embedded first when
+/// `reembed_stale` is set. Fresh files keep their vectors, so an edit never
+/// forces a full re-embed.
+pub struct EmbeddingIndex {
+    indexer: Arc<CodebaseIndexer>,
+    embedder: Arc<dyn Embedder>,
+    files: RwLock<HashMap<String, FileEmbeddings>>,
+    reembed_stale: bool,
+}
+
+impl EmbeddingIndex {
+    pub fn new(indexer: Arc<CodebaseIndexer>, embedder: Arc<dyn Embedder>, reembed_stale: bool) -> Self {
+        Self {
+            indexer,
+            embedder,
+            files: RwLock::new(HashMap::new()),
+            reembed_stale,
+        }
+    }
+
+    /// Compute vectors for the indexed version of `path`
+    pub async fn embed_file(&self, path: &str) -> anyhow::Result<()> {
+        let file = self.indexer.get_file_index(path).await
+            .ok_or_else(|| anyhow::anyhow!("{} is not indexed", path))?;
+        let texts = file.symbols.iter().map(symbol_text).collect();
+        let vectors = self.embedder.embed(texts).await?;
+        self.files.write().await.insert(path.to_string(), FileEmbeddings {
+            content_hash: file.content_hash,
+            symbols: file.symbols.into_iter().zip(vectors).collect(),
+        });
+        Ok(())
+    }
+
+    /// Embedded files whose indexed content no longer matches their vectors
+    pub async fn stale_files(&self) -> Vec<String> {
+        let embedded = self.files.read().await
+            .iter()
+            .map(|(path, file)| (path.clone(), file.content_hash.clone()))
+            .collect::<Vec<_>>();
+        let mut stale = Vec::new();
+        for (path, hash) in embedded {
+            let current = self.indexer.get_file_index(&path).await;
+            if current.map_or(true, |file| file.content_hash != hash) {
+                stale.push(path);
+            }
+        }
+        stale
+    }
+
+    /// Top `limit` symbols for `query`
+    pub async fn search(&self, query: &str, limit: usize) -> anyhow::Result<Vec<SearchResult>> {
+        let mut stale = self.stale_files().await;
+        if self.reembed_stale {
+            let mut still_stale = Vec::new();
+            for (i, path) in stale.into_iter().enumerate() {
+                let reembedded = i < MAX_REEMBED_PER_QUERY && match self.embed_file(&path).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::warn!("Failed to re-embed {}: {}", path, e);
+                        false
+                    }
+                };
+                if !reembedded {
+                    still_stale.push(path);
+                }
+            }
+            stale = still_stale;
+        }
+
+        let query_vector = self.embedder.embed(vec![query.to_string()]).await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("No embedding returned for the query"))?;
+
+        let mut results = Vec::new();
+        {
+            let files = self.files.read().await;
+            for (path, file) in files.iter().filter(|(path, _)| !stale.contains(path)) {
+                results.extend(file.symbols.iter().map(|(symbol, vector)| SearchResult {
+                    symbol: symbol.clone(),
+                    relevance_score: cosine_similarity(&query_vector, vector),
+                    context: format!("Found in {}", path),
+                    related_symbols: vec![],
+                }));
+            }
+        }
+        // Stale files are ranked on what they contain now, not on old vectors
+        let lexical = SemanticSearch::new(Arc::clone(&self.indexer));
+        for path in &stale {
+            let Some(file) = self.indexer.get_file_index(path).await else { continue };
+            results.extend(lexical_matches(&lexical, &file, query));
+        }
+
+        results.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    pub async fn cache_stats(&self) -> EmbeddingCacheStats {
+        let stale_files = self.stale_files().await.len();
+        let files = self.files.read().await;
+        EmbeddingCacheStats {
+            files: files.len(),
+            symbols: files.values().map(|file| file.symbols.len()).sum(),
+            stale_files,
+        }
+    }
+}
+
+fn lexical_matches(search: &SemanticSearch, file: &FileIndex, query: &str) -> Vec<SearchResult> {
+    file.symbols.iter()
+        .map(|symbol| SearchResult {
+            symbol: symbol.clone(),
+            relevance_score: search.calculate_relevance(symbol, query),
+            context: format!("Found in {} (name match; embeddings out of date)", file.path),
+            related_symbols: vec![],
+        })
+        .filter(|r| r.relevance_score > 0.3)
+        .collect()
+}
+
+/// What gets embedded for a symbol: its name, signature and docs
+fn symbol_text(symbol: &CodeSymbol) -> String {
+    [Some(symbol.name.as_str()), symbol.signature.as_deref(), symbol.documentation.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    (dot / denominator) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embeds text as a bag of its lowercase words, so shared words mean similar vectors
+    struct BagOfWords;
+
+    #[async_trait::async_trait]
+    impl Embedder for BagOfWords {
+        async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|text| {
+                let mut vector = vec![0.0; 32];
+                for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+                    let bucket = word.to_lowercase().bytes().fold(7usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+                    vector[bucket % 32] += 1.0;
+                }
+                vector
+            }).collect())
+        }
+    }
+
+    async fn indexed_project() -> Arc<CodebaseIndexer> {
+        let indexer = Arc::new(CodebaseIndexer::new());
+        indexer.index_file("src/config.rs".to_string(), "fn parse_config() {}\n".to_string(), "rust".to_string()).await;
+        indexer.index_file("src/page.rs".to_string(), "fn render_page() {}\n".to_string(), "rust".to_string()).await;
+        indexer
+    }
+
+    #[tokio::test]
+    async fn test_edited_files_are_not_ranked_on_old_embeddings() {
+        let indexer = indexed_project().await;
+        let index = EmbeddingIndex::new(Arc::clone(&indexer), Arc::new(BagOfWords), false);
+        index.embed_file("src/config.rs").await.unwrap();
+        index.embed_file("src/page.rs").await.unwrap();
+
+        let results = index.search("parse config", 5).await.unwrap();
+        assert_eq!(results[0].symbol.name, "parse_config");
+        assert_eq!(index.cache_stats().await.stale_files, 0);
+
+        // parse_config is renamed; its vector still matches the query perfectly
+        indexer.index_file("src/config.rs".to_string(), "fn load_settings() {}\n".to_string(), "rust".to_string()).await;
+        let stats = index.cache_stats().await;
+        assert_eq!((stats.files, stats.stale_files), (2, 1));
+
+        let results = index.search("parse config", 5).await.unwrap();
+        assert!(results.iter().all(|r| r.symbol.name != "parse_config"), "{:?}", results);
+        // The edited file is still found, by what it contains now
+        let results = index.search("load_settings", 5).await.unwrap();
+        assert_eq!(results[0].symbol.name, "load_settings");
+        assert!(results[0].context.contains("embeddings out of date"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_files_can_be_reembedded_on_demand() {
+        let indexer = indexed_project().await;
+        let index = EmbeddingIndex::new(Arc::clone(&indexer), Arc::new(BagOfWords), true);
+        index.embed_file("src/config.rs").await.unwrap();
+        index.embed_file("src/page.rs").await.unwrap();
+
+        indexer.index_file("src/config.rs".to_string(), "fn load_settings() {}\n".to_string(), "rust".to_string()).await;
+        let results = index.search("load settings", 5).await.unwrap();
+        assert_eq!(results[0].symbol.name, "load_settings");
+        assert!(results.iter().all(|r| r.symbol.name != "parse_config"));
+        assert_eq!(index.cache_stats().await.stale_files, 0);
+    }
+}
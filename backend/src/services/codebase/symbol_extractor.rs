@@ -26,23 +26,17 @@ impl SymbolExtractor {
     pub async fn extract(&mut self, code: &str, language: &str, file_path: &str) -> Vec<CodeSymbol> {
         let parsed_symbols = self.parser.extract_symbols(code, language);
         
-        parsed_symbols.into_iter()
-            .map(|ps| {
-                // Register definition with reference tracker
-                let code_symbol = self.parsed_to_code_symbol(&ps, file_path);
-                let location = ps.location.clone();
-                let symbol_name = ps.name.clone();
-                
-                // Register asynchronously
-                let rt = Arc::clone(&self.reference_tracker);
-                let cs = code_symbol.clone();
-                tokio::spawn(async move {
-                    rt.register_definition(cs, file_path.to_string(), location).await;
-                });
-                
-                code_symbol
-            })
-            .collect()
+        let mut symbols = Vec::with_capacity(parsed_symbols.len());
+        for ps in parsed_symbols {
+            // Register definition with reference tracker before returning, so a
+            // re-index that cleared the file's entries can't race with it
+            let code_symbol = self.parsed_to_code_symbol(&ps, file_path);
+            self.reference_tracker
+                .register_definition(code_symbol.clone(), file_path.to_string(), ps.location)
+                .await;
+            symbols.push(code_symbol);
+        }
+        symbols
     }
 
     /// Extract imports from code
@@ -51,20 +45,13 @@ impl SymbolExtractor {
         
         // Register import references
         for import in &imports {
-            let rt = Arc::clone(&self.reference_tracker);
-            let import_path = import.path.clone();
-            let location = import.location.clone();
-            let file = file_path.to_string();
-            
-            tokio::spawn(async move {
-                rt.register_reference(
-                    file,
-                    location,
-                    import_path,
-                    super::reference_tracker::ReferenceType::Import,
-                    "import".to_string(),
-                ).await;
-            });
+            self.reference_tracker.register_reference(
+                file_path.to_string(),
+                import.location.clone(),
+                import.path.clone(),
+                super::reference_tracker::ReferenceType::Import,
+                "import".to_string(),
+            ).await;
         }
         
         imports.into_iter().map(|i| i.path).collect()
@@ -187,6 +187,11 @@ Generate tests with:
             temperature: Some(0.7),
             max_tokens: Some(4000),
             stream: Some(false),
+            context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
         };
         
         // Use DeepSeek for code generation (fast and cheap)
@@ -194,7 +199,7 @@ Generate tests with:
             .or_else(|| self.router.get_service(ModelProvider::Moonshot))
             .ok_or("No AI service available")?;
         
-        match service.generate(request).await {
+        match self.router.generate_with(&service, request).await {
             Ok(response) => {
                 // Parse test code from response
                 // Extract code blocks
@@ -0,0 +1,291 @@
+/**
+ * Unified Codebase Search
+ *
+ * One query fanned out to symbol lookup, semantic search, the reference
+ * graph and code-pattern detection at once. Hits from every source are put on
+ * a common relevance scale, merged when they point at the same place, and
+ * returned as a single ranked list tagged with where each hit came from.
+ *
+ * Pattern detection parses files on every query, so it only runs when asked
+ * for and reads a bounded number of files.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+
+use super::analyzer_profile::AnalyzerProfiles;
+use super::ast_parser::ASTParser;
+use super::indexer::CodebaseIndexer;
+use super::pattern_detector::PatternDetector;
+use super::semantic_search::SemanticSearch;
+
+/// Hits kept per source when a request doesn't set a limit
+pub const DEFAULT_KIND_LIMIT: usize = 20;
+/// Ranking bonus for each additional source that found the same hit
+const AGREEMENT_BONUS: f64 = 0.05;
+/// Most files parsed for pattern hits in one query
+const MAX_PATTERN_FILES: usize = 200;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    Symbols,
+    Semantic,
+    References,
+    Patterns,
+}
+
+impl SearchKind {
+    pub const ALL: [SearchKind; 4] = [Self::Symbols, Self::Semantic, Self::References, Self::Patterns];
+    /// Sources searched when a request doesn't name any; patterns are opt-in
+    pub const DEFAULT: [SearchKind; 3] = [Self::Symbols, Self::Semantic, Self::References];
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    /// Source that scored this hit highest
+    pub source: SearchKind,
+    /// Other sources that found the same location
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_found_by: Vec<SearchKind>,
+    pub name: String,
+    pub file_path: String,
+    pub line: u32,
+    pub detail: String,
+    /// 0.0-1.0, comparable across sources
+    pub score: f64,
+}
+
+pub struct UnifiedSearch {
+    indexer: Arc<CodebaseIndexer>,
+    min_confidence: f64,
+    profiles: AnalyzerProfiles,
+}
+
+impl UnifiedSearch {
+    pub fn new(indexer: Arc<CodebaseIndexer>, min_confidence: f64, profiles: AnalyzerProfiles) -> Self {
+        Self { indexer, min_confidence, profiles }
+    }
+
+    /// Run the requested sources concurrently and merge their hits
+    ///
+    /// `limits` caps each source before merging; sources without an entry
+    /// keep `DEFAULT_KIND_LIMIT` hits.
+    pub async fn search(&self, query: &str, kinds: &[SearchKind], limits: &HashMap<SearchKind, usize>) -> Vec<SearchHit> {
+        let wanted = |kind| kinds.contains(&kind);
+        let limit_for = |kind| limits.get(&kind).copied().unwrap_or(DEFAULT_KIND_LIMIT);
+        let (symbols, semantic, references, patterns) = tokio::join!(
+            async { if wanted(SearchKind::Symbols) { self.symbols(query).await } else { Vec::new() } },
+            async { if wanted(SearchKind::Semantic) { self.semantic(query).await } else { Vec::new() } },
+            async { if wanted(SearchKind::References) { self.references(query).await } else { Vec::new() } },
+            async { if wanted(SearchKind::Patterns) { self.patterns(query, limit_for(SearchKind::Patterns)).await } else { Vec::new() } },
+        );
+
+        let capped = [symbols, semantic, references, patterns].into_iter()
+            .zip(SearchKind::ALL)
+            .map(|(mut hits, kind)| {
+                rank(&mut hits);
+                hits.truncate(limit_for(kind));
+                hits
+            })
+            .collect();
+        merge_hits(capped)
+    }
+
+    async fn symbols(&self, query: &str) -> Vec<SearchHit> {
+        let needle = query.to_lowercase();
+        self.indexer.search(query).await.into_iter()
+            .map(|symbol| {
+                let name = symbol.name.to_lowercase();
+                let score = if symbol.name == query {
+                    1.0
+                } else if name == needle {
+                    0.9
+                } else if name.starts_with(&needle) {
+                    0.75
+                } else {
+                    0.6
+                };
+                SearchHit {
+                    source: SearchKind::Symbols,
+                    also_found_by: Vec::new(),
+                    detail: symbol.signature.clone().unwrap_or_else(|| format!("{:?}", symbol.kind)),
+                    name: symbol.name,
+                    file_path: symbol.file_path,
+                    line: symbol.line,
+                    score,
+                }
+            })
+            .collect()
+    }
+
+    async fn semantic(&self, query: &str) -> Vec<SearchHit> {
+        SemanticSearch::new(Arc::clone(&self.indexer)).search(query).await.into_iter()
+            .map(|result| SearchHit {
+                source: SearchKind::Semantic,
+                also_found_by: Vec::new(),
+                name: result.symbol.name,
+                file_path: result.symbol.file_path,
+                line: result.symbol.line,
+                detail: result.context,
+                // Fuzzier than a name match, so it never outranks an exact symbol
+                score: (result.relevance_score * 0.8).clamp(0.0, 0.8),
+            })
+            .collect()
+    }
+
+    async fn references(&self, query: &str) -> Vec<SearchHit> {
+        self.indexer.reference_tracker().find_usages(query).await.into_iter()
+            .map(|reference| SearchHit {
+                source: SearchKind::References,
+                also_found_by: Vec::new(),
+                name: reference.to_symbol,
+                file_path: reference.from_file,
+                line: reference.from_location.start_line,
+                detail: format!("{:?}: {}", reference.reference_type, reference.context.trim()),
+                score: 0.7,
+            })
+            .collect()
+    }
+
+    /// Detected patterns whose name or description mentions the query
+    ///
+    /// Stops after `limit` hits or `MAX_PATTERN_FILES` files, whichever comes first.
+    async fn patterns(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let needle = query.to_lowercase();
+        let mut hits = Vec::new();
+        let mut files = self.indexer.indexed_files().await;
+        // Deterministic order so the same query scans the same files
+        files.sort();
+        for (path, language) in files.into_iter().take(MAX_PATTERN_FILES) {
+            if hits.len() >= limit {
+                break;
+            }
+            let Ok(code) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            let Ok(ast) = ASTParser::new().parse(&code, &language) else {
+                continue;
+            };
+            let detector = PatternDetector::with_min_confidence(self.min_confidence)
+                .with_profile(self.profiles.for_language(&language));
+            for pattern in detector.detect_patterns(&ast, &code) {
+                if !pattern.name.to_lowercase().contains(&needle)
+                    && !pattern.description.to_lowercase().contains(&needle)
+                {
+                    continue;
+                }
+                hits.push(SearchHit {
+                    source: SearchKind::Patterns,
+                    also_found_by: Vec::new(),
+                    name: pattern.name,
+                    file_path: path.clone(),
+                    line: pattern.location.start_line,
+                    detail: pattern.description,
+                    score: 0.6 * pattern.confidence,
+                });
+            }
+        }
+        hits
+    }
+}
+
+fn rank(hits: &mut [SearchHit]) {
+    hits.sort_by(|a, b| {
+        b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.file_path.cmp(&b.file_path))
+            .then_with(|| a.line.cmp(&b.line))
+    });
+}
+
+/// Merge hits from several sources into one ranked list
+///
+/// Hits for the same name at the same place are combined: the best-scoring
+/// source is kept as `source` and the hit gets a small bonus for each other
+/// source that agreed.
+pub fn merge_hits(sources: Vec<Vec<SearchHit>>) -> Vec<SearchHit> {
+    let mut merged: Vec<SearchHit> = Vec::new();
+    let mut positions: HashMap<(String, u32, String), usize> = HashMap::new();
+
+    for hit in sources.into_iter().flatten() {
+        let key = (
+            hit.file_path.trim_start_matches("./").to_string(),
+            hit.line,
+            hit.name.to_lowercase(),
+        );
+        match positions.get(&key) {
+            Some(&index) => {
+                let existing = &mut merged[index];
+                if existing.source == hit.source || existing.also_found_by.contains(&hit.source) {
+                    existing.score = existing.score.max(hit.score);
+                    continue;
+                }
+                if hit.score > existing.score {
+                    existing.also_found_by.push(existing.source);
+                    existing.source = hit.source;
+                    existing.score = hit.score;
+                    existing.detail = hit.detail;
+                } else {
+                    existing.also_found_by.push(hit.source);
+                }
+            }
+            None => {
+                positions.insert(key, merged.len());
+                merged.push(hit);
+            }
+        }
+    }
+
+    for hit in &mut merged {
+        hit.score = (hit.score + AGREEMENT_BONUS * hit.also_found_by.len() as f64).min(1.0);
+    }
+    rank(&mut merged);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(source: SearchKind, name: &str, file_path: &str, line: u32, score: f64) -> SearchHit {
+        SearchHit {
+            source,
+            also_found_by: Vec::new(),
+            name: name.to_string(),
+            file_path: file_path.to_string(),
+            line,
+            detail: String::new(),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_hits_from_several_sources_are_merged_and_ranked() {
+        let merged = merge_hits(vec![
+            vec![
+                hit(SearchKind::Symbols, "parse_config", "src/config.rs", 10, 1.0),
+                hit(SearchKind::Symbols, "parse_config_file", "src/config.rs", 40, 0.75),
+            ],
+            vec![
+                hit(SearchKind::Semantic, "parse_config", "./src/config.rs", 10, 0.64),
+                hit(SearchKind::Semantic, "load_settings", "src/settings.rs", 3, 0.5),
+            ],
+            vec![hit(SearchKind::References, "parse_config", "src/main.rs", 22, 0.7)],
+            vec![hit(SearchKind::Patterns, "Long Method", "src/config.rs", 40, 0.45)],
+        ]);
+
+        let order: Vec<_> = merged.iter().map(|h| (h.name.as_str(), h.file_path.as_str(), h.source)).collect();
+        assert_eq!(order, vec![
+            ("parse_config", "src/config.rs", SearchKind::Symbols),
+            ("parse_config_file", "src/config.rs", SearchKind::Symbols),
+            ("parse_config", "src/main.rs", SearchKind::References),
+            ("load_settings", "src/settings.rs", SearchKind::Semantic),
+            ("Long Method", "src/config.rs", SearchKind::Patterns),
+        ]);
+
+        // The definition found by both symbol and semantic search appears once
+        assert_eq!(merged[0].also_found_by, vec![SearchKind::Semantic]);
+        assert_eq!(merged[0].score, 1.0);
+        assert!(merged[1].also_found_by.is_empty());
+    }
+}
@@ -40,7 +40,7 @@ impl AgentCollaborator {
         agent_id: String,
     ) -> anyhow::Result<()> {
         // Join session as agent participant
-        self.session_manager.join_session(
+        self.session_manager.add_participant(
             session_id,
             None, // user_id
             Some(Uuid::parse_str(&agent_id)?), // agent_id
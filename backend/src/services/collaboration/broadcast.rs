@@ -0,0 +1,233 @@
+/**
+ * Session Broadcast Delivery
+ *
+ * Fans collaboration messages out to every connection in a session. Each
+ * session has one delivery path guarded by a lock, so concurrent broadcasts
+ * are serialized and every participant sees messages in the same order.
+ * Failed deliveries are counted per participant; a participant that keeps
+ * failing is dropped so it stops holding the session's buffers.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use axum::extract::ws::Message;
+use serde::Serialize;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeliveryConfig {
+    /// Messages buffered per participant before a slow reader starts losing them
+    pub send_buffer: usize,
+    /// Consecutive failed deliveries before a participant is dropped; 0 keeps it
+    pub max_consecutive_failures: u32,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            send_buffer: 1000,
+            max_consecutive_failures: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeliveryReport {
+    pub delivered: usize,
+    pub failed: usize,
+    /// Participants dropped by this broadcast after failing too often
+    pub dropped: Vec<Uuid>,
+}
+
+struct Subscriber {
+    tx: broadcast::Sender<Message>,
+    consecutive_failures: u32,
+}
+
+/// Held for a whole fan-out; this is what orders broadcasts within a session
+type SessionSubscribers = Arc<Mutex<HashMap<Uuid, Subscriber>>>;
+
+pub struct SessionBroadcaster {
+    sessions: RwLock<HashMap<Uuid, SessionSubscribers>>,
+    config: DeliveryConfig,
+}
+
+impl SessionBroadcaster {
+    pub fn new(config: DeliveryConfig) -> Self {
+        Self {
+            sessions: RwLock::new(HashMap::new()),
+            config,
+        }
+    }
+
+    async fn session(&self, session_id: Uuid) -> Option<SessionSubscribers> {
+        self.sessions.read().await.get(&session_id).cloned()
+    }
+
+    /// Register a participant's connection, replacing any earlier one
+    ///
+    /// The session map stays locked until the participant is in, so a
+    /// concurrent `unsubscribe` can't drop the session as empty in between
+    /// and leave this connection subscribed to a session nobody broadcasts to.
+    pub async fn subscribe(&self, session_id: Uuid, participant_id: Uuid) -> broadcast::Receiver<Message> {
+        let (tx, rx) = broadcast::channel(self.config.send_buffer.max(1));
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.entry(session_id).or_insert_with(SessionSubscribers::default);
+        session.lock().await.insert(participant_id, Subscriber { tx, consecutive_failures: 0 });
+        rx
+    }
+
+    pub async fn unsubscribe(&self, session_id: Uuid, participant_id: Uuid) {
+        let mut sessions = self.sessions.write().await;
+        let Some(session) = sessions.get(&session_id).cloned() else {
+            return;
+        };
+        let mut subscribers = session.lock().await;
+        subscribers.remove(&participant_id);
+        if subscribers.is_empty() {
+            sessions.remove(&session_id);
+        }
+    }
+
+    /// Deliver `message` to every participant in the session except `exclude`
+    pub async fn broadcast(&self, session_id: Uuid, message: Message, exclude: Option<Uuid>) -> DeliveryReport {
+        let mut report = DeliveryReport::default();
+        let Some(session) = self.session(session_id).await else {
+            return report;
+        };
+
+        let mut subscribers = session.lock().await;
+        for (participant_id, subscriber) in subscribers.iter_mut() {
+            if Some(*participant_id) == exclude {
+                continue;
+            }
+            match subscriber.tx.send(message.clone()) {
+                Ok(_) => {
+                    subscriber.consecutive_failures = 0;
+                    report.delivered += 1;
+                }
+                Err(_) => {
+                    subscriber.consecutive_failures += 1;
+                    report.failed += 1;
+                    tracing::warn!(
+                        "Collaboration delivery to participant {} in session {} failed ({} in a row)",
+                        participant_id, session_id, subscriber.consecutive_failures
+                    );
+                    let limit = self.config.max_consecutive_failures;
+                    if limit > 0 && subscriber.consecutive_failures >= limit {
+                        report.dropped.push(*participant_id);
+                    }
+                }
+            }
+        }
+        for participant_id in &report.dropped {
+            tracing::warn!("Dropping participant {} from session {} after repeated delivery failures", participant_id, session_id);
+            subscribers.remove(participant_id);
+        }
+        report
+    }
+
+    /// Deliver `message` to one participant only; false if it is not connected
+    pub async fn send_to(&self, session_id: Uuid, participant_id: Uuid, message: Message) -> bool {
+        let Some(session) = self.session(session_id).await else {
+            return false;
+        };
+        let subscribers = session.lock().await;
+        subscribers.get(&participant_id)
+            .map(|subscriber| subscriber.tx.send(message).is_ok())
+            .unwrap_or(false)
+    }
+
+    pub async fn participants(&self, session_id: Uuid) -> Vec<Uuid> {
+        match self.session(session_id).await {
+            Some(session) => session.lock().await.keys().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(message: &Message) -> String {
+        match message {
+            Message::Text(text) => text.clone(),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sequential_broadcasts_arrive_in_order_for_every_subscriber() {
+        let broadcaster = Arc::new(SessionBroadcaster::new(DeliveryConfig::default()));
+        let session_id = Uuid::new_v4();
+        let mut receivers = Vec::new();
+        for _ in 0..3 {
+            receivers.push(broadcaster.subscribe(session_id, Uuid::new_v4()).await);
+        }
+
+        let first = broadcaster.broadcast(session_id, Message::Text("edit 1".to_string()), None).await;
+        let second = broadcaster.broadcast(session_id, Message::Text("edit 2".to_string()), None).await;
+        assert_eq!((first.delivered, second.delivered), (3, 3));
+
+        for rx in &mut receivers {
+            assert_eq!(text(&rx.recv().await.unwrap()), "edit 1");
+            assert_eq!(text(&rx.recv().await.unwrap()), "edit 2");
+        }
+
+        // Concurrent broadcasters still produce one order shared by all subscribers
+        let mut tasks = Vec::new();
+        for i in 0..20 {
+            let broadcaster = Arc::clone(&broadcaster);
+            tasks.push(tokio::spawn(async move {
+                broadcaster.broadcast(session_id, Message::Text(format!("cursor {}", i)), None).await
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let mut orders = Vec::new();
+        for rx in &mut receivers {
+            orders.push((0..20).map(|_| text(&rx.try_recv().unwrap())).collect::<Vec<_>>());
+        }
+        assert!(orders.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[tokio::test]
+    async fn test_persistently_failing_subscriber_is_dropped() {
+        let broadcaster = SessionBroadcaster::new(DeliveryConfig { send_buffer: 8, max_consecutive_failures: 2 });
+        let session_id = Uuid::new_v4();
+        let gone = Uuid::new_v4();
+        drop(broadcaster.subscribe(session_id, gone).await);
+        let _live = broadcaster.subscribe(session_id, Uuid::new_v4()).await;
+
+        let report = broadcaster.broadcast(session_id, Message::Text("a".to_string()), None).await;
+        assert_eq!((report.delivered, report.failed), (1, 1));
+        assert!(report.dropped.is_empty());
+
+        let report = broadcaster.broadcast(session_id, Message::Text("b".to_string()), None).await;
+        assert_eq!(report.dropped, vec![gone]);
+        assert_eq!(broadcaster.participants(session_id).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_racing_last_unsubscribe_stays_reachable() {
+        let broadcaster = Arc::new(SessionBroadcaster::new(DeliveryConfig::default()));
+        for _ in 0..50 {
+            let session_id = Uuid::new_v4();
+            let leaving = Uuid::new_v4();
+            let _old = broadcaster.subscribe(session_id, leaving).await;
+
+            let unsubscribe = tokio::spawn({
+                let broadcaster = Arc::clone(&broadcaster);
+                async move { broadcaster.unsubscribe(session_id, leaving).await }
+            });
+            let mut rx = broadcaster.subscribe(session_id, Uuid::new_v4()).await;
+            unsubscribe.await.unwrap();
+
+            let report = broadcaster.broadcast(session_id, Message::Text("hello".to_string()), None).await;
+            assert_eq!(report.delivered, 1);
+            assert_eq!(text(&rx.try_recv().unwrap()), "hello");
+        }
+    }
+}
@@ -48,6 +48,44 @@ pub enum OperationType {
     Insert,
     Delete,
     Retain,
+    /// Delete `length` characters at `position`, then insert `content` there
+    Replace,
+}
+
+impl OperationType {
+    /// Type of an edit that swaps `length` characters for `content`
+    pub fn of_edit(length: usize, content: &str) -> Self {
+        match (length, content.is_empty()) {
+            (0, true) => OperationType::Retain,
+            (0, false) => OperationType::Insert,
+            (_, true) => OperationType::Delete,
+            (_, false) => OperationType::Replace,
+        }
+    }
+}
+
+/// How a session reconciles concurrent edits, from its `conflict_resolution` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// Operational Transform on positions and versions (the default)
+    #[default]
+    Ot,
+    /// RGA sequence CRDT, see `CrdtResolver`
+    Crdt,
+}
+
+impl ConflictStrategy {
+    pub const SETTING: &'static str = "conflict_resolution";
+
+    /// Strategy named in session settings; a missing setting means OT
+    pub fn from_settings(settings: &serde_json::Value) -> Result<Self, String> {
+        match settings.get(Self::SETTING) {
+            None | Some(serde_json::Value::Null) => Ok(Self::default()),
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|_| format!("{} must be \"ot\" or \"crdt\"", Self::SETTING)),
+        }
+    }
 }
 
 pub struct ConflictResolver {
@@ -157,6 +195,13 @@ impl ConflictResolver {
                 result.replace_range(start..end, "");
                 result
             }
+            OperationType::Replace => {
+                let mut result = text.to_string();
+                let start = operation.position.min(result.len());
+                let end = (operation.position + operation.length).min(result.len());
+                result.replace_range(start..end, &operation.content);
+                result
+            }
             OperationType::Retain => text.to_string(),
         }
     }
@@ -173,11 +218,13 @@ impl ConflictResolver {
         let op1_end = op1.position + match op1.operation_type {
             OperationType::Insert => op1.content.len(),
             OperationType::Delete => op1.length,
+            OperationType::Replace => op1.length.max(op1.content.len()),
             OperationType::Retain => 0,
         };
         let op2_end = op2.position + match op2.operation_type {
             OperationType::Insert => op2.content.len(),
             OperationType::Delete => op2.length,
+            OperationType::Replace => op2.length.max(op2.content.len()),
             OperationType::Retain => 0,
         };
 
@@ -0,0 +1,317 @@
+/**
+ * CRDT Conflict Resolution
+ *
+ * Replicated Growable Array (RGA) sequence CRDT for sessions that opt out of
+ * Operational Transform. Every character gets a unique id when inserted and
+ * deletes only tombstone it, so replicas that have seen the same operations
+ * hold the same text whatever order the operations arrived in.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+
+/// Unique id of one inserted character
+///
+/// Ordered by Lamport counter, then site, which is the tie-break RGA uses to
+/// place concurrent inserts at the same spot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CrdtId {
+    pub counter: u64,
+    pub site: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CrdtOp {
+    /// Place `value` right after `after` (None = start of the document)
+    Insert {
+        id: CrdtId,
+        after: Option<CrdtId>,
+        value: char,
+    },
+    Delete {
+        target: CrdtId,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    id: CrdtId,
+    value: char,
+    deleted: bool,
+}
+
+/// One replica of a file's text
+#[derive(Debug, Clone)]
+pub struct RgaDocument {
+    site: Uuid,
+    clock: u64,
+    elements: Vec<Element>,
+    /// Remote ops waiting for the character they refer to
+    pending: Vec<CrdtOp>,
+}
+
+impl RgaDocument {
+    pub fn new(site: Uuid) -> Self {
+        Self { site, clock: 0, elements: Vec::new(), pending: Vec::new() }
+    }
+
+    /// Replica seeded with `text`
+    ///
+    /// Seed characters get ids from the nil site, so every replica seeded with
+    /// the same text agrees on them without exchanging any operations.
+    pub fn from_text(site: Uuid, text: &str) -> Self {
+        let elements: Vec<Element> = text.chars().enumerate()
+            .map(|(i, value)| Element {
+                id: CrdtId { counter: i as u64 + 1, site: Uuid::nil() },
+                value,
+                deleted: false,
+            })
+            .collect();
+        Self { site, clock: elements.len() as u64, elements, pending: Vec::new() }
+    }
+
+    pub fn text(&self) -> String {
+        self.elements.iter().filter(|e| !e.deleted).map(|e| e.value).collect()
+    }
+
+    /// Ops still waiting on characters this replica has not seen
+    pub fn pending_ops(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Insert `text` before the `position`-th visible character
+    pub fn local_insert(&mut self, position: usize, text: &str) -> Vec<CrdtOp> {
+        let mut after = position.checked_sub(1).and_then(|p| self.visible_id(p));
+        let mut ops = Vec::with_capacity(text.chars().count());
+        for value in text.chars() {
+            self.clock += 1;
+            let op = CrdtOp::Insert {
+                id: CrdtId { counter: self.clock, site: self.site },
+                after,
+                value,
+            };
+            self.integrate(&op);
+            if let CrdtOp::Insert { id, .. } = op {
+                after = Some(id);
+            }
+            ops.push(op);
+        }
+        ops
+    }
+
+    /// Delete `length` visible characters starting at `position`
+    pub fn local_delete(&mut self, position: usize, length: usize) -> Vec<CrdtOp> {
+        let targets: Vec<CrdtId> = self.elements.iter()
+            .filter(|e| !e.deleted)
+            .skip(position)
+            .take(length)
+            .map(|e| e.id)
+            .collect();
+        targets.into_iter()
+            .map(|target| {
+                let op = CrdtOp::Delete { target };
+                self.integrate(&op);
+                op
+            })
+            .collect()
+    }
+
+    /// Apply an op from another replica
+    ///
+    /// Duplicates are ignored, and an op that refers to a character this
+    /// replica has not received yet is held back until that character arrives.
+    pub fn apply_remote_op(&mut self, op: CrdtOp) {
+        if !self.integrate(&op) {
+            self.pending.push(op);
+            return;
+        }
+        // Anything held back may now be applicable; repeat until nothing moves
+        loop {
+            let waiting = std::mem::take(&mut self.pending);
+            let before = waiting.len();
+            for op in waiting {
+                if !self.integrate(&op) {
+                    self.pending.push(op);
+                }
+            }
+            if self.pending.len() == before {
+                break;
+            }
+        }
+    }
+
+    /// Apply `op`, or return false if it refers to an unknown character
+    fn integrate(&mut self, op: &CrdtOp) -> bool {
+        match op {
+            CrdtOp::Insert { id, after, value } => {
+                if self.index_of(*id).is_some() {
+                    return true;
+                }
+                let mut index = match after {
+                    None => 0,
+                    Some(after) => match self.index_of(*after) {
+                        Some(i) => i + 1,
+                        None => return false,
+                    },
+                };
+                // Concurrent inserts at the same spot: larger ids sit first
+                while index < self.elements.len() && self.elements[index].id > *id {
+                    index += 1;
+                }
+                self.elements.insert(index, Element { id: *id, value: *value, deleted: false });
+                self.clock = self.clock.max(id.counter);
+                true
+            }
+            CrdtOp::Delete { target } => match self.index_of(*target) {
+                Some(i) => {
+                    self.elements[i].deleted = true;
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    fn index_of(&self, id: CrdtId) -> Option<usize> {
+        self.elements.iter().position(|e| e.id == id)
+    }
+
+    fn visible_id(&self, position: usize) -> Option<CrdtId> {
+        self.elements.iter().filter(|e| !e.deleted).nth(position).map(|e| e.id)
+    }
+}
+
+/// Server-side replicas for every file edited in a CRDT session
+pub struct CrdtResolver {
+    site: Uuid,
+    documents: Arc<RwLock<HashMap<(Uuid, String), RgaDocument>>>,
+}
+
+impl CrdtResolver {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            site: Uuid::new_v4(),
+            documents: Arc::new(RwLock::new(HashMap::new())),
+        })
+    }
+
+    /// Start tracking a file from its current contents; no-op if already tracked
+    pub async fn open_document(&self, session_id: Uuid, file_path: &str, initial_text: &str) {
+        let mut documents = self.documents.write().await;
+        documents.entry((session_id, file_path.to_string()))
+            .or_insert_with(|| RgaDocument::from_text(self.site, initial_text));
+    }
+
+    pub async fn is_open(&self, session_id: Uuid, file_path: &str) -> bool {
+        self.documents.read().await.contains_key(&(session_id, file_path.to_string()))
+    }
+
+    pub async fn apply_remote_op(&self, session_id: Uuid, file_path: &str, op: CrdtOp) {
+        let mut documents = self.documents.write().await;
+        documents.entry((session_id, file_path.to_string()))
+            .or_insert_with(|| RgaDocument::new(self.site))
+            .apply_remote_op(op);
+    }
+
+    /// Insert on the server's replica, returning the ops to broadcast
+    pub async fn local_insert(&self, session_id: Uuid, file_path: &str, position: usize, text: &str) -> Vec<CrdtOp> {
+        let mut documents = self.documents.write().await;
+        documents.entry((session_id, file_path.to_string()))
+            .or_insert_with(|| RgaDocument::new(self.site))
+            .local_insert(position, text)
+    }
+
+    /// Delete on the server's replica, returning the ops to broadcast
+    pub async fn local_delete(&self, session_id: Uuid, file_path: &str, position: usize, length: usize) -> Vec<CrdtOp> {
+        let mut documents = self.documents.write().await;
+        match documents.get_mut(&(session_id, file_path.to_string())) {
+            Some(document) => document.local_delete(position, length),
+            None => Vec::new(),
+        }
+    }
+
+    pub async fn text(&self, session_id: Uuid, file_path: &str) -> Option<String> {
+        self.documents.read().await.get(&(session_id, file_path.to_string())).map(|d| d.text())
+    }
+
+    /// Drop every replica belonging to a session
+    pub async fn close_session(&self, session_id: Uuid) {
+        self.documents.write().await.retain(|(sid, _), _| *sid != session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiny deterministic generator so the test needs no extra crates
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % bound as u64) as usize
+        }
+    }
+
+    #[test]
+    fn test_concurrent_ops_converge_in_any_order() {
+        let base = "fn main() {}\n";
+        for seed in 0..20u64 {
+            let mut rng = Lcg(seed);
+
+            // Three sites edit concurrently, each seeing only its own changes
+            let mut ops = Vec::new();
+            for _ in 0..3 {
+                let mut site = RgaDocument::from_text(Uuid::new_v4(), base);
+                for _ in 0..6 {
+                    let len = site.text().chars().count();
+                    if rng.next(3) == 0 && len > 0 {
+                        let position = rng.next(len);
+                        ops.extend(site.local_delete(position, 1 + rng.next(3)));
+                    } else {
+                        let position = rng.next(len + 1);
+                        ops.extend(site.local_insert(position, ["x", "yz", "{}"][rng.next(3)]));
+                    }
+                }
+            }
+
+            // Deliver everything to fresh replicas in shuffled orders, some twice
+            let mut texts = Vec::new();
+            for _ in 0..5 {
+                let mut order = ops.clone();
+                for i in (1..order.len()).rev() {
+                    order.swap(i, rng.next(i + 1));
+                }
+                order.push(order[rng.next(order.len())].clone());
+
+                let mut replica = RgaDocument::from_text(Uuid::new_v4(), base);
+                for op in order {
+                    replica.apply_remote_op(op);
+                }
+                assert_eq!(replica.pending_ops(), 0);
+                texts.push(replica.text());
+            }
+            assert!(texts.windows(2).all(|w| w[0] == w[1]), "seed {} diverged: {:?}", seed, texts);
+        }
+
+        // Two sites typing at the same spot interleave by id, not by arrival
+        let site_a = Uuid::from_u128(1);
+        let site_b = Uuid::from_u128(2);
+        let mut a = RgaDocument::from_text(site_a, "ac");
+        let mut b = RgaDocument::from_text(site_b, "ac");
+        let from_a = a.local_insert(1, "b");
+        let from_b = b.local_insert(1, "B");
+        for op in from_b {
+            a.apply_remote_op(op);
+        }
+        for op in from_a {
+            b.apply_remote_op(op);
+        }
+        assert_eq!(a.text(), b.text());
+        assert_eq!(a.text(), "aBbc");
+    }
+}
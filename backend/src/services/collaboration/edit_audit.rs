@@ -0,0 +1,499 @@
+/**
+ * Edit Audit Log
+ *
+ * Durable record of who made each edit in a collaboration session
+ * Powers per-line "blame" computed by replaying the op history
+ *
+ * Every `snapshot_interval` edits to a file, the full content is stored so
+ * reconstructing a version only replays the ops after the nearest snapshot.
+ * The file's content before its first edit is stored as a snapshot of zero
+ * ops, so replay starts from what was on disk rather than an empty file.
+ *
+ * Memory only holds sessions that are open: a session's history is loaded
+ * from the database on first use and dropped when its last participant leaves.
+ */
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+
+use crate::database::Database;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditRecord {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub participant_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub agent_id: Option<Uuid>,
+    pub file_path: String,
+    pub position: usize,
+    pub length: usize,
+    pub content: String,
+    pub version: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameLine {
+    pub line: usize, // 1-based
+    pub participant_id: Option<Uuid>, // None = unchanged since session start
+    pub user_id: Option<Uuid>,
+    pub agent_id: Option<Uuid>,
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+/// File content after the first `op_count` edits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub session_id: Uuid,
+    pub file_path: String,
+    pub version: usize, // Version of the last edit included
+    pub op_count: usize,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct EditAuditLog {
+    database: Option<Arc<Database>>,
+    edits: Arc<RwLock<HashMap<Uuid, Vec<EditRecord>>>>, // session_id -> edits in arrival order
+    snapshots: Arc<RwLock<HashMap<(Uuid, String), Vec<FileSnapshot>>>>, // (session_id, file) -> oldest first
+    snapshot_interval: usize,
+    loaded: Mutex<HashSet<Uuid>>, // Sessions whose stored history is in memory
+}
+
+impl EditAuditLog {
+    pub fn new(database: Option<Arc<Database>>, snapshot_interval: usize) -> Arc<Self> {
+        Arc::new(Self {
+            database,
+            edits: Arc::new(RwLock::new(HashMap::new())),
+            snapshots: Arc::new(RwLock::new(HashMap::new())),
+            snapshot_interval: snapshot_interval.max(1),
+            loaded: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Load the session's stored edits and snapshots the first time it is touched
+    async fn ensure_loaded(&self, session_id: Uuid) {
+        // Held across the load so concurrent callers don't load twice
+        let mut loaded = self.loaded.lock().await;
+        if !loaded.insert(session_id) {
+            return;
+        }
+        let Some(db) = &self.database else {
+            return;
+        };
+
+        match Self::load_session(db, session_id).await {
+            Ok((edits, snapshots)) => {
+                if !edits.is_empty() {
+                    self.edits.write().await.insert(session_id, edits);
+                }
+                let mut stored = self.snapshots.write().await;
+                for snapshot in snapshots {
+                    stored.entry((session_id, snapshot.file_path.clone()))
+                        .or_insert_with(Vec::new)
+                        .push(snapshot);
+                }
+            }
+            Err(e) => {
+                // Retry on next access rather than serving a partial history
+                loaded.remove(&session_id);
+                tracing::warn!("Failed to load edit history for session {}: {}", session_id, e);
+            }
+        }
+    }
+
+    async fn load_session(db: &Database, session_id: Uuid) -> Result<(Vec<EditRecord>, Vec<FileSnapshot>), sqlx::Error> {
+        let edit_rows = sqlx::query(
+            "SELECT id, participant_id, user_id, agent_id, file_path, position, length, content, version, created_at
+             FROM collaboration_edit_audit
+             WHERE session_id = $1
+             ORDER BY version, created_at"
+        )
+        .bind(session_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        let edits = edit_rows.iter().map(|row| Ok(EditRecord {
+            id: row.try_get("id")?,
+            session_id,
+            participant_id: row.try_get("participant_id")?,
+            user_id: row.try_get("user_id")?,
+            agent_id: row.try_get("agent_id")?,
+            file_path: row.try_get("file_path")?,
+            position: row.try_get::<i32, _>("position")?.max(0) as usize,
+            length: row.try_get::<i32, _>("length")?.max(0) as usize,
+            content: row.try_get("content")?,
+            version: row.try_get::<i32, _>("version")?.max(0) as usize,
+            timestamp: row.try_get("created_at")?,
+        })).collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        let snapshot_rows = sqlx::query(
+            "SELECT file_path, version, op_count, content, created_at
+             FROM collaboration_file_snapshots
+             WHERE session_id = $1
+             ORDER BY op_count, id"
+        )
+        .bind(session_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        let snapshots = snapshot_rows.iter().map(|row| Ok(FileSnapshot {
+            session_id,
+            file_path: row.try_get("file_path")?,
+            version: row.try_get::<i32, _>("version")?.max(0) as usize,
+            op_count: row.try_get::<i32, _>("op_count")?.max(0) as usize,
+            content: row.try_get("content")?,
+            created_at: row.try_get("created_at")?,
+        })).collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        Ok((edits, snapshots))
+    }
+
+    /// Drop a closed session from memory; its history stays in the database
+    pub async fn close_session(&self, session_id: Uuid) {
+        let mut loaded = self.loaded.lock().await;
+        loaded.remove(&session_id);
+        self.edits.write().await.remove(&session_id);
+        self.snapshots.write().await.retain(|(id, _), _| *id != session_id);
+    }
+
+    /// Record an edit with its resolved author
+    ///
+    /// The in-memory history is updated even if the database insert fails, so
+    /// blame for the open session still reflects what was applied.
+    pub async fn record_edit(&self, record: EditRecord) -> anyhow::Result<()> {
+        self.ensure_loaded(record.session_id).await;
+
+        if let Some(db) = &self.database {
+            if let Err(e) = sqlx::query(
+                "INSERT INTO collaboration_edit_audit (
+                    id, session_id, participant_id, user_id, agent_id,
+                    file_path, position, length, content, version, created_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
+            )
+            .bind(record.id)
+            .bind(record.session_id)
+            .bind(record.participant_id)
+            .bind(record.user_id)
+            .bind(record.agent_id)
+            .bind(&record.file_path)
+            .bind(record.position as i32)
+            .bind(record.length as i32)
+            .bind(&record.content)
+            .bind(record.version as i32)
+            .bind(record.timestamp)
+            .execute(db.pool())
+            .await
+            {
+                tracing::warn!("Failed to persist edit {} to {}: {}", record.id, record.file_path, e);
+            }
+        }
+
+        let (session_id, file_path) = (record.session_id, record.file_path.clone());
+        let op_count = {
+            let mut edits = self.edits.write().await;
+            let records = edits.entry(session_id).or_insert_with(Vec::new);
+            records.push(record);
+            records.iter().filter(|r| r.file_path == file_path).count()
+        };
+
+        if op_count % self.snapshot_interval == 0 {
+            self.take_snapshot(session_id, &file_path).await;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the file's pre-edit content has been recorded for this session
+    pub async fn has_base(&self, session_id: Uuid, file_path: &str) -> bool {
+        self.base(session_id, file_path).await.is_some()
+    }
+
+    /// Record the file's content before its first edit in this session; later calls are ignored
+    pub async fn record_base(&self, session_id: Uuid, file_path: &str, content: &str) {
+        if self.has_base(session_id, file_path).await {
+            return;
+        }
+        self.store_snapshot(FileSnapshot {
+            session_id,
+            file_path: file_path.to_string(),
+            version: 0,
+            op_count: 0,
+            content: content.to_string(),
+            created_at: Utc::now(),
+        }).await;
+    }
+
+    async fn base(&self, session_id: Uuid, file_path: &str) -> Option<FileSnapshot> {
+        self.ensure_loaded(session_id).await;
+        let snapshots = self.snapshots.read().await;
+        snapshots.get(&(session_id, file_path.to_string()))?
+            .iter()
+            .find(|s| s.op_count == 0)
+            .cloned()
+    }
+
+    /// Store the file's current content; a failed insert only costs replay time later
+    async fn take_snapshot(&self, session_id: Uuid, file_path: &str) {
+        let edits = self.get_edits(session_id, file_path).await;
+        let Some(last) = edits.last() else {
+            return;
+        };
+        let base = self.latest_snapshot(session_id, file_path, usize::MAX).await;
+        self.store_snapshot(FileSnapshot {
+            session_id,
+            file_path: file_path.to_string(),
+            version: last.version,
+            op_count: edits.len(),
+            content: Self::replay(base.as_ref(), &edits, usize::MAX),
+            created_at: Utc::now(),
+        }).await;
+    }
+
+    async fn store_snapshot(&self, snapshot: FileSnapshot) {
+        let file_path = snapshot.file_path.clone();
+        if let Some(db) = &self.database {
+            if let Err(e) = sqlx::query(
+                "INSERT INTO collaboration_file_snapshots (
+                    session_id, file_path, version, op_count, content, created_at
+                ) VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(snapshot.session_id)
+            .bind(&snapshot.file_path)
+            .bind(snapshot.version as i32)
+            .bind(snapshot.op_count as i32)
+            .bind(&snapshot.content)
+            .bind(snapshot.created_at)
+            .execute(db.pool())
+            .await
+            {
+                tracing::warn!("Failed to persist snapshot of {}: {}", file_path, e);
+            }
+        }
+
+        self.snapshots.write().await
+            .entry((snapshot.session_id, file_path))
+            .or_insert_with(Vec::new)
+            .push(snapshot);
+    }
+
+    /// Newest snapshot at or before `version`
+    async fn latest_snapshot(&self, session_id: Uuid, file_path: &str, version: usize) -> Option<FileSnapshot> {
+        self.ensure_loaded(session_id).await;
+        let snapshots = self.snapshots.read().await;
+        snapshots.get(&(session_id, file_path.to_string()))?
+            .iter()
+            .rev()
+            .find(|s| s.version <= version)
+            .cloned()
+    }
+
+    /// File content as of `version`, starting from the nearest snapshot
+    pub async fn reconstruct(&self, session_id: Uuid, file_path: &str, version: usize) -> String {
+        let edits = self.get_edits(session_id, file_path).await;
+        let base = self.latest_snapshot(session_id, file_path, version).await;
+        Self::replay(base.as_ref(), &edits, version)
+    }
+
+    /// Apply the edits after `base` (or all of them) up to and including `version`
+    pub fn replay(base: Option<&FileSnapshot>, edits: &[EditRecord], version: usize) -> String {
+        let (mut chars, skip): (Vec<char>, usize) = match base {
+            Some(snapshot) => (snapshot.content.chars().collect(), snapshot.op_count),
+            None => (Vec::new(), 0),
+        };
+
+        for edit in edits.iter().skip(skip).take_while(|e| e.version <= version) {
+            let start = edit.position.min(chars.len());
+            let end = (edit.position + edit.length).min(chars.len());
+            chars.splice(start..end, edit.content.chars());
+        }
+
+        chars.into_iter().collect()
+    }
+
+    /// Edits for a file in a session, oldest first
+    pub async fn get_edits(&self, session_id: Uuid, file_path: &str) -> Vec<EditRecord> {
+        self.ensure_loaded(session_id).await;
+        let edits = self.edits.read().await;
+        edits.get(&session_id)
+            .map(|records| {
+                records.iter()
+                    .filter(|r| r.file_path == file_path)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Per-line last author for a file, computed from the op history
+    pub async fn blame(&self, session_id: Uuid, file_path: &str) -> Vec<BlameLine> {
+        let edits = self.get_edits(session_id, file_path).await;
+        let base = self.base(session_id, file_path).await;
+        Self::compute_blame(base.as_ref().map(|b| b.content.as_str()), &edits)
+    }
+
+    /// Replay edits over a per-character author map and fold it into lines
+    ///
+    /// Characters of `base` (the file before the first edit) have no author.
+    pub fn compute_blame(base: Option<&str>, edits: &[EditRecord]) -> Vec<BlameLine> {
+        // Each char remembers the index of the edit that wrote it
+        let mut chars: Vec<(char, Option<usize>)> = base.unwrap_or_default().chars().map(|c| (c, None)).collect();
+
+        for (index, edit) in edits.iter().enumerate() {
+            let start = edit.position.min(chars.len());
+            let end = (edit.position + edit.length).min(chars.len());
+            chars.splice(start..end, edit.content.chars().map(|c| (c, Some(index))));
+        }
+
+        let mut lines = Vec::new();
+        let mut current: Option<usize> = None;
+        let push_line = |lines: &mut Vec<BlameLine>, author: Option<usize>| {
+            let edit = author.map(|i| &edits[i]);
+            lines.push(BlameLine {
+                line: lines.len() + 1,
+                participant_id: edit.map(|e| e.participant_id),
+                user_id: edit.and_then(|e| e.user_id),
+                agent_id: edit.and_then(|e| e.agent_id),
+                edited_at: edit.map(|e| e.timestamp),
+            });
+        };
+
+        for (c, author) in &chars {
+            // Later edits win within a line
+            current = match (current, *author) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+            if *c == '\n' {
+                push_line(&mut lines, current);
+                current = None;
+            }
+        }
+        if !chars.is_empty() && chars.last().map(|(c, _)| *c) != Some('\n') {
+            push_line(&mut lines, current);
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(participant_id: Uuid, user_id: Option<Uuid>, position: usize, length: usize, content: &str) -> EditRecord {
+        EditRecord {
+            id: Uuid::new_v4(),
+            session_id: Uuid::nil(),
+            participant_id,
+            user_id,
+            agent_id: None,
+            file_path: "src/main.rs".to_string(),
+            position,
+            length,
+            content: content.to_string(),
+            version: 1,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_edit_author_is_recorded_and_surfaced_in_blame() {
+        let log = EditAuditLog::new(None, 100);
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+        let alice_user = Some(Uuid::new_v4());
+
+        log.record_edit(edit(alice, alice_user, 0, 0, "fn main() {\n}\n")).await.unwrap();
+        // Bob inserts a new second line
+        log.record_edit(edit(bob, None, 12, 0, "    run();\n")).await.unwrap();
+
+        let recorded = log.get_edits(Uuid::nil(), "src/main.rs").await;
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].user_id, alice_user);
+
+        let blame = log.blame(Uuid::nil(), "src/main.rs").await;
+        assert_eq!(blame.len(), 3);
+        assert_eq!(blame[0].participant_id, Some(alice));
+        assert_eq!(blame[0].user_id, alice_user);
+        assert_eq!(blame[1].participant_id, Some(bob));
+        assert_eq!(blame[2].participant_id, Some(alice));
+    }
+
+    #[tokio::test]
+    async fn test_reconstruct_starts_from_nearest_snapshot() {
+        let log = EditAuditLog::new(None, 3);
+        let alice = Uuid::new_v4();
+        let ops = [(0, 0, "a"), (1, 0, "b"), (2, 0, "c"), (0, 1, "A"), (3, 0, "d"), (4, 0, "e"), (5, 0, "f")];
+        for (version, (position, length, content)) in ops.into_iter().enumerate() {
+            let mut record = edit(alice, None, position, length, content);
+            record.version = version + 1;
+            log.record_edit(record).await.unwrap();
+        }
+
+        let snapshot = log.latest_snapshot(Uuid::nil(), "src/main.rs", 5).await.unwrap();
+        assert_eq!((snapshot.version, snapshot.op_count), (3, 3));
+        assert_eq!(snapshot.content, "abc");
+        assert_eq!(log.latest_snapshot(Uuid::nil(), "src/main.rs", 6).await.unwrap().op_count, 6);
+
+        // Snapshot at version 3 plus ops 4 and 5
+        assert_eq!(log.reconstruct(Uuid::nil(), "src/main.rs", 5).await, "Abcd");
+        assert_eq!(log.reconstruct(Uuid::nil(), "src/main.rs", 7).await, "Abcdef");
+        assert_eq!(log.reconstruct(Uuid::nil(), "src/main.rs", 2).await, "ab");
+
+        let edits = log.get_edits(Uuid::nil(), "src/main.rs").await;
+        assert_eq!(EditAuditLog::replay(None, &edits, 5), "Abcd");
+    }
+
+    #[tokio::test]
+    async fn test_replay_and_blame_start_from_the_file_before_its_first_edit() {
+        let log = EditAuditLog::new(None, 2);
+        let alice = Uuid::new_v4();
+        log.record_base(Uuid::nil(), "src/main.rs", "fn main() {\n}\n").await;
+        // Only the first base counts
+        log.record_base(Uuid::nil(), "src/main.rs", "something else").await;
+
+        let mut insert = edit(alice, None, 12, 0, "    run();\n");
+        insert.version = 1;
+        log.record_edit(insert).await.unwrap();
+        let mut append = edit(alice, None, 25, 0, "// end\n");
+        append.version = 2;
+        log.record_edit(append).await.unwrap();
+
+        assert_eq!(log.reconstruct(Uuid::nil(), "src/main.rs", 1).await, "fn main() {\n    run();\n}\n");
+        // The interval snapshot after two edits includes the base content too
+        assert_eq!(log.reconstruct(Uuid::nil(), "src/main.rs", 2).await, "fn main() {\n    run();\n}\n// end\n");
+
+        let blame = log.blame(Uuid::nil(), "src/main.rs").await;
+        assert_eq!(blame.len(), 4);
+        assert_eq!(blame[0].participant_id, None);
+        assert_eq!(blame[1].participant_id, Some(alice));
+        assert_eq!(blame[2].participant_id, None);
+        assert_eq!(blame[3].participant_id, Some(alice));
+    }
+
+    #[tokio::test]
+    async fn test_closing_a_session_evicts_its_history() {
+        let log = EditAuditLog::new(None, 2);
+        let other = Uuid::new_v4();
+        log.record_base(Uuid::nil(), "src/main.rs", "").await;
+        for _ in 0..2 {
+            log.record_edit(edit(Uuid::new_v4(), None, 0, 0, "x")).await.unwrap();
+        }
+        let mut kept = edit(Uuid::new_v4(), None, 0, 0, "y");
+        kept.session_id = other;
+        log.record_edit(kept).await.unwrap();
+
+        log.close_session(Uuid::nil()).await;
+
+        assert!(log.get_edits(Uuid::nil(), "src/main.rs").await.is_empty());
+        assert!(!log.has_base(Uuid::nil(), "src/main.rs").await);
+        assert!(log.snapshots.read().await.keys().all(|(id, _)| *id != Uuid::nil()));
+        assert_eq!(log.get_edits(other, "src/main.rs").await.len(), 1);
+    }
+}
@@ -7,13 +7,21 @@
 pub mod websocket;
 pub mod session;
 pub mod conflict;
+pub mod crdt;
 pub mod presence;
 pub mod agent;
 pub mod codeintel;
+pub mod edit_audit;
+pub mod operation_log;
+pub mod rate_limit;
+pub mod broadcast;
 
 pub use websocket::CollaborationWebSocket;
 pub use session::SessionManager;
-pub use conflict::ConflictResolver;
+pub use conflict::{ConflictResolver, ConflictStrategy};
+pub use crdt::CrdtResolver;
 pub use presence::PresenceTracker;
 pub use agent::AgentCollaborator;
 pub use codeintel::CodeIntelligenceSync;
+pub use edit_audit::EditAuditLog;
+pub use operation_log::OperationLog;
@@ -0,0 +1,228 @@
+/**
+ * Operation Log
+ *
+ * Append-only, per-session history of edit operations so a client that
+ * reconnects can catch up on what it missed. The server stamps every edit
+ * with the session's next version; a rejoining client sends the last version
+ * it applied and gets the later operations replayed in order. Only the most
+ * recent operations are kept in memory; past that the client has to reload
+ * the file instead.
+ */
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use serde::Serialize;
+use sqlx::Row;
+
+use super::conflict::EditOperation;
+use crate::database::Database;
+
+/// What a rejoining client needs to catch up
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Replay {
+    /// Missed operations, oldest first; empty if the client is up to date
+    Operations { operations: Vec<EditOperation>, current_version: usize },
+    /// Too much was missed to replay; reload file contents
+    ResyncRequired { current_version: usize },
+}
+
+#[derive(Debug, Default)]
+struct SessionLog {
+    operations: VecDeque<EditOperation>,
+    current_version: usize, // Version of the newest operation; 0 before any edit
+}
+
+pub struct OperationLog {
+    database: Option<Arc<Database>>,
+    sessions: Arc<RwLock<HashMap<Uuid, SessionLog>>>,
+    max_len: usize,
+}
+
+impl OperationLog {
+    pub fn new(database: Option<Arc<Database>>, max_len: usize) -> Arc<Self> {
+        Arc::new(Self {
+            database,
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            max_len: max_len.max(1),
+        })
+    }
+
+    /// Stamp `operation` with the session's next version and append it
+    ///
+    /// The client's `version` is kept as `parent_version`, the version it
+    /// edited against. Returns the operation as stored.
+    pub async fn append(&self, mut operation: EditOperation) -> anyhow::Result<EditOperation> {
+        let session_id = operation.session_id;
+        let known = self.sessions.read().await.contains_key(&session_id);
+        let persisted_version = if known { 0 } else { self.persisted_version(session_id).await };
+
+        let mut sessions = self.sessions.write().await;
+        let log = sessions.entry(session_id).or_insert_with(|| SessionLog {
+            current_version: persisted_version,
+            ..SessionLog::default()
+        });
+        log.current_version += 1;
+        operation.parent_version = Some(operation.version);
+        operation.version = log.current_version;
+
+        if let Some(db) = &self.database {
+            // Written under the lock so rows land in version order
+            sqlx::query(
+                "INSERT INTO collaboration_operations (id, session_id, version, operation, created_at)
+                 VALUES ($1, $2, $3, $4, $5)"
+            )
+            .bind(operation.id)
+            .bind(session_id)
+            .bind(operation.version as i64)
+            .bind(serde_json::to_value(&operation)?)
+            .bind(operation.timestamp)
+            .execute(db.pool())
+            .await
+            .map_err(|e| {
+                log.current_version -= 1;
+                anyhow::anyhow!("Failed to persist edit operation: {}", e)
+            })?;
+        }
+
+        log.operations.push_back(operation.clone());
+        while log.operations.len() > self.max_len {
+            log.operations.pop_front();
+        }
+        Ok(operation)
+    }
+
+    /// Newest version in a session, 0 if nothing was edited
+    pub async fn current_version(&self, session_id: Uuid) -> usize {
+        if let Some(log) = self.sessions.read().await.get(&session_id) {
+            return log.current_version;
+        }
+        self.persisted_version(session_id).await
+    }
+
+    /// Operations after `since_version`, or a resync if they are no longer all available
+    pub async fn since(&self, session_id: Uuid, since_version: usize) -> Replay {
+        let current_version = self.current_version(session_id).await;
+        if since_version >= current_version {
+            return Replay::Operations { operations: Vec::new(), current_version };
+        }
+        if current_version - since_version > self.max_len {
+            return Replay::ResyncRequired { current_version };
+        }
+
+        {
+            let sessions = self.sessions.read().await;
+            if let Some(log) = sessions.get(&session_id) {
+                let oldest = log.operations.front().map(|op| op.version).unwrap_or(usize::MAX);
+                if oldest <= since_version + 1 {
+                    let operations = log.operations.iter()
+                        .filter(|op| op.version > since_version)
+                        .cloned()
+                        .collect();
+                    return Replay::Operations { operations, current_version };
+                }
+            }
+        }
+
+        // Not in memory (e.g. after a restart); the table may still have them
+        match self.load_since(session_id, since_version).await {
+            Some(operations) if operations.first().map(|op| op.version) == Some(since_version + 1) => {
+                Replay::Operations { operations, current_version }
+            }
+            _ => Replay::ResyncRequired { current_version },
+        }
+    }
+
+    /// Forget a session's in-memory history
+    pub async fn close_session(&self, session_id: Uuid) {
+        self.sessions.write().await.remove(&session_id);
+    }
+
+    async fn persisted_version(&self, session_id: Uuid) -> usize {
+        let Some(db) = &self.database else {
+            return 0;
+        };
+        match sqlx::query("SELECT COALESCE(MAX(version), 0) AS version FROM collaboration_operations WHERE session_id = $1")
+            .bind(session_id)
+            .fetch_one(db.pool())
+            .await
+        {
+            Ok(row) => row.try_get::<i64, _>("version").unwrap_or(0) as usize,
+            Err(e) => {
+                tracing::warn!("Failed to read operation log version for session {}: {}", session_id, e);
+                0
+            }
+        }
+    }
+
+    async fn load_since(&self, session_id: Uuid, since_version: usize) -> Option<Vec<EditOperation>> {
+        let db = self.database.as_ref()?;
+        let rows = sqlx::query(
+            "SELECT operation FROM collaboration_operations
+             WHERE session_id = $1 AND version > $2
+             ORDER BY version
+             LIMIT $3"
+        )
+        .bind(session_id)
+        .bind(since_version as i64)
+        .bind(self.max_len as i64)
+        .fetch_all(db.pool())
+        .await
+        .map_err(|e| tracing::warn!("Failed to load edit operations for session {}: {}", session_id, e))
+        .ok()?;
+
+        rows.iter()
+            .map(|row| row.try_get::<serde_json::Value, _>("operation").ok()
+                .and_then(|value| serde_json::from_value(value).ok()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::conflict::OperationType;
+
+    fn insert(session_id: Uuid, position: usize, content: &str, client_version: usize) -> EditOperation {
+        EditOperation {
+            id: Uuid::new_v4(),
+            session_id,
+            participant_id: Uuid::nil(),
+            file_path: "src/main.rs".to_string(),
+            operation_type: OperationType::Insert,
+            position,
+            length: 0,
+            content: content.to_string(),
+            version: client_version,
+            parent_version: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    fn versions(replay: &Replay) -> Vec<usize> {
+        match replay {
+            Replay::Operations { operations, .. } => operations.iter().map(|op| op.version).collect(),
+            Replay::ResyncRequired { .. } => panic!("expected operations, got {:?}", replay),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replays_missed_operations_until_the_gap_is_too_large() {
+        let log = OperationLog::new(None, 3);
+        let session_id = Uuid::new_v4();
+
+        let first = log.append(insert(session_id, 0, "a", 0)).await.unwrap();
+        assert_eq!((first.version, first.parent_version), (1, Some(0)));
+        log.append(insert(session_id, 1, "b", 1)).await.unwrap();
+        log.append(insert(session_id, 2, "c", 2)).await.unwrap();
+
+        assert_eq!(versions(&log.since(session_id, 1).await), vec![2, 3]);
+        assert_eq!(versions(&log.since(session_id, 3).await), Vec::<usize>::new());
+
+        // Version 1 falls out of the capped log, so a client at 0 must resync
+        log.append(insert(session_id, 3, "d", 3)).await.unwrap();
+        assert_eq!(versions(&log.since(session_id, 1).await), vec![2, 3, 4]);
+        assert!(matches!(log.since(session_id, 0).await, Replay::ResyncRequired { current_version: 4 }));
+    }
+}
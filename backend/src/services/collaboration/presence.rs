@@ -3,6 +3,9 @@
  * 
  * Tracks user and agent presence in sessions
  * Compatible with Phase 1, 2, 3
+ *
+ * Participants that stop sending anything (including heartbeats) are marked
+ * away after an idle window and dropped after a longer one.
  */
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -22,6 +25,23 @@ pub struct Presence {
     pub cursor_position: Option<serde_json::Value>,
     pub active_file: Option<String>,
     pub last_active: DateTime<Utc>,
+    /// Set when the idle sweep, not the participant, made this presence Away
+    #[serde(skip)]
+    idle: bool,
+}
+
+impl Presence {
+    fn is(&self, user_id: Option<Uuid>, agent_id: Option<Uuid>) -> bool {
+        (self.user_id == user_id && user_id.is_some()) ||
+        (self.agent_id == agent_id && agent_id.is_some())
+    }
+}
+
+/// A presence the tracker changed on its own, to be announced to the session
+#[derive(Debug, Clone, Serialize)]
+pub struct PresenceChange {
+    pub presence: Presence,
+    pub removed: bool,
 }
 
 pub struct PresenceTracker {
@@ -44,18 +64,19 @@ impl PresenceTracker {
         cursor_position: Option<serde_json::Value>,
         active_file: Option<String>,
     ) {
+        if user_id.is_none() && agent_id.is_none() {
+            return;
+        }
         let mut presence_map = self.presence.write().await;
         let presences = presence_map.entry(session_id).or_insert_with(Vec::new);
 
         // Update or add presence
-        if let Some(p) = presences.iter_mut().find(|p| {
-            (p.user_id == user_id && user_id.is_some()) ||
-            (p.agent_id == agent_id && agent_id.is_some())
-        }) {
+        if let Some(p) = presences.iter_mut().find(|p| p.is(user_id, agent_id)) {
             p.status = status;
             p.cursor_position = cursor_position;
             p.active_file = active_file;
             p.last_active = Utc::now();
+            p.idle = false;
         } else {
             presences.push(Presence {
                 user_id,
@@ -65,10 +86,62 @@ impl PresenceTracker {
                 cursor_position,
                 active_file,
                 last_active: Utc::now(),
+                idle: false,
             });
         }
     }
 
+    /// Heartbeat: refresh `last_active` without touching anything else
+    ///
+    /// A participant the idle sweep had marked away comes back online; that
+    /// change is returned so it can be announced.
+    pub async fn touch(
+        &self,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+        agent_id: Option<Uuid>,
+    ) -> Option<PresenceChange> {
+        let mut presence_map = self.presence.write().await;
+        let presence = presence_map.get_mut(&session_id)?
+            .iter_mut()
+            .find(|p| p.is(user_id, agent_id))?;
+        presence.last_active = Utc::now();
+        if !presence.idle {
+            return None;
+        }
+        presence.idle = false;
+        presence.status = ParticipantStatus::Online;
+        Some(PresenceChange { presence: presence.clone(), removed: false })
+    }
+
+    /// Mark presences idle past `away_after` as away and drop those idle past `remove_after`
+    pub async fn expire_idle(
+        &self,
+        away_after: chrono::Duration,
+        remove_after: chrono::Duration,
+    ) -> Vec<PresenceChange> {
+        let now = Utc::now();
+        let mut changes = Vec::new();
+        let mut presence_map = self.presence.write().await;
+        for presences in presence_map.values_mut() {
+            presences.retain_mut(|p| {
+                let idle_for = now - p.last_active;
+                if idle_for >= remove_after {
+                    changes.push(PresenceChange { presence: p.clone(), removed: true });
+                    return false;
+                }
+                if idle_for >= away_after && p.status == ParticipantStatus::Online {
+                    p.status = ParticipantStatus::Away;
+                    p.idle = true;
+                    changes.push(PresenceChange { presence: p.clone(), removed: false });
+                }
+                true
+            });
+        }
+        presence_map.retain(|_, presences| !presences.is_empty());
+        changes
+    }
+
     pub async fn get_presences(&self, session_id: Uuid) -> Vec<Presence> {
         let presence_map = self.presence.read().await;
         presence_map.get(&session_id).cloned().unwrap_or_default()
@@ -82,9 +155,49 @@ impl PresenceTracker {
     ) {
         let mut presence_map = self.presence.write().await;
         if let Some(presences) = presence_map.get_mut(&session_id) {
-            presences.retain(|p| {
-                p.user_id != user_id || p.agent_id != agent_id
-            });
+            presences.retain(|p| !p.is(user_id, agent_id));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn age(tracker: &PresenceTracker, session_id: Uuid, user_id: Option<Uuid>, minutes: i64) {
+        let mut map = tracker.presence.write().await;
+        let p = map.get_mut(&session_id).unwrap().iter_mut().find(|p| p.user_id == user_id).unwrap();
+        p.last_active = Utc::now() - chrono::Duration::minutes(minutes);
+    }
+
+    #[tokio::test]
+    async fn test_idle_presence_goes_away_then_is_removed() {
+        let tracker = PresenceTracker::new();
+        let session_id = Uuid::new_v4();
+        let (quiet, active) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        for user_id in [quiet, active] {
+            tracker.update_presence(session_id, user_id, None, ParticipantStatus::Online, None, None).await;
+        }
+        let (away, gone) = (chrono::Duration::minutes(2), chrono::Duration::minutes(10));
+
+        age(&tracker, session_id, quiet, 3).await;
+        let changes = tracker.expire_idle(away, gone).await;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].presence.user_id, quiet);
+        assert_eq!(changes[0].presence.status, ParticipantStatus::Away);
+        assert!(!changes[0].removed);
+        assert!(tracker.expire_idle(away, gone).await.is_empty());
+
+        // A heartbeat brings it back
+        let back = tracker.touch(session_id, quiet, None).await.unwrap();
+        assert_eq!(back.presence.status, ParticipantStatus::Online);
+        assert!(tracker.touch(session_id, active, None).await.is_none());
+
+        age(&tracker, session_id, quiet, 11).await;
+        let changes = tracker.expire_idle(away, gone).await;
+        assert!(changes.len() == 1 && changes[0].removed);
+        let remaining = tracker.get_presences(session_id).await;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].user_id, active);
+    }
+}
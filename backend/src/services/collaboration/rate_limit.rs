@@ -0,0 +1,111 @@
+/**
+ * Collaboration Message Rate Limiting
+ *
+ * Token bucket per WebSocket connection. Every message a participant sends is
+ * fanned out to the whole session, so one flooding client multiplies load on
+ * everyone; past its budget, messages are dropped until the bucket refills.
+ */
+use std::collections::HashMap;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Outcome of spending one message from a participant's budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allowed,
+    /// Over budget; `first` is set for the first message dropped in a row, so
+    /// the client is told once rather than once per dropped message
+    Throttled { first: bool },
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    throttled: bool,
+}
+
+pub struct MessageRateLimiter {
+    buckets: Mutex<HashMap<Uuid, Bucket>>,
+    per_second: f64,
+    burst: f64,
+}
+
+impl MessageRateLimiter {
+    /// `per_second` messages sustained, up to `burst` at once; 0 per second disables limiting
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            per_second: per_second as f64,
+            burst: burst.max(1) as f64,
+        }
+    }
+
+    pub async fn check(&self, participant_id: Uuid) -> RateDecision {
+        self.check_at(participant_id, Instant::now()).await
+    }
+
+    async fn check_at(&self, participant_id: Uuid, now: Instant) -> RateDecision {
+        if self.per_second <= 0.0 {
+            return RateDecision::Allowed;
+        }
+        let mut buckets = self.buckets.lock().await;
+        let bucket = buckets.entry(participant_id).or_insert_with(|| Bucket {
+            tokens: self.burst,
+            refilled_at: now,
+            throttled: false,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.throttled = false;
+            RateDecision::Allowed
+        } else {
+            let first = !bucket.throttled;
+            bucket.throttled = true;
+            RateDecision::Throttled { first }
+        }
+    }
+
+    /// Forget a connection's bucket once it disconnects
+    pub async fn remove(&self, participant_id: Uuid) {
+        self.buckets.lock().await.remove(&participant_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_burst_beyond_budget_is_throttled_until_refill() {
+        let limiter = MessageRateLimiter::new(10, 5);
+        let (flooder, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            assert_eq!(limiter.check_at(flooder, start).await, RateDecision::Allowed);
+        }
+        assert_eq!(limiter.check_at(flooder, start).await, RateDecision::Throttled { first: true });
+        assert_eq!(limiter.check_at(flooder, start).await, RateDecision::Throttled { first: false });
+
+        // Budgets are per connection
+        assert_eq!(limiter.check_at(other, start).await, RateDecision::Allowed);
+
+        // 10 per second refills one message every 100ms
+        let later = start + Duration::from_millis(150);
+        assert_eq!(limiter.check_at(flooder, later).await, RateDecision::Allowed);
+        assert_eq!(limiter.check_at(flooder, later).await, RateDecision::Throttled { first: true });
+
+        let unlimited = MessageRateLimiter::new(0, 1);
+        for _ in 0..100 {
+            assert_eq!(unlimited.check_at(flooder, start).await, RateDecision::Allowed);
+        }
+    }
+}
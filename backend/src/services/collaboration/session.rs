@@ -4,6 +4,7 @@
  * Manages collaboration sessions - compatible with Phase 1, 2, 3
  */
 use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
@@ -56,8 +57,47 @@ pub enum ParticipantStatus {
     Idle,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectPathError {
+    #[error("Project path does not exist: {0}")]
+    NotFound(String),
+
+    #[error("Project path is outside the workspace: {0}")]
+    OutsideWorkspace(String),
+}
+
+/// Why a participant may not edit a session's files
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum EditDenied {
+    #[error("Session not found")]
+    SessionNotFound,
+
+    #[error("Session has expired")]
+    SessionExpired,
+
+    #[error("Not a participant in this session")]
+    NotAParticipant,
+
+    #[error("Viewers cannot edit files")]
+    ReadOnly,
+}
+
+impl Participant {
+    /// Whether this is the participant identified by `user_id` or `agent_id`
+    pub fn is(&self, user_id: Option<Uuid>, agent_id: Option<Uuid>) -> bool {
+        (user_id.is_some() && self.user_id == user_id) || (agent_id.is_some() && self.agent_id == agent_id)
+    }
+}
+
+impl Session {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+}
+
 pub struct SessionManager {
     database: Option<Arc<Database>>,
+    workspace_root: PathBuf,
     sessions: Arc<RwLock<HashMap<Uuid, Session>>>,
     participants: Arc<RwLock<HashMap<Uuid, Vec<Participant>>>>,
     audit_logger: Arc<AuditLogger>,
@@ -67,9 +107,12 @@ impl SessionManager {
     pub fn new(
         database: Option<Arc<Database>>,
         audit_logger: Arc<AuditLogger>,
+        workspace_root: PathBuf,
     ) -> Arc<Self> {
+        let workspace_root = workspace_root.canonicalize().unwrap_or(workspace_root);
         Arc::new(Self {
             database,
+            workspace_root,
             sessions: Arc::new(RwLock::new(HashMap::new())),
             participants: Arc::new(RwLock::new(HashMap::new())),
             audit_logger,
@@ -81,7 +124,12 @@ impl SessionManager {
         name: String,
         owner_id: Uuid,
         project_path: String,
+        settings: serde_json::Value,
     ) -> anyhow::Result<Session> {
+        let project_path = self.resolve_project_path(&project_path)?
+            .to_string_lossy()
+            .into_owned();
+
         // Generate share token
         let share_token = self.generate_share_token();
 
@@ -90,7 +138,7 @@ impl SessionManager {
             name,
             owner_id,
             project_path,
-            settings: serde_json::json!({}),
+            settings,
             created_at: Utc::now(),
             updated_at: Utc::now(),
             expires_at: None,
@@ -140,6 +188,31 @@ impl SessionManager {
         Ok(session)
     }
 
+    /// Canonical form of a project path, which must exist inside the workspace root.
+    /// Relative paths are taken from the workspace root.
+    pub fn resolve_project_path(&self, project_path: &str) -> Result<PathBuf, ProjectPathError> {
+        let canonical = self.workspace_root
+            .join(project_path)
+            .canonicalize()
+            .map_err(|_| ProjectPathError::NotFound(project_path.to_string()))?;
+
+        if !canonical.starts_with(&self.workspace_root) {
+            return Err(ProjectPathError::OutsideWorkspace(project_path.to_string()));
+        }
+        Ok(canonical)
+    }
+
+    /// Location of a session file, or None if the session is unknown or the
+    /// path would leave the session's project directory
+    pub async fn resolve_session_file(&self, session_id: Uuid, file_path: &str) -> Option<PathBuf> {
+        let session = self.get_session(session_id).await?;
+        let relative = Path::new(file_path);
+        if !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            return None;
+        }
+        Some(Path::new(&session.project_path).join(relative))
+    }
+
     fn generate_share_token(&self) -> String {
         use rand::Rng;
         const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
@@ -186,10 +259,9 @@ impl SessionManager {
 
         // Fallback to memory
         let sessions = self.sessions.read().await;
-        sessions.values().find(|s| {
-            // In-memory sessions don't have share_token, so we'd need to add it
-            false
-        }).cloned()
+        sessions.values()
+            .find(|s| !token.is_empty() && s.share_token.as_deref() == Some(token))
+            .cloned()
     }
 
     pub async fn get_session(&self, session_id: Uuid) -> Option<Session> {
@@ -234,11 +306,34 @@ impl SessionManager {
         sessions.get(&session_id).cloned()
     }
 
+    /// Join a session with a role the server picks
+    ///
+    /// The session owner joins as Owner, a user or agent already in the
+    /// session keeps its role, and anyone else joins as a Viewer.
     pub async fn join_session(
         &self,
         session_id: Uuid,
         user_id: Option<Uuid>,
         agent_id: Option<Uuid>,
+    ) -> anyhow::Result<Participant> {
+        let session = self.get_session(session_id).await
+            .ok_or_else(|| anyhow::anyhow!("Session not found"))?;
+        let role = if user_id == Some(session.owner_id) {
+            ParticipantRole::Owner
+        } else {
+            ParticipantRole::Viewer
+        };
+        self.add_participant(session_id, user_id, agent_id, role).await
+    }
+
+    /// Add a participant with a server-chosen role, e.g. an agent assigned to the session
+    ///
+    /// A participant that is already in the session keeps the role it has.
+    pub async fn add_participant(
+        &self,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+        agent_id: Option<Uuid>,
         role: ParticipantRole,
     ) -> anyhow::Result<Participant> {
         // Verify session exists
@@ -246,6 +341,10 @@ impl SessionManager {
             return Err(anyhow::anyhow!("Session not found"));
         }
 
+        if let Some(existing) = self.find_participant(session_id, user_id, agent_id).await {
+            return Ok(existing);
+        }
+
         let role_str = match role {
             ParticipantRole::Owner => "owner",
             ParticipantRole::Editor => "editor",
@@ -286,12 +385,14 @@ impl SessionManager {
             .map_err(|e| anyhow::anyhow!("Failed to join session in database: {}", e))?;
         }
 
-        // Add to participants
+        // Add to participants; a concurrent join of the same participant keeps the first role
         {
             let mut participants = self.participants.write().await;
-            participants.entry(session_id)
-                .or_insert_with(Vec::new)
-                .push(participant.clone());
+            let participants_list = participants.entry(session_id).or_insert_with(Vec::new);
+            if let Some(existing) = participants_list.iter().find(|p| p.is(user_id, agent_id)) {
+                return Ok(existing.clone());
+            }
+            participants_list.push(participant.clone());
         }
 
         // Log audit event
@@ -315,16 +416,37 @@ impl SessionManager {
         user_id: Option<Uuid>,
         agent_id: Option<Uuid>,
     ) -> anyhow::Result<()> {
+        if user_id.is_none() && agent_id.is_none() {
+            return Err(anyhow::anyhow!("Cannot leave a session without a user or agent id"));
+        }
+
         // Remove from participants
         {
             let mut participants = self.participants.write().await;
             if let Some(participants_list) = participants.get_mut(&session_id) {
-                participants_list.retain(|p| {
-                    p.user_id != user_id || p.agent_id != agent_id
-                });
+                participants_list.retain(|p| !p.is(user_id, agent_id));
+                if participants_list.is_empty() {
+                    participants.remove(&session_id);
+                }
             }
         }
 
+        if let Some(db) = &self.database {
+            sqlx::query!(
+                r#"
+                DELETE FROM collaboration_participants
+                WHERE session_id = $1
+                  AND (($2::uuid IS NOT NULL AND user_id = $2) OR ($3::uuid IS NOT NULL AND agent_id = $3))
+                "#,
+                session_id,
+                user_id,
+                agent_id
+            )
+            .execute(db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to leave session in database: {}", e))?;
+        }
+
         // Log audit event
         self.audit_logger.log_violation(
             format!("Left session: {}", session_id),
@@ -340,6 +462,48 @@ impl SessionManager {
         Ok(())
     }
 
+    /// A joined participant, matched by user or agent id
+    async fn find_participant(
+        &self,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+        agent_id: Option<Uuid>,
+    ) -> Option<Participant> {
+        let participants = self.participants.read().await;
+        participants.get(&session_id)?
+            .iter()
+            .find(|p| p.is(user_id, agent_id))
+            .cloned()
+    }
+
+    /// Role of a joined participant, matched by user or agent id
+    pub async fn participant_role(
+        &self,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+        agent_id: Option<Uuid>,
+    ) -> Option<ParticipantRole> {
+        self.find_participant(session_id, user_id, agent_id).await.map(|p| p.role)
+    }
+
+    /// Check that a participant may edit files in a live session
+    pub async fn authorize_edit(
+        &self,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+        agent_id: Option<Uuid>,
+    ) -> Result<ParticipantRole, EditDenied> {
+        let session = self.get_session(session_id).await.ok_or(EditDenied::SessionNotFound)?;
+        if session.is_expired() {
+            return Err(EditDenied::SessionExpired);
+        }
+        match self.participant_role(session_id, user_id, agent_id).await {
+            None => Err(EditDenied::NotAParticipant),
+            Some(ParticipantRole::Viewer) => Err(EditDenied::ReadOnly),
+            Some(role) => Ok(role),
+        }
+    }
+
     pub async fn get_participants(&self, session_id: Uuid) -> Vec<Participant> {
         let participants = self.participants.read().await;
         participants.get(&session_id).cloned().unwrap_or_default()
@@ -368,3 +532,137 @@ impl SessionManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_project_path_is_canonicalized_inside_workspace() {
+        let workspace = std::env::temp_dir().join(format!("bloop-workspace-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("app/src")).unwrap();
+        let manager = SessionManager::new(None, Arc::new(AuditLogger::new(100)), workspace.clone());
+
+        let escape = manager.create_session("escape".to_string(), Uuid::new_v4(), "app/../../..".to_string(), serde_json::json!({})).await;
+        assert!(matches!(
+            escape.unwrap_err().downcast_ref::<ProjectPathError>(),
+            Some(ProjectPathError::OutsideWorkspace(_))
+        ));
+
+        let session = manager
+            .create_session("app".to_string(), Uuid::new_v4(), "app/src/..".to_string(), serde_json::json!({}))
+            .await
+            .unwrap();
+        let expected = workspace.join("app").canonicalize().unwrap();
+        assert_eq!(session.project_path, expected.to_string_lossy());
+
+        assert_eq!(
+            manager.resolve_session_file(session.id, "src/main.rs").await,
+            Some(expected.join("src/main.rs"))
+        );
+        assert_eq!(manager.resolve_session_file(session.id, "../secrets.env").await, None);
+
+        std::fs::remove_dir_all(&workspace).ok();
+    }
+
+    #[tokio::test]
+    async fn test_viewers_and_expired_sessions_cannot_edit() {
+        let workspace = std::env::temp_dir().join(format!("bloop-workspace-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        let manager = SessionManager::new(None, Arc::new(AuditLogger::new(100)), workspace.clone());
+        let session = manager
+            .create_session("roles".to_string(), Uuid::new_v4(), ".".to_string(), serde_json::json!({}))
+            .await
+            .unwrap();
+
+        let viewer = Some(Uuid::new_v4());
+        let editor = Some(Uuid::new_v4());
+        manager.join_session(session.id, viewer, None).await.unwrap();
+        manager.add_participant(session.id, editor, None, ParticipantRole::Editor).await.unwrap();
+
+        assert_eq!(manager.authorize_edit(session.id, viewer, None).await, Err(EditDenied::ReadOnly));
+        assert_eq!(manager.authorize_edit(session.id, editor, None).await, Ok(ParticipantRole::Editor));
+        assert_eq!(
+            manager.authorize_edit(session.id, Some(Uuid::new_v4()), None).await,
+            Err(EditDenied::NotAParticipant)
+        );
+
+        // Once the session expires nobody can edit
+        manager.sessions.write().await.get_mut(&session.id).unwrap().expires_at =
+            Some(Utc::now() - chrono::Duration::minutes(1));
+        assert_eq!(manager.authorize_edit(session.id, editor, None).await, Err(EditDenied::SessionExpired));
+
+        std::fs::remove_dir_all(&workspace).ok();
+    }
+
+    #[tokio::test]
+    async fn test_joining_never_raises_a_role() {
+        let workspace = std::env::temp_dir().join(format!("bloop-workspace-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        let manager = SessionManager::new(None, Arc::new(AuditLogger::new(100)), workspace.clone());
+        let owner = Uuid::new_v4();
+        let session = manager
+            .create_session("roles".to_string(), owner, ".".to_string(), serde_json::json!({}))
+            .await
+            .unwrap();
+
+        let owner_joined = manager.join_session(session.id, Some(owner), None).await.unwrap();
+        assert_eq!(owner_joined.role, ParticipantRole::Owner);
+        let viewer = Some(Uuid::new_v4());
+        assert_eq!(manager.join_session(session.id, viewer, None).await.unwrap().role, ParticipantRole::Viewer);
+
+        // Joining again, or being re-added with a higher role, keeps the role already held
+        manager.join_session(session.id, viewer, None).await.unwrap();
+        manager.add_participant(session.id, viewer, None, ParticipantRole::Editor).await.unwrap();
+        assert_eq!(manager.participant_role(session.id, viewer, None).await, Some(ParticipantRole::Viewer));
+        assert_eq!(manager.authorize_edit(session.id, viewer, None).await, Err(EditDenied::ReadOnly));
+        assert_eq!(manager.get_participants(session.id).await.len(), 2);
+
+        std::fs::remove_dir_all(&workspace).ok();
+    }
+
+    #[tokio::test]
+    async fn test_leave_removes_the_joined_participant() {
+        let workspace = std::env::temp_dir().join(format!("bloop-workspace-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        let manager = SessionManager::new(None, Arc::new(AuditLogger::new(100)), workspace.clone());
+        let session = manager
+            .create_session("leave".to_string(), Uuid::new_v4(), ".".to_string(), serde_json::json!({}))
+            .await
+            .unwrap();
+
+        let user = Some(Uuid::new_v4());
+        let agent = Some(Uuid::new_v4());
+        manager.join_session(session.id, user, None).await.unwrap();
+        manager.add_participant(session.id, None, agent, ParticipantRole::Agent).await.unwrap();
+
+        manager.leave_session(session.id, user, None).await.unwrap();
+        let remaining = manager.get_participants(session.id).await;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].agent_id, agent);
+
+        manager.leave_session(session.id, None, agent).await.unwrap();
+        assert!(manager.get_participants(session.id).await.is_empty());
+        assert!(manager.leave_session(session.id, None, None).await.is_err());
+
+        std::fs::remove_dir_all(&workspace).ok();
+    }
+
+    #[tokio::test]
+    async fn test_share_token_resolves_without_database() {
+        let workspace = std::env::temp_dir().join(format!("bloop-workspace-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        let manager = SessionManager::new(None, Arc::new(AuditLogger::new(100)), workspace.clone());
+        let session = manager
+            .create_session("shared".to_string(), Uuid::new_v4(), ".".to_string(), serde_json::json!({}))
+            .await
+            .unwrap();
+
+        let token = session.share_token.clone().unwrap();
+        assert_eq!(manager.get_session_by_token(&token).await.map(|s| s.id), Some(session.id));
+        assert!(manager.get_session_by_token("not-a-token").await.is_none());
+        assert!(manager.get_session_by_token("").await.is_none());
+
+        std::fs::remove_dir_all(&workspace).ok();
+    }
+}
@@ -7,18 +7,52 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{RwLock, broadcast};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 
-use super::session::{SessionManager, ParticipantRole};
-use super::presence::PresenceTracker;
-use super::conflict::ConflictResolver;
+use super::session::SessionManager;
+use super::presence::{PresenceChange, PresenceTracker};
+use super::conflict::{ConflictResolver, ConflictStrategy, EditOperation, OperationType};
+use super::crdt::{CrdtOp, CrdtResolver};
+use super::edit_audit::{EditAuditLog, EditRecord};
+use super::operation_log::{OperationLog, Replay};
+use super::rate_limit::{MessageRateLimiter, RateDecision};
+use super::broadcast::{DeliveryConfig, DeliveryReport, SessionBroadcaster};
 use crate::services::agent::AgentManager;
 use crate::services::codebase::CodebaseIndexer;
 use crate::security::AdvancedValidator;
 
+/// Newest collaboration protocol version this server speaks
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest protocol version still accepted
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Close code sent when a client's protocol version can't be served
+pub const CLOSE_UNSUPPORTED_PROTOCOL: u16 = 4002;
+/// Close code sent when a client fell so far behind that it missed messages
+pub const CLOSE_DELIVERY_LAGGED: u16 = 4003;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[error("Protocol version {requested} is not supported; this server accepts versions {min} to {max}", min = MIN_PROTOCOL_VERSION, max = PROTOCOL_VERSION)]
+pub struct UnsupportedProtocolVersion {
+    pub requested: u32,
+}
+
+/// Pick the protocol version to speak with a client
+///
+/// Clients newer than the server are negotiated down to PROTOCOL_VERSION;
+/// clients older than MIN_PROTOCOL_VERSION are refused. A client that sends
+/// no version predates versioning and is treated as version 1.
+pub fn negotiate_protocol_version(requested: Option<u32>) -> Result<u32, UnsupportedProtocolVersion> {
+    let requested = requested.unwrap_or(1);
+    if requested < MIN_PROTOCOL_VERSION {
+        return Err(UnsupportedProtocolVersion { requested });
+    }
+    Ok(requested.min(PROTOCOL_VERSION))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum CollaborationMessage {
@@ -27,7 +61,6 @@ pub enum CollaborationMessage {
         session_id: Uuid,
         user_id: Option<Uuid>,
         agent_id: Option<Uuid>,
-        role: Option<String>,
     },
     #[serde(rename = "leave")]
     Leave {
@@ -41,6 +74,9 @@ pub enum CollaborationMessage {
         length: usize,
         content: String,
         version: usize,
+        /// CRDT sessions: the client's own ops, used instead of position/length/content
+        #[serde(default)]
+        ops: Option<Vec<CrdtOp>>,
     },
     #[serde(rename = "cursor")]
     Cursor {
@@ -70,6 +106,21 @@ pub enum CollaborationMessage {
     Pong,
 }
 
+impl CollaborationMessage {
+    /// Session the message is addressed to; heartbeats carry none
+    pub fn session_id(&self) -> Option<Uuid> {
+        match self {
+            Self::Join { session_id, .. }
+            | Self::Leave { session_id }
+            | Self::Edit { session_id, .. }
+            | Self::Cursor { session_id, .. }
+            | Self::Selection { session_id, .. }
+            | Self::Presence { session_id, .. } => Some(*session_id),
+            Self::Ping | Self::Pong => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollaborationResponse {
     pub success: bool,
@@ -79,13 +130,19 @@ pub struct CollaborationResponse {
 }
 
 pub struct CollaborationWebSocket {
-    connections: Arc<RwLock<HashMap<Uuid, HashMap<Uuid, broadcast::Sender<Message>>>>>, // session_id -> participant_id -> sender
+    broadcaster: Arc<SessionBroadcaster>, // Per-session ordered fan-out
     session_manager: Arc<SessionManager>,
     presence_tracker: Arc<PresenceTracker>,
     conflict_resolver: Arc<ConflictResolver>,
+    crdt_resolver: Arc<CrdtResolver>,
     agent_manager: Arc<AgentManager>,
     codebase_indexer: Arc<CodebaseIndexer>,
     validator: Arc<AdvancedValidator>,
+    edit_audit: Arc<EditAuditLog>,
+    operation_log: Arc<OperationLog>,
+    message_limiter: Arc<MessageRateLimiter>, // Per-connection budget; Join/Leave are never limited
+    identities: Arc<RwLock<HashMap<Uuid, (Option<Uuid>, Option<Uuid>)>>>, // participant_id -> (user_id, agent_id)
+    shutdown: CancellationToken, // Closes every connection when the server stops
 }
 
 impl CollaborationWebSocket {
@@ -93,46 +150,93 @@ impl CollaborationWebSocket {
         session_manager: Arc<SessionManager>,
         presence_tracker: Arc<PresenceTracker>,
         conflict_resolver: Arc<ConflictResolver>,
+        crdt_resolver: Arc<CrdtResolver>,
         agent_manager: Arc<AgentManager>,
         codebase_indexer: Arc<CodebaseIndexer>,
         validator: Arc<AdvancedValidator>,
+        edit_audit: Arc<EditAuditLog>,
+        operation_log: Arc<OperationLog>,
+        message_limiter: Arc<MessageRateLimiter>,
+        delivery: DeliveryConfig,
     ) -> Arc<Self> {
         Arc::new(Self {
-            connections: Arc::new(RwLock::new(HashMap::new())),
+            broadcaster: Arc::new(SessionBroadcaster::new(delivery)),
             session_manager,
             presence_tracker,
             conflict_resolver,
+            crdt_resolver,
             agent_manager,
             codebase_indexer,
             validator,
+            edit_audit,
+            operation_log,
+            message_limiter,
+            identities: Arc::new(RwLock::new(HashMap::new())),
+            shutdown: CancellationToken::new(),
         })
     }
 
+    /// Close every connection and refuse new ones; cancelled once shutdown starts
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Associate a connection's participant_id with the user or agent it authenticated as
+    pub async fn register_identity(
+        &self,
+        participant_id: Uuid,
+        user_id: Option<Uuid>,
+        agent_id: Option<Uuid>,
+    ) {
+        if user_id.is_none() && agent_id.is_none() {
+            return;
+        }
+        let mut identities = self.identities.write().await;
+        identities.insert(participant_id, (user_id, agent_id));
+    }
+
     pub async fn handle_connection(
         &self,
         session_id: Uuid,
         participant_id: Uuid,
-        socket: WebSocket,
+        requested_version: Option<u32>,
+        since_version: Option<usize>,
+        mut socket: WebSocket,
     ) -> anyhow::Result<()> {
-        let (mut sender, mut receiver) = socket.split();
+        let protocol_version = match negotiate_protocol_version(requested_version) {
+            Ok(version) => version,
+            Err(e) => {
+                tracing::warn!("Refusing collaboration connection for participant {}: {}", participant_id, e);
+                let _ = socket.send(Message::Close(Some(CloseFrame {
+                    code: CLOSE_UNSUPPORTED_PROTOCOL,
+                    reason: e.to_string().into(),
+                }))).await;
+                return Ok(());
+            }
+        };
 
-        // Create broadcast channel for this participant
-        let (tx, _rx) = broadcast::channel::<Message>(1000);
-        {
-            let mut connections = self.connections.write().await;
-            connections
-                .entry(session_id)
-                .or_insert_with(HashMap::new)
-                .insert(participant_id, tx.clone());
+        if self.shutdown.is_cancelled() {
+            let _ = socket.send(Message::Close(Some(CloseFrame {
+                code: close_code::AWAY,
+                reason: "Server is shutting down".into(),
+            }))).await;
+            return Ok(());
         }
 
+        let (mut sender, mut receiver) = socket.split();
+
+        // Register for this session's broadcasts
+        let mut rx = self.broadcaster.subscribe(session_id, participant_id).await;
+
         // Send welcome message
         let welcome = CollaborationResponse {
             success: true,
             message_type: "connected".to_string(),
             data: Some(serde_json::json!({
                 "session_id": session_id,
-                "participant_id": participant_id
+                "participant_id": participant_id,
+                "protocol_version": protocol_version,
+                "version": self.operation_log.current_version(session_id).await
             })),
             error: None,
         };
@@ -140,17 +244,38 @@ impl CollaborationWebSocket {
             let _ = sender.send(Message::Text(msg)).await;
         }
 
+        // A rejoining client catches up before live traffic. Edits made while this
+        // runs may also arrive through the subscription; clients skip versions they have.
+        if let Some(since_version) = since_version {
+            for response in self.replay_responses(session_id, since_version).await {
+                if let Ok(msg) = serde_json::to_string(&response) {
+                    if sender.send(Message::Text(msg)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
         // Spawn task to handle incoming messages
         let session_manager = Arc::clone(&self.session_manager);
         let presence_tracker = Arc::clone(&self.presence_tracker);
         let conflict_resolver = Arc::clone(&self.conflict_resolver);
         let codebase_indexer = Arc::clone(&self.codebase_indexer);
         let validator = Arc::clone(&self.validator);
-        let connections = Arc::clone(&self.connections);
+        let broadcaster = Arc::clone(&self.broadcaster);
+        let identities = Arc::clone(&self.identities);
         let ws_self = Arc::clone(self);
+        let shutdown = self.shutdown.clone();
 
         tokio::spawn(async move {
-            while let Some(msg) = receiver.next().await {
+            loop {
+                let msg = tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    msg = receiver.next() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
+                };
                 match msg {
                     Ok(Message::Text(text)) => {
                         // Validate message size (max 100KB)
@@ -175,12 +300,16 @@ impl CollaborationWebSocket {
                         }
                     }
                     Ok(Message::Ping(_)) => {
+                        ws_self.heartbeat(session_id, participant_id).await;
                         // Respond to ping
                         if let Err(e) = sender.send(Message::Pong(vec![])).await {
                             tracing::error!("Failed to send pong: {}", e);
                             break;
                         }
                     }
+                    Ok(Message::Pong(_)) => {
+                        ws_self.heartbeat(session_id, participant_id).await;
+                    }
                     Ok(Message::Close(_)) => {
                         break;
                     }
@@ -193,21 +322,50 @@ impl CollaborationWebSocket {
             }
 
             // Cleanup on disconnect
-            let mut connections = connections.write().await;
-            if let Some(session_connections) = connections.get_mut(&session_id) {
-                session_connections.remove(&participant_id);
-                if session_connections.is_empty() {
-                    connections.remove(&session_id);
+            let identity = identities.write().await.remove(&participant_id);
+            if let Some((user_id, agent_id)) = identity {
+                if let Err(e) = session_manager.leave_session(session_id, user_id, agent_id).await {
+                    tracing::warn!("Failed to remove participant {} from session {}: {}", participant_id, session_id, e);
                 }
+                presence_tracker.remove_presence(session_id, user_id, agent_id).await;
+            }
+            broadcaster.unsubscribe(session_id, participant_id).await;
+            ws_self.message_limiter.remove(participant_id).await;
+            if broadcaster.participants(session_id).await.is_empty() {
+                ws_self.edit_audit.close_session(session_id).await;
             }
         });
 
         // Spawn task to send messages to client
-        let mut rx = tx.subscribe();
+        let shutdown = self.shutdown.clone();
         tokio::spawn(async move {
-            while let Ok(msg) = rx.recv().await {
-                if sender.send(msg).await.is_err() {
-                    break;
+            loop {
+                let received = tokio::select! {
+                    _ = shutdown.cancelled() => {
+                        let _ = sender.send(Message::Close(Some(CloseFrame {
+                            code: close_code::AWAY,
+                            reason: "Server is shutting down".into(),
+                        }))).await;
+                        break;
+                    }
+                    received = rx.recv() => received,
+                };
+                match received {
+                    Ok(msg) => {
+                        if sender.send(msg).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        // Its view of the session is now inconsistent; make it reconnect and resync
+                        tracing::warn!("Participant {} missed {} messages; closing connection", participant_id, missed);
+                        let _ = sender.send(Message::Close(Some(CloseFrame {
+                            code: CLOSE_DELIVERY_LAGGED,
+                            reason: format!("Missed {} messages; reconnect to resync", missed).into(),
+                        }))).await;
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
                 }
             }
         });
@@ -225,26 +383,53 @@ impl CollaborationWebSocket {
         let message: CollaborationMessage = serde_json::from_str(text)
             .map_err(|e| anyhow::anyhow!("Failed to parse message: {}", e))?;
 
+        let exempt = matches!(message, CollaborationMessage::Join { .. } | CollaborationMessage::Leave { .. });
+        if !exempt {
+            if let RateDecision::Throttled { first } = self.message_limiter.check(participant_id).await {
+                // Dropped edits are always reported so the client can resend; other spam only once
+                let edit = match &message {
+                    CollaborationMessage::Edit { file_path, version, .. } => Some((file_path.clone(), *version)),
+                    _ => None,
+                };
+                if first || edit.is_some() {
+                    tracing::warn!("Participant {} in session {} is over its message budget", participant_id, session_id);
+                    self.send_to_participant(session_id, participant_id, CollaborationResponse {
+                        success: false,
+                        message_type: "rate_limited".to_string(),
+                        data: edit.map(|(file_path, version)| serde_json::json!({
+                            "file_path": file_path,
+                            "version": version
+                        })),
+                        error: Some("Too many messages; slow down".to_string()),
+                    }).await?;
+                }
+                return Ok(());
+            }
+        }
+
+        // A connection only ever acts on the session it was opened for
+        if let Some(sid) = message.session_id().filter(|sid| *sid != session_id) {
+            return Err(anyhow::anyhow!(
+                "Participant {} sent a message for session {} over a connection to session {}",
+                participant_id, sid, session_id
+            ));
+        }
+
         match message {
-            CollaborationMessage::Join { session_id: sid, user_id, agent_id, role } => {
-                let role_enum = role.as_deref()
-                    .and_then(|r| match r {
-                        "owner" => Some(ParticipantRole::Owner),
-                        "editor" => Some(ParticipantRole::Editor),
-                        "viewer" => Some(ParticipantRole::Viewer),
-                        "agent" => Some(ParticipantRole::Agent),
-                        _ => None,
-                    })
-                    .unwrap_or(ParticipantRole::Viewer);
-
-                self.session_manager.join_session(
-                    sid,
-                    user_id,
-                    agent_id,
-                    role_enum,
-                ).await?;
+            CollaborationMessage::Join { user_id: claimed_user, agent_id: claimed_agent, .. } => {
+                // The identity was settled when the connection was opened; a join can't change it
+                let (user_id, agent_id) = self.identity(participant_id).await;
+                if (claimed_user.is_some() && claimed_user != user_id) || (claimed_agent.is_some() && claimed_agent != agent_id) {
+                    return Err(anyhow::anyhow!(
+                        "Participant {} tried to join session {} under another identity",
+                        participant_id, session_id
+                    ));
+                }
 
-                self.broadcast_to_session(sid, Message::Text(serde_json::to_string(&CollaborationResponse {
+                // The role is the server's choice; see SessionManager::join_session
+                self.session_manager.join_session(session_id, user_id, agent_id).await?;
+
+                self.broadcast_to_session(session_id, Message::Text(serde_json::to_string(&CollaborationResponse {
                     success: true,
                     message_type: "participant_joined".to_string(),
                     data: Some(serde_json::json!({
@@ -255,14 +440,12 @@ impl CollaborationWebSocket {
                     error: None,
                 })?)).await?;
             }
-            CollaborationMessage::Leave { session_id: sid } => {
-                self.session_manager.leave_session(
-                    sid,
-                    None, // Will be determined from participant_id
-                    None,
-                ).await?;
+            CollaborationMessage::Leave { .. } => {
+                let (user_id, agent_id) = self.identity(participant_id).await;
+                self.session_manager.leave_session(session_id, user_id, agent_id).await?;
+                self.presence_tracker.remove_presence(session_id, user_id, agent_id).await;
 
-                self.broadcast_to_session(sid, Message::Text(serde_json::to_string(&CollaborationResponse {
+                self.broadcast_to_session(session_id, Message::Text(serde_json::to_string(&CollaborationResponse {
                     success: true,
                     message_type: "participant_left".to_string(),
                     data: Some(serde_json::json!({
@@ -271,22 +454,67 @@ impl CollaborationWebSocket {
                     error: None,
                 })?)).await?;
             }
-            CollaborationMessage::Edit { session_id: sid, file_path, position, length, content, version } => {
+            CollaborationMessage::Edit { file_path, position, length, content, version, ops, .. } => {
                 // Validate file path
                 if !self.validator.validate_file_path(&file_path) {
                     return Err(anyhow::anyhow!("Invalid file path"));
                 }
+                let Some(resolved_path) = self.session_manager.resolve_session_file(session_id, &file_path).await else {
+                    return Err(anyhow::anyhow!("File is outside the session project"));
+                };
+                let (user_id, agent_id) = self.identity(participant_id).await;
+                if let Err(denied) = self.session_manager.authorize_edit(session_id, user_id, agent_id).await {
+                    tracing::warn!("Rejected edit from participant {} in session {}: {}", participant_id, session_id, denied);
+                    self.send_to_participant(session_id, participant_id, CollaborationResponse {
+                        success: false,
+                        message_type: "edit_rejected".to_string(),
+                        data: Some(serde_json::json!({ "file_path": file_path, "version": version })),
+                        error: Some(denied.to_string()),
+                    }).await?;
+                    return Ok(());
+                }
+                let session = self.session_manager.get_session(session_id).await
+                    .ok_or_else(|| anyhow::anyhow!("Session {} not found", session_id))?;
+
+                // Blame and reconstruction replay edits over the file as it was before the first one
+                if !self.edit_audit.has_base(session_id, &file_path).await {
+                    let initial = tokio::fs::read_to_string(&resolved_path).await.unwrap_or_default();
+                    self.edit_audit.record_base(session_id, &file_path, &initial).await;
+                }
 
-                // Apply edit with conflict resolution
-                // In production, would use Operational Transform here
-                self.broadcast_edit(sid, participant_id, &file_path, position, length, &content, version).await?;
+                // Stamp the edit with the session's next version; `version` is what the client edited against
+                let operation = self.operation_log.append(EditOperation {
+                    id: Uuid::new_v4(),
+                    session_id: session_id,
+                    participant_id,
+                    file_path: file_path.clone(),
+                    operation_type: OperationType::of_edit(length, &content),
+                    position,
+                    length,
+                    content: content.clone(),
+                    version,
+                    parent_version: None,
+                    timestamp: chrono::Utc::now(),
+                }).await?;
+
+                match ConflictStrategy::from_settings(&session.settings).unwrap_or_default() {
+                    ConflictStrategy::Ot => {
+                        // Clients transform against the version they edited
+                        self.broadcast_edit(&operation, None).await?;
+                    }
+                    ConflictStrategy::Crdt => {
+                        let ops = self.apply_crdt_edit(session_id, &file_path, &resolved_path, position, length, &content, ops).await;
+                        self.broadcast_edit(&operation, Some(&ops)).await?;
+                    }
+                }
             }
-            CollaborationMessage::Cursor { session_id: sid, file_path, line, column } => {
+            CollaborationMessage::Cursor { file_path, line, column, .. } => {
                 // Update presence
+                let (user_id, agent_id) = self.identity(participant_id).await;
                 self.presence_tracker.update_presence(
-                    sid,
-                    None, // user_id
-                    None, // agent_id
+                    session_id,
+                    user_id,
+                    agent_id,
                     super::session::ParticipantStatus::Online,
                     Some(serde_json::json!({
                         "line": line,
@@ -296,7 +524,7 @@ impl CollaborationWebSocket {
                 ).await;
 
                 // Broadcast cursor position
-                self.broadcast_to_session(sid, Message::Text(serde_json::to_string(&CollaborationResponse {
+                self.broadcast_to_session(session_id, Message::Text(serde_json::to_string(&CollaborationResponse {
                     success: true,
                     message_type: "cursor_update".to_string(),
                     data: Some(serde_json::json!({
@@ -308,9 +536,9 @@ impl CollaborationWebSocket {
                     error: None,
                 })?)).await?;
             }
-            CollaborationMessage::Selection { session_id: sid, file_path, start_line, start_column, end_line, end_column } => {
+            CollaborationMessage::Selection { file_path, start_line, start_column, end_line, end_column, .. } => {
                 // Broadcast selection
-                self.broadcast_to_session(sid, Message::Text(serde_json::to_string(&CollaborationResponse {
+                self.broadcast_to_session(session_id, Message::Text(serde_json::to_string(&CollaborationResponse {
                     success: true,
                     message_type: "selection_update".to_string(),
                     data: Some(serde_json::json!({
@@ -324,59 +552,179 @@ impl CollaborationWebSocket {
                     error: None,
                 })?)).await?;
             }
-            CollaborationMessage::Presence { session_id: sid, status, active_file } => {
+            CollaborationMessage::Presence { status, active_file, .. } => {
                 let status_enum = match status.as_str() {
                     "away" => super::session::ParticipantStatus::Away,
                     "idle" => super::session::ParticipantStatus::Idle,
                     _ => super::session::ParticipantStatus::Online,
                 };
 
+                let (user_id, agent_id) = self.identity(participant_id).await;
                 self.presence_tracker.update_presence(
-                    sid,
-                    None,
-                    None,
+                    session_id,
+                    user_id,
+                    agent_id,
                     status_enum,
                     None,
                     active_file,
                 ).await;
             }
-            CollaborationMessage::Ping => {
-                // Respond with pong (handled in connection handler)
-            }
-            CollaborationMessage::Pong => {
-                // Heartbeat received
+            CollaborationMessage::Ping | CollaborationMessage::Pong => {
+                // Heartbeat; a ping frame's pong is sent by the connection handler
+                self.heartbeat(session_id, participant_id).await;
             }
         }
 
         Ok(())
     }
 
-    async fn broadcast_edit(
+    /// Merge an edit into the server's CRDT replica of the file
+    ///
+    /// Clients that send their own ops have them applied as-is; plain position
+    /// edits (counted in characters) replace `length` characters with `content`
+    /// on the server's replica. Returns the ops for other replicas.
+    async fn apply_crdt_edit(
         &self,
         session_id: Uuid,
-        participant_id: Uuid,
         file_path: &str,
+        resolved_path: &std::path::Path,
         position: usize,
         length: usize,
         content: &str,
-        version: usize,
+        ops: Option<Vec<CrdtOp>>,
+    ) -> Vec<CrdtOp> {
+        if !self.crdt_resolver.is_open(session_id, file_path).await {
+            // Replicas start from the file as it is on disk
+            let initial = tokio::fs::read_to_string(resolved_path).await.unwrap_or_default();
+            self.crdt_resolver.open_document(session_id, file_path, &initial).await;
+        }
+
+        match ops {
+            Some(ops) => {
+                for op in &ops {
+                    self.crdt_resolver.apply_remote_op(session_id, file_path, op.clone()).await;
+                }
+                ops
+            }
+            None => {
+                let mut ops = self.crdt_resolver.local_delete(session_id, file_path, position, length).await;
+                ops.extend(self.crdt_resolver.local_insert(session_id, file_path, position, content).await);
+                ops
+            }
+        }
+    }
+
+    async fn broadcast_edit(
+        &self,
+        operation: &EditOperation,
+        crdt_ops: Option<&[CrdtOp]>,
     ) -> anyhow::Result<()> {
+        // Record authorship before fanning out
+        let (user_id, agent_id) = self.identity(operation.participant_id).await;
+        if let Err(e) = self.edit_audit.record_edit(EditRecord {
+            id: operation.id,
+            session_id: operation.session_id,
+            participant_id: operation.participant_id,
+            user_id,
+            agent_id,
+            file_path: operation.file_path.clone(),
+            position: operation.position,
+            length: operation.length,
+            content: operation.content.clone(),
+            version: operation.version,
+            timestamp: operation.timestamp,
+        }).await {
+            tracing::warn!("Failed to record edit audit: {}", e);
+        }
+
         // Broadcast edit to all participants except sender
+        let response = edit_response(operation, crdt_ops, false);
+        self.broadcast_to_session_except(
+            operation.session_id,
+            operation.participant_id,
+            Message::Text(serde_json::to_string(&response)?),
+        ).await?;
+        Ok(())
+    }
+
+    /// Messages that bring a client at `since_version` up to date
+    async fn replay_responses(&self, session_id: Uuid, since_version: usize) -> Vec<CollaborationResponse> {
+        match self.operation_log.since(session_id, since_version).await {
+            Replay::Operations { operations, .. } => operations.iter()
+                .map(|operation| edit_response(operation, None, true))
+                .collect(),
+            Replay::ResyncRequired { current_version } => vec![CollaborationResponse {
+                success: true,
+                message_type: "resync_required".to_string(),
+                data: Some(serde_json::json!({
+                    "since_version": since_version,
+                    "version": current_version
+                })),
+                error: None,
+            }],
+        }
+    }
+
+    /// Refresh a participant's presence, announcing it if it had gone idle
+    async fn heartbeat(&self, session_id: Uuid, participant_id: Uuid) {
+        let (user_id, agent_id) = self.identity(participant_id).await;
+        if let Some(change) = self.presence_tracker.touch(session_id, user_id, agent_id).await {
+            self.announce_presence(change).await;
+        }
+    }
+
+    async fn announce_presence(&self, change: PresenceChange) {
+        let session_id = change.presence.session_id;
         let response = CollaborationResponse {
             success: true,
-            message_type: "edit".to_string(),
-            data: Some(serde_json::json!({
-                "participant_id": participant_id,
-                "file_path": file_path,
-                "position": position,
-                "length": length,
-                "content": content,
-                "version": version
-            })),
+            message_type: "presence_update".to_string(),
+            data: serde_json::to_value(&change).ok(),
             error: None,
         };
+        match serde_json::to_string(&response) {
+            Ok(text) => {
+                let _ = self.broadcast_to_session(session_id, Message::Text(text)).await;
+            }
+            Err(e) => tracing::warn!("Failed to encode presence update: {}", e),
+        }
+    }
 
-        self.broadcast_to_session_except(session_id, participant_id, Message::Text(serde_json::to_string(&response)?)).await?;
+    /// Periodically age out participants that stopped sending anything
+    ///
+    /// Presences idle for `away_after` become Away; after `remove_after` they
+    /// are dropped. Every change is broadcast to its session as `presence_update`.
+    pub fn spawn_presence_monitor(self: &Arc<Self>, away_after: std::time::Duration, remove_after: std::time::Duration) {
+        let ws = Arc::clone(self);
+        let interval = (away_after / 4)
+            .clamp(std::time::Duration::from_secs(1), std::time::Duration::from_secs(30));
+        let away_after = chrono::Duration::from_std(away_after).unwrap_or_else(|_| chrono::Duration::minutes(2));
+        let remove_after = chrono::Duration::from_std(remove_after).unwrap_or_else(|_| chrono::Duration::minutes(10));
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                for change in ws.presence_tracker.expire_idle(away_after, remove_after).await {
+                    ws.announce_presence(change).await;
+                }
+            }
+        });
+    }
+
+    /// User and agent ids registered for a connection
+    async fn identity(&self, participant_id: Uuid) -> (Option<Uuid>, Option<Uuid>) {
+        let identities = self.identities.read().await;
+        identities.get(&participant_id).cloned().unwrap_or((None, None))
+    }
+
+    async fn send_to_participant(
+        &self,
+        session_id: Uuid,
+        participant_id: Uuid,
+        response: CollaborationResponse,
+    ) -> anyhow::Result<()> {
+        let message = Message::Text(serde_json::to_string(&response)?);
+        if !self.broadcaster.send_to(session_id, participant_id, message).await {
+            tracing::debug!("Participant {} in session {} is no longer connected", participant_id, session_id);
+        }
         Ok(())
     }
 
@@ -385,12 +733,7 @@ impl CollaborationWebSocket {
         session_id: Uuid,
         message: Message,
     ) -> anyhow::Result<()> {
-        let connections = self.connections.read().await;
-        if let Some(session_connections) = connections.get(&session_id) {
-            for tx in session_connections.values() {
-                let _ = tx.send(message.clone());
-            }
-        }
+        log_failed_delivery(session_id, self.broadcaster.broadcast(session_id, message, None).await);
         Ok(())
     }
 
@@ -400,26 +743,88 @@ impl CollaborationWebSocket {
         exclude_participant_id: Uuid,
         message: Message,
     ) -> anyhow::Result<()> {
-        let connections = self.connections.read().await;
-        if let Some(session_connections) = connections.get(&session_id) {
-            for (participant_id, tx) in session_connections.iter() {
-                if *participant_id != exclude_participant_id {
-                    let _ = tx.send(message.clone());
-                }
-            }
-        }
+        let report = self.broadcaster.broadcast(session_id, message, Some(exclude_participant_id)).await;
+        log_failed_delivery(session_id, report);
         Ok(())
     }
 
     pub async fn is_connected(&self, session_id: Uuid) -> bool {
-        let connections = self.connections.read().await;
-        connections.contains_key(&session_id) && !connections.get(&session_id).map(|m| m.is_empty()).unwrap_or(true)
+        !self.broadcaster.participants(session_id).await.is_empty()
     }
 
     pub async fn get_connected_participants(&self, session_id: Uuid) -> Vec<Uuid> {
-        let connections = self.connections.read().await;
-        connections.get(&session_id)
-            .map(|m| m.keys().cloned().collect())
-            .unwrap_or_default()
+        self.broadcaster.participants(session_id).await
+    }
+}
+
+fn edit_response(operation: &EditOperation, crdt_ops: Option<&[CrdtOp]>, replayed: bool) -> CollaborationResponse {
+    CollaborationResponse {
+        success: true,
+        message_type: "edit".to_string(),
+        data: Some(serde_json::json!({
+            "participant_id": operation.participant_id,
+            "file_path": operation.file_path,
+            "position": operation.position,
+            "length": operation.length,
+            "content": operation.content,
+            "version": operation.version,
+            "base_version": operation.parent_version,
+            "ops": crdt_ops,
+            "replayed": replayed
+        })),
+        error: None,
+    }
+}
+
+fn log_failed_delivery(session_id: Uuid, report: DeliveryReport) {
+    if report.failed > 0 {
+        tracing::debug!(
+            "Session {} broadcast: {} delivered, {} failed, {} dropped",
+            session_id, report.delivered, report.failed, report.dropped.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_name_their_session() {
+        let session_id = Uuid::new_v4();
+        let edit: CollaborationMessage = serde_json::from_value(serde_json::json!({
+            "type": "edit",
+            "session_id": session_id,
+            "file_path": "src/main.rs",
+            "position": 0,
+            "length": 0,
+            "content": "x",
+            "version": 1
+        })).unwrap();
+        assert_eq!(edit.session_id(), Some(session_id));
+
+        // A client-chosen role is no longer part of the protocol and is ignored
+        let join: CollaborationMessage = serde_json::from_value(serde_json::json!({
+            "type": "join",
+            "session_id": session_id,
+            "role": "editor"
+        })).unwrap();
+        assert_eq!(join.session_id(), Some(session_id));
+        assert_eq!(CollaborationMessage::Ping.session_id(), None);
+    }
+
+    #[test]
+    fn test_protocol_version_negotiation() {
+        // Legacy clients without a version and clients on the current version are accepted as-is
+        assert_eq!(negotiate_protocol_version(None), Ok(1));
+        assert_eq!(negotiate_protocol_version(Some(PROTOCOL_VERSION)), Ok(PROTOCOL_VERSION));
+
+        // A newer client is negotiated down to what the server speaks
+        assert_eq!(negotiate_protocol_version(Some(PROTOCOL_VERSION + 3)), Ok(PROTOCOL_VERSION));
+
+        // Anything older than the minimum is refused
+        let err = negotiate_protocol_version(Some(MIN_PROTOCOL_VERSION - 1)).unwrap_err();
+        assert_eq!(err.requested, MIN_PROTOCOL_VERSION - 1);
+        assert!(err.to_string().contains("not supported"));
     }
 }
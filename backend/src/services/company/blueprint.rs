@@ -0,0 +1,304 @@
+/**
+ * Company Blueprint
+ *
+ * Portable description of a company's shape: which teams exist, their
+ * capacity, and how many agents of each role staff them. Exported from a
+ * running company and imported elsewhere, where the existing roster is
+ * converged onto it instead of being duplicated.
+ */
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use super::types::{CompanyMember, CompanyRole, Team};
+
+pub const BLUEPRINT_VERSION: u32 = 1;
+
+/// Upper bound on a single role entry, so a typo can't spawn thousands of agents
+const MAX_AGENTS_PER_ROLE: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompanyBlueprint {
+    pub version: u32,
+    pub teams: Vec<TeamBlueprint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeamBlueprint {
+    pub name: String,
+    /// Scaling bound for the team; None for groups without a team record (e.g. Leadership)
+    #[serde(default)]
+    pub capacity: Option<usize>,
+    pub roles: Vec<RoleBlueprint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoleBlueprint {
+    pub role: CompanyRole,
+    pub count: usize,
+    /// Skills given to newly created agents; empty uses the role's defaults
+    #[serde(default)]
+    pub skills: Vec<String>,
+}
+
+/// What an import changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub added: Vec<String>,
+    pub retired: Vec<String>,
+    pub teams_created: Vec<String>,
+    pub teams_removed: Vec<String>,
+}
+
+fn role_key(role: &CompanyRole) -> String {
+    format!("{:?}", role)
+}
+
+impl CompanyBlueprint {
+    /// Describe the current roster. Output is sorted so identical companies export identically.
+    pub fn from_state(members: &HashMap<String, CompanyMember>, teams: &HashMap<String, Team>) -> Self {
+        let mut by_team: HashMap<&str, HashMap<String, RoleBlueprint>> = HashMap::new();
+        for name in teams.keys() {
+            by_team.entry(name.as_str()).or_default();
+        }
+        for member in members.values() {
+            let entry = by_team.entry(member.team.as_str()).or_default()
+                .entry(role_key(&member.role))
+                .or_insert_with(|| RoleBlueprint {
+                    role: member.role.clone(),
+                    count: 0,
+                    skills: member.skills.clone(),
+                });
+            entry.count += 1;
+        }
+
+        let mut teams: Vec<TeamBlueprint> = by_team.into_iter()
+            .map(|(name, roles)| {
+                let mut roles: Vec<RoleBlueprint> = roles.into_values().collect();
+                roles.sort_by_key(|r| role_key(&r.role));
+                TeamBlueprint {
+                    name: name.to_string(),
+                    capacity: teams.get(name).map(|t| t.capacity),
+                    roles,
+                }
+            })
+            .collect();
+        teams.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self { version: BLUEPRINT_VERSION, teams }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.version != BLUEPRINT_VERSION {
+            anyhow::bail!("Unsupported blueprint version {} (expected {})", self.version, BLUEPRINT_VERSION);
+        }
+
+        let mut team_names = HashSet::new();
+        for team in &self.teams {
+            if team.name.trim().is_empty() {
+                anyhow::bail!("Team names must not be empty");
+            }
+            if !team_names.insert(team.name.as_str()) {
+                anyhow::bail!("Team '{}' appears more than once", team.name);
+            }
+
+            let mut roles = HashSet::new();
+            for role in &team.roles {
+                if !roles.insert(role_key(&role.role)) {
+                    anyhow::bail!("Role {:?} appears more than once in team '{}'", role.role, team.name);
+                }
+                if role.count > MAX_AGENTS_PER_ROLE {
+                    anyhow::bail!(
+                        "Team '{}' asks for {} {:?} agents (max {})",
+                        team.name, role.count, role.role, MAX_AGENTS_PER_ROLE
+                    );
+                }
+            }
+
+            let staffed: usize = team.roles.iter().map(|r| r.count).sum();
+            if let Some(capacity) = team.capacity {
+                if staffed > capacity {
+                    anyhow::bail!("Team '{}' has {} agents but a capacity of {}", team.name, staffed, capacity);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add and retire members until the roster matches the blueprint.
+    /// `new_member` builds an agent for a (role, team, skills) slot.
+    pub fn converge(
+        &self,
+        members: &mut HashMap<String, CompanyMember>,
+        teams: &mut HashMap<String, Team>,
+        new_member: impl Fn(&CompanyRole, &str, &[String]) -> CompanyMember,
+    ) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        let wanted: HashMap<&str, &TeamBlueprint> = self.teams.iter()
+            .map(|t| (t.name.as_str(), t))
+            .collect();
+
+        // Members whose (team, role) slot is gone entirely
+        let orphaned: Vec<String> = members.values()
+            .filter(|m| !wanted.get(m.team.as_str())
+                .is_some_and(|t| t.roles.iter().any(|r| r.role == m.role)))
+            .map(|m| m.agent.id.clone())
+            .collect();
+        for id in orphaned {
+            members.remove(&id);
+            summary.retired.push(id);
+        }
+
+        for team in &self.teams {
+            for slot in &team.roles {
+                // Keep the most active, most experienced members
+                let mut current: Vec<&CompanyMember> = members.values()
+                    .filter(|m| m.team == team.name && m.role == slot.role)
+                    .collect();
+                current.sort_by(|a, b| {
+                    b.is_active.cmp(&a.is_active)
+                        .then(b.tasks_completed.cmp(&a.tasks_completed))
+                        .then(a.agent.id.cmp(&b.agent.id))
+                });
+
+                if current.len() > slot.count {
+                    let extra: Vec<String> = current[slot.count..].iter()
+                        .map(|m| m.agent.id.clone())
+                        .collect();
+                    for id in extra {
+                        members.remove(&id);
+                        summary.retired.push(id);
+                    }
+                } else {
+                    for _ in current.len()..slot.count {
+                        let member = new_member(&slot.role, &team.name, &slot.skills);
+                        summary.added.push(member.agent.id.clone());
+                        members.insert(member.agent.id.clone(), member);
+                    }
+                }
+            }
+        }
+
+        // Team records follow the blueprint's capacities and the new roster
+        let stale: Vec<String> = teams.keys()
+            .filter(|name| !wanted.get(name.as_str()).is_some_and(|t| t.capacity.is_some()))
+            .cloned()
+            .collect();
+        for name in stale {
+            teams.remove(&name);
+            summary.teams_removed.push(name);
+        }
+        for team in &self.teams {
+            let Some(capacity) = team.capacity else {
+                continue;
+            };
+            let record = teams.entry(team.name.clone()).or_insert_with(|| {
+                summary.teams_created.push(team.name.clone());
+                Team {
+                    name: team.name.clone(),
+                    members: Vec::new(),
+                    lead: None,
+                    capacity,
+                    current_load: 0,
+                }
+            });
+            record.capacity = capacity;
+            record.members = members.values()
+                .filter(|m| m.team == team.name)
+                .map(|m| m.agent.id.clone())
+                .collect();
+            record.members.sort();
+            if record.lead.as_ref().is_some_and(|lead| !members.contains_key(lead)) {
+                record.lead = None;
+            }
+        }
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::services::agent::types::{Agent, AgentType};
+
+    fn member(role: &CompanyRole, team: &str, skills: &[String]) -> CompanyMember {
+        CompanyMember {
+            agent: Agent::new(uuid::Uuid::new_v4().to_string(), format!("{:?}", role), AgentType::CodeGenerator),
+            role: role.clone(),
+            team: team.to_string(),
+            skills: skills.to_vec(),
+            performance_score: 1.0,
+            tasks_completed: 0,
+            tasks_failed: 0,
+            average_task_time_ms: 0,
+            last_active: Utc::now(),
+            is_active: true,
+            openclaw_id: None,
+            moltbook_id: None,
+        }
+    }
+
+    fn team(name: &str, capacity: usize) -> Team {
+        Team { name: name.to_string(), members: Vec::new(), lead: None, capacity, current_load: 0 }
+    }
+
+    #[test]
+    fn test_import_converges_to_exported_shape() {
+        let mut members = HashMap::new();
+        let mut teams = HashMap::from([
+            ("Engineering".to_string(), team("Engineering", 8)),
+            ("Creative".to_string(), team("Creative", 4)),
+        ]);
+        for (role, team) in [
+            (CompanyRole::Ceo, "Leadership"),
+            (CompanyRole::BackendEngineer, "Engineering"),
+            (CompanyRole::BackendEngineer, "Engineering"),
+            (CompanyRole::QaEngineer, "Engineering"),
+            (CompanyRole::UiDesigner, "Creative"),
+        ] {
+            let m = member(&role, team, &[]);
+            members.insert(m.agent.id.clone(), m);
+        }
+
+        let exported = CompanyBlueprint::from_state(&members, &teams);
+        exported.validate().unwrap();
+        let json = serde_json::to_string(&exported).unwrap();
+        let imported: CompanyBlueprint = serde_json::from_str(&json).unwrap();
+
+        // Drift: an extra backend engineer, QA gone, a new team, Creative removed
+        let extra = member(&CompanyRole::BackendEngineer, "Engineering", &[]);
+        members.insert(extra.agent.id.clone(), extra);
+        members.retain(|_, m| m.role != CompanyRole::QaEngineer && m.team != "Creative");
+        let stray = member(&CompanyRole::ContentCreator, "Marketing", &[]);
+        members.insert(stray.agent.id.clone(), stray);
+        teams.insert("Marketing".to_string(), team("Marketing", 2));
+        teams.remove("Creative");
+
+        let summary = imported.converge(&mut members, &mut teams, member);
+        assert_eq!(summary.added.len(), 2); // QA engineer and UI designer
+        assert_eq!(summary.retired.len(), 2); // extra backend engineer and content creator
+        assert_eq!(summary.teams_created, vec!["Creative".to_string()]);
+        assert_eq!(summary.teams_removed, vec!["Marketing".to_string()]);
+
+        assert_eq!(CompanyBlueprint::from_state(&members, &teams), imported);
+        assert_eq!(teams["Engineering"].members.len(), 2 + 1);
+
+        // Converging again is a no-op
+        let again = imported.converge(&mut members, &mut teams, member);
+        assert!(again.added.is_empty() && again.retired.is_empty());
+    }
+
+    #[test]
+    fn test_over_capacity_blueprint_is_rejected() {
+        let blueprint = CompanyBlueprint {
+            version: BLUEPRINT_VERSION,
+            teams: vec![TeamBlueprint {
+                name: "Engineering".to_string(),
+                capacity: Some(2),
+                roles: vec![RoleBlueprint { role: CompanyRole::BackendEngineer, count: 3, skills: vec![] }],
+            }],
+        };
+        assert!(blueprint.validate().is_err());
+    }
+}
@@ -92,11 +92,9 @@ impl CollaborationHub {
         let start_time = std::time::Instant::now();
         let participants = collaboration.to_agents.clone();
 
-        // Connect to OpenClaw if not connected
+        // The client reconnects on its own; until then messages fall back below
         if !self.openclaw_client.is_connected().await {
-            if let Err(e) = self.openclaw_client.connect().await {
-                tracing::warn!("Failed to connect to OpenClaw: {}, using fallback", e);
-            }
+            tracing::warn!("OpenClaw Gateway is not connected, using fallback");
         }
 
         // Send collaboration task via OpenClaw
@@ -111,6 +109,7 @@ impl CollaborationHub {
                 ),
                 thinking_level: Some("high".to_string()),
                 model: None,
+                session_id: None,
             };
 
             match self.openclaw_client.send_message(message).await {
@@ -4,7 +4,10 @@
  * Monitors health of the agent company and ensures 24/7/365 operation
  */
 use std::sync::Arc;
+use tokio::sync::RwLock;
+use crate::services::ai::router::ModelRouter;
 use crate::services::company::orchestrator::CompanyOrchestrator;
+use crate::services::company::types::{CompanyMetrics, CompanyState};
 
 pub struct CompanyHealthMonitor {
     last_health_check: Arc<tokio::sync::RwLock<chrono::DateTime<chrono::Utc>>>,
@@ -52,8 +55,83 @@ impl CompanyHealthMonitor {
         );
     }
 
+    /// Move the company in or out of Degraded based on AI provider availability.
+    /// Returns whether tasks can be routed.
+    pub async fn check_provider_availability(
+        &self,
+        router: &ModelRouter,
+        metrics: &RwLock<CompanyMetrics>,
+    ) -> bool {
+        let available = router.has_available_provider();
+        let state = if available { CompanyState::Running } else { CompanyState::Degraded };
+
+        let mut metrics = metrics.write().await;
+        if metrics.state != state {
+            match state {
+                CompanyState::Degraded => tracing::warn!(
+                    "No AI provider available; pausing task routing"
+                ),
+                CompanyState::Running => tracing::info!(
+                    "AI provider available again; resuming task routing"
+                ),
+            }
+            metrics.state = state;
+            metrics.last_updated = chrono::Utc::now();
+        }
+
+        available
+    }
+
     /// Get last health check time
     pub async fn last_health_check(&self) -> chrono::DateTime<chrono::Utc> {
         *self.last_health_check.read().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::services::ai::OpenAIService;
+
+    fn metrics() -> RwLock<CompanyMetrics> {
+        RwLock::new(CompanyMetrics {
+            state: CompanyState::Running,
+            total_agents: 0,
+            active_agents: 0,
+            total_tasks_completed: 0,
+            total_tasks_failed: 0,
+            success_rate: 0.0,
+            average_task_time_ms: 0,
+            total_tokens_used: 0,
+            uptime_seconds: 0,
+            visual_creatives_completed: 0,
+            collaborations_count: 0,
+            last_updated: chrono::Utc::now(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_total_provider_outage_degrades_company() {
+        let config = Config::from_env().unwrap();
+        let router = ModelRouter::new(&config).with_adapter(Arc::new(OpenAIService::new(&config)));
+        let monitor = CompanyHealthMonitor::new();
+        let metrics = metrics();
+
+        assert!(monitor.check_provider_availability(&router, &metrics).await);
+        assert_eq!(metrics.read().await.state, CompanyState::Running);
+
+        // Every configured provider fails until it is taken out of rotation
+        for provider in router.configured_providers() {
+            for _ in 0..3 {
+                router.health().record_failure(&provider);
+            }
+        }
+        assert!(!monitor.check_provider_availability(&router, &metrics).await);
+        assert_eq!(metrics.read().await.state, CompanyState::Degraded);
+
+        router.health().record_success(&crate::types::ModelProvider::OpenAI);
+        assert!(monitor.check_provider_availability(&router, &metrics).await);
+        assert_eq!(metrics.read().await.state, CompanyState::Running);
+    }
+}
@@ -13,7 +13,9 @@ pub mod persistence;
 pub mod health;
 pub mod types;
 pub mod scaling;
+pub mod blueprint;
 
 pub use orchestrator::CompanyOrchestrator;
 pub use types::*;
-pub use scaling::PredictiveScaler;
+pub use scaling::{PredictiveScaler, ScalingPolicy};
+pub use blueprint::{CompanyBlueprint, ImportSummary};
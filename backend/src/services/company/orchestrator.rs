@@ -5,37 +5,57 @@
  * Manages all agents, routes tasks, and ensures 24/7/365 operation.
  */
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use std::collections::HashMap;
 use uuid::Uuid;
 use chrono::Utc;
 
-use crate::services::agent::AgentManager;
+use crate::services::agent::{AgentManager, CoordinationEvent};
 use crate::services::ai::router::ModelRouter;
+use crate::services::integrations::{MoltbookApiClient, OpenClawWebSocketClient};
 use crate::config::Config;
 use crate::database::Database;
+use crate::types::{AgentTask, Priority, TaskType};
 
 use super::types::*;
 use super::demand::DemandAnalyzer;
 use super::visual::VisualCreativeEngine;
 use super::collaboration::CollaborationHub;
-use super::persistence::CompanyPersistence;
+use super::persistence::{CompanyPersistence, CompanyStateSnapshot};
 use super::health::CompanyHealthMonitor;
-use super::scaling::PredictiveScaler;
+use super::scaling::{PredictiveScaler, ScalingPolicy};
+use super::blueprint::{CompanyBlueprint, ImportSummary};
 
 
 pub struct CompanyOrchestrator {
     members: Arc<RwLock<HashMap<String, CompanyMember>>>,
     teams: Arc<RwLock<HashMap<String, Team>>>,
     agent_manager: Arc<AgentManager>,
+    router: Arc<ModelRouter>,
     demand_analyzer: Arc<DemandAnalyzer>,
     visual_engine: Arc<VisualCreativeEngine>,
     collaboration_hub: Arc<CollaborationHub>,
+    openclaw_client: Arc<OpenClawWebSocketClient>,
+    moltbook_client: Arc<MoltbookApiClient>,
     persistence: Arc<CompanyPersistence>,
     health_monitor: Arc<CompanyHealthMonitor>,
     predictive_scaler: Arc<PredictiveScaler>,
     metrics: Arc<RwLock<CompanyMetrics>>,
     is_running: Arc<RwLock<bool>>,
+    assignments: Arc<RwLock<HashMap<String, String>>>, // task_id -> team carrying its load
+    member_tasks: Arc<RwLock<HashMap<String, String>>>, // task_id -> member it was routed to
+    loops: Mutex<Option<RunningLoops>>, // Background loops while running
+}
+
+/// Weight of the latest task outcome in a member's performance score
+const SCORE_SMOOTHING: f64 = 0.1;
+
+/// Handles to the continuous-operation loops and the signal that stops them
+struct RunningLoops {
+    shutdown: CancellationToken,
+    handles: Vec<JoinHandle<()>>,
 }
 
 impl CompanyOrchestrator {
@@ -56,34 +76,41 @@ impl CompanyOrchestrator {
         let openclaw_client = Arc::new(OpenClawWebSocketClient::new(Arc::clone(&config)));
         let moltbook_client = Arc::new(MoltbookApiClient::new(Arc::clone(&config)));
         
-        // Connect to OpenClaw in background
-        let openclaw_client_clone = Arc::clone(&openclaw_client);
-        tokio::spawn(async move {
-            if let Err(e) = openclaw_client_clone.connect().await {
-                tracing::warn!("Failed to connect to OpenClaw Gateway: {}", e);
-            }
-        });
+        // Keep a connection to OpenClaw in the background, reconnecting as needed
+        let openclaw_enabled = std::env::var("OPENCLAW_ENABLED")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+        if openclaw_enabled {
+            openclaw_client.start();
+        }
 
         let collaboration_hub = Arc::new(CollaborationHub::new(
             Arc::clone(&agent_manager),
             Arc::clone(&openclaw_client),
             database.clone(),
         ));
-        let persistence = Arc::new(CompanyPersistence::new(database.clone()));
+        let persistence = Arc::new(CompanyPersistence::new(database.clone(), config.company_metrics_retention_days));
         let health_monitor = Arc::new(CompanyHealthMonitor::new());
-        let predictive_scaler = Arc::new(PredictiveScaler::new(Arc::clone(&agent_manager)));
+        let predictive_scaler = Arc::new(PredictiveScaler::new(
+            Arc::clone(&agent_manager),
+            ScalingPolicy::from_config(&config),
+        ));
 
         let orchestrator = Arc::new(Self {
             members: Arc::new(RwLock::new(HashMap::new())),
             teams: Arc::new(RwLock::new(HashMap::new())),
             agent_manager,
+            router,
             demand_analyzer,
             visual_engine,
             collaboration_hub,
+            openclaw_client,
+            moltbook_client,
             persistence,
             health_monitor,
             predictive_scaler,
             metrics: Arc::new(RwLock::new(CompanyMetrics {
+                state: CompanyState::Running,
                 total_agents: 0,
                 active_agents: 0,
                 total_tasks_completed: 0,
@@ -97,6 +124,15 @@ impl CompanyOrchestrator {
                 last_updated: Utc::now(),
             })),
             is_running: Arc::new(RwLock::new(false)),
+            assignments: Arc::new(RwLock::new(HashMap::new())),
+            member_tasks: Arc::new(RwLock::new(HashMap::new())),
+            loops: Mutex::new(None),
+        });
+
+        // Credit finished tasks to the members they were routed to
+        let orchestrator_clone = Arc::clone(&orchestrator);
+        tokio::spawn(async move {
+            orchestrator_clone.track_member_results().await;
         });
 
         // Initialize company structure
@@ -109,7 +145,7 @@ impl CompanyOrchestrator {
     }
 
     /// Initialize the company structure with default teams and roles
-    async fn initialize_company(&self) {
+    async fn initialize_company(self: &Arc<Self>) {
         tracing::info!("Initializing Agent Company...");
 
         // Create teams
@@ -147,19 +183,19 @@ impl CompanyOrchestrator {
         }
         drop(teams_map);
 
-        // Create strategic agents (CEO, CTO, PM)
+        // Load persisted state first so restored members aren't recreated
+        if let Err(e) = self.persistence.load_company_state(self).await {
+            tracing::warn!("Failed to load persisted state: {}", e);
+        }
+
+        // Create strategic agents (CEO, CTO, PM) that weren't restored
         self.create_strategic_agents().await;
 
         // Register agents with OpenClaw and Moltbook
         self.register_agents_with_integrations().await;
 
-        // Load persisted state
-        if let Err(e) = self.persistence.load_company_state(self).await {
-            tracing::warn!("Failed to load persisted state: {}", e);
-        }
-
         // Start continuous operation (spawns async tasks)
-        self.start_continuous_operation().await;
+        self.start().await;
     }
 
     /// Register agents with OpenClaw and Moltbook
@@ -190,86 +226,144 @@ impl CompanyOrchestrator {
             (CompanyRole::ProductManager, "Feature planning and prioritization"),
         ];
 
+        let existing_roles: Vec<CompanyRole> = self.members.read().await
+            .values()
+            .map(|m| m.role.clone())
+            .collect();
+        let mut created = 0;
+
         for (role, description) in strategic_roles {
-            let agent_id = Uuid::new_v4().to_string();
-            let agent = crate::services::agent::types::Agent {
-                id: agent_id.clone(),
-                name: format!("{:?}", role),
-                agent_type: self.role_to_agent_type(&role),
-                status: crate::services::agent::types::AgentStatus::Idle,
-                current_task: None,
-                capabilities: self.role_to_capabilities(&role),
-                created_at: Utc::now(),
-                metadata: Some(HashMap::from([
-                    ("role".to_string(), serde_json::json!(format!("{:?}", role))),
-                    ("description".to_string(), serde_json::json!(description)),
-                ])),
-            };
+            if existing_roles.contains(&role) {
+                continue;
+            }
 
-            let member = CompanyMember {
-                agent,
-                role: role.clone(),
-                team: "Leadership".to_string(),
-                skills: self.role_to_skills(&role),
-                performance_score: 1.0,
-                tasks_completed: 0,
-                tasks_failed: 0,
-                average_task_time_ms: 0,
-                last_active: Utc::now(),
-                is_active: true,
-                openclaw_id: None,
-                moltbook_id: None,
-            };
+            let member = self.new_member(&role, "Leadership", Some(description), &[]);
+            let agent_id = member.agent.id.clone();
 
             let mut members = self.members.write().await;
             members.insert(agent_id, member);
+            created += 1;
         }
 
+        if created > 0 {
+            self.save_state_now("strategic agents created").await;
+        }
         tracing::info!("Strategic agents created");
     }
 
+    /// Build a fresh member for a role. Empty `skills` uses the role's defaults.
+    fn new_member(&self, role: &CompanyRole, team: &str, description: Option<&str>, skills: &[String]) -> CompanyMember {
+        let mut metadata = HashMap::from([
+            ("role".to_string(), serde_json::json!(format!("{:?}", role))),
+        ]);
+        if let Some(description) = description {
+            metadata.insert("description".to_string(), serde_json::json!(description));
+        }
+
+        let agent = crate::services::agent::types::Agent {
+            id: Uuid::new_v4().to_string(),
+            name: format!("{:?}", role),
+            agent_type: self.role_to_agent_type(role),
+            status: crate::services::agent::types::AgentStatus::Idle,
+            current_task: None,
+            capabilities: self.role_to_capabilities(role),
+            created_at: Utc::now(),
+            last_heartbeat: Utc::now(),
+            metadata: Some(metadata),
+        };
+
+        CompanyMember {
+            agent,
+            role: role.clone(),
+            team: team.to_string(),
+            skills: if skills.is_empty() { self.role_to_skills(role) } else { skills.to_vec() },
+            performance_score: 1.0,
+            tasks_completed: 0,
+            tasks_failed: 0,
+            average_task_time_ms: 0,
+            last_active: Utc::now(),
+            is_active: true,
+            openclaw_id: None,
+            moltbook_id: None,
+        }
+    }
+
     /// Start continuous 24/7/365 operation
-    async fn start_continuous_operation(&self) {
-        let mut is_running = self.is_running.write().await;
-        *is_running = true;
-        drop(is_running);
+    ///
+    /// Returns false if the loops were already running.
+    pub async fn start(self: &Arc<Self>) -> bool {
+        let mut loops = self.loops.lock().await;
+        if loops.is_some() {
+            return false;
+        }
+        *self.is_running.write().await = true;
 
         tracing::info!("Starting 24/7/365 continuous operation");
+        self.predictive_scaler.seed_cooldowns().await;
 
-        // Start demand monitoring loop
-        let orchestrator = Arc::clone(self);
-        tokio::spawn(async move {
-            orchestrator.demand_monitoring_loop().await;
-        });
-
-        // Start health monitoring loop
-        let orchestrator = Arc::clone(self);
-        tokio::spawn(async move {
-            orchestrator.health_monitoring_loop().await;
-        });
+        let shutdown = CancellationToken::new();
+        let handles = vec![
+            // Demand monitoring loop
+            tokio::spawn({
+                let (orchestrator, shutdown) = (Arc::clone(self), shutdown.clone());
+                async move { orchestrator.demand_monitoring_loop(shutdown).await }
+            }),
+            // Health monitoring loop
+            tokio::spawn({
+                let (orchestrator, shutdown) = (Arc::clone(self), shutdown.clone());
+                async move { orchestrator.health_monitoring_loop(shutdown).await }
+            }),
+            // Metrics update loop
+            tokio::spawn({
+                let (orchestrator, shutdown) = (Arc::clone(self), shutdown.clone());
+                async move { orchestrator.metrics_update_loop(shutdown).await }
+            }),
+            // Persistence save loop
+            tokio::spawn({
+                let (orchestrator, shutdown) = (Arc::clone(self), shutdown.clone());
+                async move { orchestrator.persistence_save_loop(shutdown).await }
+            }),
+        ];
+        *loops = Some(RunningLoops { shutdown, handles });
+        true
+    }
 
-        // Start metrics update loop
-        let orchestrator = Arc::clone(self);
-        tokio::spawn(async move {
-            orchestrator.metrics_update_loop().await;
-        });
+    /// Pause continuous operation, waiting for every loop to exit
+    ///
+    /// A loop in the middle of its work finishes that pass first. Returns
+    /// false if nothing was running.
+    pub async fn stop(&self) -> bool {
+        let mut loops = self.loops.lock().await;
+        let Some(running) = loops.take() else {
+            return false;
+        };
+        *self.is_running.write().await = false;
 
-        // Start persistence save loop
-        let orchestrator = Arc::clone(self);
-        tokio::spawn(async move {
-            orchestrator.persistence_save_loop().await;
-        });
+        tracing::info!("Stopping continuous operation");
+        running.shutdown.cancel();
+        for handle in running.handles {
+            if let Err(e) = handle.await {
+                tracing::error!("Company loop ended abnormally: {}", e);
+            }
+        }
+        true
     }
 
     /// Demand monitoring loop - analyzes and routes tasks
-    async fn demand_monitoring_loop(&self) {
+    async fn demand_monitoring_loop(&self, shutdown: CancellationToken) {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
         
         loop {
-            interval.tick().await;
-            
-            if !*self.is_running.read().await {
-                break;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+
+            self.release_finished_assignments().await;
+
+            // Nothing can execute without a provider; wait for one to recover
+            if !self.health_monitor.check_provider_availability(&self.router, &self.metrics).await {
+                continue;
             }
 
             // Analyze current demand
@@ -281,10 +375,14 @@ impl CompanyOrchestrator {
                     // Route tasks based on demand
                     self.route_tasks_based_on_demand(&demand).await;
                     
-                    // Predict future demand and scale if needed
-                    let predicted = self.predictive_scaler.predict_demand(1).await; // 1 hour ahead
-                    let optimal_agents = self.predictive_scaler.calculate_optimal_agents(&predicted).await;
-                    self.predictive_scaler.scale_agents(&optimal_agents).await;
+                    // Predict future demand and scale if needed, once there is history to predict from
+                    if self.predictive_scaler.has_demand_history().await {
+                        let predicted = self.predictive_scaler.predict_demand(1).await; // 1 hour ahead
+                        let optimal_agents = self.predictive_scaler.calculate_optimal_agents(&predicted).await;
+                        let current = self.active_members_by_role().await;
+                        let targets = self.predictive_scaler.scale_agents(&current, &optimal_agents).await;
+                        self.apply_scaling(&current, &targets).await;
+                    }
                 }
                 Err(e) => {
                     tracing::error!("Demand analysis failed: {}", e);
@@ -294,14 +392,13 @@ impl CompanyOrchestrator {
     }
 
     /// Health monitoring loop
-    async fn health_monitoring_loop(&self) {
+    async fn health_monitoring_loop(&self, shutdown: CancellationToken) {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
         
         loop {
-            interval.tick().await;
-            
-            if !*self.is_running.read().await {
-                break;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
             }
 
             self.health_monitor.check_company_health(self).await;
@@ -309,15 +406,14 @@ impl CompanyOrchestrator {
     }
 
     /// Metrics update loop
-    async fn metrics_update_loop(&self) {
+    async fn metrics_update_loop(&self, shutdown: CancellationToken) {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
         let start_time = Utc::now();
         
         loop {
-            interval.tick().await;
-            
-            if !*self.is_running.read().await {
-                break;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
             }
 
             let members = self.members.read().await;
@@ -331,21 +427,25 @@ impl CompanyOrchestrator {
                 .signed_duration_since(start_time)
                 .num_seconds() as u64;
             metrics.last_updated = Utc::now();
+            let sample = metrics.clone();
             
             drop(members);
             drop(metrics);
+
+            if let Err(e) = self.persistence.record_metrics(&sample).await {
+                tracing::warn!("{}", e);
+            }
         }
     }
 
     /// Persistence save loop
-    async fn persistence_save_loop(&self) {
+    async fn persistence_save_loop(&self, shutdown: CancellationToken) {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300)); // Every 5 minutes
         
         loop {
-            interval.tick().await;
-            
-            if !*self.is_running.read().await {
-                break;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
             }
 
             if let Err(e) = self.persistence.save_company_state(self).await {
@@ -359,10 +459,12 @@ impl CompanyOrchestrator {
         // Get all pending tasks
         let tasks = self.agent_manager.list_tasks().await;
         
-        // Filter to only pending tasks
+        // Filter to pending tasks that haven't been routed to a member yet
+        let routed = self.member_tasks.read().await;
         let pending_tasks: Vec<_> = tasks.iter()
-            .filter(|t| matches!(t.status, crate::types::TaskStatus::Pending))
+            .filter(|t| matches!(t.status, crate::types::TaskStatus::Pending) && !routed.contains_key(&t.id))
             .collect();
+        drop(routed);
 
         if pending_tasks.is_empty() {
             return;
@@ -376,19 +478,12 @@ impl CompanyOrchestrator {
         // Route each task to appropriate agent based on demand
         for task in pending_tasks {
             // Find best agent for this task
-            let role = match task.r#type {
-                crate::types::TaskType::CodeGeneration => CompanyRole::BackendEngineer,
-                crate::types::TaskType::CodeAnalysis => CompanyRole::BackendEngineer,
-                crate::types::TaskType::Refactoring => CompanyRole::BackendEngineer,
-                crate::types::TaskType::Debugging => CompanyRole::QaEngineer,
-                crate::types::TaskType::Documentation => CompanyRole::DocumentationSpecialist,
-                crate::types::TaskType::Testing => CompanyRole::QaEngineer,
-            };
+            let role = Self::role_for_task_type(&task.r#type);
             
             // Find available agent with matching role
-            let members = self.members.read().await;
-            let available_agent = members.values()
-                .find(|m| m.role == role && m.is_active && m.agent.status == crate::services::agent::types::AgentStatus::Idle);
+            let available_agent = self.members.read().await.values()
+                .find(|m| m.role == role && m.is_active && m.agent.status == crate::services::agent::types::AgentStatus::Idle)
+                .cloned();
 
             if let Some(member) = available_agent {
                 tracing::debug!(
@@ -397,6 +492,7 @@ impl CompanyOrchestrator {
                     member.agent.id,
                     member.role
                 );
+                self.publish_assignment(task, &member).await;
                 // Task will be picked up by agent manager's queue processor
             } else {
                 tracing::debug!(
@@ -408,6 +504,231 @@ impl CompanyOrchestrator {
         }
     }
 
+    /// Hand the company a specific job
+    ///
+    /// The task is queued with the agent manager like any other, then routed to
+    /// the idle member in the task type's role whose team has the most spare
+    /// capacity (best performer on ties). The member is marked busy and the
+    /// team's load raised until the task finishes. Without such a member it stays queued for demand routing.
+    pub async fn submit_task(
+        &self,
+        description: String,
+        task_type: TaskType,
+        priority: Priority,
+    ) -> Result<TaskSubmission, String> {
+        let role = Self::role_for_task_type(&task_type);
+        let task = self.agent_manager.create_task(AgentTask {
+            id: Uuid::new_v4().to_string(),
+            r#type: task_type,
+            description,
+            context: Default::default(),
+            priority,
+            status: crate::types::TaskStatus::Pending,
+            result: None,
+            error: None,
+            created_at: Utc::now(),
+            completed_at: None,
+        }).await?;
+
+        let member = {
+            // Choose and claim under the write lock so concurrent submissions can't pick the same member
+            let mut members = self.members.write().await;
+            let mut teams = self.teams.write().await;
+            let chosen = members.values()
+                .filter(|m| m.role == role && m.is_active && m.agent.status == crate::services::agent::types::AgentStatus::Idle)
+                .filter_map(|m| {
+                    let team = teams.get(&m.team)?;
+                    (team.current_load < team.capacity)
+                        .then(|| (team.current_load as f64 / team.capacity as f64, m))
+                })
+                .min_by(|(load_a, a), (load_b, b)| {
+                    load_a.total_cmp(load_b).then(b.performance_score.total_cmp(&a.performance_score))
+                })
+                .map(|(_, m)| m.clone());
+            if let Some(member) = &chosen {
+                if let Some(team) = teams.get_mut(&member.team) {
+                    team.current_load += 1;
+                }
+                if let Some(claimed) = members.get_mut(&member.agent.id) {
+                    claimed.agent.status = crate::services::agent::types::AgentStatus::Working;
+                    claimed.agent.current_task = Some(task.id.clone());
+                }
+            }
+            chosen
+        };
+
+        match &member {
+            Some(member) => {
+                self.assignments.write().await.insert(task.id.clone(), member.team.clone());
+                tracing::info!("Submitted task {} assigned to {} ({:?}, {})", task.id, member.agent.id, role, member.team);
+                self.publish_assignment(&task, member).await;
+            }
+            None => {
+                tracing::info!("No idle {:?} with spare team capacity; task {} stays queued", role, task.id);
+            }
+        }
+
+        Ok(TaskSubmission {
+            team: member.as_ref().map(|m| m.team.clone()),
+            queued: member.is_none(),
+            member,
+            role,
+            task,
+        })
+    }
+
+    /// Remember which member a task went to, mark it busy and announce the assignment
+    async fn publish_assignment(&self, task: &AgentTask, member: &CompanyMember) {
+        self.member_tasks.write().await.insert(task.id.clone(), member.agent.id.clone());
+        if let Some(member) = self.members.write().await.get_mut(&member.agent.id) {
+            member.agent.status = crate::services::agent::types::AgentStatus::Working;
+            member.agent.current_task = Some(task.id.clone());
+        }
+        self.agent_manager.coordination_log().publish(CoordinationEvent::TaskAssigned {
+            task_id: task.id.clone(),
+            agent_id: member.agent.id.clone(),
+            team: member.team.clone(),
+            description: task.description.clone(),
+            timestamp: Utc::now(),
+        });
+    }
+
+    /// Follow task results from the agent manager for the life of the company
+    async fn track_member_results(&self) {
+        let mut events = self.agent_manager.coordination_log().subscribe();
+        loop {
+            match events.recv().await {
+                Ok(CoordinationEvent::TaskFinished { task_id, agent_id, success, execution_time_ms, .. }) => {
+                    self.record_task_result(&task_id, &agent_id, success, execution_time_ms).await;
+                }
+                Ok(CoordinationEvent::TaskCancelled { task_id, .. }) => {
+                    // Nothing to credit, but the member would otherwise stay busy
+                    self.release_member(&task_id).await;
+                }
+                Ok(_) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Missed {} coordination events; member stats may undercount", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Update the stats of the member that executed a finished task
+    ///
+    /// `agent_id` is the agent that actually ran it; when that agent isn't a
+    /// member, the member the task was routed to is credited instead.
+    /// The performance score is a moving average of outcomes, so recent
+    /// results count most; the average time covers completed tasks only.
+    async fn record_task_result(&self, task_id: &str, agent_id: &str, success: bool, execution_time_ms: u64) {
+        let routed_to = self.release_member(task_id).await;
+        let mut members = self.members.write().await;
+        let credited = if members.contains_key(agent_id) {
+            Some(agent_id.to_string())
+        } else {
+            routed_to
+        };
+        let Some(member) = credited.and_then(|id| members.get_mut(&id)) else {
+            return;
+        };
+
+        if success {
+            member.tasks_completed += 1;
+            let completed = member.tasks_completed;
+            member.average_task_time_ms =
+                (member.average_task_time_ms * (completed - 1) + execution_time_ms) / completed;
+        } else {
+            member.tasks_failed += 1;
+        }
+        let outcome = if success { 1.0 } else { 0.0 };
+        member.performance_score = member.performance_score * (1.0 - SCORE_SMOOTHING) + outcome * SCORE_SMOOTHING;
+        member.last_active = Utc::now();
+    }
+
+    /// Forget where a task was routed and free that member; returns its id
+    async fn release_member(&self, task_id: &str) -> Option<String> {
+        let agent_id = self.member_tasks.write().await.remove(task_id)?;
+        if let Some(member) = self.members.write().await.get_mut(&agent_id) {
+            if member.agent.current_task.as_deref() == Some(task_id) {
+                member.agent.status = crate::services::agent::types::AgentStatus::Idle;
+                member.agent.current_task = None;
+            }
+        }
+        Some(agent_id)
+    }
+
+    /// Members ranked by `sort_by`, best first
+    pub async fn leaderboard(&self, sort_by: LeaderboardSort, limit: usize) -> Vec<LeaderboardEntry> {
+        let mut members: Vec<CompanyMember> = self.members.read().await.values().cloned().collect();
+        members.sort_by(|a, b| {
+            let primary = match sort_by {
+                LeaderboardSort::Score => b.performance_score.total_cmp(&a.performance_score),
+                LeaderboardSort::Completed => b.tasks_completed.cmp(&a.tasks_completed),
+                LeaderboardSort::Speed => (a.tasks_completed == 0, a.average_task_time_ms)
+                    .cmp(&(b.tasks_completed == 0, b.average_task_time_ms)),
+            };
+            primary
+                .then(b.tasks_completed.cmp(&a.tasks_completed))
+                .then_with(|| a.agent.id.cmp(&b.agent.id))
+        });
+
+        members.into_iter()
+            .take(limit)
+            .enumerate()
+            .map(|(i, m)| {
+                let finished = m.tasks_completed + m.tasks_failed;
+                LeaderboardEntry {
+                    rank: i + 1,
+                    agent_id: m.agent.id,
+                    name: m.agent.name,
+                    role: m.role,
+                    team: m.team,
+                    performance_score: m.performance_score,
+                    tasks_completed: m.tasks_completed,
+                    tasks_failed: m.tasks_failed,
+                    success_rate: (finished > 0).then(|| m.tasks_completed as f64 / finished as f64),
+                    average_task_time_ms: m.average_task_time_ms,
+                    last_active: m.last_active,
+                }
+            })
+            .collect()
+    }
+
+    /// Give back team load held by submitted tasks that have finished
+    async fn release_finished_assignments(&self) {
+        let assigned: Vec<(String, String)> = self.assignments.read().await
+            .iter()
+            .map(|(task_id, team)| (task_id.clone(), team.clone()))
+            .collect();
+
+        for (task_id, team) in assigned {
+            let running = self.agent_manager.get_task_status(&task_id).await
+                .is_some_and(|t| matches!(
+                    t.status,
+                    crate::types::TaskStatus::Pending | crate::types::TaskStatus::Waiting | crate::types::TaskStatus::Processing
+                ));
+            if running {
+                continue;
+            }
+            self.assignments.write().await.remove(&task_id);
+            if let Some(team) = self.teams.write().await.get_mut(&team) {
+                team.current_load = team.current_load.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Company role that handles a task type
+    fn role_for_task_type(task_type: &TaskType) -> CompanyRole {
+        match task_type {
+            TaskType::CodeGeneration => CompanyRole::BackendEngineer,
+            TaskType::CodeAnalysis => CompanyRole::BackendEngineer,
+            TaskType::Refactoring => CompanyRole::BackendEngineer,
+            TaskType::Debugging => CompanyRole::QaEngineer,
+            TaskType::Documentation => CompanyRole::DocumentationSpecialist,
+            TaskType::Testing => CompanyRole::QaEngineer,
+        }
+    }
+
     // Helper methods
     fn role_to_agent_type(&self, role: &CompanyRole) -> crate::services::agent::types::AgentType {
         match role {
@@ -443,11 +764,222 @@ impl CompanyOrchestrator {
         }
     }
 
+    /// Active members per role
+    async fn active_members_by_role(&self) -> HashMap<CompanyRole, usize> {
+        let mut counts = HashMap::new();
+        for member in self.members.read().await.values().filter(|m| m.is_active) {
+            *counts.entry(member.role.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Grow or shrink each role toward the scaler's target
+    ///
+    /// Growth reactivates the role's inactive members before hiring and never
+    /// takes a team past its capacity. Only idle members are deactivated,
+    /// weakest performers first, so a role with busy members may stay above
+    /// its target until they finish. Deactivated members are kept (and
+    /// persisted) as inactive, not deleted.
+    async fn apply_scaling(&self, current: &HashMap<CompanyRole, usize>, targets: &HashMap<CompanyRole, usize>) {
+        let mut changed = false;
+
+        for (role, &target) in targets {
+            let count = current.get(role).copied().unwrap_or(0);
+            if target > count {
+                changed |= self.grow_role(role, target - count).await > 0;
+            } else if target < count {
+                let mut idle: Vec<(String, f64)> = self.members.read().await.values()
+                    .filter(|m| &m.role == role
+                        && m.is_active
+                        && m.agent.status == crate::services::agent::types::AgentStatus::Idle
+                        && m.agent.current_task.is_none())
+                    .map(|m| (m.agent.id.clone(), m.performance_score))
+                    .collect();
+                idle.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+                for (agent_id, _) in idle.into_iter().take(count - target) {
+                    changed |= self.deactivate_member(&agent_id).await;
+                }
+            }
+        }
+
+        if changed {
+            self.save_state_now("agents scaled").await;
+        }
+    }
+
+    /// Add up to `wanted` active members to `role` within its team's capacity; returns how many were added
+    async fn grow_role(&self, role: &CompanyRole, wanted: usize) -> usize {
+        let team = role.team();
+        let room = self.teams.read().await.get(team)
+            .map_or(wanted, |t| t.capacity.saturating_sub(t.members.len()));
+        let adding = wanted.min(room);
+        if adding < wanted {
+            tracing::info!("{} team is at capacity; adding {} of {} {:?} members", team, adding, wanted, role);
+        }
+
+        let inactive: Vec<String> = self.members.read().await.values()
+            .filter(|m| &m.role == role && m.team == team && !m.is_active)
+            .map(|m| m.agent.id.clone())
+            .take(adding)
+            .collect();
+        for agent_id in &inactive {
+            self.reactivate_member(agent_id).await;
+        }
+        for _ in inactive.len()..adding {
+            self.insert_member(self.new_member(role, team, None, &[])).await;
+        }
+        adding
+    }
+
+    /// Take a member off its team's roster but keep its record; returns whether it was active
+    async fn deactivate_member(&self, agent_id: &str) -> bool {
+        let team = {
+            let mut members = self.members.write().await;
+            match members.get_mut(agent_id) {
+                Some(member) if member.is_active => {
+                    member.is_active = false;
+                    member.team.clone()
+                }
+                _ => return false,
+            }
+        };
+        if let Some(team) = self.teams.write().await.get_mut(&team) {
+            team.members.retain(|id| id != agent_id);
+            if team.lead.as_deref() == Some(agent_id) {
+                team.lead = None;
+            }
+        }
+        true
+    }
+
+    async fn reactivate_member(&self, agent_id: &str) {
+        let team = {
+            let mut members = self.members.write().await;
+            let Some(member) = members.get_mut(agent_id) else { return };
+            member.is_active = true;
+            member.last_active = Utc::now();
+            member.team.clone()
+        };
+        if let Some(team) = self.teams.write().await.get_mut(&team) {
+            if !team.members.iter().any(|id| id == agent_id) {
+                team.members.push(agent_id.to_string());
+            }
+        }
+    }
+
+    pub async fn scaling_policy(&self) -> ScalingPolicy {
+        self.predictive_scaler.policy().await
+    }
+
+    /// Update the scaling policy, rejecting one that is inconsistent
+    pub async fn set_scaling_policy(&self, policy: ScalingPolicy) -> Result<(), (&'static str, String)> {
+        self.predictive_scaler.set_policy(policy).await
+    }
+
+    /// Add a member (and its team roster entry), persisting immediately
+    pub async fn add_member(&self, member: CompanyMember) {
+        self.insert_member(member).await;
+        self.save_state_now("member added").await;
+    }
+
+    /// Retire a member, persisting immediately
+    pub async fn retire_member(&self, agent_id: &str) -> Option<CompanyMember> {
+        let retired = self.remove_member(agent_id).await?;
+        self.save_state_now("member retired").await;
+        Some(retired)
+    }
+
+    async fn insert_member(&self, member: CompanyMember) {
+        let agent_id = member.agent.id.clone();
+        if let Some(team) = self.teams.write().await.get_mut(&member.team) {
+            if !team.members.contains(&agent_id) {
+                team.members.push(agent_id.clone());
+            }
+        }
+        self.members.write().await.insert(agent_id, member);
+    }
+
+    async fn remove_member(&self, agent_id: &str) -> Option<CompanyMember> {
+        let retired = self.members.write().await.remove(agent_id)?;
+        if let Some(team) = self.teams.write().await.get_mut(&retired.team) {
+            team.members.retain(|id| id != agent_id);
+            if team.lead.as_deref() == Some(agent_id) {
+                team.lead = None;
+            }
+        }
+        Some(retired)
+    }
+
+    /// Replace in-memory state with a persisted snapshot
+    pub async fn restore_state(&self, snapshot: CompanyStateSnapshot) {
+        let mut members = self.members.write().await;
+        for member in snapshot.members {
+            members.insert(member.agent.id.clone(), member);
+        }
+        drop(members);
+
+        let mut teams = self.teams.write().await;
+        for team in snapshot.teams {
+            teams.insert(team.name.clone(), team);
+        }
+        drop(teams);
+
+        if let Some(metrics) = snapshot.metrics {
+            *self.metrics.write().await = metrics;
+        }
+    }
+
+    /// Event-driven save, in addition to the periodic save loop
+    async fn save_state_now(&self, reason: &str) {
+        if let Err(e) = self.persistence.save_company_state(self).await {
+            tracing::error!("Failed to save company state ({}): {}", reason, e);
+        }
+    }
+
+    /// Current teams and role counts as a portable blueprint
+    pub async fn export_blueprint(&self) -> CompanyBlueprint {
+        let members = self.members.read().await;
+        let teams = self.teams.read().await;
+        CompanyBlueprint::from_state(&members, &teams)
+    }
+
+    /// Validate a blueprint and converge the roster onto it, persisting the result
+    pub async fn import_blueprint(&self, blueprint: &CompanyBlueprint) -> anyhow::Result<ImportSummary> {
+        blueprint.validate()?;
+
+        let mut members = self.members.write().await;
+        let mut teams = self.teams.write().await;
+        let summary = blueprint.converge(&mut members, &mut teams, |role, team, skills| {
+            self.new_member(role, team, None, skills)
+        });
+        drop(teams);
+        drop(members);
+
+        tracing::info!(
+            "Imported company blueprint: {} agents added, {} retired",
+            summary.added.len(),
+            summary.retired.len()
+        );
+        self.save_state_now("blueprint imported").await;
+        Ok(summary)
+    }
+
     /// Get company metrics
     pub async fn get_metrics(&self) -> CompanyMetrics {
         self.metrics.read().await.clone()
     }
 
+    /// Downsampled metrics history; empty without a database
+    pub async fn metrics_history(
+        &self,
+        from: chrono::DateTime<Utc>,
+        to: chrono::DateTime<Utc>,
+        granularity: MetricsGranularity,
+    ) -> anyhow::Result<Vec<MetricsHistoryPoint>> {
+        self.persistence.metrics_history(from, to, granularity).await
+    }
+
     /// Get all company members
     pub async fn get_members(&self) -> Vec<CompanyMember> {
         self.members.read().await.values().cloned().collect()
@@ -458,8 +990,198 @@ impl CompanyOrchestrator {
         self.teams.read().await.values().cloned().collect()
     }
 
+    /// Visual creative engine (queue status, requests)
+    pub fn visual_engine(&self) -> Arc<VisualCreativeEngine> {
+        Arc::clone(&self.visual_engine)
+    }
+
+    /// OpenClaw Gateway client shared by collaboration and the OpenClaw routes
+    pub fn openclaw_client(&self) -> Arc<OpenClawWebSocketClient> {
+        Arc::clone(&self.openclaw_client)
+    }
+
+    /// Moltbook API client
+    pub fn moltbook_client(&self) -> Arc<MoltbookApiClient> {
+        Arc::clone(&self.moltbook_client)
+    }
+
     /// Check if company is running
     pub async fn is_running(&self) -> bool {
         *self.is_running.read().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_submitted_task_goes_to_idle_member_in_matching_role() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let router = Arc::new(ModelRouter::new(&config));
+        let agent_manager = AgentManager::new(Arc::clone(&router), Arc::clone(&config), None);
+        let orchestrator = CompanyOrchestrator::new(agent_manager, router, config, None);
+
+        // Teams are set up in the background
+        for _ in 0..100 {
+            if orchestrator.get_teams().await.iter().any(|t| t.name == "Engineering") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let engineer = orchestrator.new_member(&CompanyRole::BackendEngineer, "Engineering", None, &[]);
+        orchestrator.add_member(engineer.clone()).await;
+
+        let submission = orchestrator
+            .submit_task("Add a health endpoint".to_string(), TaskType::CodeGeneration, Priority::High)
+            .await
+            .unwrap();
+        assert_eq!(submission.role, CompanyRole::BackendEngineer);
+        assert_eq!(submission.member.map(|m| m.agent.id), Some(engineer.agent.id.clone()));
+        assert_eq!(submission.team.as_deref(), Some("Engineering"));
+        assert!(!submission.queued);
+        let engineering = orchestrator.get_teams().await.into_iter().find(|t| t.name == "Engineering").unwrap();
+        assert_eq!(engineering.current_load, 1);
+
+        // The engineer is busy until that task finishes, so the next one waits
+        let busy = orchestrator
+            .submit_task("Add a readiness endpoint".to_string(), TaskType::CodeGeneration, Priority::High)
+            .await
+            .unwrap();
+        assert!(busy.queued);
+        let member_status = |members: Vec<CompanyMember>| members.into_iter()
+            .find(|m| m.agent.id == engineer.agent.id)
+            .map(|m| (m.agent.status, m.agent.current_task))
+            .unwrap();
+        assert_eq!(
+            member_status(orchestrator.get_members().await),
+            (crate::services::agent::types::AgentStatus::Working, Some(submission.task.id.clone()))
+        );
+        orchestrator.record_task_result(&submission.task.id, "executor-1", true, 10).await;
+        assert_eq!(
+            member_status(orchestrator.get_members().await),
+            (crate::services::agent::types::AgentStatus::Idle, None)
+        );
+
+        // Nobody writes documentation yet, so that task waits in the queue
+        let queued = orchestrator
+            .submit_task("Document the API".to_string(), TaskType::Documentation, Priority::Low)
+            .await
+            .unwrap();
+        assert!(queued.queued && queued.member.is_none() && queued.team.is_none());
+        assert_eq!(queued.role, CompanyRole::DocumentationSpecialist);
+    }
+
+    #[tokio::test]
+    async fn test_stop_waits_for_loops_and_start_is_idempotent() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let router = Arc::new(ModelRouter::new(&config));
+        let agent_manager = AgentManager::new(Arc::clone(&router), Arc::clone(&config), None);
+        let orchestrator = CompanyOrchestrator::new(agent_manager, router, config, None);
+
+        // Operation starts on its own once the company is initialized
+        for _ in 0..100 {
+            if orchestrator.is_running().await {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(!orchestrator.start().await);
+
+        // Loops sleeping on minute-long intervals still exit right away
+        let stopped = tokio::time::timeout(std::time::Duration::from_secs(2), orchestrator.stop()).await;
+        assert_eq!(stopped.ok(), Some(true));
+        assert!(!orchestrator.is_running().await);
+        assert!(!orchestrator.stop().await);
+
+        assert!(orchestrator.start().await);
+        assert!(orchestrator.is_running().await);
+        assert!(orchestrator.stop().await);
+    }
+
+    #[tokio::test]
+    async fn test_finished_tasks_update_member_stats_and_leaderboard() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let router = Arc::new(ModelRouter::new(&config));
+        let agent_manager = AgentManager::new(Arc::clone(&router), Arc::clone(&config), None);
+        let orchestrator = CompanyOrchestrator::new(agent_manager, router, config, None);
+
+        let fast = orchestrator.new_member(&CompanyRole::BackendEngineer, "Engineering", None, &[]);
+        let slow = orchestrator.new_member(&CompanyRole::QaEngineer, "Engineering", None, &[]);
+        orchestrator.add_member(fast.clone()).await;
+        orchestrator.add_member(slow.clone()).await;
+
+        let results = [("t1", &fast, true, 1_000), ("t2", &fast, true, 3_000), ("t3", &slow, true, 9_000), ("t4", &slow, false, 0)];
+        for (task_id, member, success, time_ms) in results {
+            orchestrator.member_tasks.write().await.insert(task_id.to_string(), member.agent.id.clone());
+            orchestrator.record_task_result(task_id, &member.agent.id, success, time_ms).await;
+        }
+        // Tasks neither routed to nor run by a member are ignored
+        orchestrator.record_task_result("unrouted", "not-a-member", true, 5).await;
+
+        let by_speed = orchestrator.leaderboard(LeaderboardSort::Speed, 100).await;
+        let first = &by_speed[0];
+        assert_eq!(first.agent_id, fast.agent.id);
+        assert_eq!((first.tasks_completed, first.average_task_time_ms), (2, 2_000));
+        assert_eq!(first.success_rate, Some(1.0));
+
+        let by_score = orchestrator.leaderboard(LeaderboardSort::Score, 100).await;
+        let slow_entry = by_score.iter().find(|e| e.agent_id == slow.agent.id).unwrap();
+        assert_eq!((slow_entry.tasks_completed, slow_entry.tasks_failed), (1, 1));
+        assert!(slow_entry.performance_score < 1.0);
+        assert_eq!(by_score[0].rank, 1);
+
+        assert_eq!(orchestrator.leaderboard(LeaderboardSort::Completed, 1).await.len(), 1);
+
+        // A task routed to one member but run by another credits the one that ran it
+        orchestrator.member_tasks.write().await.insert("t5".to_string(), fast.agent.id.clone());
+        orchestrator.record_task_result("t5", &slow.agent.id, true, 4_000).await;
+        let members = orchestrator.members.read().await;
+        assert_eq!(members[&fast.agent.id].tasks_completed, 2);
+        assert_eq!(members[&slow.agent.id].tasks_completed, 2);
+        drop(members);
+        assert!(orchestrator.member_tasks.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scaling_deactivates_and_stays_within_team_capacity() {
+        let config = Arc::new(Config::from_env().unwrap());
+        let router = Arc::new(ModelRouter::new(&config));
+        let agent_manager = AgentManager::new(Arc::clone(&router), Arc::clone(&config), None);
+        let orchestrator = CompanyOrchestrator::new(agent_manager, router, config, None);
+        for _ in 0..100 {
+            if orchestrator.get_teams().await.iter().any(|t| t.name == "Engineering") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let role = CompanyRole::BackendEngineer;
+        let engineering = || async {
+            orchestrator.get_teams().await.into_iter().find(|t| t.name == "Engineering").unwrap()
+        };
+
+        let current = orchestrator.active_members_by_role().await;
+        let count = current.get(&role).copied().unwrap_or(0);
+        orchestrator.apply_scaling(&current, &HashMap::from([(role.clone(), count + 2)])).await;
+        let current = orchestrator.active_members_by_role().await;
+        assert_eq!(current[&role], count + 2);
+
+        // Shrinking keeps the member on record, just inactive and off the roster
+        orchestrator.apply_scaling(&current, &HashMap::from([(role.clone(), count + 1)])).await;
+        let inactive: Vec<_> = orchestrator.members.read().await.values()
+            .filter(|m| m.role == role && !m.is_active)
+            .map(|m| m.agent.id.clone())
+            .collect();
+        assert_eq!(inactive.len(), 1);
+        assert!(!engineering().await.members.contains(&inactive[0]));
+
+        // Growing again brings that member back before hiring, and stops at capacity
+        let current = orchestrator.active_members_by_role().await;
+        let room = { let team = engineering().await; team.capacity - team.members.len() };
+        orchestrator.apply_scaling(&current, &HashMap::from([(role.clone(), current[&role] + room + 5)])).await;
+        let team = engineering().await;
+        assert_eq!(team.members.len(), team.capacity);
+        assert!(team.members.contains(&inactive[0]));
+        assert!(orchestrator.members.read().await[&inactive[0]].is_active);
+    }
+}
@@ -1,27 +1,95 @@
 /**
  * Predictive Scaling System
  * 
- * Predicts demand and scales agent resources accordingly
+ * Predicts demand and scales agent resources accordingly, within the
+ * operator's `ScalingPolicy`
  */
 use std::sync::Arc;
 use std::collections::HashMap;
-use chrono::Utc;
+use chrono::{DateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
 use crate::services::agent::AgentManager;
 use super::types::{DemandAnalysis, CompanyRole};
 
+/// Tasks one agent can work on concurrently when fully utilized
+const TASKS_PER_AGENT: f64 = 5.0;
+
+/// Limits on how many agents each role may have and how quickly that changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScalingPolicy {
+    pub min_agents_per_role: usize, // Kept even with no demand, so strategic roles persist
+    pub max_agents_per_role: usize,
+    pub target_utilization: f64, // Share of agent capacity predicted demand should fill, (0, 1]
+    pub scale_up_cooldown_secs: u64,
+    pub scale_down_cooldown_secs: u64,
+}
+
+impl ScalingPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        let utilization = config.scaling_target_utilization;
+        Self {
+            min_agents_per_role: config.scaling_min_agents_per_role,
+            max_agents_per_role: config.scaling_max_agents_per_role.max(config.scaling_min_agents_per_role),
+            target_utilization: if utilization > 0.0 && utilization <= 1.0 { utilization } else { 0.8 },
+            scale_up_cooldown_secs: config.scaling_up_cooldown_secs,
+            scale_down_cooldown_secs: config.scaling_down_cooldown_secs,
+        }
+    }
+
+    /// Check the policy is usable, naming the offending field if not
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        if self.max_agents_per_role < self.min_agents_per_role {
+            return Err((
+                "max_agents_per_role",
+                format!(
+                    "max_agents_per_role ({}) must be at least min_agents_per_role ({})",
+                    self.max_agents_per_role, self.min_agents_per_role
+                ),
+            ));
+        }
+        if !(self.target_utilization > 0.0 && self.target_utilization <= 1.0) {
+            return Err((
+                "target_utilization",
+                "target_utilization must be greater than 0 and at most 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Demand samples needed before scaling acts; a short history predicts too little demand
+const MIN_DEMAND_SAMPLES: usize = 12;
+
 pub struct PredictiveScaler {
     agent_manager: Arc<AgentManager>,
     demand_history: Arc<tokio::sync::RwLock<Vec<(chrono::DateTime<chrono::Utc>, DemandAnalysis)>>>,
+    policy: tokio::sync::RwLock<ScalingPolicy>,
+    last_scaled: tokio::sync::RwLock<HashMap<CompanyRole, DateTime<Utc>>>, // Last change per role, for cooldowns
 }
 
 impl PredictiveScaler {
-    pub fn new(agent_manager: Arc<AgentManager>) -> Self {
+    pub fn new(agent_manager: Arc<AgentManager>, policy: ScalingPolicy) -> Self {
         Self {
             agent_manager,
             demand_history: Arc::new(tokio::sync::RwLock::new(Vec::new())),
+            policy: tokio::sync::RwLock::new(policy),
+            last_scaled: tokio::sync::RwLock::new(HashMap::new()),
         }
     }
 
+    pub async fn policy(&self) -> ScalingPolicy {
+        self.policy.read().await.clone()
+    }
+
+    /// Replace the policy; takes effect on the next scaling pass
+    pub async fn set_policy(&self, policy: ScalingPolicy) -> Result<(), (&'static str, String)> {
+        policy.validate()?;
+        tracing::info!("Scaling policy updated: {:?}", policy);
+        *self.policy.write().await = policy;
+        Ok(())
+    }
+
     /// Record demand for predictive analysis
     pub async fn record_demand(&self, demand: DemandAnalysis) {
         let mut history = self.demand_history.write().await;
@@ -33,6 +101,21 @@ impl PredictiveScaler {
         }
     }
 
+    /// Whether enough demand has been recorded for predictions to be worth acting on
+    pub async fn has_demand_history(&self) -> bool {
+        self.demand_history.read().await.len() >= MIN_DEMAND_SAMPLES
+    }
+
+    /// Treat every role as just scaled, so a restart waits out the cooldowns
+    /// before resizing a roster it knows nothing about yet
+    pub async fn seed_cooldowns(&self) {
+        let now = Utc::now();
+        let mut last_scaled = self.last_scaled.write().await;
+        for role in CompanyRole::ALL.iter() {
+            last_scaled.entry(role.clone()).or_insert(now);
+        }
+    }
+
     /// Predict future demand based on historical patterns
     pub async fn predict_demand(&self, hours_ahead: u32) -> DemandAnalysis {
         let history = self.demand_history.read().await;
@@ -81,6 +164,20 @@ impl PredictiveScaler {
 
         let predicted_demand = (avg_demand as f64 * time_multiplier * hours_ahead as f64) as u32;
 
+        // Concurrent demand per role: recent average, adjusted for time of day
+        let mut role_totals: HashMap<CompanyRole, u32> = HashMap::new();
+        for (_, demand) in &recent_history {
+            for (role, count) in &demand.demand_by_role {
+                *role_totals.entry(role.clone()).or_insert(0) += count;
+            }
+        }
+        let demand_by_role = role_totals.into_iter()
+            .map(|(role, total)| {
+                let average = total as f64 / recent_history.len() as f64;
+                (role, (average * time_multiplier).ceil() as u32)
+            })
+            .collect();
+
         DemandAnalysis {
             total_demand: predicted_demand,
             urgent_tasks: (predicted_demand as f64 * 0.1) as u32,
@@ -88,7 +185,7 @@ impl PredictiveScaler {
             medium_priority_tasks: (predicted_demand as f64 * 0.4) as u32,
             low_priority_tasks: (predicted_demand as f64 * 0.2) as u32,
             demand_by_type: HashMap::new(),
-            demand_by_role: HashMap::new(),
+            demand_by_role,
             estimated_completion_time: Some(predicted_demand as u64 * 30000), // 30s per task
             resource_requirements: super::types::ResourceRequirements {
                 agents_needed: HashMap::new(),
@@ -100,28 +197,165 @@ impl PredictiveScaler {
         }
     }
 
-    /// Calculate optimal agent count for predicted demand
+    /// Calculate optimal agent count for every role, within the policy's bounds
     pub async fn calculate_optimal_agents(&self, predicted_demand: &DemandAnalysis) -> HashMap<CompanyRole, usize> {
-        let mut optimal_agents = HashMap::new();
+        let policy = self.policy.read().await;
+        let capacity = TASKS_PER_AGENT * policy.target_utilization;
 
-        // Calculate agents needed based on predicted demand
-        for (role, demand_count) in &predicted_demand.demand_by_role {
-            // Assume each agent can handle ~5 tasks concurrently
-            let needed = (*demand_count as f64 / 5.0).ceil() as usize;
-            optimal_agents.insert(role.clone(), needed.max(1));
+        // Roles without demand still get the minimum
+        CompanyRole::ALL.iter()
+            .map(|role| {
+                let demand = predicted_demand.demand_by_role.get(role).copied().unwrap_or(0);
+                let needed = (demand as f64 / capacity).ceil() as usize;
+                (role.clone(), needed.clamp(policy.min_agents_per_role, policy.max_agents_per_role))
+            })
+            .collect()
+    }
+
+    /// Decide which roles to resize now, given their current agent counts
+    ///
+    /// Returns the new count for each role that should change. A role that
+    /// changed recently waits out the cooldown for the direction it would move
+    /// in, unless it is outside the policy's bounds.
+    pub async fn scale_agents(
+        &self,
+        current: &HashMap<CompanyRole, usize>,
+        optimal_agents: &HashMap<CompanyRole, usize>,
+    ) -> HashMap<CompanyRole, usize> {
+        self.scale_agents_at(current, optimal_agents, Utc::now()).await
+    }
+
+    async fn scale_agents_at(
+        &self,
+        current: &HashMap<CompanyRole, usize>,
+        optimal_agents: &HashMap<CompanyRole, usize>,
+        now: DateTime<Utc>,
+    ) -> HashMap<CompanyRole, usize> {
+        let policy = self.policy.read().await.clone();
+        let mut last_scaled = self.last_scaled.write().await;
+        let mut changes = HashMap::new();
+
+        for (role, &target) in optimal_agents {
+            let count = current.get(role).copied().unwrap_or(0);
+            if target == count {
+                continue;
+            }
+
+            let out_of_bounds = count < policy.min_agents_per_role || count > policy.max_agents_per_role;
+            let cooldown = if target > count { policy.scale_up_cooldown_secs } else { policy.scale_down_cooldown_secs };
+            let cooling_down = last_scaled.get(role)
+                .is_some_and(|at| now.signed_duration_since(*at).num_seconds() < cooldown as i64);
+            if cooling_down && !out_of_bounds {
+                continue;
+            }
+
+            last_scaled.insert(role.clone(), now);
+            changes.insert(role.clone(), target);
         }
 
-        optimal_agents
+        if !changes.is_empty() {
+            tracing::info!("Scaling agents: {:?}", changes);
+        }
+        changes
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    /// Scale agents up or down based on prediction
-    pub async fn scale_agents(&self, optimal_agents: &HashMap<CompanyRole, usize>) {
-        // In production, this would:
-        // 1. Compare current agent count with optimal
-        // 2. Create new agents if needed
-        // 3. Deactivate excess agents
-        // 4. Balance load across agents
+    fn scaler(policy: ScalingPolicy) -> PredictiveScaler {
+        let config = Arc::new(Config::from_env().unwrap());
+        let router = Arc::new(crate::services::ai::router::ModelRouter::new(&config));
+        let agent_manager = AgentManager::new(router, config, None);
+        PredictiveScaler::new(agent_manager, policy)
+    }
 
-        tracing::debug!("Scaling agents based on prediction: {:?}", optimal_agents);
+    fn demand(by_role: &[(CompanyRole, u32)]) -> DemandAnalysis {
+        DemandAnalysis {
+            total_demand: by_role.iter().map(|(_, count)| count).sum(),
+            urgent_tasks: 0,
+            high_priority_tasks: 0,
+            medium_priority_tasks: 0,
+            low_priority_tasks: 0,
+            demand_by_type: HashMap::new(),
+            demand_by_role: by_role.iter().cloned().collect(),
+            estimated_completion_time: None,
+            resource_requirements: super::super::types::ResourceRequirements {
+                agents_needed: HashMap::new(),
+                estimated_tokens: 0,
+                estimated_time_ms: 0,
+                visual_assets_needed: false,
+                collaboration_needed: false,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_targets_respect_bounds_and_cooldowns() {
+        let scaler = scaler(ScalingPolicy {
+            min_agents_per_role: 1,
+            max_agents_per_role: 3,
+            target_utilization: 0.5,
+            scale_up_cooldown_secs: 60,
+            scale_down_cooldown_secs: 600,
+        });
+
+        // 2.5 tasks per agent at 50% utilization: 6 tasks need 3, 100 are capped at 3
+        let optimal = scaler.calculate_optimal_agents(&demand(&[
+            (CompanyRole::BackendEngineer, 6),
+            (CompanyRole::QaEngineer, 100),
+        ])).await;
+        assert_eq!(optimal[&CompanyRole::BackendEngineer], 3);
+        assert_eq!(optimal[&CompanyRole::QaEngineer], 3);
+        // No demand still keeps the minimum
+        assert_eq!(optimal[&CompanyRole::Ceo], 1);
+        assert_eq!(optimal.len(), CompanyRole::ALL.len());
+
+        let start = Utc::now();
+        let current = HashMap::from([(CompanyRole::BackendEngineer, 1)]);
+        let targets = HashMap::from([(CompanyRole::BackendEngineer, 3)]);
+        let changes = scaler.scale_agents_at(&current, &targets, start).await;
+        assert_eq!(changes.get(&CompanyRole::BackendEngineer), Some(&3));
+
+        // Shrinking right after growing waits for the scale-down cooldown
+        let current = HashMap::from([(CompanyRole::BackendEngineer, 3)]);
+        let targets = HashMap::from([(CompanyRole::BackendEngineer, 1)]);
+        let later = start + chrono::Duration::seconds(120);
+        assert!(scaler.scale_agents_at(&current, &targets, later).await.is_empty());
+        let much_later = start + chrono::Duration::seconds(601);
+        assert_eq!(scaler.scale_agents_at(&current, &targets, much_later).await.len(), 1);
+
+        // A role below the minimum is restored despite its cooldown
+        let current = HashMap::from([(CompanyRole::BackendEngineer, 0)]);
+        assert_eq!(scaler.scale_agents_at(&current, &targets, much_later).await.len(), 1);
+
+        let invalid = ScalingPolicy { max_agents_per_role: 0, ..scaler.policy().await };
+        assert_eq!(scaler.set_policy(invalid).await.unwrap_err().0, "max_agents_per_role");
+    }
+
+    #[tokio::test]
+    async fn test_scaling_waits_for_history_and_cooldowns_after_start() {
+        let scaler = scaler(ScalingPolicy {
+            min_agents_per_role: 1,
+            max_agents_per_role: 3,
+            target_utilization: 0.5,
+            scale_up_cooldown_secs: 60,
+            scale_down_cooldown_secs: 600,
+        });
+        scaler.seed_cooldowns().await;
+
+        // Just started: nothing shrinks until the scale-down cooldown has passed
+        let current = HashMap::from([(CompanyRole::BackendEngineer, 3)]);
+        let targets = HashMap::from([(CompanyRole::BackendEngineer, 1)]);
+        assert!(scaler.scale_agents_at(&current, &targets, Utc::now()).await.is_empty());
+        let later = Utc::now() + chrono::Duration::seconds(601);
+        assert_eq!(scaler.scale_agents_at(&current, &targets, later).await.len(), 1);
+
+        assert!(!scaler.has_demand_history().await);
+        for _ in 0..MIN_DEMAND_SAMPLES {
+            scaler.record_demand(demand(&[(CompanyRole::BackendEngineer, 2)])).await;
+        }
+        assert!(scaler.has_demand_history().await);
     }
 }
@@ -39,6 +39,40 @@ pub enum CompanyRole {
     CustomerSupport,
 }
 
+impl CompanyRole {
+    pub const ALL: [CompanyRole; 13] = [
+        CompanyRole::Ceo,
+        CompanyRole::Cto,
+        CompanyRole::ProductManager,
+        CompanyRole::BackendEngineer,
+        CompanyRole::FrontendEngineer,
+        CompanyRole::DevOpsEngineer,
+        CompanyRole::QaEngineer,
+        CompanyRole::UiDesigner,
+        CompanyRole::UxDesigner,
+        CompanyRole::VisualDesigner,
+        CompanyRole::ContentCreator,
+        CompanyRole::DocumentationSpecialist,
+        CompanyRole::CustomerSupport,
+    ];
+
+    /// Team that new members in this role join
+    pub fn team(&self) -> &'static str {
+        match self {
+            CompanyRole::Ceo | CompanyRole::Cto | CompanyRole::ProductManager => "Leadership",
+            CompanyRole::BackendEngineer
+            | CompanyRole::FrontendEngineer
+            | CompanyRole::DevOpsEngineer
+            | CompanyRole::QaEngineer => "Engineering",
+            CompanyRole::UiDesigner
+            | CompanyRole::UxDesigner
+            | CompanyRole::VisualDesigner
+            | CompanyRole::ContentCreator => "Creative",
+            CompanyRole::DocumentationSpecialist | CompanyRole::CustomerSupport => "Support",
+        }
+    }
+}
+
 /// Agent company member
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompanyMember {
@@ -66,6 +100,18 @@ pub struct Team {
     pub current_load: usize,
 }
 
+/// Where a manually submitted task was routed
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskSubmission {
+    pub task: AgentTask,
+    /// Role that handles this task type
+    pub role: CompanyRole,
+    /// Member it was assigned to; None while it waits in the queue
+    pub member: Option<CompanyMember>,
+    pub team: Option<String>,
+    pub queued: bool,
+}
+
 /// Demand analysis result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DemandAnalysis {
@@ -129,15 +175,127 @@ pub enum VisualCreativeStatus {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisualCreativeResult {
-    pub asset_url: String,
+    pub asset_url: String, // Primary asset; the first of `asset_urls`
+    #[serde(default)]
+    pub asset_urls: Vec<String>, // Every asset produced, e.g. one per requested variation
     pub asset_type: String,
     pub metadata: HashMap<String, serde_json::Value>,
     pub generation_time_ms: u64,
 }
 
+/// Whether the company can currently do AI work
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CompanyState {
+    #[default]
+    Running,
+    /// No AI provider is available; task routing is paused until one recovers
+    Degraded,
+}
+
+/// Ordering for the member leaderboard
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardSort {
+    /// Highest performance score first
+    #[default]
+    Score,
+    /// Most tasks completed first
+    Completed,
+    /// Fastest average task time first; members with no completed tasks last
+    Speed,
+}
+
+/// A member's standing on the leaderboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub agent_id: String,
+    pub name: String,
+    pub role: CompanyRole,
+    pub team: String,
+    pub performance_score: f64,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    pub success_rate: Option<f64>, // None until the member has finished a task
+    pub average_task_time_ms: u64,
+    pub last_active: DateTime<Utc>,
+}
+
+/// Most points one metrics history query returns
+pub const MAX_HISTORY_POINTS: i64 = 1_000;
+
+/// Bucket size for downsampled metrics history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsGranularity {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl MetricsGranularity {
+    /// Finest granularity that keeps a range to a chartable number of points
+    pub fn for_range(from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        let span = to.signed_duration_since(from);
+        if span <= chrono::Duration::hours(6) {
+            MetricsGranularity::Minute
+        } else if span <= chrono::Duration::days(14) {
+            MetricsGranularity::Hour
+        } else {
+            MetricsGranularity::Day
+        }
+    }
+
+    pub fn bucket_secs(&self) -> i64 {
+        match self {
+            MetricsGranularity::Minute => 60,
+            MetricsGranularity::Hour => 3_600,
+            MetricsGranularity::Day => 86_400,
+        }
+    }
+
+    /// Buckets the range spans at this granularity
+    pub fn buckets(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> i64 {
+        let secs = to.signed_duration_since(from).num_seconds().max(0);
+        (secs + self.bucket_secs() - 1) / self.bucket_secs()
+    }
+
+    /// This granularity or the next coarser one that keeps the range within
+    /// `MAX_HISTORY_POINTS`; `None` if even daily buckets don't
+    pub fn capped(self, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<Self> {
+        [MetricsGranularity::Minute, MetricsGranularity::Hour, MetricsGranularity::Day]
+            .into_iter()
+            .skip_while(|granularity| *granularity != self)
+            .find(|granularity| granularity.buckets(from, to) <= MAX_HISTORY_POINTS)
+    }
+}
+
+/// One bucket of company metrics history
+///
+/// Gauges are averaged over the bucket; running totals are the bucket's
+/// latest value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsHistoryPoint {
+    pub bucket_start: DateTime<Utc>,
+    pub samples: u64,
+    pub degraded_samples: u64, // Samples taken while no AI provider was available
+    pub total_agents: f64,
+    pub active_agents: f64,
+    pub success_rate: f64,
+    pub average_task_time_ms: f64,
+    pub total_tasks_completed: u64,
+    pub total_tasks_failed: u64,
+    pub total_tokens_used: u64,
+    pub visual_creatives_completed: u64,
+    pub collaborations_count: u64,
+}
+
 /// Company metrics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompanyMetrics {
+    #[serde(default)]
+    pub state: CompanyState,
     pub total_agents: usize,
     pub active_agents: usize,
     pub total_tasks_completed: u64,
@@ -4,15 +4,193 @@
  * Handles visual creative tasks: image generation, UI mockups, etc.
  */
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use uuid::Uuid;
 use chrono::Utc;
 
 use crate::services::ai::router::ModelRouter;
 use crate::services::visual::{ImageGenerationService, AssetStorage, FigmaIntegration};
+use crate::services::visual::image_generation::ImageGenerationResponse;
+use crate::services::visual::optimize::{optimize_image, OptimizeOptions, OutputFormat};
+use futures::stream::{self, StreamExt};
 use crate::config::Config;
+use crate::security::{resolve_public_host, validate_outbound_url};
 use super::types::{VisualCreativeRequest, VisualCreativeType, VisualCreativeStatus, VisualCreativeResult, Priority};
 
+/// Words that suggest a prompt already describes style/composition in detail
+const DETAIL_KEYWORDS: &[&str] = &[
+    "lighting", "style", "composition", "background", "foreground", "palette", "color",
+    "perspective", "lens", "photorealistic", "illustration", "render", "texture",
+    "mood", "shot", "angle", "resolution", "watercolor", "minimalist",
+];
+
+/// Most image variations one request may ask for
+const MAX_VARIATIONS: usize = 8;
+
+/// Variations of one request generated at the same time
+const VARIATION_CONCURRENCY: usize = 3;
+
+/// Number of images asked for in `requirements.variations`, within 1..=MAX_VARIATIONS
+fn requested_variations(requirements: &HashMap<String, serde_json::Value>) -> usize {
+    requirements
+        .get("variations")
+        .and_then(|v| v.as_u64())
+        .map(|n| (n as usize).clamp(1, MAX_VARIATIONS))
+        .unwrap_or(1)
+}
+
+/// Outcome of the prompt-enhancement step, recorded in result metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptEnhancement {
+    Enhanced,
+    SkippedDetailed,
+    Disabled,
+    Failed,
+}
+
+impl PromptEnhancement {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromptEnhancement::Enhanced => "enhanced",
+            PromptEnhancement::SkippedDetailed => "skipped_detailed",
+            PromptEnhancement::Disabled => "disabled",
+            PromptEnhancement::Failed => "failed",
+        }
+    }
+}
+
+/// Decide up front whether enhancement can be skipped; None means call the model
+pub fn enhancement_skip_reason(description: &str, enabled: bool, detailed_word_count: usize) -> Option<PromptEnhancement> {
+    if !enabled {
+        return Some(PromptEnhancement::Disabled);
+    }
+
+    let words = description.split_whitespace().count();
+    let lower = description.to_lowercase();
+    let keyword_hits = DETAIL_KEYWORDS.iter().filter(|k| lower.contains(*k)).count();
+
+    // Long prompts, or medium ones that already spell out style, are sent as-is
+    if words >= detailed_word_count || (words * 2 >= detailed_word_count && keyword_hits >= 3) {
+        return Some(PromptEnhancement::SkippedDetailed);
+    }
+
+    None
+}
+
+/// Bounded, priority-ordered scheduler for visual jobs
+///
+/// Jobs wait (as `Pending`) until a slot frees up; the highest priority job,
+/// oldest first within a priority, gets the next slot.
+pub struct VisualJobScheduler {
+    slots: Arc<Semaphore>,
+    max_concurrent: AtomicUsize,
+    owed: Arc<std::sync::Mutex<usize>>, // Slots to retire after shrinking, taken back as running jobs finish
+    queue: std::sync::Mutex<BinaryHeap<(u8, Reverse<u64>, String)>>,
+    sequence: AtomicU64,
+}
+
+impl VisualJobScheduler {
+    pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent: AtomicUsize::new(max_concurrent),
+            owed: Arc::new(std::sync::Mutex::new(0)),
+            queue: std::sync::Mutex::new(BinaryHeap::new()),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enqueue(&self, request_id: String, priority: &Priority) {
+        let rank = match priority {
+            Priority::Urgent => 3,
+            Priority::High => 2,
+            Priority::Medium => 1,
+            Priority::Low => 0,
+        };
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
+        self.queue.lock().unwrap().push((rank, Reverse(sequence), request_id));
+    }
+
+    /// Wait for a free slot, then take the best queued job
+    ///
+    /// Callers must call this once per `enqueue`, so a job is always available.
+    pub async fn acquire_next(&self) -> Option<(VisualJobSlot, String)> {
+        let permit = Arc::clone(&self.slots).acquire_owned().await.ok()?;
+        let slot = VisualJobSlot {
+            permit: Some(permit),
+            owed: Arc::clone(&self.owed),
+        };
+        let next = self.queue.lock().unwrap().pop()?;
+        Some((slot, next.2))
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    pub fn running(&self) -> usize {
+        let owed = self.owed.lock().unwrap();
+        (self.max_concurrent() + *owed).saturating_sub(self.slots.available_permits())
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent.load(Ordering::SeqCst)
+    }
+
+    /// Resize the pool; when shrinking, idle slots are retired now and busy ones as their jobs finish
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        let max_concurrent = max_concurrent.max(1);
+        let mut owed = self.owed.lock().unwrap();
+        let previous = self.max_concurrent.swap(max_concurrent, Ordering::SeqCst);
+
+        if max_concurrent > previous {
+            // Growing cancels retirements still pending before adding slots
+            let grown = max_concurrent - previous;
+            let repaid = grown.min(*owed);
+            *owed -= repaid;
+            self.slots.add_permits(grown - repaid);
+        } else if max_concurrent < previous {
+            *owed += previous - max_concurrent;
+            let idle = self.slots.available_permits().min(*owed);
+            if idle > 0 {
+                if let Ok(permits) = self.slots.try_acquire_many(idle as u32) {
+                    permits.forget();
+                    *owed -= idle;
+                }
+            }
+        }
+    }
+}
+
+/// A running job's slot; freed when dropped, or retired if the pool has shrunk since
+pub struct VisualJobSlot {
+    permit: Option<OwnedSemaphorePermit>,
+    owed: Arc<std::sync::Mutex<usize>>,
+}
+
+impl Drop for VisualJobSlot {
+    fn drop(&mut self) {
+        let mut owed = self.owed.lock().unwrap();
+        if *owed > 0 {
+            *owed -= 1;
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VisualQueueStatus {
+    pub queue_depth: usize,
+    pub running: usize,
+    pub max_concurrent: usize,
+}
+
 pub struct VisualCreativeEngine {
     router: Arc<ModelRouter>,
     config: Arc<Config>,
@@ -20,6 +198,7 @@ pub struct VisualCreativeEngine {
     asset_storage: Arc<AssetStorage>,
     figma: Arc<FigmaIntegration>,
     requests: Arc<tokio::sync::RwLock<HashMap<String, VisualCreativeRequest>>>,
+    scheduler: Arc<VisualJobScheduler>,
 }
 
 impl VisualCreativeEngine {
@@ -32,8 +211,9 @@ impl VisualCreativeEngine {
             Arc::clone(&config),
             Arc::clone(&router),
         ));
-        let asset_storage = Arc::new(AssetStorage::new(database));
+        let asset_storage = Arc::new(AssetStorage::new(database, config.asset_storage_dir.clone()));
         let figma = Arc::new(FigmaIntegration::new(Arc::clone(&config)));
+        let scheduler = Arc::new(VisualJobScheduler::new(config.max_concurrent_visual_jobs));
 
         Self {
             router,
@@ -42,6 +222,7 @@ impl VisualCreativeEngine {
             asset_storage,
             figma,
             requests: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            scheduler,
         }
     }
 
@@ -54,7 +235,7 @@ impl VisualCreativeEngine {
         priority: Priority,
     ) -> String {
         let request_id = Uuid::new_v4().to_string();
-        
+
         let request = VisualCreativeRequest {
             id: request_id.clone(),
             request_type,
@@ -68,15 +249,18 @@ impl VisualCreativeEngine {
             result: None,
         };
 
+        // Stored before it's queued, so a worker never dequeues an id it can't find
         let mut requests = self.requests.write().await;
         requests.insert(request_id.clone(), request);
+        self.scheduler.enqueue(request_id.clone(), &requests[&request_id].priority);
         drop(requests);
 
-        // Process request asynchronously
+        // Process asynchronously once a worker slot is free (stays Pending until then)
         let engine = Arc::new(self.clone());
-        let request_id_clone = request_id.clone();
         tokio::spawn(async move {
-            engine.process_request(&request_id_clone).await;
+            if let Some((_permit, next_id)) = engine.scheduler.acquire_next().await {
+                engine.process_request(&next_id).await;
+            }
         });
 
         request_id
@@ -142,6 +326,10 @@ impl VisualCreativeEngine {
     }
 
     /// Generate an image using AI
+    ///
+    /// `requirements.variations` asks for several images from the same prompt
+    /// (up to MAX_VARIATIONS); they are generated concurrently and each is
+    /// stored as its own asset. Failed variations are skipped unless all fail.
     async fn generate_image(
         &self,
         description: &str,
@@ -149,9 +337,6 @@ impl VisualCreativeEngine {
     ) -> anyhow::Result<VisualCreativeResult> {
         let start_time = std::time::Instant::now();
 
-        // Enhance prompt using AI router
-        let enhanced_prompt = self.enhance_prompt(description).await?;
-
         // Determine model from requirements or default to DALL-E 3
         let model = requirements
             .get("model")
@@ -174,46 +359,130 @@ impl VisualCreativeEngine {
             })
             .unwrap_or(crate::services::visual::image_generation::ImageSize::Square1024);
 
-        // Generate image using actual API
-        let image_request = crate::services::visual::image_generation::ImageGenerationRequest {
-            prompt: enhanced_prompt.clone(),
+        // Cached on the prompt as submitted, since enhancement varies between runs
+        let mut image_request = crate::services::visual::image_generation::ImageGenerationRequest {
+            prompt: description.to_string(),
             model,
             size,
             quality: crate::services::visual::image_generation::ImageQuality::HD,
             style: Some(crate::services::visual::image_generation::ImageStyle::Vivid),
             n: Some(1),
         };
+        let cache_key = image_request.clone();
+        let bypass_cache = requirements
+            .get("bypass_cache")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let variations = requested_variations(requirements);
+
+        // Asking for several variations means wanting new images
+        if !bypass_cache && variations == 1 {
+            if let Some(cached) = self.image_service.cache().get(&cache_key) {
+                tracing::debug!("Reusing cached image asset {}", cached.asset_id);
+                return Ok(VisualCreativeResult {
+                    asset_urls: vec![cached.asset_url.clone()],
+                    asset_url: cached.asset_url,
+                    asset_type: "image".to_string(),
+                    metadata: HashMap::from([
+                        ("asset_id".to_string(), serde_json::json!(cached.asset_id)),
+                        ("model".to_string(), serde_json::json!(cached.response.model)),
+                        ("size".to_string(), serde_json::json!(cached.response.size)),
+                        ("cached".to_string(), serde_json::json!(true)),
+                    ]),
+                    generation_time_ms: start_time.elapsed().as_millis() as u64,
+                });
+            }
+        }
 
-        let image_response = self.image_service.generate(image_request).await?;
+        // Enhance prompt using AI router (skipped for detailed prompts)
+        let (enhanced_prompt, enhancement) = self.enhance_prompt(description).await;
+        image_request.prompt = enhanced_prompt.clone();
 
-        // Store asset
-        let asset_id = self.asset_storage.store_asset(
-            image_response.image_url.clone(),
-            "image".to_string(),
-            description.to_string(),
-            HashMap::from([
-                ("prompt".to_string(), serde_json::json!(enhanced_prompt)),
-                ("model".to_string(), serde_json::json!(image_response.model)),
-                ("revised_prompt".to_string(), serde_json::json!(image_response.revised_prompt)),
-            ]),
-        ).await;
+        // Generate every variation from the same prompt, a few at a time
+        let generations: Vec<(usize, anyhow::Result<ImageGenerationResponse>)> = stream::iter(0..variations)
+            .map(|i| {
+                let request = image_request.clone();
+                async move { (i, self.image_service.generate(request).await) }
+            })
+            .buffered(VARIATION_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut generated = Vec::with_capacity(variations);
+        let mut last_error = None;
+        for (i, generation) in generations {
+            match generation {
+                Ok(response) => generated.push((i, response)),
+                Err(e) => {
+                    tracing::warn!("Image variation {} of {} failed: {}", i + 1, variations, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        if generated.is_empty() {
+            return Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No image was generated")));
+        }
+        let failed_variations = variations - generated.len();
+
+        // Store each asset
+        let mut asset_ids = Vec::with_capacity(generated.len());
+        for (i, image_response) in &generated {
+            let asset_id = self.asset_storage.store_asset(
+                image_response.image_url.clone(),
+                "image".to_string(),
+                description.to_string(),
+                HashMap::from([
+                    ("prompt".to_string(), serde_json::json!(enhanced_prompt)),
+                    ("model".to_string(), serde_json::json!(image_response.model)),
+                    ("revised_prompt".to_string(), serde_json::json!(image_response.revised_prompt)),
+                    ("variation".to_string(), serde_json::json!(i + 1)),
+                ]),
+            ).await;
+            asset_ids.push(asset_id);
+        }
+        let primary = &generated[0].1;
+
+        // A bypassed request still refreshes the entry for later ones
+        if variations == 1 {
+            self.image_service.cache().put(&cache_key, crate::services::visual::image_generation::CachedImage {
+                asset_id: asset_ids[0].clone(),
+                asset_url: primary.image_url.clone(),
+                response: primary.clone(),
+            });
+        }
 
         let duration_ms = start_time.elapsed().as_millis() as u64;
 
         Ok(VisualCreativeResult {
-            asset_url: image_response.image_url,
+            asset_url: primary.image_url.clone(),
+            asset_urls: generated.iter().map(|(_, response)| response.image_url.clone()).collect(),
             asset_type: "image".to_string(),
             metadata: HashMap::from([
-                ("asset_id".to_string(), serde_json::json!(asset_id)),
-                ("model".to_string(), serde_json::json!(image_response.model)),
-                ("size".to_string(), serde_json::json!(image_response.size)),
+                ("asset_id".to_string(), serde_json::json!(asset_ids[0])),
+                ("asset_ids".to_string(), serde_json::json!(asset_ids)),
+                ("variations".to_string(), serde_json::json!(generated.len())),
+                ("failed_variations".to_string(), serde_json::json!(failed_variations)),
+                ("model".to_string(), serde_json::json!(primary.model)),
+                ("size".to_string(), serde_json::json!(primary.size)),
+                ("prompt_enhanced".to_string(), serde_json::json!(enhancement == PromptEnhancement::Enhanced)),
+                ("prompt_enhancement".to_string(), serde_json::json!(enhancement.as_str())),
+                ("cached".to_string(), serde_json::json!(false)),
             ]),
             generation_time_ms: duration_ms,
         })
     }
 
-    /// Enhance prompt using AI
-    async fn enhance_prompt(&self, description: &str) -> anyhow::Result<String> {
+    /// Enhance prompt using AI, falling back to the original description
+    async fn enhance_prompt(&self, description: &str) -> (String, PromptEnhancement) {
+        if let Some(skipped) = enhancement_skip_reason(
+            description,
+            self.config.prompt_enhancement_enabled,
+            self.config.prompt_enhancement_detailed_words,
+        ) {
+            tracing::debug!("Skipping prompt enhancement: {}", skipped.as_str());
+            return (description.to_string(), skipped);
+        }
+
         // Use AI router to enhance the prompt for better image generation
         use crate::types::{AIMessage, MessageRole};
         let messages = vec![AIMessage {
@@ -229,28 +498,31 @@ impl VisualCreativeEngine {
         use crate::types::AIRequest;
         let request = AIRequest {
             messages,
-            model: Some("gpt-4-turbo-preview".to_string()), // Use GPT-4 for prompt enhancement
+            model: Some(self.config.prompt_enhancement_model.clone()),
             temperature: Some(0.7),
             max_tokens: Some(200),
             stream: None,
             context: None,
+            context_overflow: None,
+            response_language: None,
+            sanitize_output: false,
+            json_output: false,
         };
 
-        match self.router.select_best_model(&request) {
-            Ok(model_info) => {
-                if let Some(service) = self.router.get_service(model_info.provider) {
-                    match service.generate(request).await {
-                        Ok(response) => Ok(response.content),
-                        Err(e) => {
-                            tracing::warn!("Failed to enhance prompt: {}", e);
-                            Ok(description.to_string()) // Fallback to original
-                        }
-                    }
-                } else {
-                    Ok(description.to_string())
-                }
+        let fallback = (description.to_string(), PromptEnhancement::Failed);
+        let Ok(model_info) = self.router.select_best_model(&request) else {
+            return fallback;
+        };
+        let Some(service) = self.router.get_service(model_info.provider) else {
+            return fallback;
+        };
+
+        match self.router.generate_with(&service, request).await {
+            Ok(response) => (response.content, PromptEnhancement::Enhanced),
+            Err(e) => {
+                tracing::warn!("Failed to enhance prompt: {}", e);
+                fallback
             }
-            Err(_) => Ok(description.to_string()),
         }
     }
 
@@ -281,6 +553,7 @@ impl VisualCreativeEngine {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
 
                 Ok(VisualCreativeResult {
+                    asset_urls: vec![export_url.clone()],
                     asset_url: export_url,
                     asset_type: "ui_mockup".to_string(),
                     metadata: HashMap::from([
@@ -334,26 +607,120 @@ impl VisualCreativeEngine {
         self.generate_image(description, &HashMap::new()).await
     }
 
-    /// Optimize asset
+    /// Optimize asset: fetch `source_url`, shrink it and re-encode it
+    ///
+    /// Optional requirements: `max_width`/`max_height` (px), `format`
+    /// ("webp" or "jpeg") and `quality` (1-100); defaults come from config.
     async fn optimize_asset(
         &self,
         description: &str,
-        _requirements: &HashMap<String, serde_json::Value>,
+        requirements: &HashMap<String, serde_json::Value>,
     ) -> anyhow::Result<VisualCreativeResult> {
         let start_time = std::time::Instant::now();
-        let asset_url = format!("optimized://asset/{}", Uuid::new_v4());
+
+        let source_url = requirements
+            .get("source_url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Asset optimization requires requirements.source_url"))?;
+        let dimension = |key: &str| requirements
+            .get(key)
+            .and_then(|v| v.as_u64())
+            .map(|v| v.min(u32::MAX as u64) as u32)
+            .unwrap_or(self.config.asset_optimize_max_dimension);
+        let format_name = requirements
+            .get("format")
+            .and_then(|v| v.as_str())
+            .unwrap_or(self.config.asset_optimize_format.as_str());
+        let format = OutputFormat::parse(format_name)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported output format '{}' (use webp or jpeg)", format_name))?;
+        let options = OptimizeOptions {
+            max_width: dimension("max_width"),
+            max_height: dimension("max_height"),
+            format,
+            quality: requirements
+                .get("quality")
+                .and_then(|v| v.as_u64())
+                .map(|q| q.clamp(1, 100) as u8)
+                .unwrap_or(self.config.asset_optimize_quality),
+        };
+
+        let source = self.fetch_source_image(source_url).await?;
+        let original_bytes = source.len();
+        let optimized = tokio::task::spawn_blocking(move || optimize_image(&source, &options))
+            .await
+            .map_err(|e| anyhow::anyhow!("Image optimization task failed: {}", e))??;
+
+        let metadata = HashMap::from([
+            ("source_url".to_string(), serde_json::json!(source_url)),
+            ("original_bytes".to_string(), serde_json::json!(original_bytes)),
+            ("optimized_bytes".to_string(), serde_json::json!(optimized.bytes.len())),
+            ("original_dimensions".to_string(), serde_json::json!([optimized.original_width, optimized.original_height])),
+            ("dimensions".to_string(), serde_json::json!([optimized.width, optimized.height])),
+            ("format".to_string(), serde_json::json!(optimized.format)),
+            ("content_type".to_string(), serde_json::json!(optimized.format.content_type())),
+        ]);
+        let (asset_id, asset_url) = self.asset_storage.store_file(
+            &optimized.bytes,
+            optimized.format.extension(),
+            "optimized_asset".to_string(),
+            description.to_string(),
+            metadata.clone(),
+        ).await?;
+
+        let mut metadata = metadata;
+        metadata.insert("asset_id".to_string(), serde_json::json!(asset_id));
         let duration_ms = start_time.elapsed().as_millis() as u64;
 
         Ok(VisualCreativeResult {
+            asset_urls: vec![asset_url.clone()],
             asset_url,
             asset_type: "optimized_asset".to_string(),
-            metadata: HashMap::from([
-                ("original".to_string(), serde_json::json!(description)),
-            ]),
+            metadata,
             generation_time_ms: duration_ms,
         })
     }
 
+    /// Download an image to optimize, refusing anything over the configured size
+    ///
+    /// The URL is caller-supplied, so only public https hosts are fetched; the
+    /// checked address is pinned and redirects aren't followed.
+    async fn fetch_source_image(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let max_bytes = self.config.asset_optimize_max_source_bytes;
+        let parsed = source_image_url(url)?;
+        let (host, address) = resolve_public_host(&parsed).await
+            .map_err(|e| anyhow::anyhow!("Refusing source image {}: {}", url, e))?;
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve(&host, address)
+            .build()?;
+        let mut response = client.get(parsed).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| anyhow::anyhow!("Failed to fetch source image {}: {}", url, e))?;
+
+        if response.content_length().is_some_and(|len| len as usize > max_bytes) {
+            anyhow::bail!("Source image {} is larger than {} bytes", url, max_bytes);
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| anyhow::anyhow!("Failed to read source image {}: {}", url, e))?
+        {
+            if bytes.len() + chunk.len() > max_bytes {
+                anyhow::bail!("Source image {} is larger than {} bytes", url, max_bytes);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+
+    /// A file written for an optimized asset, with its content type
+    pub async fn asset_file(&self, file_name: &str) -> Option<(Vec<u8>, &'static str)> {
+        let extension = file_name.rsplit_once('.')?.1;
+        let format = OutputFormat::parse(extension)?;
+        let bytes = self.asset_storage.read_file(file_name).await?;
+        Some((bytes, format.content_type()))
+    }
+
     /// Get request status
     pub async fn get_request(&self, request_id: &str) -> Option<VisualCreativeRequest> {
         self.requests.read().await.get(request_id).cloned()
@@ -363,6 +730,29 @@ impl VisualCreativeEngine {
     pub async fn list_requests(&self) -> Vec<VisualCreativeRequest> {
         self.requests.read().await.values().cloned().collect()
     }
+
+    /// Worker pool utilisation and queue depth
+    pub fn queue_status(&self) -> VisualQueueStatus {
+        VisualQueueStatus {
+            queue_depth: self.scheduler.queue_depth(),
+            running: self.scheduler.running(),
+            max_concurrent: self.scheduler.max_concurrent(),
+        }
+    }
+
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        self.scheduler.set_max_concurrent(max_concurrent);
+    }
+}
+
+/// Check a caller-supplied source image URL: https to a public host only
+fn source_image_url(url: &str) -> anyhow::Result<reqwest::Url> {
+    let parsed = validate_outbound_url(url, false)
+        .map_err(|e| anyhow::anyhow!("Refusing source image {}: {}", url, e))?;
+    if parsed.scheme() != "https" {
+        anyhow::bail!("Refusing source image {}: only https URLs are fetched", url);
+    }
+    Ok(parsed)
 }
 
 // Implement Clone for VisualCreativeEngine
@@ -375,6 +765,127 @@ impl Clone for VisualCreativeEngine {
             asset_storage: Arc::clone(&self.asset_storage),
             figma: Arc::clone(&self.figma),
             requests: Arc::clone(&self.requests),
+            scheduler: Arc::clone(&self.scheduler),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requested_variations_are_bounded() {
+        let requirements = |value: serde_json::Value| HashMap::from([("variations".to_string(), value)]);
+        assert_eq!(requested_variations(&HashMap::new()), 1);
+        assert_eq!(requested_variations(&requirements(serde_json::json!(4))), 4);
+        assert_eq!(requested_variations(&requirements(serde_json::json!(0))), 1);
+        assert_eq!(requested_variations(&requirements(serde_json::json!(50))), MAX_VARIATIONS);
+    }
+
+    #[test]
+    fn test_detailed_prompt_skips_enhancement() {
+        let detailed = "A photorealistic product shot of a matte black espresso machine on a walnut \
+            counter, soft morning lighting from the left, shallow depth of field with an 85mm lens, \
+            warm neutral color palette, minimalist kitchen background slightly out of focus, \
+            subtle steam rising from a ceramic cup in the foreground";
+        assert_eq!(enhancement_skip_reason(detailed, true, 40), Some(PromptEnhancement::SkippedDetailed));
+
+        // Short prompts still go through the model
+        assert_eq!(enhancement_skip_reason("a coffee machine", true, 40), None);
+        assert_eq!(enhancement_skip_reason("a coffee machine", false, 40), Some(PromptEnhancement::Disabled));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_never_exceeds_limit() {
+        use std::sync::atomic::AtomicUsize;
+
+        let scheduler = Arc::new(VisualJobScheduler::new(2));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for i in 0..10 {
+            scheduler.enqueue(format!("job-{}", i), &Priority::Medium);
+            let (scheduler, in_flight, peak) = (Arc::clone(&scheduler), Arc::clone(&in_flight), Arc::clone(&peak));
+            handles.push(tokio::spawn(async move {
+                let (_permit, _job) = scheduler.acquire_next().await.unwrap();
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(scheduler.queue_depth(), 0);
+        assert_eq!(scheduler.running(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_queued_jobs_run_by_priority() {
+        let scheduler = VisualJobScheduler::new(1);
+        scheduler.enqueue("low".to_string(), &Priority::Low);
+        scheduler.enqueue("urgent".to_string(), &Priority::Urgent);
+        scheduler.enqueue("medium-1".to_string(), &Priority::Medium);
+        scheduler.enqueue("medium-2".to_string(), &Priority::Medium);
+
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            let (permit, job) = scheduler.acquire_next().await.unwrap();
+            order.push(job);
+            drop(permit);
+        }
+        assert_eq!(order, vec!["urgent", "medium-1", "medium-2", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_shrinking_then_growing_restores_capacity() {
+        let scheduler = VisualJobScheduler::new(2);
+        for job in ["a", "b", "c"] {
+            scheduler.enqueue(job.to_string(), &Priority::Medium);
+        }
+        let (first, _) = scheduler.acquire_next().await.unwrap();
+        let (second, _) = scheduler.acquire_next().await.unwrap();
+
+        // Both slots are busy, so shrinking can only retire one once its job finishes
+        scheduler.set_max_concurrent(1);
+        assert_eq!(scheduler.running(), 2);
+
+        // Growing back before then cancels the retirement rather than adding a slot
+        scheduler.set_max_concurrent(2);
+        assert_eq!(scheduler.running(), 2);
+        assert_eq!(scheduler.slots.available_permits(), 0);
+        drop(first);
+        drop(second);
+        assert_eq!(scheduler.running(), 0);
+        assert_eq!(scheduler.slots.available_permits(), 2);
+
+        // Idle slots are retired right away; the running job keeps its own
+        let (third, _) = scheduler.acquire_next().await.unwrap();
+        scheduler.set_max_concurrent(1);
+        assert_eq!(scheduler.running(), 1);
+        assert_eq!(scheduler.slots.available_permits(), 0);
+        drop(third);
+        assert_eq!(scheduler.running(), 0);
+        assert_eq!(scheduler.slots.available_permits(), 1);
+    }
+
+    #[test]
+    fn test_source_images_must_be_public_https() {
+        assert!(source_image_url("https://images.example.com/logo.png").is_ok());
+        for url in [
+            "http://images.example.com/logo.png",
+            "https://127.0.0.1/logo.png",
+            "https://169.254.169.254/latest/meta-data",
+            "https://localhost/logo.png",
+            "https://[::1]/logo.png",
+            "file:///etc/passwd",
+        ] {
+            assert!(source_image_url(url).is_err(), "{}", url);
         }
     }
 }
@@ -5,6 +5,8 @@
  */
 pub mod openclaw_ws;
 pub mod moltbook_api;
+pub mod moltbook_sync;
 
-pub use openclaw_ws::OpenClawWebSocketClient;
-pub use moltbook_api::MoltbookApiClient;
+pub use openclaw_ws::{OpenClawError, OpenClawMessage, OpenClawWebSocketClient};
+pub use moltbook_api::{MoltbookApiClient, NewMoltbookPost, PublishedPost};
+pub use moltbook_sync::MoltbookSync;
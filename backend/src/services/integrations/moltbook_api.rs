@@ -4,6 +4,7 @@
  * Real API integration for Moltbook agent social network
  */
 use std::sync::Arc;
+use std::time::Duration;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use crate::config::Config;
@@ -36,10 +37,27 @@ pub struct MoltbookSkill {
     pub installs: u32,
 }
 
+/// A post to publish on Moltbook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewMoltbookPost {
+    pub title: String,
+    pub content: String,
+    pub submolt: String,
+    pub language: Option<String>,
+}
+
+/// Where Moltbook published a post
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedPost {
+    pub id: String,
+    pub url: String,
+}
+
 pub struct MoltbookApiClient {
     client: Client,
     config: Arc<Config>,
     api_url: String,
+    web_url: String, // Base for post links when the API doesn't return one
     api_key: Option<String>,
 }
 
@@ -47,16 +65,28 @@ impl MoltbookApiClient {
     pub fn new(config: Arc<Config>) -> Self {
         let api_url = std::env::var("MOLTBOOK_API_URL")
             .unwrap_or_else(|_| "https://api.moltbook.com".to_string());
+        let web_url = std::env::var("MOLTBOOK_WEB_URL")
+            .unwrap_or_else(|_| "https://moltbook.com".to_string());
         let api_key = std::env::var("MOLTBOOK_API_KEY").ok();
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.moltbook_timeout_secs))
+            .build()
+            .unwrap_or_default();
 
         Self {
-            client: Client::new(),
+            client,
             config,
             api_url,
+            web_url,
             api_key,
         }
     }
 
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into();
+        self
+    }
+
     /// Register an agent on Moltbook
     pub async fn register_agent(&self, agent: MoltbookAgent) -> anyhow::Result<MoltbookAgent> {
         let mut request = self.client
@@ -99,6 +129,44 @@ impl MoltbookApiClient {
         Ok(shared_post)
     }
 
+    /// Publish a post and return its Moltbook id and URL
+    pub async fn submit_post(&self, post: &NewMoltbookPost) -> anyhow::Result<PublishedPost> {
+        #[derive(Deserialize)]
+        struct Created {
+            id: String,
+            #[serde(default)]
+            url: Option<String>,
+        }
+
+        let mut request = self.client
+            .post(format!("{}/api/v1/posts", self.api_url))
+            .json(&serde_json::json!({
+                "title": post.title,
+                "content": post.content,
+                "submolt": post.submolt,
+                "language": post.language,
+                "content_type": "code",
+            }));
+
+        if let Some(ref key) = self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Moltbook API error ({}): {}", status, error_text);
+        }
+
+        let created: Created = response.json().await?;
+        Ok(PublishedPost {
+            url: created.url.unwrap_or_else(|| format!("{}/post/{}", self.web_url, created.id)),
+            id: created.id,
+        })
+    }
+
     /// Get trending skills
     pub async fn get_trending_skills(&self, limit: Option<u32>) -> anyhow::Result<Vec<MoltbookSkill>> {
         let mut request = self.client
@@ -141,3 +209,46 @@ impl MoltbookApiClient {
         Ok(skills)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::post, Json, Router};
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    fn post_fixture() -> NewMoltbookPost {
+        NewMoltbookPost {
+            title: "Binary search".to_string(),
+            content: "```rust\nfn search() {}\n```".to_string(),
+            submolt: "coding".to_string(),
+            language: Some("rust".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_post_returns_remote_id_and_url() {
+        let api = serve(Router::new().route("/api/v1/posts", post(|Json(body): Json<serde_json::Value>| async move {
+            assert_eq!(body["submolt"], "coding");
+            assert_eq!(body["language"], "rust");
+            Json(serde_json::json!({ "id": "mb-42" }))
+        }))).await;
+        let client = MoltbookApiClient::new(Arc::new(Config::from_env().unwrap())).with_api_url(api);
+
+        let published = client.submit_post(&post_fixture()).await.unwrap();
+        assert_eq!(published.id, "mb-42");
+        assert!(published.url.ends_with("/post/mb-42"));
+
+        let failing = serve(Router::new().route("/api/v1/posts", post(|| async {
+            (StatusCode::SERVICE_UNAVAILABLE, "try later")
+        }))).await;
+        let client = MoltbookApiClient::new(Arc::new(Config::from_env().unwrap())).with_api_url(failing);
+        let err = client.submit_post(&post_fixture()).await.unwrap_err();
+        assert!(err.to_string().contains("503"));
+    }
+}
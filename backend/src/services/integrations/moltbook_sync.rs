@@ -0,0 +1,113 @@
+/**
+ * Moltbook Post Sync
+ *
+ * Publishes stored posts to Moltbook and records where they landed. Posts the
+ * API refused or couldn't be reached for stay `pending_sync` and are retried
+ * in the background.
+ */
+use std::sync::Arc;
+use std::time::Duration;
+use sqlx::Row;
+use crate::database::Database;
+use super::moltbook_api::{MoltbookApiClient, NewMoltbookPost, PublishedPost};
+
+/// Posts retried per pass
+const RETRY_BATCH: i64 = 20;
+/// After this many failed attempts a post is given up on; it stays
+/// `pending_sync` with its last error but is no longer retried
+const MAX_SYNC_ATTEMPTS: i32 = 20;
+
+pub struct MoltbookSync {
+    client: Arc<MoltbookApiClient>,
+    database: Option<Arc<Database>>,
+}
+
+impl MoltbookSync {
+    pub fn new(client: Arc<MoltbookApiClient>, database: Option<Arc<Database>>) -> Self {
+        Self { client, database }
+    }
+
+    /// Publish the post stored as `post_id` (if stored) and record the outcome
+    pub async fn publish(&self, post_id: &str, post: &NewMoltbookPost) -> anyhow::Result<PublishedPost> {
+        let result = self.client.submit_post(post).await;
+        if let Some(ref db) = self.database {
+            if let Err(e) = record_attempt(db, post_id, &result).await {
+                tracing::error!("Failed to record Moltbook sync for post {}: {}", post_id, e);
+            }
+        }
+        result
+    }
+
+    /// Re-attempt posts left `pending_sync`; returns how many were published
+    pub async fn retry_pending(&self) -> anyhow::Result<usize> {
+        let Some(ref db) = self.database else { return Ok(0) };
+        let rows = sqlx::query(
+            "SELECT post_id, title, content, submolt, language FROM moltbook_posts
+             WHERE sync_status = 'pending_sync' AND sync_attempts < $1
+             ORDER BY created_at
+             LIMIT $2"
+        )
+        .bind(MAX_SYNC_ATTEMPTS)
+        .bind(RETRY_BATCH)
+        .fetch_all(db.pool())
+        .await?;
+
+        let mut published = 0;
+        for row in rows {
+            let post_id: String = row.try_get("post_id")?;
+            let post = NewMoltbookPost {
+                title: row.try_get("title")?,
+                content: row.try_get("content")?,
+                submolt: row.try_get("submolt")?,
+                language: row.try_get("language")?,
+            };
+            match self.publish(&post_id, &post).await {
+                Ok(remote) => {
+                    tracing::info!("Published pending Moltbook post {} as {}", post_id, remote.id);
+                    published += 1;
+                }
+                Err(e) => tracing::warn!("Moltbook post {} is still pending: {}", post_id, e),
+            }
+        }
+        Ok(published)
+    }
+
+    /// Retry pending posts every `interval` for the life of the process
+    pub fn spawn_retry(self: &Arc<Self>, interval: Duration) {
+        if self.database.is_none() || interval.is_zero() {
+            return;
+        }
+        let sync = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = sync.retry_pending().await {
+                    tracing::warn!("Moltbook sync retry failed: {}", e);
+                }
+            }
+        });
+    }
+}
+
+async fn record_attempt(db: &Database, post_id: &str, result: &anyhow::Result<PublishedPost>) -> Result<(), sqlx::Error> {
+    match result {
+        Ok(remote) => sqlx::query(
+            "UPDATE moltbook_posts
+             SET remote_id = $2, remote_url = $3, sync_status = 'synced', last_sync_error = NULL, updated_at = NOW()
+             WHERE post_id = $1"
+        )
+        .bind(post_id)
+        .bind(&remote.id)
+        .bind(&remote.url),
+        Err(e) => sqlx::query(
+            "UPDATE moltbook_posts
+             SET sync_status = 'pending_sync', sync_attempts = sync_attempts + 1, last_sync_error = $2, updated_at = NOW()
+             WHERE post_id = $1"
+        )
+        .bind(post_id)
+        .bind(e.to_string()),
+    }
+    .execute(db.pool())
+    .await?;
+    Ok(())
+}
@@ -1,14 +1,49 @@
 /**
  * OpenClaw WebSocket Client
- * 
+ *
  * Real WebSocket integration for OpenClaw Gateway
+ *
+ * `start` runs a supervisor that keeps the connection up: when the gateway is
+ * unreachable or drops the socket it reconnects with jittered exponential
+ * backoff. Messages sent while disconnected are queued (up to a cap) and
+ * flushed once the connection is back.
+ *
+ * Replies are matched to requests by message id: `send_message` waits for the
+ * gateway's response carrying the same id.
  */
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{oneshot, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use futures_util::stream::SplitSink;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use crate::config::Config;
+use crate::services::agent::fault_tolerance::RetryConfig;
+
+type GatewayStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type GatewaySink = SplitSink<GatewayStream, Message>;
+
+/// Messages held for delivery while disconnected; further sends are rejected
+const MAX_QUEUED_MESSAGES: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OpenClawError {
+    #[error("Not connected to OpenClaw Gateway; message queued for delivery")]
+    Queued,
+    #[error("Not connected to OpenClaw Gateway and {0} messages are already queued")]
+    QueueFull(usize),
+    #[error("OpenClaw Gateway connection lost before a reply arrived")]
+    ConnectionLost,
+    #[error("No reply from OpenClaw Gateway within {0:?}")]
+    ReplyTimeout(Duration),
+    #[error("Failed to send to OpenClaw Gateway: {0}")]
+    Send(String),
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenClawMessage {
@@ -17,6 +52,8 @@ pub struct OpenClawMessage {
     pub message: String,
     pub thinking_level: Option<String>,
     pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>, // Continue an existing gateway session
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,9 +67,14 @@ pub struct OpenClawResponse {
 pub struct OpenClawWebSocketClient {
     config: Arc<Config>,
     gateway_url: String,
-    connection: Arc<RwLock<Option<tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>>>>,
-    message_queue: Arc<RwLock<Vec<OpenClawMessage>>>,
+    connection: Arc<Mutex<Option<GatewaySink>>>, // Write half; the supervisor owns the read half
+    message_queue: Arc<RwLock<VecDeque<OpenClawMessage>>>,
     is_connected: Arc<RwLock<bool>>,
+    connected_since: Arc<RwLock<Option<Instant>>>,
+    pending_replies: Arc<Mutex<HashMap<String, oneshot::Sender<OpenClawResponse>>>>, // message id -> waiter
+    reply_timeout: Duration,
+    reconnect: RetryConfig, // max_retries is ignored; the supervisor retries until stopped
+    shutdown: CancellationToken,
 }
 
 impl OpenClawWebSocketClient {
@@ -43,95 +85,212 @@ impl OpenClawWebSocketClient {
         Self {
             config,
             gateway_url,
-            connection: Arc::new(RwLock::new(None)),
-            message_queue: Arc::new(RwLock::new(Vec::new())),
+            connection: Arc::new(Mutex::new(None)),
+            message_queue: Arc::new(RwLock::new(VecDeque::new())),
             is_connected: Arc::new(RwLock::new(false)),
+            connected_since: Arc::new(RwLock::new(None)),
+            pending_replies: Arc::new(Mutex::new(HashMap::new())),
+            reply_timeout: Duration::from_secs(120),
+            reconnect: RetryConfig {
+                max_retries: u32::MAX,
+                initial_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(60),
+                backoff_multiplier: 2.0,
+            },
+            shutdown: CancellationToken::new(),
         }
     }
 
-    /// Connect to OpenClaw Gateway
-    pub async fn connect(&self) -> anyhow::Result<()> {
-        tracing::info!("Connecting to OpenClaw Gateway: {}", self.gateway_url);
-
-        match connect_async(&self.gateway_url).await {
-            Ok((ws_stream, _)) => {
-                let mut conn = self.connection.write().await;
-                *conn = Some(ws_stream);
-                *self.is_connected.write().await = true;
-                tracing::info!("Connected to OpenClaw Gateway");
-                
-                // Spawn message handler
-                let connection_clone = Arc::clone(&self.connection);
-                let is_connected_clone = Arc::clone(&self.is_connected);
-                tokio::spawn(async move {
-                    Self::handle_messages(connection_clone, is_connected_clone).await;
-                });
+    pub fn with_gateway_url(mut self, gateway_url: impl Into<String>) -> Self {
+        self.gateway_url = gateway_url.into();
+        self
+    }
+
+    pub fn with_reconnect(mut self, reconnect: RetryConfig) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    pub fn with_reply_timeout(mut self, reply_timeout: Duration) -> Self {
+        self.reply_timeout = reply_timeout;
+        self
+    }
 
-                Ok(())
+    pub fn gateway_url(&self) -> &str {
+        &self.gateway_url
+    }
+
+    /// Connect to OpenClaw Gateway and keep reconnecting until `disconnect`
+    pub fn start(self: &Arc<Self>) -> JoinHandle<()> {
+        let client = Arc::clone(self);
+        tokio::spawn(async move { client.supervise().await })
+    }
+
+    async fn supervise(&self) {
+        let mut failures = 0u32;
+        while !self.shutdown.is_cancelled() {
+            tracing::info!("Connecting to OpenClaw Gateway: {}", self.gateway_url);
+            let connected = tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                connected = connect_async(&self.gateway_url) => connected,
+            };
+
+            match connected {
+                Ok((stream, _)) => {
+                    failures = 0;
+                    self.run_connection(stream).await;
+                    if self.shutdown.is_cancelled() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    failures = failures.saturating_add(1);
+                    tracing::warn!("Failed to connect to OpenClaw Gateway (attempt {}): {}", failures, e);
+                }
             }
-            Err(e) => {
-                tracing::error!("Failed to connect to OpenClaw Gateway: {}", e);
-                *self.is_connected.write().await = false;
-                Err(anyhow::anyhow!("Connection failed: {}", e))
+
+            let delay = self.reconnect.backoff_with_jitter(failures.saturating_sub(1));
+            tracing::info!("Reconnecting to OpenClaw Gateway in {:?}", delay);
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
             }
         }
+        tracing::info!("OpenClaw Gateway supervisor stopped");
     }
 
-    /// Handle incoming WebSocket messages
-    async fn handle_messages(
-        connection: Arc<RwLock<Option<tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>>>>,
-        is_connected: Arc<RwLock<bool>>,
-    ) {
+    /// Serve one connection until the gateway closes it or it errors
+    async fn run_connection(&self, stream: GatewayStream) {
+        let (sink, mut incoming) = stream.split();
+        *self.connection.lock().await = Some(sink);
+        *self.connected_since.write().await = Some(Instant::now());
+        *self.is_connected.write().await = true;
+        tracing::info!("Connected to OpenClaw Gateway");
+        self.flush_queue().await;
+
         loop {
-            let mut conn_guard = connection.write().await;
-            if let Some(ref mut ws) = *conn_guard {
-                match ws.next().await {
-                    Some(Ok(Message::Text(text))) => {
-                        tracing::debug!("OpenClaw message received: {}", text);
-                        // Parse and handle message
-                    }
-                    Some(Ok(Message::Close(_))) => {
-                        tracing::warn!("OpenClaw connection closed");
-                        *is_connected.write().await = false;
-                        break;
-                    }
-                    Some(Err(e)) => {
-                        tracing::error!("OpenClaw WebSocket error: {}", e);
-                        *is_connected.write().await = false;
-                        break;
-                    }
-                    None => break,
-                    _ => {}
+            let next = tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                next = incoming.next() => next,
+            };
+            match next {
+                Some(Ok(Message::Text(text))) => self.handle_text(&text).await,
+                Some(Ok(Message::Close(_))) | None => {
+                    tracing::warn!("OpenClaw connection closed");
+                    break;
+                }
+                Some(Err(e)) => {
+                    tracing::error!("OpenClaw WebSocket error: {}", e);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        *self.is_connected.write().await = false;
+        *self.connected_since.write().await = None;
+        if let Some(mut sink) = self.connection.lock().await.take() {
+            let _ = sink.close().await;
+        }
+        // Replies can't arrive on a new connection; wake the waiters
+        self.pending_replies.lock().await.clear();
+        tracing::info!("Disconnected from OpenClaw Gateway");
+    }
+
+    /// Hand a gateway reply to whoever sent the message it answers
+    async fn handle_text(&self, text: &str) {
+        let response = match serde_json::from_str::<OpenClawResponse>(text) {
+            Ok(response) => response,
+            Err(_) => {
+                tracing::debug!("OpenClaw message received: {}", text);
+                return;
+            }
+        };
+        let waiter = match response.id.as_deref() {
+            Some(id) => self.pending_replies.lock().await.remove(id),
+            None => None,
+        };
+        match waiter {
+            Some(waiter) => {
+                let _ = waiter.send(response);
+            }
+            None => tracing::debug!("Unsolicited OpenClaw response: {:?}", response.id),
+        }
+    }
+
+    /// Deliver messages queued while disconnected, oldest first
+    async fn flush_queue(&self) {
+        let mut queue = self.message_queue.write().await;
+        if queue.is_empty() {
+            return;
+        }
+        tracing::info!("Delivering {} queued OpenClaw messages", queue.len());
+
+        let mut conn = self.connection.lock().await;
+        let Some(sink) = conn.as_mut() else { return };
+        while let Some(message) = queue.pop_front() {
+            let sent = match serde_json::to_string(&message) {
+                Ok(json) => sink.send(Message::Text(json)).await,
+                Err(e) => {
+                    tracing::warn!("Dropping unserializable OpenClaw message: {}", e);
+                    continue;
                 }
-            } else {
+            };
+            if let Err(e) = sent {
+                tracing::warn!("Failed to deliver queued OpenClaw message: {}", e);
+                queue.push_front(message);
                 break;
             }
         }
     }
 
-    /// Send message via OpenClaw
-    pub async fn send_message(&self, message: OpenClawMessage) -> anyhow::Result<OpenClawResponse> {
-        if !*self.is_connected.read().await {
-            // Queue message if not connected
-            self.message_queue.write().await.push(message.clone());
-            return Err(anyhow::anyhow!("Not connected to OpenClaw Gateway"));
+    /// Send message via OpenClaw and wait for the agent's reply
+    ///
+    /// Messages without an id are given one so the reply can be matched. While
+    /// disconnected the message is queued for delivery on reconnect and
+    /// `Queued` is returned (nobody waits for its reply); once the queue is
+    /// full messages are rejected.
+    pub async fn send_message(&self, message: OpenClawMessage) -> Result<OpenClawResponse, OpenClawError> {
+        if !self.is_connected().await {
+            let mut queue = self.message_queue.write().await;
+            if queue.len() >= MAX_QUEUED_MESSAGES {
+                return Err(OpenClawError::QueueFull(queue.len()));
+            }
+            queue.push_back(message);
+            return Err(OpenClawError::Queued);
         }
+        self.send_now(message).await
+    }
+
+    /// Send message via OpenClaw and wait for the agent's reply, failing with
+    /// `ConnectionLost` instead of queueing while disconnected
+    pub async fn send_now(&self, mut message: OpenClawMessage) -> Result<OpenClawResponse, OpenClawError> {
+        if !self.is_connected().await {
+            return Err(OpenClawError::ConnectionLost);
+        }
+
+        let id = message.id.get_or_insert_with(|| uuid::Uuid::new_v4().to_string()).clone();
+        let message_json = serde_json::to_string(&message).map_err(|e| OpenClawError::Send(e.to_string()))?;
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending_replies.lock().await.insert(id.clone(), reply_tx);
 
-        let mut conn = self.connection.write().await;
-        if let Some(ref mut ws) = *conn {
-            let message_json = serde_json::to_string(&message)?;
-            ws.send(Message::Text(message_json)).await?;
-
-            // In production, wait for response
-            // For now, return placeholder
-            Ok(OpenClawResponse {
-                id: message.id,
-                response: format!("Response to: {}", message.message),
-                session_id: Some(uuid::Uuid::new_v4().to_string()),
-                metadata: None,
-            })
-        } else {
-            Err(anyhow::anyhow!("WebSocket connection not available"))
+        let sent = match self.connection.lock().await.as_mut() {
+            Some(sink) => sink.send(Message::Text(message_json)).await
+                .map_err(|e| OpenClawError::Send(e.to_string())),
+            None => Err(OpenClawError::ConnectionLost),
+        };
+        if let Err(e) = sent {
+            self.pending_replies.lock().await.remove(&id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(self.reply_timeout, reply_rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(OpenClawError::ConnectionLost),
+            Err(_) => {
+                self.pending_replies.lock().await.remove(&id);
+                Err(OpenClawError::ReplyTimeout(self.reply_timeout))
+            }
         }
     }
 
@@ -140,14 +299,141 @@ impl OpenClawWebSocketClient {
         *self.is_connected.read().await
     }
 
-    /// Disconnect from Gateway
+    /// How long the current connection has been up
+    pub async fn uptime(&self) -> Option<Duration> {
+        self.connected_since.read().await.map(|since| since.elapsed())
+    }
+
+    /// Messages waiting for the connection to come back
+    pub async fn queued_messages(&self) -> usize {
+        self.message_queue.read().await.len()
+    }
+
+    /// Disconnect from Gateway and stop reconnecting
     pub async fn disconnect(&self) {
-        let mut conn = self.connection.write().await;
-        if let Some(ref mut ws) = *conn {
-            let _ = ws.close(None).await;
+        self.shutdown.cancel();
+        if let Some(mut sink) = self.connection.lock().await.take() {
+            let _ = sink.close().await;
         }
-        *conn = None;
         *self.is_connected.write().await = false;
+        *self.connected_since.write().await = None;
         tracing::info!("Disconnected from OpenClaw Gateway");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn wait_for(client: &OpenClawWebSocketClient, connected: bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.is_connected().await != connected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("connection state did not change in time");
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_gateway_drops_and_flushes_queue() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let client = Arc::new(
+            OpenClawWebSocketClient::new(Arc::new(Config::from_env().unwrap()))
+                .with_gateway_url(url)
+                .with_reconnect(RetryConfig {
+                    max_retries: u32::MAX,
+                    initial_delay: Duration::from_millis(10),
+                    max_delay: Duration::from_millis(50),
+                    backoff_multiplier: 2.0,
+                }),
+        );
+
+        // Sent before the gateway is reachable: queued, not lost
+        let queued = OpenClawMessage {
+            id: Some("queued-1".to_string()),
+            channel: "main".to_string(),
+            message: "hello".to_string(),
+            thinking_level: None,
+            model: None,
+            session_id: None,
+        };
+        assert!(matches!(client.send_message(queued.clone()).await, Err(OpenClawError::Queued)));
+        assert_eq!(client.queued_messages().await, 1);
+        assert!(matches!(client.send_now(queued).await, Err(OpenClawError::ConnectionLost)));
+        assert_eq!(client.queued_messages().await, 1);
+
+        let supervisor = client.start();
+
+        // First connection: the queued message arrives, then the gateway drops us
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut gateway = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let Some(Ok(Message::Text(text))) = gateway.next().await else { panic!("expected queued message") };
+        assert!(text.contains("queued-1"));
+        wait_for(&client, true).await;
+        assert_eq!(client.queued_messages().await, 0);
+        drop(gateway);
+        wait_for(&client, false).await;
+
+        // The supervisor reconnects on its own
+        let (socket, _) = listener.accept().await.unwrap();
+        let _gateway = tokio_tungstenite::accept_async(socket).await.unwrap();
+        wait_for(&client, true).await;
+        assert!(client.uptime().await.is_some());
+
+        client.disconnect().await;
+        tokio::time::timeout(Duration::from_secs(5), supervisor).await.unwrap().unwrap();
+        assert!(!client.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_send_message_waits_for_matching_reply() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let client = Arc::new(
+            OpenClawWebSocketClient::new(Arc::new(Config::from_env().unwrap()))
+                .with_gateway_url(url)
+                .with_reply_timeout(Duration::from_millis(300)),
+        );
+        client.start();
+
+        // Gateway: an unrelated event first, then the reply to each request; ignores "silent"
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut gateway = tokio_tungstenite::accept_async(socket).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = gateway.next().await {
+                let request: OpenClawMessage = serde_json::from_str(&text).unwrap();
+                if request.message == "silent" {
+                    continue;
+                }
+                let event = serde_json::json!({ "id": "someone-else", "response": "noise" });
+                gateway.send(Message::Text(event.to_string())).await.unwrap();
+                let reply = serde_json::json!({
+                    "id": request.id,
+                    "response": format!("echo: {}", request.message),
+                    "session_id": request.session_id.unwrap_or_else(|| "new-session".to_string()),
+                });
+                gateway.send(Message::Text(reply.to_string())).await.unwrap();
+            }
+        });
+        wait_for(&client, true).await;
+
+        let message = |text: &str| OpenClawMessage {
+            id: None,
+            channel: "webchat".to_string(),
+            message: text.to_string(),
+            thinking_level: Some("low".to_string()),
+            model: None,
+            session_id: Some("s-1".to_string()),
+        };
+        let response = client.send_message(message("hi")).await.unwrap();
+        assert_eq!(response.response, "echo: hi");
+        assert_eq!(response.session_id.as_deref(), Some("s-1"));
+
+        let err = client.send_message(message("silent")).await.unwrap_err();
+        assert!(matches!(err, OpenClawError::ReplyTimeout(_)));
+        assert!(client.pending_replies.lock().await.is_empty());
+
+        client.disconnect().await;
+    }
+}
@@ -5,6 +5,7 @@
  */
 use std::sync::Arc;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use uuid::Uuid;
 use chrono::Utc;
 use crate::database::Database;
@@ -21,17 +22,54 @@ pub struct StoredAsset {
     pub parent_id: Option<String>, // For versioning
 }
 
+/// URL prefix under which files written by `store_file` are served
+pub const ASSET_FILES_PATH: &str = "/api/v1/company/visual/assets";
+
 pub struct AssetStorage {
     database: Option<Arc<Database>>,
     assets: Arc<tokio::sync::RwLock<HashMap<String, StoredAsset>>>,
+    files_dir: PathBuf, // Where asset bytes produced by the server are written
 }
 
 impl AssetStorage {
-    pub fn new(database: Option<Arc<Database>>) -> Self {
+    pub fn new(database: Option<Arc<Database>>, files_dir: impl Into<PathBuf>) -> Self {
         Self {
             database,
             assets: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            files_dir: files_dir.into(),
+        }
+    }
+
+    /// Write asset bytes to disk and record the asset with its served URL
+    ///
+    /// Returns the asset id and URL.
+    pub async fn store_file(
+        &self,
+        bytes: &[u8],
+        extension: &str,
+        asset_type: String,
+        original_request: String,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> anyhow::Result<(String, String)> {
+        let file_name = format!("{}.{}", Uuid::new_v4(), extension);
+        tokio::fs::create_dir_all(&self.files_dir).await
+            .map_err(|e| anyhow::anyhow!("Failed to create asset directory {}: {}", self.files_dir.display(), e))?;
+        tokio::fs::write(self.files_dir.join(&file_name), bytes).await
+            .map_err(|e| anyhow::anyhow!("Failed to write asset {}: {}", file_name, e))?;
+
+        let asset_url = format!("{}/{}", ASSET_FILES_PATH, file_name);
+        let asset_id = self.store_asset(asset_url.clone(), asset_type, original_request, metadata).await;
+        Ok((asset_id, asset_url))
+    }
+
+    /// Bytes of a file written by `store_file`; None for unknown or malformed names
+    pub async fn read_file(&self, file_name: &str) -> Option<Vec<u8>> {
+        // Only "<uuid>.<ext>" names are ever written, which also rules out traversal
+        let (stem, extension) = file_name.split_once('.')?;
+        if Uuid::parse_str(stem).is_err() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
         }
+        tokio::fs::read(self.files_dir.join(file_name)).await.ok()
     }
 
     /// Store an asset
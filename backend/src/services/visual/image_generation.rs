@@ -1,9 +1,15 @@
 /**
  * Image Generation Service
  * 
- * Integrates with DALL-E, Stable Diffusion, and other image generation APIs
+ * Integrates with DALL-E, Stable Diffusion, and other image generation APIs.
+ * Generated images are remembered per prompt and settings for a while, so an
+ * identical request reuses the stored asset instead of paying for another
+ * generation.
  */
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use crate::config::Config;
@@ -55,21 +61,118 @@ pub struct ImageGenerationResponse {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// A generated image already stored as an asset
+#[derive(Debug, Clone)]
+pub struct CachedImage {
+    pub asset_id: String,
+    pub asset_url: String,
+    pub response: ImageGenerationResponse,
+}
+
+struct CacheEntry {
+    image: CachedImage,
+    stored_at: Instant,
+}
+
+/// Generated images by prompt and settings, kept until provider URLs may have expired
+pub struct ImageCache {
+    ttl: Duration,
+    max_entries: usize, // 0 = caching off
+    entries: Mutex<HashMap<u64, CacheEntry>>,
+}
+
+impl ImageCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.max_entries > 0 && !self.ttl.is_zero()
+    }
+
+    /// Stored image for the same prompt, model, size, quality and style, if still fresh
+    pub fn get(&self, request: &ImageGenerationRequest) -> Option<CachedImage> {
+        if !self.enabled() {
+            return None;
+        }
+        let key = cache_key(request);
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some(entry) if entry.stored_at.elapsed() < self.ttl => Some(entry.image.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Remember the asset generated for `request`, evicting the oldest entry when full
+    pub fn put(&self, request: &ImageGenerationRequest, image: CachedImage) {
+        if !self.enabled() {
+            return;
+        }
+        let key = cache_key(request);
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.stored_at.elapsed() < self.ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries.iter()
+                    .min_by_key(|(_, entry)| entry.stored_at)
+                    .map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, CacheEntry { image, stored_at: Instant::now() });
+    }
+}
+
+/// Hash of the prompt (whitespace-normalized) and the settings that change the image
+fn cache_key(request: &ImageGenerationRequest) -> u64 {
+    let identity = serde_json::json!({
+        "prompt": request.prompt.split_whitespace().collect::<Vec<_>>().join(" "),
+        "model": request.model,
+        "size": request.size,
+        "quality": request.quality,
+        "style": request.style,
+    });
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    identity.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
 pub struct ImageGenerationService {
     client: Client,
     config: Arc<Config>,
     router: Arc<ModelRouter>,
+    cache: ImageCache,
 }
 
 impl ImageGenerationService {
     pub fn new(config: Arc<Config>, router: Arc<ModelRouter>) -> Self {
+        let cache = ImageCache::new(
+            Duration::from_secs(config.image_cache_ttl_secs),
+            config.image_cache_max_entries,
+        );
         Self {
             client: Client::new(),
             config,
             router,
+            cache,
         }
     }
 
+    pub fn cache(&self) -> &ImageCache {
+        &self.cache
+    }
+
     /// Generate image using DALL-E 3
     pub async fn generate_with_dalle3(
         &self,
@@ -178,3 +281,51 @@ impl ImageGenerationService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(prompt: &str, size: ImageSize) -> ImageGenerationRequest {
+        ImageGenerationRequest {
+            prompt: prompt.to_string(),
+            model: ImageModel::DallE3,
+            size,
+            quality: ImageQuality::HD,
+            style: Some(ImageStyle::Vivid),
+            n: Some(1),
+        }
+    }
+
+    fn image(asset_id: &str) -> CachedImage {
+        CachedImage {
+            asset_id: asset_id.to_string(),
+            asset_url: format!("https://images.example/{}.png", asset_id),
+            response: ImageGenerationResponse {
+                image_url: format!("https://images.example/{}.png", asset_id),
+                revised_prompt: None,
+                model: "dall-e-3".to_string(),
+                size: "1024x1024".to_string(),
+                created_at: chrono::Utc::now(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_cache_hits_same_prompt_and_settings_until_ttl() {
+        let cache = ImageCache::new(Duration::from_millis(50), 10);
+        cache.put(&request("a red  fox", ImageSize::Square1024), image("fox"));
+
+        let hit = cache.get(&request("a red fox", ImageSize::Square1024)).unwrap();
+        assert_eq!(hit.asset_id, "fox");
+        // Different settings are a different image
+        assert!(cache.get(&request("a red fox", ImageSize::Portrait1792)).is_none());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(cache.get(&request("a red fox", ImageSize::Square1024)).is_none());
+
+        let disabled = ImageCache::new(Duration::from_secs(60), 0);
+        disabled.put(&request("a red fox", ImageSize::Square1024), image("fox"));
+        assert!(disabled.get(&request("a red fox", ImageSize::Square1024)).is_none());
+    }
+}
@@ -6,6 +6,7 @@
 pub mod image_generation;
 pub mod asset_storage;
 pub mod figma;
+pub mod optimize;
 
 pub use image_generation::ImageGenerationService;
 pub use asset_storage::AssetStorage;
@@ -0,0 +1,159 @@
+/**
+ * Image Optimization
+ *
+ * Decodes an image, shrinks it to fit within a bounding box (keeping its
+ * aspect ratio, never enlarging) and re-encodes it as WebP or JPEG.
+ */
+use std::io::Cursor;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::{WebPEncoder, WebPQuality};
+use image::imageops::FilterType;
+use image::{ColorType, ImageFormat};
+use serde::{Deserialize, Serialize};
+
+/// Source formats that can be decoded
+const SUPPORTED_FORMATS: &[ImageFormat] = &[ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP, ImageFormat::Gif];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    WebP,
+    Jpeg,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "webp" => Some(OutputFormat::WebP),
+            "jpeg" | "jpg" => Some(OutputFormat::Jpeg),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::WebP => "webp",
+            OutputFormat::Jpeg => "jpg",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizeOptions {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub format: OutputFormat,
+    pub quality: u8, // 1-100
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizedImage {
+    pub bytes: Vec<u8>,
+    pub format: OutputFormat,
+    pub width: u32,
+    pub height: u32,
+    pub original_width: u32,
+    pub original_height: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OptimizeError {
+    #[error("Unsupported image format: {0} (supported: PNG, JPEG, WebP, GIF)")]
+    UnsupportedFormat(String),
+    #[error("Could not decode image: {0}")]
+    Decode(String),
+    #[error("Could not encode image: {0}")]
+    Encode(String),
+}
+
+/// Resize and re-encode `source`. CPU-bound; run it off the async executor.
+pub fn optimize_image(source: &[u8], options: &OptimizeOptions) -> Result<OptimizedImage, OptimizeError> {
+    let format = image::guess_format(source)
+        .map_err(|_| OptimizeError::UnsupportedFormat("unrecognised data".to_string()))?;
+    if !SUPPORTED_FORMATS.contains(&format) {
+        return Err(OptimizeError::UnsupportedFormat(format!("{:?}", format)));
+    }
+
+    let image = image::load_from_memory_with_format(source, format)
+        .map_err(|e| OptimizeError::Decode(e.to_string()))?;
+    let (original_width, original_height) = (image.width(), image.height());
+
+    let max_width = options.max_width.max(1);
+    let max_height = options.max_height.max(1);
+    let image = if original_width > max_width || original_height > max_height {
+        image.resize(max_width, max_height, FilterType::Lanczos3)
+    } else {
+        image
+    };
+    let (width, height) = (image.width(), image.height());
+    let quality = options.quality.clamp(1, 100);
+
+    let mut bytes = Cursor::new(Vec::new());
+    match options.format {
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let rgb = image.to_rgb8();
+            JpegEncoder::new_with_quality(&mut bytes, quality)
+                .encode(rgb.as_raw(), width, height, ColorType::Rgb8)
+                .map_err(|e| OptimizeError::Encode(e.to_string()))?;
+        }
+        OutputFormat::WebP => {
+            let rgba = image.to_rgba8();
+            WebPEncoder::new_with_quality(&mut bytes, WebPQuality::lossy(quality))
+                .encode(rgba.as_raw(), width, height, ColorType::Rgba8)
+                .map_err(|e| OptimizeError::Encode(e.to_string()))?;
+        }
+    }
+
+    Ok(OptimizedImage {
+        bytes: bytes.into_inner(),
+        format: options.format,
+        width,
+        height,
+        original_width,
+        original_height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_fn(width, height, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, 128, 255]));
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image).write_to(&mut bytes, ImageOutputFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_png_is_shrunk_and_reencoded() {
+        let source = png(64, 32);
+        let options = OptimizeOptions { max_width: 16, max_height: 16, format: OutputFormat::Jpeg, quality: 70 };
+
+        let optimized = optimize_image(&source, &options).unwrap();
+        assert_eq!((optimized.original_width, optimized.original_height), (64, 32));
+        // Aspect ratio is kept
+        assert_eq!((optimized.width, optimized.height), (16, 8));
+        assert_eq!(image::guess_format(&optimized.bytes).unwrap(), ImageFormat::Jpeg);
+
+        // Images already within bounds are not enlarged
+        let webp = OptimizeOptions { max_width: 1920, max_height: 1920, format: OutputFormat::WebP, quality: 80 };
+        let optimized = optimize_image(&source, &webp).unwrap();
+        assert_eq!((optimized.width, optimized.height), (64, 32));
+        assert_eq!(image::guess_format(&optimized.bytes).unwrap(), ImageFormat::WebP);
+
+        assert!(matches!(
+            optimize_image(b"definitely not an image", &options),
+            Err(OptimizeError::UnsupportedFormat(_))
+        ));
+    }
+}
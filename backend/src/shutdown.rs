@@ -0,0 +1,67 @@
+/**
+ * Graceful shutdown
+ *
+ * Waits for SIGTERM or Ctrl-C, then closes long-lived collaboration
+ * WebSockets and lets the agent manager finish what is running and save what
+ * is still queued before the server stops.
+ */
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use crate::services::agent::AgentManager;
+
+/// Resolves on the first SIGTERM or Ctrl-C
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Ctrl-C handler unavailable: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("SIGTERM handler unavailable: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Future for `axum::serve(..).with_graceful_shutdown`: on a signal, cancel
+/// `connections` (closing collaboration WebSockets), drain the agent queue,
+/// then let the server stop
+pub async fn drain_on_signal(agent_manager: Arc<AgentManager>, drain_timeout: Duration, connections: CancellationToken) {
+    signal().await;
+    connections.cancel();
+    tracing::info!("Shutdown requested; draining agent tasks (up to {:?})", drain_timeout);
+
+    let report = agent_manager.shutdown(drain_timeout).await;
+    tracing::info!(
+        "Agent queue drained: {} running task(s) finished, {} still running, {} queued task(s) persisted, {} queued task(s) not saved",
+        report.drained,
+        report.still_running,
+        report.persisted,
+        report.unsaved
+    );
+}
+
+/// Resolves `timeout` after `connections` is cancelled; bounds how long the
+/// server waits for open connections once shutdown has started
+pub async fn connection_deadline(connections: CancellationToken, timeout: Duration) {
+    connections.cancelled().await;
+    tokio::time::sleep(timeout).await;
+}
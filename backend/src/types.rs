@@ -35,6 +35,40 @@ pub struct AIRequest {
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<CodebaseContext>,
+    /// What to do when the context exceeds the selected model's window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_overflow: Option<ContextOverflowPolicy>,
+    /// Locale code the model should answer in (e.g. "es", "ja"); defaults to English
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_language: Option<String>,
+    /// Escape script tags, `javascript:` URLs and inline event handlers in the
+    /// response for clients that render it as HTML. Code spans are kept verbatim.
+    /// Off by default: API clients get the model's raw text.
+    #[serde(default)]
+    pub sanitize_output: bool,
+    /// Ask the provider for a JSON object using its native JSON mode, where it has one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub json_output: bool,
+}
+
+/// Policy applied when a request does not fit the selected model's context window
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextOverflowPolicy {
+    /// Drop the oldest context until the request fits
+    Truncate,
+    /// Promote selection to a longer-context provider
+    Escalate,
+}
+
+impl ContextOverflowPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "truncate" => Some(Self::Truncate),
+            "escalate" => Some(Self::Escalate),
+            _ => None,
+        }
+    }
 }
 
 impl AIRequest {
@@ -47,6 +81,10 @@ impl AIRequest {
             max_tokens: self.max_tokens,
             stream: self.stream,
             context: self.context.clone(),
+            context_overflow: self.context_overflow,
+            response_language: self.response_language.clone(),
+            sanitize_output: self.sanitize_output,
+            json_output: self.json_output,
         }
     }
 }
@@ -190,9 +228,11 @@ pub enum Priority {
 #[serde(rename_all = "lowercase")]
 pub enum TaskStatus {
     Pending,
+    Waiting, // Decomposed into subtasks; finishes when they do and never runs itself
     Processing,
     Completed,
     Failed,
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -246,6 +286,7 @@ pub enum ModelProvider {
     Qwen,          // Alibaba Qwen
     ZeroOne,       // 01.ai models
     Baidu,         // Ernie models
+    Ollama,        // Locally hosted models
     Auto,
 }
 
@@ -71,6 +71,9 @@ pub mod error_codes {
     pub const EXTERNAL_SERVICE_ERROR: &str = "EXTERNAL_SERVICE_ERROR";
     pub const INVALID_INPUT: &str = "INVALID_INPUT";
     pub const PAYLOAD_TOO_LARGE: &str = "PAYLOAD_TOO_LARGE";
+    pub const GATEWAY_TIMEOUT: &str = "GATEWAY_TIMEOUT";
+    pub const SERVICE_UNAVAILABLE: &str = "SERVICE_UNAVAILABLE";
+    pub const CONFLICT: &str = "CONFLICT";
 }
 
 impl IntoResponse for ApiError {
@@ -82,8 +85,10 @@ impl IntoResponse for ApiError {
             error_codes::FORBIDDEN => StatusCode::FORBIDDEN,
             error_codes::RATE_LIMIT_EXCEEDED => StatusCode::TOO_MANY_REQUESTS,
             error_codes::PAYLOAD_TOO_LARGE => StatusCode::PAYLOAD_TOO_LARGE,
-            error_codes::DATABASE_ERROR => StatusCode::SERVICE_UNAVAILABLE,
+            error_codes::DATABASE_ERROR | error_codes::SERVICE_UNAVAILABLE => StatusCode::SERVICE_UNAVAILABLE,
             error_codes::EXTERNAL_SERVICE_ERROR => StatusCode::BAD_GATEWAY,
+            error_codes::GATEWAY_TIMEOUT => StatusCode::GATEWAY_TIMEOUT,
+            error_codes::CONFLICT => StatusCode::CONFLICT,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -138,6 +143,17 @@ impl ApiError {
         Self::new(error_codes::INTERNAL_ERROR.to_string(), message)
     }
 
+    pub fn conflict(message: String) -> Self {
+        Self::new(error_codes::CONFLICT.to_string(), message)
+    }
+
+    pub fn service_unavailable(service: &str) -> Self {
+        Self::new(
+            error_codes::SERVICE_UNAVAILABLE.to_string(),
+            format!("{} is unavailable", service),
+        )
+    }
+
     pub fn external_service_error(service: &str, message: String) -> Self {
         Self::new(
             error_codes::EXTERNAL_SERVICE_ERROR.to_string(),
@@ -157,3 +173,14 @@ impl fmt::Display for ApiError {
 }
 
 impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+impl ApiError {
+    /// Status and JSON body as a client receives them
+    pub async fn into_test_response(self) -> (StatusCode, serde_json::Value) {
+        let response = self.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+}
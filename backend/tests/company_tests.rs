@@ -13,10 +13,11 @@ async fn test_company_orchestrator_initialization() {
     // Test that company orchestrator initializes correctly
     let config = Arc::new(Config::load().unwrap());
     let router = Arc::new(ModelRouter::new(Arc::clone(&config)));
-    let agent_manager = Arc::new(AgentManager::new(
+    let agent_manager = AgentManager::new(
         Arc::clone(&router),
         Arc::clone(&config),
-    ));
+        None,
+    );
 
     // Note: This would require database setup for full test
     // For now, test basic structure
@@ -28,10 +29,11 @@ async fn test_demand_analyzer() {
     // Test demand analysis functionality
     let config = Arc::new(Config::load().unwrap());
     let router = Arc::new(ModelRouter::new(Arc::clone(&config)));
-    let agent_manager = Arc::new(AgentManager::new(
+    let agent_manager = AgentManager::new(
         Arc::clone(&router),
         Arc::clone(&config),
-    ));
+        None,
+    );
     
     let analyzer = DemandAnalyzer::new(Arc::clone(&agent_manager));
     
@@ -45,12 +47,13 @@ async fn test_predictive_scaling() {
     // Test predictive scaling calculations
     let config = Arc::new(Config::load().unwrap());
     let router = Arc::new(ModelRouter::new(Arc::clone(&config)));
-    let agent_manager = Arc::new(AgentManager::new(
+    let agent_manager = AgentManager::new(
         Arc::clone(&router),
         Arc::clone(&config),
-    ));
+        None,
+    );
     
-    let scaler = PredictiveScaler::new(Arc::clone(&agent_manager));
+    let scaler = PredictiveScaler::new(Arc::clone(&agent_manager), ScalingPolicy::from_config(&config));
     
     // Test prediction
     let prediction = scaler.predict_demand(1).await; // 1 hour ahead